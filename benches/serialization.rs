@@ -0,0 +1,125 @@
+//! Allocation cost of serializing list and export responses
+//!
+//! Run with `cargo bench --bench serialization`. Each group compares the
+//! previous approach (copy every item, grow the buffer from empty) with the
+//! current one (share items through `Arc`, pre-size or reuse buffers).
+
+use chrono::Utc;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use ferrous::{
+    handlers::ListResponse,
+    json::{to_vec_with_capacity, ITEM_SIZE_HINT},
+    links::Hypermedia,
+    models::Item,
+};
+use serde::Serialize;
+use std::sync::Arc;
+
+/// `ListResponse` as it was before items were shared
+#[derive(Serialize)]
+struct OwnedListResponse {
+    items: Vec<Item>,
+    total: usize,
+    limit: usize,
+    offset: usize,
+}
+
+fn items(count: usize) -> Vec<Arc<Item>> {
+    (0..count)
+        .map(|i| {
+            Arc::new(Item {
+                id: uuid::Uuid::new_v4().to_string(),
+                name: format!("Benchmark item {i}"),
+                slug: format!("benchmark-item-{i}"),
+                external_id: None,
+                source: None,
+                description: Some("A representative description of moderate length".repeat(2)),
+                metadata: None,
+                custom_fields: Default::default(),
+                owner_id: Some("user-123".to_string()),
+                tenant_id: None,
+                status: Default::default(),
+                publish_at: None,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                version: 1,
+            })
+        })
+        .collect()
+}
+
+fn list_responses(c: &mut Criterion) {
+    let mut group = c.benchmark_group("list_response");
+    for count in [20, 100, 1000] {
+        let stored = items(count);
+        group.throughput(Throughput::Elements(count as u64));
+
+        group.bench_with_input(BenchmarkId::new("owned_items", count), &stored, |b, stored| {
+            b.iter(|| {
+                let response = OwnedListResponse {
+                    items: stored.iter().map(|item| Item::clone(item)).collect(),
+                    total: count,
+                    limit: count,
+                    offset: 0,
+                };
+                black_box(serde_json::to_vec(&response).unwrap())
+            });
+        });
+
+        group.bench_with_input(BenchmarkId::new("shared_items", count), &stored, |b, stored| {
+            b.iter(|| {
+                let response = ListResponse {
+                    items: stored
+                        .iter()
+                        .cloned()
+                        .map(|item| Hypermedia(false).item(item))
+                        .collect(),
+                    total: Some(count),
+                    limit: count,
+                    offset: 0,
+                    next_cursor: None,
+                    links: None,
+                };
+                let capacity = (count + 1) * ITEM_SIZE_HINT;
+                black_box(to_vec_with_capacity(&response, capacity).unwrap())
+            });
+        });
+    }
+    group.finish();
+}
+
+fn export_records(c: &mut Criterion) {
+    let stored = items(1000);
+    let mut group = c.benchmark_group("export_records");
+    group.throughput(Throughput::Elements(stored.len() as u64));
+
+    group.bench_function("vec_per_record", |b| {
+        b.iter(|| {
+            let mut written = 0;
+            for item in &stored {
+                let mut line = serde_json::to_vec(item).unwrap();
+                line.push(b'\n');
+                written += black_box(line).len();
+            }
+            written
+        });
+    });
+
+    group.bench_function("reused_buffer", |b| {
+        b.iter(|| {
+            let mut written = 0;
+            let mut line = Vec::with_capacity(1024);
+            for item in &stored {
+                line.clear();
+                serde_json::to_writer(&mut line, item).unwrap();
+                line.push(b'\n');
+                written += black_box(&line).len();
+            }
+            written
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(benches, list_responses, export_records);
+criterion_main!(benches);
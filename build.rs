@@ -0,0 +1,252 @@
+//! Embeds build metadata for `GET /version`, the health report and the
+//! startup log, and the dependency inventory served by `GET /admin/v1/sbom`
+
+use serde_json::{json, Map, Value};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=GIT_SHA");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    println!("cargo:rerun-if-changed=Cargo.toml");
+    // Watched only where they exist; Cargo reruns on every build otherwise.
+    // A commit moves the branch HEAD points at, not HEAD itself.
+    let head = Path::new(".git/HEAD");
+    if head.exists() {
+        println!("cargo:rerun-if-changed=.git/HEAD");
+        let branch = fs::read_to_string(head).unwrap_or_default();
+        if let Some(branch) = branch.trim().strip_prefix("ref: ") {
+            let branch = Path::new(".git").join(branch);
+            if branch.exists() {
+                println!("cargo:rerun-if-changed={}", branch.display());
+            }
+        }
+    }
+
+    // Container builds have no repository, so CI passes the SHA in
+    let git_sha = env::var("GIT_SHA")
+        .ok()
+        .filter(|sha| !sha.is_empty())
+        .or_else(|| output("git", &["rev-parse", "HEAD"]))
+        .unwrap_or_else(|| "unknown".to_string());
+
+    // Reproducible builds pin the timestamp
+    let built_at = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default()
+        });
+
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = output(&rustc, &["--version"]).unwrap_or_else(|| "unknown".to_string());
+
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(name, _)| {
+            let feature = name.strip_prefix("CARGO_FEATURE_")?;
+            Some(feature.to_lowercase().replace('_', "-"))
+        })
+        .collect();
+    features.sort();
+
+    println!("cargo:rustc-env=FERROUS_GIT_SHA={git_sha}");
+    println!("cargo:rustc-env=FERROUS_BUILT_AT={built_at}");
+    println!("cargo:rustc-env=FERROUS_RUSTC_VERSION={rustc_version}");
+    println!("cargo:rustc-env=FERROUS_FEATURES={}", features.join(","));
+
+    // An empty inventory would misreport what the binary links, so fail instead
+    let sbom = sbom(built_at).unwrap_or_else(|e| panic!("Dependency inventory unavailable: {e}"));
+    let out = PathBuf::from(env::var("OUT_DIR").expect("OUT_DIR is set by Cargo"));
+    fs::write(out.join("sbom.json"), sbom.to_string()).expect("Failed to write sbom.json");
+}
+
+/// CycloneDX document of the crates linked into the binary: those reached
+/// from this package through normal dependencies, as resolved for this build
+///
+/// Resolution is limited to the target being built, so dependencies of other
+/// platforms need not be downloaded for an offline build.
+fn sbom(built_at: u64) -> Result<Value, String> {
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let target = env::var("TARGET").map_err(|e| format!("TARGET: {e}"))?;
+    let output = Command::new(cargo)
+        .args(["metadata", "--format-version", "1", "--offline", "--locked"])
+        .args(["--filter-platform", &target])
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    let metadata: Value = serde_json::from_slice(&output.stdout).map_err(|e| e.to_string())?;
+
+    let packages: BTreeMap<&str, &Value> = metadata["packages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|package| Some((package["id"].as_str()?, package)))
+        .collect();
+    let nodes: BTreeMap<&str, Vec<&str>> = metadata["resolve"]["nodes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|node| {
+            let normal = node["deps"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter(|dep| {
+                    dep["dep_kinds"]
+                        .as_array()
+                        .is_some_and(|kinds| kinds.iter().any(|kind| kind["kind"].is_null()))
+                })
+                .filter_map(|dep| dep["pkg"].as_str())
+                .collect();
+            Some((node["id"].as_str()?, normal))
+        })
+        .collect();
+    let root = metadata["resolve"]["root"]
+        .as_str()
+        .ok_or("no root package")?;
+
+    // Everything the root links, directly or not
+    let mut linked = BTreeSet::new();
+    let mut pending = vec![root];
+    while let Some(id) = pending.pop() {
+        for dep in nodes.get(id).into_iter().flatten() {
+            if linked.insert(*dep) {
+                pending.push(dep);
+            }
+        }
+    }
+
+    let checksums = checksums();
+    let purl = |id: &str| {
+        let package = packages[id];
+        format!(
+            "pkg:cargo/{}@{}",
+            package["name"].as_str().unwrap_or_default(),
+            package["version"].as_str().unwrap_or_default()
+        )
+    };
+    let components: Vec<Value> = linked
+        .iter()
+        .filter_map(|id| {
+            let package = packages.get(id)?;
+            let (name, version) = (package["name"].as_str()?, package["version"].as_str()?);
+            let mut component = Map::new();
+            component.insert("type".into(), json!("library"));
+            component.insert("bom-ref".into(), json!(purl(id)));
+            component.insert("name".into(), json!(name));
+            component.insert("version".into(), json!(version));
+            if let Some(description) = package["description"].as_str() {
+                component.insert("description".into(), json!(description.trim()));
+            }
+            component.insert("purl".into(), json!(purl(id)));
+            if let Some(license) = package["license"].as_str() {
+                component.insert("licenses".into(), json!([{ "expression": license }]));
+            }
+            if let Some(checksum) = checksums.get(&(name.to_string(), version.to_string())) {
+                component
+                    .insert("hashes".into(), json!([{ "alg": "SHA-256", "content": checksum }]));
+            }
+            Some(Value::Object(component))
+        })
+        .collect();
+    let dependencies: Vec<Value> = std::iter::once(root)
+        .chain(linked.iter().copied())
+        .filter(|id| packages.contains_key(id))
+        .map(|id| {
+            let depends_on: Vec<String> = nodes
+                .get(id)
+                .into_iter()
+                .flatten()
+                .filter(|dep| packages.contains_key(*dep))
+                .map(|dep| purl(dep))
+                .collect();
+            json!({ "ref": purl(id), "dependsOn": depends_on })
+        })
+        .collect();
+
+    let package = packages.get(root).ok_or("root package missing")?;
+    let mut application = json!({
+        "type": "application",
+        "bom-ref": purl(root),
+        "name": package["name"],
+        "version": package["version"],
+        "purl": purl(root),
+    });
+    if let Some(license) = package["license"].as_str() {
+        application["licenses"] = json!([{ "expression": license }]);
+    }
+    Ok(json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "version": 1,
+        "metadata": {
+            "timestamp": rfc3339(built_at),
+            "component": application,
+        },
+        "components": components,
+        "dependencies": dependencies,
+    }))
+}
+
+/// `seconds` since the Unix epoch as an RFC 3339 UTC timestamp
+fn rfc3339(seconds: u64) -> String {
+    let (days, time) = (seconds / 86_400, seconds % 86_400);
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        time / 3_600,
+        time % 3_600 / 60,
+        time % 60
+    )
+}
+
+/// SHA-256 of each registry crate in `Cargo.lock`, by name and version
+fn checksums() -> BTreeMap<(String, String), String> {
+    let lock = fs::read_to_string("Cargo.lock").unwrap_or_default();
+    let mut checksums = BTreeMap::new();
+    for package in lock.split("[[package]]") {
+        let field = |key: &str| {
+            package.lines().find_map(|line| {
+                let value = line.strip_prefix(key)?.trim().strip_prefix("= ")?;
+                Some(value.trim_matches('"').to_string())
+            })
+        };
+        if let (Some(name), Some(version), Some(checksum)) =
+            (field("name"), field("version"), field("checksum"))
+        {
+            checksums.insert((name, version), checksum);
+        }
+    }
+    checksums
+}
+
+/// Trimmed standard output of a successful `program args` run
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    output
+        .status
+        .success()
+        .then(|| stdout.trim().to_string())
+        .filter(|stdout| !stdout.is_empty())
+}
@@ -0,0 +1,322 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    sync::mpsc::{self, Receiver, SyncSender, TrySendError},
+    thread,
+};
+use tracing::{info, warn};
+
+use crate::{
+    config::{AccessLogFormat, AccessLogRotation, LoggingConfig},
+    metrics::track_access_log_dropped,
+};
+
+/// Lines queued for the writer before further lines are dropped
+const ACCESS_LOG_QUEUE_LINES: usize = 8192;
+
+/// One request, as written to the access log
+#[derive(Debug, Clone, Serialize)]
+pub struct AccessLogEntry {
+    pub time: DateTime<Utc>,
+    pub remote_addr: Option<String>,
+    pub method: String,
+    pub uri: String,
+    pub protocol: String,
+    pub status: u16,
+    /// Response body size, when known up front
+    pub bytes: Option<u64>,
+    pub referer: Option<String>,
+    pub user_agent: Option<String>,
+    pub duration_ms: u64,
+    pub request_id: String,
+}
+
+impl AccessLogEntry {
+    /// The entry as one line (without the newline) in `format`
+    ///
+    /// Client-supplied values are escaped, so a request cannot forge lines.
+    pub fn format(&self, format: AccessLogFormat) -> String {
+        let common = || {
+            format!(
+                "{} - - [{}] \"{} {} {}\" {} {}",
+                self.remote_addr.as_deref().map_or("-".to_string(), escape),
+                self.time.format("%d/%b/%Y:%H:%M:%S %z"),
+                escape(&self.method),
+                escape(&self.uri),
+                self.protocol,
+                self.status,
+                self.bytes
+                    .map_or("-".to_string(), |bytes| bytes.to_string()),
+            )
+        };
+        match format {
+            AccessLogFormat::Common => common(),
+            AccessLogFormat::Combined => format!(
+                "{} \"{}\" \"{}\"",
+                common(),
+                self.referer.as_deref().map_or("-".to_string(), escape),
+                self.user_agent.as_deref().map_or("-".to_string(), escape),
+            ),
+            // serde_json escapes control characters itself
+            AccessLogFormat::Json => serde_json::to_string(self).unwrap_or_default(),
+        }
+    }
+}
+
+/// Escape quotes, backslashes and control characters
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\x{:02x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Writes request lines to a file, independently of application logging
+///
+/// Requests only queue their line; a writer thread appends queued lines to the
+/// file and rotates it. If the writer falls behind and the queue fills up,
+/// lines are dropped (and counted) rather than making requests wait on disk.
+pub struct AccessLog {
+    format: AccessLogFormat,
+    sender: SyncSender<String>,
+}
+
+impl AccessLog {
+    /// Open the configured access log and start its writer, if one is configured
+    pub fn start(config: &LoggingConfig) -> io::Result<Option<Self>> {
+        let Some(path) = &config.access_log_path else {
+            return Ok(None);
+        };
+        let file = RotatingFile::open(PathBuf::from(path), config)?;
+        let (sender, receiver) = mpsc::sync_channel(ACCESS_LOG_QUEUE_LINES);
+        thread::Builder::new()
+            .name("access-log".to_string())
+            .spawn(move || write_lines(file, receiver))?;
+
+        info!(path, format = ?config.access_log_format, "Writing access log");
+        Ok(Some(Self {
+            format: config.access_log_format,
+            sender,
+        }))
+    }
+
+    /// Queue a request's line without waiting for it to be written
+    pub fn record(&self, entry: &AccessLogEntry) {
+        if let Err(TrySendError::Full(_)) = self.sender.try_send(entry.format(self.format)) {
+            track_access_log_dropped();
+        }
+    }
+}
+
+/// Write queued lines until every sender is gone, flushing whenever the queue
+/// runs empty
+fn write_lines(mut file: RotatingFile, receiver: Receiver<String>) {
+    while let Ok(line) = receiver.recv() {
+        let mut result = file.write_line(&line);
+        while let Ok(line) = receiver.try_recv() {
+            result = result.and(file.write_line(&line));
+        }
+        if let Err(e) = result.and(file.flush()) {
+            warn!(path = %file.path.display(), "Failed to write access log: {}", e);
+        }
+    }
+}
+
+/// A log file rotated by size and by time
+///
+/// Rotated files are renamed with the time of rotation appended, and only the
+/// newest `max_files` of them are kept.
+struct RotatingFile {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    size: u64,
+    /// Rotation period the current file belongs to
+    period: Option<String>,
+    max_bytes: Option<u64>,
+    rotation: AccessLogRotation,
+    max_files: usize,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, config: &LoggingConfig) -> io::Result<Self> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let metadata = file.metadata()?;
+        // A file left over from before a restart belongs to the period it was
+        // last written in, so it is still rotated on schedule
+        let modified = metadata.modified().map(DateTime::<Utc>::from)?;
+
+        Ok(Self {
+            path,
+            writer: BufWriter::new(file),
+            size: metadata.len(),
+            period: period(config.access_log_rotation, modified),
+            max_bytes: config.access_log_max_bytes,
+            rotation: config.access_log_rotation,
+            max_files: config.access_log_max_files,
+        })
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let now = Utc::now();
+        let current = period(self.rotation, now);
+        let len = line.len() as u64 + 1;
+        let full = self
+            .max_bytes
+            .is_some_and(|max| self.size > 0 && self.size + len > max);
+        if current != self.period || full {
+            self.rotate(now)?;
+            self.period = current;
+        }
+
+        self.writer.write_all(line.as_bytes())?;
+        self.writer.write_all(b"\n")?;
+        self.size += len;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    fn rotate(&mut self, now: DateTime<Utc>) -> io::Result<()> {
+        self.writer.flush()?;
+        if self.size > 0 {
+            let stamp = now.format("%Y%m%dT%H%M%S");
+            let mut rotated = self
+                .path
+                .with_extension(format!("{}.{stamp}", self.extension()));
+            let mut n = 1;
+            while rotated.exists() {
+                rotated = self
+                    .path
+                    .with_extension(format!("{}.{stamp}-{n}", self.extension()));
+                n += 1;
+            }
+            fs::rename(&self.path, &rotated)?;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.writer = BufWriter::new(file);
+        self.size = 0;
+        self.prune()
+    }
+
+    fn extension(&self) -> String {
+        self.path
+            .extension()
+            .map(|extension| extension.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    /// Delete the oldest rotated files beyond `max_files`
+    fn prune(&self) -> io::Result<()> {
+        let (Some(dir), Some(name)) = (self.path.parent(), self.path.file_name()) else {
+            return Ok(());
+        };
+        let dir = if dir.as_os_str().is_empty() {
+            std::path::Path::new(".")
+        } else {
+            dir
+        };
+        let prefix = format!("{}.", name.to_string_lossy());
+        let mut rotated: Vec<PathBuf> = fs::read_dir(dir)?
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+            .map(|entry| entry.path())
+            .collect();
+        // Timestamps sort chronologically
+        rotated.sort();
+        let excess = rotated.len().saturating_sub(self.max_files);
+        for path in &rotated[..excess] {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Identifies the rotation period `time` falls in
+fn period(rotation: AccessLogRotation, time: DateTime<Utc>) -> Option<String> {
+    match rotation {
+        AccessLogRotation::Never => None,
+        AccessLogRotation::Hourly => Some(time.format("%Y%m%d%H").to_string()),
+        AccessLogRotation::Daily => Some(time.format("%Y%m%d").to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry() -> AccessLogEntry {
+        AccessLogEntry {
+            time: DateTime::parse_from_rfc3339("2026-10-14T09:30:00Z")
+                .unwrap()
+                .into(),
+            remote_addr: Some("203.0.113.7".to_string()),
+            method: "GET".to_string(),
+            uri: "/api/v1/items?limit=10".to_string(),
+            protocol: "HTTP/1.1".to_string(),
+            status: 200,
+            bytes: Some(512),
+            referer: None,
+            user_agent: Some("curl/8.0 \"quoted\"\n".to_string()),
+            duration_ms: 3,
+            request_id: "req-1".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_formats() {
+        assert_eq!(
+            entry().format(AccessLogFormat::Common),
+            "203.0.113.7 - - [14/Oct/2026:09:30:00 +0000] \"GET /api/v1/items?limit=10 HTTP/1.1\" 200 512"
+        );
+        assert_eq!(
+            entry().format(AccessLogFormat::Combined),
+            "203.0.113.7 - - [14/Oct/2026:09:30:00 +0000] \"GET /api/v1/items?limit=10 HTTP/1.1\" 200 512 \"-\" \"curl/8.0 \\\"quoted\\\"\\x0a\""
+        );
+        let json: serde_json::Value =
+            serde_json::from_str(&entry().format(AccessLogFormat::Json)).unwrap();
+        assert_eq!(json["status"], 200);
+        assert_eq!(json["request_id"], "req-1");
+    }
+
+    #[test]
+    fn test_rotation_by_size() {
+        let dir = std::env::temp_dir().join(format!("ferrous-access-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("access.log");
+        let config = LoggingConfig {
+            access_log_path: Some(path.to_string_lossy().into_owned()),
+            access_log_max_bytes: Some(100),
+            access_log_rotation: AccessLogRotation::Never,
+            access_log_max_files: 2,
+            ..LoggingConfig::default()
+        };
+        let mut file = RotatingFile::open(path.clone(), &config).unwrap();
+        let line = "x".repeat(59);
+        for _ in 0..8 {
+            file.write_line(&line).unwrap();
+        }
+        file.flush().unwrap();
+
+        // Each file holds one 60-byte line, and two rotated files are kept
+        assert_eq!(fs::read_to_string(&path).unwrap(), format!("{line}\n"));
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,225 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{error::AppError, events::ItemEventType};
+
+/// Most buckets one request may ask for
+pub const MAX_BUCKETS: i64 = 1000;
+
+/// Width of the buckets item changes are counted in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Granularity {
+    Hour,
+    #[default]
+    Day,
+    /// Weeks starting on Monday
+    Week,
+}
+
+impl Granularity {
+    fn seconds(self) -> i64 {
+        match self {
+            Self::Hour => 60 * 60,
+            Self::Day => 24 * 60 * 60,
+            Self::Week => 7 * 24 * 60 * 60,
+        }
+    }
+
+    /// Span covered when the request gives no `from`
+    fn default_span(self) -> Duration {
+        match self {
+            Self::Hour => Duration::hours(24),
+            Self::Day => Duration::days(30),
+            Self::Week => Duration::weeks(12),
+        }
+    }
+
+    /// Start of the bucket holding `at`
+    pub fn truncate(self, at: DateTime<Utc>) -> DateTime<Utc> {
+        // The epoch fell on a Thursday; weeks are aligned to the Monday after
+        let origin = match self {
+            Self::Week => 4 * 24 * 60 * 60,
+            Self::Hour | Self::Day => 0,
+        };
+        let seconds = at.timestamp();
+        let start = seconds - (seconds - origin).rem_euclid(self.seconds());
+        DateTime::from_timestamp(start, 0).unwrap_or(at)
+    }
+}
+
+/// Buckets to count item changes in, from the one holding `from` up to `to`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnalyticsRange {
+    /// Start of the first bucket
+    pub from: DateTime<Utc>,
+    /// End of the range, exclusive
+    pub to: DateTime<Utc>,
+    pub granularity: Granularity,
+}
+
+impl AnalyticsRange {
+    /// Range ending at `to` (default the end of the bucket holding `now`)
+    /// and starting at `from`, or a span suited to the granularity before `to`
+    pub fn new(
+        granularity: Granularity,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Result<Self, AppError> {
+        let to = to.unwrap_or_else(|| {
+            granularity.truncate(now) + Duration::seconds(granularity.seconds())
+        });
+        let from = granularity.truncate(from.unwrap_or(to - granularity.default_span()));
+        if from >= to {
+            return Err(AppError::BadRequest("from must be before to".to_string()));
+        }
+        let range = Self {
+            from,
+            to,
+            granularity,
+        };
+        if range.len() > MAX_BUCKETS {
+            return Err(AppError::BadRequest(format!(
+                "Range spans more than {MAX_BUCKETS} buckets; narrow it or use a coarser granularity"
+            )));
+        }
+        Ok(range)
+    }
+
+    fn len(&self) -> i64 {
+        let width = self.granularity.seconds();
+        (self.to.timestamp() - self.from.timestamp() + width - 1) / width
+    }
+
+    pub fn contains(&self, at: DateTime<Utc>) -> bool {
+        self.from <= at && at < self.to
+    }
+
+    /// Empty counts for every bucket of the range
+    pub fn histogram(&self) -> Histogram {
+        let width = Duration::seconds(self.granularity.seconds());
+        let buckets = (0..self.len())
+            .map(|i| AnalyticsBucket {
+                start: self.from + width * i32::try_from(i).unwrap_or(i32::MAX),
+                created: 0,
+                updated: 0,
+                deleted: 0,
+            })
+            .collect();
+        Histogram {
+            range: *self,
+            buckets,
+        }
+    }
+}
+
+/// Item changes counted in one bucket
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct AnalyticsBucket {
+    pub start: DateTime<Utc>,
+    pub created: u64,
+    pub updated: u64,
+    pub deleted: u64,
+}
+
+/// Change counts across the buckets of a range, filled in by a repository
+pub struct Histogram {
+    range: AnalyticsRange,
+    buckets: Vec<AnalyticsBucket>,
+}
+
+impl Histogram {
+    /// Count a change of `event_type` made at `at`; changes outside the
+    /// range are ignored
+    pub fn record(&mut self, event_type: ItemEventType, at: DateTime<Utc>) {
+        if !self.range.contains(at) {
+            return;
+        }
+        let index =
+            (at.timestamp() - self.range.from.timestamp()) / self.range.granularity.seconds();
+        let Some(bucket) = usize::try_from(index)
+            .ok()
+            .and_then(|index| self.buckets.get_mut(index))
+        else {
+            return;
+        };
+        match event_type {
+            ItemEventType::Created => bucket.created += 1,
+            ItemEventType::Updated => bucket.updated += 1,
+            ItemEventType::Deleted => bucket.deleted += 1,
+        }
+    }
+
+    pub fn into_buckets(self) -> Vec<AnalyticsBucket> {
+        self.buckets
+    }
+}
+
+/// Item changes per time bucket
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AnalyticsResponse {
+    pub granularity: Granularity,
+    /// Start of the first bucket
+    pub from: DateTime<Utc>,
+    /// End of the last bucket, exclusive
+    pub to: DateTime<Utc>,
+    /// Every bucket of the range in order, including those without changes
+    pub buckets: Vec<AnalyticsBucket>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_buckets_are_aligned_to_the_granularity() {
+        let wednesday = at("2026-01-07T15:42:10Z");
+        assert_eq!(Granularity::Hour.truncate(wednesday), at("2026-01-07T15:00:00Z"));
+        assert_eq!(Granularity::Day.truncate(wednesday), at("2026-01-07T00:00:00Z"));
+        assert_eq!(Granularity::Week.truncate(wednesday), at("2026-01-05T00:00:00Z"));
+    }
+
+    #[test]
+    fn test_changes_are_counted_in_their_bucket() {
+        let range = AnalyticsRange::new(
+            Granularity::Day,
+            Some(at("2026-01-01T12:00:00Z")),
+            Some(at("2026-01-03T06:00:00Z")),
+            Utc::now(),
+        )
+        .unwrap();
+        let mut histogram = range.histogram();
+        histogram.record(ItemEventType::Created, at("2026-01-01T00:30:00Z"));
+        histogram.record(ItemEventType::Created, at("2026-01-02T10:00:00Z"));
+        histogram.record(ItemEventType::Deleted, at("2026-01-03T05:59:59Z"));
+        // Past the end of the range
+        histogram.record(ItemEventType::Updated, at("2026-01-03T06:00:00Z"));
+
+        let buckets = histogram.into_buckets();
+        let counts: Vec<_> = buckets
+            .iter()
+            .map(|bucket| (bucket.created, bucket.updated, bucket.deleted))
+            .collect();
+        assert_eq!(counts, [(1, 0, 0), (1, 0, 0), (0, 0, 1)]);
+        assert_eq!(buckets[2].start, at("2026-01-03T00:00:00Z"));
+    }
+
+    #[test]
+    fn test_ranges_are_bounded() {
+        let now = at("2026-01-01T00:00:00Z");
+        let hours = AnalyticsRange::new(Granularity::Hour, None, None, now).unwrap();
+        assert_eq!(hours.histogram().into_buckets().len(), 24);
+        assert!(AnalyticsRange::new(Granularity::Day, Some(now), Some(now), now).is_err());
+        let long_ago = Some(at("2020-01-01T00:00:00Z"));
+        assert!(AnalyticsRange::new(Granularity::Hour, long_ago, None, now).is_err());
+        assert!(AnalyticsRange::new(Granularity::Week, long_ago, None, now).is_ok());
+    }
+}
@@ -0,0 +1,342 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    collections::ItemCollection,
+    config::ApprovalConfig,
+    db::{AccessRepository, CollectionRepository, DatabaseError, DatabaseResult},
+    events::ItemEventType,
+    inbox::{Notification, NotificationReason},
+    models::{Grantee, Item, Permission, UpdateItemRequest},
+    tenancy::current_tenant,
+};
+
+/// Which items need approval for changes, and how many approvals
+///
+/// Items filed in a designated collection, or anywhere below one, are only
+/// updated or deleted once a change request for it is approved.
+#[derive(Debug, Clone, Default)]
+pub struct Approvals {
+    collections: HashSet<String>,
+    required: u32,
+}
+
+impl Approvals {
+    pub fn from_config(config: &ApprovalConfig) -> Self {
+        Self {
+            collections: config.collections.iter().cloned().collect(),
+            required: config.required_approvals,
+        }
+    }
+
+    /// Approvals a change request needs before it is applied
+    pub fn required(&self) -> u32 {
+        self.required
+    }
+
+    /// Whether changes to `item_id` go through change requests
+    pub async fn requires_approval(
+        &self,
+        collections: &dyn CollectionRepository,
+        item_id: &str,
+    ) -> DatabaseResult<bool> {
+        if self.collections.is_empty() {
+            return Ok(false);
+        }
+        let Some(collection_id) = collections.item_collection(item_id).await? else {
+            return Ok(false);
+        };
+        match collections.get(&collection_id).await {
+            Ok(collection) => Ok(self.governs(&collection)),
+            Err(DatabaseError::NotFound) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Whether changes to items filed in `collection` need approval
+    pub fn governs(&self, collection: &ItemCollection) -> bool {
+        collection
+            .lineage()
+            .iter()
+            .any(|id| self.collections.contains(id))
+    }
+
+    /// Whether changes to items filed in `collection` would still need
+    /// approval with the collection moved under `parent`, or to the root
+    pub fn governs_under(
+        &self,
+        collection: &ItemCollection,
+        parent: Option<&ItemCollection>,
+    ) -> bool {
+        self.collections.contains(&collection.id)
+            || parent.is_some_and(|parent| self.governs(parent))
+    }
+}
+
+/// Principals who may approve changes to `item`: its owner and the
+/// principals granted write access to it or a collection it is filed in or
+/// below
+///
+/// Like inbox notifications, role grants are not expanded; members of a
+/// role with write access may still approve, they are just not told.
+pub async fn approvers(
+    access: &dyn AccessRepository,
+    collections: &dyn CollectionRepository,
+    item: &Item,
+) -> DatabaseResult<Vec<String>> {
+    let mut resources = vec![item.id.clone()];
+    if let Some(collection_id) = collections.item_collection(&item.id).await? {
+        match collections.get(&collection_id).await {
+            Ok(collection) => resources.extend(collection.lineage()),
+            Err(DatabaseError::NotFound) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    let mut approvers: Vec<String> = item.owner_id.iter().cloned().collect();
+    for resource in &resources {
+        let grants = match access.list_grants(resource).await {
+            Ok(grants) => grants,
+            Err(DatabaseError::NotFound) => continue,
+            Err(e) => return Err(e),
+        };
+        for grant in grants {
+            if let Grantee::Principal(principal) = grant.grantee {
+                if grant.permission.allows(Permission::Write) && !approvers.contains(&principal) {
+                    approvers.push(principal);
+                }
+            }
+        }
+    }
+    Ok(approvers)
+}
+
+/// Mutation a change request asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeAction {
+    Update,
+    Delete,
+    /// Publish a draft now
+    Publish,
+}
+
+impl ChangeAction {
+    /// Kind of item event the change makes once applied
+    pub fn event_type(self) -> ItemEventType {
+        match self {
+            Self::Update | Self::Publish => ItemEventType::Updated,
+            Self::Delete => ItemEventType::Deleted,
+        }
+    }
+}
+
+/// Where a change request stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeStatus {
+    /// Waiting for approvals
+    Pending,
+    /// Approved and made to the item
+    Applied,
+    /// Turned down by an approver, or withdrawn by its requester
+    Rejected,
+    /// Approved, but the item changed or was deleted since it was requested
+    Failed,
+}
+
+impl ChangeStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Applied => "applied",
+            Self::Rejected => "rejected",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// A principal's approval of a change request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Approval {
+    #[schema(example = "user-456")]
+    pub principal: String,
+    pub approved_at: DateTime<Utc>,
+}
+
+/// A change to an item held until enough principals approve it
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ChangeRequest {
+    #[schema(example = "3f2b8c1e-6a4d-4e9f-8b7a-1c2d3e4f5a6b")]
+    pub id: String,
+    pub item_id: String,
+    pub action: ChangeAction,
+    /// Fields to set, for updates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update: Option<UpdateItemRequest>,
+    /// Version of the item the change was requested against; it is only
+    /// applied to that version
+    pub base_version: u64,
+    /// Subject of the principal that asked for the change
+    #[schema(example = "user-123")]
+    pub requested_by: String,
+    pub required_approvals: u32,
+    pub approvals: Vec<Approval>,
+    pub status: ChangeStatus,
+    /// Subject of the principal that rejected the change, or whose approval applied it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_by: Option<String>,
+    /// Why the change was rejected or failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+}
+
+impl ChangeRequest {
+    /// A pending request, in the current tenant, to make `action` to `item`
+    pub fn new(
+        item: &Item,
+        action: ChangeAction,
+        update: Option<UpdateItemRequest>,
+        requested_by: &str,
+        required_approvals: u32,
+        now: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            item_id: item.id.clone(),
+            action,
+            update,
+            base_version: item.version,
+            requested_by: requested_by.to_string(),
+            required_approvals,
+            approvals: Vec::new(),
+            status: ChangeStatus::Pending,
+            resolved_by: None,
+            reason: None,
+            created_at: now,
+            resolved_at: None,
+            tenant_id: current_tenant(),
+        }
+    }
+
+    /// Whether the request has all the approvals it needs
+    pub fn is_approved(&self) -> bool {
+        self.approvals.len() >= self.required_approvals as usize
+    }
+
+    /// Whether `principal` approved the request
+    pub fn approved_by(&self, principal: &str) -> bool {
+        self.approvals
+            .iter()
+            .any(|approval| approval.principal == principal)
+    }
+
+    /// Inbox notifications asking `approvers` to review the request
+    pub fn approval_requested(&self, item_name: &str, approvers: &[String]) -> Vec<Notification> {
+        approvers
+            .iter()
+            .filter(|approver| **approver != self.requested_by)
+            .map(|approver| Notification {
+                id: Uuid::new_v4().to_string(),
+                recipient: approver.clone(),
+                event_id: self.id.clone(),
+                event_type: self.action.event_type(),
+                item_id: self.item_id.clone(),
+                item_name: item_name.to_string(),
+                actor: Some(self.requested_by.clone()),
+                reason: NotificationReason::Approval,
+                saved_search_id: None,
+                change_request_id: Some(self.id.clone()),
+                created_at: self.created_at,
+                read_at: None,
+                tenant_id: self.tenant_id.clone(),
+            })
+            .collect()
+    }
+
+    /// Inbox notification telling the requester how the request was resolved
+    pub fn resolved(&self, item_name: &str) -> Notification {
+        Notification {
+            id: Uuid::new_v4().to_string(),
+            recipient: self.requested_by.clone(),
+            event_id: format!("{}:{}", self.id, self.status.as_str()),
+            event_type: self.action.event_type(),
+            item_id: self.item_id.clone(),
+            item_name: item_name.to_string(),
+            actor: self.resolved_by.clone(),
+            reason: NotificationReason::ChangeRequest,
+            saved_search_id: None,
+            change_request_id: Some(self.id.clone()),
+            created_at: self.resolved_at.unwrap_or(self.created_at),
+            read_at: None,
+            tenant_id: self.tenant_id.clone(),
+        }
+    }
+}
+
+/// Request to reject a change request
+#[derive(Debug, Default, Deserialize, Validate, ToSchema)]
+#[schema(example = json!({ "reason": "The description is out of date" }))]
+pub struct RejectChangeRequest {
+    /// Why the change is rejected (at most 1000 characters)
+    #[validate(length(max = 1000, message = "Reason must not exceed 1000 characters"))]
+    pub reason: Option<String>,
+}
+
+/// A page of the change requests the caller asked for or may decide on
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ChangeRequestList {
+    pub changes: Vec<ChangeRequest>,
+    /// Requests matching the query
+    pub total: usize,
+    pub limit: usize,
+    pub offset: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::InMemoryCollectionRepository;
+
+    #[tokio::test]
+    async fn test_items_below_designated_collections_need_approval() {
+        let collections = InMemoryCollectionRepository::new();
+        let root = ItemCollection::new("Contracts".to_string(), None, None, Utc::now());
+        let nested = ItemCollection::new("Signed".to_string(), Some(&root), None, Utc::now());
+        collections.create(root.clone()).await.unwrap();
+        collections.create(nested.clone()).await.unwrap();
+        collections
+            .file_item("item-1", Some(&nested.id))
+            .await
+            .unwrap();
+        let approvals = Approvals::from_config(&ApprovalConfig {
+            collections: vec![root.id.clone()],
+            required_approvals: 2,
+        });
+
+        assert!(approvals
+            .requires_approval(&collections, "item-1")
+            .await
+            .unwrap());
+        assert!(!approvals
+            .requires_approval(&collections, "item-2")
+            .await
+            .unwrap());
+        assert!(approvals.governs_under(&root, None));
+        assert!(!approvals.governs_under(&nested, None));
+        assert!(approvals.governs_under(&nested, Some(&root)));
+        assert!(!Approvals::default()
+            .requires_approval(&collections, "item-1")
+            .await
+            .unwrap());
+    }
+}
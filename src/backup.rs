@@ -0,0 +1,360 @@
+use async_compression::tokio::{bufread::GzipDecoder, write::GzipEncoder};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::{
+    db::{AccessRepository, ItemFilter, ItemRepository},
+    error::{AppError, AppResult},
+    models::{AccessGrant, Item},
+    pagination::{Page, MAX_PAGE_LIMIT},
+    tenancy::{current_tenant, with_optional_tenant},
+};
+
+/// Version of the backup format written by this build
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Items fetched from the repository per page while exporting
+const EXPORT_PAGE_SIZE: usize = MAX_PAGE_LIMIT;
+
+/// Buffer between the export task and the response body
+const EXPORT_BUFFER_BYTES: usize = 64 * 1024;
+
+/// Initial size of the buffer each record is serialized into
+const RECORD_BUFFER_BYTES: usize = 1024;
+
+/// One line of a backup
+///
+/// A backup is gzip-compressed newline-delimited JSON: a header, then every
+/// item followed by its access grants, then a footer with record counts.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BackupRecord {
+    Header {
+        backup_id: String,
+        version: u32,
+        created_at: DateTime<Utc>,
+    },
+    Item(Arc<Item>),
+    Grant(AccessGrant),
+    Footer {
+        items: usize,
+        grants: usize,
+    },
+}
+
+/// Summary of a completed restore
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RestoreReport {
+    pub backup_id: String,
+    pub created_at: DateTime<Utc>,
+    pub items: usize,
+    pub grants: usize,
+}
+
+/// Start streaming a backup of all repositories
+///
+/// Returns the backup ID (which doubles as the restore confirmation token) and
+/// a reader producing the compressed archive. Items are read page by page, so
+/// the backup is not a point-in-time snapshot of concurrent writes.
+pub fn export(
+    repo: Arc<dyn ItemRepository>,
+    access: Arc<dyn AccessRepository>,
+) -> (String, impl AsyncRead + Send + 'static) {
+    let backup_id = uuid::Uuid::new_v4().to_string();
+    let (writer, reader) = tokio::io::duplex(EXPORT_BUFFER_BYTES);
+
+    let id = backup_id.clone();
+    let tenant = current_tenant();
+    tokio::spawn(with_optional_tenant(tenant, async move {
+        let mut encoder = GzipEncoder::new(writer);
+        match write_backup(&mut encoder, &id, repo.as_ref(), access.as_ref()).await {
+            Ok((items, grants)) => {
+                if let Err(e) = encoder.shutdown().await {
+                    warn!(backup_id = %id, "Failed to finish backup stream: {}", e);
+                } else {
+                    info!(backup_id = %id, items, grants, "Backup completed");
+                }
+            }
+            // Dropping the encoder without a gzip trailer makes the archive
+            // unreadable, so a partial backup can never be restored
+            Err(e) => warn!(backup_id = %id, "Backup aborted: {}", e),
+        }
+    }));
+
+    (backup_id, reader)
+}
+
+async fn write_backup<W: tokio::io::AsyncWrite + Unpin>(
+    out: &mut W,
+    backup_id: &str,
+    repo: &dyn ItemRepository,
+    access: &dyn AccessRepository,
+) -> Result<(usize, usize), String> {
+    // One buffer is reused for every record, so a large export does not
+    // allocate per line
+    let mut line = Vec::with_capacity(RECORD_BUFFER_BYTES);
+    write_record(
+        out,
+        &mut line,
+        &BackupRecord::Header {
+            backup_id: backup_id.to_string(),
+            version: FORMAT_VERSION,
+            created_at: Utc::now(),
+        },
+    )
+    .await?;
+
+    let filter = ItemFilter::default();
+    let (mut items, mut grants) = (0, 0);
+    let mut page = Page::first(EXPORT_PAGE_SIZE).map_err(|e| e.to_string())?;
+    loop {
+        let batch = repo.list(&filter, &page).await.map_err(|e| e.to_string())?;
+        let batch_len = batch.len();
+        if let Some(last) = batch.last() {
+            page = page.next_after(last);
+        }
+
+        for item in batch {
+            let item_grants = access
+                .list_grants(&item.id)
+                .await
+                .map_err(|e| e.to_string())?;
+            write_record(out, &mut line, &BackupRecord::Item(item)).await?;
+            for grant in item_grants {
+                write_record(out, &mut line, &BackupRecord::Grant(grant)).await?;
+                grants += 1;
+            }
+        }
+
+        items += batch_len;
+        if batch_len < EXPORT_PAGE_SIZE {
+            break;
+        }
+    }
+
+    write_record(out, &mut line, &BackupRecord::Footer { items, grants }).await?;
+    Ok((items, grants))
+}
+
+async fn write_record<W: tokio::io::AsyncWrite + Unpin>(
+    out: &mut W,
+    line: &mut Vec<u8>,
+    record: &BackupRecord,
+) -> Result<(), String> {
+    line.clear();
+    serde_json::to_writer(&mut *line, record).map_err(|e| e.to_string())?;
+    line.push(b'\n');
+    out.write_all(line).await.map_err(|e| e.to_string())
+}
+
+/// Restore a compressed backup, upserting every record it contains
+///
+/// `confirm` must equal the backup ID in the archive header; nothing is
+/// written until it matches. Records are applied as they are read, so an
+/// archive that turns out to be corrupt part-way is reported as an error
+/// after the records before the corruption were restored.
+pub async fn restore<R: AsyncBufRead + Unpin>(
+    archive: R,
+    repo: &dyn ItemRepository,
+    access: &dyn AccessRepository,
+    confirm: &str,
+) -> AppResult<RestoreReport> {
+    let mut lines = BufReader::new(GzipDecoder::new(archive)).lines();
+
+    let (backup_id, created_at) = match next_record(&mut lines, 1).await? {
+        Some(BackupRecord::Header {
+            backup_id,
+            version,
+            created_at,
+        }) => {
+            if version != FORMAT_VERSION {
+                return Err(AppError::BadRequest(format!(
+                    "Unsupported backup format version {version} (expected {FORMAT_VERSION})"
+                )));
+            }
+            (backup_id, created_at)
+        }
+        _ => {
+            return Err(AppError::BadRequest("Backup must start with a header record".to_string()))
+        }
+    };
+
+    if confirm != backup_id {
+        return Err(AppError::BadRequest(
+            "Confirmation token does not match the backup ID".to_string(),
+        ));
+    }
+
+    let (mut items, mut grants) = (0, 0);
+    let mut line_number = 1;
+    loop {
+        line_number += 1;
+        match next_record(&mut lines, line_number).await? {
+            Some(BackupRecord::Item(item)) => {
+                repo.restore(Arc::unwrap_or_clone(item)).await?;
+                items += 1;
+            }
+            Some(BackupRecord::Grant(grant)) => {
+                access.restore_grant(grant).await?;
+                grants += 1;
+            }
+            Some(BackupRecord::Footer {
+                items: expected_items,
+                grants: expected_grants,
+            }) => {
+                if (expected_items, expected_grants) != (items, grants) {
+                    return Err(AppError::BadRequest(format!(
+                        "Backup footer expects {expected_items} items and {expected_grants} grants, found {items} and {grants}"
+                    )));
+                }
+                break;
+            }
+            Some(BackupRecord::Header { .. }) => {
+                return Err(AppError::BadRequest(format!(
+                    "Unexpected header on line {line_number}"
+                )))
+            }
+            None => {
+                return Err(AppError::BadRequest(
+                    "Backup is truncated (missing footer)".to_string(),
+                ))
+            }
+        }
+    }
+
+    info!(%backup_id, items, grants, "Backup restored");
+    Ok(RestoreReport {
+        backup_id,
+        created_at,
+        items,
+        grants,
+    })
+}
+
+async fn next_record<R: AsyncBufRead + Unpin>(
+    lines: &mut tokio::io::Lines<R>,
+    line_number: usize,
+) -> AppResult<Option<BackupRecord>> {
+    let line = lines
+        .next_line()
+        .await
+        .map_err(|e| AppError::BadRequest(format!("Backup is not a valid gzip archive: {e}")))?;
+
+    line.map(|line| {
+        serde_json::from_str(&line).map_err(|e| {
+            AppError::BadRequest(format!("Invalid backup record on line {line_number}: {e}"))
+        })
+    })
+    .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{InMemoryAccessRepository, InMemoryRepository},
+        models::{CreateItemRequest, Grantee, Permission},
+    };
+    use tokio::io::AsyncReadExt;
+
+    async fn export_to_vec(
+        repo: Arc<dyn ItemRepository>,
+        access: Arc<dyn AccessRepository>,
+    ) -> (String, Vec<u8>) {
+        let (backup_id, mut reader) = export(repo, access);
+        let mut archive = Vec::new();
+        reader.read_to_end(&mut archive).await.unwrap();
+        (backup_id, archive)
+    }
+
+    #[tokio::test]
+    async fn test_backup_round_trip() {
+        let repo: Arc<dyn ItemRepository> = Arc::new(InMemoryRepository::new());
+        let access: Arc<dyn AccessRepository> = Arc::new(InMemoryAccessRepository::new());
+
+        for i in 0..EXPORT_PAGE_SIZE + 3 {
+            let request = CreateItemRequest {
+                name: format!("Item {i}"),
+                description: None,
+                metadata: None,
+                custom_fields: Default::default(),
+                status: Default::default(),
+                publish_at: None,
+                external_id: None,
+                source: None,
+            };
+            repo.create(request, Some("alice".to_string()))
+                .await
+                .unwrap();
+        }
+        let first = &repo
+            .list(&ItemFilter::default(), &Page::first(1).unwrap())
+            .await
+            .unwrap()[0];
+        access
+            .grant(&first.id, Grantee::Principal("bob".to_string()), Permission::Read, None)
+            .await
+            .unwrap();
+
+        let (backup_id, archive) = export_to_vec(repo, access).await;
+
+        let restored_repo = InMemoryRepository::new();
+        let restored_access = InMemoryAccessRepository::new();
+        let report = restore(archive.as_slice(), &restored_repo, &restored_access, &backup_id)
+            .await
+            .unwrap();
+
+        assert_eq!(report.items, EXPORT_PAGE_SIZE + 3);
+        assert_eq!(report.grants, 1);
+        let restored = restored_repo.get(&first.id).await.unwrap();
+        assert_eq!(restored.slug, first.slug);
+        assert_eq!(restored.owner_id.as_deref(), Some("alice"));
+        assert_eq!(restored_access.list_grants(&first.id).await.unwrap().len(), 1);
+        // Restores do not replay as new events
+        assert_eq!(restored_repo.pending_event_count().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_restore_requires_matching_confirmation() {
+        let repo: Arc<dyn ItemRepository> = Arc::new(InMemoryRepository::new());
+        let access: Arc<dyn AccessRepository> = Arc::new(InMemoryAccessRepository::new());
+        repo.create(
+            CreateItemRequest {
+                name: "Widget".to_string(),
+                description: None,
+                metadata: None,
+                custom_fields: Default::default(),
+                status: Default::default(),
+                publish_at: None,
+                external_id: None,
+                source: None,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+        let (_, archive) = export_to_vec(repo, access.clone()).await;
+
+        let target = InMemoryRepository::new();
+        let result = restore(archive.as_slice(), &target, access.as_ref(), "wrong").await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+        assert_eq!(target.count(&ItemFilter::default()).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_restore_rejects_truncated_archive() {
+        let repo: Arc<dyn ItemRepository> = Arc::new(InMemoryRepository::new());
+        let access: Arc<dyn AccessRepository> = Arc::new(InMemoryAccessRepository::new());
+        let (backup_id, archive) = export_to_vec(repo, access.clone()).await;
+
+        let truncated = &archive[..archive.len() / 2];
+        let target = InMemoryRepository::new();
+        let result = restore(truncated, &target, access.as_ref(), &backup_id).await;
+        assert!(result.is_err());
+    }
+}
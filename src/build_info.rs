@@ -0,0 +1,62 @@
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use utoipa::ToSchema;
+
+/// What this binary was built from, embedded at compile time by `build.rs`
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BuildInfo {
+    #[schema(example = "0.1.0")]
+    pub version: String,
+    /// Commit built, `unknown` outside a repository unless `GIT_SHA` was set
+    #[schema(example = "436ef85c1f6b7e2d9a0b3c4d5e6f7a8b9c0d1e2f")]
+    pub git_sha: String,
+    pub built_at: DateTime<Utc>,
+    #[schema(example = "rustc 1.89.0 (29483883e 2025-08-04)")]
+    pub rustc_version: String,
+    /// Cargo features enabled, such as `console`
+    pub features: Vec<String>,
+}
+
+/// Build metadata of the running binary
+pub static BUILD_INFO: Lazy<BuildInfo> = Lazy::new(|| BuildInfo {
+    version: env!("CARGO_PKG_VERSION").to_string(),
+    git_sha: env!("FERROUS_GIT_SHA").to_string(),
+    built_at: env!("FERROUS_BUILT_AT")
+        .parse()
+        .ok()
+        .and_then(|seconds| DateTime::from_timestamp(seconds, 0))
+        .unwrap_or_default(),
+    rustc_version: env!("FERROUS_RUSTC_VERSION").to_string(),
+    features: env!("FERROUS_FEATURES")
+        .split(',')
+        .filter(|feature| !feature.is_empty())
+        .map(str::to_string)
+        .collect(),
+});
+
+impl BuildInfo {
+    /// The commit, shortened as `git log --oneline` shows it
+    pub fn short_sha(&self) -> &str {
+        self.git_sha.get(..7).unwrap_or(&self.git_sha)
+    }
+}
+
+/// One line for the startup banner
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ferrous {} ({}, built {}, {}",
+            self.version,
+            self.short_sha(),
+            self.built_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            self.rustc_version
+        )?;
+        if !self.features.is_empty() {
+            write!(f, ", features: {}", self.features.join(", "))?;
+        }
+        write!(f, ")")
+    }
+}
@@ -0,0 +1,452 @@
+use async_compression::tokio::write::GzipEncoder;
+use chrono::{DateTime, Duration, Utc};
+use reqwest::{header::CONTENT_TYPE, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, sync::Arc};
+use tokio::{io::AsyncWriteExt, sync::Mutex, task::JoinHandle};
+use tracing::{debug, info, warn};
+
+use crate::{
+    attachments::S3Signer,
+    config::CdcExportConfig,
+    db::{EventFilter, ItemRepository},
+    events::{ItemEventType, OutboxEvent},
+    http_client::HttpClient,
+    leadership::Leadership,
+    metrics::track_cdc_export,
+    models::Item,
+};
+
+/// Object recording the last exported sequence, under the prefix
+const CHECKPOINT: &str = "_checkpoint.json";
+
+/// How long the presigned URL of each request is valid
+const REQUEST_EXPIRY: Duration = Duration::minutes(5);
+
+/// One exported item change: a line of an exported file
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CdcRecord {
+    /// Outbox position of the change; files can be merged in this order
+    pub sequence: u64,
+    /// Event ID, stable across exports of the same change
+    pub id: String,
+    #[serde(rename = "type")]
+    pub change_type: ItemEventType,
+    pub item_id: String,
+    /// The item after the change (before it, for deletions)
+    pub item: Item,
+    pub occurred_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor: Option<String>,
+}
+
+impl From<&OutboxEvent> for CdcRecord {
+    fn from(event: &OutboxEvent) -> Self {
+        Self {
+            sequence: event.sequence,
+            id: event.id.clone(),
+            change_type: event.event_type,
+            item_id: event.item_id.clone(),
+            item: event.item.clone(),
+            occurred_at: event.occurred_at,
+            actor: event.actor.clone(),
+        }
+    }
+}
+
+/// Where the export left off, kept in the bucket beside the files
+#[derive(Debug, Serialize, Deserialize)]
+struct Checkpoint {
+    sequence: u64,
+    exported_at: DateTime<Utc>,
+}
+
+/// Changes and files written by one export run
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CdcExportReport {
+    pub changes: usize,
+    pub files: usize,
+}
+
+/// Periodically exports item changes from the outbox to S3, for analytics
+/// pipelines to consume without calling the API
+///
+/// Changes are written as gzip-compressed NDJSON, partitioned Hive-style by
+/// the hour they occurred in:
+/// `<prefix>/date=2024-01-15/hour=10/<first sequence>-<last sequence>.ndjson.gz`.
+/// Each run exports the changes after the sequence in `<prefix>/_checkpoint.json`
+/// and then advances it, so a run that fails part-way is repeated from the
+/// checkpoint and may export some changes twice; readers can drop duplicates
+/// by `id`. Changes retention purges before they are exported are lost.
+pub struct CdcExporter {
+    repo: Arc<dyn ItemRepository>,
+    signer: S3Signer,
+    http: HttpClient,
+    prefix: String,
+    interval: std::time::Duration,
+    batch_size: usize,
+    leadership: Leadership,
+    /// Last sequence exported, once read from the checkpoint
+    exported: Mutex<Option<u64>>,
+}
+
+impl CdcExporter {
+    /// Exporter for the configured bucket; `None` when no bucket is configured
+    pub fn from_config(
+        config: &CdcExportConfig,
+        repo: Arc<dyn ItemRepository>,
+        http: HttpClient,
+    ) -> Result<Option<Self>, String> {
+        let Some(bucket) = &config.bucket else {
+            return Ok(None);
+        };
+        let (Some(access_key_id), Some(secret_access_key)) =
+            (&config.access_key_id, &config.secret_access_key)
+        else {
+            return Err("CDC export requires S3 access keys".to_string());
+        };
+        let signer = S3Signer::new(
+            bucket,
+            &config.region,
+            config.endpoint.as_deref(),
+            access_key_id,
+            secret_access_key,
+        )
+        .map_err(|e| format!("Invalid CDC_EXPORT_S3_ENDPOINT: {e}"))?;
+        Ok(Some(Self {
+            repo,
+            signer,
+            http,
+            prefix: config.prefix.clone(),
+            interval: std::time::Duration::from_secs(config.interval_seconds),
+            batch_size: config.batch_size.max(1),
+            leadership: Leadership::always(),
+            exported: Mutex::new(None),
+        }))
+    }
+
+    /// Export only while this replica leads
+    #[must_use]
+    pub fn with_leadership(mut self, leadership: Leadership) -> Self {
+        self.leadership = leadership;
+        self
+    }
+
+    /// Run the export until the task is aborted
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.interval);
+            loop {
+                interval.tick().await;
+                if !self.leadership.is_leader() {
+                    // Another replica may advance the checkpoint meanwhile
+                    *self.exported.lock().await = None;
+                    continue;
+                }
+                if let Err(e) = self.run_once().await {
+                    warn!("CDC export failed: {}", e);
+                }
+            }
+        })
+    }
+
+    /// Export every change after the checkpoint
+    pub async fn run_once(&self) -> Result<CdcExportReport, String> {
+        let mut exported = self.exported.lock().await;
+        let mut after = match *exported {
+            Some(sequence) => sequence,
+            None => self.read_checkpoint().await?,
+        };
+        let mut report = CdcExportReport::default();
+        loop {
+            let events = self
+                .repo
+                .event_history(&EventFilter::default(), after, self.batch_size)
+                .await
+                .map_err(|e| e.to_string())?;
+            let Some(last) = events.last().map(|event| event.sequence) else {
+                break;
+            };
+            for file in partition(&events) {
+                let key = self.key(&object_name(file));
+                self.put(&key, "application/x-ndjson", &ndjson_gz(file).await?)
+                    .await?;
+                track_cdc_export(file.len(), file[file.len() - 1].sequence);
+                report.changes += file.len();
+                report.files += 1;
+            }
+            self.write_checkpoint(last).await?;
+            after = last;
+            *exported = Some(last);
+            if events.len() < self.batch_size {
+                break;
+            }
+        }
+        *exported = Some(after);
+        if report.files > 0 {
+            info!(
+                changes = report.changes,
+                files = report.files,
+                sequence = after,
+                "Exported item changes"
+            );
+        } else {
+            debug!(sequence = after, "No item changes to export");
+        }
+        Ok(report)
+    }
+
+    fn key(&self, name: &str) -> String {
+        if self.prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{name}", self.prefix)
+        }
+    }
+
+    async fn read_checkpoint(&self) -> Result<u64, String> {
+        let url = self.signer.presign(
+            "GET",
+            &self.key(CHECKPOINT),
+            &BTreeMap::new(),
+            Utc::now(),
+            REQUEST_EXPIRY,
+        );
+        let response = self
+            .http
+            .send(self.http.get(url))
+            .await
+            .map_err(|e| format!("reading the checkpoint failed: {e}"))?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(0),
+            status if status.is_success() => response
+                .json::<Checkpoint>()
+                .await
+                .map(|checkpoint| checkpoint.sequence)
+                .map_err(|e| format!("invalid checkpoint: {e}")),
+            status => Err(format!("reading the checkpoint answered {status}")),
+        }
+    }
+
+    async fn write_checkpoint(&self, sequence: u64) -> Result<(), String> {
+        let checkpoint = Checkpoint {
+            sequence,
+            exported_at: Utc::now(),
+        };
+        let body = serde_json::to_vec(&checkpoint).map_err(|e| e.to_string())?;
+        self.put(&self.key(CHECKPOINT), "application/json", &body)
+            .await
+    }
+
+    async fn put(&self, key: &str, content_type: &str, body: &[u8]) -> Result<(), String> {
+        let headers = BTreeMap::from([("content-type".to_string(), content_type.to_string())]);
+        let url = self
+            .signer
+            .presign("PUT", key, &headers, Utc::now(), REQUEST_EXPIRY);
+        let request = self
+            .http
+            .put(url)
+            .header(CONTENT_TYPE, content_type)
+            .body(body.to_vec());
+        let response = self
+            .http
+            .send(request)
+            .await
+            .map_err(|e| format!("writing {key} failed: {e}"))?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("writing {key} answered {}", response.status()))
+        }
+    }
+}
+
+/// `events` split into runs occurring in the same hour, one file each
+fn partition(events: &[OutboxEvent]) -> Vec<&[OutboxEvent]> {
+    let hour = |event: &OutboxEvent| event.occurred_at.format("%Y-%m-%d %H").to_string();
+    let mut files = Vec::new();
+    let mut start = 0;
+    for i in 1..=events.len() {
+        if i == events.len() || hour(&events[i]) != hour(&events[start]) {
+            files.push(&events[start..i]);
+            start = i;
+        }
+    }
+    files
+}
+
+/// Key of the file holding `events`, relative to the prefix
+fn object_name(events: &[OutboxEvent]) -> String {
+    let (first, last) = (&events[0], &events[events.len() - 1]);
+    format!(
+        "{}/{:020}-{:020}.ndjson.gz",
+        first.occurred_at.format("date=%Y-%m-%d/hour=%H"),
+        first.sequence,
+        last.sequence
+    )
+}
+
+async fn ndjson_gz(events: &[OutboxEvent]) -> Result<Vec<u8>, String> {
+    let mut encoder = GzipEncoder::new(Vec::new());
+    for event in events {
+        let mut line = serde_json::to_vec(&CdcRecord::from(event)).map_err(|e| e.to_string())?;
+        line.push(b'\n');
+        encoder.write_all(&line).await.map_err(|e| e.to_string())?;
+    }
+    encoder.shutdown().await.map_err(|e| e.to_string())?;
+    Ok(encoder.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::InMemoryRepository, models::CreateItemRequest};
+    use async_compression::tokio::bufread::GzipDecoder;
+    use axum::{
+        body::Bytes,
+        extract::{Path, State},
+        routing::get,
+        Router,
+    };
+    use std::{collections::HashMap, sync::Mutex as StdMutex};
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    type Objects = Arc<StdMutex<HashMap<String, Vec<u8>>>>;
+
+    /// A bucket served path-style, keeping objects in memory
+    async fn spawn_bucket(objects: Objects) -> String {
+        let app = Router::new()
+            .route(
+                "/bucket/{*key}",
+                get(
+                    |State(objects): State<Objects>, Path(key): Path<String>| async move {
+                        match objects.lock().unwrap().get(&key) {
+                            Some(body) => Ok(body.clone()),
+                            None => Err(StatusCode::NOT_FOUND),
+                        }
+                    },
+                )
+                .put(
+                    |State(objects): State<Objects>, Path(key): Path<String>, body: Bytes| async move {
+                        objects.lock().unwrap().insert(key, body.to_vec());
+                    },
+                ),
+            )
+            .with_state(objects);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}")
+    }
+
+    async fn lines(body: &[u8]) -> Vec<CdcRecord> {
+        let mut lines = BufReader::new(GzipDecoder::new(body)).lines();
+        let mut records = Vec::new();
+        while let Some(line) = lines.next_line().await.unwrap() {
+            records.push(serde_json::from_str(&line).unwrap());
+        }
+        records
+    }
+
+    fn exporter(endpoint: String, repo: Arc<dyn ItemRepository>, batch_size: usize) -> CdcExporter {
+        let config = CdcExportConfig {
+            bucket: Some("bucket".to_string()),
+            endpoint: Some(endpoint),
+            access_key_id: Some("key".to_string()),
+            secret_access_key: Some("secret".to_string()),
+            batch_size,
+            ..CdcExportConfig::default()
+        };
+        CdcExporter::from_config(&config, repo, HttpClient::default())
+            .unwrap()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_changes_are_exported_incrementally() {
+        let objects = Objects::default();
+        let endpoint = spawn_bucket(objects.clone()).await;
+        let repo: Arc<dyn ItemRepository> = Arc::new(InMemoryRepository::new());
+        let create = |name: &str| CreateItemRequest {
+            name: name.to_string(),
+            description: None,
+            metadata: None,
+            custom_fields: BTreeMap::new(),
+            status: Default::default(),
+            publish_at: None,
+            external_id: None,
+            source: None,
+        };
+        for name in ["one", "two", "three"] {
+            repo.create(create(name), None).await.unwrap();
+        }
+
+        let first = exporter(endpoint.clone(), repo.clone(), 2);
+        let report = first.run_once().await.unwrap();
+        assert_eq!(report.changes, 3);
+        assert!(report.files >= 2);
+
+        let files: Vec<(String, Vec<u8>)> = objects
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(key, _)| key.ends_with(".ndjson.gz"))
+            .map(|(key, body)| (key.clone(), body.clone()))
+            .collect();
+        let mut exported = Vec::new();
+        for (key, body) in &files {
+            assert!(key.starts_with("cdc/date="), "{key}");
+            assert!(key.contains("/hour="), "{key}");
+            exported.extend(lines(body).await);
+        }
+        exported.sort_by_key(|record| record.sequence);
+        let names: Vec<_> = exported
+            .iter()
+            .map(|record| record.item.name.as_str())
+            .collect();
+        assert_eq!(names, ["one", "two", "three"]);
+
+        // A new exporter, as after a restart, continues from the checkpoint
+        repo.create(create("four"), None).await.unwrap();
+        let second = exporter(endpoint, repo, 100);
+        let report = second.run_once().await.unwrap();
+        assert_eq!(
+            report,
+            CdcExportReport {
+                changes: 1,
+                files: 1
+            }
+        );
+        assert_eq!(second.run_once().await.unwrap(), CdcExportReport::default());
+    }
+
+    #[test]
+    fn test_files_do_not_span_hours() {
+        let item: Item = serde_json::from_value(serde_json::json!({
+            "id": "item-1",
+            "name": "Item",
+            "slug": "item",
+            "created_at": "2024-01-15T10:00:00Z",
+            "updated_at": "2024-01-15T10:00:00Z",
+            "version": 1
+        }))
+        .unwrap();
+        let at = |sequence, time: &str| {
+            let mut event = OutboxEvent::new(sequence, ItemEventType::Updated, item.clone());
+            event.occurred_at = time.parse().unwrap();
+            event
+        };
+        let events = [
+            at(7, "2024-01-15T10:58:00Z"),
+            at(8, "2024-01-15T10:59:59Z"),
+            at(9, "2024-01-15T11:00:00Z"),
+        ];
+        let files = partition(&events);
+        assert_eq!(files.len(), 2);
+        assert_eq!(
+            object_name(files[0]),
+            "date=2024-01-15/hour=10/00000000000000000007-00000000000000000008.ndjson.gz"
+        );
+        assert_eq!(files[1].len(), 1);
+    }
+}
@@ -0,0 +1,134 @@
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::{db::ItemLockRepository, error::AppError, middleware::auth::Claims};
+
+/// Principal holding the lock on an item, on item responses
+pub static X_LOCKED_BY: HeaderName = HeaderName::from_static("x-locked-by");
+/// When the lock on an item expires, on item responses
+pub static X_LOCK_EXPIRES: HeaderName = HeaderName::from_static("x-lock-expires");
+
+/// An item checked out by one principal, who alone may change it until the
+/// lock is released or expires
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ItemLock {
+    pub item_id: String,
+    /// Subject of the principal holding the lock
+    #[schema(example = "user-123")]
+    pub owner: String,
+    /// When the owner first took the lock; renewing it keeps this time
+    pub acquired_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Request to lock an item, or to extend a lock the caller holds
+#[derive(Debug, Default, Deserialize, Validate, ToSchema)]
+#[schema(example = json!({ "ttl_seconds": 600 }))]
+pub struct LockItemRequest {
+    /// How long the lock lasts; the configured default when absent
+    #[validate(range(min = 1))]
+    pub ttl_seconds: Option<u64>,
+}
+
+/// Refuse a change to `item_id` while someone other than `claims` holds it
+pub async fn ensure_unlocked(
+    locks: &dyn ItemLockRepository,
+    item_id: &str,
+    claims: Option<&Claims>,
+) -> Result<(), AppError> {
+    match locks.get(item_id).await? {
+        Some(lock) if claims.is_none_or(|claims| claims.sub != lock.owner) => Err(locked(&lock)),
+        _ => Ok(()),
+    }
+}
+
+/// The error for a change refused because `lock` is someone else's
+pub fn locked(lock: &ItemLock) -> AppError {
+    AppError::Locked(format!(
+        "Item {} is locked by {} until {}",
+        lock.item_id,
+        lock.owner,
+        lock.expires_at.to_rfc3339_opts(SecondsFormat::Secs, true)
+    ))
+}
+
+/// Headers telling who holds `lock`, if the item is locked
+pub fn headers(lock: Option<&ItemLock>) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if let Some(lock) = lock {
+        if let Ok(owner) = HeaderValue::from_str(&lock.owner) {
+            headers.insert(X_LOCKED_BY.clone(), owner);
+        }
+        let expires = lock.expires_at.to_rfc3339_opts(SecondsFormat::Secs, true);
+        if let Ok(expires) = HeaderValue::from_str(&expires) {
+            headers.insert(X_LOCK_EXPIRES.clone(), expires);
+        }
+    }
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        clock::{Clock, ManualClock, SharedClock},
+        db::InMemoryItemLockRepository,
+    };
+    use std::{sync::Arc, time::Duration};
+
+    fn claims(sub: &str) -> Claims {
+        Claims {
+            sub: sub.to_string(),
+            ..Claims::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_locks_stop_other_principals_until_they_expire() {
+        let clock = Arc::new(ManualClock::default());
+        let shared: SharedClock = clock.clone();
+        let locks = InMemoryItemLockRepository::new().with_clock(shared);
+        let now = clock.now();
+        let lock = |owner: &str, expires_at| ItemLock {
+            item_id: "item-1".to_string(),
+            owner: owner.to_string(),
+            acquired_at: now,
+            expires_at,
+        };
+
+        let held = locks
+            .acquire(lock("alice", now + chrono::Duration::seconds(60)))
+            .await
+            .unwrap();
+        assert_eq!(held.owner, "alice");
+        assert_eq!(
+            locks
+                .acquire(lock("bob", now + chrono::Duration::seconds(60)))
+                .await
+                .unwrap(),
+            held
+        );
+        assert!(ensure_unlocked(&locks, "item-1", Some(&claims("alice")))
+            .await
+            .is_ok());
+        assert!(matches!(
+            ensure_unlocked(&locks, "item-1", Some(&claims("bob"))).await,
+            Err(AppError::Locked(_))
+        ));
+        assert!(ensure_unlocked(&locks, "item-1", None).await.is_err());
+
+        clock.advance(Duration::from_secs(61));
+        assert_eq!(locks.get("item-1").await.unwrap(), None);
+        assert!(ensure_unlocked(&locks, "item-1", Some(&claims("bob")))
+            .await
+            .is_ok());
+        let taken = locks
+            .acquire(lock("bob", clock.now() + chrono::Duration::seconds(60)))
+            .await
+            .unwrap();
+        assert_eq!(taken.owner, "bob");
+    }
+}
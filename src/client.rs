@@ -0,0 +1,159 @@
+use futures_util::{stream, Stream, TryStreamExt};
+use reqwest::{RequestBuilder, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::{models::Item, pagination::DEFAULT_PAGE_LIMIT};
+
+/// Why a request to the API failed
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+    #[error("{status}: {message}")]
+    Api { status: StatusCode, message: String },
+}
+
+/// Typed client for the items API
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    token: Option<String>,
+}
+
+/// Which items to list
+#[derive(Debug, Clone)]
+pub struct ListItems {
+    /// Items fetched per request
+    pub limit: usize,
+    /// Every owner's items (administrators only)
+    pub all: bool,
+    /// OData `$filter`
+    pub filter: Option<String>,
+    /// Ask for `total`, which the server has to count
+    pub include_total: bool,
+}
+
+impl Default for ListItems {
+    fn default() -> Self {
+        Self {
+            limit: DEFAULT_PAGE_LIMIT,
+            all: false,
+            filter: None,
+            include_total: true,
+        }
+    }
+}
+
+/// A page of items
+#[derive(Debug, Clone, Deserialize)]
+pub struct ItemPage {
+    pub items: Vec<Item>,
+    /// Absent when not asked for
+    #[serde(default)]
+    pub total: Option<usize>,
+    /// Pass to [`Client::list_items_page`] for the next page
+    pub next_cursor: Option<String>,
+}
+
+/// Where an item stream is in the list
+enum Position {
+    Start,
+    After(String),
+    End,
+}
+
+impl Client {
+    /// Client for the API served at `base_url`, such as `http://localhost:3000`
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            token: None,
+        }
+    }
+
+    /// Send `token` as a bearer token
+    #[must_use]
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// One page of items, starting just past `cursor` when set
+    pub async fn list_items_page(
+        &self,
+        list: &ListItems,
+        cursor: Option<&str>,
+    ) -> Result<ItemPage, ClientError> {
+        let mut query = vec![("limit", list.limit.to_string())];
+        if list.all {
+            query.push(("all", "true".to_string()));
+        }
+        if let Some(filter) = &list.filter {
+            query.push(("$filter", filter.clone()));
+        }
+        if !list.include_total {
+            query.push(("include_total", "false".to_string()));
+        }
+        if let Some(cursor) = cursor {
+            query.push(("cursor", cursor.to_string()));
+        }
+        let request = self
+            .http
+            .get(format!("{}/api/v1/items", self.base_url))
+            .query(&query);
+        self.send(request).await
+    }
+
+    /// Every item `list` matches, fetching pages as the stream is read
+    ///
+    /// Pages are followed by cursor, so items created or deleted while
+    /// streaming do not shift the rest of the list. Totals are not asked for.
+    /// The stream ends after the first error.
+    pub fn list_items(
+        &self,
+        list: ListItems,
+    ) -> impl Stream<Item = Result<Item, ClientError>> + Send + '_ {
+        let list = ListItems {
+            include_total: false,
+            ..list
+        };
+        stream::try_unfold(Position::Start, move |position| {
+            let list = list.clone();
+            async move {
+                let cursor = match position {
+                    Position::Start => None,
+                    Position::After(cursor) => Some(cursor),
+                    Position::End => return Ok::<_, ClientError>(None),
+                };
+                let page = self.list_items_page(&list, cursor.as_deref()).await?;
+                let next = page.next_cursor.map_or(Position::End, Position::After);
+                Ok(Some((stream::iter(page.items.into_iter().map(Ok)), next)))
+            }
+        })
+        .try_flatten()
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, ClientError> {
+        let request = match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        };
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response.json().await?);
+        }
+
+        #[derive(Deserialize)]
+        struct ErrorBody {
+            message: String,
+        }
+        let message = match response.json::<ErrorBody>().await {
+            Ok(body) => body.message,
+            Err(_) => status.canonical_reason().unwrap_or_default().to_string(),
+        };
+        Err(ClientError::Api { status, message })
+    }
+}
@@ -0,0 +1,107 @@
+use chrono::{DateTime, Utc};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Source of the current time
+///
+/// Code that stamps or measures time takes a clock rather than calling
+/// `Utc::now` or `Instant::now`, so tests can control time with a
+/// [`ManualClock`].
+pub trait Clock: Send + Sync {
+    /// Wall-clock time, for timestamps
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Monotonic time, for measuring intervals
+    fn instant(&self) -> Instant;
+}
+
+/// Clock shared between the state and the components using it
+pub type SharedClock = Arc<dyn Clock>;
+
+/// The system's clocks
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// The system clock, as a shared clock
+pub fn system_clock() -> SharedClock {
+    Arc::new(SystemClock)
+}
+
+/// Clock that stands still until advanced, for tests
+///
+/// Both the wall-clock and the monotonic time move together, only when
+/// [`advance`](ManualClock::advance) is called.
+#[derive(Debug)]
+pub struct ManualClock {
+    start: Instant,
+    /// Wall-clock time and time elapsed since `start`
+    state: Mutex<(DateTime<Utc>, Duration)>,
+}
+
+impl ManualClock {
+    /// A clock frozen at `now`
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            start: Instant::now(),
+            state: Mutex::new((now, Duration::ZERO)),
+        }
+    }
+
+    /// Move the clock forward
+    pub fn advance(&self, by: Duration) {
+        let mut state = self.lock();
+        state.0 += chrono::Duration::from_std(by).unwrap_or(chrono::Duration::MAX);
+        state.1 += by;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, (DateTime<Utc>, Duration)> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new(Utc::now())
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.lock().0
+    }
+
+    fn instant(&self) -> Instant {
+        self.start + self.lock().1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manual_clock_moves_only_when_advanced() {
+        let start = DateTime::parse_from_rfc3339("2026-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let clock = ManualClock::new(start);
+        let instant = clock.instant();
+        assert_eq!(clock.now(), start);
+
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(clock.now(), start + chrono::Duration::seconds(90));
+        assert_eq!(clock.instant() - instant, Duration::from_secs(90));
+    }
+}
@@ -0,0 +1,124 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{models::Item, tenancy::current_tenant};
+
+/// A folder in the tree items are filed in
+///
+/// Named `ItemCollection` in the API schema, as `Collection` already
+/// describes the collections of the API index.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ItemCollection {
+    #[schema(example = "5e6f7a8b-9c0d-4e1f-a2b3-c4d5e6f7a8b9")]
+    pub id: String,
+    #[schema(example = "Invoices")]
+    pub name: String,
+    /// Collection this one is nested in; absent for root collections
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+    /// IDs of the collection's ancestors, root first
+    pub path: Vec<String>,
+    /// Subject of the principal that created the collection
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "user-123")]
+    pub owner: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+}
+
+impl ItemCollection {
+    /// A new collection of the current tenant, under `parent` or at the root
+    pub fn new(
+        name: String,
+        parent: Option<&ItemCollection>,
+        owner: Option<String>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            name,
+            parent_id: parent.map(|parent| parent.id.clone()),
+            path: parent.map(ItemCollection::lineage).unwrap_or_default(),
+            owner,
+            created_at: now,
+            updated_at: now,
+            tenant_id: current_tenant(),
+        }
+    }
+
+    /// IDs of the collection's ancestors and the collection itself, root first
+    pub fn lineage(&self) -> Vec<String> {
+        let mut lineage = self.path.clone();
+        lineage.push(self.id.clone());
+        lineage
+    }
+
+    /// Whether the collection is `id` or nested anywhere below it
+    pub fn is_within(&self, id: &str) -> bool {
+        self.id == id || self.path.iter().any(|ancestor| ancestor == id)
+    }
+}
+
+/// Request to create a collection
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct CreateCollectionRequest {
+    /// Name of the collection, unique among its siblings (1-100 characters)
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "Name must be between 1 and 100 characters"
+    ))]
+    #[schema(example = "Invoices", min_length = 1, max_length = 100)]
+    pub name: String,
+    /// Collection to nest the new one in; omitted for a root collection
+    pub parent_id: Option<String>,
+}
+
+/// Request to move a collection, with everything below it
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct MoveCollectionRequest {
+    /// New parent collection; `null` moves the collection to the root
+    pub parent_id: Option<String>,
+}
+
+/// Request to file an item in a collection
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct FileItemRequest {
+    /// Collection to file the item in; `null` takes it out of its collection
+    pub collection_id: Option<String>,
+}
+
+/// The collection an item is filed in
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CollectionPlacement {
+    pub item_id: String,
+    /// Absent when the item is not filed in any collection
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collection_id: Option<String>,
+}
+
+/// A page of the collections below a collection
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CollectionList {
+    pub collections: Vec<ItemCollection>,
+    /// Collections below the collection, at any depth
+    pub total: usize,
+    pub limit: usize,
+    pub offset: usize,
+}
+
+/// A page of the items filed in a collection
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CollectionItems {
+    pub collection: ItemCollection,
+    pub items: Vec<Item>,
+    /// Items filed in the collection, or anywhere below it when listed recursively
+    pub total: usize,
+    pub limit: usize,
+    pub offset: usize,
+}
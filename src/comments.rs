@@ -0,0 +1,115 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    events::{
+        cloudevent::{DATA_CONTENT_TYPE, SPEC_VERSION},
+        CloudEvent,
+    },
+    tenancy::current_tenant,
+};
+
+/// CloudEvents `source` of comment events
+pub const COMMENT_EVENT_SOURCE: &str = "/ferrous/comments";
+
+/// A remark someone made on an item
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Comment {
+    #[schema(example = "9a8b7c6d-5e4f-4a3b-2c1d-0e9f8a7b6c5d")]
+    pub id: String,
+    pub item_id: String,
+    /// Subject of the principal that wrote the comment
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "user-123")]
+    pub author: Option<String>,
+    #[schema(example = "Looks good to me.")]
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Set once the comment is deleted; deleted comments are only listed on request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+}
+
+impl Comment {
+    /// A new comment on `item_id` in the current tenant
+    pub fn new(item_id: &str, author: Option<String>, body: String, now: DateTime<Utc>) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            item_id: item_id.to_string(),
+            author,
+            body,
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+            tenant_id: current_tenant(),
+        }
+    }
+}
+
+/// Request to comment on an item, or to edit a comment
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct CommentRequest {
+    /// Text of the comment (1-10000 characters)
+    #[validate(length(
+        min = 1,
+        max = 10000,
+        message = "Body must be between 1 and 10000 characters"
+    ))]
+    #[schema(example = "Looks good to me.", min_length = 1, max_length = 10000)]
+    pub body: String,
+}
+
+/// A page of an item's comments, oldest first
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CommentList {
+    pub comments: Vec<Comment>,
+    /// Comments matching the query
+    pub total: usize,
+    pub limit: usize,
+    pub offset: usize,
+}
+
+/// Kind of change made to a comment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentEventType {
+    Created,
+    Updated,
+    Deleted,
+}
+
+impl CommentEventType {
+    /// CloudEvents `type` attribute for this kind of change
+    pub fn cloud_event_type(self) -> &'static str {
+        match self {
+            Self::Created => "com.ferrous.comment.created",
+            Self::Updated => "com.ferrous.comment.updated",
+            Self::Deleted => "com.ferrous.comment.deleted",
+        }
+    }
+}
+
+/// Comment change event; `data` is the comment after the change
+pub type CommentEvent = CloudEvent<Comment>;
+
+impl CommentEvent {
+    /// Event announcing the `event_type` change that left `comment` as it is
+    pub fn new(event_type: CommentEventType, comment: &Comment) -> Self {
+        Self {
+            specversion: SPEC_VERSION.to_string(),
+            id: Uuid::new_v4().to_string(),
+            source: COMMENT_EVENT_SOURCE.to_string(),
+            event_type: event_type.cloud_event_type().to_string(),
+            subject: Some(comment.id.clone()),
+            time: comment.deleted_at.unwrap_or(comment.updated_at),
+            datacontenttype: DATA_CONTENT_TYPE.to_string(),
+            sequence: None,
+            data: comment.clone(),
+        }
+    }
+}
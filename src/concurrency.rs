@@ -0,0 +1,399 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tracing::debug;
+
+use crate::{
+    analytics::{AnalyticsBucket, AnalyticsRange},
+    config::ConcurrencyConfig,
+    db::{DatabaseError, DatabaseResult, EventFilter, ItemFilter, ItemRepository},
+    events::OutboxEvent,
+    metrics::{DATABASE_CALLS_IN_FLIGHT, DATABASE_CALLS_SHED, DATABASE_CONCURRENCY_LIMIT},
+    models::{CreateItemRequest, Item, ItemField, UpdateItemRequest},
+    pagination::Page,
+};
+
+/// Share of the limit kept when a call signals overload
+const BACKOFF: f64 = 0.9;
+
+/// Limit on concurrent calls to one backend, tuned by additive increase and
+/// multiplicative decrease (AIMD)
+///
+/// Calls answered within the latency target raise the limit by one per
+/// limit's worth of calls; a slower call, a connection failure, or a call
+/// abandoned before it finished lowers it by a tenth. Calls beyond the limit
+/// wait for a slot, and fail with [`DatabaseError::Overloaded`] if none frees
+/// up within the queue timeout, so a struggling backend sheds load instead of
+/// collecting unbounded concurrent calls.
+pub struct AdaptiveLimiter {
+    backend: String,
+    semaphore: Semaphore,
+    state: Mutex<LimitState>,
+    min_limit: usize,
+    max_limit: usize,
+    latency_target: Duration,
+    queue_timeout: Duration,
+}
+
+struct LimitState {
+    /// The tuned limit; slots follow its integer part
+    limit: f64,
+    /// Slots in existence, taken or free
+    slots: usize,
+}
+
+impl AdaptiveLimiter {
+    /// Limiter for `backend`, or `None` when limiting is disabled
+    pub fn from_config(config: &ConcurrencyConfig, backend: &str) -> Option<Arc<Self>> {
+        config.enabled.then(|| Arc::new(Self::new(config, backend)))
+    }
+
+    pub fn new(config: &ConcurrencyConfig, backend: &str) -> Self {
+        let min_limit = config.min_limit.max(1);
+        let max_limit = config.max_limit.max(min_limit);
+        let initial = config.initial_limit.clamp(min_limit, max_limit);
+        DATABASE_CONCURRENCY_LIMIT
+            .with_label_values(&[backend])
+            .set(i64::try_from(initial).unwrap_or(i64::MAX));
+        Self {
+            backend: backend.to_string(),
+            semaphore: Semaphore::new(initial),
+            state: Mutex::new(LimitState {
+                limit: initial as f64,
+                slots: initial,
+            }),
+            min_limit,
+            max_limit,
+            latency_target: Duration::from_millis(config.latency_target_ms),
+            queue_timeout: Duration::from_millis(config.queue_timeout_ms),
+        }
+    }
+
+    /// Calls allowed in flight at once
+    pub fn limit(&self) -> DatabaseResult<usize> {
+        let state = self.state.lock().map_err(|_| DatabaseError::LockError)?;
+        Ok(state.slots)
+    }
+
+    /// Wait for a slot, giving up after the queue timeout
+    pub async fn acquire(&self) -> DatabaseResult<Permit<'_>> {
+        let permit = match tokio::time::timeout(self.queue_timeout, self.semaphore.acquire()).await
+        {
+            Ok(Ok(permit)) => permit,
+            // The semaphore is never closed
+            Ok(Err(_)) | Err(_) => {
+                DATABASE_CALLS_SHED
+                    .with_label_values(&[&self.backend])
+                    .inc();
+                return Err(DatabaseError::Overloaded);
+            }
+        };
+        DATABASE_CALLS_IN_FLIGHT
+            .with_label_values(&[&self.backend])
+            .inc();
+        Ok(Permit {
+            limiter: self,
+            permit: Some(permit),
+            started: Instant::now(),
+            overloaded: None,
+        })
+    }
+
+    fn release(&self, permit: SemaphorePermit<'_>, overloaded: bool) {
+        DATABASE_CALLS_IN_FLIGHT
+            .with_label_values(&[&self.backend])
+            .dec();
+        // Released from `Drop`, so the limit is left as it is when its state
+        // cannot be locked; the slot goes back with the dropped permit
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        state.limit = if overloaded {
+            (state.limit * BACKOFF).max(self.min_limit as f64)
+        } else {
+            (state.limit + 1.0 / state.limit).min(self.max_limit as f64)
+        };
+
+        // Slots are added at once but removed one per finished call, as
+        // taken slots cannot be reclaimed early
+        let target = state.limit as usize;
+        if target > state.slots {
+            self.semaphore.add_permits(target - state.slots);
+            state.slots = target;
+        } else if target < state.slots {
+            permit.forget();
+            state.slots -= 1;
+            debug!(backend = %self.backend, limit = state.slots, "Lowered concurrency limit");
+        }
+        DATABASE_CONCURRENCY_LIMIT
+            .with_label_values(&[&self.backend])
+            .set(i64::try_from(state.slots).unwrap_or(i64::MAX));
+    }
+}
+
+/// A slot taken from an [`AdaptiveLimiter`]; dropping it without
+/// [`Permit::finish`] counts the call as abandoned
+pub struct Permit<'a> {
+    limiter: &'a AdaptiveLimiter,
+    permit: Option<SemaphorePermit<'a>>,
+    started: Instant,
+    overloaded: Option<bool>,
+}
+
+impl Permit<'_> {
+    /// Release the slot, its call having failed with `error` or succeeded
+    pub fn finish(mut self, error: Option<&DatabaseError>) {
+        self.overloaded = Some(error.is_some_and(signals_overload));
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        let overloaded =
+            self.overloaded.unwrap_or(true) || self.started.elapsed() > self.limiter.latency_target;
+        if let Some(permit) = self.permit.take() {
+            self.limiter.release(permit, overloaded);
+        }
+    }
+}
+
+/// Whether a failure suggests the backend is struggling, rather than that
+/// the call was wrong
+fn signals_overload(error: &DatabaseError) -> bool {
+    matches!(
+        error,
+        DatabaseError::ConnectionError(_)
+            | DatabaseError::DeadlineExceeded
+            | DatabaseError::Overloaded
+    )
+}
+
+/// Passes calls to `inner` through an [`AdaptiveLimiter`]
+///
+/// Health checks bypass the limiter, so probes report the backend itself
+/// rather than the queue in front of it.
+pub struct LimitedRepository {
+    inner: Arc<dyn ItemRepository>,
+    limiter: Arc<AdaptiveLimiter>,
+}
+
+impl LimitedRepository {
+    pub fn new(inner: Arc<dyn ItemRepository>, limiter: Arc<AdaptiveLimiter>) -> Self {
+        Self { inner, limiter }
+    }
+
+    async fn call<T>(&self, call: impl Future<Output = DatabaseResult<T>>) -> DatabaseResult<T> {
+        let permit = self.limiter.acquire().await?;
+        let result = call.await;
+        permit.finish(result.as_ref().err());
+        result
+    }
+}
+
+#[async_trait]
+impl ItemRepository for LimitedRepository {
+    async fn create(
+        &self,
+        request: CreateItemRequest,
+        owner_id: Option<String>,
+    ) -> DatabaseResult<Item> {
+        self.call(self.inner.create(request, owner_id)).await
+    }
+
+    async fn get(&self, id: &str) -> DatabaseResult<Item> {
+        self.call(self.inner.get(id)).await
+    }
+
+    async fn get_by_slug(&self, slug: &str) -> DatabaseResult<Item> {
+        self.call(self.inner.get_by_slug(slug)).await
+    }
+
+    async fn get_by_external_id(
+        &self,
+        source: Option<&str>,
+        external_id: &str,
+    ) -> DatabaseResult<Item> {
+        self.call(self.inner.get_by_external_id(source, external_id))
+            .await
+    }
+
+    async fn update(
+        &self,
+        id: &str,
+        request: UpdateItemRequest,
+        expected_version: Option<u64>,
+    ) -> DatabaseResult<Item> {
+        self.call(self.inner.update(id, request, expected_version))
+            .await
+    }
+
+    async fn changed_since(&self, id: &str, version: u64) -> DatabaseResult<Vec<ItemField>> {
+        self.call(self.inner.changed_since(id, version)).await
+    }
+
+    async fn delete(&self, id: &str) -> DatabaseResult<()> {
+        self.call(self.inner.delete(id)).await
+    }
+
+    async fn list(&self, filter: &ItemFilter, page: &Page) -> DatabaseResult<Vec<Arc<Item>>> {
+        self.call(self.inner.list(filter, page)).await
+    }
+
+    async fn count(&self, filter: &ItemFilter) -> DatabaseResult<usize> {
+        self.call(self.inner.count(filter)).await
+    }
+
+    async fn list_with_total(
+        &self,
+        filter: &ItemFilter,
+        page: &Page,
+    ) -> DatabaseResult<(Vec<Arc<Item>>, usize)> {
+        self.call(self.inner.list_with_total(filter, page)).await
+    }
+
+    async fn health_check(&self) -> DatabaseResult<()> {
+        self.inner.health_check().await
+    }
+
+    async fn reassign_owner(
+        &self,
+        owner_id: &str,
+        replacement: &str,
+    ) -> DatabaseResult<(usize, usize)> {
+        self.call(self.inner.reassign_owner(owner_id, replacement))
+            .await
+    }
+
+    async fn restore(&self, item: Item) -> DatabaseResult<Item> {
+        self.call(self.inner.restore(item)).await
+    }
+
+    async fn provision_tenant(&self, tenant: &str) -> DatabaseResult<()> {
+        self.call(self.inner.provision_tenant(tenant)).await
+    }
+
+    async fn drop_tenant(&self, tenant: &str) -> DatabaseResult<Vec<String>> {
+        self.call(self.inner.drop_tenant(tenant)).await
+    }
+
+    async fn pending_events(&self, limit: usize) -> DatabaseResult<Vec<OutboxEvent>> {
+        self.call(self.inner.pending_events(limit)).await
+    }
+
+    async fn event_history(
+        &self,
+        filter: &EventFilter,
+        after: u64,
+        limit: usize,
+    ) -> DatabaseResult<Vec<OutboxEvent>> {
+        self.call(self.inner.event_history(filter, after, limit))
+            .await
+    }
+
+    async fn pending_event_count(&self) -> DatabaseResult<usize> {
+        self.call(self.inner.pending_event_count()).await
+    }
+
+    async fn mark_events_published(&self, sequences: &[u64]) -> DatabaseResult<()> {
+        self.call(self.inner.mark_events_published(sequences)).await
+    }
+
+    async fn mark_event_failed(&self, sequence: u64, error: &str) -> DatabaseResult<()> {
+        self.call(self.inner.mark_event_failed(sequence, error))
+            .await
+    }
+
+    async fn purge_published_events(
+        &self,
+        cutoff: DateTime<Utc>,
+        dry_run: bool,
+    ) -> DatabaseResult<usize> {
+        self.call(self.inner.purge_published_events(cutoff, dry_run))
+            .await
+    }
+
+    async fn event_histogram(
+        &self,
+        filter: &ItemFilter,
+        range: &AnalyticsRange,
+    ) -> DatabaseResult<Vec<AnalyticsBucket>> {
+        self.call(self.inner.event_histogram(filter, range)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::InMemoryRepository;
+
+    fn config(initial_limit: usize) -> ConcurrencyConfig {
+        ConcurrencyConfig {
+            enabled: true,
+            initial_limit,
+            min_limit: 2,
+            max_limit: 8,
+            latency_target_ms: 1000,
+            queue_timeout_ms: 20,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_limit_backs_off_on_overload_and_recovers() {
+        let limiter = AdaptiveLimiter::new(&config(8), "test-aimd");
+        let overload = DatabaseError::ConnectionError("refused".to_string());
+
+        limiter.acquire().await.unwrap().finish(Some(&overload));
+        assert_eq!(limiter.limit().unwrap(), 7);
+        // Abandoned calls count as overload too
+        drop(limiter.acquire().await.unwrap());
+        assert_eq!(limiter.limit().unwrap(), 6);
+        for _ in 0..10 {
+            limiter.acquire().await.unwrap().finish(Some(&overload));
+        }
+        assert_eq!(limiter.limit().unwrap(), 2);
+
+        // Wrong calls are not the backend's fault
+        for _ in 0..20 {
+            limiter
+                .acquire()
+                .await
+                .unwrap()
+                .finish(Some(&DatabaseError::NotFound));
+        }
+        assert!(limiter.limit().unwrap() > 4);
+        for _ in 0..200 {
+            limiter.acquire().await.unwrap().finish(None);
+        }
+        assert_eq!(limiter.limit().unwrap(), 8);
+    }
+
+    #[tokio::test]
+    async fn test_calls_beyond_the_limit_are_shed() {
+        let limiter = Arc::new(AdaptiveLimiter::new(&config(2), "test-shed"));
+        let repo = LimitedRepository::new(Arc::new(InMemoryRepository::new()), limiter.clone());
+        let request = || CreateItemRequest {
+            name: "Limited".to_string(),
+            description: None,
+            metadata: None,
+            custom_fields: Default::default(),
+            status: Default::default(),
+            publish_at: None,
+            external_id: None,
+            source: None,
+        };
+
+        let held = (limiter.acquire().await.unwrap(), limiter.acquire().await.unwrap());
+        assert!(matches!(repo.create(request(), None).await, Err(DatabaseError::Overloaded)));
+        // Health checks skip the queue
+        repo.health_check().await.unwrap();
+
+        drop(held);
+        let item = repo.create(request(), None).await.unwrap();
+        assert_eq!(repo.get(&item.id).await.unwrap().name, "Limited");
+    }
+}
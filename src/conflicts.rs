@@ -0,0 +1,278 @@
+use axum::{
+    http::{
+        header::{ETAG, IF_MATCH},
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+#[allow(unused_imports)] // Used in #[schema(example = json!({...}))] attributes
+use serde_json::json;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    error::{AppError, AppResult, ErrorCode},
+    models::{Item, ItemField, UpdateItemRequest},
+    slug::slugify,
+};
+
+/// `ETag` of an item: its quoted version
+pub fn etag(item: &Item) -> HeaderValue {
+    HeaderValue::from_str(&format!("\"{}\"", item.version)).expect("digits are a valid header")
+}
+
+/// Item version an update is based on, from `If-Match`
+///
+/// Without the header, or with `*`, the update applies to whatever version is
+/// current (last write wins).
+pub fn if_match(headers: &HeaderMap) -> AppResult<Option<u64>> {
+    let Some(value) = headers.get(IF_MATCH) else {
+        return Ok(None);
+    };
+    let invalid =
+        || AppError::BadRequest("If-Match must be an item ETag such as \"3\"".to_string());
+    let value = value.to_str().map_err(|_| invalid())?.trim();
+    if value == "*" {
+        return Ok(None);
+    }
+    let tag = value.strip_prefix("W/").unwrap_or(value);
+    tag.strip_prefix('"')
+        .and_then(|tag| tag.strip_suffix('"'))
+        .and_then(|version| version.parse().ok())
+        .map(Some)
+        .ok_or_else(invalid)
+}
+
+/// How an update based on an older version of the item is handled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    /// Answer 409 with both versions
+    #[default]
+    Reject,
+    /// Apply the update to the current version when it only sets fields that
+    /// nobody changed since its base version
+    Fields,
+}
+
+/// Query parameters for updating an item
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct UpdateQuery {
+    /// What to do when `If-Match` names an older version
+    #[serde(default)]
+    #[param(inline)]
+    pub merge: MergeStrategy,
+}
+
+/// A requested field value differing from the current one
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FieldDiff {
+    pub field: ItemField,
+    pub current: Option<String>,
+    pub proposed: Option<String>,
+    /// The field was also changed after the version the update was based on
+    pub conflicting: bool,
+}
+
+/// Response to an update based on an older version of the item
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(example = json!({
+    "error": "CONFLICT",
+    "message": "Item has been modified since version 3",
+    "base_version": 3,
+    "current": {
+        "id": "550e8400-e29b-41d4-a716-446655440000",
+        "name": "Renamed elsewhere",
+        "slug": "example-item",
+        "description": "This is an example item",
+        "created_at": "2024-01-01T00:00:00Z",
+        "updated_at": "2024-01-01T00:05:00Z",
+        "version": 4
+    },
+    "proposed": {
+        "id": "550e8400-e29b-41d4-a716-446655440000",
+        "name": "Renamed here",
+        "slug": "example-item",
+        "description": "This is an example item",
+        "created_at": "2024-01-01T00:00:00Z",
+        "updated_at": "2024-01-01T00:05:00Z",
+        "version": 4
+    },
+    "diff": [{
+        "field": "name",
+        "current": "Renamed elsewhere",
+        "proposed": "Renamed here",
+        "conflicting": true
+    }],
+    "timestamp": "2024-01-01T00:06:00Z"
+}))]
+pub struct UpdateConflict {
+    pub error: ErrorCode,
+    pub message: String,
+    /// Version named by `If-Match`
+    pub base_version: u64,
+    pub current: Item,
+    /// The current item with the requested changes applied
+    pub proposed: Item,
+    /// Requested changes, by field
+    pub diff: Vec<FieldDiff>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl UpdateConflict {
+    /// Compare `request`, based on `base_version`, with the current item;
+    /// `changed` are the fields changed after `base_version`
+    pub fn detect(
+        base_version: u64,
+        current: &Item,
+        request: &UpdateItemRequest,
+        changed: &[ItemField],
+    ) -> Self {
+        let mut proposed = current.clone();
+        if let Some(name) = &request.name {
+            proposed.name.clone_from(name);
+        }
+        if request.regenerate_slug {
+            proposed.slug = slugify(&proposed.name);
+        }
+        if request.description.is_some() {
+            proposed.description.clone_from(&request.description);
+        }
+        if request.metadata.is_some() {
+            proposed.metadata.clone_from(&request.metadata);
+        }
+        proposed.set_custom_fields(&request.custom_fields);
+
+        let diff = request
+            .fields()
+            .into_iter()
+            .filter_map(|field| {
+                let (current, proposed) = match field {
+                    ItemField::Name
+                        if (&current.name, &current.slug) == (&proposed.name, &proposed.slug) =>
+                    {
+                        return None
+                    }
+                    ItemField::Name => (Some(current.name.clone()), Some(proposed.name.clone())),
+                    ItemField::Description if current.description == proposed.description => {
+                        return None
+                    }
+                    ItemField::Description => {
+                        (current.description.clone(), proposed.description.clone())
+                    }
+                    ItemField::Metadata if current.metadata == proposed.metadata => return None,
+                    // Compared as a whole, so shown as JSON text
+                    ItemField::Metadata => (
+                        current.metadata.as_ref().map(ToString::to_string),
+                        proposed.metadata.as_ref().map(ToString::to_string),
+                    ),
+                    ItemField::CustomFields if current.custom_fields == proposed.custom_fields => {
+                        return None
+                    }
+                    ItemField::CustomFields => (
+                        serde_json::to_string(&current.custom_fields).ok(),
+                        serde_json::to_string(&proposed.custom_fields).ok(),
+                    ),
+                };
+                Some(FieldDiff {
+                    field,
+                    current,
+                    proposed,
+                    conflicting: changed.contains(&field),
+                })
+            })
+            .collect();
+
+        Self {
+            error: ErrorCode::Conflict,
+            message: format!("Item has been modified since version {base_version}"),
+            base_version,
+            current: current.clone(),
+            proposed,
+            diff,
+            timestamp: Utc::now(),
+        }
+    }
+
+    /// Whether the requested changes only touch fields nobody else changed
+    pub fn mergeable(&self) -> bool {
+        !self.diff.iter().any(|diff| diff.conflicting)
+    }
+}
+
+impl IntoResponse for UpdateConflict {
+    fn into_response(self) -> Response {
+        let etag = etag(&self.current);
+        (StatusCode::CONFLICT, [(ETAG, etag)], Json(self)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(name: &str, description: Option<&str>) -> Item {
+        Item {
+            id: "item-1".to_string(),
+            name: name.to_string(),
+            slug: slugify(name),
+            external_id: None,
+            source: None,
+            description: description.map(str::to_string),
+            metadata: None,
+            custom_fields: Default::default(),
+            owner_id: None,
+            tenant_id: None,
+            status: Default::default(),
+            publish_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            version: 4,
+        }
+    }
+
+    fn headers(if_match: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(IF_MATCH, HeaderValue::from_str(if_match).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_if_match() {
+        assert_eq!(if_match(&HeaderMap::new()).unwrap(), None);
+        assert_eq!(if_match(&headers("*")).unwrap(), None);
+        assert_eq!(if_match(&headers("\"3\"")).unwrap(), Some(3));
+        assert_eq!(if_match(&headers("W/\"3\"")).unwrap(), Some(3));
+        assert!(if_match(&headers("3")).is_err());
+        assert!(if_match(&headers("\"abc\"")).is_err());
+    }
+
+    #[test]
+    fn test_detect_marks_fields_changed_on_both_sides() {
+        let current = item("Renamed elsewhere", Some("Same"));
+        let request = UpdateItemRequest {
+            name: Some("Renamed here".to_string()),
+            description: Some("Same".to_string()),
+            metadata: None,
+            custom_fields: Default::default(),
+            regenerate_slug: false,
+            status: Default::default(),
+            publish_at: None,
+        };
+
+        let conflict = UpdateConflict::detect(3, &current, &request, &[ItemField::Name]);
+        // The description already has the requested value
+        assert_eq!(conflict.diff.len(), 1);
+        assert_eq!(conflict.diff[0].field, ItemField::Name);
+        assert!(conflict.diff[0].conflicting);
+        assert!(!conflict.mergeable());
+        assert_eq!(conflict.proposed.name, "Renamed here");
+        assert_eq!(conflict.proposed.slug, current.slug);
+
+        let conflict = UpdateConflict::detect(3, &current, &request, &[ItemField::Description]);
+        assert!(!conflict.diff[0].conflicting);
+        assert!(conflict.mergeable());
+    }
+}
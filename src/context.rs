@@ -0,0 +1,154 @@
+use std::{
+    fmt,
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    clock::{system_clock, SharedClock},
+    http_client::current_request_id,
+    tenancy::current_tenant,
+};
+
+tokio::task_local! {
+    /// Caller and deadline of the request being handled, set by the request
+    /// context middleware
+    static CURRENT_CONTEXT: RequestContext;
+}
+
+/// What code running on behalf of a request knows about it
+///
+/// Repositories and other code far from the handlers read it with
+/// [`RequestContext::current`] rather than taking it as a parameter, like the
+/// tenant and request ID it includes. Outside a request every field is empty,
+/// except those set by enclosing tenant or request ID scopes.
+#[derive(Clone)]
+pub struct RequestContext {
+    pub request_id: Option<String>,
+    pub tenant: Option<String>,
+    /// Subject of the authenticated caller
+    pub principal: Option<String>,
+    /// When the caller stops waiting for the response, if known, by `clock`
+    pub deadline: Option<Instant>,
+    pub(crate) clock: SharedClock,
+}
+
+impl Default for RequestContext {
+    fn default() -> Self {
+        Self {
+            request_id: None,
+            tenant: None,
+            principal: None,
+            deadline: None,
+            clock: system_clock(),
+        }
+    }
+}
+
+impl fmt::Debug for RequestContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestContext")
+            .field("request_id", &self.request_id)
+            .field("tenant", &self.tenant)
+            .field("principal", &self.principal)
+            .field("deadline", &self.deadline)
+            .finish_non_exhaustive()
+    }
+}
+
+// The clock only measures the deadline, so contexts are compared without it
+impl PartialEq for RequestContext {
+    fn eq(&self, other: &Self) -> bool {
+        self.request_id == other.request_id
+            && self.tenant == other.tenant
+            && self.principal == other.principal
+            && self.deadline == other.deadline
+    }
+}
+
+impl Eq for RequestContext {}
+
+impl RequestContext {
+    /// Measure the deadline with `clock` instead of the system clock
+    #[must_use]
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Context of the current task
+    pub fn current() -> Self {
+        let scoped = CURRENT_CONTEXT.try_with(Clone::clone).unwrap_or_default();
+        Self {
+            request_id: scoped.request_id.or_else(current_request_id),
+            // Background work re-enters tenant scopes without a request context
+            tenant: current_tenant(),
+            ..scoped
+        }
+    }
+
+    /// Time left before the deadline, zero once it has passed
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(self.clock.instant()))
+    }
+
+    /// Whether the caller has already given up
+    pub fn is_expired(&self) -> bool {
+        self.remaining() == Some(Duration::ZERO)
+    }
+
+    /// Run `future` with this as the current context
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        CURRENT_CONTEXT.scope(self, future).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        clock::{Clock, ManualClock},
+        http_client::with_request_id,
+        tenancy::with_tenant,
+    };
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_current_combines_request_and_tenant_scopes() {
+        assert_eq!(RequestContext::current(), RequestContext::default());
+
+        let context = RequestContext {
+            principal: Some("alice".to_string()),
+            ..Default::default()
+        };
+        let current = with_request_id(
+            "req-1".to_string(),
+            with_tenant("acme".to_string(), context.scope(async { RequestContext::current() })),
+        )
+        .await;
+        assert_eq!(current.request_id.as_deref(), Some("req-1"));
+        assert_eq!(current.tenant.as_deref(), Some("acme"));
+        assert_eq!(current.principal.as_deref(), Some("alice"));
+        assert_eq!(current.deadline, None);
+    }
+
+    #[test]
+    fn test_remaining_time_until_deadline() {
+        let clock = Arc::new(ManualClock::default());
+        let context = RequestContext {
+            deadline: Some(clock.instant() + Duration::from_secs(60)),
+            ..Default::default()
+        }
+        .with_clock(clock.clone());
+        assert_eq!(context.remaining(), Some(Duration::from_secs(60)));
+        assert!(!context.is_expired());
+
+        clock.advance(Duration::from_secs(45));
+        assert_eq!(context.remaining(), Some(Duration::from_secs(15)));
+
+        clock.advance(Duration::from_secs(30));
+        assert!(context.is_expired());
+        assert_eq!(RequestContext::default().remaining(), None);
+    }
+}
@@ -0,0 +1,396 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::RwLock,
+};
+use utoipa::ToSchema;
+
+use crate::{
+    db::DatabaseError,
+    error::AppError,
+    query::{Condition, FilterField},
+    tenancy::current_tenant,
+    views::is_valid_view_name,
+};
+
+/// Most custom fields a tenant may define
+pub const MAX_CUSTOM_FIELDS: usize = 100;
+
+/// Kind of value a custom field holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CustomFieldType {
+    String,
+    Number,
+    Bool,
+    /// A calendar date such as `2024-01-15`
+    Date,
+    /// One of the field's `values`
+    Enum,
+}
+
+/// A typed field a tenant defines for its items
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[schema(example = json!({
+    "name": "priority",
+    "type": "enum",
+    "values": ["low", "high"],
+    "required": true,
+    "description": "How urgent the item is",
+    "created_at": "2024-01-01T00:00:00Z"
+}))]
+pub struct CustomFieldDefinition {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub field_type: CustomFieldType,
+    /// Values allowed for `enum` fields
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<String>,
+    /// Whether every new item must set the field
+    pub required: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Request to define a custom field, or replace its definition
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "type": "enum",
+    "values": ["low", "high"],
+    "required": true
+}))]
+pub struct DefineCustomFieldRequest {
+    #[serde(rename = "type")]
+    pub field_type: CustomFieldType,
+    /// Values allowed for `enum` fields
+    #[serde(default)]
+    pub values: Vec<String>,
+    #[serde(default)]
+    pub required: bool,
+    pub description: Option<String>,
+}
+
+impl CustomFieldDefinition {
+    /// Why `value` cannot be stored in the field, if it cannot
+    fn check(&self, value: &Value) -> Option<String> {
+        let valid = match (self.field_type, value) {
+            (CustomFieldType::String, Value::String(_))
+            | (CustomFieldType::Number, Value::Number(_))
+            | (CustomFieldType::Bool, Value::Bool(_)) => true,
+            (CustomFieldType::Date, Value::String(date)) => {
+                NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok()
+            }
+            (CustomFieldType::Enum, Value::String(value)) => self.values.contains(value),
+            _ => false,
+        };
+        if valid {
+            return None;
+        }
+        Some(match self.field_type {
+            CustomFieldType::String => "Must be a string".to_string(),
+            CustomFieldType::Number => "Must be a number".to_string(),
+            CustomFieldType::Bool => "Must be true or false".to_string(),
+            CustomFieldType::Date => "Must be a date such as 2024-01-15".to_string(),
+            CustomFieldType::Enum => format!("Must be one of {}", self.values.join(", ")),
+        })
+    }
+
+    /// JSON Schema of the field's values
+    fn json_schema(&self) -> Value {
+        let mut schema = match self.field_type {
+            CustomFieldType::String => json!({ "type": "string" }),
+            CustomFieldType::Number => json!({ "type": "number" }),
+            CustomFieldType::Bool => json!({ "type": "boolean" }),
+            CustomFieldType::Date => json!({ "type": "string", "format": "date" }),
+            CustomFieldType::Enum => json!({ "type": "string", "enum": self.values }),
+        };
+        if let Some(description) = &self.description {
+            schema["description"] = Value::String(description.clone());
+        }
+        schema
+    }
+}
+
+/// Custom fields defined by each tenant
+///
+/// Items may only set the fields their tenant defines, with values of the
+/// field's type; required fields must be set when an item is created. List
+/// queries can filter on them as `custom_fields/<name>`. Redefining a field
+/// does not change the values items already hold. Without multi-tenancy the
+/// definitions apply to every item.
+#[derive(Default)]
+pub struct CustomFields {
+    /// Keyed by tenant, `None` without multi-tenancy
+    definitions: RwLock<HashMap<Option<String>, BTreeMap<String, CustomFieldDefinition>>>,
+}
+
+impl CustomFields {
+    /// Define `name` for the current tenant, replacing any definition of it
+    pub fn define(
+        &self,
+        name: &str,
+        request: DefineCustomFieldRequest,
+    ) -> Result<CustomFieldDefinition, AppError> {
+        if !is_valid_view_name(name) {
+            return Err(AppError::BadRequest(
+                "Field names use lowercase letters, digits, - and _".to_string(),
+            ));
+        }
+        match (request.field_type, request.values.is_empty()) {
+            (CustomFieldType::Enum, true) => {
+                return Err(AppError::ValidationError(
+                    "values: Enum fields need at least one value".to_string(),
+                ))
+            }
+            (CustomFieldType::Enum, false) => {}
+            (_, false) => {
+                return Err(AppError::ValidationError(
+                    "values: Only enum fields take values".to_string(),
+                ))
+            }
+            (_, true) => {}
+        }
+
+        let mut definitions = self
+            .definitions
+            .write()
+            .map_err(|_| DatabaseError::LockError)?;
+        let fields = definitions.entry(current_tenant()).or_default();
+        if fields.len() >= MAX_CUSTOM_FIELDS && !fields.contains_key(name) {
+            return Err(AppError::Forbidden(format!(
+                "At most {MAX_CUSTOM_FIELDS} custom fields can be defined"
+            )));
+        }
+        let definition = CustomFieldDefinition {
+            name: name.to_string(),
+            field_type: request.field_type,
+            values: request.values,
+            required: request.required,
+            description: request.description,
+            created_at: Utc::now(),
+        };
+        fields.insert(name.to_string(), definition.clone());
+        Ok(definition)
+    }
+
+    /// The current tenant's fields, by name
+    pub fn list(&self) -> Result<Vec<CustomFieldDefinition>, AppError> {
+        let definitions = self
+            .definitions
+            .read()
+            .map_err(|_| DatabaseError::LockError)?;
+        Ok(definitions
+            .get(&current_tenant())
+            .map(|fields| fields.values().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    pub fn get(&self, name: &str) -> Result<Option<CustomFieldDefinition>, AppError> {
+        let definitions = self
+            .definitions
+            .read()
+            .map_err(|_| DatabaseError::LockError)?;
+        Ok(definitions
+            .get(&current_tenant())
+            .and_then(|fields| fields.get(name))
+            .cloned())
+    }
+
+    /// Forget the definition of `name`; whether there was one. Items keep
+    /// the values they hold until they clear them
+    pub fn remove(&self, name: &str) -> Result<bool, AppError> {
+        let mut definitions = self
+            .definitions
+            .write()
+            .map_err(|_| DatabaseError::LockError)?;
+        Ok(definitions
+            .get_mut(&current_tenant())
+            .and_then(|fields| fields.remove(name))
+            .is_some())
+    }
+
+    /// Check the custom field values of a new item
+    pub fn validate_create(&self, values: &BTreeMap<String, Value>) -> Result<(), AppError> {
+        self.validate(values, true)
+    }
+
+    /// Check custom field changes; `null` clears a field unless it is required
+    pub fn validate_update(&self, changes: &BTreeMap<String, Value>) -> Result<(), AppError> {
+        self.validate(changes, false)
+    }
+
+    fn validate(&self, values: &BTreeMap<String, Value>, creating: bool) -> Result<(), AppError> {
+        let definitions = self
+            .definitions
+            .read()
+            .map_err(|_| DatabaseError::LockError)?;
+        let empty = BTreeMap::new();
+        let fields = definitions.get(&current_tenant()).unwrap_or(&empty);
+
+        let mut errors = Vec::new();
+        for (name, value) in values {
+            let message = match fields.get(name) {
+                // Values of fields no longer defined can still be cleared
+                None if value.is_null() => None,
+                None => Some("Is not a defined custom field".to_string()),
+                Some(field) if value.is_null() => field.required.then(|| "Is required".to_string()),
+                Some(field) => field.check(value),
+            };
+            if let Some(message) = message {
+                errors.push(format!("custom_fields.{name}: {message}"));
+            }
+        }
+        if creating {
+            for field in fields.values().filter(|field| field.required) {
+                if values.get(&field.name).is_none_or(Value::is_null) {
+                    errors.push(format!("custom_fields.{}: Is required", field.name));
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::ValidationError(errors.join("\n")))
+        }
+    }
+
+    /// Reject conditions on custom fields the current tenant does not define
+    pub fn check_conditions(&self, conditions: &[Condition]) -> Result<(), AppError> {
+        let definitions = self
+            .definitions
+            .read()
+            .map_err(|_| DatabaseError::LockError)?;
+        let fields = definitions.get(&current_tenant());
+        for condition in conditions {
+            if let FilterField::Custom(name) = &condition.field {
+                if !fields.is_some_and(|fields| fields.contains_key(name)) {
+                    return Err(AppError::BadRequest(format!(
+                        "unknown field `custom_fields/{name}`"
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// JSON Schema of the current tenant's `custom_fields` object; with
+    /// `creating`, required fields are listed as such
+    pub fn json_schema(&self, creating: bool) -> Result<Value, AppError> {
+        let fields = self.list()?;
+        let properties: Map<String, Value> = fields
+            .iter()
+            .map(|field| (field.name.clone(), field.json_schema()))
+            .collect();
+        let mut schema = json!({
+            "type": "object",
+            "properties": properties,
+            "additionalProperties": false,
+        });
+        let required: Vec<_> = fields
+            .iter()
+            .filter(|field| field.required)
+            .map(|field| field.name.as_str())
+            .collect();
+        if creating && !required.is_empty() {
+            schema["required"] = json!(required);
+        }
+        Ok(schema)
+    }
+
+    /// Describe the current tenant's custom fields in the item schemas of
+    /// the OpenAPI document `spec`
+    pub fn document(&self, spec: &mut Value) -> Result<(), AppError> {
+        if self.list()?.is_empty() {
+            return Ok(());
+        }
+        for (schema, creating) in [
+            ("Item", false),
+            ("CreateItemRequest", true),
+            ("UpdateItemRequest", false),
+        ] {
+            let pointer = format!("/components/schemas/{schema}/properties/custom_fields");
+            if let Some(property) = spec.pointer_mut(&pointer) {
+                let description = property.get("description").cloned();
+                *property = self.json_schema(creating)?;
+                if let Some(description) = description {
+                    property["description"] = description;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn define(fields: &CustomFields, name: &str, field_type: CustomFieldType, values: &[&str]) {
+        let request = DefineCustomFieldRequest {
+            field_type,
+            values: values.iter().map(|value| value.to_string()).collect(),
+            required: name == "priority",
+            description: None,
+        };
+        fields.define(name, request).unwrap();
+    }
+
+    fn errors(result: Result<(), AppError>) -> String {
+        match result {
+            Err(AppError::ValidationError(message)) => message,
+            other => panic!("expected a validation error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_values_must_match_the_field_type() {
+        let fields = CustomFields::default();
+        define(&fields, "priority", CustomFieldType::Enum, &["low", "high"]);
+        define(&fields, "due", CustomFieldType::Date, &[]);
+        define(&fields, "size", CustomFieldType::Number, &[]);
+
+        let values = |value: Value| match value {
+            Value::Object(values) => values.into_iter().collect::<BTreeMap<_, _>>(),
+            _ => unreachable!(),
+        };
+        assert!(fields
+            .validate_create(&values(json!({ "priority": "low", "due": "2024-02-29" })))
+            .is_ok());
+        assert_eq!(
+            errors(fields.validate_create(&values(json!({ "due": "tomorrow", "color": "red" })))),
+            "custom_fields.color: Is not a defined custom field\n\
+             custom_fields.due: Must be a date such as 2024-01-15\n\
+             custom_fields.priority: Is required"
+        );
+        // Updates need not repeat required fields, but cannot clear them
+        assert!(fields
+            .validate_update(&values(json!({ "size": 2.5 })))
+            .is_ok());
+        assert_eq!(
+            errors(fields.validate_update(&values(json!({ "priority": null, "size": null })))),
+            "custom_fields.priority: Is required"
+        );
+    }
+
+    #[test]
+    fn test_openapi_schemas_describe_the_fields() {
+        let fields = CustomFields::default();
+        define(&fields, "priority", CustomFieldType::Enum, &["low", "high"]);
+        let mut spec = json!({
+            "components": { "schemas": {
+                "Item": { "properties": { "custom_fields": { "type": "object" } } },
+                "CreateItemRequest": { "properties": { "custom_fields": { "type": "object" } } },
+            } }
+        });
+        fields.document(&mut spec).unwrap();
+
+        let create = &spec["components"]["schemas"]["CreateItemRequest"]["properties"];
+        assert_eq!(create["custom_fields"]["required"], json!(["priority"]));
+        let item = &spec["components"]["schemas"]["Item"]["properties"]["custom_fields"];
+        assert_eq!(item["properties"]["priority"]["enum"], json!(["low", "high"]));
+        assert!(item.get("required").is_none());
+    }
+}
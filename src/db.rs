@@ -1,18 +1,47 @@
 use async_trait::async_trait;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use dashmap::{mapref::entry::Entry, DashMap};
 use std::{
-    collections::HashMap,
-    sync::{Arc, RwLock},
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
+    future::Future,
+    ops::Bound,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::Duration,
 };
 use uuid::Uuid;
 
 use crate::{
-    config::Config,
+    analytics::{AnalyticsBucket, AnalyticsRange},
+    approvals::{Approval, ChangeRequest, ChangeStatus},
+    checkout::ItemLock,
+    clock::{system_clock, SharedClock},
+    collections::ItemCollection,
+    comments::Comment,
+    concurrency::{AdaptiveLimiter, LimitedRepository},
+    config::{Config, TenantIsolation},
+    context::RequestContext,
+    dual_write::DualWriteRepository,
+    events::{ItemEventType, OutboxEvent},
+    hedging::HedgedRepository,
+    inbox::Notification,
     metrics::{
-        track_database_query, track_item_created, track_item_deleted, track_item_updated, Timer,
-        DATABASE_CONNECTIONS,
+        track_cancelled_operation, track_database_query, track_item_created, track_item_deleted,
+        track_item_updated, Timer, DATABASE_CONNECTIONS,
+    },
+    models::{
+        AccessGrant, CreateItemRequest, Grantee, Item, ItemField, ItemStatus, Permission, Tenant,
+        UpdateItemRequest, UpdateTenantRequest,
     },
-    models::{CreateItemRequest, Item, UpdateItemRequest},
+    pagination::{Page, PageStart},
+    query::{self, Comparison, Condition, FieldValue, QueryField},
+    saved_searches::SavedSearch,
+    shadow::ShadowRepository,
+    slow_log::record_query,
+    slug::{slugify, unique_slug},
+    tenancy::current_tenant,
 };
 
 /// Database errors that can occur across all implementations
@@ -21,6 +50,17 @@ pub enum DatabaseError {
     #[error("Item not found")]
     NotFound,
 
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    /// The item's version is not the one the change was based on
+    #[error("Item has been modified since version {expected}")]
+    VersionMismatch { expected: u64 },
+
+    /// The request's deadline passed before the operation finished
+    #[error("Deadline exceeded")]
+    DeadlineExceeded,
+
     #[error("Database connection error: {0}")]
     ConnectionError(String),
 
@@ -32,34 +72,598 @@ pub enum DatabaseError {
 
     #[error("Lock error")]
     LockError,
+
+    /// Too many calls to the database were already in flight
+    #[error("Database is overloaded")]
+    Overloaded,
 }
 
 pub type DatabaseResult<T> = Result<T, DatabaseError>;
 
+/// Filters applied to list and count queries
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ItemFilter {
+    /// Only include items owned by this principal
+    pub owner_id: Option<String>,
+    /// Conditions every included item satisfies
+    pub conditions: Vec<Condition>,
+}
+
+impl ItemFilter {
+    /// Filter restricted to items owned by `owner_id`
+    pub fn owned_by(owner_id: impl Into<String>) -> Self {
+        Self {
+            owner_id: Some(owner_id.into()),
+            conditions: Vec::new(),
+        }
+    }
+
+    /// Filter restricted to published items
+    pub fn published() -> Self {
+        Self::default().with_conditions(vec![Condition {
+            field: QueryField::Status.into(),
+            comparison: Comparison::Eq,
+            value: FieldValue::Text(ItemStatus::Published.as_str().to_string()),
+        }])
+    }
+
+    /// This filter, also restricted to items without an owner
+    #[must_use]
+    pub fn unowned(self) -> Self {
+        self.with_conditions(vec![Condition {
+            field: QueryField::OwnerId.into(),
+            comparison: Comparison::Eq,
+            value: FieldValue::Null,
+        }])
+    }
+
+    /// This filter, also requiring `conditions`
+    #[must_use]
+    pub fn with_conditions(mut self, conditions: Vec<Condition>) -> Self {
+        self.conditions.extend(conditions);
+        self
+    }
+
+    /// Whether an item satisfies this filter
+    pub fn matches(&self, item: &Item) -> bool {
+        self.owner_id
+            .as_ref()
+            .is_none_or(|owner| item.owner_id.as_ref() == Some(owner))
+            && self
+                .conditions
+                .iter()
+                .all(|condition| condition.matches(item))
+    }
+}
+
+/// Filters applied to outbox event history queries
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventFilter {
+    /// Only include events that occurred at or after this time
+    pub from: Option<DateTime<Utc>>,
+    /// Only include events that occurred before this time
+    pub to: Option<DateTime<Utc>>,
+    /// Only include events of these types; every type when empty
+    pub types: Vec<ItemEventType>,
+    pub item_id: Option<String>,
+}
+
+impl EventFilter {
+    /// Whether an event satisfies this filter
+    pub fn matches(&self, event: &OutboxEvent) -> bool {
+        self.from.is_none_or(|from| event.occurred_at >= from)
+            && self.to.is_none_or(|to| event.occurred_at < to)
+            && (self.types.is_empty() || self.types.contains(&event.event_type))
+            && self
+                .item_id
+                .as_ref()
+                .is_none_or(|item_id| event.item_id == *item_id)
+    }
+}
+
 /// Main repository trait for items
+///
+/// Slugs are unique within a tenant, and external IDs within a tenant and
+/// source; creating an item with an external ID another item of its source
+/// holds fails with `Conflict`.
 #[async_trait]
 pub trait ItemRepository: Send + Sync {
-    async fn create(&self, request: CreateItemRequest) -> DatabaseResult<Item>;
+    async fn create(
+        &self,
+        request: CreateItemRequest,
+        owner_id: Option<String>,
+    ) -> DatabaseResult<Item>;
     async fn get(&self, id: &str) -> DatabaseResult<Item>;
-    async fn update(&self, id: &str, request: UpdateItemRequest) -> DatabaseResult<Item>;
+    async fn get_by_slug(&self, slug: &str) -> DatabaseResult<Item>;
+    /// Item of `source` (or of no source) known by `external_id` there
+    async fn get_by_external_id(
+        &self,
+        source: Option<&str>,
+        external_id: &str,
+    ) -> DatabaseResult<Item>;
+    /// Apply `request`, failing with `VersionMismatch` unless the item is at
+    /// `expected_version` (when given); every update increments the version
+    async fn update(
+        &self,
+        id: &str,
+        request: UpdateItemRequest,
+        expected_version: Option<u64>,
+    ) -> DatabaseResult<Item>;
+    /// Fields whose values changed after `version`; repositories that do not
+    /// track this report every field
+    async fn changed_since(&self, id: &str, version: u64) -> DatabaseResult<Vec<ItemField>>;
     async fn delete(&self, id: &str) -> DatabaseResult<()>;
-    async fn list(&self, limit: usize, offset: usize) -> DatabaseResult<Vec<Item>>;
-    async fn count(&self) -> DatabaseResult<usize>;
+    /// One page of items in creation order; they are shared rather than
+    /// copied, so serializing a large page does not duplicate the store
+    async fn list(&self, filter: &ItemFilter, page: &Page) -> DatabaseResult<Vec<Arc<Item>>>;
+    async fn count(&self, filter: &ItemFilter) -> DatabaseResult<usize>;
+    /// One page of items and the number of items `filter` matches in all
+    ///
+    /// Runs `list` and `count` concurrently unless overridden; backends that
+    /// can answer both with one query, or can count approximately, should
+    /// override it.
+    async fn list_with_total(
+        &self,
+        filter: &ItemFilter,
+        page: &Page,
+    ) -> DatabaseResult<(Vec<Arc<Item>>, usize)> {
+        tokio::try_join!(self.list(filter, page), self.count(filter))
+    }
     async fn health_check(&self) -> DatabaseResult<()>;
+
+    /// Replace `owner_id` on every item owned by `owner_id`, and in outbox event
+    /// snapshots and actors, with `replacement`; returns (items, events) changed
+    async fn reassign_owner(
+        &self,
+        owner_id: &str,
+        replacement: &str,
+    ) -> DatabaseResult<(usize, usize)>;
+
+    /// Insert or replace an item exactly as given, without recording an outbox
+    /// event; used when restoring backups. A slug already held by another item
+    /// gets a numeric suffix.
+    async fn restore(&self, item: Item) -> DatabaseResult<Item>;
+
+    /// Prepare storage for a newly provisioned tenant (its schema, collection,
+    /// or database); a no-op where tenants share storage
+    async fn provision_tenant(&self, tenant: &str) -> DatabaseResult<()>;
+
+    /// Delete every item of a deprovisioned tenant without recording outbox
+    /// events; returns the IDs of the deleted items
+    async fn drop_tenant(&self, tenant: &str) -> DatabaseResult<Vec<String>>;
+
+    // Outbox: every mutation above records an event atomically with the change
+
+    /// Oldest unpublished outbox events, in sequence order
+    async fn pending_events(&self, limit: usize) -> DatabaseResult<Vec<OutboxEvent>>;
+    /// Outbox events after sequence `after` that match `filter`, published or
+    /// not, in sequence order; of every tenant, and only those not yet purged
+    async fn event_history(
+        &self,
+        filter: &EventFilter,
+        after: u64,
+        limit: usize,
+    ) -> DatabaseResult<Vec<OutboxEvent>>;
+    async fn pending_event_count(&self) -> DatabaseResult<usize>;
+    async fn mark_events_published(&self, sequences: &[u64]) -> DatabaseResult<()>;
+    async fn mark_event_failed(&self, sequence: u64, error: &str) -> DatabaseResult<()>;
+
+    /// Delete events published before `cutoff`, returning how many matched;
+    /// with `dry_run` nothing is deleted
+    async fn purge_published_events(
+        &self,
+        cutoff: DateTime<Utc>,
+        dry_run: bool,
+    ) -> DatabaseResult<usize>;
+
+    /// Changes per bucket of `range` to the current tenant's items matching
+    /// `filter`, counted from the outbox events not yet purged
+    async fn event_histogram(
+        &self,
+        filter: &ItemFilter,
+        range: &AnalyticsRange,
+    ) -> DatabaseResult<Vec<AnalyticsBucket>>;
+}
+
+/// Repository for per-item access control lists
+#[async_trait]
+pub trait AccessRepository: Send + Sync {
+    /// Grant access, replacing any existing grant for the same grantee
+    async fn grant(
+        &self,
+        item_id: &str,
+        grantee: Grantee,
+        permission: Permission,
+        granted_by: Option<String>,
+    ) -> DatabaseResult<AccessGrant>;
+    async fn list_grants(&self, item_id: &str) -> DatabaseResult<Vec<AccessGrant>>;
+    async fn revoke(&self, item_id: &str, grant_id: &str) -> DatabaseResult<()>;
+    async fn revoke_all(&self, item_id: &str) -> DatabaseResult<()>;
+    /// Remove grants to `principal` and clear it from `granted_by` on others;
+    /// returns (grants removed, grants scrubbed)
+    async fn forget_principal(&self, principal: &str) -> DatabaseResult<(usize, usize)>;
+    /// Insert or replace a grant exactly as given; used when restoring backups
+    async fn restore_grant(&self, grant: AccessGrant) -> DatabaseResult<()>;
+}
+
+/// Repository for provisioned tenants
+#[async_trait]
+pub trait TenantRepository: Send + Sync {
+    /// Store a new tenant; fails with `Conflict` if its ID is taken
+    async fn create(&self, tenant: Tenant) -> DatabaseResult<Tenant>;
+    async fn get(&self, id: &str) -> DatabaseResult<Tenant>;
+    /// Every tenant, ordered by ID
+    async fn list(&self) -> DatabaseResult<Vec<Tenant>>;
+    async fn update(&self, id: &str, request: UpdateTenantRequest) -> DatabaseResult<Tenant>;
+    async fn delete(&self, id: &str) -> DatabaseResult<()>;
+}
+
+/// Repository for the notification inbox
+///
+/// Reads and changes are scoped to the current tenant.
+#[async_trait]
+pub trait NotificationRepository: Send + Sync {
+    /// Store notifications, skipping any whose recipient already has one for
+    /// the same event; returns how many were stored
+    async fn add(&self, notifications: Vec<Notification>) -> DatabaseResult<usize>;
+    /// A page of a recipient's notifications, newest first, and how many match
+    async fn list(
+        &self,
+        recipient: &str,
+        unread_only: bool,
+        limit: usize,
+        offset: usize,
+    ) -> DatabaseResult<(Vec<Notification>, usize)>;
+    async fn unread_count(&self, recipient: &str) -> DatabaseResult<usize>;
+    /// Mark one of a recipient's notifications read
+    async fn mark_read(&self, recipient: &str, id: &str) -> DatabaseResult<Notification>;
+    /// Mark all of a recipient's notifications read; returns how many were unread
+    async fn mark_all_read(&self, recipient: &str) -> DatabaseResult<usize>;
+    /// Remove notifications to `principal` and clear it as the actor of
+    /// others, in every tenant; returns (notifications removed, scrubbed)
+    async fn forget_principal(&self, principal: &str) -> DatabaseResult<(usize, usize)>;
+}
+
+/// Repository for comments on items
+///
+/// Comments are addressed by item and ID; reads and changes are scoped to
+/// the current tenant. Deleting a comment only marks it deleted.
+#[async_trait]
+pub trait CommentRepository: Send + Sync {
+    async fn create(&self, comment: Comment) -> DatabaseResult<Comment>;
+    /// A comment, deleted or not
+    async fn get(&self, item_id: &str, id: &str) -> DatabaseResult<Comment>;
+    /// A page of an item's comments, oldest first, and how many match
+    async fn list(
+        &self,
+        item_id: &str,
+        include_deleted: bool,
+        limit: usize,
+        offset: usize,
+    ) -> DatabaseResult<(Vec<Comment>, usize)>;
+    /// Replace the body of a comment that is not deleted
+    async fn update(&self, item_id: &str, id: &str, body: String) -> DatabaseResult<Comment>;
+    /// Mark a comment deleted; `NotFound` if it already is
+    async fn delete(&self, item_id: &str, id: &str) -> DatabaseResult<Comment>;
+    /// Clear `principal` as the author of comments, in every tenant;
+    /// returns how many
+    async fn forget_principal(&self, principal: &str) -> DatabaseResult<usize>;
+}
+
+/// Repository for collections, the folders items are filed in
+///
+/// Collections form a tree per tenant. Each stores the IDs of its ancestors,
+/// root first, in `path`, so a backend can find a subtree with one indexed
+/// query on `path` rather than walking it a level at a time; moving a
+/// collection rewrites the paths below it. An item is filed in at most one
+/// collection. Reads and changes are scoped to the current tenant.
+#[async_trait]
+pub trait CollectionRepository: Send + Sync {
+    /// Store a new collection; `NotFound` if its parent no longer exists, and
+    /// `Conflict` if the parent has a child of the same name
+    async fn create(&self, collection: ItemCollection) -> DatabaseResult<ItemCollection>;
+    async fn get(&self, id: &str) -> DatabaseResult<ItemCollection>;
+    /// Collections directly under `parent_id`, or the root collections, by name
+    async fn children(&self, parent_id: Option<&str>) -> DatabaseResult<Vec<ItemCollection>>;
+    /// A page of the collections below `id` at any depth, shallowest first
+    /// and then by name, and how many there are
+    async fn descendants(
+        &self,
+        id: &str,
+        limit: usize,
+        offset: usize,
+    ) -> DatabaseResult<(Vec<ItemCollection>, usize)>;
+    /// Move a collection under `parent_id`, or to the root, with everything
+    /// below it; `Conflict` if the parent is the collection itself or below
+    /// it, or already has a child of the same name
+    async fn move_to(&self, id: &str, parent_id: Option<&str>) -> DatabaseResult<ItemCollection>;
+    /// Delete a collection; `Conflict` unless it is empty
+    async fn delete(&self, id: &str) -> DatabaseResult<()>;
+    /// File an item in `collection_id`, or take it out of its collection
+    async fn file_item(&self, item_id: &str, collection_id: Option<&str>) -> DatabaseResult<()>;
+    /// The collection an item is filed in
+    async fn item_collection(&self, item_id: &str) -> DatabaseResult<Option<String>>;
+    /// A page of the IDs of the items filed in `id`, or anywhere below it
+    /// when `recursive`, in the order they were filed, and how many there are
+    async fn items(
+        &self,
+        id: &str,
+        recursive: bool,
+        limit: usize,
+        offset: usize,
+    ) -> DatabaseResult<(Vec<String>, usize)>;
+    /// Forget where a deleted item was filed
+    async fn forget_item(&self, item_id: &str) -> DatabaseResult<()>;
+    /// Hand collections owned by `principal` over to `owner`, in every
+    /// tenant; returns how many
+    async fn reassign_owner(&self, principal: &str, owner: &str) -> DatabaseResult<usize>;
+}
+
+/// Repository for saved searches
+///
+/// Reads and changes are scoped to the current tenant.
+#[async_trait]
+pub trait SavedSearchRepository: Send + Sync {
+    /// Save a search; `Conflict` if its owner has one of the same name
+    async fn create(&self, search: SavedSearch) -> DatabaseResult<SavedSearch>;
+    async fn get(&self, id: &str) -> DatabaseResult<SavedSearch>;
+    /// An owner's searches, by name
+    async fn list(&self, owner: &str) -> DatabaseResult<Vec<SavedSearch>>;
+    async fn delete(&self, id: &str) -> DatabaseResult<()>;
+    /// Searches someone is told about new matching items for
+    async fn subscriptions(&self) -> DatabaseResult<Vec<SavedSearch>>;
+    /// Remove the searches `principal` owns, in every tenant; returns how many
+    async fn forget_principal(&self, principal: &str) -> DatabaseResult<usize>;
+}
+
+/// Repository for leases: named rows held by one replica until they expire
+///
+/// Leases are deployment-wide rather than scoped to a tenant. Taking one must
+/// be a single conditional write, so two replicas claiming an expired lease
+/// at once cannot both succeed.
+#[async_trait]
+pub trait LeaseRepository: Send + Sync {
+    /// Hold lease `name` for `holder` for `ttl`, if it is free, expired, or
+    /// already `holder`'s; returns whether `holder` now holds it
+    async fn try_acquire(&self, name: &str, holder: &str, ttl: Duration) -> DatabaseResult<bool>;
+    /// Free lease `name`, if `holder` holds it
+    async fn release(&self, name: &str, holder: &str) -> DatabaseResult<()>;
+}
+
+/// Repository for check-out locks on items
+///
+/// A lock belongs to one principal until it is released or expires; expired
+/// locks are treated as absent. Like leases, taking a lock must be a single
+/// conditional write.
+#[async_trait]
+pub trait ItemLockRepository: Send + Sync {
+    /// Lock `lock.item_id` for `lock.owner` until `lock.expires_at`, if it is
+    /// unlocked, its lock expired, or its owner already holds it; returns the
+    /// lock now on the item, which is someone else's if they held it
+    async fn acquire(&self, lock: ItemLock) -> DatabaseResult<ItemLock>;
+    /// The unexpired lock on an item
+    async fn get(&self, item_id: &str) -> DatabaseResult<Option<ItemLock>>;
+    /// Remove the lock on an item, whoever holds it
+    async fn release(&self, item_id: &str) -> DatabaseResult<()>;
+    /// Remove every lock `owner` holds, in every tenant; returns how many
+    async fn release_owned_by(&self, owner: &str) -> DatabaseResult<usize>;
+}
+
+/// Repository for change requests awaiting approval
+///
+/// Change requests are scoped to the current tenant. Approving and resolving
+/// a request must each be a single conditional write that only succeeds
+/// while it is pending, so one change is never applied twice.
+#[async_trait]
+pub trait ChangeRequestRepository: Send + Sync {
+    async fn create(&self, change: ChangeRequest) -> DatabaseResult<ChangeRequest>;
+    async fn get(&self, id: &str) -> DatabaseResult<ChangeRequest>;
+    /// Change requests, newest first, in `status` or in any state
+    async fn list(&self, status: Option<ChangeStatus>) -> DatabaseResult<Vec<ChangeRequest>>;
+    /// Record `approval` of a pending request; `Conflict` if it is no longer
+    /// pending or its principal already approved it
+    async fn approve(&self, id: &str, approval: Approval) -> DatabaseResult<ChangeRequest>;
+    /// Move a pending request to `status`; `Conflict` if it is no longer pending
+    async fn resolve(
+        &self,
+        id: &str,
+        status: ChangeStatus,
+        resolved_by: &str,
+        reason: Option<String>,
+    ) -> DatabaseResult<ChangeRequest>;
+    /// Replace `principal` with `pseudonym` as requester, approver and
+    /// resolver of requests, in every tenant; returns how many requests
+    async fn reassign_principal(&self, principal: &str, pseudonym: &str) -> DatabaseResult<usize>;
 }
 
 /// In-memory implementation of the repository
+///
+/// Items and slugs live in sharded concurrent maps, so operations on different
+/// items rarely contend. A mutation holds its item's map entry while recording
+/// the outbox event, so each item's events are sequenced in the order its
+/// mutations were applied.
+///
+/// Items carry their `tenant_id` (the current tenant when they were written)
+/// and are only visible within that tenant; the outbox is deployment-wide.
 pub struct InMemoryRepository {
-    data: Arc<RwLock<HashMap<String, Item>>>,
+    items: DashMap<String, Arc<Item>>,
+    /// Version at which each item's fields last changed, kept beside the items
+    /// so they stay out of API responses
+    field_versions: DashMap<String, FieldVersions>,
+    slugs: DashMap<SlugKey, String>,
+    external_ids: DashMap<ExternalIdKey, String>,
+    /// Items ordered by creation time, so pages are read in order without sorting
+    order: RwLock<BTreeSet<OrderKey>>,
+    outbox: Mutex<BTreeMap<u64, OutboxEvent>>,
+    next_sequence: Arc<AtomicU64>,
+    clock: SharedClock,
+}
+
+/// Version at which each client-editable field last changed
+#[derive(Debug, Clone, Copy)]
+struct FieldVersions {
+    name: u64,
+    description: u64,
+    metadata: u64,
+    custom_fields: u64,
+}
+
+impl FieldVersions {
+    /// Every field changed at `version`
+    fn at(version: u64) -> Self {
+        Self {
+            name: version,
+            description: version,
+            metadata: version,
+            custom_fields: version,
+        }
+    }
+
+    fn changed_since(self, version: u64) -> Vec<ItemField> {
+        [
+            (ItemField::Name, self.name),
+            (ItemField::Description, self.description),
+            (ItemField::Metadata, self.metadata),
+            (ItemField::CustomFields, self.custom_fields),
+        ]
+        .into_iter()
+        .filter(|(_, changed)| *changed > version)
+        .map(|(field, _)| field)
+        .collect()
+    }
+}
+
+/// Slugs are unique per tenant
+type SlugKey = (Option<String>, String);
+
+/// External IDs are unique per tenant and source
+type ExternalIdKey = (Option<String>, Option<String>, String);
+
+/// Position of an item in list order; the ID breaks ties between items
+/// created in the same instant
+type OrderKey = (DateTime<Utc>, String);
+
+fn order_key(item: &Item) -> OrderKey {
+    (item.created_at, item.id.clone())
 }
 
 impl InMemoryRepository {
     #[must_use]
     pub fn new() -> Self {
+        Self::with_sequence(Arc::default())
+    }
+
+    /// Create a repository drawing outbox sequence numbers from a shared
+    /// counter, so events stay globally ordered across per-tenant stores
+    #[must_use]
+    pub fn with_sequence(sequence: Arc<AtomicU64>) -> Self {
         Self {
-            data: Arc::new(RwLock::new(HashMap::new())),
+            items: DashMap::new(),
+            field_versions: DashMap::new(),
+            slugs: DashMap::new(),
+            external_ids: DashMap::new(),
+            order: RwLock::new(BTreeSet::new()),
+            outbox: Mutex::new(BTreeMap::new()),
+            next_sequence: sequence,
+            clock: system_clock(),
+        }
+    }
+
+    /// Stamp items with `clock` instead of the system clock
+    #[must_use]
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Append an outbox event, attributed to the current request; callers
+    /// hold the item's entry while doing so
+    fn record_event(&self, event_type: ItemEventType, item: Item) -> DatabaseResult<()> {
+        let context = RequestContext::current();
+        let mut outbox = self.outbox.lock().map_err(|_| DatabaseError::LockError)?;
+        let sequence = self.next_sequence.fetch_add(1, Ordering::SeqCst) + 1;
+        let event = OutboxEvent::new(sequence, event_type, item).attributed(&context);
+        outbox.insert(sequence, event);
+        Ok(())
+    }
+
+    /// Claim the first free slug for `base` in the tenant on behalf of `id`
+    fn claim_slug(&self, tenant: &Option<String>, base: &str, id: &str) -> String {
+        // A candidate counts as taken unless this call manages to claim it, so
+        // concurrent writers can never end up with the same slug
+        unique_slug(base, |candidate| {
+            match self.slugs.entry((tenant.clone(), candidate.to_string())) {
+                Entry::Vacant(entry) => {
+                    entry.insert(id.to_string());
+                    false
+                }
+                Entry::Occupied(entry) => entry.get() != id,
+            }
+        })
+    }
+
+    fn release_slug(&self, item: &Item) {
+        self.slugs
+            .remove(&(item.tenant_id.clone(), item.slug.clone()));
+    }
+
+    /// Claim `external_id` of `source` in the tenant on behalf of `id`,
+    /// unless another item holds it
+    fn claim_external_id(
+        &self,
+        tenant: &Option<String>,
+        source: &Option<String>,
+        external_id: &str,
+        id: &str,
+    ) -> DatabaseResult<()> {
+        match self
+            .external_ids
+            .entry((tenant.clone(), source.clone(), external_id.to_string()))
+        {
+            Entry::Vacant(entry) => {
+                entry.insert(id.to_string());
+                Ok(())
+            }
+            Entry::Occupied(entry) if entry.get() == id => Ok(()),
+            Entry::Occupied(_) => Err(DatabaseError::Conflict(match source {
+                Some(source) => {
+                    format!("An item with external ID {external_id} from {source} already exists")
+                }
+                None => format!("An item with external ID {external_id} already exists"),
+            })),
+        }
+    }
+
+    fn release_external_id(&self, item: &Item) {
+        if let Some(external_id) = &item.external_id {
+            let key = (item.tenant_id.clone(), item.source.clone(), external_id.clone());
+            self.external_ids.remove_if(&key, |_, id| *id == item.id);
         }
     }
+
+    /// Item with `id`, if it belongs to `tenant`
+    fn visible(&self, id: &str, tenant: &Option<String>) -> Option<Arc<Item>> {
+        self.items
+            .get(id)
+            .map(|item| item.clone())
+            .filter(|item| item.tenant_id == *tenant)
+    }
+
+    fn index(&self, key: OrderKey) -> DatabaseResult<()> {
+        self.order
+            .write()
+            .map_err(|_| DatabaseError::LockError)?
+            .insert(key);
+        Ok(())
+    }
+
+    fn unindex(&self, key: &OrderKey) -> DatabaseResult<()> {
+        self.order
+            .write()
+            .map_err(|_| DatabaseError::LockError)?
+            .remove(key);
+        Ok(())
+    }
 }
 
 impl Default for InMemoryRepository {
@@ -68,35 +672,126 @@ impl Default for InMemoryRepository {
     }
 }
 
+// Lock order: an item entry may be held while taking the slug map or the
+// outbox, never the order index (which `list` holds while it reads items), so
+// the index is only updated after the entry is released.
 #[async_trait]
 impl ItemRepository for InMemoryRepository {
-    async fn create(&self, request: CreateItemRequest) -> DatabaseResult<Item> {
-        let mut items = self.data.write().map_err(|_| DatabaseError::LockError)?;
-
+    async fn create(
+        &self,
+        request: CreateItemRequest,
+        owner_id: Option<String>,
+    ) -> DatabaseResult<Item> {
+        let tenant = current_tenant();
         let id = Uuid::new_v4().to_string();
-        let now = Utc::now();
+        let now = self.clock.now();
+        if let Some(external_id) = &request.external_id {
+            self.claim_external_id(&tenant, &request.source, external_id, &id)?;
+        }
+        let slug = self.claim_slug(&tenant, &slugify(&request.name), &id);
 
         let item = Item {
             id: id.clone(),
             name: request.name,
+            slug,
+            external_id: request.external_id,
+            source: request.source,
             description: request.description,
+            metadata: request.metadata,
+            custom_fields: request
+                .custom_fields
+                .into_iter()
+                .filter(|(_, value)| !value.is_null())
+                .collect(),
+            owner_id,
+            tenant_id: tenant,
+            status: request.status,
+            publish_at: request
+                .publish_at
+                .filter(|_| request.status == ItemStatus::Draft),
             created_at: now,
             updated_at: now,
+            version: 1,
         };
 
-        items.insert(id, item.clone());
+        {
+            let Entry::Vacant(entry) = self.items.entry(id.clone()) else {
+                return Err(DatabaseError::QueryError(format!("Duplicate item ID {id}")));
+            };
+            let _entry = entry.insert(Arc::new(item.clone()));
+            self.field_versions.insert(id.clone(), FieldVersions::at(1));
+            self.record_event(ItemEventType::Created, item.clone())?;
+        }
+        self.index(order_key(&item))?;
         Ok(item)
     }
 
     async fn get(&self, id: &str) -> DatabaseResult<Item> {
-        let items = self.data.read().map_err(|_| DatabaseError::LockError)?;
-        items.get(id).cloned().ok_or(DatabaseError::NotFound)
+        self.visible(id, &current_tenant())
+            .map(Arc::unwrap_or_clone)
+            .ok_or(DatabaseError::NotFound)
     }
 
-    async fn update(&self, id: &str, request: UpdateItemRequest) -> DatabaseResult<Item> {
-        let mut items = self.data.write().map_err(|_| DatabaseError::LockError)?;
+    async fn get_by_slug(&self, slug: &str) -> DatabaseResult<Item> {
+        let tenant = current_tenant();
+        let id = self
+            .slugs
+            .get(&(tenant.clone(), slug.to_string()))
+            .map(|id| id.clone())
+            .ok_or(DatabaseError::NotFound)?;
+        self.visible(&id, &tenant)
+            .map(Arc::unwrap_or_clone)
+            .ok_or(DatabaseError::NotFound)
+    }
+
+    async fn get_by_external_id(
+        &self,
+        source: Option<&str>,
+        external_id: &str,
+    ) -> DatabaseResult<Item> {
+        let tenant = current_tenant();
+        let key = (tenant.clone(), source.map(str::to_string), external_id.to_string());
+        let id = self
+            .external_ids
+            .get(&key)
+            .map(|id| id.clone())
+            .ok_or(DatabaseError::NotFound)?;
+        self.visible(&id, &tenant)
+            .map(Arc::unwrap_or_clone)
+            .ok_or(DatabaseError::NotFound)
+    }
+
+    async fn update(
+        &self,
+        id: &str,
+        request: UpdateItemRequest,
+        expected_version: Option<u64>,
+    ) -> DatabaseResult<Item> {
+        let tenant = current_tenant();
+        let mut entry = self
+            .items
+            .get_mut(id)
+            .filter(|item| item.tenant_id == tenant)
+            .ok_or(DatabaseError::NotFound)?;
+        if let Some(expected) = expected_version.filter(|v| *v != entry.version) {
+            return Err(DatabaseError::VersionMismatch { expected });
+        }
+        let item = Arc::make_mut(&mut entry);
+        let before = (
+            item.name.clone(),
+            item.slug.clone(),
+            item.description.clone(),
+            item.metadata.clone(),
+            item.custom_fields.clone(),
+        );
 
-        let item = items.get_mut(id).ok_or(DatabaseError::NotFound)?;
+        // Release the current slug first so an unchanged name keeps it
+        if request.regenerate_slug {
+            self.release_slug(item);
+            let name = request.name.as_deref().unwrap_or(&item.name);
+            let slug = self.claim_slug(&tenant, &slugify(name), id);
+            item.slug = slug;
+        }
 
         if let Some(name) = request.name {
             item.name = name;
@@ -104,185 +799,2785 @@ impl ItemRepository for InMemoryRepository {
         if request.description.is_some() {
             item.description = request.description;
         }
-        item.updated_at = Utc::now();
+        if request.metadata.is_some() {
+            item.metadata = request.metadata;
+        }
+        item.set_custom_fields(&request.custom_fields);
+        if let Some(status) = request.status {
+            item.status = status;
+        }
+        if let Some(publish_at) = request.publish_at {
+            item.publish_at = publish_at;
+        }
+        item.updated_at = self.clock.now();
+        item.version += 1;
+
+        let mut fields = self
+            .field_versions
+            .entry(id.to_string())
+            .or_insert_with(|| FieldVersions::at(item.version));
+        if (&item.name, &item.slug) != (&before.0, &before.1) {
+            fields.name = item.version;
+        }
+        if item.description != before.2 {
+            fields.description = item.version;
+        }
+        if item.metadata != before.3 {
+            fields.metadata = item.version;
+        }
+        if item.custom_fields != before.4 {
+            fields.custom_fields = item.version;
+        }
+        drop(fields);
+
+        let item = item.clone();
+        self.record_event(ItemEventType::Updated, item.clone())?;
+        Ok(item)
+    }
 
-        Ok(item.clone())
+    async fn changed_since(&self, id: &str, version: u64) -> DatabaseResult<Vec<ItemField>> {
+        let item = self
+            .visible(id, &current_tenant())
+            .ok_or(DatabaseError::NotFound)?;
+        let fields = self
+            .field_versions
+            .get(id)
+            .map_or(FieldVersions::at(item.version), |fields| *fields);
+        Ok(fields.changed_since(version))
     }
 
     async fn delete(&self, id: &str) -> DatabaseResult<()> {
-        let mut items = self.data.write().map_err(|_| DatabaseError::LockError)?;
-        items.remove(id).ok_or(DatabaseError::NotFound)?;
-        Ok(())
+        let tenant = current_tenant();
+        let key = {
+            let Entry::Occupied(entry) = self.items.entry(id.to_string()) else {
+                return Err(DatabaseError::NotFound);
+            };
+            if entry.get().tenant_id != tenant {
+                return Err(DatabaseError::NotFound);
+            }
+            let item = entry.remove();
+            self.field_versions.remove(id);
+            self.release_slug(&item);
+            self.release_external_id(&item);
+            let key = order_key(&item);
+            self.record_event(ItemEventType::Deleted, Arc::unwrap_or_clone(item))?;
+            key
+        };
+        self.unindex(&key)
     }
 
-    async fn list(&self, limit: usize, offset: usize) -> DatabaseResult<Vec<Item>> {
-        let items = self.data.read().map_err(|_| DatabaseError::LockError)?;
+    async fn list(&self, filter: &ItemFilter, page: &Page) -> DatabaseResult<Vec<Arc<Item>>> {
+        let tenant = current_tenant();
+        let order = self.order.read().map_err(|_| DatabaseError::LockError)?;
+
+        // A cursor seeks straight to its position in the index
+        let keys: Box<dyn Iterator<Item = &OrderKey>> = match page.start() {
+            PageStart::Offset(_) => Box::new(order.iter()),
+            PageStart::After(cursor) => Box::new(order.range((
+                Bound::Excluded((cursor.created_at, cursor.id.clone())),
+                Bound::Unbounded,
+            ))),
+        };
+
+        // Sorted pages need every matching item in hand before skipping
+        if !page.order().is_empty() {
+            let mut items: Vec<Arc<Item>> = keys
+                .filter_map(|(_, id)| self.items.get(id).map(|item| item.clone()))
+                .filter(|item| item.tenant_id == tenant && filter.matches(item))
+                .collect();
+            items.sort_by(|a, b| query::compare(page.order(), a, b));
+            items.truncate(page.skipped() + page.limit());
+            return Ok(items.into_iter().skip(page.skipped()).collect());
+        }
 
-        let mut all_items: Vec<Item> = items.values().cloned().collect();
-        // Sort by created_at for consistent ordering
-        all_items.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+        // Only the page is shared with the caller, and without copying items.
+        // Entries whose item was just removed are skipped
+        Ok(keys
+            .filter_map(|(_, id)| self.items.get(id).map(|item| item.clone()))
+            .filter(|item| item.tenant_id == tenant && filter.matches(item))
+            .skip(page.skipped())
+            .take(page.limit())
+            .collect())
+    }
 
-        Ok(all_items.into_iter().skip(offset).take(limit).collect())
+    async fn count(&self, filter: &ItemFilter) -> DatabaseResult<usize> {
+        let tenant = current_tenant();
+        Ok(self
+            .items
+            .iter()
+            .filter(|item| item.tenant_id == tenant && filter.matches(item))
+            .count())
     }
 
-    async fn count(&self) -> DatabaseResult<usize> {
-        let items = self.data.read().map_err(|_| DatabaseError::LockError)?;
-        Ok(items.len())
+    /// Counts the matching items in the same pass over the index that
+    /// collects the page
+    async fn list_with_total(
+        &self,
+        filter: &ItemFilter,
+        page: &Page,
+    ) -> DatabaseResult<(Vec<Arc<Item>>, usize)> {
+        let tenant = current_tenant();
+        let order = self.order.read().map_err(|_| DatabaseError::LockError)?;
+        let matching = order
+            .iter()
+            .filter_map(|(_, id)| self.items.get(id).map(|item| item.clone()))
+            .filter(|item| item.tenant_id == tenant && filter.matches(item));
+
+        if !page.order().is_empty() {
+            let mut items: Vec<Arc<Item>> = matching.collect();
+            let total = items.len();
+            items.sort_by(|a, b| query::compare(page.order(), a, b));
+            let items = items
+                .into_iter()
+                .skip(page.skipped())
+                .take(page.limit())
+                .collect();
+            return Ok((items, total));
+        }
+
+        let mut items = Vec::with_capacity(page.limit());
+        let (mut total, mut skipped) = (0, 0);
+        for item in matching {
+            total += 1;
+            if let PageStart::After(cursor) = page.start() {
+                if (item.created_at, &item.id) <= (cursor.created_at, &cursor.id) {
+                    continue;
+                }
+            }
+            if skipped < page.skipped() {
+                skipped += 1;
+            } else if items.len() < page.limit() {
+                items.push(item);
+            }
+        }
+        Ok((items, total))
     }
 
     async fn health_check(&self) -> DatabaseResult<()> {
         // In-memory database is always healthy
         Ok(())
     }
-}
 
-/// Future implementation for Convex database
-pub struct ConvexRepository {
-    #[allow(dead_code)]
-    deployment_url: String,
-}
+    async fn reassign_owner(
+        &self,
+        owner_id: &str,
+        replacement: &str,
+    ) -> DatabaseResult<(usize, usize)> {
+        let tenant = current_tenant();
+        let replacement = Some(replacement.to_string());
+        let (mut items, mut events) = (0, 0);
 
-impl ConvexRepository {
-    pub fn new(deployment_url: String) -> Self {
-        Self { deployment_url }
+        for mut item in self.items.iter_mut() {
+            if item.tenant_id == tenant && item.owner_id.as_deref() == Some(owner_id) {
+                Arc::make_mut(&mut item).owner_id.clone_from(&replacement);
+                items += 1;
+            }
+        }
+
+        let mut outbox = self.outbox.lock().map_err(|_| DatabaseError::LockError)?;
+        for event in outbox
+            .values_mut()
+            .filter(|event| event.item.tenant_id == tenant)
+        {
+            let owned = event.item.owner_id.as_deref() == Some(owner_id);
+            let acted = event.actor.as_deref() == Some(owner_id);
+            if owned {
+                event.item.owner_id.clone_from(&replacement);
+            }
+            if acted {
+                event.actor.clone_from(&replacement);
+            }
+            if owned || acted {
+                events += 1;
+            }
+        }
+        Ok((items, events))
     }
-}
 
-#[async_trait]
-impl ItemRepository for ConvexRepository {
-    async fn create(&self, _request: CreateItemRequest) -> DatabaseResult<Item> {
-        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    async fn restore(&self, mut item: Item) -> DatabaseResult<Item> {
+        // Restored items belong to the tenant performing the restore
+        let tenant = current_tenant();
+        item.tenant_id.clone_from(&tenant);
+
+        // The restored creation time may move an existing item in list order
+        let previous_key = match self.items.entry(item.id.clone()) {
+            Entry::Occupied(mut entry) => {
+                if entry.get().tenant_id != tenant {
+                    return Err(DatabaseError::QueryError(format!(
+                        "Item {} belongs to another tenant",
+                        item.id
+                    )));
+                }
+                if let Some(external_id) = &item.external_id {
+                    self.claim_external_id(&tenant, &item.source, external_id, &item.id)?;
+                }
+                self.release_slug(entry.get());
+                if (&entry.get().source, &entry.get().external_id)
+                    != (&item.source, &item.external_id)
+                {
+                    self.release_external_id(entry.get());
+                }
+                item.slug = self.claim_slug(&tenant, &item.slug, &item.id);
+                // Never reuse a version, so stale ETags cannot match restored data
+                item.version = item.version.max(entry.get().version + 1);
+                let previous = entry.insert(Arc::new(item.clone()));
+                Some(order_key(&previous))
+            }
+            Entry::Vacant(entry) => {
+                if let Some(external_id) = &item.external_id {
+                    self.claim_external_id(&tenant, &item.source, external_id, &item.id)?;
+                }
+                item.slug = self.claim_slug(&tenant, &item.slug, &item.id);
+                entry.insert(Arc::new(item.clone()));
+                None
+            }
+        };
+
+        // Which fields changed when is not part of a backup
+        self.field_versions
+            .insert(item.id.clone(), FieldVersions::at(item.version));
+        let key = order_key(&item);
+        if let Some(previous) = previous_key.filter(|previous| *previous != key) {
+            self.unindex(&previous)?;
+        }
+        self.index(key)?;
+        Ok(item)
     }
 
-    async fn get(&self, _id: &str) -> DatabaseResult<Item> {
-        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    async fn provision_tenant(&self, _tenant: &str) -> DatabaseResult<()> {
+        Ok(())
     }
 
-    async fn update(&self, _id: &str, _request: UpdateItemRequest) -> DatabaseResult<Item> {
-        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    async fn drop_tenant(&self, tenant: &str) -> DatabaseResult<Vec<String>> {
+        let tenant_id = Some(tenant.to_string());
+
+        let ids: Vec<String> = self
+            .items
+            .iter()
+            .filter(|item| item.tenant_id == tenant_id)
+            .map(|item| item.key().clone())
+            .collect();
+        for id in &ids {
+            if let Some((_, item)) = self.items.remove(id) {
+                self.field_versions.remove(id);
+                self.unindex(&order_key(&item))?;
+            }
+        }
+        self.slugs
+            .retain(|(slug_tenant, _), _| *slug_tenant != tenant_id);
+        self.external_ids
+            .retain(|(external_tenant, _, _), _| *external_tenant != tenant_id);
+        Ok(ids)
     }
 
-    async fn delete(&self, _id: &str) -> DatabaseResult<()> {
-        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    async fn pending_events(&self, limit: usize) -> DatabaseResult<Vec<OutboxEvent>> {
+        let outbox = self.outbox.lock().map_err(|_| DatabaseError::LockError)?;
+        Ok(outbox
+            .values()
+            .filter(|event| event.published_at.is_none())
+            .take(limit)
+            .cloned()
+            .collect())
     }
 
-    async fn list(&self, _limit: usize, _offset: usize) -> DatabaseResult<Vec<Item>> {
-        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    async fn event_history(
+        &self,
+        filter: &EventFilter,
+        after: u64,
+        limit: usize,
+    ) -> DatabaseResult<Vec<OutboxEvent>> {
+        let outbox = self.outbox.lock().map_err(|_| DatabaseError::LockError)?;
+        Ok(outbox
+            .range((Bound::Excluded(after), Bound::Unbounded))
+            .map(|(_, event)| event)
+            .filter(|event| filter.matches(event))
+            .take(limit)
+            .cloned()
+            .collect())
     }
 
-    async fn count(&self) -> DatabaseResult<usize> {
-        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    async fn pending_event_count(&self) -> DatabaseResult<usize> {
+        let outbox = self.outbox.lock().map_err(|_| DatabaseError::LockError)?;
+        Ok(outbox
+            .values()
+            .filter(|event| event.published_at.is_none())
+            .count())
     }
 
-    async fn health_check(&self) -> DatabaseResult<()> {
-        // TODO: Implement actual health check
+    async fn mark_events_published(&self, sequences: &[u64]) -> DatabaseResult<()> {
+        let mut outbox = self.outbox.lock().map_err(|_| DatabaseError::LockError)?;
+        let now = self.clock.now();
+        for sequence in sequences {
+            if let Some(event) = outbox.get_mut(sequence) {
+                event.published_at = Some(now);
+            }
+        }
+        Ok(())
+    }
+
+    async fn mark_event_failed(&self, sequence: u64, error: &str) -> DatabaseResult<()> {
+        let mut outbox = self.outbox.lock().map_err(|_| DatabaseError::LockError)?;
+        let event = outbox.get_mut(&sequence).ok_or(DatabaseError::NotFound)?;
+        event.attempts += 1;
+        event.last_error = Some(error.to_string());
         Ok(())
     }
+
+    async fn purge_published_events(
+        &self,
+        cutoff: DateTime<Utc>,
+        dry_run: bool,
+    ) -> DatabaseResult<usize> {
+        let mut outbox = self.outbox.lock().map_err(|_| DatabaseError::LockError)?;
+        let expired = |event: &OutboxEvent| event.published_at.is_some_and(|at| at < cutoff);
+
+        if dry_run {
+            return Ok(outbox.values().filter(|e| expired(e)).count());
+        }
+
+        let before = outbox.len();
+        outbox.retain(|_, event| !expired(event));
+        Ok(before - outbox.len())
+    }
+
+    async fn event_histogram(
+        &self,
+        filter: &ItemFilter,
+        range: &AnalyticsRange,
+    ) -> DatabaseResult<Vec<AnalyticsBucket>> {
+        let tenant = current_tenant();
+        let mut histogram = range.histogram();
+        let outbox = self.outbox.lock().map_err(|_| DatabaseError::LockError)?;
+        for event in outbox.values() {
+            if event.item.tenant_id == tenant && filter.matches(&event.item) {
+                histogram.record(event.event_type, event.occurred_at);
+            }
+        }
+        Ok(histogram.into_buckets())
+    }
 }
 
-/// Metrics wrapper for `ItemRepository`
-pub struct MetricsRepository {
-    inner: Arc<dyn ItemRepository>,
+/// In-memory implementation of the access repository
+pub struct InMemoryAccessRepository {
+    grants: Arc<RwLock<HashMap<String, Vec<AccessGrant>>>>,
+    clock: SharedClock,
 }
 
-impl MetricsRepository {
-    pub fn new(inner: Arc<dyn ItemRepository>) -> Self {
-        DATABASE_CONNECTIONS.inc();
-        Self { inner }
+impl InMemoryAccessRepository {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            grants: Arc::new(RwLock::new(HashMap::new())),
+            clock: system_clock(),
+        }
+    }
+
+    /// Stamp grants with `clock` instead of the system clock
+    #[must_use]
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
     }
 }
 
-impl Drop for MetricsRepository {
-    fn drop(&mut self) {
-        DATABASE_CONNECTIONS.dec();
+impl Default for InMemoryAccessRepository {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 #[async_trait]
-impl ItemRepository for MetricsRepository {
-    async fn create(&self, request: CreateItemRequest) -> DatabaseResult<Item> {
-        let timer = Timer::new();
-        let result = self.inner.create(request).await;
-        track_database_query("create", "items", result.is_ok(), timer.elapsed_seconds());
+impl AccessRepository for InMemoryAccessRepository {
+    async fn grant(
+        &self,
+        item_id: &str,
+        grantee: Grantee,
+        permission: Permission,
+        granted_by: Option<String>,
+    ) -> DatabaseResult<AccessGrant> {
+        let mut grants = self.grants.write().map_err(|_| DatabaseError::LockError)?;
+        let item_grants = grants.entry(item_id.to_string()).or_default();
+        item_grants.retain(|existing| existing.grantee != grantee);
 
-        if result.is_ok() {
-            track_item_created();
-        }
+        let grant = AccessGrant {
+            id: Uuid::new_v4().to_string(),
+            item_id: item_id.to_string(),
+            grantee,
+            permission,
+            granted_by,
+            created_at: self.clock.now(),
+        };
+        item_grants.push(grant.clone());
+        Ok(grant)
+    }
 
-        result
+    async fn list_grants(&self, item_id: &str) -> DatabaseResult<Vec<AccessGrant>> {
+        let grants = self.grants.read().map_err(|_| DatabaseError::LockError)?;
+        Ok(grants.get(item_id).cloned().unwrap_or_default())
     }
 
-    async fn get(&self, id: &str) -> DatabaseResult<Item> {
-        let timer = Timer::new();
-        let result = self.inner.get(id).await;
-        track_database_query("get", "items", result.is_ok(), timer.elapsed_seconds());
-        result
+    async fn forget_principal(&self, principal: &str) -> DatabaseResult<(usize, usize)> {
+        let mut grants = self.grants.write().map_err(|_| DatabaseError::LockError)?;
+        let grantee = Grantee::Principal(principal.to_string());
+        let (mut removed, mut scrubbed) = (0, 0);
+
+        for item_grants in grants.values_mut() {
+            let before = item_grants.len();
+            item_grants.retain(|grant| grant.grantee != grantee);
+            removed += before - item_grants.len();
+
+            for grant in item_grants.iter_mut() {
+                if grant.granted_by.as_deref() == Some(principal) {
+                    grant.granted_by = None;
+                    scrubbed += 1;
+                }
+            }
+        }
+        Ok((removed, scrubbed))
     }
 
-    async fn update(&self, id: &str, request: UpdateItemRequest) -> DatabaseResult<Item> {
-        let timer = Timer::new();
-        let result = self.inner.update(id, request).await;
-        track_database_query("update", "items", result.is_ok(), timer.elapsed_seconds());
+    async fn restore_grant(&self, grant: AccessGrant) -> DatabaseResult<()> {
+        let mut grants = self.grants.write().map_err(|_| DatabaseError::LockError)?;
+        let item_grants = grants.entry(grant.item_id.clone()).or_default();
+        item_grants.retain(|existing| existing.id != grant.id && existing.grantee != grant.grantee);
+        item_grants.push(grant);
+        Ok(())
+    }
 
-        if result.is_ok() {
-            track_item_updated();
+    async fn revoke(&self, item_id: &str, grant_id: &str) -> DatabaseResult<()> {
+        let mut grants = self.grants.write().map_err(|_| DatabaseError::LockError)?;
+        let item_grants = grants.get_mut(item_id).ok_or(DatabaseError::NotFound)?;
+        let before = item_grants.len();
+        item_grants.retain(|grant| grant.id != grant_id);
+        if item_grants.len() == before {
+            return Err(DatabaseError::NotFound);
         }
+        Ok(())
+    }
 
-        result
+    async fn revoke_all(&self, item_id: &str) -> DatabaseResult<()> {
+        let mut grants = self.grants.write().map_err(|_| DatabaseError::LockError)?;
+        grants.remove(item_id);
+        Ok(())
     }
+}
 
-    async fn delete(&self, id: &str) -> DatabaseResult<()> {
-        let timer = Timer::new();
-        let result = self.inner.delete(id).await;
-        track_database_query("delete", "items", result.is_ok(), timer.elapsed_seconds());
+/// In-memory implementation of the tenant repository
+pub struct InMemoryTenantRepository {
+    tenants: Arc<RwLock<BTreeMap<String, Tenant>>>,
+    clock: SharedClock,
+}
 
-        if result.is_ok() {
-            track_item_deleted();
+impl InMemoryTenantRepository {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            tenants: Arc::new(RwLock::new(BTreeMap::new())),
+            clock: system_clock(),
         }
+    }
 
-        result
+    /// Stamp updates with `clock` instead of the system clock
+    #[must_use]
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
     }
+}
 
-    async fn list(&self, limit: usize, offset: usize) -> DatabaseResult<Vec<Item>> {
-        let timer = Timer::new();
-        let result = self.inner.list(limit, offset).await;
-        track_database_query("list", "items", result.is_ok(), timer.elapsed_seconds());
-        result
+impl Default for InMemoryTenantRepository {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    async fn count(&self) -> DatabaseResult<usize> {
-        let timer = Timer::new();
-        let result = self.inner.count().await;
-        track_database_query("count", "items", result.is_ok(), timer.elapsed_seconds());
-        result
+#[async_trait]
+impl TenantRepository for InMemoryTenantRepository {
+    async fn create(&self, tenant: Tenant) -> DatabaseResult<Tenant> {
+        let mut tenants = self.tenants.write().map_err(|_| DatabaseError::LockError)?;
+        if tenants.contains_key(&tenant.id) {
+            return Err(DatabaseError::Conflict(format!("Tenant {} already exists", tenant.id)));
+        }
+        tenants.insert(tenant.id.clone(), tenant.clone());
+        Ok(tenant)
     }
 
-    async fn health_check(&self) -> DatabaseResult<()> {
-        let timer = Timer::new();
-        let result = self.inner.health_check().await;
-        track_database_query("health_check", "database", result.is_ok(), timer.elapsed_seconds());
-        result
+    async fn get(&self, id: &str) -> DatabaseResult<Tenant> {
+        let tenants = self.tenants.read().map_err(|_| DatabaseError::LockError)?;
+        tenants.get(id).cloned().ok_or(DatabaseError::NotFound)
     }
-}
 
-/// Factory function to create the appropriate repository based on config
-#[must_use]
+    async fn list(&self) -> DatabaseResult<Vec<Tenant>> {
+        let tenants = self.tenants.read().map_err(|_| DatabaseError::LockError)?;
+        Ok(tenants.values().cloned().collect())
+    }
+
+    async fn update(&self, id: &str, request: UpdateTenantRequest) -> DatabaseResult<Tenant> {
+        let mut tenants = self.tenants.write().map_err(|_| DatabaseError::LockError)?;
+        let tenant = tenants.get_mut(id).ok_or(DatabaseError::NotFound)?;
+
+        if let Some(name) = request.name {
+            tenant.name = name;
+        }
+        if let Some(status) = request.status {
+            tenant.status = status;
+        }
+        if let Some(quotas) = request.quotas {
+            tenant.quotas = quotas;
+        }
+        tenant.updated_at = self.clock.now();
+        Ok(tenant.clone())
+    }
+
+    async fn delete(&self, id: &str) -> DatabaseResult<()> {
+        let mut tenants = self.tenants.write().map_err(|_| DatabaseError::LockError)?;
+        tenants
+            .remove(id)
+            .map(|_| ())
+            .ok_or(DatabaseError::NotFound)
+    }
+}
+
+/// Tenant and recipient a notification inbox belongs to
+type InboxKey = (Option<String>, String);
+
+/// In-memory implementation of the notification repository
+///
+/// Each recipient keeps at most `limit` notifications per tenant; the oldest
+/// are dropped beyond it.
+pub struct InMemoryNotificationRepository {
+    inboxes: RwLock<HashMap<InboxKey, VecDeque<Notification>>>,
+    limit: usize,
+    clock: SharedClock,
+}
+
+impl InMemoryNotificationRepository {
+    /// Most notifications kept per recipient unless configured otherwise
+    pub const DEFAULT_LIMIT: usize = 500;
+
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            inboxes: RwLock::new(HashMap::new()),
+            limit: Self::DEFAULT_LIMIT,
+            clock: system_clock(),
+        }
+    }
+
+    /// Keep at most `limit` notifications per recipient
+    #[must_use]
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit.max(1);
+        self
+    }
+
+    /// Stamp reads with `clock` instead of the system clock
+    #[must_use]
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn inbox_key(recipient: &str) -> InboxKey {
+        (current_tenant(), recipient.to_string())
+    }
+}
+
+impl Default for InMemoryNotificationRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl NotificationRepository for InMemoryNotificationRepository {
+    async fn add(&self, notifications: Vec<Notification>) -> DatabaseResult<usize> {
+        let mut inboxes = self.inboxes.write().map_err(|_| DatabaseError::LockError)?;
+        let mut added = 0;
+        for notification in notifications {
+            let key = (notification.tenant_id.clone(), notification.recipient.clone());
+            let inbox = inboxes.entry(key).or_default();
+            if inbox
+                .iter()
+                .any(|existing| existing.event_id == notification.event_id)
+            {
+                continue;
+            }
+            // Newest first
+            inbox.push_front(notification);
+            inbox.truncate(self.limit);
+            added += 1;
+        }
+        Ok(added)
+    }
+
+    async fn list(
+        &self,
+        recipient: &str,
+        unread_only: bool,
+        limit: usize,
+        offset: usize,
+    ) -> DatabaseResult<(Vec<Notification>, usize)> {
+        let inboxes = self.inboxes.read().map_err(|_| DatabaseError::LockError)?;
+        let Some(inbox) = inboxes.get(&Self::inbox_key(recipient)) else {
+            return Ok((Vec::new(), 0));
+        };
+        let matching = || {
+            inbox
+                .iter()
+                .filter(|notification| !unread_only || notification.read_at.is_none())
+        };
+        let page = matching().skip(offset).take(limit).cloned().collect();
+        Ok((page, matching().count()))
+    }
+
+    async fn unread_count(&self, recipient: &str) -> DatabaseResult<usize> {
+        let inboxes = self.inboxes.read().map_err(|_| DatabaseError::LockError)?;
+        Ok(inboxes.get(&Self::inbox_key(recipient)).map_or(0, |inbox| {
+            inbox
+                .iter()
+                .filter(|notification| notification.read_at.is_none())
+                .count()
+        }))
+    }
+
+    async fn mark_read(&self, recipient: &str, id: &str) -> DatabaseResult<Notification> {
+        let mut inboxes = self.inboxes.write().map_err(|_| DatabaseError::LockError)?;
+        let notification = inboxes
+            .get_mut(&Self::inbox_key(recipient))
+            .and_then(|inbox| inbox.iter_mut().find(|notification| notification.id == id))
+            .ok_or(DatabaseError::NotFound)?;
+        notification.read_at.get_or_insert_with(|| self.clock.now());
+        Ok(notification.clone())
+    }
+
+    async fn mark_all_read(&self, recipient: &str) -> DatabaseResult<usize> {
+        let mut inboxes = self.inboxes.write().map_err(|_| DatabaseError::LockError)?;
+        let now = self.clock.now();
+        let mut marked = 0;
+        if let Some(inbox) = inboxes.get_mut(&Self::inbox_key(recipient)) {
+            for notification in inbox.iter_mut().filter(|n| n.read_at.is_none()) {
+                notification.read_at = Some(now);
+                marked += 1;
+            }
+        }
+        Ok(marked)
+    }
+
+    async fn forget_principal(&self, principal: &str) -> DatabaseResult<(usize, usize)> {
+        let mut inboxes = self.inboxes.write().map_err(|_| DatabaseError::LockError)?;
+        let (mut removed, mut scrubbed) = (0, 0);
+        inboxes.retain(|(_, recipient), inbox| {
+            if recipient == principal {
+                removed += inbox.len();
+                return false;
+            }
+            for notification in inbox.iter_mut() {
+                if notification.actor.as_deref() == Some(principal) {
+                    notification.actor = None;
+                    scrubbed += 1;
+                }
+            }
+            true
+        });
+        Ok((removed, scrubbed))
+    }
+}
+
+/// In-memory implementation of the comment repository
+pub struct InMemoryCommentRepository {
+    /// Comments per item, in the order they were made
+    comments: RwLock<HashMap<String, Vec<Comment>>>,
+    clock: SharedClock,
+}
+
+impl InMemoryCommentRepository {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            comments: RwLock::new(HashMap::new()),
+            clock: system_clock(),
+        }
+    }
+
+    /// Stamp changes with `clock` instead of the system clock
+    #[must_use]
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Apply `change` to a comment of the current tenant that is not deleted
+    fn change(
+        &self,
+        item_id: &str,
+        id: &str,
+        change: impl FnOnce(&mut Comment, DateTime<Utc>),
+    ) -> DatabaseResult<Comment> {
+        let mut comments = self
+            .comments
+            .write()
+            .map_err(|_| DatabaseError::LockError)?;
+        let tenant = current_tenant();
+        let comment = comments
+            .get_mut(item_id)
+            .and_then(|comments| comments.iter_mut().find(|comment| comment.id == id))
+            .filter(|comment| comment.tenant_id == tenant && comment.deleted_at.is_none())
+            .ok_or(DatabaseError::NotFound)?;
+        change(comment, self.clock.now());
+        Ok(comment.clone())
+    }
+}
+
+impl Default for InMemoryCommentRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl CommentRepository for InMemoryCommentRepository {
+    async fn create(&self, comment: Comment) -> DatabaseResult<Comment> {
+        let mut comments = self
+            .comments
+            .write()
+            .map_err(|_| DatabaseError::LockError)?;
+        comments
+            .entry(comment.item_id.clone())
+            .or_default()
+            .push(comment.clone());
+        Ok(comment)
+    }
+
+    async fn get(&self, item_id: &str, id: &str) -> DatabaseResult<Comment> {
+        let comments = self.comments.read().map_err(|_| DatabaseError::LockError)?;
+        comments
+            .get(item_id)
+            .and_then(|comments| comments.iter().find(|comment| comment.id == id))
+            .filter(|comment| comment.tenant_id == current_tenant())
+            .cloned()
+            .ok_or(DatabaseError::NotFound)
+    }
+
+    async fn list(
+        &self,
+        item_id: &str,
+        include_deleted: bool,
+        limit: usize,
+        offset: usize,
+    ) -> DatabaseResult<(Vec<Comment>, usize)> {
+        let comments = self.comments.read().map_err(|_| DatabaseError::LockError)?;
+        let tenant = current_tenant();
+        let Some(comments) = comments.get(item_id) else {
+            return Ok((Vec::new(), 0));
+        };
+        let matching = || {
+            comments.iter().filter(|comment| {
+                comment.tenant_id == tenant && (include_deleted || comment.deleted_at.is_none())
+            })
+        };
+        let page = matching().skip(offset).take(limit).cloned().collect();
+        Ok((page, matching().count()))
+    }
+
+    async fn update(&self, item_id: &str, id: &str, body: String) -> DatabaseResult<Comment> {
+        self.change(item_id, id, |comment, now| {
+            comment.body = body;
+            comment.updated_at = now;
+        })
+    }
+
+    async fn delete(&self, item_id: &str, id: &str) -> DatabaseResult<Comment> {
+        self.change(item_id, id, |comment, now| comment.deleted_at = Some(now))
+    }
+
+    async fn forget_principal(&self, principal: &str) -> DatabaseResult<usize> {
+        let mut comments = self
+            .comments
+            .write()
+            .map_err(|_| DatabaseError::LockError)?;
+        let mut scrubbed = 0;
+        for comment in comments.values_mut().flatten() {
+            if comment.author.as_deref() == Some(principal) {
+                comment.author = None;
+                scrubbed += 1;
+            }
+        }
+        Ok(scrubbed)
+    }
+}
+
+/// Where an item is filed
+struct Placement {
+    collection_id: String,
+    /// Orders a collection's items by when they were filed
+    sequence: u64,
+    tenant_id: Option<String>,
+}
+
+/// Collections and the items filed in them
+#[derive(Default)]
+struct CollectionTree {
+    collections: HashMap<String, ItemCollection>,
+    placements: HashMap<String, Placement>,
+    sequence: u64,
+}
+
+impl CollectionTree {
+    /// A collection of the current tenant
+    fn get(&self, id: &str) -> DatabaseResult<&ItemCollection> {
+        self.collections
+            .get(id)
+            .filter(|collection| collection.tenant_id == current_tenant())
+            .ok_or(DatabaseError::NotFound)
+    }
+
+    /// Fail unless `name` is free among the current tenant's children of
+    /// `parent_id`
+    fn check_name(&self, parent_id: Option<&str>, name: &str) -> DatabaseResult<()> {
+        let tenant = current_tenant();
+        if self.collections.values().any(|existing| {
+            existing.tenant_id == tenant
+                && existing.parent_id.as_deref() == parent_id
+                && existing.name == name
+        }) {
+            return Err(DatabaseError::Conflict(format!(
+                "A collection named '{name}' already exists here"
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// In-memory implementation of the collection repository
+pub struct InMemoryCollectionRepository {
+    tree: RwLock<CollectionTree>,
+    clock: SharedClock,
+}
+
+impl InMemoryCollectionRepository {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            tree: RwLock::new(CollectionTree::default()),
+            clock: system_clock(),
+        }
+    }
+
+    /// Stamp changes with `clock` instead of the system clock
+    #[must_use]
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+}
+
+impl Default for InMemoryCollectionRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl CollectionRepository for InMemoryCollectionRepository {
+    async fn create(&self, collection: ItemCollection) -> DatabaseResult<ItemCollection> {
+        let mut tree = self.tree.write().map_err(|_| DatabaseError::LockError)?;
+        if let Some(parent_id) = &collection.parent_id {
+            tree.get(parent_id)?;
+        }
+        tree.check_name(collection.parent_id.as_deref(), &collection.name)?;
+        tree.collections
+            .insert(collection.id.clone(), collection.clone());
+        Ok(collection)
+    }
+
+    async fn get(&self, id: &str) -> DatabaseResult<ItemCollection> {
+        let tree = self.tree.read().map_err(|_| DatabaseError::LockError)?;
+        tree.get(id).cloned()
+    }
+
+    async fn children(&self, parent_id: Option<&str>) -> DatabaseResult<Vec<ItemCollection>> {
+        let tree = self.tree.read().map_err(|_| DatabaseError::LockError)?;
+        let tenant = current_tenant();
+        let mut children: Vec<ItemCollection> = tree
+            .collections
+            .values()
+            .filter(|collection| {
+                collection.tenant_id == tenant && collection.parent_id.as_deref() == parent_id
+            })
+            .cloned()
+            .collect();
+        children.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(children)
+    }
+
+    async fn descendants(
+        &self,
+        id: &str,
+        limit: usize,
+        offset: usize,
+    ) -> DatabaseResult<(Vec<ItemCollection>, usize)> {
+        let tree = self.tree.read().map_err(|_| DatabaseError::LockError)?;
+        let root = tree.get(id)?;
+        let mut descendants: Vec<&ItemCollection> = tree
+            .collections
+            .values()
+            .filter(|collection| {
+                collection.tenant_id == root.tenant_id && collection.path.contains(&root.id)
+            })
+            .collect();
+        descendants.sort_by(|a, b| (a.path.len(), &a.name).cmp(&(b.path.len(), &b.name)));
+        let total = descendants.len();
+        let page = descendants
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .cloned()
+            .collect();
+        Ok((page, total))
+    }
+
+    async fn move_to(&self, id: &str, parent_id: Option<&str>) -> DatabaseResult<ItemCollection> {
+        let mut tree = self.tree.write().map_err(|_| DatabaseError::LockError)?;
+        let moved = tree.get(id)?.clone();
+        let path = match parent_id {
+            Some(parent_id) => {
+                let parent = tree.get(parent_id)?;
+                if parent.is_within(id) {
+                    return Err(DatabaseError::Conflict(
+                        "A collection cannot be moved into itself".to_string(),
+                    ));
+                }
+                parent.lineage()
+            }
+            None => Vec::new(),
+        };
+        if moved.parent_id.as_deref() == parent_id {
+            return Ok(moved);
+        }
+        tree.check_name(parent_id, &moved.name)?;
+
+        // Below the moved collection, paths keep what follows it
+        let now = self.clock.now();
+        let depth = moved.path.len();
+        for collection in tree.collections.values_mut() {
+            if collection.id == id {
+                collection.parent_id = parent_id.map(str::to_string);
+                collection.path = path.clone();
+                collection.updated_at = now;
+            } else if collection.tenant_id == moved.tenant_id
+                && collection.path.iter().any(|ancestor| ancestor == id)
+            {
+                let below = collection.path.split_off(depth);
+                collection.path = path.iter().cloned().chain(below).collect();
+            }
+        }
+        tree.get(id).cloned()
+    }
+
+    async fn delete(&self, id: &str) -> DatabaseResult<()> {
+        let mut tree = self.tree.write().map_err(|_| DatabaseError::LockError)?;
+        tree.get(id)?;
+        if tree
+            .collections
+            .values()
+            .any(|collection| collection.parent_id.as_deref() == Some(id))
+            || tree
+                .placements
+                .values()
+                .any(|placement| placement.collection_id == id)
+        {
+            return Err(DatabaseError::Conflict(
+                "Only empty collections can be deleted".to_string(),
+            ));
+        }
+        tree.collections.remove(id);
+        Ok(())
+    }
+
+    async fn file_item(&self, item_id: &str, collection_id: Option<&str>) -> DatabaseResult<()> {
+        let mut tree = self.tree.write().map_err(|_| DatabaseError::LockError)?;
+        let Some(collection_id) = collection_id else {
+            tree.placements.remove(item_id);
+            return Ok(());
+        };
+        tree.get(collection_id)?;
+        if tree
+            .placements
+            .get(item_id)
+            .is_some_and(|placement| placement.collection_id == collection_id)
+        {
+            return Ok(());
+        }
+        tree.sequence += 1;
+        let placement = Placement {
+            collection_id: collection_id.to_string(),
+            sequence: tree.sequence,
+            tenant_id: current_tenant(),
+        };
+        tree.placements.insert(item_id.to_string(), placement);
+        Ok(())
+    }
+
+    async fn item_collection(&self, item_id: &str) -> DatabaseResult<Option<String>> {
+        let tree = self.tree.read().map_err(|_| DatabaseError::LockError)?;
+        Ok(tree
+            .placements
+            .get(item_id)
+            .filter(|placement| placement.tenant_id == current_tenant())
+            .map(|placement| placement.collection_id.clone()))
+    }
+
+    async fn items(
+        &self,
+        id: &str,
+        recursive: bool,
+        limit: usize,
+        offset: usize,
+    ) -> DatabaseResult<(Vec<String>, usize)> {
+        let tree = self.tree.read().map_err(|_| DatabaseError::LockError)?;
+        let root = tree.get(id)?;
+        let mut filed: Vec<(&String, &Placement)> = tree
+            .placements
+            .iter()
+            .filter(|(_, placement)| {
+                placement.tenant_id == root.tenant_id
+                    && (placement.collection_id == root.id
+                        || recursive
+                            && tree
+                                .collections
+                                .get(&placement.collection_id)
+                                .is_some_and(|collection| collection.path.contains(&root.id)))
+            })
+            .collect();
+        filed.sort_by_key(|(_, placement)| placement.sequence);
+        let total = filed.len();
+        let page = filed
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(|(item_id, _)| item_id.clone())
+            .collect();
+        Ok((page, total))
+    }
+
+    async fn forget_item(&self, item_id: &str) -> DatabaseResult<()> {
+        let mut tree = self.tree.write().map_err(|_| DatabaseError::LockError)?;
+        tree.placements.remove(item_id);
+        Ok(())
+    }
+
+    async fn reassign_owner(&self, principal: &str, owner: &str) -> DatabaseResult<usize> {
+        let mut tree = self.tree.write().map_err(|_| DatabaseError::LockError)?;
+        let mut reassigned = 0;
+        for collection in tree.collections.values_mut() {
+            if collection.owner.as_deref() == Some(principal) {
+                collection.owner = Some(owner.to_string());
+                reassigned += 1;
+            }
+        }
+        Ok(reassigned)
+    }
+}
+
+/// In-memory implementation of the saved search repository
+#[derive(Default)]
+pub struct InMemorySavedSearchRepository {
+    searches: RwLock<HashMap<String, SavedSearch>>,
+}
+
+impl InMemorySavedSearchRepository {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl SavedSearchRepository for InMemorySavedSearchRepository {
+    async fn create(&self, search: SavedSearch) -> DatabaseResult<SavedSearch> {
+        let mut searches = self
+            .searches
+            .write()
+            .map_err(|_| DatabaseError::LockError)?;
+        if searches.values().any(|existing| {
+            existing.tenant_id == search.tenant_id
+                && existing.owner == search.owner
+                && existing.name == search.name
+        }) {
+            return Err(DatabaseError::Conflict(format!(
+                "A saved search named '{}' already exists",
+                search.name
+            )));
+        }
+        searches.insert(search.id.clone(), search.clone());
+        Ok(search)
+    }
+
+    async fn get(&self, id: &str) -> DatabaseResult<SavedSearch> {
+        let searches = self.searches.read().map_err(|_| DatabaseError::LockError)?;
+        searches
+            .get(id)
+            .filter(|search| search.tenant_id == current_tenant())
+            .cloned()
+            .ok_or(DatabaseError::NotFound)
+    }
+
+    async fn list(&self, owner: &str) -> DatabaseResult<Vec<SavedSearch>> {
+        let searches = self.searches.read().map_err(|_| DatabaseError::LockError)?;
+        let tenant = current_tenant();
+        let mut owned: Vec<_> = searches
+            .values()
+            .filter(|search| search.tenant_id == tenant && search.owner == owner)
+            .cloned()
+            .collect();
+        owned.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(owned)
+    }
+
+    async fn delete(&self, id: &str) -> DatabaseResult<()> {
+        let mut searches = self
+            .searches
+            .write()
+            .map_err(|_| DatabaseError::LockError)?;
+        match searches.get(id) {
+            Some(search) if search.tenant_id == current_tenant() => {
+                searches.remove(id);
+                Ok(())
+            }
+            _ => Err(DatabaseError::NotFound),
+        }
+    }
+
+    async fn subscriptions(&self) -> DatabaseResult<Vec<SavedSearch>> {
+        let searches = self.searches.read().map_err(|_| DatabaseError::LockError)?;
+        let tenant = current_tenant();
+        Ok(searches
+            .values()
+            .filter(|search| search.tenant_id == tenant && search.is_subscribed())
+            .cloned()
+            .collect())
+    }
+
+    async fn forget_principal(&self, principal: &str) -> DatabaseResult<usize> {
+        let mut searches = self
+            .searches
+            .write()
+            .map_err(|_| DatabaseError::LockError)?;
+        let before = searches.len();
+        searches.retain(|_, search| search.owner != principal);
+        Ok(before - searches.len())
+    }
+}
+
+/// In-memory implementation of the lease repository, for a single process
+pub struct InMemoryLeaseRepository {
+    /// Holder and expiry of each lease
+    leases: Mutex<HashMap<String, (String, DateTime<Utc>)>>,
+    clock: SharedClock,
+}
+
+impl InMemoryLeaseRepository {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            leases: Mutex::new(HashMap::new()),
+            clock: system_clock(),
+        }
+    }
+
+    /// Expire leases by `clock` instead of the system clock
+    #[must_use]
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+}
+
+impl Default for InMemoryLeaseRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl LeaseRepository for InMemoryLeaseRepository {
+    async fn try_acquire(&self, name: &str, holder: &str, ttl: Duration) -> DatabaseResult<bool> {
+        let mut leases = self.leases.lock().map_err(|_| DatabaseError::LockError)?;
+        let now = self.clock.now();
+        if let Some((current, expires_at)) = leases.get(name) {
+            if current != holder && *expires_at > now {
+                return Ok(false);
+            }
+        }
+        let ttl = chrono::Duration::from_std(ttl)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        leases.insert(name.to_string(), (holder.to_string(), now + ttl));
+        Ok(true)
+    }
+
+    async fn release(&self, name: &str, holder: &str) -> DatabaseResult<()> {
+        let mut leases = self.leases.lock().map_err(|_| DatabaseError::LockError)?;
+        if leases
+            .get(name)
+            .is_some_and(|(current, _)| current == holder)
+        {
+            leases.remove(name);
+        }
+        Ok(())
+    }
+}
+
+/// In-memory implementation of the item lock repository, for a single process
+pub struct InMemoryItemLockRepository {
+    locks: Mutex<HashMap<String, ItemLock>>,
+    clock: SharedClock,
+}
+
+impl InMemoryItemLockRepository {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            locks: Mutex::new(HashMap::new()),
+            clock: system_clock(),
+        }
+    }
+
+    /// Expire locks by `clock` instead of the system clock
+    #[must_use]
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+}
+
+impl Default for InMemoryItemLockRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl ItemLockRepository for InMemoryItemLockRepository {
+    async fn acquire(&self, mut lock: ItemLock) -> DatabaseResult<ItemLock> {
+        let mut locks = self.locks.lock().map_err(|_| DatabaseError::LockError)?;
+        let now = self.clock.now();
+        if let Some(current) = locks.get(&lock.item_id).filter(|c| c.expires_at > now) {
+            if current.owner != lock.owner {
+                return Ok(current.clone());
+            }
+            // Renewing keeps the time the lock was first taken
+            lock.acquired_at = current.acquired_at;
+        }
+        locks.insert(lock.item_id.clone(), lock.clone());
+        Ok(lock)
+    }
+
+    async fn get(&self, item_id: &str) -> DatabaseResult<Option<ItemLock>> {
+        let locks = self.locks.lock().map_err(|_| DatabaseError::LockError)?;
+        let now = self.clock.now();
+        Ok(locks
+            .get(item_id)
+            .filter(|lock| lock.expires_at > now)
+            .cloned())
+    }
+
+    async fn release(&self, item_id: &str) -> DatabaseResult<()> {
+        let mut locks = self.locks.lock().map_err(|_| DatabaseError::LockError)?;
+        locks.remove(item_id);
+        Ok(())
+    }
+
+    async fn release_owned_by(&self, owner: &str) -> DatabaseResult<usize> {
+        let mut locks = self.locks.lock().map_err(|_| DatabaseError::LockError)?;
+        let before = locks.len();
+        locks.retain(|_, lock| lock.owner != owner);
+        Ok(before - locks.len())
+    }
+}
+
+/// In-memory implementation of the change request repository
+pub struct InMemoryChangeRequestRepository {
+    /// Change requests in the order they were made
+    changes: RwLock<Vec<ChangeRequest>>,
+    clock: SharedClock,
+}
+
+impl InMemoryChangeRequestRepository {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            changes: RwLock::new(Vec::new()),
+            clock: system_clock(),
+        }
+    }
+
+    /// Stamp resolutions with `clock` instead of the system clock
+    #[must_use]
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Apply `change` to a pending request of the current tenant
+    fn pending(
+        &self,
+        id: &str,
+        change: impl FnOnce(&mut ChangeRequest, DateTime<Utc>) -> DatabaseResult<()>,
+    ) -> DatabaseResult<ChangeRequest> {
+        let mut changes = self.changes.write().map_err(|_| DatabaseError::LockError)?;
+        let tenant = current_tenant();
+        let request = changes
+            .iter_mut()
+            .find(|request| request.id == id && request.tenant_id == tenant)
+            .ok_or(DatabaseError::NotFound)?;
+        if request.status != ChangeStatus::Pending {
+            return Err(DatabaseError::Conflict(format!(
+                "Change request {id} is already {}",
+                request.status.as_str()
+            )));
+        }
+        change(request, self.clock.now())?;
+        Ok(request.clone())
+    }
+}
+
+impl Default for InMemoryChangeRequestRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl ChangeRequestRepository for InMemoryChangeRequestRepository {
+    async fn create(&self, change: ChangeRequest) -> DatabaseResult<ChangeRequest> {
+        let mut changes = self.changes.write().map_err(|_| DatabaseError::LockError)?;
+        changes.push(change.clone());
+        Ok(change)
+    }
+
+    async fn get(&self, id: &str) -> DatabaseResult<ChangeRequest> {
+        let changes = self.changes.read().map_err(|_| DatabaseError::LockError)?;
+        let tenant = current_tenant();
+        changes
+            .iter()
+            .find(|request| request.id == id && request.tenant_id == tenant)
+            .cloned()
+            .ok_or(DatabaseError::NotFound)
+    }
+
+    async fn list(&self, status: Option<ChangeStatus>) -> DatabaseResult<Vec<ChangeRequest>> {
+        let changes = self.changes.read().map_err(|_| DatabaseError::LockError)?;
+        let tenant = current_tenant();
+        Ok(changes
+            .iter()
+            .rev()
+            .filter(|request| request.tenant_id == tenant)
+            .filter(|request| status.is_none_or(|status| request.status == status))
+            .cloned()
+            .collect())
+    }
+
+    async fn approve(&self, id: &str, approval: Approval) -> DatabaseResult<ChangeRequest> {
+        self.pending(id, |request, _| {
+            if request.approved_by(&approval.principal) {
+                return Err(DatabaseError::Conflict(format!(
+                    "{} already approved change request {id}",
+                    approval.principal
+                )));
+            }
+            request.approvals.push(approval);
+            Ok(())
+        })
+    }
+
+    async fn resolve(
+        &self,
+        id: &str,
+        status: ChangeStatus,
+        resolved_by: &str,
+        reason: Option<String>,
+    ) -> DatabaseResult<ChangeRequest> {
+        self.pending(id, |request, now| {
+            request.status = status;
+            request.resolved_by = Some(resolved_by.to_string());
+            request.reason = reason;
+            request.resolved_at = Some(now);
+            Ok(())
+        })
+    }
+
+    async fn reassign_principal(&self, principal: &str, pseudonym: &str) -> DatabaseResult<usize> {
+        let mut changes = self.changes.write().map_err(|_| DatabaseError::LockError)?;
+        let mut reassigned = 0;
+        for request in changes.iter_mut() {
+            let mut named = false;
+            if request.requested_by == principal {
+                request.requested_by = pseudonym.to_string();
+                named = true;
+            }
+            for approval in &mut request.approvals {
+                if approval.principal == principal {
+                    approval.principal = pseudonym.to_string();
+                    named = true;
+                }
+            }
+            if request.resolved_by.as_deref() == Some(principal) {
+                request.resolved_by = Some(pseudonym.to_string());
+                named = true;
+            }
+            reassigned += usize::from(named);
+        }
+        Ok(reassigned)
+    }
+}
+
+/// Future implementation for Convex database
+pub struct ConvexRepository {
+    #[allow(dead_code)]
+    deployment_url: String,
+    /// Table name prefix isolating one tenant's data (schema-per-tenant)
+    #[allow(dead_code)]
+    namespace: Option<String>,
+}
+
+impl ConvexRepository {
+    pub fn new(deployment_url: String) -> Self {
+        Self {
+            deployment_url,
+            namespace: None,
+        }
+    }
+
+    /// Store data in tables prefixed with `namespace`
+    #[must_use]
+    pub fn with_namespace(mut self, namespace: String) -> Self {
+        self.namespace = Some(namespace);
+        self
+    }
+}
+
+#[async_trait]
+impl ItemRepository for ConvexRepository {
+    async fn create(
+        &self,
+        _request: CreateItemRequest,
+        _owner_id: Option<String>,
+    ) -> DatabaseResult<Item> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn get(&self, _id: &str) -> DatabaseResult<Item> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn get_by_slug(&self, _slug: &str) -> DatabaseResult<Item> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn get_by_external_id(
+        &self,
+        _source: Option<&str>,
+        _external_id: &str,
+    ) -> DatabaseResult<Item> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn update(
+        &self,
+        _id: &str,
+        _request: UpdateItemRequest,
+        _expected_version: Option<u64>,
+    ) -> DatabaseResult<Item> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn changed_since(&self, _id: &str, _version: u64) -> DatabaseResult<Vec<ItemField>> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn delete(&self, _id: &str) -> DatabaseResult<()> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn list(&self, _filter: &ItemFilter, _page: &Page) -> DatabaseResult<Vec<Arc<Item>>> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn count(&self, _filter: &ItemFilter) -> DatabaseResult<usize> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn health_check(&self) -> DatabaseResult<()> {
+        // TODO: Implement actual health check
+        Ok(())
+    }
+
+    async fn reassign_owner(
+        &self,
+        _owner_id: &str,
+        _replacement: &str,
+    ) -> DatabaseResult<(usize, usize)> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn restore(&self, _item: Item) -> DatabaseResult<Item> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn provision_tenant(&self, _tenant: &str) -> DatabaseResult<()> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn drop_tenant(&self, _tenant: &str) -> DatabaseResult<Vec<String>> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn pending_events(&self, _limit: usize) -> DatabaseResult<Vec<OutboxEvent>> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn event_history(
+        &self,
+        _filter: &EventFilter,
+        _after: u64,
+        _limit: usize,
+    ) -> DatabaseResult<Vec<OutboxEvent>> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn pending_event_count(&self) -> DatabaseResult<usize> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn mark_events_published(&self, _sequences: &[u64]) -> DatabaseResult<()> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn mark_event_failed(&self, _sequence: u64, _error: &str) -> DatabaseResult<()> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn purge_published_events(
+        &self,
+        _cutoff: DateTime<Utc>,
+        _dry_run: bool,
+    ) -> DatabaseResult<usize> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn event_histogram(
+        &self,
+        _filter: &ItemFilter,
+        _range: &AnalyticsRange,
+    ) -> DatabaseResult<Vec<AnalyticsBucket>> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+}
+
+/// Future implementation of access control lists for Convex
+pub struct ConvexAccessRepository {
+    #[allow(dead_code)]
+    deployment_url: String,
+}
+
+impl ConvexAccessRepository {
+    pub fn new(deployment_url: String) -> Self {
+        Self { deployment_url }
+    }
+}
+
+#[async_trait]
+impl AccessRepository for ConvexAccessRepository {
+    async fn grant(
+        &self,
+        _item_id: &str,
+        _grantee: Grantee,
+        _permission: Permission,
+        _granted_by: Option<String>,
+    ) -> DatabaseResult<AccessGrant> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn list_grants(&self, _item_id: &str) -> DatabaseResult<Vec<AccessGrant>> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn revoke(&self, _item_id: &str, _grant_id: &str) -> DatabaseResult<()> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn revoke_all(&self, _item_id: &str) -> DatabaseResult<()> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn forget_principal(&self, _principal: &str) -> DatabaseResult<(usize, usize)> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn restore_grant(&self, _grant: AccessGrant) -> DatabaseResult<()> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+}
+
+/// Future implementation of tenant records for Convex
+pub struct ConvexTenantRepository {
+    #[allow(dead_code)]
+    deployment_url: String,
+}
+
+impl ConvexTenantRepository {
+    pub fn new(deployment_url: String) -> Self {
+        Self { deployment_url }
+    }
+}
+
+#[async_trait]
+impl TenantRepository for ConvexTenantRepository {
+    async fn create(&self, _tenant: Tenant) -> DatabaseResult<Tenant> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn get(&self, _id: &str) -> DatabaseResult<Tenant> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn list(&self) -> DatabaseResult<Vec<Tenant>> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn update(&self, _id: &str, _request: UpdateTenantRequest) -> DatabaseResult<Tenant> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn delete(&self, _id: &str) -> DatabaseResult<()> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+}
+
+/// Future implementation of comments for Convex
+pub struct ConvexCommentRepository {
+    #[allow(dead_code)]
+    deployment_url: String,
+}
+
+impl ConvexCommentRepository {
+    pub fn new(deployment_url: String) -> Self {
+        Self { deployment_url }
+    }
+}
+
+#[async_trait]
+impl CommentRepository for ConvexCommentRepository {
+    async fn create(&self, _comment: Comment) -> DatabaseResult<Comment> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn get(&self, _item_id: &str, _id: &str) -> DatabaseResult<Comment> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn list(
+        &self,
+        _item_id: &str,
+        _include_deleted: bool,
+        _limit: usize,
+        _offset: usize,
+    ) -> DatabaseResult<(Vec<Comment>, usize)> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn update(&self, _item_id: &str, _id: &str, _body: String) -> DatabaseResult<Comment> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn delete(&self, _item_id: &str, _id: &str) -> DatabaseResult<Comment> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn forget_principal(&self, _principal: &str) -> DatabaseResult<usize> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+}
+
+/// Future implementation of collections for Convex
+pub struct ConvexCollectionRepository {
+    #[allow(dead_code)]
+    deployment_url: String,
+}
+
+impl ConvexCollectionRepository {
+    pub fn new(deployment_url: String) -> Self {
+        Self { deployment_url }
+    }
+}
+
+#[async_trait]
+impl CollectionRepository for ConvexCollectionRepository {
+    async fn create(&self, _collection: ItemCollection) -> DatabaseResult<ItemCollection> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn get(&self, _id: &str) -> DatabaseResult<ItemCollection> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn children(&self, _parent_id: Option<&str>) -> DatabaseResult<Vec<ItemCollection>> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn descendants(
+        &self,
+        _id: &str,
+        _limit: usize,
+        _offset: usize,
+    ) -> DatabaseResult<(Vec<ItemCollection>, usize)> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn move_to(&self, _id: &str, _parent_id: Option<&str>) -> DatabaseResult<ItemCollection> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn delete(&self, _id: &str) -> DatabaseResult<()> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn file_item(&self, _item_id: &str, _collection_id: Option<&str>) -> DatabaseResult<()> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn item_collection(&self, _item_id: &str) -> DatabaseResult<Option<String>> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn items(
+        &self,
+        _id: &str,
+        _recursive: bool,
+        _limit: usize,
+        _offset: usize,
+    ) -> DatabaseResult<(Vec<String>, usize)> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn forget_item(&self, _item_id: &str) -> DatabaseResult<()> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn reassign_owner(&self, _principal: &str, _owner: &str) -> DatabaseResult<usize> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+}
+
+/// Future implementation of saved searches for Convex
+pub struct ConvexSavedSearchRepository {
+    #[allow(dead_code)]
+    deployment_url: String,
+}
+
+impl ConvexSavedSearchRepository {
+    pub fn new(deployment_url: String) -> Self {
+        Self { deployment_url }
+    }
+}
+
+#[async_trait]
+impl SavedSearchRepository for ConvexSavedSearchRepository {
+    async fn create(&self, _search: SavedSearch) -> DatabaseResult<SavedSearch> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn get(&self, _id: &str) -> DatabaseResult<SavedSearch> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn list(&self, _owner: &str) -> DatabaseResult<Vec<SavedSearch>> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn delete(&self, _id: &str) -> DatabaseResult<()> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn subscriptions(&self) -> DatabaseResult<Vec<SavedSearch>> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn forget_principal(&self, _principal: &str) -> DatabaseResult<usize> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+}
+
+/// Future implementation of leases for Convex
+pub struct ConvexLeaseRepository {
+    #[allow(dead_code)]
+    deployment_url: String,
+}
+
+impl ConvexLeaseRepository {
+    pub fn new(deployment_url: String) -> Self {
+        Self { deployment_url }
+    }
+}
+
+#[async_trait]
+impl LeaseRepository for ConvexLeaseRepository {
+    async fn try_acquire(
+        &self,
+        _name: &str,
+        _holder: &str,
+        _ttl: Duration,
+    ) -> DatabaseResult<bool> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn release(&self, _name: &str, _holder: &str) -> DatabaseResult<()> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+}
+
+/// Future implementation of item locks for Convex
+pub struct ConvexItemLockRepository {
+    #[allow(dead_code)]
+    deployment_url: String,
+}
+
+impl ConvexItemLockRepository {
+    pub fn new(deployment_url: String) -> Self {
+        Self { deployment_url }
+    }
+}
+
+#[async_trait]
+impl ItemLockRepository for ConvexItemLockRepository {
+    async fn acquire(&self, _lock: ItemLock) -> DatabaseResult<ItemLock> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn get(&self, _item_id: &str) -> DatabaseResult<Option<ItemLock>> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn release(&self, _item_id: &str) -> DatabaseResult<()> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn release_owned_by(&self, _owner: &str) -> DatabaseResult<usize> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+}
+
+/// Future implementation of change requests for Convex
+pub struct ConvexChangeRequestRepository {
+    #[allow(dead_code)]
+    deployment_url: String,
+}
+
+impl ConvexChangeRequestRepository {
+    pub fn new(deployment_url: String) -> Self {
+        Self { deployment_url }
+    }
+}
+
+#[async_trait]
+impl ChangeRequestRepository for ConvexChangeRequestRepository {
+    async fn create(&self, _change: ChangeRequest) -> DatabaseResult<ChangeRequest> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn get(&self, _id: &str) -> DatabaseResult<ChangeRequest> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn list(&self, _status: Option<ChangeStatus>) -> DatabaseResult<Vec<ChangeRequest>> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn approve(&self, _id: &str, _approval: Approval) -> DatabaseResult<ChangeRequest> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn resolve(
+        &self,
+        _id: &str,
+        _status: ChangeStatus,
+        _resolved_by: &str,
+        _reason: Option<String>,
+    ) -> DatabaseResult<ChangeRequest> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn reassign_principal(
+        &self,
+        _principal: &str,
+        _pseudonym: &str,
+    ) -> DatabaseResult<usize> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+}
+
+/// Future implementation of the notification inbox for Convex
+pub struct ConvexNotificationRepository {
+    #[allow(dead_code)]
+    deployment_url: String,
+}
+
+impl ConvexNotificationRepository {
+    pub fn new(deployment_url: String) -> Self {
+        Self { deployment_url }
+    }
+}
+
+#[async_trait]
+impl NotificationRepository for ConvexNotificationRepository {
+    async fn add(&self, _notifications: Vec<Notification>) -> DatabaseResult<usize> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn list(
+        &self,
+        _recipient: &str,
+        _unread_only: bool,
+        _limit: usize,
+        _offset: usize,
+    ) -> DatabaseResult<(Vec<Notification>, usize)> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn unread_count(&self, _recipient: &str) -> DatabaseResult<usize> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn mark_read(&self, _recipient: &str, _id: &str) -> DatabaseResult<Notification> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn mark_all_read(&self, _recipient: &str) -> DatabaseResult<usize> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+
+    async fn forget_principal(&self, _principal: &str) -> DatabaseResult<(usize, usize)> {
+        Err(DatabaseError::QueryError("Convex not implemented yet".to_string()))
+    }
+}
+
+/// Metrics wrapper for `ItemRepository`
+pub struct MetricsRepository {
+    inner: Arc<dyn ItemRepository>,
+    /// Calls slower than this are logged
+    slow_query: Option<Duration>,
+}
+
+impl MetricsRepository {
+    pub fn new(inner: Arc<dyn ItemRepository>) -> Self {
+        DATABASE_CONNECTIONS.inc();
+        Self {
+            inner,
+            slow_query: None,
+        }
+    }
+
+    /// Log calls that take longer than `threshold`
+    #[must_use]
+    pub fn with_slow_query_threshold(mut self, threshold: Option<Duration>) -> Self {
+        self.slow_query = threshold;
+        self
+    }
+
+    fn track(&self, operation: &str, repository: &str, success: bool, duration: Duration) {
+        track_database_query(operation, repository, success, duration.as_secs_f64());
+        record_query(operation, repository, duration, self.slow_query);
+    }
+
+    /// Refuse to start an operation for a request whose caller has given up
+    fn check_deadline(&self, operation: &str) -> DatabaseResult<()> {
+        if RequestContext::current().is_expired() {
+            track_cancelled_operation(operation, "items");
+            return Err(DatabaseError::DeadlineExceeded);
+        }
+        Ok(())
+    }
+
+    /// Run a read, cancelling it when the request's deadline passes
+    ///
+    /// Writes are only checked before they start: one cancelled halfway
+    /// would leave the caller unsure whether it happened.
+    async fn read<T>(
+        &self,
+        operation: &str,
+        call: impl Future<Output = DatabaseResult<T>>,
+    ) -> DatabaseResult<T> {
+        self.check_deadline(operation)?;
+        let Some(remaining) = RequestContext::current().remaining() else {
+            return call.await;
+        };
+        tokio::time::timeout(remaining, call)
+            .await
+            .unwrap_or_else(|_| {
+                track_cancelled_operation(operation, "items");
+                Err(DatabaseError::DeadlineExceeded)
+            })
+    }
+}
+
+impl Drop for MetricsRepository {
+    fn drop(&mut self) {
+        DATABASE_CONNECTIONS.dec();
+    }
+}
+
+#[async_trait]
+impl ItemRepository for MetricsRepository {
+    async fn create(
+        &self,
+        request: CreateItemRequest,
+        owner_id: Option<String>,
+    ) -> DatabaseResult<Item> {
+        self.check_deadline("create")?;
+        let timer = Timer::new();
+        let result = self.inner.create(request, owner_id).await;
+        self.track("create", "items", result.is_ok(), timer.elapsed());
+
+        if result.is_ok() {
+            track_item_created();
+        }
+
+        result
+    }
+
+    async fn get(&self, id: &str) -> DatabaseResult<Item> {
+        let timer = Timer::new();
+        let result = self.read("get", self.inner.get(id)).await;
+        self.track("get", "items", result.is_ok(), timer.elapsed());
+        result
+    }
+
+    async fn get_by_slug(&self, slug: &str) -> DatabaseResult<Item> {
+        let timer = Timer::new();
+        let result = self.read("get_by_slug", self.inner.get_by_slug(slug)).await;
+        self.track("get_by_slug", "items", result.is_ok(), timer.elapsed());
+        result
+    }
+
+    async fn get_by_external_id(
+        &self,
+        source: Option<&str>,
+        external_id: &str,
+    ) -> DatabaseResult<Item> {
+        let timer = Timer::new();
+        let result = self
+            .read("get_by_external_id", self.inner.get_by_external_id(source, external_id))
+            .await;
+        self.track("get_by_external_id", "items", result.is_ok(), timer.elapsed());
+        result
+    }
+
+    async fn update(
+        &self,
+        id: &str,
+        request: UpdateItemRequest,
+        expected_version: Option<u64>,
+    ) -> DatabaseResult<Item> {
+        self.check_deadline("update")?;
+        let timer = Timer::new();
+        let result = self.inner.update(id, request, expected_version).await;
+        self.track("update", "items", result.is_ok(), timer.elapsed());
+
+        if result.is_ok() {
+            track_item_updated();
+        }
+
+        result
+    }
+
+    async fn changed_since(&self, id: &str, version: u64) -> DatabaseResult<Vec<ItemField>> {
+        let timer = Timer::new();
+        let result = self
+            .read("changed_since", self.inner.changed_since(id, version))
+            .await;
+        self.track("changed_since", "items", result.is_ok(), timer.elapsed());
+        result
+    }
+
+    async fn delete(&self, id: &str) -> DatabaseResult<()> {
+        self.check_deadline("delete")?;
+        let timer = Timer::new();
+        let result = self.inner.delete(id).await;
+        self.track("delete", "items", result.is_ok(), timer.elapsed());
+
+        if result.is_ok() {
+            track_item_deleted();
+        }
+
+        result
+    }
+
+    async fn list(&self, filter: &ItemFilter, page: &Page) -> DatabaseResult<Vec<Arc<Item>>> {
+        let timer = Timer::new();
+        let result = self.read("list", self.inner.list(filter, page)).await;
+        self.track("list", "items", result.is_ok(), timer.elapsed());
+        result
+    }
+
+    async fn count(&self, filter: &ItemFilter) -> DatabaseResult<usize> {
+        let timer = Timer::new();
+        let result = self.read("count", self.inner.count(filter)).await;
+        self.track("count", "items", result.is_ok(), timer.elapsed());
+        result
+    }
+
+    async fn list_with_total(
+        &self,
+        filter: &ItemFilter,
+        page: &Page,
+    ) -> DatabaseResult<(Vec<Arc<Item>>, usize)> {
+        // Tracked as the list it answers, so dashboards see one operation
+        let timer = Timer::new();
+        let result = self
+            .read("list", self.inner.list_with_total(filter, page))
+            .await;
+        self.track("list", "items", result.is_ok(), timer.elapsed());
+        result
+    }
+
+    async fn health_check(&self) -> DatabaseResult<()> {
+        let timer = Timer::new();
+        let result = self.inner.health_check().await;
+        self.track("health_check", "database", result.is_ok(), timer.elapsed());
+        result
+    }
+
+    async fn reassign_owner(
+        &self,
+        owner_id: &str,
+        replacement: &str,
+    ) -> DatabaseResult<(usize, usize)> {
+        let timer = Timer::new();
+        let result = self.inner.reassign_owner(owner_id, replacement).await;
+        self.track("reassign_owner", "items", result.is_ok(), timer.elapsed());
+        result
+    }
+
+    async fn restore(&self, item: Item) -> DatabaseResult<Item> {
+        let timer = Timer::new();
+        let result = self.inner.restore(item).await;
+        self.track("restore", "items", result.is_ok(), timer.elapsed());
+        result
+    }
+
+    async fn provision_tenant(&self, tenant: &str) -> DatabaseResult<()> {
+        let timer = Timer::new();
+        let result = self.inner.provision_tenant(tenant).await;
+        self.track("provision_tenant", "items", result.is_ok(), timer.elapsed());
+        result
+    }
+
+    async fn drop_tenant(&self, tenant: &str) -> DatabaseResult<Vec<String>> {
+        let timer = Timer::new();
+        let result = self.inner.drop_tenant(tenant).await;
+        self.track("drop_tenant", "items", result.is_ok(), timer.elapsed());
+        result
+    }
+
+    async fn pending_events(&self, limit: usize) -> DatabaseResult<Vec<OutboxEvent>> {
+        let timer = Timer::new();
+        let result = self.inner.pending_events(limit).await;
+        self.track("pending_events", "outbox", result.is_ok(), timer.elapsed());
+        result
+    }
+
+    async fn event_history(
+        &self,
+        filter: &EventFilter,
+        after: u64,
+        limit: usize,
+    ) -> DatabaseResult<Vec<OutboxEvent>> {
+        let timer = Timer::new();
+        let result = self
+            .read("event_history", self.inner.event_history(filter, after, limit))
+            .await;
+        self.track("event_history", "outbox", result.is_ok(), timer.elapsed());
+        result
+    }
+
+    async fn pending_event_count(&self) -> DatabaseResult<usize> {
+        let timer = Timer::new();
+        let result = self.inner.pending_event_count().await;
+        self.track("pending_event_count", "outbox", result.is_ok(), timer.elapsed());
+        result
+    }
+
+    async fn mark_events_published(&self, sequences: &[u64]) -> DatabaseResult<()> {
+        let timer = Timer::new();
+        let result = self.inner.mark_events_published(sequences).await;
+        self.track("mark_published", "outbox", result.is_ok(), timer.elapsed());
+        result
+    }
+
+    async fn mark_event_failed(&self, sequence: u64, error: &str) -> DatabaseResult<()> {
+        let timer = Timer::new();
+        let result = self.inner.mark_event_failed(sequence, error).await;
+        self.track("mark_failed", "outbox", result.is_ok(), timer.elapsed());
+        result
+    }
+
+    async fn purge_published_events(
+        &self,
+        cutoff: DateTime<Utc>,
+        dry_run: bool,
+    ) -> DatabaseResult<usize> {
+        let timer = Timer::new();
+        let result = self.inner.purge_published_events(cutoff, dry_run).await;
+        self.track("purge_published", "outbox", result.is_ok(), timer.elapsed());
+        result
+    }
+
+    async fn event_histogram(
+        &self,
+        filter: &ItemFilter,
+        range: &AnalyticsRange,
+    ) -> DatabaseResult<Vec<AnalyticsBucket>> {
+        let timer = Timer::new();
+        let result = self
+            .read("event_histogram", self.inner.event_histogram(filter, range))
+            .await;
+        self.track("event_histogram", "outbox", result.is_ok(), timer.elapsed());
+        result
+    }
+}
+
+/// Creates the repository holding one tenant's data
+pub type TenantRepositoryFactory = Box<dyn Fn(&str) -> Arc<dyn ItemRepository> + Send + Sync>;
+
+/// Routes each call to the repository of the current tenant, for schema- and
+/// database-per-tenant isolation
+///
+/// Tenant repositories are created on first use. Calls made outside a tenant
+/// scope go to the default repository. Outbox operations are deployment-wide
+/// and fan out to every repository, so tenant repositories must share one
+/// outbox sequence counter.
+pub struct TenantRoutingRepository {
+    default: Arc<dyn ItemRepository>,
+    tenants: RwLock<HashMap<String, Arc<dyn ItemRepository>>>,
+    factory: TenantRepositoryFactory,
+}
+
+impl TenantRoutingRepository {
+    pub fn new(default: Arc<dyn ItemRepository>, factory: TenantRepositoryFactory) -> Self {
+        Self {
+            default,
+            tenants: RwLock::new(HashMap::new()),
+            factory,
+        }
+    }
+
+    /// Repository for the current tenant
+    fn current(&self) -> DatabaseResult<Arc<dyn ItemRepository>> {
+        match current_tenant() {
+            Some(tenant) => self.for_tenant(&tenant),
+            None => Ok(self.default.clone()),
+        }
+    }
+
+    /// Repository holding `tenant`'s data, created on first use
+    fn for_tenant(&self, tenant: &str) -> DatabaseResult<Arc<dyn ItemRepository>> {
+        if let Some(repo) = self
+            .tenants
+            .read()
+            .map_err(|_| DatabaseError::LockError)?
+            .get(tenant)
+        {
+            return Ok(repo.clone());
+        }
+
+        let mut tenants = self.tenants.write().map_err(|_| DatabaseError::LockError)?;
+        Ok(tenants
+            .entry(tenant.to_string())
+            .or_insert_with_key(|tenant| (self.factory)(tenant))
+            .clone())
+    }
+
+    /// Every repository, default first
+    fn all(&self) -> DatabaseResult<Vec<Arc<dyn ItemRepository>>> {
+        let tenants = self.tenants.read().map_err(|_| DatabaseError::LockError)?;
+        Ok(std::iter::once(self.default.clone())
+            .chain(tenants.values().cloned())
+            .collect())
+    }
+}
+
+#[async_trait]
+impl ItemRepository for TenantRoutingRepository {
+    async fn create(
+        &self,
+        request: CreateItemRequest,
+        owner_id: Option<String>,
+    ) -> DatabaseResult<Item> {
+        self.current()?.create(request, owner_id).await
+    }
+
+    async fn get(&self, id: &str) -> DatabaseResult<Item> {
+        self.current()?.get(id).await
+    }
+
+    async fn get_by_slug(&self, slug: &str) -> DatabaseResult<Item> {
+        self.current()?.get_by_slug(slug).await
+    }
+
+    async fn get_by_external_id(
+        &self,
+        source: Option<&str>,
+        external_id: &str,
+    ) -> DatabaseResult<Item> {
+        self.current()?
+            .get_by_external_id(source, external_id)
+            .await
+    }
+
+    async fn update(
+        &self,
+        id: &str,
+        request: UpdateItemRequest,
+        expected_version: Option<u64>,
+    ) -> DatabaseResult<Item> {
+        self.current()?.update(id, request, expected_version).await
+    }
+
+    async fn changed_since(&self, id: &str, version: u64) -> DatabaseResult<Vec<ItemField>> {
+        self.current()?.changed_since(id, version).await
+    }
+
+    async fn delete(&self, id: &str) -> DatabaseResult<()> {
+        self.current()?.delete(id).await
+    }
+
+    async fn list(&self, filter: &ItemFilter, page: &Page) -> DatabaseResult<Vec<Arc<Item>>> {
+        self.current()?.list(filter, page).await
+    }
+
+    async fn count(&self, filter: &ItemFilter) -> DatabaseResult<usize> {
+        self.current()?.count(filter).await
+    }
+
+    async fn list_with_total(
+        &self,
+        filter: &ItemFilter,
+        page: &Page,
+    ) -> DatabaseResult<(Vec<Arc<Item>>, usize)> {
+        self.current()?.list_with_total(filter, page).await
+    }
+
+    async fn health_check(&self) -> DatabaseResult<()> {
+        for repo in self.all()? {
+            repo.health_check().await?;
+        }
+        Ok(())
+    }
+
+    async fn reassign_owner(
+        &self,
+        owner_id: &str,
+        replacement: &str,
+    ) -> DatabaseResult<(usize, usize)> {
+        self.current()?.reassign_owner(owner_id, replacement).await
+    }
+
+    async fn restore(&self, item: Item) -> DatabaseResult<Item> {
+        self.current()?.restore(item).await
+    }
+
+    async fn provision_tenant(&self, tenant: &str) -> DatabaseResult<()> {
+        self.for_tenant(tenant)?.provision_tenant(tenant).await
+    }
+
+    async fn drop_tenant(&self, tenant: &str) -> DatabaseResult<Vec<String>> {
+        // The tenant's repository stays registered: its outbox may still hold
+        // events that have not been published
+        self.for_tenant(tenant)?.drop_tenant(tenant).await
+    }
+
+    async fn pending_events(&self, limit: usize) -> DatabaseResult<Vec<OutboxEvent>> {
+        let mut events = Vec::new();
+        for repo in self.all()? {
+            events.extend(repo.pending_events(limit).await?);
+        }
+        events.sort_by_key(|event| event.sequence);
+        events.truncate(limit);
+        Ok(events)
+    }
+
+    async fn event_history(
+        &self,
+        filter: &EventFilter,
+        after: u64,
+        limit: usize,
+    ) -> DatabaseResult<Vec<OutboxEvent>> {
+        let mut events = Vec::new();
+        for repo in self.all()? {
+            events.extend(repo.event_history(filter, after, limit).await?);
+        }
+        events.sort_by_key(|event| event.sequence);
+        events.truncate(limit);
+        Ok(events)
+    }
+
+    async fn pending_event_count(&self) -> DatabaseResult<usize> {
+        let mut count = 0;
+        for repo in self.all()? {
+            count += repo.pending_event_count().await?;
+        }
+        Ok(count)
+    }
+
+    async fn mark_events_published(&self, sequences: &[u64]) -> DatabaseResult<()> {
+        // Each repository ignores sequences it does not hold
+        for repo in self.all()? {
+            repo.mark_events_published(sequences).await?;
+        }
+        Ok(())
+    }
+
+    async fn mark_event_failed(&self, sequence: u64, error: &str) -> DatabaseResult<()> {
+        for repo in self.all()? {
+            match repo.mark_event_failed(sequence, error).await {
+                Err(DatabaseError::NotFound) => continue,
+                result => return result,
+            }
+        }
+        Err(DatabaseError::NotFound)
+    }
+
+    async fn purge_published_events(
+        &self,
+        cutoff: DateTime<Utc>,
+        dry_run: bool,
+    ) -> DatabaseResult<usize> {
+        let mut purged = 0;
+        for repo in self.all()? {
+            purged += repo.purge_published_events(cutoff, dry_run).await?;
+        }
+        Ok(purged)
+    }
+
+    async fn event_histogram(
+        &self,
+        filter: &ItemFilter,
+        range: &AnalyticsRange,
+    ) -> DatabaseResult<Vec<AnalyticsBucket>> {
+        self.current()?.event_histogram(filter, range).await
+    }
+}
+
+/// Factory function to create the appropriate repository based on config
+#[must_use]
 pub fn create_repository(config: &Config) -> Arc<dyn ItemRepository> {
-    let base_repo: Arc<dyn ItemRepository> = match config.database.db_type.as_str() {
-        "memory" => Arc::new(InMemoryRepository::new()),
+    create_dual_write_repository(config).0
+}
+
+/// Create the configured repository, along with the dual-write decorator in
+/// it when a secondary backend is configured, for reporting on divergences
+#[must_use]
+pub fn create_dual_write_repository(
+    config: &Config,
+) -> (Arc<dyn ItemRepository>, Option<Arc<DualWriteRepository>>) {
+    // Shared by every repository that calls the configured deployment
+    let limiter = (config.database.db_type == "convex")
+        .then(|| AdaptiveLimiter::from_config(&config.database.concurrency, "convex"))
+        .flatten();
+    let mut base_repo = create_store(config, limiter);
+
+    // Apply every write to the secondary backend as well
+    let mut dual_write = None;
+    if let Some(secondary_type) = &config.database.secondary_type {
+        let secondary_config = secondary(config, secondary_type, &config.database.secondary_url);
+        let repo = Arc::new(DualWriteRepository::new(
+            base_repo,
+            create_store(&secondary_config, None),
+            secondary_type,
+        ));
+        base_repo = repo.clone();
+        dual_write = Some(repo);
+    }
+
+    // Mirror sampled reads to the shadow backend, laid out like the primary
+    if let Some(shadow_type) = &config.database.shadow_type {
+        let shadow =
+            create_store(&secondary(config, shadow_type, &config.database.shadow_url), None);
+        base_repo = Arc::new(ShadowRepository::new(
+            base_repo,
+            shadow,
+            config.database.shadow_percent,
+            shadow_type,
+        ));
+    }
+
+    // Wrap with metrics tracking
+    let slow_query = config
+        .logging
+        .slow_query_threshold_ms
+        .map(Duration::from_millis);
+    let repo = Arc::new(MetricsRepository::new(base_repo).with_slow_query_threshold(slow_query));
+    (repo, dual_write)
+}
+
+/// `config` for a second backend, laid out like the primary
+fn secondary(config: &Config, db_type: &str, url: &Option<String>) -> Config {
+    let mut config = config.clone();
+    config.database.db_type = db_type.to_string();
+    config.database.convex_deployment_url = url.clone();
+    config.database.convex_replica_url = None;
+    config.database.hedge_after_ms = None;
+    config
+}
+
+/// Create the configured backend, routing each tenant to a store of its
+/// own unless tenants share one
+fn create_store(config: &Config, limiter: Option<Arc<AdaptiveLimiter>>) -> Arc<dyn ItemRepository> {
+    let tenancy = &config.tenancy;
+    if !tenancy.enabled || tenancy.isolation == TenantIsolation::Shared {
+        return create_backend(config, None, Arc::default(), limiter);
+    }
+
+    let sequence = Arc::new(AtomicU64::new(0));
+    let default = create_backend(config, None, sequence.clone(), limiter.clone());
+    let config = config.clone();
+    Arc::new(TenantRoutingRepository::new(
+        default,
+        Box::new(move |tenant| {
+            create_backend(&config, Some(tenant), sequence.clone(), limiter.clone())
+        }),
+    ))
+}
+
+/// Create the storage backend, scoped to `tenant` for per-tenant isolation
+///
+/// Calls to a remote deployment go through `limiter`; a tenant with a
+/// database of its own gets a limiter of its own.
+fn create_backend(
+    config: &Config,
+    tenant: Option<&str>,
+    sequence: Arc<AtomicU64>,
+    limiter: Option<Arc<AdaptiveLimiter>>,
+) -> Arc<dyn ItemRepository> {
+    match config.database.db_type.as_str() {
+        "memory" => Arc::new(InMemoryRepository::with_sequence(sequence)),
+        "convex" => {
+            let url = config
+                .database
+                .convex_deployment_url
+                .as_ref()
+                .expect("Convex deployment URL required");
+            let replica = config.database.convex_replica_url.clone();
+            let (backend, url, replica, namespace, limiter) =
+                match (tenant, config.tenancy.isolation) {
+                    (Some(tenant), TenantIsolation::Schema) => (
+                        "convex".to_string(),
+                        url.clone(),
+                        replica,
+                        Some(format!("tenant_{tenant}")),
+                        limiter,
+                    ),
+                    (Some(tenant), TenantIsolation::Database) => {
+                        let template = config
+                            .tenancy
+                            .database_url_template
+                            .as_ref()
+                            .expect("Tenant database URL template required");
+                        let backend = format!("convex:{tenant}");
+                        let limiter =
+                            AdaptiveLimiter::from_config(&config.database.concurrency, &backend);
+                        // A tenant database has no replica
+                        (backend, template.replace("{tenant}", tenant), None, None, limiter)
+                    }
+                    _ => ("convex".to_string(), url.clone(), replica, None, limiter),
+                };
+
+            // Hedges to a replica count against the deployment's limit, so
+            // hedging cannot double the load on a struggling backend
+            let connect = |url: String| -> Arc<dyn ItemRepository> {
+                let mut repo = ConvexRepository::new(url);
+                if let Some(namespace) = &namespace {
+                    repo = repo.with_namespace(namespace.clone());
+                }
+                match &limiter {
+                    Some(limiter) => {
+                        Arc::new(LimitedRepository::new(Arc::new(repo), limiter.clone()))
+                    }
+                    None => Arc::new(repo),
+                }
+            };
+            let primary = connect(url);
+            match config.database.hedge_after_ms {
+                Some(ms) => {
+                    let secondary = replica.map_or_else(|| primary.clone(), connect);
+                    Arc::new(HedgedRepository::new(
+                        primary,
+                        secondary,
+                        Duration::from_millis(ms),
+                        &backend,
+                    ))
+                }
+                None => primary,
+            }
+        }
+        _ => panic!("Unknown database type: {}", config.database.db_type),
+    }
+}
+
+/// Factory function to create the access control repository matching the item backend
+#[must_use]
+pub fn create_access_repository(config: &Config) -> Arc<dyn AccessRepository> {
+    match config.database.db_type.as_str() {
+        "memory" => Arc::new(InMemoryAccessRepository::new()),
         "convex" => {
             let url = config
                 .database
                 .convex_deployment_url
                 .as_ref()
                 .expect("Convex deployment URL required");
-            Arc::new(ConvexRepository::new(url.clone()))
+            Arc::new(ConvexAccessRepository::new(url.clone()))
         }
         _ => panic!("Unknown database type: {}", config.database.db_type),
-    };
+    }
+}
 
-    // Wrap with metrics tracking
-    Arc::new(MetricsRepository::new(base_repo))
+/// Factory function to create the tenant repository matching the item backend
+#[must_use]
+pub fn create_tenant_repository(config: &Config) -> Arc<dyn TenantRepository> {
+    match config.database.db_type.as_str() {
+        "memory" => Arc::new(InMemoryTenantRepository::new()),
+        "convex" => {
+            let url = config
+                .database
+                .convex_deployment_url
+                .as_ref()
+                .expect("Convex deployment URL required");
+            Arc::new(ConvexTenantRepository::new(url.clone()))
+        }
+        _ => panic!("Unknown database type: {}", config.database.db_type),
+    }
+}
+
+/// Factory function to create the notification repository matching the item backend
+#[must_use]
+pub fn create_notification_repository(config: &Config) -> Arc<dyn NotificationRepository> {
+    match config.database.db_type.as_str() {
+        "memory" => Arc::new(
+            InMemoryNotificationRepository::new().with_limit(config.inbox.max_per_recipient),
+        ),
+        "convex" => {
+            let url = config
+                .database
+                .convex_deployment_url
+                .as_ref()
+                .expect("Convex deployment URL required");
+            Arc::new(ConvexNotificationRepository::new(url.clone()))
+        }
+        _ => panic!("Unknown database type: {}", config.database.db_type),
+    }
+}
+
+/// Factory function to create the comment repository matching the item backend
+#[must_use]
+pub fn create_comment_repository(config: &Config) -> Arc<dyn CommentRepository> {
+    match config.database.db_type.as_str() {
+        "memory" => Arc::new(InMemoryCommentRepository::new()),
+        "convex" => {
+            let url = config
+                .database
+                .convex_deployment_url
+                .as_ref()
+                .expect("Convex deployment URL required");
+            Arc::new(ConvexCommentRepository::new(url.clone()))
+        }
+        _ => panic!("Unknown database type: {}", config.database.db_type),
+    }
+}
+
+/// Factory function to create the collection repository matching the item backend
+#[must_use]
+pub fn create_collection_repository(config: &Config) -> Arc<dyn CollectionRepository> {
+    match config.database.db_type.as_str() {
+        "memory" => Arc::new(InMemoryCollectionRepository::new()),
+        "convex" => {
+            let url = config
+                .database
+                .convex_deployment_url
+                .as_ref()
+                .expect("Convex deployment URL required");
+            Arc::new(ConvexCollectionRepository::new(url.clone()))
+        }
+        _ => panic!("Unknown database type: {}", config.database.db_type),
+    }
+}
+
+/// Factory function to create the saved search repository matching the item backend
+#[must_use]
+pub fn create_saved_search_repository(config: &Config) -> Arc<dyn SavedSearchRepository> {
+    match config.database.db_type.as_str() {
+        "memory" => Arc::new(InMemorySavedSearchRepository::new()),
+        "convex" => {
+            let url = config
+                .database
+                .convex_deployment_url
+                .as_ref()
+                .expect("Convex deployment URL required");
+            Arc::new(ConvexSavedSearchRepository::new(url.clone()))
+        }
+        _ => panic!("Unknown database type: {}", config.database.db_type),
+    }
+}
+
+/// Factory function to create the lease repository matching the item backend
+#[must_use]
+pub fn create_lease_repository(config: &Config) -> Arc<dyn LeaseRepository> {
+    match config.database.db_type.as_str() {
+        "memory" => Arc::new(InMemoryLeaseRepository::new()),
+        "convex" => {
+            let url = config
+                .database
+                .convex_deployment_url
+                .as_ref()
+                .expect("Convex deployment URL required");
+            Arc::new(ConvexLeaseRepository::new(url.clone()))
+        }
+        _ => panic!("Unknown database type: {}", config.database.db_type),
+    }
+}
+
+/// Factory function to create the item lock repository matching the item backend
+#[must_use]
+pub fn create_item_lock_repository(config: &Config) -> Arc<dyn ItemLockRepository> {
+    match config.database.db_type.as_str() {
+        "memory" => Arc::new(InMemoryItemLockRepository::new()),
+        "convex" => {
+            let url = config
+                .database
+                .convex_deployment_url
+                .as_ref()
+                .expect("Convex deployment URL required");
+            Arc::new(ConvexItemLockRepository::new(url.clone()))
+        }
+        _ => panic!("Unknown database type: {}", config.database.db_type),
+    }
+}
+
+/// Factory function to create the change request repository matching the item backend
+#[must_use]
+pub fn create_change_request_repository(config: &Config) -> Arc<dyn ChangeRequestRepository> {
+    match config.database.db_type.as_str() {
+        "memory" => Arc::new(InMemoryChangeRequestRepository::new()),
+        "convex" => {
+            let url = config
+                .database
+                .convex_deployment_url
+                .as_ref()
+                .expect("Convex deployment URL required");
+            Arc::new(ConvexChangeRequestRepository::new(url.clone()))
+        }
+        _ => panic!("Unknown database type: {}", config.database.db_type),
+    }
 }
 
 #[cfg(test)]
@@ -297,8 +3592,14 @@ mod tests {
         let create_req = CreateItemRequest {
             name: "Test Item".to_string(),
             description: Some("Test Description".to_string()),
+            metadata: None,
+            custom_fields: Default::default(),
+            status: Default::default(),
+            publish_at: None,
+            external_id: None,
+            source: None,
         };
-        let created = repo.create(create_req).await.unwrap();
+        let created = repo.create(create_req, None).await.unwrap();
         assert_eq!(created.name, "Test Item");
 
         // Get
@@ -309,17 +3610,26 @@ mod tests {
         let update_req = UpdateItemRequest {
             name: Some("Updated Name".to_string()),
             description: None,
+            metadata: None,
+            custom_fields: Default::default(),
+            regenerate_slug: false,
+            status: Default::default(),
+            publish_at: None,
         };
-        let updated = repo.update(&created.id, update_req).await.unwrap();
+        let updated = repo.update(&created.id, update_req, None).await.unwrap();
         assert_eq!(updated.name, "Updated Name");
         assert_eq!(updated.description, Some("Test Description".to_string()));
+        assert_eq!(updated.slug, "test-item");
 
         // List
-        let items = repo.list(10, 0).await.unwrap();
+        let items = repo
+            .list(&ItemFilter::default(), &Page::first(10).unwrap())
+            .await
+            .unwrap();
         assert_eq!(items.len(), 1);
 
         // Count
-        let count = repo.count().await.unwrap();
+        let count = repo.count(&ItemFilter::default()).await.unwrap();
         assert_eq!(count, 1);
 
         // Delete
@@ -328,9 +3638,741 @@ mod tests {
         assert!(matches!(result, Err(DatabaseError::NotFound)));
     }
 
+    #[tokio::test]
+    async fn test_slug_collisions_and_regeneration() {
+        let repo = InMemoryRepository::new();
+
+        let request = || CreateItemRequest {
+            name: "Widget".to_string(),
+            description: None,
+            metadata: None,
+            custom_fields: Default::default(),
+            status: Default::default(),
+            publish_at: None,
+            external_id: None,
+            source: None,
+        };
+        let first = repo.create(request(), None).await.unwrap();
+        let second = repo.create(request(), None).await.unwrap();
+        assert_eq!(first.slug, "widget");
+        assert_eq!(second.slug, "widget-2");
+
+        // Renaming keeps the slug unless regeneration is requested
+        let rename = |regenerate_slug| UpdateItemRequest {
+            name: Some("Gadget".to_string()),
+            description: None,
+            metadata: None,
+            custom_fields: Default::default(),
+            regenerate_slug,
+            status: Default::default(),
+            publish_at: None,
+        };
+        let renamed = repo.update(&second.id, rename(false), None).await.unwrap();
+        assert_eq!(renamed.slug, "widget-2");
+        let renamed = repo.update(&second.id, rename(true), None).await.unwrap();
+        assert_eq!(renamed.slug, "gadget");
+
+        // The released slug is free again and the new one resolves
+        assert!(matches!(repo.get_by_slug("widget-2").await, Err(DatabaseError::NotFound)));
+        assert_eq!(repo.get_by_slug("gadget").await.unwrap().id, second.id);
+
+        repo.delete(&first.id).await.unwrap();
+        assert!(matches!(repo.get_by_slug("widget").await, Err(DatabaseError::NotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_external_ids_are_unique_per_tenant() {
+        let repo = InMemoryRepository::new();
+        let request = || CreateItemRequest {
+            name: "Widget".to_string(),
+            external_id: Some("crm-1".to_string()),
+            ..CreateItemRequest::default()
+        };
+
+        let item = repo.create(request(), None).await.unwrap();
+        assert_eq!(item.external_id.as_deref(), Some("crm-1"));
+        assert!(matches!(repo.create(request(), None).await, Err(DatabaseError::Conflict(_))));
+        assert_eq!(repo.get_by_external_id(None, "crm-1").await.unwrap().id, item.id);
+
+        // Other tenants have their own external IDs
+        let other = in_tenant("acme", repo.create(request(), None))
+            .await
+            .unwrap();
+        assert_ne!(other.id, item.id);
+
+        repo.delete(&item.id).await.unwrap();
+        assert!(matches!(
+            repo.get_by_external_id(None, "crm-1").await,
+            Err(DatabaseError::NotFound)
+        ));
+        assert!(repo.create(request(), None).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_external_ids_are_unique_per_source() {
+        let repo = InMemoryRepository::new();
+        let request = |source: &str| CreateItemRequest {
+            name: "Widget".to_string(),
+            external_id: Some("4711".to_string()),
+            source: Some(source.to_string()),
+            ..CreateItemRequest::default()
+        };
+
+        let crm = repo.create(request("crm"), None).await.unwrap();
+        let erp = repo.create(request("erp"), None).await.unwrap();
+        assert!(matches!(
+            repo.create(request("crm"), None).await,
+            Err(DatabaseError::Conflict(_))
+        ));
+        assert_eq!(
+            repo.get_by_external_id(Some("crm"), "4711")
+                .await
+                .unwrap()
+                .id,
+            crm.id
+        );
+        assert_eq!(
+            repo.get_by_external_id(Some("erp"), "4711")
+                .await
+                .unwrap()
+                .id,
+            erp.id
+        );
+        assert!(matches!(
+            repo.get_by_external_id(None, "4711").await,
+            Err(DatabaseError::NotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_versions_and_changed_fields() {
+        let repo = InMemoryRepository::new();
+        let item = repo
+            .create(
+                CreateItemRequest {
+                    name: "Widget".to_string(),
+                    description: None,
+                    metadata: None,
+                    custom_fields: Default::default(),
+                    status: Default::default(),
+                    publish_at: None,
+                    external_id: None,
+                    source: None,
+                },
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(item.version, 1);
+
+        let describe = UpdateItemRequest {
+            description: Some("Blue".to_string()),
+            ..Default::default()
+        };
+        let updated = repo
+            .update(&item.id, describe.clone(), Some(1))
+            .await
+            .unwrap();
+        assert_eq!(updated.version, 2);
+        assert_eq!(repo.changed_since(&item.id, 1).await.unwrap(), vec![ItemField::Description]);
+        assert!(repo.changed_since(&item.id, 2).await.unwrap().is_empty());
+
+        // Stale versions are refused; setting a field to its value changes nothing
+        let stale = repo.update(&item.id, describe.clone(), Some(1)).await;
+        assert!(matches!(stale, Err(DatabaseError::VersionMismatch { expected: 1 })));
+        let updated = repo.update(&item.id, describe, None).await.unwrap();
+        assert_eq!(updated.version, 3);
+        assert!(repo.changed_since(&item.id, 2).await.unwrap().is_empty());
+
+        // Restores never reuse a version, and mark every field changed
+        let restored = repo.restore(item.clone()).await.unwrap();
+        assert_eq!(restored.version, 4);
+        assert_eq!(
+            repo.changed_since(&item.id, 3).await.unwrap(),
+            vec![
+                ItemField::Name,
+                ItemField::Description,
+                ItemField::Metadata,
+                ItemField::CustomFields
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_events_are_attributed_to_the_request() {
+        let repo = InMemoryRepository::new();
+        let context = RequestContext {
+            request_id: Some("req-1".to_string()),
+            principal: Some("alice".to_string()),
+            ..Default::default()
+        };
+        let request = CreateItemRequest {
+            name: "Widget".to_string(),
+            description: None,
+            metadata: None,
+            custom_fields: Default::default(),
+            status: Default::default(),
+            publish_at: None,
+            external_id: None,
+            source: None,
+        };
+        context.scope(repo.create(request, None)).await.unwrap();
+
+        let events = repo.pending_events(10).await.unwrap();
+        assert_eq!(events[0].actor.as_deref(), Some("alice"));
+        assert_eq!(events[0].request_id.as_deref(), Some("req-1"));
+
+        // Erasing the principal also removes it as an actor
+        assert_eq!(repo.reassign_owner("alice", "erased").await.unwrap(), (0, 1));
+        let events = repo.pending_events(10).await.unwrap();
+        assert_eq!(events[0].actor.as_deref(), Some("erased"));
+    }
+
+    #[tokio::test]
+    async fn test_expired_deadline_cancels_repository_calls() {
+        use crate::clock::{Clock, ManualClock};
+
+        let inner = Arc::new(InMemoryRepository::new());
+        let repo = MetricsRepository::new(inner.clone());
+        let clock = Arc::new(ManualClock::default());
+        let expired = RequestContext {
+            deadline: Some(clock.instant()),
+            ..Default::default()
+        }
+        .with_clock(clock.clone());
+        let cancelled = || {
+            crate::metrics::DATABASE_CANCELLED_OPERATIONS_COUNTER
+                .with_label_values(&["create", "items"])
+                .get()
+        };
+        let before = cancelled();
+
+        let request = CreateItemRequest {
+            name: "Widget".to_string(),
+            description: None,
+            metadata: None,
+            custom_fields: Default::default(),
+            status: Default::default(),
+            publish_at: None,
+            external_id: None,
+            source: None,
+        };
+        let result = expired.clone().scope(repo.create(request, None)).await;
+        assert!(matches!(result, Err(DatabaseError::DeadlineExceeded)));
+        assert_eq!(cancelled(), before + 1);
+        assert_eq!(inner.count(&ItemFilter::default()).await.unwrap(), 0);
+
+        let result = expired.scope(repo.count(&ItemFilter::default())).await;
+        assert!(matches!(result, Err(DatabaseError::DeadlineExceeded)));
+
+        // A deadline still ahead leaves calls alone
+        let ahead = RequestContext {
+            deadline: Some(clock.instant() + Duration::from_secs(60)),
+            ..Default::default()
+        }
+        .with_clock(clock);
+        let count = ahead.scope(repo.count(&ItemFilter::default())).await;
+        assert_eq!(count.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_list_is_ordered_by_creation_time() {
+        let repo = InMemoryRepository::new();
+        for i in 0..5 {
+            let request = CreateItemRequest {
+                name: format!("Item {i}"),
+                description: None,
+                metadata: None,
+                custom_fields: Default::default(),
+                status: Default::default(),
+                publish_at: None,
+                external_id: None,
+                source: None,
+            };
+            repo.create(request, None).await.unwrap();
+        }
+
+        // A restored item takes its place by creation time, not restore time
+        let mut oldest = repo
+            .list(&ItemFilter::default(), &Page::offset(1, 4).unwrap())
+            .await
+            .unwrap()[0]
+            .as_ref()
+            .clone();
+        oldest.created_at -= chrono::Duration::days(1);
+        repo.restore(oldest.clone()).await.unwrap();
+
+        let page = repo
+            .list(&ItemFilter::default(), &Page::first(1).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(page[0].id, oldest.id);
+        let all = repo
+            .list(&ItemFilter::default(), &Page::first(10).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(all.len(), 5);
+        assert!(all
+            .windows(2)
+            .all(|pair| pair[0].created_at <= pair[1].created_at));
+    }
+
+    #[tokio::test]
+    async fn test_list_with_total_matches_list_and_count() {
+        let repo = InMemoryRepository::new();
+        for i in 0..5 {
+            let request = CreateItemRequest {
+                name: format!("Item {i}"),
+                description: None,
+                metadata: None,
+                custom_fields: Default::default(),
+                status: Default::default(),
+                publish_at: None,
+                external_id: None,
+                source: None,
+            };
+            repo.create(request, None).await.unwrap();
+        }
+        let filter = ItemFilter::default().with_conditions(vec![Condition {
+            field: query::QueryField::Name.into(),
+            comparison: query::Comparison::Ne,
+            value: query::FieldValue::Text("Item 0".to_string()),
+        }]);
+        let first = repo.list(&filter, &Page::first(1).unwrap()).await.unwrap();
+        let pages = [
+            Page::offset(2, 1).unwrap(),
+            Page::after(2, crate::pagination::Cursor::after(&first[0])).unwrap(),
+            Page::first(2)
+                .unwrap()
+                .ordered_by(vec![query::Sort {
+                    field: query::QueryField::Name,
+                    descending: true,
+                }])
+                .unwrap(),
+        ];
+        for page in pages {
+            let ids = |items: Vec<Arc<Item>>| {
+                items.iter().map(|item| item.id.clone()).collect::<Vec<_>>()
+            };
+            let (items, total) = repo.list_with_total(&filter, &page).await.unwrap();
+            assert_eq!(ids(items), ids(repo.list(&filter, &page).await.unwrap()));
+            assert_eq!(total, 4);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_writers() {
+        const WRITERS: usize = 8;
+        const ITEMS_PER_WRITER: usize = 50;
+        let repo = Arc::new(InMemoryRepository::new());
+
+        let tasks: Vec<_> = (0..WRITERS)
+            .map(|_| {
+                let repo = repo.clone();
+                tokio::spawn(async move {
+                    for _ in 0..ITEMS_PER_WRITER {
+                        let request = CreateItemRequest {
+                            name: "Widget".to_string(),
+                            description: None,
+                            metadata: None,
+                            custom_fields: Default::default(),
+                            status: Default::default(),
+                            publish_at: None,
+                            external_id: None,
+                            source: None,
+                        };
+                        let item = repo.create(request, None).await.unwrap();
+                        let update = UpdateItemRequest {
+                            name: None,
+                            description: Some("Updated".to_string()),
+                            metadata: None,
+                            custom_fields: Default::default(),
+                            regenerate_slug: false,
+                            status: Default::default(),
+                            publish_at: None,
+                        };
+                        repo.update(&item.id, update, None).await.unwrap();
+                        // Readers page through the collection while it changes
+                        repo.list(&ItemFilter::default(), &Page::first(10).unwrap())
+                            .await
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        let total = WRITERS * ITEMS_PER_WRITER;
+        let filter = ItemFilter::default();
+        assert_eq!(repo.count(&filter).await.unwrap(), total);
+        let mut items = Vec::new();
+        let mut page = Page::first(crate::pagination::MAX_PAGE_LIMIT).unwrap();
+        loop {
+            let batch = repo.list(&filter, &page).await.unwrap();
+            let Some(last) = batch.last() else { break };
+            page = page.next_after(last);
+            items.extend(batch);
+        }
+        assert_eq!(items.len(), total);
+        let slugs: std::collections::HashSet<_> = items.iter().map(|item| &item.slug).collect();
+        assert_eq!(slugs.len(), total);
+
+        // Every mutation produced one event, each item's in mutation order
+        let events = repo.pending_events(2 * total + 1).await.unwrap();
+        assert_eq!(events.len(), 2 * total);
+        let mut created = HashMap::new();
+        for event in &events {
+            match event.event_type {
+                ItemEventType::Created => {
+                    created.insert(event.item.id.clone(), event.sequence);
+                }
+                ItemEventType::Updated => {
+                    assert!(created[&event.item.id] < event.sequence);
+                }
+                ItemEventType::Deleted => unreachable!(),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_owner_filter() {
+        let repo = InMemoryRepository::new();
+        let request = |name: &str| CreateItemRequest {
+            name: name.to_string(),
+            description: None,
+            metadata: None,
+            custom_fields: Default::default(),
+            status: Default::default(),
+            publish_at: None,
+            external_id: None,
+            source: None,
+        };
+
+        repo.create(request("Alice 1"), Some("alice".to_string()))
+            .await
+            .unwrap();
+        repo.create(request("Alice 2"), Some("alice".to_string()))
+            .await
+            .unwrap();
+        repo.create(request("Bob 1"), Some("bob".to_string()))
+            .await
+            .unwrap();
+        repo.create(request("Anonymous"), None).await.unwrap();
+
+        let alice = ItemFilter::owned_by("alice");
+        let items = repo.list(&alice, &Page::first(10).unwrap()).await.unwrap();
+        assert_eq!(items.len(), 2);
+        assert!(items
+            .iter()
+            .all(|item| item.owner_id.as_deref() == Some("alice")));
+        assert_eq!(repo.count(&alice).await.unwrap(), 2);
+        assert_eq!(repo.count(&ItemFilter::owned_by("bob")).await.unwrap(), 1);
+        assert_eq!(repo.count(&ItemFilter::default()).await.unwrap(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_access_grants() {
+        let access = InMemoryAccessRepository::new();
+        let bob = Grantee::Principal("bob".to_string());
+
+        access
+            .grant("item-1", bob.clone(), Permission::Read, None)
+            .await
+            .unwrap();
+        let upgraded = access
+            .grant("item-1", bob, Permission::Write, None)
+            .await
+            .unwrap();
+        access
+            .grant("item-1", Grantee::Role("editors".to_string()), Permission::Read, None)
+            .await
+            .unwrap();
+
+        // Re-granting to the same grantee replaces the previous grant
+        let grants = access.list_grants("item-1").await.unwrap();
+        assert_eq!(grants.len(), 2);
+        assert!(grants
+            .iter()
+            .any(|g| g.id == upgraded.id && g.permission == Permission::Write));
+
+        access.revoke("item-1", &upgraded.id).await.unwrap();
+        assert_eq!(access.list_grants("item-1").await.unwrap().len(), 1);
+        assert!(matches!(
+            access.revoke("item-1", &upgraded.id).await,
+            Err(DatabaseError::NotFound)
+        ));
+
+        access.revoke_all("item-1").await.unwrap();
+        assert!(access.list_grants("item-1").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cursor_pages_resume_after_last_item() {
+        let repo = InMemoryRepository::new();
+        for i in 0..5 {
+            let request = CreateItemRequest {
+                name: format!("Item {i}"),
+                description: None,
+                metadata: None,
+                custom_fields: Default::default(),
+                status: Default::default(),
+                publish_at: None,
+                external_id: None,
+                source: None,
+            };
+            repo.create(request, None).await.unwrap();
+        }
+        let filter = ItemFilter::default();
+        let first = repo.list(&filter, &Page::first(2).unwrap()).await.unwrap();
+
+        // Deleting an item already seen does not shift the next page
+        repo.delete(&first[0].id).await.unwrap();
+        let next = repo
+            .list(&filter, &Page::first(2).unwrap().next_after(&first[1]))
+            .await
+            .unwrap();
+        let by_offset = repo
+            .list(&filter, &Page::offset(2, 1).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(next.len(), 2);
+        assert!(next[0].created_at >= first[1].created_at);
+        assert_eq!(
+            next.iter().map(|item| &item.id).collect::<Vec<_>>(),
+            by_offset.iter().map(|item| &item.id).collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_timestamps_come_from_clock() {
+        use crate::clock::{Clock, ManualClock};
+
+        let clock = Arc::new(ManualClock::default());
+        let repo = InMemoryRepository::new().with_clock(clock.clone());
+        let created = repo.create(widget(), None).await.unwrap();
+        assert_eq!(created.created_at, clock.now());
+
+        clock.advance(Duration::from_secs(30));
+        let update = UpdateItemRequest {
+            name: Some("Gadget".to_string()),
+            description: None,
+            metadata: None,
+            custom_fields: Default::default(),
+            regenerate_slug: false,
+            status: Default::default(),
+            publish_at: None,
+        };
+        let updated = repo.update(&created.id, update, None).await.unwrap();
+        assert_eq!(updated.created_at, created.created_at);
+        assert_eq!(updated.updated_at - created.created_at, chrono::Duration::seconds(30));
+    }
+
+    #[tokio::test]
+    async fn test_leases_are_held_until_released_or_expired() {
+        use crate::clock::ManualClock;
+
+        let clock = Arc::new(ManualClock::default());
+        let repo = InMemoryLeaseRepository::new().with_clock(clock.clone());
+        let ttl = Duration::from_secs(15);
+
+        assert!(repo.try_acquire("scheduler", "a", ttl).await.unwrap());
+        assert!(!repo.try_acquire("scheduler", "b", ttl).await.unwrap());
+        assert!(repo.try_acquire("other", "b", ttl).await.unwrap());
+
+        // Renewal extends the lease
+        clock.advance(Duration::from_secs(10));
+        assert!(repo.try_acquire("scheduler", "a", ttl).await.unwrap());
+        clock.advance(Duration::from_secs(10));
+        assert!(!repo.try_acquire("scheduler", "b", ttl).await.unwrap());
+
+        clock.advance(Duration::from_secs(5));
+        assert!(repo.try_acquire("scheduler", "b", ttl).await.unwrap());
+
+        repo.release("scheduler", "a").await.unwrap();
+        assert!(!repo.try_acquire("scheduler", "a", ttl).await.unwrap());
+        repo.release("scheduler", "b").await.unwrap();
+        assert!(repo.try_acquire("scheduler", "a", ttl).await.unwrap());
+    }
+
     #[tokio::test]
     async fn test_health_check() {
         let repo = InMemoryRepository::new();
         assert!(repo.health_check().await.is_ok());
     }
+
+    async fn in_tenant<F: std::future::Future>(tenant: &str, future: F) -> F::Output {
+        crate::tenancy::with_tenant(tenant.to_string(), future).await
+    }
+
+    fn widget() -> CreateItemRequest {
+        CreateItemRequest {
+            name: "Widget".to_string(),
+            description: None,
+            metadata: None,
+            custom_fields: Default::default(),
+            status: Default::default(),
+            publish_at: None,
+            external_id: None,
+            source: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shared_schema_isolates_tenants() {
+        let repo = InMemoryRepository::new();
+
+        let acme = in_tenant("acme", repo.create(widget(), None))
+            .await
+            .unwrap();
+        let globex = in_tenant("globex", repo.create(widget(), None))
+            .await
+            .unwrap();
+
+        assert_eq!(acme.tenant_id.as_deref(), Some("acme"));
+        // Slugs are only unique within a tenant
+        assert_eq!(acme.slug, "widget");
+        assert_eq!(globex.slug, "widget");
+
+        assert!(in_tenant("acme", repo.get(&globex.id)).await.is_err());
+        assert!(in_tenant("acme", repo.delete(&globex.id)).await.is_err());
+        let by_slug = in_tenant("globex", repo.get_by_slug("widget"))
+            .await
+            .unwrap();
+        assert_eq!(by_slug.id, globex.id);
+
+        let filter = ItemFilter::default();
+        assert_eq!(in_tenant("acme", repo.count(&filter)).await.unwrap(), 1);
+        assert_eq!(repo.count(&filter).await.unwrap(), 0);
+        // The outbox spans all tenants
+        assert_eq!(repo.pending_event_count().await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_tenant_routing_repository() {
+        let sequence = Arc::new(AtomicU64::new(0));
+        let default: Arc<dyn ItemRepository> =
+            Arc::new(InMemoryRepository::with_sequence(sequence.clone()));
+        let created = Arc::new(RwLock::new(Vec::new()));
+        let log = created.clone();
+        let repo = TenantRoutingRepository::new(
+            default,
+            Box::new(move |tenant| {
+                log.write().unwrap().push(tenant.to_string());
+                Arc::new(InMemoryRepository::with_sequence(sequence.clone()))
+            }),
+        );
+
+        let acme = in_tenant("acme", repo.create(widget(), None))
+            .await
+            .unwrap();
+        in_tenant("acme", repo.create(widget(), None))
+            .await
+            .unwrap();
+        in_tenant("globex", repo.create(widget(), None))
+            .await
+            .unwrap();
+
+        assert_eq!(*created.read().unwrap(), vec!["acme", "globex"]);
+        assert!(in_tenant("globex", repo.get(&acme.id)).await.is_err());
+        assert_eq!(in_tenant("acme", repo.get(&acme.id)).await.unwrap().id, acme.id);
+
+        // Outbox events from all tenants come back in global sequence order
+        let pending = repo.pending_events(10).await.unwrap();
+        let sequences: Vec<u64> = pending.iter().map(|event| event.sequence).collect();
+        assert_eq!(sequences, vec![1, 2, 3]);
+
+        repo.mark_events_published(&sequences).await.unwrap();
+        assert_eq!(repo.pending_event_count().await.unwrap(), 0);
+        assert!(matches!(repo.mark_event_failed(99, "boom").await, Err(DatabaseError::NotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_drop_tenant_removes_only_its_items() {
+        let repo = InMemoryRepository::new();
+        let acme = in_tenant("acme", repo.create(widget(), None))
+            .await
+            .unwrap();
+        in_tenant("globex", repo.create(widget(), None))
+            .await
+            .unwrap();
+
+        let dropped = repo.drop_tenant("acme").await.unwrap();
+        assert_eq!(dropped, vec![acme.id]);
+
+        let filter = ItemFilter::default();
+        assert_eq!(in_tenant("acme", repo.count(&filter)).await.unwrap(), 0);
+        assert_eq!(in_tenant("globex", repo.count(&filter)).await.unwrap(), 1);
+        // The slug is free again once the tenant is dropped
+        let recreated = in_tenant("acme", repo.create(widget(), None))
+            .await
+            .unwrap();
+        assert_eq!(recreated.slug, "widget");
+    }
+
+    #[tokio::test]
+    async fn test_tenant_repository() {
+        let repo = InMemoryTenantRepository::new();
+        let tenant = Tenant {
+            id: "acme".to_string(),
+            name: "Acme".to_string(),
+            status: crate::models::TenantStatus::Active,
+            quotas: crate::models::TenantQuotas::default(),
+            api_key_hash: String::new(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        repo.create(tenant.clone()).await.unwrap();
+        assert!(matches!(repo.create(tenant).await, Err(DatabaseError::Conflict(_))));
+
+        let update = UpdateTenantRequest {
+            status: Some(crate::models::TenantStatus::Suspended),
+            ..UpdateTenantRequest::default()
+        };
+        let updated = repo.update("acme", update).await.unwrap();
+        assert_eq!(updated.status, crate::models::TenantStatus::Suspended);
+        assert_eq!(updated.name, "Acme");
+
+        repo.delete("acme").await.unwrap();
+        assert!(matches!(repo.get("acme").await, Err(DatabaseError::NotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_collections_move_with_their_subtree() {
+        let repo = InMemoryCollectionRepository::new();
+        let collection = |name: &str, parent: Option<&ItemCollection>| {
+            ItemCollection::new(name.to_string(), parent, None, Utc::now())
+        };
+        let archive = repo.create(collection("Archive", None)).await.unwrap();
+        let invoices = repo.create(collection("Invoices", None)).await.unwrap();
+        let year = repo
+            .create(collection("2024", Some(&invoices)))
+            .await
+            .unwrap();
+        let month = repo.create(collection("01", Some(&year))).await.unwrap();
+        repo.file_item("item-1", Some(&invoices.id)).await.unwrap();
+        repo.file_item("item-2", Some(&month.id)).await.unwrap();
+
+        // Nothing can be moved into its own subtree
+        assert!(matches!(
+            repo.move_to(&invoices.id, Some(&month.id)).await,
+            Err(DatabaseError::Conflict(_))
+        ));
+
+        let moved = repo.move_to(&invoices.id, Some(&archive.id)).await.unwrap();
+        assert_eq!(moved.path, vec![archive.id.clone()]);
+        let month = repo.get(&month.id).await.unwrap();
+        assert_eq!(month.path, vec![archive.id.clone(), invoices.id.clone(), year.id]);
+
+        let (descendants, total) = repo.descendants(&archive.id, 10, 0).await.unwrap();
+        assert_eq!(total, 3);
+        assert_eq!(descendants[0].id, invoices.id);
+        let (items, total) = repo.items(&archive.id, true, 1, 1).await.unwrap();
+        assert_eq!((items, total), (vec!["item-2".to_string()], 2));
+        assert_eq!(repo.items(&archive.id, false, 10, 0).await.unwrap().1, 0);
+
+        assert!(matches!(repo.delete(&archive.id).await, Err(DatabaseError::Conflict(_))));
+    }
 }
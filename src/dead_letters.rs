@@ -0,0 +1,295 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex, RwLock},
+};
+use tracing::warn;
+use utoipa::ToSchema;
+
+use crate::{
+    db::DatabaseError,
+    metrics::{track_dead_letter, DEAD_LETTER_QUEUE_DEPTH},
+};
+
+/// Most dead letters kept; the oldest are dropped beyond it
+pub const MAX_DEAD_LETTERS: usize = 10_000;
+
+/// Kind of work a dead letter holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeadLetterSource {
+    /// An event a webhook endpoint kept refusing
+    Webhook,
+    /// An outbox event a publisher kept refusing
+    Outbox,
+    /// A notification email the mail server kept refusing
+    Notification,
+}
+
+impl DeadLetterSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Webhook => "webhook",
+            Self::Outbox => "outbox",
+            Self::Notification => "notification",
+        }
+    }
+}
+
+/// Work that exhausted its retries, without its payload
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DeadLetterSummary {
+    #[schema(example = "3f2b8c1e-6d4a-4f7b-9e21-0c5d8a7b6e43")]
+    pub id: String,
+    pub source: DeadLetterSource,
+    /// What the work was
+    #[schema(example = "com.ferrous.item.created a1b2c3d4 to webhook orders")]
+    pub description: String,
+    /// Attempts made, requeues included
+    pub attempts: u32,
+    pub last_error: String,
+    pub dead_since: DateTime<Utc>,
+    pub last_attempt_at: DateTime<Utc>,
+}
+
+/// Work that exhausted its retries
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DeadLetter {
+    #[serde(flatten)]
+    pub summary: DeadLetterSummary,
+    /// The work, as its source needs it to try again
+    #[schema(value_type = Object)]
+    pub payload: Value,
+}
+
+/// Tries dead-lettered work of one source again
+#[async_trait]
+pub trait Retry: Send + Sync {
+    async fn retry(&self, payload: &Value) -> Result<(), String>;
+}
+
+/// Why a dead letter was not requeued
+#[derive(Debug, thiserror::Error)]
+pub enum RequeueError {
+    #[error("dead letter {0} not found")]
+    NotFound(String),
+    #[error("{0} work cannot be retried on this instance")]
+    Unsupported(&'static str),
+    #[error("retry failed: {0}")]
+    Failed(String),
+    #[error(transparent)]
+    Database(#[from] DatabaseError),
+}
+
+/// Work that exhausted its retries, kept for operators to inspect and requeue
+///
+/// Sources register how to retry their work; a requeued letter is tried once
+/// more right away and leaves the queue if that succeeds. The queue is held
+/// in memory, so it is per instance and empties on restart.
+#[derive(Default)]
+pub struct DeadLetterQueue {
+    letters: Mutex<VecDeque<DeadLetter>>,
+    retries: RwLock<HashMap<DeadLetterSource, Arc<dyn Retry>>>,
+}
+
+impl DeadLetterQueue {
+    /// Retry `source` work with `retry` when it is requeued
+    pub fn register(
+        &self,
+        source: DeadLetterSource,
+        retry: Arc<dyn Retry>,
+    ) -> Result<(), DatabaseError> {
+        self.retries
+            .write()
+            .map_err(|_| DatabaseError::LockError)?
+            .insert(source, retry);
+        Ok(())
+    }
+
+    /// Add work that exhausted its retries
+    pub fn push(
+        &self,
+        source: DeadLetterSource,
+        description: String,
+        attempts: u32,
+        last_error: String,
+        payload: Value,
+    ) -> Result<(), DatabaseError> {
+        warn!(
+            source = source.as_str(),
+            attempts, "Dead-lettered {}: {}", description, last_error
+        );
+        let now = Utc::now();
+        let mut letters = self.letters.lock().map_err(|_| DatabaseError::LockError)?;
+        letters.push_back(DeadLetter {
+            summary: DeadLetterSummary {
+                id: uuid::Uuid::new_v4().to_string(),
+                source,
+                description,
+                attempts,
+                last_error,
+                dead_since: now,
+                last_attempt_at: now,
+            },
+            payload,
+        });
+        if letters.len() > MAX_DEAD_LETTERS {
+            if let Some(dropped) = letters.pop_front() {
+                warn!("Dead letter queue is full; dropped {}", dropped.summary.description);
+            }
+        }
+        track_dead_letter(source.as_str());
+        DEAD_LETTER_QUEUE_DEPTH.set(i64::try_from(letters.len()).unwrap_or(i64::MAX));
+        Ok(())
+    }
+
+    /// Letters in the order they died, optionally only from `source`
+    pub fn list(
+        &self,
+        source: Option<DeadLetterSource>,
+    ) -> Result<Vec<DeadLetterSummary>, DatabaseError> {
+        Ok(self
+            .letters
+            .lock()
+            .map_err(|_| DatabaseError::LockError)?
+            .iter()
+            .filter(|letter| source.is_none_or(|source| letter.summary.source == source))
+            .map(|letter| letter.summary.clone())
+            .collect())
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<DeadLetter>, DatabaseError> {
+        Ok(self
+            .letters
+            .lock()
+            .map_err(|_| DatabaseError::LockError)?
+            .iter()
+            .find(|letter| letter.summary.id == id)
+            .cloned())
+    }
+
+    pub fn len(&self) -> Result<usize, DatabaseError> {
+        Ok(self
+            .letters
+            .lock()
+            .map_err(|_| DatabaseError::LockError)?
+            .len())
+    }
+
+    pub fn is_empty(&self) -> Result<bool, DatabaseError> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Drop a letter without retrying it
+    pub fn discard(&self, id: &str) -> Result<Option<DeadLetter>, DatabaseError> {
+        let mut letters = self.letters.lock().map_err(|_| DatabaseError::LockError)?;
+        let Some(index) = letters.iter().position(|letter| letter.summary.id == id) else {
+            return Ok(None);
+        };
+        let letter = letters.remove(index);
+        DEAD_LETTER_QUEUE_DEPTH.set(i64::try_from(letters.len()).unwrap_or(i64::MAX));
+        Ok(letter)
+    }
+
+    /// Try a letter's work again now
+    ///
+    /// The letter leaves the queue once its work succeeds; otherwise it stays
+    /// with the new error recorded.
+    pub async fn requeue(&self, id: &str) -> Result<(), RequeueError> {
+        let letter = self
+            .get(id)?
+            .ok_or_else(|| RequeueError::NotFound(id.to_string()))?;
+        let source = letter.summary.source;
+        let retry = self
+            .retries
+            .read()
+            .map_err(|_| DatabaseError::LockError)?
+            .get(&source)
+            .cloned()
+            .ok_or(RequeueError::Unsupported(source.as_str()))?;
+
+        let result = retry.retry(&letter.payload).await;
+        if result.is_ok() {
+            self.discard(id)?;
+            return Ok(());
+        }
+        let mut letters = self.letters.lock().map_err(|_| DatabaseError::LockError)?;
+        if let Some(letter) = letters.iter_mut().find(|letter| letter.summary.id == id) {
+            letter.summary.attempts += 1;
+            letter.summary.last_attempt_at = Utc::now();
+            if let Err(e) = &result {
+                letter.summary.last_error.clone_from(e);
+            }
+        }
+        result.map_err(RequeueError::Failed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[derive(Default)]
+    struct Flaky {
+        failing: AtomicBool,
+    }
+
+    #[async_trait]
+    impl Retry for Flaky {
+        async fn retry(&self, _payload: &Value) -> Result<(), String> {
+            if self.failing.load(Ordering::SeqCst) {
+                Err("still down".to_string())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_requeued_letters_leave_once_retried() {
+        let queue = DeadLetterQueue::default();
+        let flaky = Arc::new(Flaky::default());
+        queue
+            .register(DeadLetterSource::Webhook, flaky.clone())
+            .unwrap();
+        let push = |source| {
+            queue
+                .push(source, "work".to_string(), 3, "down".to_string(), json!({ "n": 1 }))
+                .unwrap();
+        };
+        push(DeadLetterSource::Webhook);
+        push(DeadLetterSource::Outbox);
+
+        assert_eq!(queue.list(Some(DeadLetterSource::Webhook)).unwrap().len(), 1);
+        let webhook = queue.list(Some(DeadLetterSource::Webhook)).unwrap()[0]
+            .id
+            .clone();
+        let outbox = queue.list(Some(DeadLetterSource::Outbox)).unwrap()[0]
+            .id
+            .clone();
+        assert_eq!(queue.get(&webhook).unwrap().unwrap().payload, json!({ "n": 1 }));
+
+        flaky.failing.store(true, Ordering::SeqCst);
+        assert!(matches!(queue.requeue(&webhook).await, Err(RequeueError::Failed(_))));
+        let letter = queue.get(&webhook).unwrap().unwrap();
+        assert_eq!(
+            (letter.summary.attempts, letter.summary.last_error.as_str()),
+            (4, "still down")
+        );
+
+        flaky.failing.store(false, Ordering::SeqCst);
+        queue.requeue(&webhook).await.unwrap();
+        assert!(queue.get(&webhook).unwrap().is_none());
+
+        // Nothing registered to retry outbox work
+        assert!(matches!(queue.requeue(&outbox).await, Err(RequeueError::Unsupported(_))));
+        assert!(matches!(queue.requeue("missing").await, Err(RequeueError::NotFound(_))));
+        assert!(queue.discard(&outbox).unwrap().is_some());
+        assert!(queue.is_empty().unwrap());
+    }
+}
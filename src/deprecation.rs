@@ -0,0 +1,222 @@
+use axum::{
+    extract::{MatchedPath, Request},
+    http::{HeaderName, HeaderValue, Method},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::{collections::BTreeMap, sync::Arc};
+
+use crate::metrics;
+
+pub static DEPRECATION: HeaderName = HeaderName::from_static("deprecation");
+pub static SUNSET: HeaderName = HeaderName::from_static("sunset");
+pub static LINK: HeaderName = HeaderName::from_static("link");
+
+/// How an endpoint is on its way out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deprecation {
+    /// When the endpoint was deprecated
+    pub since: DateTime<Utc>,
+    /// When the endpoint stops being served
+    pub sunset: Option<DateTime<Utc>>,
+    /// Page explaining the deprecation and what to use instead
+    pub link: Option<String>,
+    /// Path of the endpoint replacing this one
+    pub successor: Option<String>,
+}
+
+impl Deprecation {
+    pub fn new(since: DateTime<Utc>) -> Self {
+        Self {
+            since,
+            sunset: None,
+            link: None,
+            successor: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_sunset(mut self, sunset: DateTime<Utc>) -> Self {
+        self.sunset = Some(sunset);
+        self
+    }
+
+    #[must_use]
+    pub fn with_link(mut self, link: impl Into<String>) -> Self {
+        self.link = Some(link.into());
+        self
+    }
+
+    #[must_use]
+    pub fn with_successor(mut self, successor: impl Into<String>) -> Self {
+        self.successor = Some(successor.into());
+        self
+    }
+
+    /// Headers announcing the deprecation: `Deprecation` (RFC 9745),
+    /// `Sunset` (RFC 8594) and `Link`
+    pub fn headers(&self) -> Vec<(HeaderName, String)> {
+        let mut headers = vec![(DEPRECATION.clone(), format!("@{}", self.since.timestamp()))];
+        if let Some(sunset) = self.sunset {
+            let date = sunset.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+            headers.push((SUNSET.clone(), date));
+        }
+        if let Some(link) = &self.link {
+            headers.push((LINK.clone(), format!("<{link}>; rel=\"deprecation\"")));
+        }
+        if let Some(successor) = &self.successor {
+            headers.push((LINK.clone(), format!("<{successor}>; rel=\"successor-version\"")));
+        }
+        headers
+    }
+
+    /// Sentence added to the endpoint's OpenAPI description
+    fn describe(&self) -> String {
+        let mut description = format!("Deprecated since {}.", self.since.format("%Y-%m-%d"));
+        if let Some(sunset) = self.sunset {
+            description.push_str(&format!(" Removed on {}.", sunset.format("%Y-%m-%d")));
+        }
+        if let Some(successor) = &self.successor {
+            description.push_str(&format!(" Use `{successor}` instead."));
+        }
+        if let Some(link) = &self.link {
+            description.push_str(&format!(" See {link}."));
+        }
+        description
+    }
+}
+
+/// Endpoints on their way out, by method and route path
+///
+/// Announced on every response of a deprecated endpoint by
+/// [`deprecation_middleware`], added to the routes when they are built, and
+/// in the OpenAPI document by [`Deprecations::document`].
+#[derive(Debug, Clone, Default)]
+pub struct Deprecations {
+    endpoints: BTreeMap<(String, String), Deprecation>,
+}
+
+impl Deprecations {
+    /// Deprecate `method` on the route declared as `path`, such as
+    /// `/api/v1/items/{id}`
+    #[must_use]
+    pub fn deprecate(mut self, method: Method, path: &str, deprecation: Deprecation) -> Self {
+        self.endpoints
+            .insert((method.to_string(), path.to_string()), deprecation);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.endpoints.is_empty()
+    }
+
+    pub fn get(&self, method: &Method, path: &str) -> Option<&Deprecation> {
+        self.endpoints.get(&(method.to_string(), path.to_string()))
+    }
+
+    /// Mark the deprecated operations of `spec` as such
+    pub fn document(&self, spec: &mut Value) {
+        for ((method, path), deprecation) in &self.endpoints {
+            let Some(operation) = spec
+                .get_mut("paths")
+                .and_then(|paths| paths.get_mut(path))
+                .and_then(|item| item.get_mut(method.to_lowercase()))
+            else {
+                continue;
+            };
+            operation["deprecated"] = Value::Bool(true);
+            let description = match operation.get("description").and_then(Value::as_str) {
+                Some(existing) => format!("{existing}\n\n{}", deprecation.describe()),
+                None => deprecation.describe(),
+            };
+            operation["description"] = Value::String(description);
+            if let Some(sunset) = deprecation.sunset {
+                operation["x-sunset"] = Value::String(sunset.to_rfc3339());
+            }
+        }
+    }
+}
+
+/// Deprecation middleware - adds the deprecation headers to the responses of
+/// deprecated endpoints and counts their calls
+///
+/// Added as a route layer, so the route matched is known.
+pub async fn deprecation_middleware(
+    req: Request,
+    next: Next,
+    deprecations: Arc<Deprecations>,
+) -> Response {
+    let method = req.method().clone();
+    let Some(path) = req.extensions().get::<MatchedPath>().cloned() else {
+        return next.run(req).await;
+    };
+    let Some(deprecation) = deprecations.get(&method, path.as_str()) else {
+        return next.run(req).await;
+    };
+
+    let mut response = next.run(req).await;
+    metrics::track_deprecated_call(method.as_str(), path.as_str());
+    for (name, value) in deprecation.headers() {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            response.headers_mut().append(name, value);
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn deprecation() -> Deprecation {
+        Deprecation::new(Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap())
+            .with_sunset(Utc.with_ymd_and_hms(2026, 7, 1, 0, 0, 0).unwrap())
+            .with_link("https://example.com/deprecations/slug")
+            .with_successor("/api/v1/items/{id}")
+    }
+
+    #[test]
+    fn test_headers() {
+        let headers = deprecation().headers();
+        let values: Vec<&str> = headers.iter().map(|(_, value)| value.as_str()).collect();
+        assert_eq!(
+            values,
+            [
+                "@1767225600",
+                "Wed, 01 Jul 2026 00:00:00 GMT",
+                "<https://example.com/deprecations/slug>; rel=\"deprecation\"",
+                "</api/v1/items/{id}>; rel=\"successor-version\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_document_marks_operations() {
+        let deprecations = Deprecations::default().deprecate(
+            Method::GET,
+            "/api/v1/items/slug/{slug}",
+            deprecation(),
+        );
+        let mut spec = json!({ "paths": {
+            "/api/v1/items/slug/{slug}": { "get": { "description": "Fetch by slug" } },
+            "/api/v1/items/{id}": { "get": {} }
+        } });
+
+        deprecations.document(&mut spec);
+
+        let operation = &spec["paths"]["/api/v1/items/slug/{slug}"]["get"];
+        assert_eq!(operation["deprecated"], true);
+        assert!(operation["description"]
+            .as_str()
+            .unwrap()
+            .starts_with("Fetch by slug\n\nDeprecated since 2026-01-01."));
+        assert_eq!(operation["x-sunset"], "2026-07-01T00:00:00+00:00");
+        assert!(spec["paths"]["/api/v1/items/{id}"]["get"]
+            .get("deprecated")
+            .is_none());
+    }
+}
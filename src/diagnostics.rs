@@ -0,0 +1,130 @@
+use std::{fmt, sync::Arc};
+
+use crate::{
+    auth::JwtValidator,
+    config::{Config, ConfigError},
+    db::{create_repository, create_tenant_repository},
+    events::create_publisher,
+    health::HealthMonitor,
+    http_client::HttpClient,
+    outbound_auth::TokenProvider,
+};
+
+/// Result of checking that one declared backend is reachable
+#[derive(Debug)]
+pub struct BackendCheck {
+    pub component: String,
+    pub result: Result<(), String>,
+}
+
+impl fmt::Display for BackendCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.result {
+            Ok(()) => write!(f, "ok    {}", self.component),
+            Err(e) => write!(f, "FAIL  {}: {}", self.component, e),
+        }
+    }
+}
+
+/// Load and fully validate configuration, then connect to every declared backend
+///
+/// Used by `ferrous config check`. Configuration errors are returned as-is;
+/// connectivity failures are reported per backend so all of them are shown.
+pub async fn check_config() -> Result<(Config, Vec<BackendCheck>), ConfigError> {
+    let config = Config::load()?;
+    config.validate_runtime_dependencies()?;
+    let checks = check_backends(&config).await;
+    Ok((config, checks))
+}
+
+/// Connect to the database, JWKS endpoint, event broker and synthetic check targets
+pub async fn check_backends(config: &Config) -> Vec<BackendCheck> {
+    let mut checks = Vec::new();
+
+    let http = HttpClient::new(&config.http_client).unwrap_or_else(|e| {
+        checks.push(BackendCheck {
+            component: "outbound HTTP client".to_string(),
+            result: Err(e),
+        });
+        HttpClient::default()
+    });
+    let egress_http =
+        HttpClient::restricted(&config.http_client, &config.egress).unwrap_or_else(|e| {
+            checks.push(BackendCheck {
+                component: "egress policy".to_string(),
+                result: Err(e),
+            });
+            http.clone()
+        });
+
+    let repo = create_repository(config);
+    checks.push(BackendCheck {
+        component: format!("database ({})", config.database.db_type),
+        result: repo.health_check().await.map_err(|e| e.to_string()),
+    });
+
+    if config.auth.enabled {
+        checks.push(BackendCheck {
+            component: "authentication".to_string(),
+            result: JwtValidator::new(&config.auth)
+                .with_http_client(egress_http)
+                .verify_setup()
+                .await
+                .map_err(|e| e.to_string()),
+        });
+    }
+
+    let tenants = create_tenant_repository(config);
+    checks.push(BackendCheck {
+        component: "tenant directory".to_string(),
+        result: tenants.list().await.map(|_| ()).map_err(|e| e.to_string()),
+    });
+
+    let tokens = TokenProvider::from_config(&config.outbound_auth)
+        .map(|tokens| Arc::new(tokens.with_http_client(http.clone())));
+    if let Some(tokens) = &tokens {
+        checks.push(BackendCheck {
+            component: "outbound auth token endpoint".to_string(),
+            result: tokens.token().await.map(|_| ()).map_err(|e| e.to_string()),
+        });
+    }
+
+    if let Some(name) = &config.events.publisher {
+        let result = match create_publisher(&config.events, tokens, &http).await {
+            Ok(Some(publisher)) => publisher.health_check().await,
+            Ok(None) => Ok(()),
+            Err(e) => Err(e),
+        };
+        checks.push(BackendCheck {
+            component: format!("event publisher ({name})"),
+            result,
+        });
+    }
+
+    let health = HealthMonitor::new(config.health.clone()).with_http_client(http);
+    health.run_checks().await;
+    for check in health.checks() {
+        checks.push(BackendCheck {
+            component: format!("health check {}", check.name),
+            result: match check.error {
+                None => Ok(()),
+                Some(e) => Err(e),
+            },
+        });
+    }
+
+    checks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_default_backends_are_reachable() {
+        let checks = check_backends(&Config::default()).await;
+        assert_eq!(checks.len(), 2);
+        assert!(checks.iter().all(|check| check.result.is_ok()));
+        assert_eq!(checks[0].to_string(), "ok    database (memory)");
+    }
+}
@@ -0,0 +1,195 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+#[allow(unused_imports)] // Used in #[schema(example = json!({...}))] attributes
+use serde_json::json;
+use std::collections::BTreeMap;
+use utoipa::{
+    openapi::{path::PathItem, OpenApi as Spec},
+    OpenApi, ToSchema,
+};
+
+use crate::{openapi::ApiDoc, state::AppState};
+
+/// Prefix of the versioned resource routes
+const API_PREFIX: &str = "/api/";
+
+/// Index of the API, served at `GET /api`
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[schema(example = json!({
+    "name": "Ferrous API",
+    "version": "0.1.0",
+    "versions": [{
+        "version": "v1",
+        "href": "/api/v1",
+        "collections": [{
+            "name": "items",
+            "href": "/api/v1/items",
+            "methods": ["GET", "POST"],
+            "paths": ["/api/v1/items", "/api/v1/items/{id}"]
+        }]
+    }],
+    "links": {
+        "self": "/api",
+        "openapi": "/openapi.json",
+        "health": "/health",
+        "version": "/version",
+        "metrics": "/metrics"
+    },
+    "features": ["change_feed", "rate_limiting"]
+}))]
+pub struct ApiIndex {
+    pub name: String,
+    pub version: String,
+    pub versions: Vec<ApiVersion>,
+    pub links: BTreeMap<&'static str, &'static str>,
+    /// Optional features enabled on this instance
+    pub features: Vec<&'static str>,
+}
+
+/// A version of the API and the collections it serves
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct ApiVersion {
+    #[schema(example = "v1")]
+    pub version: String,
+    pub href: String,
+    pub collections: Vec<Collection>,
+}
+
+/// A resource collection
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct Collection {
+    #[schema(example = "items")]
+    pub name: String,
+    pub href: String,
+    /// Methods the collection itself supports
+    pub methods: Vec<&'static str>,
+    /// Every route under the collection, as path templates
+    pub paths: Vec<String>,
+}
+
+/// Versions read from the OpenAPI document once, as it never changes
+static VERSIONS: Lazy<Vec<ApiVersion>> = Lazy::new(|| versions(&ApiDoc::openapi()));
+
+/// Build the index for `state`
+///
+/// Versions and collections come from the routes in the OpenAPI document,
+/// so a route shows up here as soon as it is documented.
+pub fn api_index(state: &AppState) -> ApiIndex {
+    ApiIndex {
+        name: "Ferrous API".to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        versions: VERSIONS.clone(),
+        links: BTreeMap::from([
+            ("self", "/api"),
+            ("openapi", "/openapi.json"),
+            ("health", "/health"),
+            ("version", "/version"),
+            ("metrics", "/metrics"),
+        ]),
+        features: features(state),
+    }
+}
+
+fn features(state: &AppState) -> Vec<&'static str> {
+    let config = &state.config;
+    [
+        (config.auth.enabled, "authentication"),
+        (true, "change_feed"),
+        (state.duplicates.is_some(), "duplicate_detection"),
+        (state.publisher.is_some(), "event_publishing"),
+        (state.mocks.is_some(), "mock_endpoints"),
+        (config.tenancy.enabled, "multi_tenancy"),
+        (config.rate_limit.enabled, "rate_limiting"),
+        (config.server.request_timeout_ms.is_some(), "request_deadlines"),
+    ]
+    .into_iter()
+    .filter_map(|(enabled, feature)| enabled.then_some(feature))
+    .collect()
+}
+
+/// Group the spec's `/api/{version}/{collection}...` paths
+fn versions(spec: &Spec) -> Vec<ApiVersion> {
+    let mut versions: BTreeMap<&str, BTreeMap<&str, Collection>> = BTreeMap::new();
+    for (path, item) in &spec.paths.paths {
+        let Some(rest) = path.strip_prefix(API_PREFIX) else {
+            continue;
+        };
+        let mut segments = rest.split('/');
+        let (Some(version), Some(name)) = (segments.next(), segments.next()) else {
+            continue;
+        };
+        if name.is_empty() || name.starts_with('{') {
+            continue;
+        }
+
+        let href = format!("{API_PREFIX}{version}/{name}");
+        let collection = versions
+            .entry(version)
+            .or_default()
+            .entry(name)
+            .or_insert_with(|| Collection {
+                name: name.to_string(),
+                href: href.clone(),
+                methods: Vec::new(),
+                paths: Vec::new(),
+            });
+        if *path == href {
+            collection.methods = methods(item);
+        }
+        collection.paths.push(path.clone());
+    }
+
+    versions
+        .into_iter()
+        .map(|(version, collections)| ApiVersion {
+            version: version.to_string(),
+            href: format!("{API_PREFIX}{version}"),
+            // Routes nested under a path that is not itself served, such as
+            // webhook deliveries, are not a collection to browse
+            collections: collections
+                .into_values()
+                .filter(|collection| !collection.methods.is_empty())
+                .collect(),
+        })
+        .collect()
+}
+
+fn methods(item: &PathItem) -> Vec<&'static str> {
+    [
+        (item.get.is_some(), "GET"),
+        (item.post.is_some(), "POST"),
+        (item.put.is_some(), "PUT"),
+        (item.patch.is_some(), "PATCH"),
+        (item.delete.is_some(), "DELETE"),
+    ]
+    .into_iter()
+    .filter_map(|(present, method)| present.then_some(method))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_versions_group_documented_routes_by_collection() {
+        let versions = versions(&ApiDoc::openapi());
+        assert_eq!(versions.len(), 1);
+        let v1 = &versions[0];
+        assert_eq!((v1.version.as_str(), v1.href.as_str()), ("v1", "/api/v1"));
+
+        let items = v1
+            .collections
+            .iter()
+            .find(|collection| collection.name == "items")
+            .unwrap();
+        assert_eq!(items.href, "/api/v1/items");
+        assert_eq!(items.methods, vec!["GET", "POST"]);
+        assert!(items.paths.contains(&"/api/v1/items/{id}".to_string()));
+        // Admin routes are not part of the versioned API
+        assert!(v1
+            .collections
+            .iter()
+            .all(|collection| !collection.href.starts_with("/admin")));
+    }
+}
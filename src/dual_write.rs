@@ -0,0 +1,422 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+use tracing::warn;
+use utoipa::ToSchema;
+
+use crate::{
+    analytics::{AnalyticsBucket, AnalyticsRange},
+    db::{DatabaseError, DatabaseResult, EventFilter, ItemFilter, ItemRepository},
+    events::OutboxEvent,
+    metrics::track_dual_write_divergence,
+    models::{CreateItemRequest, Item, ItemField, UpdateItemRequest},
+    pagination::Page,
+    tenancy::{current_tenant, with_optional_tenant},
+};
+
+/// Most divergences kept; the ones beyond it are only counted
+pub const MAX_DIVERGENCES: usize = 10_000;
+
+/// Applies every write to a second backend as well, for migrating between
+/// backends without downtime
+///
+/// Reads, and the outbox, are served by `primary`, and callers get its
+/// answer: a write the secondary fails to apply is logged and kept as a
+/// [`Divergence`] rather than failing the request. Items are written to the
+/// secondary as the primary stored them, IDs, versions and timestamps
+/// included, so creates and updates reach it once the primary has answered;
+/// deletes and bulk changes go to both at once. [`DualWriteRepository::reconcile`]
+/// copies the divergent items from the primary again.
+pub struct DualWriteRepository {
+    primary: Arc<dyn ItemRepository>,
+    secondary: Arc<dyn ItemRepository>,
+    backend: String,
+    writes: AtomicU64,
+    /// Divergences by tenant and item ID, or operation for bulk changes
+    divergences: Mutex<BTreeMap<(Option<String>, String), Divergence>>,
+    untracked: AtomicU64,
+}
+
+/// A write the secondary backend failed to apply
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Divergence {
+    /// Item the write was to, unset for bulk changes such as tenant drops
+    #[schema(example = "3f2b8c1e-6d4a-4f7b-9e21-0c5d8a7b6e43")]
+    pub item_id: Option<String>,
+    pub tenant: Option<String>,
+    /// Latest write that failed
+    #[schema(example = "update")]
+    pub operation: String,
+    pub error: String,
+    pub failed_at: DateTime<Utc>,
+}
+
+/// Where the secondary backend stands against the primary
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DualWriteReport {
+    /// Backend writes are also applied to
+    #[schema(example = "convex")]
+    pub secondary: String,
+    /// Writes since startup
+    pub writes: u64,
+    /// Writes the secondary has not applied, oldest first
+    pub divergences: Vec<Divergence>,
+    /// Divergences beyond the most kept, which only a migration repairs
+    pub untracked: u64,
+}
+
+impl DualWriteRepository {
+    pub fn new(
+        primary: Arc<dyn ItemRepository>,
+        secondary: Arc<dyn ItemRepository>,
+        backend: &str,
+    ) -> Self {
+        Self {
+            primary,
+            secondary,
+            backend: backend.to_string(),
+            writes: AtomicU64::new(0),
+            divergences: Mutex::new(BTreeMap::new()),
+            untracked: AtomicU64::new(0),
+        }
+    }
+
+    pub fn report(&self) -> DatabaseResult<DualWriteReport> {
+        let mut divergences: Vec<Divergence> = self
+            .divergences
+            .lock()
+            .map_err(|_| DatabaseError::LockError)?
+            .values()
+            .cloned()
+            .collect();
+        divergences.sort_by_key(|divergence| divergence.failed_at);
+        Ok(DualWriteReport {
+            secondary: self.backend.clone(),
+            writes: self.writes.load(Ordering::Relaxed),
+            divergences,
+            untracked: self.untracked.load(Ordering::Relaxed),
+        })
+    }
+
+    /// Copy each divergent item from the primary to the secondary again,
+    /// deleting it there if the primary no longer has it
+    ///
+    /// Divergences of bulk changes are kept; they take a migration to repair.
+    pub async fn reconcile(&self) -> DatabaseResult<DualWriteReport> {
+        let pending: Vec<(Option<String>, String)> = self
+            .divergences
+            .lock()
+            .map_err(|_| DatabaseError::LockError)?
+            .values()
+            .filter_map(|divergence| {
+                let id = divergence.item_id.clone()?;
+                Some((divergence.tenant.clone(), id))
+            })
+            .collect();
+
+        for (tenant, id) in pending {
+            with_optional_tenant(tenant, async {
+                let result = match self.primary.get(&id).await {
+                    Ok(item) => self.secondary.restore(item).await.map(drop),
+                    Err(DatabaseError::NotFound) => self.delete_secondary(&id).await,
+                    Err(e) => Err(e),
+                };
+                self.record("reconcile", Some(&id), result);
+            })
+            .await;
+        }
+        self.report()
+    }
+
+    async fn delete_secondary(&self, id: &str) -> DatabaseResult<()> {
+        match self.secondary.delete(id).await {
+            Err(DatabaseError::NotFound) => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Record how the secondary took a write to `item_id` (or a bulk change
+    /// when `None`) that the primary applied
+    fn applied<T>(&self, operation: &str, item_id: Option<&str>, result: DatabaseResult<T>) {
+        self.writes.fetch_add(1, Ordering::Relaxed);
+        self.record(operation, item_id, result);
+    }
+
+    /// Keep or clear the divergence of `item_id` as `result` says; those of
+    /// bulk changes are only ever kept
+    fn record<T>(&self, operation: &str, item_id: Option<&str>, result: DatabaseResult<T>) {
+        let tenant = current_tenant();
+        let key = (tenant.clone(), item_id.unwrap_or(operation).to_string());
+        let Ok(mut divergences) = self.divergences.lock() else {
+            // Divergences can no longer be kept, so failures go untracked
+            if result.is_err() {
+                self.untracked.fetch_add(1, Ordering::Relaxed);
+            }
+            return;
+        };
+        let Err(e) = result else {
+            if item_id.is_some() {
+                divergences.remove(&key);
+            }
+            return;
+        };
+
+        warn!(
+            backend = %self.backend,
+            operation,
+            item_id = item_id.unwrap_or_default(),
+            error = %e,
+            "Secondary backend failed to apply a write"
+        );
+        track_dual_write_divergence(&self.backend, operation);
+        if divergences.len() >= MAX_DIVERGENCES && !divergences.contains_key(&key) {
+            self.untracked.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        divergences.insert(
+            key,
+            Divergence {
+                item_id: item_id.map(str::to_string),
+                tenant,
+                operation: operation.to_string(),
+                error: e.to_string(),
+                failed_at: Utc::now(),
+            },
+        );
+    }
+
+    /// Write `result`'s item to the secondary as the primary stored it
+    async fn mirror(&self, operation: &str, result: DatabaseResult<Item>) -> DatabaseResult<Item> {
+        if let Ok(item) = &result {
+            let mirrored = self.secondary.restore(item.clone()).await;
+            self.applied(operation, Some(&item.id), mirrored);
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl ItemRepository for DualWriteRepository {
+    async fn create(
+        &self,
+        request: CreateItemRequest,
+        owner_id: Option<String>,
+    ) -> DatabaseResult<Item> {
+        let result = self.primary.create(request, owner_id).await;
+        self.mirror("create", result).await
+    }
+
+    async fn get(&self, id: &str) -> DatabaseResult<Item> {
+        self.primary.get(id).await
+    }
+
+    async fn get_by_slug(&self, slug: &str) -> DatabaseResult<Item> {
+        self.primary.get_by_slug(slug).await
+    }
+
+    async fn get_by_external_id(
+        &self,
+        source: Option<&str>,
+        external_id: &str,
+    ) -> DatabaseResult<Item> {
+        self.primary.get_by_external_id(source, external_id).await
+    }
+
+    async fn update(
+        &self,
+        id: &str,
+        request: UpdateItemRequest,
+        expected_version: Option<u64>,
+    ) -> DatabaseResult<Item> {
+        let result = self.primary.update(id, request, expected_version).await;
+        self.mirror("update", result).await
+    }
+
+    async fn changed_since(&self, id: &str, version: u64) -> DatabaseResult<Vec<ItemField>> {
+        self.primary.changed_since(id, version).await
+    }
+
+    async fn delete(&self, id: &str) -> DatabaseResult<()> {
+        let (result, mirrored) = tokio::join!(self.primary.delete(id), self.delete_secondary(id));
+        if result.is_ok() {
+            self.applied("delete", Some(id), mirrored);
+        }
+        result
+    }
+
+    async fn list(&self, filter: &ItemFilter, page: &Page) -> DatabaseResult<Vec<Arc<Item>>> {
+        self.primary.list(filter, page).await
+    }
+
+    async fn count(&self, filter: &ItemFilter) -> DatabaseResult<usize> {
+        self.primary.count(filter).await
+    }
+
+    async fn list_with_total(
+        &self,
+        filter: &ItemFilter,
+        page: &Page,
+    ) -> DatabaseResult<(Vec<Arc<Item>>, usize)> {
+        self.primary.list_with_total(filter, page).await
+    }
+
+    async fn health_check(&self) -> DatabaseResult<()> {
+        self.primary.health_check().await
+    }
+
+    async fn reassign_owner(
+        &self,
+        owner_id: &str,
+        replacement: &str,
+    ) -> DatabaseResult<(usize, usize)> {
+        let (result, mirrored) = tokio::join!(
+            self.primary.reassign_owner(owner_id, replacement),
+            self.secondary.reassign_owner(owner_id, replacement)
+        );
+        if result.is_ok() {
+            self.applied("reassign_owner", None, mirrored);
+        }
+        result
+    }
+
+    async fn restore(&self, item: Item) -> DatabaseResult<Item> {
+        let result = self.primary.restore(item).await;
+        self.mirror("restore", result).await
+    }
+
+    async fn provision_tenant(&self, tenant: &str) -> DatabaseResult<()> {
+        let (result, mirrored) = tokio::join!(
+            self.primary.provision_tenant(tenant),
+            self.secondary.provision_tenant(tenant)
+        );
+        if result.is_ok() {
+            self.applied("provision_tenant", None, mirrored);
+        }
+        result
+    }
+
+    async fn drop_tenant(&self, tenant: &str) -> DatabaseResult<Vec<String>> {
+        let (result, mirrored) =
+            tokio::join!(self.primary.drop_tenant(tenant), self.secondary.drop_tenant(tenant));
+        if result.is_ok() {
+            self.applied("drop_tenant", None, mirrored);
+        }
+        result
+    }
+
+    async fn pending_events(&self, limit: usize) -> DatabaseResult<Vec<OutboxEvent>> {
+        self.primary.pending_events(limit).await
+    }
+
+    async fn event_history(
+        &self,
+        filter: &EventFilter,
+        after: u64,
+        limit: usize,
+    ) -> DatabaseResult<Vec<OutboxEvent>> {
+        self.primary.event_history(filter, after, limit).await
+    }
+
+    async fn pending_event_count(&self) -> DatabaseResult<usize> {
+        self.primary.pending_event_count().await
+    }
+
+    async fn mark_events_published(&self, sequences: &[u64]) -> DatabaseResult<()> {
+        self.primary.mark_events_published(sequences).await
+    }
+
+    async fn mark_event_failed(&self, sequence: u64, error: &str) -> DatabaseResult<()> {
+        self.primary.mark_event_failed(sequence, error).await
+    }
+
+    async fn purge_published_events(
+        &self,
+        cutoff: DateTime<Utc>,
+        dry_run: bool,
+    ) -> DatabaseResult<usize> {
+        self.primary.purge_published_events(cutoff, dry_run).await
+    }
+
+    async fn event_histogram(
+        &self,
+        filter: &ItemFilter,
+        range: &AnalyticsRange,
+    ) -> DatabaseResult<Vec<AnalyticsBucket>> {
+        self.primary.event_histogram(filter, range).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::InMemoryRepository;
+
+    fn request(name: &str) -> CreateItemRequest {
+        CreateItemRequest {
+            name: name.to_string(),
+            description: None,
+            metadata: None,
+            custom_fields: Default::default(),
+            status: Default::default(),
+            publish_at: None,
+            external_id: None,
+            source: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_writes_reach_both_backends() {
+        let primary = Arc::new(InMemoryRepository::new());
+        let secondary = Arc::new(InMemoryRepository::new());
+        let repo = DualWriteRepository::new(primary.clone(), secondary.clone(), "memory");
+
+        let item = repo.create(request("Mirrored"), None).await.unwrap();
+        let update = UpdateItemRequest {
+            name: Some("Renamed".to_string()),
+            ..Default::default()
+        };
+        let updated = repo
+            .update(&item.id, update, Some(item.version))
+            .await
+            .unwrap();
+        let copy = secondary.get(&item.id).await.unwrap();
+        assert_eq!((copy.name.as_str(), copy.version), ("Renamed", updated.version));
+
+        repo.delete(&item.id).await.unwrap();
+        assert!(matches!(secondary.get(&item.id).await, Err(DatabaseError::NotFound)));
+        let report = repo.report().unwrap();
+        assert_eq!(report.writes, 3);
+        assert!(report.divergences.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_divergences_are_reported_and_reconciled() {
+        let primary = Arc::new(InMemoryRepository::new());
+        let secondary = Arc::new(InMemoryRepository::new());
+        let repo = DualWriteRepository::new(primary.clone(), secondary.clone(), "memory");
+
+        // Writes the secondary failed to apply: an update, and a delete
+        let item = primary.create(request("Unmirrored"), None).await.unwrap();
+        let deleted = primary.create(request("Deleted"), None).await.unwrap();
+        secondary.restore(deleted.clone()).await.unwrap();
+        repo.applied("update", Some(&item.id), Err::<(), _>(DatabaseError::NotFound));
+        repo.applied("delete", Some(&deleted.id), Err::<(), _>(DatabaseError::LockError));
+        primary.delete(&deleted.id).await.unwrap();
+
+        let report = repo.report().unwrap();
+        assert_eq!(report.divergences.len(), 2);
+        assert_eq!(report.divergences[0].item_id.as_deref(), Some(item.id.as_str()));
+
+        let report = repo.reconcile().await.unwrap();
+        assert!(report.divergences.is_empty());
+        assert_eq!(secondary.get(&item.id).await.unwrap().name, "Unmirrored");
+        assert!(matches!(secondary.get(&deleted.id).await, Err(DatabaseError::NotFound)));
+    }
+}
@@ -0,0 +1,339 @@
+use dashmap::DashMap;
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::{Mutex, OwnedMutexGuard};
+use utoipa::ToSchema;
+
+use crate::{
+    clock::{system_clock, SharedClock},
+    models::{CreateItemRequest, Item},
+    tenancy::current_tenant,
+};
+
+/// Submissions between sweeps of expired entries
+const PRUNE_EVERY: u64 = 256;
+
+type Digest32 = [u8; 32];
+
+/// Item created for a submission, and when
+struct Recent {
+    item_id: String,
+    at: Instant,
+}
+
+/// Detects the same item being submitted again within a short window
+///
+/// Retrying clients without idempotency keys, such as mobile apps on flaky
+/// networks, would otherwise create one item per attempt. Submissions are
+/// identical when the same principal in the same tenant sends the same
+/// sanitized body, every field of it, regardless of the order of object
+/// keys; a resubmission that changes anything creates an item. Concurrent
+/// identical submissions wait for the first, so only one item is created.
+pub struct DuplicateGuard {
+    window: Duration,
+    recent: DashMap<Digest32, Arc<Mutex<Option<Recent>>>>,
+    submissions: AtomicU64,
+    clock: SharedClock,
+}
+
+/// An item submission holding its place until it completes
+pub struct Submission {
+    slot: OwnedMutexGuard<Option<Recent>>,
+    previous: Option<String>,
+    clock: SharedClock,
+}
+
+impl DuplicateGuard {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            recent: DashMap::new(),
+            submissions: AtomicU64::new(0),
+            clock: system_clock(),
+        }
+    }
+
+    /// Measure the window with `clock` instead of the system clock
+    #[must_use]
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Start a submission by `principal`, waiting for any identical one in
+    /// progress to finish
+    pub async fn begin(&self, principal: &str, request: &CreateItemRequest) -> Submission {
+        if self.submissions.fetch_add(1, Ordering::Relaxed) % PRUNE_EVERY == PRUNE_EVERY - 1 {
+            self.prune();
+        }
+
+        let slot = self
+            .recent
+            .entry(digest(principal, request))
+            .or_default()
+            .clone();
+        let slot = slot.lock_owned().await;
+        let now = self.clock.instant();
+        let previous = slot
+            .as_ref()
+            .filter(|recent| now.duration_since(recent.at) < self.window)
+            .map(|recent| recent.item_id.clone());
+        Submission {
+            slot,
+            previous,
+            clock: self.clock.clone(),
+        }
+    }
+
+    /// Forget submissions older than the window that nobody is waiting on
+    fn prune(&self) {
+        let now = self.clock.instant();
+        self.recent.retain(|_, slot| {
+            slot.try_lock().map_or(true, |recent| {
+                recent
+                    .as_ref()
+                    .is_some_and(|recent| now.duration_since(recent.at) < self.window)
+            })
+        });
+    }
+}
+
+impl Submission {
+    /// Item created by an identical submission within the window
+    pub fn previous(&self) -> Option<&str> {
+        self.previous.as_deref()
+    }
+
+    /// Record the item this submission created
+    pub fn created(mut self, item_id: &str) {
+        *self.slot = Some(Recent {
+            item_id: item_id.to_string(),
+            at: self.clock.instant(),
+        });
+    }
+}
+
+fn digest(principal: &str, request: &CreateItemRequest) -> Digest32 {
+    let mut hasher = Sha256::new();
+    // Length prefixes keep field boundaries unambiguous
+    let tenant = current_tenant().unwrap_or_default();
+    let body = canonical_json(request);
+    for field in [tenant.as_str(), principal, body.as_str()] {
+        hasher.update((field.len() as u64).to_le_bytes());
+        hasher.update(field.as_bytes());
+    }
+    hasher.finalize().into()
+}
+
+/// `request` as JSON with the keys of every object sorted, so bodies that
+/// only order their keys differently are alike
+fn canonical_json(request: &CreateItemRequest) -> String {
+    fn sorted(value: Value) -> Value {
+        match value {
+            Value::Object(fields) => {
+                let mut fields: Vec<_> = fields
+                    .into_iter()
+                    .map(|(key, value)| (key, sorted(value)))
+                    .collect();
+                fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+                Value::Object(fields.into_iter().collect())
+            }
+            Value::Array(values) => Value::Array(values.into_iter().map(sorted).collect()),
+            value => value,
+        }
+    }
+    // Requests always serialize; were one not to, its debug form still tells
+    // bodies apart
+    serde_json::to_value(request)
+        .map(|value| sorted(value).to_string())
+        .unwrap_or_else(|_| format!("{request:?}"))
+}
+
+/// How alike two item names are, from 0 to 1
+///
+/// One minus the edit distance between the names over the length of the
+/// longer, ignoring case and runs of whitespace, so `"Widget Pro"` and
+/// `"widget  pro"` are the same and `"Widget"` and `"Widgets"` score 0.86.
+pub fn name_similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (comparable(a), comparable(b));
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - edit_distance(&a, &b) as f64 / longest as f64
+}
+
+fn comparable(name: &str) -> Vec<char> {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+        .chars()
+        .collect()
+}
+
+/// Levenshtein distance between `a` and `b`
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, a_char) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// An item whose name is close to another's
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PossibleDuplicate {
+    pub item: Item,
+    /// Similarity of the names, from 0 to 1
+    #[schema(example = 0.92)]
+    pub similarity: f64,
+}
+
+/// Items that may duplicate an item, most similar first
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DuplicateList {
+    pub item_id: String,
+    /// Similarity the listed items reach
+    #[schema(example = 0.8)]
+    pub threshold: f64,
+    pub duplicates: Vec<PossibleDuplicate>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    fn request(name: &str) -> CreateItemRequest {
+        CreateItemRequest {
+            name: name.to_string(),
+            description: None,
+            metadata: None,
+            custom_fields: Default::default(),
+            status: Default::default(),
+            publish_at: None,
+            external_id: None,
+            source: None,
+        }
+    }
+
+    #[test]
+    fn test_name_similarity() {
+        assert_eq!(name_similarity("Widget Pro", "widget   PRO"), 1.0);
+        assert!((name_similarity("Widget", "Widgets") - 6.0 / 7.0).abs() < 1e-9);
+        assert!(name_similarity("Widget", "Gadget") < 0.7);
+        assert_eq!(name_similarity("", "Widget"), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_repeat_within_window_finds_previous_item() {
+        let clock = Arc::new(ManualClock::default());
+        let guard = DuplicateGuard::new(Duration::from_secs(10)).with_clock(clock.clone());
+
+        let first = guard.begin("alice", &request("Widget")).await;
+        assert_eq!(first.previous(), None);
+        first.created("item-1");
+
+        let repeat = guard.begin("alice", &request("Widget")).await;
+        assert_eq!(repeat.previous(), Some("item-1"));
+        drop(repeat);
+        // Other principals and bodies are not duplicates
+        assert_eq!(guard.begin("bob", &request("Widget")).await.previous(), None);
+        assert_eq!(guard.begin("alice", &request("Gadget")).await.previous(), None);
+
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(guard.begin("alice", &request("Widget")).await.previous(), None);
+    }
+
+    #[tokio::test]
+    async fn test_resubmissions_with_other_data_are_not_duplicates() {
+        let guard = DuplicateGuard::new(Duration::from_secs(10));
+        let colored = |metadata: Value| CreateItemRequest {
+            metadata: Some(metadata),
+            ..request("Widget")
+        };
+
+        let first = guard
+            .begin("alice", &colored(serde_json::json!({ "color": "red", "size": 2 })))
+            .await;
+        first.created("item-1");
+        let reordered = colored(serde_json::json!({ "size": 2, "color": "red" }));
+        assert_eq!(guard.begin("alice", &reordered).await.previous(), Some("item-1"));
+        let recolored = colored(serde_json::json!({ "color": "blue", "size": 2 }));
+        assert_eq!(guard.begin("alice", &recolored).await.previous(), None);
+    }
+
+    #[tokio::test]
+    async fn test_every_field_tells_submissions_apart() {
+        let guard = DuplicateGuard::new(Duration::from_secs(10));
+        guard
+            .begin("alice", &request("Widget"))
+            .await
+            .created("item-1");
+
+        let variants = [
+            CreateItemRequest {
+                custom_fields: [("priority".to_string(), serde_json::json!(3))].into(),
+                ..request("Widget")
+            },
+            CreateItemRequest {
+                status: crate::models::ItemStatus::Draft,
+                ..request("Widget")
+            },
+            CreateItemRequest {
+                status: crate::models::ItemStatus::Draft,
+                publish_at: Some(chrono::Utc::now()),
+                ..request("Widget")
+            },
+            CreateItemRequest {
+                external_id: Some("4711".to_string()),
+                ..request("Widget")
+            },
+            CreateItemRequest {
+                external_id: Some("4711".to_string()),
+                source: Some("crm".to_string()),
+                ..request("Widget")
+            },
+        ];
+        // Each variant is told apart from the original and every one before it
+        for variant in variants {
+            let submission = guard.begin("alice", &variant).await;
+            assert_eq!(submission.previous(), None, "{variant:?}");
+            submission.created("item-2");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_duplicate_waits_for_first() {
+        let guard = Arc::new(DuplicateGuard::new(Duration::from_secs(10)));
+        let first = guard.begin("alice", &request("Widget")).await;
+
+        let waiting = {
+            let guard = guard.clone();
+            tokio::spawn(async move {
+                let submission = guard.begin("alice", &request("Widget")).await;
+                submission.previous().map(str::to_string)
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!waiting.is_finished());
+
+        first.created("item-1");
+        assert_eq!(waiting.await.unwrap().as_deref(), Some("item-1"));
+    }
+}
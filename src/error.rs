@@ -76,6 +76,11 @@ pub enum ErrorCode {
     NotFound,
     Unauthorized,
     Forbidden,
+    MethodNotAllowed,
+    Conflict,
+    /// The item is checked out by someone else
+    Locked,
+    PayloadTooLarge,
     RateLimitExceeded,
 
     // Server errors (5xx)
@@ -83,6 +88,7 @@ pub enum ErrorCode {
     DatabaseError,
     LockError,
     ServiceUnavailable,
+    DeadlineExceeded,
 }
 
 #[derive(Debug)]
@@ -90,7 +96,13 @@ pub enum AppError {
     NotFound(String),
     InternalServerError(String),
     BadRequest(String),
+    Unauthorized(String),
+    Forbidden(String),
+    /// The item is locked by another principal (423)
+    Locked(String),
     ValidationError(String),
+    ServiceUnavailable(String),
+    TooManyRequests(String),
     LockError,
     DatabaseError(DatabaseError),
 }
@@ -101,7 +113,12 @@ impl fmt::Display for AppError {
             AppError::NotFound(msg) => write!(f, "Not found: {msg}"),
             AppError::InternalServerError(msg) => write!(f, "Internal server error: {msg}"),
             AppError::BadRequest(msg) => write!(f, "Bad request: {msg}"),
+            AppError::Unauthorized(msg) => write!(f, "Unauthorized: {msg}"),
+            AppError::Forbidden(msg) => write!(f, "Forbidden: {msg}"),
+            AppError::Locked(msg) => write!(f, "Locked: {msg}"),
             AppError::ValidationError(msg) => write!(f, "Validation error: {msg}"),
+            AppError::ServiceUnavailable(msg) => write!(f, "Service unavailable: {msg}"),
+            AppError::TooManyRequests(msg) => write!(f, "Too many requests: {msg}"),
             AppError::LockError => write!(f, "Failed to acquire lock"),
             AppError::DatabaseError(e) => write!(f, "Database error: {e}"),
         }
@@ -121,6 +138,11 @@ impl IntoResponse for AppError {
             AppError::BadRequest(msg) => {
                 (StatusCode::BAD_REQUEST, ErrorCode::BadRequest, msg, None)
             }
+            AppError::Unauthorized(msg) => {
+                (StatusCode::UNAUTHORIZED, ErrorCode::Unauthorized, msg, None)
+            }
+            AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, ErrorCode::Forbidden, msg, None),
+            AppError::Locked(msg) => (StatusCode::LOCKED, ErrorCode::Locked, msg, None),
             AppError::ValidationError(msg) => {
                 // Try to parse validation errors for field-specific details
                 let details = parse_validation_errors(&msg);
@@ -134,6 +156,12 @@ impl IntoResponse for AppError {
                     }),
                 )
             }
+            AppError::ServiceUnavailable(msg) => {
+                (StatusCode::SERVICE_UNAVAILABLE, ErrorCode::ServiceUnavailable, msg, None)
+            }
+            AppError::TooManyRequests(msg) => {
+                (StatusCode::TOO_MANY_REQUESTS, ErrorCode::RateLimitExceeded, msg, None)
+            }
             AppError::LockError => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 ErrorCode::LockError,
@@ -147,6 +175,18 @@ impl IntoResponse for AppError {
                     "Resource not found".to_string(),
                     None,
                 ),
+                DatabaseError::Conflict(msg) => {
+                    (StatusCode::CONFLICT, ErrorCode::Conflict, msg, None)
+                }
+                mismatch @ DatabaseError::VersionMismatch { .. } => {
+                    (StatusCode::CONFLICT, ErrorCode::Conflict, mismatch.to_string(), None)
+                }
+                DatabaseError::DeadlineExceeded => (
+                    StatusCode::GATEWAY_TIMEOUT,
+                    ErrorCode::DeadlineExceeded,
+                    "Request deadline exceeded".to_string(),
+                    None,
+                ),
                 DatabaseError::ConnectionError(msg) => (
                     StatusCode::SERVICE_UNAVAILABLE,
                     ErrorCode::ServiceUnavailable,
@@ -180,6 +220,12 @@ impl IntoResponse for AppError {
                     "Failed to acquire database lock".to_string(),
                     None,
                 ),
+                DatabaseError::Overloaded => (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    ErrorCode::ServiceUnavailable,
+                    "Database is overloaded".to_string(),
+                    None,
+                ),
             },
         };
 
@@ -243,6 +289,17 @@ impl From<crate::validation::ValidationRejection> for AppError {
     }
 }
 
+impl From<crate::locks::LockError> for AppError {
+    fn from(error: crate::locks::LockError) -> Self {
+        match error {
+            crate::locks::LockError::Timeout(_) => AppError::DatabaseError(
+                DatabaseError::Conflict("Another request is changing this resource".to_string()),
+            ),
+            crate::locks::LockError::Backend(e) => AppError::ServiceUnavailable(e),
+        }
+    }
+}
+
 impl From<validator::ValidationErrors> for AppError {
     fn from(errors: validator::ValidationErrors) -> Self {
         // For query parameter validation, we want to return BadRequest
@@ -258,6 +315,14 @@ mod tests {
     fn test_error_to_status_code_mapping() {
         let test_cases = vec![
             (AppError::NotFound("test".to_string()), StatusCode::NOT_FOUND),
+            (AppError::Unauthorized("test".to_string()), StatusCode::UNAUTHORIZED),
+            (AppError::Forbidden("test".to_string()), StatusCode::FORBIDDEN),
+            (AppError::Locked("test".to_string()), StatusCode::LOCKED),
+            (
+                AppError::ServiceUnavailable("test".to_string()),
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+            (AppError::TooManyRequests("test".to_string()), StatusCode::TOO_MANY_REQUESTS),
             (AppError::ValidationError("test".to_string()), StatusCode::UNPROCESSABLE_ENTITY),
             (AppError::DatabaseError(DatabaseError::NotFound), StatusCode::NOT_FOUND),
             (
@@ -288,6 +353,7 @@ mod tests {
     fn test_database_error_conversion() {
         let db_errors = vec![
             (DatabaseError::NotFound, StatusCode::NOT_FOUND),
+            (DatabaseError::Conflict("test".to_string()), StatusCode::CONFLICT),
             (DatabaseError::QueryError("test".to_string()), StatusCode::INTERNAL_SERVER_ERROR),
             (
                 DatabaseError::ConnectionError("test".to_string()),
@@ -309,6 +375,7 @@ mod tests {
             ErrorCode::NotFound,
             ErrorCode::Unauthorized,
             ErrorCode::Forbidden,
+            ErrorCode::Conflict,
             ErrorCode::RateLimitExceeded,
             ErrorCode::InternalServerError,
             ErrorCode::ServiceUnavailable,
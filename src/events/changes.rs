@@ -0,0 +1,316 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, sync::Mutex, time::Duration};
+use tokio::sync::watch;
+use utoipa::ToSchema;
+
+use super::{ItemEventType, OutboxEvent};
+use crate::{error::AppError, models::Item};
+
+/// How long a change feed request waits when the caller does not say
+pub const DEFAULT_CHANGES_WAIT: Duration = Duration::from_secs(30);
+
+/// Longest a change feed request may wait
+pub const MAX_CHANGES_WAIT: Duration = Duration::from_secs(60);
+
+/// Most changes returned by one change feed request
+pub const MAX_CHANGES_PER_RESPONSE: usize = 100;
+
+/// Item change as returned by the change feed
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Change {
+    /// Outbox position of the change
+    #[schema(example = 42)]
+    pub sequence: u64,
+    #[serde(rename = "type")]
+    pub change_type: ItemEventType,
+    /// The item after the change (before it, for deletions)
+    pub item: Item,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl From<&OutboxEvent> for Change {
+    fn from(event: &OutboxEvent) -> Self {
+        Self {
+            sequence: event.sequence,
+            change_type: event.event_type,
+            item: event.item.clone(),
+            occurred_at: event.occurred_at,
+        }
+    }
+}
+
+/// Changes for one change feed request, and where the next one continues
+#[derive(Debug, Clone)]
+pub struct ChangeBatch {
+    pub changes: Vec<Change>,
+    pub next_cursor: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ChangeFeedError {
+    #[error("Invalid change cursor")]
+    Cursor,
+    /// The cursor is older than the changes still retained, or was issued
+    /// before this instance restarted
+    #[error("Change cursor has expired; list items again and follow the feed from now")]
+    Expired,
+}
+
+impl From<ChangeFeedError> for AppError {
+    fn from(error: ChangeFeedError) -> Self {
+        AppError::BadRequest(error.to_string())
+    }
+}
+
+struct Retained {
+    /// Position of the oldest retained change
+    first: u64,
+    changes: VecDeque<Change>,
+}
+
+/// Most recent changes delivered through the event bus, in delivery order
+///
+/// Cursors are positions in this log rather than outbox sequences, because
+/// the outbox may deliver an event that failed earlier after later ones. They
+/// carry an epoch so that cursors from before a restart are recognised as
+/// expired instead of pointing at unrelated changes.
+pub struct ChangeLog {
+    epoch: String,
+    capacity: usize,
+    retained: Mutex<Retained>,
+    /// Position after the newest change, bumped on every delivery
+    head: watch::Sender<u64>,
+}
+
+impl ChangeLog {
+    pub fn new(capacity: usize) -> Self {
+        let (head, _) = watch::channel(0);
+        Self {
+            epoch: uuid::Uuid::new_v4().simple().to_string()[..8].to_string(),
+            capacity: capacity.max(1),
+            retained: Mutex::new(Retained {
+                first: 0,
+                changes: VecDeque::with_capacity(capacity),
+            }),
+            head,
+        }
+    }
+
+    /// Append a delivered event, dropping the oldest change when full
+    pub fn record(&self, event: &OutboxEvent) {
+        let mut retained = self.retained.lock().unwrap_or_else(|e| e.into_inner());
+        if retained.changes.len() == self.capacity {
+            retained.changes.pop_front();
+            retained.first += 1;
+        }
+        retained.changes.push_back(Change::from(event));
+        let head = retained.first + retained.changes.len() as u64;
+        drop(retained);
+        self.head.send_replace(head);
+    }
+
+    /// Cursor positioned after the newest change
+    pub fn cursor(&self) -> String {
+        self.encode(*self.head.borrow())
+    }
+
+    /// Changes after `since` (or from now) accepted by `visible`, waiting up to
+    /// `wait` for the first one to arrive
+    pub async fn wait(
+        &self,
+        since: Option<&str>,
+        wait: Duration,
+        visible: impl Fn(&Change) -> bool,
+    ) -> Result<ChangeBatch, ChangeFeedError> {
+        // Subscribe before reading so a change landing in between still wakes us
+        let mut head = self.head.subscribe();
+        let mut position = match since {
+            Some(cursor) => self.decode(cursor)?,
+            None => *head.borrow_and_update(),
+        };
+        let deadline = tokio::time::Instant::now() + wait;
+
+        loop {
+            let (changes, scanned) = self.after(position, &visible)?;
+            position = scanned;
+            if !changes.is_empty() {
+                return Ok(ChangeBatch {
+                    changes,
+                    next_cursor: self.encode(position),
+                });
+            }
+            // Nothing new before the deadline answers with an empty batch
+            match tokio::time::timeout_at(deadline, head.changed()).await {
+                Ok(Ok(())) => {}
+                Ok(Err(_)) | Err(_) => {
+                    return Ok(ChangeBatch {
+                        changes: Vec::new(),
+                        next_cursor: self.encode(position),
+                    })
+                }
+            }
+        }
+    }
+
+    /// Visible changes from `position`, and the position after the last one
+    /// scanned
+    fn after(
+        &self,
+        position: u64,
+        visible: &impl Fn(&Change) -> bool,
+    ) -> Result<(Vec<Change>, u64), ChangeFeedError> {
+        let retained = self.retained.lock().unwrap_or_else(|e| e.into_inner());
+        let head = retained.first + retained.changes.len() as u64;
+        if position < retained.first || position > head {
+            return Err(ChangeFeedError::Expired);
+        }
+
+        let mut changes = Vec::new();
+        let mut scanned = position;
+        let start = usize::try_from(position - retained.first).unwrap_or(usize::MAX);
+        for change in retained.changes.iter().skip(start) {
+            if changes.len() == MAX_CHANGES_PER_RESPONSE {
+                break;
+            }
+            scanned += 1;
+            if visible(change) {
+                changes.push(change.clone());
+            }
+        }
+        Ok((changes, scanned))
+    }
+
+    fn encode(&self, position: u64) -> String {
+        format!("{}-{position}", self.epoch)
+    }
+
+    fn decode(&self, cursor: &str) -> Result<u64, ChangeFeedError> {
+        let (epoch, position) = cursor.split_once('-').ok_or(ChangeFeedError::Cursor)?;
+        let position = position.parse().map_err(|_| ChangeFeedError::Cursor)?;
+        if epoch != self.epoch {
+            return Err(ChangeFeedError::Expired);
+        }
+        Ok(position)
+    }
+}
+
+/// Parse a wait such as `30s`, `500ms` or `1m` (bare numbers are seconds),
+/// capped at [`MAX_CHANGES_WAIT`]
+pub fn parse_wait(value: &str) -> Result<Duration, AppError> {
+    let invalid = || AppError::BadRequest(format!("Invalid wait: {value}"));
+    let value = value.trim();
+    let (number, unit) = value
+        .find(|c: char| !c.is_ascii_digit())
+        .map_or((value, ""), |at| value.split_at(at));
+    let number: u64 = number.parse().map_err(|_| invalid())?;
+    let wait = match unit {
+        "" | "s" => Duration::from_secs(number),
+        "ms" => Duration::from_millis(number),
+        "m" => Duration::from_secs(number.saturating_mul(60)),
+        _ => return Err(invalid()),
+    };
+    Ok(wait.min(MAX_CHANGES_WAIT))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn event(sequence: u64, name: &str) -> OutboxEvent {
+        let now = Utc::now();
+        OutboxEvent::new(
+            sequence,
+            ItemEventType::Created,
+            Item {
+                id: format!("item-{sequence}"),
+                name: name.to_string(),
+                slug: name.to_lowercase(),
+                external_id: None,
+                source: None,
+                description: None,
+                metadata: None,
+                custom_fields: Default::default(),
+                owner_id: None,
+                tenant_id: None,
+                status: Default::default(),
+                publish_at: None,
+                created_at: now,
+                updated_at: now,
+                version: 1,
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn test_changes_after_cursor_skip_invisible_ones() {
+        let log = ChangeLog::new(10);
+        let start = log.cursor();
+        log.record(&event(1, "Visible"));
+        log.record(&event(2, "Hidden"));
+        log.record(&event(3, "Visible"));
+
+        let batch = log
+            .wait(Some(&start), Duration::ZERO, |c| c.item.name == "Visible")
+            .await
+            .unwrap();
+        let sequences: Vec<_> = batch.changes.iter().map(|c| c.sequence).collect();
+        assert_eq!(sequences, vec![1, 3]);
+        assert_eq!(batch.next_cursor, log.cursor());
+
+        // Without a cursor the feed starts from now
+        let batch = log.wait(None, Duration::ZERO, |_| true).await.unwrap();
+        assert!(batch.changes.is_empty());
+        assert_eq!(batch.next_cursor, log.cursor());
+    }
+
+    #[tokio::test]
+    async fn test_wait_returns_when_a_change_arrives() {
+        let log = Arc::new(ChangeLog::new(10));
+        let cursor = log.cursor();
+        let waiting = {
+            let log = log.clone();
+            tokio::spawn(async move {
+                log.wait(Some(&cursor), Duration::from_secs(5), |_| true)
+                    .await
+                    .unwrap()
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!waiting.is_finished());
+
+        log.record(&event(7, "Widget"));
+        let batch = waiting.await.unwrap();
+        assert_eq!(batch.changes.len(), 1);
+        assert_eq!(batch.changes[0].sequence, 7);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_and_foreign_cursors_expire() {
+        let log = ChangeLog::new(2);
+        let start = log.cursor();
+        for sequence in 1..=3 {
+            log.record(&event(sequence, "Widget"));
+        }
+        let expired = log.wait(Some(&start), Duration::ZERO, |_| true).await;
+        assert_eq!(expired.unwrap_err(), ChangeFeedError::Expired);
+
+        let other = ChangeLog::new(2).cursor();
+        let foreign = log.wait(Some(&other), Duration::ZERO, |_| true).await;
+        assert_eq!(foreign.unwrap_err(), ChangeFeedError::Expired);
+
+        let invalid = log.wait(Some("garbage"), Duration::ZERO, |_| true).await;
+        assert_eq!(invalid.unwrap_err(), ChangeFeedError::Cursor);
+    }
+
+    #[test]
+    fn test_parse_wait() {
+        assert_eq!(parse_wait("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_wait("15").unwrap(), Duration::from_secs(15));
+        assert_eq!(parse_wait("250ms").unwrap(), Duration::from_millis(250));
+        assert_eq!(parse_wait("10m").unwrap(), MAX_CHANGES_WAIT);
+        assert!(parse_wait("soon").is_err());
+        assert!(parse_wait("5h").is_err());
+    }
+}
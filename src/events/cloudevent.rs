@@ -0,0 +1,122 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{ItemEventType, OutboxEvent};
+use crate::models::Item;
+
+/// CloudEvents specification version emitted by this service
+pub const SPEC_VERSION: &str = "1.0";
+
+/// CloudEvents `source` attribute for item events
+pub const EVENT_SOURCE: &str = "/ferrous/items";
+
+/// Content type of every event's `data` payload
+pub const DATA_CONTENT_TYPE: &str = "application/json";
+
+/// CloudEvents 1.0 envelope (structured JSON mode) with a typed `data` payload
+///
+/// This is the single event contract shared by every transport: the in-process
+/// bus, external brokers, and any future push channels.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CloudEvent<T> {
+    #[schema(example = "1.0")]
+    pub specversion: String,
+    /// Unique per event and stable across redeliveries, for consumer deduplication
+    #[schema(example = "a1b2c3d4-e5f6-7890-abcd-ef1234567890")]
+    pub id: String,
+    #[schema(example = "/ferrous/items")]
+    pub source: String,
+    /// One of `com.ferrous.item.created`, `com.ferrous.item.updated`, `com.ferrous.item.deleted`
+    #[serde(rename = "type")]
+    #[schema(example = "com.ferrous.item.created")]
+    pub event_type: String,
+    /// ID of the resource the event is about
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    pub time: DateTime<Utc>,
+    #[schema(example = "application/json")]
+    pub datacontenttype: String,
+    /// Outbox position as a decimal string (CloudEvents `sequence` extension)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "42")]
+    pub sequence: Option<String>,
+    pub data: T,
+}
+
+/// Item change event; `data` is the item after the change (before it, for deletions)
+pub type ItemEvent = CloudEvent<Item>;
+
+impl ItemEventType {
+    /// CloudEvents `type` attribute for this kind of change
+    pub fn cloud_event_type(self) -> &'static str {
+        match self {
+            Self::Created => "com.ferrous.item.created",
+            Self::Updated => "com.ferrous.item.updated",
+            Self::Deleted => "com.ferrous.item.deleted",
+        }
+    }
+}
+
+impl From<&OutboxEvent> for ItemEvent {
+    fn from(event: &OutboxEvent) -> Self {
+        Self {
+            specversion: SPEC_VERSION.to_string(),
+            id: event.id.clone(),
+            source: EVENT_SOURCE.to_string(),
+            event_type: event.event_type.cloud_event_type().to_string(),
+            subject: Some(event.item_id.clone()),
+            time: event.occurred_at,
+            datacontenttype: DATA_CONTENT_TYPE.to_string(),
+            sequence: Some(event.sequence.to_string()),
+            data: event.item.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_item_event_envelope() {
+        let outbox = OutboxEvent::new(
+            7,
+            ItemEventType::Updated,
+            Item {
+                id: "item-1".to_string(),
+                name: "Widget".to_string(),
+                slug: "widget".to_string(),
+                external_id: None,
+                source: None,
+                description: None,
+                metadata: None,
+                custom_fields: Default::default(),
+                owner_id: None,
+                tenant_id: None,
+                status: Default::default(),
+                publish_at: None,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                version: 1,
+            },
+        );
+
+        let event = ItemEvent::from(&outbox);
+        let json = serde_json::to_value(&event).unwrap();
+
+        assert_eq!(json["specversion"], "1.0");
+        assert_eq!(json["id"], outbox.id);
+        assert_eq!(json["source"], "/ferrous/items");
+        assert_eq!(json["type"], "com.ferrous.item.updated");
+        assert_eq!(json["subject"], "item-1");
+        assert_eq!(json["datacontenttype"], "application/json");
+        assert_eq!(json["sequence"], "7");
+        assert_eq!(json["data"]["name"], "Widget");
+
+        let parsed: ItemEvent = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.id, event.id);
+        assert_eq!(parsed.event_type, event.event_type);
+        assert_eq!(parsed.data.id, "item-1");
+    }
+}
@@ -0,0 +1,200 @@
+use async_trait::async_trait;
+use reqwest::{header::CONTENT_TYPE, RequestBuilder, Response, StatusCode};
+use serde_json::json;
+use std::sync::Arc;
+
+use super::{EventPublisher, ItemEvent, OutboxEvent};
+use crate::{http_client::HttpClient, outbound_auth::TokenProvider};
+
+/// Content type for JSON records accepted by the Kafka REST Proxy (v2 API)
+const KAFKA_JSON_V2: &str = "application/vnd.kafka.json.v2+json";
+
+/// Publishes events to a Kafka topic through a Kafka REST Proxy
+///
+/// Records are keyed by item id so every event for an item lands on the same
+/// partition, preserving per-item ordering for consumers.
+pub struct KafkaPublisher {
+    http: HttpClient,
+    rest_url: String,
+    topic: String,
+    tokens: Option<Arc<TokenProvider>>,
+}
+
+impl KafkaPublisher {
+    pub fn new(rest_url: impl Into<String>, topic: impl Into<String>) -> Self {
+        Self {
+            http: HttpClient::default(),
+            rest_url: rest_url.into().trim_end_matches('/').to_string(),
+            topic: topic.into(),
+            tokens: None,
+        }
+    }
+
+    /// Send requests with the shared outbound client
+    #[must_use]
+    pub fn with_http_client(mut self, http: HttpClient) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Authenticate to the REST proxy with client-credentials tokens
+    #[must_use]
+    pub fn with_token_provider(mut self, tokens: Option<Arc<TokenProvider>>) -> Self {
+        self.tokens = tokens;
+        self
+    }
+
+    fn topic_url(&self) -> String {
+        format!("{}/topics/{}", self.rest_url, self.topic)
+    }
+
+    /// Send a request, with a bearer token when configured
+    async fn send(&self, request: impl Fn() -> RequestBuilder) -> Result<Response, String> {
+        let Some(tokens) = &self.tokens else {
+            return self
+                .http
+                .send(request())
+                .await
+                .map_err(|e| format!("request failed: {e}"));
+        };
+
+        let mut retried = false;
+        loop {
+            let request = tokens
+                .authorize(request())
+                .await
+                .map_err(|e| e.to_string())?;
+            let response = self
+                .http
+                .send(request)
+                .await
+                .map_err(|e| format!("request failed: {e}"))?;
+
+            // The token may have been revoked before it expired; retry once
+            // with a new one
+            if response.status() == StatusCode::UNAUTHORIZED && !retried {
+                tokens.invalidate().await;
+                retried = true;
+                continue;
+            }
+            return Ok(response);
+        }
+    }
+}
+
+#[async_trait]
+impl EventPublisher for KafkaPublisher {
+    fn name(&self) -> &'static str {
+        "kafka"
+    }
+
+    async fn publish(&self, event: &OutboxEvent) -> Result<(), String> {
+        let body = json!({
+            "records": [{
+                "key": event.item_id,
+                "value": ItemEvent::from(event),
+            }]
+        });
+
+        let response = self
+            .send(|| {
+                self.http
+                    .post(self.topic_url())
+                    .header(CONTENT_TYPE, KAFKA_JSON_V2)
+                    .json(&body)
+            })
+            .await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("REST proxy responded with {}", response.status()))
+        }
+    }
+
+    async fn health_check(&self) -> Result<(), String> {
+        let response = self.send(|| self.http.get(self.topic_url())).await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("REST proxy responded with {}", response.status()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{events::ItemEventType, models::Item};
+    use axum::{extract::State, http::HeaderMap, routing::post, Json, Router};
+    use chrono::Utc;
+    use std::sync::{Arc, Mutex};
+
+    type Received = Arc<Mutex<Vec<(String, serde_json::Value)>>>;
+
+    async fn spawn_rest_proxy(received: Received) -> String {
+        let app = Router::new()
+            .route(
+                "/topics/{topic}",
+                post(
+                    |State(received): State<Received>,
+                     headers: HeaderMap,
+                     Json(body): Json<serde_json::Value>| async move {
+                        let content_type = headers[CONTENT_TYPE].to_str().unwrap().to_string();
+                        received.lock().unwrap().push((content_type, body));
+                        Json(json!({ "offsets": [{ "partition": 0, "offset": 1 }] }))
+                    },
+                )
+                .get(|| async { "{}" }),
+            )
+            .with_state(received);
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn test_publishes_keyed_cloud_event() {
+        let received = Received::default();
+        let url = spawn_rest_proxy(received.clone()).await;
+        let publisher = KafkaPublisher::new(url, "ferrous.items");
+
+        let item = Item {
+            id: "item-1".to_string(),
+            name: "Item".to_string(),
+            slug: "item".to_string(),
+            external_id: None,
+            source: None,
+            description: None,
+            metadata: None,
+            custom_fields: Default::default(),
+            owner_id: None,
+            tenant_id: None,
+            status: Default::default(),
+            publish_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            version: 1,
+        };
+        let event = OutboxEvent::new(1, ItemEventType::Created, item);
+
+        publisher.health_check().await.unwrap();
+        publisher.publish(&event).await.unwrap();
+
+        let received = received.lock().unwrap();
+        let (content_type, body) = &received[0];
+        assert_eq!(content_type, KAFKA_JSON_V2);
+        assert_eq!(body["records"][0]["key"], "item-1");
+        assert_eq!(body["records"][0]["value"]["specversion"], "1.0");
+        assert_eq!(body["records"][0]["value"]["type"], "com.ferrous.item.created");
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_proxy_is_reported() {
+        let publisher = KafkaPublisher::new("http://127.0.0.1:1", "ferrous.items");
+        assert!(publisher.health_check().await.is_err());
+    }
+}
@@ -0,0 +1,556 @@
+pub mod changes;
+pub mod cloudevent;
+pub mod kafka;
+pub mod nats;
+pub mod replay;
+pub mod webhook;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, sync::Arc, time::Duration};
+use tokio::{sync::broadcast, task::JoinHandle};
+use tracing::{debug, warn};
+use utoipa::ToSchema;
+
+use crate::{
+    comments::CommentEvent,
+    config::EventsConfig,
+    context::RequestContext,
+    db::{DatabaseResult, ItemRepository},
+    dead_letters::{DeadLetterQueue, DeadLetterSource, Retry},
+    http_client::HttpClient,
+    leadership::Leadership,
+    metrics::{track_event_publish, track_outbox_dispatch, Timer, OUTBOX_PENDING_EVENTS},
+    models::Item,
+    outbound_auth::TokenProvider,
+};
+
+pub use changes::{Change, ChangeLog};
+pub use cloudevent::{CloudEvent, ItemEvent};
+pub use kafka::KafkaPublisher;
+pub use nats::NatsPublisher;
+pub use webhook::WebhookPublisher;
+
+/// Kind of change recorded for an item
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemEventType {
+    Created,
+    Updated,
+    Deleted,
+}
+
+impl ItemEventType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Created => "created",
+            Self::Updated => "updated",
+            Self::Deleted => "deleted",
+        }
+    }
+}
+
+/// Item change written to the outbox in the same transaction as the mutation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxEvent {
+    /// Monotonic position in the outbox, defining publish order
+    pub sequence: u64,
+    /// Unique event identifier, stable across redeliveries
+    pub id: String,
+    pub event_type: ItemEventType,
+    pub item_id: String,
+    /// Snapshot of the item after the change (before it, for deletions)
+    pub item: Item,
+    pub occurred_at: DateTime<Utc>,
+    /// Principal whose request made the change, for auditing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor: Option<String>,
+    /// ID of the request that made the change
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// Set once every publisher has accepted the event
+    pub published_at: Option<DateTime<Utc>>,
+    /// Number of failed delivery attempts
+    pub attempts: u32,
+    pub last_error: Option<String>,
+}
+
+impl OutboxEvent {
+    pub fn new(sequence: u64, event_type: ItemEventType, item: Item) -> Self {
+        Self {
+            sequence,
+            id: uuid::Uuid::new_v4().to_string(),
+            event_type,
+            item_id: item.id.clone(),
+            item,
+            occurred_at: Utc::now(),
+            actor: None,
+            request_id: None,
+            published_at: None,
+            attempts: 0,
+            last_error: None,
+        }
+    }
+
+    /// Attribute the event to the caller and request in `context`
+    #[must_use]
+    pub fn attributed(mut self, context: &RequestContext) -> Self {
+        self.actor.clone_from(&context.principal);
+        self.request_id.clone_from(&context.request_id);
+        self
+    }
+}
+
+/// Destination for dispatched outbox events
+#[async_trait]
+pub trait EventPublisher: Send + Sync {
+    /// Short name used in logs and metrics
+    fn name(&self) -> &'static str;
+
+    /// Deliver a single event; returning an error schedules a retry
+    async fn publish(&self, event: &OutboxEvent) -> Result<(), String>;
+
+    /// Check connectivity to the underlying broker
+    async fn health_check(&self) -> Result<(), String>;
+}
+
+/// Create the external broker publisher selected by configuration, if any
+pub async fn create_publisher(
+    config: &EventsConfig,
+    tokens: Option<Arc<TokenProvider>>,
+    http: &HttpClient,
+) -> Result<Option<Arc<dyn EventPublisher>>, String> {
+    match config.publisher.as_deref() {
+        None => Ok(None),
+        Some("kafka") => {
+            let url = config
+                .kafka_rest_url
+                .as_ref()
+                .ok_or("Kafka publisher requires KAFKA_REST_URL")?;
+            let publisher = KafkaPublisher::new(url, &config.kafka_topic)
+                .with_http_client(http.clone())
+                .with_token_provider(tokens);
+            Ok(Some(Arc::new(publisher)))
+        }
+        Some("nats") => {
+            let url = config
+                .nats_url
+                .as_ref()
+                .ok_or("NATS publisher requires NATS_URL")?;
+            let publisher = NatsPublisher::connect(url, &config.nats_subject_prefix).await?;
+            Ok(Some(Arc::new(publisher)))
+        }
+        Some(other) => Err(format!("Unknown event publisher: {other}")),
+    }
+}
+
+/// In-process broadcast bus delivering CloudEvents to subscribers inside this service
+///
+/// The most recent `capacity` changes are also kept for the change feed.
+/// Comment events travel on a channel of their own; they are emitted as
+/// comments change rather than through the outbox, so they are not
+/// redelivered and never reach external brokers.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<ItemEvent>,
+    comments: broadcast::Sender<CommentEvent>,
+    changes: Arc<ChangeLog>,
+}
+
+impl EventBus {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        let (comments, _) = broadcast::channel(capacity);
+        Self {
+            sender,
+            comments,
+            changes: Arc::new(ChangeLog::new(capacity)),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ItemEvent> {
+        self.sender.subscribe()
+    }
+
+    pub fn subscribe_comments(&self) -> broadcast::Receiver<CommentEvent> {
+        self.comments.subscribe()
+    }
+
+    /// Announce a comment change to subscribers
+    pub fn publish_comment(&self, event: CommentEvent) {
+        // Having no subscribers is not a delivery failure
+        let _ = self.comments.send(event);
+    }
+
+    /// Recent changes delivered through the bus
+    pub fn changes(&self) -> &ChangeLog {
+        &self.changes
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(1024)
+    }
+}
+
+#[async_trait]
+impl EventPublisher for EventBus {
+    fn name(&self) -> &'static str {
+        "bus"
+    }
+
+    async fn publish(&self, event: &OutboxEvent) -> Result<(), String> {
+        self.changes.record(event);
+        // Having no subscribers is not a delivery failure
+        let _ = self.sender.send(ItemEvent::from(event));
+        Ok(())
+    }
+
+    async fn health_check(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Background task that drains the outbox into the configured publishers
+///
+/// Delivery is at-least-once: an event is only marked published after every
+/// publisher accepted it, so a crash between publishing and marking causes a
+/// redelivery rather than a lost event. When an event fails, later events for
+/// the same item are held back until it succeeds, preserving per-item order.
+/// With `max_attempts` and a dead letter queue, an event failing that often
+/// goes to the queue instead, releasing the events behind it.
+#[derive(Clone)]
+pub struct OutboxDispatcher {
+    repo: Arc<dyn ItemRepository>,
+    publishers: Vec<Arc<dyn EventPublisher>>,
+    batch_size: usize,
+    poll_interval: Duration,
+    max_attempts: Option<u32>,
+    dead_letters: Option<Arc<DeadLetterQueue>>,
+    leadership: Leadership,
+}
+
+impl OutboxDispatcher {
+    pub fn new(
+        repo: Arc<dyn ItemRepository>,
+        publishers: Vec<Arc<dyn EventPublisher>>,
+        config: &EventsConfig,
+    ) -> Self {
+        Self {
+            repo,
+            publishers,
+            batch_size: config.outbox_batch_size,
+            poll_interval: Duration::from_millis(config.outbox_poll_interval_ms),
+            max_attempts: config.max_attempts,
+            dead_letters: None,
+            leadership: Leadership::always(),
+        }
+    }
+
+    /// Dead-letter events that exhaust `max_attempts`
+    #[must_use]
+    pub fn with_dead_letters(mut self, dead_letters: Arc<DeadLetterQueue>) -> Self {
+        self.dead_letters = Some(dead_letters);
+        self
+    }
+
+    /// Dispatch only while this replica leads, so replicas sharing a
+    /// database do not deliver the same events
+    #[must_use]
+    pub fn with_leadership(mut self, leadership: Leadership) -> Self {
+        self.leadership = leadership;
+        self
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.leadership.is_leader()
+    }
+
+    /// Run the dispatcher until the task is aborted
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.poll_interval);
+            loop {
+                interval.tick().await;
+                if !self.is_leader() {
+                    continue;
+                }
+                if let Err(e) = self.dispatch_once().await {
+                    warn!("Outbox dispatch failed: {}", e);
+                }
+            }
+        })
+    }
+
+    /// Publish one batch of pending events, returning how many were published
+    pub async fn dispatch_once(&self) -> DatabaseResult<usize> {
+        let pending = self.repo.pending_events(self.batch_size).await?;
+        let mut blocked_items = HashSet::new();
+        let mut published = Vec::new();
+        let mut dead_lettered = 0;
+
+        for event in &pending {
+            if blocked_items.contains(&event.item_id) {
+                continue;
+            }
+
+            match self.publish_to_all(event).await {
+                Ok(()) => published.push(event.sequence),
+                Err(error) if self.exhausted(event) => {
+                    if let Some(dead_letters) = &self.dead_letters {
+                        dead_letters.push(
+                            DeadLetterSource::Outbox,
+                            format!(
+                                "{} {} for item {}",
+                                event.event_type.as_str(),
+                                event.id,
+                                event.item_id
+                            ),
+                            event.attempts + 1,
+                            error,
+                            serde_json::to_value(event).unwrap_or_default(),
+                        )?;
+                    }
+                    // Out of the outbox, and out of the way of later events
+                    published.push(event.sequence);
+                    dead_lettered += 1;
+                }
+                Err(error) => {
+                    warn!(
+                        sequence = event.sequence,
+                        item_id = %event.item_id,
+                        attempts = event.attempts + 1,
+                        "Failed to publish outbox event: {}", error
+                    );
+                    blocked_items.insert(event.item_id.clone());
+                    self.repo.mark_event_failed(event.sequence, &error).await?;
+                }
+            }
+        }
+
+        if !published.is_empty() {
+            self.repo.mark_events_published(&published).await?;
+        }
+        let delivered = published.len() - dead_lettered;
+        if delivered > 0 {
+            debug!("Published {} outbox events", delivered);
+        }
+
+        let failed = pending.len() - delivered;
+        track_outbox_dispatch(delivered, failed);
+        let remaining = self.repo.pending_event_count().await?;
+        OUTBOX_PENDING_EVENTS.set(i64::try_from(remaining).unwrap_or(i64::MAX));
+
+        Ok(delivered)
+    }
+
+    fn exhausted(&self, event: &OutboxEvent) -> bool {
+        self.dead_letters.is_some()
+            && self
+                .max_attempts
+                .is_some_and(|max| event.attempts + 1 >= max)
+    }
+
+    async fn publish_to_all(&self, event: &OutboxEvent) -> Result<(), String> {
+        for publisher in &self.publishers {
+            let timer = Timer::new();
+            let result = publisher.publish(event).await;
+            track_event_publish(publisher.name(), result.is_ok(), timer.elapsed_seconds());
+            result.map_err(|e| format!("{}: {e}", publisher.name()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Dead-lettered events are published to every publisher again
+#[async_trait]
+impl Retry for OutboxDispatcher {
+    async fn retry(&self, payload: &serde_json::Value) -> Result<(), String> {
+        let event: OutboxEvent =
+            serde_json::from_value(payload.clone()).map_err(|e| format!("invalid event: {e}"))?;
+        self.publish_to_all(&event).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::InMemoryRepository,
+        models::{CreateItemRequest, UpdateItemRequest},
+    };
+    use std::sync::Mutex;
+
+    /// Publisher that records deliveries and fails for selected item names
+    #[derive(Default)]
+    struct RecordingPublisher {
+        delivered: Mutex<Vec<(String, ItemEventType)>>,
+        failing_names: Mutex<HashSet<String>>,
+    }
+
+    #[async_trait]
+    impl EventPublisher for RecordingPublisher {
+        fn name(&self) -> &'static str {
+            "recording"
+        }
+
+        async fn publish(&self, event: &OutboxEvent) -> Result<(), String> {
+            if self
+                .failing_names
+                .lock()
+                .unwrap()
+                .contains(&event.item.name)
+            {
+                return Err("broker unavailable".to_string());
+            }
+            self.delivered
+                .lock()
+                .unwrap()
+                .push((event.item_id.clone(), event.event_type));
+            Ok(())
+        }
+
+        async fn health_check(&self) -> Result<(), String> {
+            Ok(())
+        }
+    }
+
+    fn create_request(name: &str) -> CreateItemRequest {
+        CreateItemRequest {
+            name: name.to_string(),
+            description: None,
+            metadata: None,
+            custom_fields: Default::default(),
+            status: Default::default(),
+            publish_at: None,
+            external_id: None,
+            source: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mutations_are_published_in_order() {
+        let repo: Arc<dyn ItemRepository> = Arc::new(InMemoryRepository::new());
+        let publisher = Arc::new(RecordingPublisher::default());
+        let dispatcher =
+            OutboxDispatcher::new(repo.clone(), vec![publisher.clone()], &EventsConfig::default());
+
+        let item = repo.create(create_request("Tracked"), None).await.unwrap();
+        let update = UpdateItemRequest {
+            name: Some("Still tracked".to_string()),
+            description: None,
+            metadata: None,
+            custom_fields: Default::default(),
+            regenerate_slug: false,
+            status: Default::default(),
+            publish_at: None,
+        };
+        repo.update(&item.id, update, None).await.unwrap();
+        repo.delete(&item.id).await.unwrap();
+
+        assert_eq!(dispatcher.dispatch_once().await.unwrap(), 3);
+        assert_eq!(
+            *publisher.delivered.lock().unwrap(),
+            vec![
+                (item.id.clone(), ItemEventType::Created),
+                (item.id.clone(), ItemEventType::Updated),
+                (item.id.clone(), ItemEventType::Deleted),
+            ]
+        );
+
+        // Published events are not delivered again
+        assert_eq!(dispatcher.dispatch_once().await.unwrap(), 0);
+        assert_eq!(repo.pending_event_count().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_failed_events_block_later_events_for_same_item() {
+        let repo: Arc<dyn ItemRepository> = Arc::new(InMemoryRepository::new());
+        let publisher = Arc::new(RecordingPublisher::default());
+        publisher
+            .failing_names
+            .lock()
+            .unwrap()
+            .insert("Flaky".to_string());
+        let dispatcher =
+            OutboxDispatcher::new(repo.clone(), vec![publisher.clone()], &EventsConfig::default());
+
+        let flaky = repo.create(create_request("Flaky"), None).await.unwrap();
+        let healthy = repo.create(create_request("Healthy"), None).await.unwrap();
+        repo.delete(&flaky.id).await.unwrap();
+
+        // The flaky item's delete must not overtake its failed create
+        assert_eq!(dispatcher.dispatch_once().await.unwrap(), 1);
+        assert_eq!(
+            *publisher.delivered.lock().unwrap(),
+            vec![(healthy.id.clone(), ItemEventType::Created)]
+        );
+        let pending = repo.pending_events(10).await.unwrap();
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].attempts, 1);
+        assert_eq!(pending[0].last_error.as_deref(), Some("recording: broker unavailable"));
+
+        // Once the publisher recovers both events go out in order
+        publisher.failing_names.lock().unwrap().clear();
+        assert_eq!(dispatcher.dispatch_once().await.unwrap(), 2);
+        let delivered = publisher.delivered.lock().unwrap();
+        assert_eq!(delivered[1], (flaky.id.clone(), ItemEventType::Created));
+        assert_eq!(delivered[2], (flaky.id.clone(), ItemEventType::Deleted));
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_events_are_dead_lettered() {
+        let repo: Arc<dyn ItemRepository> = Arc::new(InMemoryRepository::new());
+        let publisher = Arc::new(RecordingPublisher::default());
+        publisher
+            .failing_names
+            .lock()
+            .unwrap()
+            .insert("Poison".to_string());
+        let config = EventsConfig {
+            max_attempts: Some(2),
+            ..EventsConfig::default()
+        };
+        let dead_letters = Arc::new(DeadLetterQueue::default());
+        let dispatcher = OutboxDispatcher::new(repo.clone(), vec![publisher.clone()], &config)
+            .with_dead_letters(dead_letters.clone());
+        dead_letters
+            .register(DeadLetterSource::Outbox, Arc::new(dispatcher.clone()))
+            .unwrap();
+
+        let poison = repo.create(create_request("Poison"), None).await.unwrap();
+        let rename = UpdateItemRequest {
+            name: Some("Antidote".to_string()),
+            description: None,
+            metadata: None,
+            custom_fields: Default::default(),
+            regenerate_slug: false,
+            status: Default::default(),
+            publish_at: None,
+        };
+        repo.update(&poison.id, rename, None).await.unwrap();
+
+        assert_eq!(dispatcher.dispatch_once().await.unwrap(), 0);
+        assert!(dead_letters.is_empty().unwrap());
+
+        // The second failure exhausts the create, which no longer holds up the update
+        assert_eq!(dispatcher.dispatch_once().await.unwrap(), 1);
+        assert_eq!(repo.pending_event_count().await.unwrap(), 0);
+        let letters = dead_letters.list(None).unwrap();
+        assert_eq!((letters.len(), letters[0].attempts), (1, 2));
+        assert_eq!(letters[0].last_error, "recording: broker unavailable");
+
+        publisher.failing_names.lock().unwrap().clear();
+        dead_letters.requeue(&letters[0].id).await.unwrap();
+        assert!(dead_letters.is_empty().unwrap());
+        assert_eq!(
+            *publisher.delivered.lock().unwrap(),
+            vec![
+                (poison.id.clone(), ItemEventType::Updated),
+                (poison.id.clone(), ItemEventType::Created),
+            ]
+        );
+    }
+}
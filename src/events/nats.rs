@@ -0,0 +1,93 @@
+use async_nats::{connection::State, Client, ConnectOptions};
+use async_trait::async_trait;
+
+use super::{EventPublisher, ItemEvent, OutboxEvent};
+
+/// Publishes events to NATS subjects named `<prefix>.<event type>`
+pub struct NatsPublisher {
+    client: Client,
+    subject_prefix: String,
+}
+
+impl NatsPublisher {
+    /// Connect to the NATS server, retrying in the background if it is not reachable yet
+    pub async fn connect(url: &str, subject_prefix: impl Into<String>) -> Result<Self, String> {
+        let client = ConnectOptions::new()
+            .retry_on_initial_connect()
+            .connect(url)
+            .await
+            .map_err(|e| format!("failed to connect to NATS at {url}: {e}"))?;
+
+        Ok(Self {
+            client,
+            subject_prefix: subject_prefix.into(),
+        })
+    }
+
+    fn subject(&self, event: &OutboxEvent) -> String {
+        subject_for(&self.subject_prefix, event)
+    }
+}
+
+fn subject_for(prefix: &str, event: &OutboxEvent) -> String {
+    format!("{prefix}.{}", event.event_type.as_str())
+}
+
+#[async_trait]
+impl EventPublisher for NatsPublisher {
+    fn name(&self) -> &'static str {
+        "nats"
+    }
+
+    async fn publish(&self, event: &OutboxEvent) -> Result<(), String> {
+        let payload = serde_json::to_vec(&ItemEvent::from(event)).map_err(|e| e.to_string())?;
+
+        self.client
+            .publish(self.subject(event), payload.into())
+            .await
+            .map_err(|e| format!("publish failed: {e}"))?;
+
+        // Flush so a successful return means the server has the message
+        self.client
+            .flush()
+            .await
+            .map_err(|e| format!("flush failed: {e}"))
+    }
+
+    async fn health_check(&self) -> Result<(), String> {
+        match self.client.connection_state() {
+            State::Connected => Ok(()),
+            state => Err(format!("connection state: {state}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{events::ItemEventType, models::Item};
+    use chrono::Utc;
+
+    #[test]
+    fn test_subject_includes_event_type() {
+        let item = Item {
+            id: "item-1".to_string(),
+            name: "Item".to_string(),
+            slug: "item".to_string(),
+            external_id: None,
+            source: None,
+            description: None,
+            metadata: None,
+            custom_fields: Default::default(),
+            owner_id: None,
+            tenant_id: None,
+            status: Default::default(),
+            publish_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            version: 1,
+        };
+        let event = OutboxEvent::new(1, ItemEventType::Deleted, item);
+        assert_eq!(subject_for("ferrous.items", &event), "ferrous.items.deleted");
+    }
+}
@@ -0,0 +1,169 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use utoipa::ToSchema;
+use validator::Validate;
+
+use super::{EventPublisher, ItemEventType, OutboxEvent, WebhookPublisher};
+use crate::{
+    db::{EventFilter, ItemRepository},
+    error::AppError,
+    metrics::track_event_replay,
+};
+
+/// Events replayed by a request that does not say how many
+const DEFAULT_REPLAY_LIMIT: usize = 100;
+
+/// Kind of destination historical events are replayed to
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplaySinkKind {
+    /// One configured webhook endpoint
+    Webhook,
+    /// The configured event broker
+    Broker,
+}
+
+/// Request to send outbox events to a sink again, for consumers that lost them
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[schema(example = json!({
+    "sink": "webhook",
+    "webhook": "billing",
+    "from": "2024-01-01T00:00:00Z",
+    "to": "2024-01-02T00:00:00Z",
+    "types": ["created", "updated"]
+}))]
+pub struct ReplayRequest {
+    pub sink: ReplaySinkKind,
+    /// ID of the webhook to replay to; required for the `webhook` sink
+    pub webhook: Option<String>,
+    /// Only replay events that occurred at or after this time
+    pub from: Option<DateTime<Utc>>,
+    /// Only replay events that occurred before this time
+    pub to: Option<DateTime<Utc>>,
+    /// Only replay events of these types; every type when empty
+    #[serde(default)]
+    pub types: Vec<ItemEventType>,
+    /// Only replay events of this item
+    pub item_id: Option<String>,
+    /// Only replay events after this outbox sequence, to continue a replay
+    #[serde(default)]
+    pub after_sequence: u64,
+    /// Most events replayed by this request
+    #[serde(default = "default_replay_limit")]
+    #[validate(range(min = 1, max = 1000))]
+    pub limit: usize,
+}
+
+fn default_replay_limit() -> usize {
+    DEFAULT_REPLAY_LIMIT
+}
+
+impl ReplayRequest {
+    pub fn filter(&self) -> EventFilter {
+        EventFilter {
+            from: self.from,
+            to: self.to,
+            types: self.types.clone(),
+            item_id: self.item_id.clone(),
+        }
+    }
+}
+
+/// Event a sink refused, which stopped the replay
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ReplayFailure {
+    pub sequence: u64,
+    /// ID of the event, kept by the replayed delivery
+    pub event_id: String,
+    pub error: String,
+}
+
+/// Outcome of a replay
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ReplayReport {
+    /// Number of events the sink accepted
+    pub replayed: usize,
+    /// Sequence of the last event accepted; pass it as `after_sequence` to
+    /// continue from there
+    pub last_sequence: Option<u64>,
+    /// Whether matching events remain after this replay
+    pub has_more: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failed: Option<ReplayFailure>,
+}
+
+/// Destination of a replay
+pub enum ReplaySink<'a> {
+    Webhook(&'a WebhookPublisher, &'a str),
+    Broker(&'a dyn EventPublisher),
+}
+
+impl ReplaySink<'_> {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Webhook(..) => "webhook",
+            Self::Broker(publisher) => publisher.name(),
+        }
+    }
+
+    async fn send(&self, event: &OutboxEvent) -> Result<(), String> {
+        match self {
+            Self::Webhook(webhooks, id) => webhooks.replay(id, event).await,
+            Self::Broker(publisher) => publisher.publish(event).await,
+        }
+    }
+}
+
+/// Send the outbox events `request` selects to `sink`, in sequence order
+///
+/// Only events retention has not purged can be replayed. The replay stops at
+/// the first event the sink refuses, so a consumer never receives an item's
+/// events out of order; published state in the outbox is left untouched.
+pub async fn replay(
+    repo: &dyn ItemRepository,
+    sink: &ReplaySink<'_>,
+    request: &ReplayRequest,
+) -> Result<ReplayReport, AppError> {
+    if let (Some(from), Some(to)) = (request.from, request.to) {
+        if from >= to {
+            return Err(AppError::ValidationError("from: must be earlier than to".to_string()));
+        }
+    }
+
+    let mut events = repo
+        .event_history(&request.filter(), request.after_sequence, request.limit + 1)
+        .await?;
+    let mut has_more = events.len() > request.limit;
+    events.truncate(request.limit);
+
+    let mut report = ReplayReport {
+        replayed: 0,
+        last_sequence: None,
+        has_more: false,
+        failed: None,
+    };
+    for event in &events {
+        let result = sink.send(event).await;
+        track_event_replay(sink.name(), result.is_ok());
+        if let Err(error) = result {
+            warn!(
+                sequence = event.sequence,
+                sink = sink.name(),
+                "Failed to replay outbox event: {}",
+                error
+            );
+            report.failed = Some(ReplayFailure {
+                sequence: event.sequence,
+                event_id: event.id.clone(),
+                error,
+            });
+            has_more = true;
+            break;
+        }
+        report.replayed += 1;
+        report.last_sequence = Some(event.sequence);
+    }
+    report.has_more = has_more;
+    Ok(report)
+}
@@ -0,0 +1,657 @@
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use utoipa::ToSchema;
+
+use super::{EventPublisher, ItemEvent, OutboxEvent};
+use crate::{
+    config::{WebhookEndpoint, WebhooksConfig},
+    db::DatabaseError,
+    dead_letters::{DeadLetterQueue, DeadLetterSource, Retry},
+    error::AppError,
+    http_client::HttpClient,
+};
+
+/// Header carrying the delivery ID, the same for every redelivery
+pub const WEBHOOK_ID: &str = "webhook-id";
+
+/// Header carrying when the delivery was sent, in seconds since the epoch
+pub const WEBHOOK_TIMESTAMP: &str = "webhook-timestamp";
+
+/// Header carrying `v1,<signature>`
+pub const WEBHOOK_SIGNATURE: &str = "webhook-signature";
+
+/// Content type of CloudEvents in structured mode
+const CLOUDEVENTS_JSON: &str = "application/cloudevents+json";
+
+/// Signs webhook deliveries, and checks them on the receiving end
+///
+/// The signature is an HMAC-SHA256 of `{id}.{timestamp}.{body}`, so a
+/// captured delivery cannot be replayed with a fresh timestamp, and an old
+/// one is rejected once its timestamp falls outside the tolerance window.
+#[derive(Clone)]
+pub struct WebhookSigner {
+    key: Vec<u8>,
+}
+
+/// Why a delivery failed verification
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum SignatureError {
+    #[error("missing or malformed {0} header")]
+    Header(&'static str),
+    #[error("timestamp is outside the tolerance window")]
+    Expired,
+    #[error("signature does not match")]
+    Mismatch,
+}
+
+impl WebhookSigner {
+    pub fn new(secret: &str) -> Self {
+        Self {
+            key: secret.as_bytes().to_vec(),
+        }
+    }
+
+    /// Signature header value for a delivery
+    pub fn sign(&self, id: &str, timestamp: i64, body: &[u8]) -> String {
+        format!("v1,{}", STANDARD.encode(self.mac(id, timestamp, body)))
+    }
+
+    /// Check a received delivery's headers against its body
+    ///
+    /// Deliveries whose timestamp is more than `tolerance` away from `now`
+    /// are rejected. Consumers should also drop IDs they have already
+    /// processed, as redeliveries reuse them.
+    pub fn verify(
+        &self,
+        headers: &HeaderMap,
+        body: &[u8],
+        tolerance: Duration,
+        now: DateTime<Utc>,
+    ) -> Result<(), SignatureError> {
+        let header = |name: &'static str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .ok_or(SignatureError::Header(name))
+        };
+        let id = header(WEBHOOK_ID)?;
+        let timestamp: i64 = header(WEBHOOK_TIMESTAMP)?
+            .parse()
+            .map_err(|_| SignatureError::Header(WEBHOOK_TIMESTAMP))?;
+        let age = now.timestamp().abs_diff(timestamp);
+        if age > tolerance.as_secs() {
+            return Err(SignatureError::Expired);
+        }
+
+        let expected = self.mac(id, timestamp, body);
+        // Several signatures may be listed, space-separated, as when keys rotate
+        let matched = header(WEBHOOK_SIGNATURE)?
+            .split_whitespace()
+            .filter_map(|signature| signature.strip_prefix("v1,"))
+            .filter_map(|signature| STANDARD.decode(signature).ok())
+            .any(|signature| constant_time_eq(&signature, &expected));
+        if matched {
+            Ok(())
+        } else {
+            Err(SignatureError::Mismatch)
+        }
+    }
+
+    fn mac(&self, id: &str, timestamp: i64, body: &[u8]) -> [u8; 32] {
+        hmac_sha256(
+            &self.key,
+            &[
+                id.as_bytes(),
+                b".",
+                timestamp.to_string().as_bytes(),
+                b".",
+                body,
+            ],
+        )
+    }
+}
+
+/// HMAC (RFC 2104) over the concatenation of `message`
+pub(crate) fn hmac_sha256(key: &[u8], message: &[&[u8]]) -> [u8; 32] {
+    const BLOCK_SIZE: usize = 64;
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.update(block.map(|byte| byte ^ 0x36));
+    for part in message {
+        inner.update(part);
+    }
+    let mut outer = Sha256::new();
+    outer.update(block.map(|byte| byte ^ 0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().into()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// A recent delivery of an event to a webhook
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct WebhookDelivery {
+    /// Delivery ID, sent as `webhook-id`; the ID of the event delivered
+    #[schema(example = "a1b2c3d4-e5f6-7890-abcd-ef1234567890")]
+    pub id: String,
+    #[schema(example = "com.ferrous.item.created")]
+    pub event_type: String,
+    pub attempts: u32,
+    pub last_attempt_at: DateTime<Utc>,
+    /// Whether the endpoint accepted the last attempt
+    pub delivered: bool,
+    /// Status of the endpoint's last response, if it responded
+    pub status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// Whether the event ran out of attempts and went to the dead letter
+    /// queue; the outbox no longer retries it
+    pub dead_lettered: bool,
+    /// Event as sent; each attempt is signed anew
+    #[serde(skip)]
+    body: Vec<u8>,
+}
+
+/// Why a redelivery was not made
+#[derive(Debug, thiserror::Error)]
+pub enum RedeliveryError {
+    #[error("webhook {0} not found")]
+    Webhook(String),
+    #[error("delivery {0} not found")]
+    Delivery(String),
+    #[error("webhook delivery history is unavailable")]
+    Lock,
+}
+
+impl From<RedeliveryError> for AppError {
+    fn from(error: RedeliveryError) -> Self {
+        match error {
+            RedeliveryError::Lock => DatabaseError::LockError.into(),
+            error => AppError::NotFound(error.to_string()),
+        }
+    }
+}
+
+/// Delivers item events to the configured webhook endpoints
+///
+/// Every endpoint receives every event as a signed CloudEvent. An event's
+/// delivery is retried with the outbox until each endpoint accepted it, and
+/// endpoints that already did are not sent it again. With a dead letter
+/// queue, an endpoint that refuses an event `max_attempts` times no longer
+/// holds it up: the event goes to the queue for that endpoint instead.
+pub struct WebhookPublisher {
+    http: HttpClient,
+    endpoints: Vec<WebhookEndpoint>,
+    signer: WebhookSigner,
+    history: usize,
+    max_attempts: u32,
+    dead_letters: Option<Arc<DeadLetterQueue>>,
+    deliveries: Mutex<HashMap<String, VecDeque<WebhookDelivery>>>,
+}
+
+impl WebhookPublisher {
+    /// Publisher for `config`, or `None` when no endpoints are configured
+    pub fn from_config(config: &WebhooksConfig, http: HttpClient) -> Option<Self> {
+        let secret = config.secret.as_deref()?;
+        if config.endpoints.is_empty() {
+            return None;
+        }
+        Some(Self {
+            http,
+            endpoints: config.endpoints.clone(),
+            signer: WebhookSigner::new(secret),
+            history: config.history.max(1),
+            max_attempts: config.max_attempts.max(1),
+            dead_letters: None,
+            deliveries: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Dead-letter events endpoints keep refusing
+    #[must_use]
+    pub fn with_dead_letters(mut self, dead_letters: Arc<DeadLetterQueue>) -> Self {
+        self.dead_letters = Some(dead_letters);
+        self
+    }
+
+    /// Recent deliveries to webhook `id`, newest first
+    pub fn deliveries(&self, id: &str) -> Result<Vec<WebhookDelivery>, RedeliveryError> {
+        let endpoint = self.endpoint(id)?;
+        let deliveries = self.deliveries.lock().map_err(|_| RedeliveryError::Lock)?;
+        Ok(deliveries
+            .get(&endpoint.id)
+            .map(|deliveries| deliveries.iter().rev().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    /// Send a recent delivery again, under the same delivery ID
+    ///
+    /// The outcome of the attempt is recorded on the delivery, which is
+    /// returned whether or not the endpoint accepted it.
+    pub async fn redeliver(
+        &self,
+        id: &str,
+        delivery: &str,
+    ) -> Result<WebhookDelivery, RedeliveryError> {
+        let endpoint = self.endpoint(id)?;
+        let (event_type, body) = self
+            .find(&endpoint.id, delivery)?
+            .map(|found| (found.event_type, found.body))
+            .ok_or_else(|| RedeliveryError::Delivery(delivery.to_string()))?;
+        // The outcome is recorded on the delivery returned
+        let _ = self.attempt(endpoint, delivery, &event_type, body).await;
+        self.find(&endpoint.id, delivery)?
+            .ok_or_else(|| RedeliveryError::Delivery(delivery.to_string()))
+    }
+
+    /// Whether webhook `id` is configured
+    pub fn has_endpoint(&self, id: &str) -> bool {
+        self.endpoint(id).is_ok()
+    }
+
+    /// Send `event` to webhook `id` alone, unless it was delivered already
+    ///
+    /// Unlike item events, a refused delivery is not dead-lettered here; the
+    /// error is for the caller to retry.
+    pub async fn deliver(&self, id: &str, event: &ItemEvent) -> Result<(), String> {
+        let endpoint = self.endpoint(id).map_err(|e| e.to_string())?;
+        if self
+            .find(&endpoint.id, &event.id)
+            .map_err(|e| e.to_string())?
+            .is_some_and(|delivery| delivery.delivered)
+        {
+            return Ok(());
+        }
+        let body = serde_json::to_vec(event).map_err(|e| e.to_string())?;
+        self.attempt(endpoint, &event.id, &event.event_type, body)
+            .await
+    }
+
+    /// Send outbox `event` to webhook `id` again, even if it was delivered
+    ///
+    /// The delivery keeps the event's ID, so endpoints that still remember it
+    /// may ignore it as a duplicate; a refusal is not dead-lettered.
+    pub async fn replay(&self, id: &str, event: &OutboxEvent) -> Result<(), String> {
+        let endpoint = self.endpoint(id).map_err(|e| e.to_string())?;
+        let cloud_event = ItemEvent::from(event);
+        let body = serde_json::to_vec(&cloud_event).map_err(|e| e.to_string())?;
+        self.attempt(endpoint, &event.id, &cloud_event.event_type, body)
+            .await
+    }
+
+    fn endpoint(&self, id: &str) -> Result<&WebhookEndpoint, RedeliveryError> {
+        self.endpoints
+            .iter()
+            .find(|endpoint| endpoint.id == id)
+            .ok_or_else(|| RedeliveryError::Webhook(id.to_string()))
+    }
+
+    fn find(
+        &self,
+        webhook: &str,
+        delivery: &str,
+    ) -> Result<Option<WebhookDelivery>, RedeliveryError> {
+        Ok(self
+            .deliveries
+            .lock()
+            .map_err(|_| RedeliveryError::Lock)?
+            .get(webhook)
+            .and_then(|deliveries| deliveries.iter().find(|found| found.id == delivery))
+            .cloned())
+    }
+
+    fn mark_dead_lettered(&self, webhook: &str, delivery: &str) -> Result<(), RedeliveryError> {
+        let mut deliveries = self.deliveries.lock().map_err(|_| RedeliveryError::Lock)?;
+        let found = deliveries
+            .get_mut(webhook)
+            .and_then(|deliveries| deliveries.iter_mut().find(|found| found.id == delivery));
+        if let Some(found) = found {
+            found.dead_lettered = true;
+        }
+        Ok(())
+    }
+
+    /// Send `body` to `endpoint` once and record the outcome
+    async fn attempt(
+        &self,
+        endpoint: &WebhookEndpoint,
+        id: &str,
+        event_type: &str,
+        body: Vec<u8>,
+    ) -> Result<(), String> {
+        let timestamp = Utc::now().timestamp();
+        let request = self
+            .http
+            .post(&endpoint.url)
+            .header(CONTENT_TYPE, CLOUDEVENTS_JSON)
+            .header(WEBHOOK_ID, id)
+            .header(WEBHOOK_TIMESTAMP, timestamp.to_string())
+            .header(WEBHOOK_SIGNATURE, self.signer.sign(id, timestamp, &body))
+            .body(body.clone());
+        let (status, result) = match self.http.send(request).await {
+            Ok(response) if response.status().is_success() => {
+                (Some(response.status().as_u16()), Ok(()))
+            }
+            Ok(response) => (
+                Some(response.status().as_u16()),
+                Err(format!("{} responded with {}", endpoint.id, response.status())),
+            ),
+            Err(e) => (None, Err(format!("{}: request failed: {e}", endpoint.id))),
+        };
+
+        // Without a record of the outcome, the delivery is made again
+        let mut deliveries = self
+            .deliveries
+            .lock()
+            .map_err(|_| RedeliveryError::Lock.to_string())?;
+        let deliveries = deliveries.entry(endpoint.id.clone()).or_default();
+        let (attempts, dead_lettered) = match deliveries.iter().position(|found| found.id == id) {
+            Some(index) => deliveries
+                .remove(index)
+                .map_or((0, false), |found| (found.attempts, found.dead_lettered)),
+            None => (0, false),
+        };
+        deliveries.push_back(WebhookDelivery {
+            id: id.to_string(),
+            event_type: event_type.to_string(),
+            attempts: attempts + 1,
+            last_attempt_at: Utc::now(),
+            delivered: result.is_ok(),
+            status,
+            last_error: result.as_ref().err().cloned(),
+            dead_lettered: dead_lettered && result.is_err(),
+            body,
+        });
+        while deliveries.len() > self.history {
+            deliveries.pop_front();
+        }
+        result
+    }
+}
+
+/// Dead-lettered deliveries are sent to their endpoint again
+#[async_trait]
+impl Retry for WebhookPublisher {
+    async fn retry(&self, payload: &Value) -> Result<(), String> {
+        let webhook = payload["webhook"].as_str().unwrap_or_default();
+        let endpoint = self.endpoint(webhook).map_err(|e| e.to_string())?;
+        let delivery = payload["delivery"]
+            .as_str()
+            .ok_or("payload has no delivery")?;
+        let event_type = payload["event"]["type"].as_str().unwrap_or_default();
+        let body = serde_json::to_vec(&payload["event"]).map_err(|e| e.to_string())?;
+        self.attempt(endpoint, delivery, event_type, body).await
+    }
+}
+
+#[async_trait]
+impl EventPublisher for WebhookPublisher {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    async fn publish(&self, event: &OutboxEvent) -> Result<(), String> {
+        let cloud_event = ItemEvent::from(event);
+        let body = serde_json::to_vec(&cloud_event).map_err(|e| e.to_string())?;
+        let mut errors = Vec::new();
+        for endpoint in &self.endpoints {
+            if self
+                .find(&endpoint.id, &event.id)
+                .map_err(|e| e.to_string())?
+                .is_some_and(|delivery| delivery.delivered || delivery.dead_lettered)
+            {
+                continue;
+            }
+            let Err(e) = self
+                .attempt(endpoint, &event.id, &cloud_event.event_type, body.clone())
+                .await
+            else {
+                continue;
+            };
+            let attempts = self
+                .find(&endpoint.id, &event.id)
+                .map_err(|e| e.to_string())?
+                .map_or(0, |delivery| delivery.attempts);
+            match &self.dead_letters {
+                Some(dead_letters) if attempts >= self.max_attempts => {
+                    // Queued first, so a failure leaves the delivery to be retried
+                    dead_letters
+                        .push(
+                            DeadLetterSource::Webhook,
+                            format!(
+                                "{} {} to webhook {}",
+                                cloud_event.event_type, event.id, endpoint.id
+                            ),
+                            attempts,
+                            e,
+                            json!({
+                                "webhook": endpoint.id,
+                                "delivery": event.id,
+                                "event": cloud_event,
+                            }),
+                        )
+                        .map_err(|e| e.to_string())?;
+                    self.mark_dead_lettered(&endpoint.id, &event.id)
+                        .map_err(|e| e.to_string())?;
+                }
+                _ => errors.push(e),
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+
+    async fn health_check(&self) -> Result<(), String> {
+        // Endpoints only answer deliveries, so there is nothing to probe
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{events::ItemEventType, models::Item};
+    use axum::{body::Bytes, extract::State, http::StatusCode, routing::post, Router};
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    #[test]
+    fn test_hmac_matches_rfc_4231() {
+        let mac = hmac_sha256(b"Jefe", &[b"what do ya want ", b"for nothing?"]);
+        let hex: String = mac.iter().map(|byte| format!("{byte:02x}")).collect();
+        assert_eq!(hex, "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+    }
+
+    #[test]
+    fn test_signatures_are_checked_within_the_tolerance_window() {
+        let signer = WebhookSigner::new("secret");
+        let now = Utc::now();
+        let headers = |timestamp: i64, signature: String| {
+            let mut headers = HeaderMap::new();
+            headers.insert(WEBHOOK_ID, "evt-1".parse().unwrap());
+            headers.insert(WEBHOOK_TIMESTAMP, timestamp.to_string().parse().unwrap());
+            headers.insert(WEBHOOK_SIGNATURE, signature.parse().unwrap());
+            headers
+        };
+        let tolerance = Duration::from_secs(300);
+        let sent = now.timestamp() - 60;
+        let signed = headers(sent, signer.sign("evt-1", sent, b"{}"));
+        assert_eq!(signer.verify(&signed, b"{}", tolerance, now), Ok(()));
+        assert_eq!(
+            signer.verify(&signed, b"{\"x\":1}", tolerance, now),
+            Err(SignatureError::Mismatch)
+        );
+        assert_eq!(
+            WebhookSigner::new("other").verify(&signed, b"{}", tolerance, now),
+            Err(SignatureError::Mismatch)
+        );
+
+        // Replaying an old delivery fails, and so does restamping it
+        let old = now.timestamp() - 600;
+        let stale = headers(old, signer.sign("evt-1", old, b"{}"));
+        assert_eq!(signer.verify(&stale, b"{}", tolerance, now), Err(SignatureError::Expired));
+        let restamped = headers(now.timestamp(), signer.sign("evt-1", old, b"{}"));
+        assert_eq!(signer.verify(&restamped, b"{}", tolerance, now), Err(SignatureError::Mismatch));
+    }
+
+    #[derive(Clone, Default)]
+    struct Receiver {
+        failing: Arc<AtomicBool>,
+        received: Arc<Mutex<Vec<(HeaderMap, Bytes)>>>,
+    }
+
+    async fn spawn_receiver(receiver: Receiver) -> String {
+        let app = Router::new()
+            .route(
+                "/hooks",
+                post(
+                    |State(receiver): State<Receiver>, headers: HeaderMap, body: Bytes| async move {
+                        receiver.received.lock().unwrap().push((headers, body));
+                        if receiver.failing.load(Ordering::SeqCst) {
+                            StatusCode::SERVICE_UNAVAILABLE
+                        } else {
+                            StatusCode::NO_CONTENT
+                        }
+                    },
+                ),
+            )
+            .with_state(receiver);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}/hooks")
+    }
+
+    fn event() -> OutboxEvent {
+        let item = Item {
+            id: "item-1".to_string(),
+            name: "Item".to_string(),
+            slug: "item".to_string(),
+            external_id: None,
+            source: None,
+            description: None,
+            metadata: None,
+            custom_fields: Default::default(),
+            owner_id: None,
+            tenant_id: None,
+            status: Default::default(),
+            publish_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            version: 1,
+        };
+        OutboxEvent::new(1, ItemEventType::Created, item)
+    }
+
+    #[tokio::test]
+    async fn test_failed_deliveries_can_be_redelivered() {
+        let receiver = Receiver::default();
+        let url = spawn_receiver(receiver.clone()).await;
+        let config = WebhooksConfig {
+            endpoints: vec![WebhookEndpoint {
+                id: "orders".to_string(),
+                url,
+            }],
+            secret: Some("secret".to_string()),
+            ..WebhooksConfig::default()
+        };
+        let publisher = WebhookPublisher::from_config(&config, HttpClient::default()).unwrap();
+        let event = event();
+
+        receiver.failing.store(true, Ordering::SeqCst);
+        assert!(publisher.publish(&event).await.is_err());
+        let deliveries = publisher.deliveries("orders").unwrap();
+        assert_eq!((deliveries[0].delivered, deliveries[0].status), (false, Some(503)));
+
+        receiver.failing.store(false, Ordering::SeqCst);
+        let delivery = publisher.redeliver("orders", &event.id).await.unwrap();
+        assert!(delivery.delivered);
+        assert_eq!(delivery.attempts, 2);
+
+        // The outbox retry does not send it a third time
+        publisher.publish(&event).await.unwrap();
+        let received = receiver.received.lock().unwrap().clone();
+        assert_eq!(received.len(), 2);
+        let (headers, body) = &received[1];
+        assert_eq!(headers[WEBHOOK_ID], event.id.as_str());
+        let signer = WebhookSigner::new("secret");
+        assert_eq!(signer.verify(headers, body, Duration::from_secs(300), Utc::now()), Ok(()));
+
+        assert!(matches!(
+            publisher.redeliver("orders", "missing").await,
+            Err(RedeliveryError::Delivery(_))
+        ));
+        assert!(matches!(publisher.deliveries("audit"), Err(RedeliveryError::Webhook(_))));
+    }
+
+    #[tokio::test]
+    async fn test_refused_events_are_dead_lettered_after_max_attempts() {
+        let receiver = Receiver::default();
+        let url = spawn_receiver(receiver.clone()).await;
+        let config = WebhooksConfig {
+            endpoints: vec![WebhookEndpoint {
+                id: "orders".to_string(),
+                url,
+            }],
+            secret: Some("secret".to_string()),
+            max_attempts: 2,
+            ..WebhooksConfig::default()
+        };
+        let dead_letters = Arc::new(DeadLetterQueue::default());
+        let publisher = Arc::new(
+            WebhookPublisher::from_config(&config, HttpClient::default())
+                .unwrap()
+                .with_dead_letters(dead_letters.clone()),
+        );
+        dead_letters
+            .register(DeadLetterSource::Webhook, publisher.clone())
+            .unwrap();
+        let event = event();
+
+        receiver.failing.store(true, Ordering::SeqCst);
+        assert!(publisher.publish(&event).await.is_err());
+        // The second refusal dead-letters the event, releasing the outbox
+        publisher.publish(&event).await.unwrap();
+        publisher.publish(&event).await.unwrap();
+        assert_eq!(receiver.received.lock().unwrap().len(), 2);
+        assert!(publisher.deliveries("orders").unwrap()[0].dead_lettered);
+
+        let letters = dead_letters.list(Some(DeadLetterSource::Webhook)).unwrap();
+        assert_eq!((letters.len(), letters[0].attempts), (1, 2));
+        let letter = dead_letters.get(&letters[0].id).unwrap().unwrap();
+        assert_eq!(letter.payload["delivery"], event.id.as_str());
+
+        receiver.failing.store(false, Ordering::SeqCst);
+        dead_letters.requeue(&letters[0].id).await.unwrap();
+        assert!(dead_letters.is_empty().unwrap());
+        let delivery = &publisher.deliveries("orders").unwrap()[0];
+        assert!(delivery.delivered && !delivery.dead_lettered);
+        let received = receiver.received.lock().unwrap().clone();
+        let signer = WebhookSigner::new("secret");
+        let (headers, body) = &received[2];
+        assert_eq!(signer.verify(headers, body, Duration::from_secs(300), Utc::now()), Ok(()));
+    }
+}
@@ -1,22 +1,100 @@
 use crate::{
-    error::{AppResult, ErrorResponse},
-    metrics::get_metrics,
-    models::{CreateItemRequest, Item, UpdateItemRequest},
-    state::SharedState,
+    analytics::{AnalyticsRange, AnalyticsResponse, Granularity},
+    approvals::{
+        self, Approval, ChangeAction, ChangeRequest, ChangeRequestList, ChangeStatus,
+        RejectChangeRequest,
+    },
+    attachments::{Attachment, Attachments, PresignUploadRequest, PresignedUpload},
+    backup::{self, RestoreReport},
+    build_info::{BuildInfo, BUILD_INFO},
+    checkout::{self, ItemLock, LockItemRequest},
+    collections::{
+        CollectionItems, CollectionList, CollectionPlacement, CreateCollectionRequest,
+        FileItemRequest, ItemCollection, MoveCollectionRequest,
+    },
+    comments::{Comment, CommentEvent, CommentEventType, CommentList, CommentRequest},
+    conflicts::{self, MergeStrategy, UpdateConflict, UpdateQuery},
+    context::RequestContext,
+    custom_fields::{CustomFieldDefinition, DefineCustomFieldRequest},
+    db::{DatabaseError, ItemFilter, NotificationRepository},
+    dead_letters::{DeadLetter, DeadLetterSource, DeadLetterSummary, RequeueError},
+    discovery::{self, ApiIndex},
+    dual_write::{DualWriteReport, DualWriteRepository},
+    duplicates::{name_similarity, DuplicateList, PossibleDuplicate, Submission},
+    error::{AppError, AppResult, ErrorResponse},
+    events::{
+        changes::{self, DEFAULT_CHANGES_WAIT},
+        replay::{self, ReplayReport, ReplayRequest, ReplaySink, ReplaySinkKind},
+        webhook::WebhookDelivery,
+        Change, WebhookPublisher,
+    },
+    health::{CheckHealth, HealthSignals},
+    hooks::HookContext,
+    inbox::{MarkedRead, Notification, NotificationList, UnreadCount},
+    json::{stream_object_with_array, SizedJson, ITEM_SIZE_HINT, STREAM_THRESHOLD},
+    jsonapi::{self, JsonApi},
+    links::{Hypermedia, Linked, Links},
+    log_filter::{
+        LogFilter, LogFilterStatus, SetLogFilterRequest, DEFAULT_FILTER_TTL_SECONDS,
+        MAX_FILTER_TTL_SECONDS,
+    },
+    merge::{self, MergeItemsRequest, MergeReport},
+    metrics::{
+        get_metrics, track_change_request, track_duplicate_submission, track_item_published,
+        ERROR_RATES,
+    },
+    middleware::{
+        auth::{AdminUser, Claims, OptionalAuthUser},
+        chaos::{FaultInjector, FaultRule},
+        mocks::{MockEndpoint, MockEndpointRequest, MockRegistry},
+    },
+    models::{
+        AccessGrant, CreateItemRequest, CreateTenantRequest, ErasureRequest,
+        GrantPermissionRequest, Item, ItemStatus, Permission, ProvisionedTenant, Tenant,
+        TenantStatus, UpdateItemRequest, UpdateTenantRequest,
+    },
+    odata,
+    pagination::{Cursor, Page, DEFAULT_PAGE_LIMIT, MAX_PAGE_LIMIT},
+    policy::{self, Action},
+    privacy::{self, SignedErasureReport},
+    profiling::{self, CpuProfile, HeapProfile},
+    publishing::{self, PublishItemRequest},
+    query::{Comparison, Condition, FieldValue, ListOptions, QueryField},
+    saved_searches::{CreateSavedSearchRequest, SavedSearch, SavedSearchResults},
+    sbom::{self, LicenseReport},
+    schemas::{MetadataSchema, RegisterSchemaRequest},
+    sharing::{CreateShareRequest, CreatedShare, Share, ShareLinks, SharedAttachment, SharedItem},
+    sql::{self, QueryRequest, QueryResult},
+    state::{AppState, SharedState},
+    stats::ItemStatsResponse,
+    system::SystemSnapshot,
+    tenancy::{current_tenant, generate_api_key, hash_api_key, with_optional_tenant},
     validation::ValidatedJson,
+    views::ViewResponse,
 };
 use axum::{
-    extract::{Path, Query, State},
-    http::{header::CONTENT_TYPE, StatusCode},
+    body::Body,
+    extract::{FromRequestParts, Path, Query, State},
+    http::{
+        header::{CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_TYPE, ETAG, LOCATION},
+        request::Parts,
+        HeaderMap, StatusCode,
+    },
     response::{IntoResponse, Response},
-    Json,
+    Extension, Json,
 };
 use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
 use serde::{Deserialize, Serialize};
 #[allow(unused_imports)] // Used in #[schema(example = json!({...}))] attributes
 use serde_json::json;
-use std::time::Instant;
-use sysinfo::System;
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::{atomic::Ordering, Arc},
+    time::{Duration, Instant},
+};
+use tokio_util::io::{ReaderStream, StreamReader};
 use utoipa::{IntoParams, ToSchema};
 use validator::Validate;
 
@@ -40,7 +118,17 @@ pub static APP_START_TIME: std::sync::OnceLock<Instant> = std::sync::OnceLock::n
         "memory_used_mb": 1024,
         "memory_total_mb": 8192,
         "memory_usage_percent": 12.5,
-        "cpu_count": 8
+        "cpu_count": 8,
+        "container": {
+            "cgroup_version": 2,
+            "memory_used_mb": 384,
+            "memory_limit_mb": 512,
+            "memory_usage_percent": 75.0,
+            "cpu_quota": 2.0,
+            "cpu_used": 0.5,
+            "cpu_usage_percent": 25.0
+        },
+        "sampled_at": "2024-01-01T00:00:00Z"
     }
 }))]
 pub struct HealthResponse {
@@ -48,12 +136,19 @@ pub struct HealthResponse {
     pub timestamp: DateTime<Utc>,
     pub uptime_seconds: u64,
     pub version: String,
+    pub build: BuildInfo,
     pub database: DatabaseHealth,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event_broker: Option<EventBrokerHealth>,
     pub system: SystemHealth,
+    pub error_rate: ErrorRateHealth,
+    /// Synthetic checks configured through `HEALTH_CHECKS`
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub checks: Vec<CheckHealth>,
 }
 
 /// Health status
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum HealthStatus {
     Healthy,
@@ -68,13 +163,55 @@ pub struct DatabaseHealth {
     pub response_time_ms: Option<u64>,
 }
 
+/// External event broker health information
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct EventBrokerHealth {
+    #[schema(example = "nats")]
+    pub publisher: String,
+    pub connected: bool,
+}
+
 /// System health information
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct SystemHealth {
+    /// Host memory, whatever share of it the service may use
     pub memory_used_mb: u64,
     pub memory_total_mb: u64,
     pub memory_usage_percent: f32,
     pub cpu_count: usize,
+    /// Usage against the limits of the service's cgroup, when it runs in one
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub container: Option<ContainerHealth>,
+    /// When the figures above were sampled (every few seconds, not per request)
+    pub sampled_at: DateTime<Utc>,
+}
+
+/// Resource usage of the service's cgroup; limits and CPU usage are unset
+/// where the cgroup sets no limit or nothing was sampled yet
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ContainerHealth {
+    pub cgroup_version: u8,
+    /// Memory in use, less the page cache the kernel can reclaim
+    pub memory_used_mb: u64,
+    pub memory_limit_mb: Option<u64>,
+    /// What the memory health thresholds are held to when set
+    pub memory_usage_percent: Option<f32>,
+    /// CPUs the cgroup may use
+    pub cpu_quota: Option<f64>,
+    /// CPUs used on average since the previous sample
+    pub cpu_used: Option<f64>,
+    pub cpu_usage_percent: Option<f32>,
+}
+
+/// Server errors among the requests this instance handled recently
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ErrorRateHealth {
+    pub window_seconds: u64,
+    pub requests: u64,
+    pub server_errors: u64,
+    /// `server_errors / requests`, or 0 without requests
+    #[schema(example = 0.002)]
+    pub ratio: f64,
 }
 
 /// Basic health check endpoint (liveness probe)
@@ -93,6 +230,19 @@ pub async fn liveness() -> impl IntoResponse {
     }))
 }
 
+/// Build metadata of the running binary, for telling deployments apart
+#[utoipa::path(
+    get,
+    path = "/version",
+    tag = "health",
+    responses(
+        (status = 200, description = "Build metadata", body = BuildInfo),
+    ),
+)]
+pub async fn version() -> Json<BuildInfo> {
+    Json(BUILD_INFO.clone())
+}
+
 /// Readiness check endpoint
 #[utoipa::path(
     get,
@@ -104,6 +254,18 @@ pub async fn liveness() -> impl IntoResponse {
     ),
 )]
 pub async fn readiness(State(state): State<SharedState>) -> impl IntoResponse {
+    // Shutting down: stop receiving traffic before the server stops
+    if state.draining.load(Ordering::Relaxed) {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({
+                "status": "not_ready",
+                "timestamp": Utc::now(),
+                "reason": "draining",
+            })),
+        );
+    }
+
     // Check database connectivity
     let db_healthy = state.repo.health_check().await.is_ok();
 
@@ -140,54 +302,129 @@ pub async fn readiness(State(state): State<SharedState>) -> impl IntoResponse {
 pub async fn health_check(State(state): State<SharedState>) -> AppResult<impl IntoResponse> {
     let start_time = APP_START_TIME.get_or_init(Instant::now);
     let uptime = start_time.elapsed().as_secs();
-
-    // Check database health
-    let db_start = Instant::now();
-    let db_connected = state.repo.health_check().await.is_ok();
-    let db_response_time = if db_connected {
-        Some(db_start.elapsed().as_millis() as u64)
-    } else {
-        None
-    };
-
-    // Get system information
-    let mut sys = System::new_all();
-    sys.refresh_memory();
-
-    let memory_used = sys.used_memory() / 1024 / 1024; // Convert to MB
-    let memory_total = sys.total_memory() / 1024 / 1024; // Convert to MB
-    let memory_usage_percent = (memory_used as f32 / memory_total as f32) * 100.0;
-    let cpu_count = num_cpus::get();
-
-    // Determine overall health status
-    let status = if !db_connected {
-        HealthStatus::Unhealthy
-    } else if memory_usage_percent > 90.0 {
-        HealthStatus::Degraded
-    } else {
-        HealthStatus::Healthy
-    };
+    let HealthProbe {
+        status,
+        db_connected,
+        db_response_time,
+        system,
+        event_broker,
+        checks,
+    } = probe_health(&state).await;
 
     let response = HealthResponse {
         status,
         timestamp: Utc::now(),
         uptime_seconds: uptime,
         version: env!("CARGO_PKG_VERSION").to_string(),
+        build: BUILD_INFO.clone(),
         database: DatabaseHealth {
             connected: db_connected,
             response_time_ms: db_response_time,
         },
+        event_broker,
         system: SystemHealth {
-            memory_used_mb: memory_used,
-            memory_total_mb: memory_total,
-            memory_usage_percent,
-            cpu_count,
+            memory_used_mb: system.memory_used_mb,
+            memory_total_mb: system.memory_total_mb,
+            memory_usage_percent: system.memory_usage_percent,
+            cpu_count: system.cpu_count,
+            container: system.container.map(|container| ContainerHealth {
+                cgroup_version: container.cgroup_version,
+                memory_used_mb: container.memory_used_mb,
+                memory_limit_mb: container.memory_limit_mb,
+                memory_usage_percent: container.memory_usage_percent,
+                cpu_quota: container.cpu_quota,
+                cpu_used: container.cpu_used,
+                cpu_usage_percent: container.cpu_usage_percent,
+            }),
+            sampled_at: system.sampled_at,
+        },
+        error_rate: {
+            let counts = ERROR_RATES.total();
+            ErrorRateHealth {
+                window_seconds: ERROR_RATES.window().as_secs(),
+                requests: counts.requests,
+                server_errors: counts.server_errors,
+                ratio: counts.ratio(),
+            }
         },
+        checks,
     };
 
     Ok(Json(response))
 }
 
+/// What the health report is judged from
+struct HealthProbe {
+    status: HealthStatus,
+    db_connected: bool,
+    db_response_time: Option<u64>,
+    system: SystemSnapshot,
+    event_broker: Option<EventBrokerHealth>,
+    checks: Vec<CheckHealth>,
+}
+
+async fn probe_health(state: &AppState) -> HealthProbe {
+    // Check database health
+    let db_start = Instant::now();
+    let db_connected = state.repo.health_check().await.is_ok();
+    let db_response_time = if db_connected {
+        Some(db_start.elapsed().as_millis() as u64)
+    } else {
+        None
+    };
+
+    // System information is sampled in the background
+    let system = state.system.snapshot();
+
+    // Check the external event broker, if one is configured
+    let event_broker = match &state.publisher {
+        Some(publisher) => Some(EventBrokerHealth {
+            publisher: publisher.name().to_string(),
+            connected: publisher.health_check().await.is_ok(),
+        }),
+        None => None,
+    };
+    let broker_connected = event_broker.as_ref().is_none_or(|broker| broker.connected);
+
+    // Determine overall health status from the configured thresholds
+    let checks = state.health.checks();
+    let status = state.health.status(&HealthSignals {
+        db_connected,
+        db_response_time_ms: db_response_time,
+        broker_connected,
+        memory_usage_percent: system.effective_memory_usage_percent(),
+        checks: &checks,
+    });
+    HealthProbe {
+        status,
+        db_connected,
+        db_response_time,
+        system,
+        event_broker,
+        checks,
+    }
+}
+
+/// Overall status, judged as for `/health`
+pub async fn health_status(state: &AppState) -> HealthStatus {
+    probe_health(state).await.status
+}
+
+// ===== DISCOVERY HANDLERS =====
+
+/// Index of the API versions, collections and features this instance serves
+#[utoipa::path(
+    get,
+    path = "/api",
+    tag = "discovery",
+    responses(
+        (status = 200, description = "API index", body = ApiIndex),
+    ),
+)]
+pub async fn api_index(State(state): State<SharedState>) -> impl IntoResponse {
+    Json(discovery::api_index(&state))
+}
+
 // ===== ITEM HANDLERS =====
 
 /// Query parameters for listing items
@@ -197,12 +434,97 @@ pub struct ListQuery {
     #[validate(range(min = 1, max = 100))]
     pub limit: usize,
 
+    /// Items to skip, at most 10000; use `cursor` for deeper pages
     #[serde(default)]
     pub offset: usize,
+
+    /// `next_cursor` of the previous page, to continue just past it
+    pub cursor: Option<String>,
+
+    /// List items from every owner (administrators only)
+    #[serde(default)]
+    pub all: bool,
+
+    /// Only items with this external ID
+    #[param(example = "4711")]
+    pub external_id: Option<String>,
+
+    /// Only items synchronized from this source
+    #[param(example = "crm")]
+    pub source: Option<String>,
+
+    /// OData filter: comparisons (`eq`, `ne`, `gt`, `ge`, `lt`, `le`) and
+    /// `contains`, `startswith` or `endswith`, joined by `and`
+    #[serde(rename = "$filter")]
+    #[param(
+        rename = "$filter",
+        example = "version ge 2 and startswith(name, 'Wid')"
+    )]
+    pub filter: Option<String>,
+
+    /// OData sort order, as fields each followed by `asc` or `desc`; sorted
+    /// pages use offsets rather than cursors
+    #[serde(rename = "$orderby")]
+    #[param(rename = "$orderby", example = "name desc, created_at")]
+    pub orderby: Option<String>,
+
+    /// OData page size; overrides `limit`
+    #[serde(rename = "$top")]
+    #[param(rename = "$top")]
+    pub top: Option<usize>,
+
+    /// OData items to skip; overrides `offset`
+    #[serde(rename = "$skip")]
+    #[param(rename = "$skip")]
+    pub skip: Option<usize>,
+
+    /// OData fields to include in each item, comma-separated
+    #[serde(rename = "$select")]
+    #[param(rename = "$select", example = "id,name")]
+    pub select: Option<String>,
+
+    /// Count the matching items for `total`; counting can be expensive on
+    /// large stores, so set to `false` when the total is not needed
+    #[serde(default = "default_include_total")]
+    #[param(default = true)]
+    pub include_total: bool,
+}
+
+impl ListQuery {
+    /// Page size, `$top` taking precedence over `limit`
+    pub fn page_limit(&self) -> usize {
+        self.top.unwrap_or(self.limit)
+    }
+
+    /// Items to skip, `$skip` taking precedence over `offset`
+    pub fn page_offset(&self) -> usize {
+        self.skip.unwrap_or(self.offset)
+    }
+
+    /// Conditions `external_id` and `source` put on the listed items
+    pub fn conditions(&self) -> Vec<Condition> {
+        [
+            (QueryField::ExternalId, &self.external_id),
+            (QueryField::Source, &self.source),
+        ]
+        .into_iter()
+        .filter_map(|(field, value)| {
+            value.as_ref().map(|value| Condition {
+                field: field.into(),
+                comparison: Comparison::Eq,
+                value: FieldValue::Text(value.clone()),
+            })
+        })
+        .collect()
+    }
+}
+
+const fn default_include_total() -> bool {
+    true
 }
 
 const fn default_limit() -> usize {
-    20
+    DEFAULT_PAGE_LIMIT
 }
 
 /// Response for list operations
@@ -211,7 +533,9 @@ const fn default_limit() -> usize {
     "items": [{
         "id": "550e8400-e29b-41d4-a716-446655440000",
         "name": "Example Item",
+        "slug": "example-item",
         "description": "Example description",
+        "owner_id": "user-123",
         "created_at": "2024-01-01T00:00:00Z",
         "updated_at": "2024-01-01T00:00:00Z"
     }],
@@ -220,10 +544,82 @@ const fn default_limit() -> usize {
     "offset": 0
 }))]
 pub struct ListResponse {
-    pub items: Vec<Item>,
-    pub total: usize,
+    #[schema(value_type = Vec<Item>)]
+    pub items: Vec<Linked<Arc<Item>>>,
+    /// Items matching the query in all; absent with `include_total=false`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<usize>,
     pub limit: usize,
     pub offset: usize,
+    /// Pass as `cursor` to get the next page; absent on the last page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    /// Links to this page, the next one, and item creation, when requested
+    #[serde(rename = "_links", skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub links: Option<Links>,
+}
+
+/// Fields of `ListResponse` after `items`, for streamed responses
+#[derive(Serialize)]
+struct ListTrailer {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total: Option<usize>,
+    limit: usize,
+    offset: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_cursor: Option<String>,
+    #[serde(rename = "_links", skip_serializing_if = "Option::is_none")]
+    links: Option<Links>,
+}
+
+impl IntoResponse for ListResponse {
+    fn into_response(self) -> Response {
+        if self.items.len() <= STREAM_THRESHOLD {
+            let capacity = (self.items.len() + 1) * ITEM_SIZE_HINT;
+            return SizedJson::new(self, capacity).into_response();
+        }
+
+        let trailer = ListTrailer {
+            total: self.total,
+            limit: self.limit,
+            offset: self.offset,
+            next_cursor: self.next_cursor,
+            links: self.links,
+        };
+        stream_object_with_array("items", self.items, &trailer)
+    }
+}
+
+/// How the client asked for items to be represented
+#[derive(Debug, Clone, Copy)]
+pub struct Representation {
+    pub hypermedia: Hypermedia,
+    pub json_api: JsonApi,
+}
+
+impl FromRequestParts<SharedState> for Representation {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &SharedState,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(Self {
+            hypermedia: Hypermedia::from_request_parts(parts, state).await?,
+            json_api: JsonApi::from_request_parts(parts, state).await?,
+        })
+    }
+}
+
+impl Representation {
+    /// Response body for `item`
+    fn item(self, item: Item) -> Response {
+        if self.json_api.0 {
+            return jsonapi::item_document(&item).into_response();
+        }
+        Json(self.hypermedia.item(item)).into_response()
+    }
 }
 
 /// Create a new item
@@ -233,18 +629,228 @@ pub struct ListResponse {
     tag = "items",
     request_body = CreateItemRequest,
     responses(
+        (status = 200, description = "Repeat of a recent submission; the item it created", body = Item),
         (status = 201, description = "Item created successfully", body = Item),
         (status = 400, description = "Bad request", body = ErrorResponse),
+        (status = 403, description = "Tenant item quota reached", body = ErrorResponse),
         (status = 422, description = "Validation error", body = ErrorResponse),
         (status = 500, description = "Internal server error", body = ErrorResponse),
     ),
 )]
 pub async fn create_item(
     State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    representation: Representation,
+    tenant: Option<Extension<Tenant>>,
     ValidatedJson(request): ValidatedJson<CreateItemRequest>,
 ) -> AppResult<impl IntoResponse> {
-    let item = state.repo.create(request).await?;
-    Ok((StatusCode::CREATED, Json(item)))
+    // A repeat of a recent submission gets the item the first one created
+    let submission = match (&state.duplicates, &claims) {
+        (Some(duplicates), Some(claims)) => Some(duplicates.begin(&claims.sub, &request).await),
+        _ => None,
+    };
+    if let Some(previous) = submission.as_ref().and_then(Submission::previous) {
+        match state.repo.get(previous).await {
+            Ok(item) => {
+                track_duplicate_submission();
+                let etag = conflicts::etag(&item);
+                let body = representation.item(item);
+                return Ok((StatusCode::OK, [(ETAG, etag)], body));
+            }
+            Err(DatabaseError::NotFound) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    let tenant = tenant.map(|Extension(tenant)| tenant);
+    let item = insert_item(&state, claims.as_ref(), tenant.as_ref(), request).await?;
+    if let Some(submission) = submission {
+        submission.created(&item.id);
+    }
+    let etag = conflicts::etag(&item);
+    Ok((StatusCode::CREATED, [(ETAG, etag)], representation.item(item)))
+}
+
+/// Create an item owned by the caller, within the tenant's quota and once
+/// hooks and validation accept it
+async fn insert_item(
+    state: &SharedState,
+    claims: Option<&Claims>,
+    tenant: Option<&Tenant>,
+    mut request: CreateItemRequest,
+) -> AppResult<Item> {
+    if let Some(max_items) = tenant.and_then(|tenant| tenant.quotas.max_items) {
+        if state.repo.count(&ItemFilter::default()).await? >= max_items {
+            return Err(AppError::Forbidden(format!("Tenant item quota of {max_items} reached")));
+        }
+    }
+
+    if request.publish_at.is_some() && request.status != ItemStatus::Draft {
+        return Err(AppError::ValidationError(
+            "publish_at: only drafts can be scheduled for publication".to_string(),
+        ));
+    }
+
+    let ctx = HookContext { claims };
+    state.hooks.before_create(ctx, &mut request).await?;
+    // Items without metadata are held to the schemas as an empty object
+    let empty = serde_json::Value::Object(serde_json::Map::new());
+    state
+        .schemas
+        .validate(request.metadata.as_ref().unwrap_or(&empty))?;
+    state
+        .custom_fields
+        .validate_create(&request.custom_fields)?;
+
+    let owner_id = claims.map(|claims| claims.sub.clone());
+    let item = state.repo.create(request, owner_id).await?;
+    state.hooks.after_create(ctx, &item).await;
+    Ok(item)
+}
+
+/// Create or update the item with an external ID
+///
+/// Lets automation manage items by its own identifiers: the first request
+/// creates the item with `external_id`, and later ones update it to match
+/// the body. External IDs are looked up within the body's `source`, so
+/// systems that sync into the same tenant cannot collide. Fields the body
+/// leaves out keep their values, except `status`, which defaults to
+/// published, and a body that matches the item changes nothing, so
+/// repeating a request is safe.
+/// Updates need write access, like any other, and are held for approval
+/// where that applies.
+#[utoipa::path(
+    put,
+    path = "/api/v1/items/by-external-id/{external_id}",
+    tag = "items",
+    params(("external_id" = String, Path, description = "The item's ID in its source system")),
+    request_body = CreateItemRequest,
+    responses(
+        (status = 200, description = "Item updated, or already as requested", body = Item),
+        (status = 201, description = "Item created", body = Item),
+        (status = 202, description = "Update held for approval", body = ChangeRequest),
+        (status = 403, description = "Caller cannot modify the item, or the tenant item quota is reached", body = ErrorResponse),
+        (status = 409, description = "The item changed while being updated", body = ErrorResponse),
+        (status = 422, description = "Validation error, or the body names another external ID", body = ErrorResponse),
+        (status = 423, description = "Item is locked by another principal", body = ErrorResponse),
+    ),
+)]
+pub async fn upsert_item(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    representation: Representation,
+    tenant: Option<Extension<Tenant>>,
+    Path(external_id): Path<String>,
+    ValidatedJson(mut request): ValidatedJson<CreateItemRequest>,
+) -> AppResult<Response> {
+    if request
+        .external_id
+        .as_ref()
+        .is_some_and(|id| *id != external_id)
+    {
+        return Err(AppError::ValidationError(
+            "external_id: must match the external ID in the path".to_string(),
+        ));
+    }
+    if external_id.is_empty() || external_id.len() > 255 {
+        return Err(AppError::ValidationError(
+            "external_id: must be between 1 and 255 characters".to_string(),
+        ));
+    }
+    if request.publish_at.is_some() && request.status != ItemStatus::Draft {
+        return Err(AppError::ValidationError(
+            "publish_at: only drafts can be scheduled for publication".to_string(),
+        ));
+    }
+    request.external_id = Some(external_id.clone());
+    // `status` has a default, so the body always says which one it wants
+    let mut update = UpdateItemRequest {
+        name: Some(request.name.clone()),
+        description: request.description.clone(),
+        metadata: request.metadata.clone(),
+        custom_fields: request.custom_fields.clone(),
+        status: Some(request.status),
+        publish_at: Some(request.publish_at),
+        ..UpdateItemRequest::default()
+    };
+
+    let source = request.source.clone();
+    let current = match state
+        .repo
+        .get_by_external_id(source.as_deref(), &external_id)
+        .await
+    {
+        Ok(item) => item,
+        Err(DatabaseError::NotFound) => {
+            let tenant = tenant.map(|Extension(tenant)| tenant);
+            match insert_item(&state, claims.as_ref(), tenant.as_ref(), request).await {
+                Ok(item) => {
+                    let etag = conflicts::etag(&item);
+                    let body = representation.item(item);
+                    return Ok((StatusCode::CREATED, [(ETAG, etag)], body).into_response());
+                }
+                // Another request created it first; update that item instead
+                Err(AppError::DatabaseError(DatabaseError::Conflict(_))) => {
+                    state
+                        .repo
+                        .get_by_external_id(source.as_deref(), &external_id)
+                        .await?
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    policy::authorize(
+        state.access.as_ref(),
+        state.collections.as_ref(),
+        &current,
+        claims.as_ref(),
+        Action::Write,
+    )
+    .await?;
+    if update.is_unchanged(&current) {
+        let etag = conflicts::etag(&current);
+        return Ok(([(ETAG, etag)], representation.item(current)).into_response());
+    }
+    checkout::ensure_unlocked(state.item_locks.as_ref(), &current.id, claims.as_ref()).await?;
+    let ctx = HookContext {
+        claims: claims.as_ref(),
+    };
+    state
+        .hooks
+        .before_update(ctx, &current, &mut update)
+        .await?;
+    if let Some(metadata) = &update.metadata {
+        state.schemas.validate(metadata)?;
+    }
+    state.custom_fields.validate_update(&update.custom_fields)?;
+
+    if needs_approval(&state, &current.id).await? {
+        let now = state.clock.now();
+        let publish_at = update.publish_at.flatten();
+        if publish_at != current.publish_at && publish_at.is_some_and(|at| at > now) {
+            return Err(DatabaseError::Conflict(
+                "Publishing this draft needs approval, so it cannot be scheduled".to_string(),
+            )
+            .into());
+        }
+        let change =
+            request_change(&state, claims.as_ref(), &current, ChangeAction::Update, Some(update))
+                .await?;
+        return Ok((StatusCode::ACCEPTED, Json(change)).into_response());
+    }
+    let item = state
+        .repo
+        .update(&current.id, update, Some(current.version))
+        .await?;
+    if current.is_draft() && !item.is_draft() {
+        track_item_published("request");
+    }
+    state.hooks.after_update(ctx, &item).await;
+    let etag = conflicts::etag(&item);
+    Ok(([(ETAG, etag)], representation.item(item)).into_response())
 }
 
 /// Get an item by ID
@@ -257,45 +863,180 @@ pub async fn create_item(
     ),
     responses(
         (status = 200, description = "Item retrieved successfully", body = Item),
+        (status = 403, description = "Caller cannot read the item", body = ErrorResponse),
         (status = 404, description = "Item not found", body = ErrorResponse),
         (status = 500, description = "Internal server error", body = ErrorResponse),
     ),
 )]
 pub async fn get_item(
     State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    representation: Representation,
     Path(id): Path<String>,
 ) -> AppResult<impl IntoResponse> {
     let item = state.repo.get(&id).await?;
-    Ok(Json(item))
+    policy::authorize(
+        state.access.as_ref(),
+        state.collections.as_ref(),
+        &item,
+        claims.as_ref(),
+        Action::Read,
+    )
+    .await?;
+    let lock = state.item_locks.get(&item.id).await?;
+    let etag = conflicts::etag(&item);
+    Ok((checkout::headers(lock.as_ref()), [(ETAG, etag)], representation.item(item)))
+}
+
+/// Get an item by slug
+#[utoipa::path(
+    get,
+    path = "/api/v1/items/slug/{slug}",
+    tag = "items",
+    params(
+        ("slug" = String, Path, description = "Item slug")
+    ),
+    responses(
+        (status = 200, description = "Item retrieved successfully", body = Item),
+        (status = 403, description = "Caller cannot read the item", body = ErrorResponse),
+        (status = 404, description = "Item not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+)]
+pub async fn get_item_by_slug(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    representation: Representation,
+    Path(slug): Path<String>,
+) -> AppResult<impl IntoResponse> {
+    let item = state.repo.get_by_slug(&slug).await?;
+    policy::authorize(
+        state.access.as_ref(),
+        state.collections.as_ref(),
+        &item,
+        claims.as_ref(),
+        Action::Read,
+    )
+    .await?;
+    let lock = state.item_locks.get(&item.id).await?;
+    let etag = conflicts::etag(&item);
+    Ok((checkout::headers(lock.as_ref()), [(ETAG, etag)], representation.item(item)))
 }
 
+/// Times an update is retried when the item changes between reading and writing it
+const MAX_UPDATE_ATTEMPTS: usize = 3;
+
 /// Update an item
+///
+/// With `If-Match`, the update only applies to that version of the item.
+/// When the item has changed since, the response is 409 with the current and
+/// proposed versions and a diff, unless `merge=fields` is given and the
+/// update only sets fields that nobody else changed.
+///
+/// Updates to items that need approval are not made here: the response is
+/// 202 with a change request, applied once it is approved.
 #[utoipa::path(
     put,
     path = "/api/v1/items/{id}",
     tag = "items",
     params(
-        ("id" = String, Path, description = "Item ID")
+        ("id" = String, Path, description = "Item ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag of the version the update is based on"),
+        UpdateQuery,
     ),
     request_body = UpdateItemRequest,
     responses(
         (status = 200, description = "Item updated successfully", body = Item),
+        (status = 202, description = "Update held for approval", body = ChangeRequest),
         (status = 400, description = "Bad request", body = ErrorResponse),
+        (status = 401, description = "Updating an item that needs approval requires a principal", body = ErrorResponse),
+        (status = 403, description = "Caller cannot modify the item", body = ErrorResponse),
         (status = 404, description = "Item not found", body = ErrorResponse),
+        (status = 409, description = "Item changed since the If-Match version", body = UpdateConflict),
         (status = 422, description = "Validation error", body = ErrorResponse),
+        (status = 423, description = "Item is locked by another principal", body = ErrorResponse),
         (status = 500, description = "Internal server error", body = ErrorResponse),
     ),
 )]
 pub async fn update_item(
     State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    representation: Representation,
     Path(id): Path<String>,
-    ValidatedJson(request): ValidatedJson<UpdateItemRequest>,
-) -> AppResult<impl IntoResponse> {
-    let item = state.repo.update(&id, request).await?;
-    Ok(Json(item))
+    Query(query): Query<UpdateQuery>,
+    headers: HeaderMap,
+    ValidatedJson(mut request): ValidatedJson<UpdateItemRequest>,
+) -> AppResult<Response> {
+    let base = conflicts::if_match(&headers)?;
+    let mut current = state.repo.get(&id).await?;
+    policy::authorize(
+        state.access.as_ref(),
+        state.collections.as_ref(),
+        &current,
+        claims.as_ref(),
+        Action::Write,
+    )
+    .await?;
+    checkout::ensure_unlocked(state.item_locks.as_ref(), &id, claims.as_ref()).await?;
+    let ctx = HookContext {
+        claims: claims.as_ref(),
+    };
+    state
+        .hooks
+        .before_update(ctx, &current, &mut request)
+        .await?;
+    if let Some(metadata) = &request.metadata {
+        state.schemas.validate(metadata)?;
+    }
+    state
+        .custom_fields
+        .validate_update(&request.custom_fields)?;
+
+    if needs_approval(&state, &id).await? {
+        if let Some(base) = base.filter(|base| *base != current.version) {
+            return Err(DatabaseError::VersionMismatch { expected: base }.into());
+        }
+        let change =
+            request_change(&state, claims.as_ref(), &current, ChangeAction::Update, Some(request))
+                .await?;
+        return Ok((StatusCode::ACCEPTED, Json(change)).into_response());
+    }
+
+    for _ in 0..MAX_UPDATE_ATTEMPTS {
+        let expected = match base {
+            Some(base) if base != current.version => {
+                let changed = state.repo.changed_since(&id, base).await?;
+                let conflict = UpdateConflict::detect(base, &current, &request, &changed);
+                if query.merge == MergeStrategy::Reject || !conflict.mergeable() {
+                    return Ok(conflict.into_response());
+                }
+                // Nothing the request sets changed since its base version
+                Some(current.version)
+            }
+            base => base,
+        };
+
+        match state.repo.update(&id, request.clone(), expected).await {
+            Ok(item) => {
+                state.hooks.after_update(ctx, &item).await;
+                let etag = conflicts::etag(&item);
+                return Ok(([(ETAG, etag)], representation.item(item)).into_response());
+            }
+            // Changed after it was read; check the request against the new version
+            Err(DatabaseError::VersionMismatch { .. }) => current = state.repo.get(&id).await?,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Err(DatabaseError::VersionMismatch {
+        expected: current.version,
+    }
+    .into())
 }
 
 /// Delete an item
+///
+/// Items that need approval are not deleted here: the response is 202 with
+/// a change request, applied once it is approved.
 #[utoipa::path(
     delete,
     path = "/api/v1/items/{id}",
@@ -304,17 +1045,55 @@ pub async fn update_item(
         ("id" = String, Path, description = "Item ID")
     ),
     responses(
+        (status = 202, description = "Deletion held for approval", body = ChangeRequest),
         (status = 204, description = "Item deleted successfully"),
+        (status = 401, description = "Deleting an item that needs approval requires a principal", body = ErrorResponse),
+        (status = 403, description = "Caller cannot modify the item", body = ErrorResponse),
         (status = 404, description = "Item not found", body = ErrorResponse),
+        (status = 423, description = "Item is locked by another principal", body = ErrorResponse),
         (status = 500, description = "Internal server error", body = ErrorResponse),
     ),
 )]
 pub async fn delete_item(
     State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
     Path(id): Path<String>,
-) -> AppResult<impl IntoResponse> {
-    state.repo.delete(&id).await?;
-    Ok(StatusCode::NO_CONTENT)
+) -> AppResult<Response> {
+    let existing = state.repo.get(&id).await?;
+    policy::authorize(
+        state.access.as_ref(),
+        state.collections.as_ref(),
+        &existing,
+        claims.as_ref(),
+        Action::Write,
+    )
+    .await?;
+    checkout::ensure_unlocked(state.item_locks.as_ref(), &id, claims.as_ref()).await?;
+    let ctx = HookContext {
+        claims: claims.as_ref(),
+    };
+    state.hooks.before_delete(ctx, &existing).await?;
+
+    if needs_approval(&state, &id).await? {
+        let change =
+            request_change(&state, claims.as_ref(), &existing, ChangeAction::Delete, None).await?;
+        return Ok((StatusCode::ACCEPTED, Json(change)).into_response());
+    }
+
+    remove_item(&state, &id).await?;
+    state.hooks.after_delete(ctx, &existing).await;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Delete an item with its grants, collection placement, lock and attachments
+async fn remove_item(state: &SharedState, id: &str) -> Result<(), DatabaseError> {
+    state.repo.delete(id).await?;
+    state.access.revoke_all(id).await?;
+    state.collections.forget_item(id).await?;
+    if let Some(attachments) = &state.attachments {
+        attachments.forget_item(id)?;
+    }
+    state.item_locks.release(id).await
 }
 
 /// List items with pagination
@@ -326,24 +1105,3928 @@ pub async fn delete_item(
     responses(
         (status = 200, description = "Items retrieved successfully", body = ListResponse),
         (status = 400, description = "Bad request", body = ErrorResponse),
+        (status = 403, description = "Listing all items requires the admin role", body = ErrorResponse),
         (status = 500, description = "Internal server error", body = ErrorResponse),
     ),
 )]
 pub async fn list_items(
     State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    representation: Representation,
     Query(query): Query<ListQuery>,
-) -> AppResult<impl IntoResponse> {
-    let items = state.repo.list(query.limit, query.offset).await?;
-    let total = state.repo.count().await?;
+) -> AppResult<Response> {
+    let ListOptions {
+        filter,
+        page,
+        select,
+    } = odata::list_options(
+        &query,
+        list_filter(&state, claims.as_ref(), query.all)?.with_conditions(query.conditions()),
+    )?;
+    state.custom_fields.check_conditions(&filter.conditions)?;
+    let (items, total) = if query.include_total {
+        let (items, total) = state.repo.list_with_total(&filter, &page).await?;
+        (items, Some(total))
+    } else {
+        (state.repo.list(&filter, &page).await?, None)
+    };
 
+    // A full page may be followed by more; cursors only follow list order
+    let next_cursor = items
+        .last()
+        .filter(|_| items.len() == page.limit() && page.order().is_empty())
+        .map(|last| Cursor::after(last).encode());
+    let hypermedia = representation.hypermedia;
+    let links = hypermedia.list(&query, next_cursor.as_deref());
     let response = ListResponse {
-        items,
+        items: items
+            .into_iter()
+            .map(|item| hypermedia.item(item).selecting(select.clone()))
+            .collect(),
         total,
-        limit: query.limit,
-        offset: query.offset,
+        limit: page.limit(),
+        offset: page.skipped(),
+        next_cursor,
+        links,
     };
 
-    Ok(Json(response))
+    if representation.json_api.0 {
+        return Ok(jsonapi::list_document(&query, &response).into_response());
+    }
+    Ok(response.into_response())
+}
+
+/// Authenticated callers see their own items; admins may opt into all items,
+/// and anonymous callers see published ones. When authentication is enabled,
+/// anonymous callers only see unowned items, as they may only read those.
+fn list_filter(state: &AppState, claims: Option<&Claims>, all: bool) -> AppResult<ItemFilter> {
+    match claims {
+        Some(claims) if all && claims.is_admin() => Ok(ItemFilter::default()),
+        Some(_) if all => {
+            Err(AppError::Forbidden("Listing all items requires the admin role".to_string()))
+        }
+        Some(claims) => Ok(ItemFilter::owned_by(claims.sub.clone())),
+        None if state.auth.enabled() => Ok(ItemFilter::published().unowned()),
+        None => Ok(ItemFilter::published()),
+    }
+}
+
+/// Query parameters for the change feed
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ChangesQuery {
+    /// `next_cursor` of the previous response; omit to follow changes from now
+    pub since: Option<String>,
+
+    /// How long to wait for a change, such as `30s` or `500ms` (at most 60s)
+    #[param(example = "30s")]
+    pub wait: Option<String>,
+
+    /// Follow changes to every owner's items (administrators only)
+    #[serde(default)]
+    pub all: bool,
+}
+
+/// Response of the change feed
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ChangesResponse {
+    /// Changes in delivery order; empty when none arrived before the wait expired
+    pub changes: Vec<Change>,
+    /// Pass as `since` to continue just past these changes
+    pub next_cursor: String,
+}
+
+/// Wait for item changes (long polling)
+///
+/// Answers as soon as changes the caller may see arrive after `since`, or with
+/// no changes once `wait` expires. A change may be repeated if it was
+/// redelivered. Cursors expire when the change was pushed out of the recent
+/// history or the service restarted; list items again and continue without
+/// `since`.
+#[utoipa::path(
+    get,
+    path = "/api/v1/items/changes",
+    tag = "items",
+    params(ChangesQuery),
+    responses(
+        (status = 200, description = "Changes after the cursor, possibly none", body = ChangesResponse),
+        (status = 400, description = "Invalid wait, or invalid or expired cursor", body = ErrorResponse),
+        (status = 403, description = "Following all items requires the admin role", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+)]
+pub async fn item_changes(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Query(query): Query<ChangesQuery>,
+) -> AppResult<Json<ChangesResponse>> {
+    let filter = list_filter(&state, claims.as_ref(), query.all)?;
+    let wait = match query.wait.as_deref() {
+        Some(wait) => changes::parse_wait(wait)?,
+        None => DEFAULT_CHANGES_WAIT,
+    };
+    // Answer with what there is before the caller gives up
+    let wait = RequestContext::current()
+        .remaining()
+        .map_or(wait, |remaining| wait.min(remaining));
+    // The outbox is shared by every tenant
+    let tenant = current_tenant();
+    let visible = |change: &Change| change.item.tenant_id == tenant && filter.matches(&change.item);
+
+    let batch = state
+        .events
+        .changes()
+        .wait(query.since.as_deref(), wait, visible)
+        .await?;
+    Ok(Json(ChangesResponse {
+        changes: batch.changes,
+        next_cursor: batch.next_cursor,
+    }))
+}
+
+/// Query parameters for item analytics
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct AnalyticsQuery {
+    /// Bucket width
+    #[serde(default)]
+    #[param(inline)]
+    pub granularity: Granularity,
+
+    /// Start of the range; defaults to 24 hours, 30 days or 12 weeks before `to`
+    pub from: Option<DateTime<Utc>>,
+
+    /// End of the range, exclusive; defaults to the end of the current bucket
+    pub to: Option<DateTime<Utc>>,
+
+    /// Count changes to every owner's items (administrators only)
+    #[serde(default)]
+    pub all: bool,
+}
+
+/// Item changes counted per time bucket
+///
+/// Counts come from the change events kept in the outbox, so they reach
+/// back as far as event retention does.
+#[utoipa::path(
+    get,
+    path = "/api/v1/items/analytics",
+    tag = "items",
+    params(AnalyticsQuery),
+    responses(
+        (status = 200, description = "Changes per bucket", body = AnalyticsResponse),
+        (status = 400, description = "Invalid range, or more than 1000 buckets", body = ErrorResponse),
+        (status = 403, description = "Counting all items requires the admin role", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+)]
+pub async fn item_analytics(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Query(query): Query<AnalyticsQuery>,
+) -> AppResult<Json<AnalyticsResponse>> {
+    let filter = list_filter(&state, claims.as_ref(), query.all)?;
+    let range = AnalyticsRange::new(query.granularity, query.from, query.to, state.clock.now())?;
+    let buckets = state.repo.event_histogram(&filter, &range).await?;
+    Ok(Json(AnalyticsResponse {
+        granularity: range.granularity,
+        from: range.from,
+        to: range.to,
+        buckets,
+    }))
+}
+
+/// Item statistics of the tenant
+///
+/// Counts are kept as items change rather than scanned for, so they are
+/// cheap to poll. Windowed counts, owners and top owners cover the changes
+/// this instance has seen since it started; the total covers every item.
+#[utoipa::path(
+    get,
+    path = "/api/v1/items/stats",
+    tag = "items",
+    responses(
+        (status = 200, description = "Item statistics", body = ItemStatsResponse),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Statistics cover every owner's items and require the admin role", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn item_stats(
+    State(state): State<SharedState>,
+    _admin: AdminUser,
+) -> AppResult<Json<ItemStatsResponse>> {
+    Ok(Json(state.stats.snapshot(state.repo.as_ref()).await?))
+}
+
+/// Query parameters for finding possible duplicates
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct DuplicatesQuery {
+    /// Name similarity from 0 to 1 the listed items reach; defaults to
+    /// `DUPLICATE_SIMILARITY_THRESHOLD`
+    pub threshold: Option<f64>,
+
+    /// Most items to list, at most 100
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+
+    /// Compare against every owner's items (admin only)
+    #[serde(default)]
+    pub all: bool,
+}
+
+/// Items that may duplicate an item
+///
+/// Compares the item's name with the names of the caller's other items, or
+/// every item for administrators listing all, and lists those similar
+/// enough, most similar first.
+#[utoipa::path(
+    get,
+    path = "/api/v1/items/{id}/duplicates",
+    tag = "items",
+    params(("id" = String, Path, description = "Item ID"), DuplicatesQuery),
+    responses(
+        (status = 200, description = "Possible duplicates, most similar first", body = DuplicateList),
+        (status = 403, description = "Caller cannot read the item, or list all items", body = ErrorResponse),
+        (status = 404, description = "Item not found", body = ErrorResponse),
+        (status = 422, description = "Invalid threshold or limit", body = ErrorResponse),
+    ),
+)]
+pub async fn find_duplicates(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path(id): Path<String>,
+    Query(query): Query<DuplicatesQuery>,
+) -> AppResult<Json<DuplicateList>> {
+    let item = state.repo.get(&id).await?;
+    policy::authorize(
+        state.access.as_ref(),
+        state.collections.as_ref(),
+        &item,
+        claims.as_ref(),
+        Action::Read,
+    )
+    .await?;
+    let threshold = query
+        .threshold
+        .unwrap_or(state.config.duplicates.similarity_threshold);
+    if !(0.0..=1.0).contains(&threshold) {
+        return Err(AppError::ValidationError("threshold: must be between 0 and 1".to_string()));
+    }
+    if !(1..=100).contains(&query.limit) {
+        return Err(AppError::ValidationError("limit: must be between 1 and 100".to_string()));
+    }
+
+    let filter = list_filter(&state, claims.as_ref(), query.all)?;
+    let mut duplicates = Vec::new();
+    let mut page = Page::first(MAX_PAGE_LIMIT)?;
+    loop {
+        let batch = state.repo.list(&filter, &page).await?;
+        for other in &batch {
+            if other.id == item.id {
+                continue;
+            }
+            let similarity = name_similarity(&item.name, &other.name);
+            if similarity >= threshold {
+                duplicates.push(PossibleDuplicate {
+                    item: other.as_ref().clone(),
+                    similarity,
+                });
+            }
+        }
+        match batch.last() {
+            Some(last) if batch.len() == MAX_PAGE_LIMIT => page = page.next_after(last),
+            _ => break,
+        }
+    }
+    duplicates.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+    duplicates.truncate(query.limit);
+
+    Ok(Json(DuplicateList {
+        item_id: id,
+        threshold,
+        duplicates,
+    }))
+}
+
+/// Merge another item into an item
+///
+/// The item keeps its own values and gains those only the other item has:
+/// a missing description, metadata keys and array values, custom fields,
+/// access grants to other grantees, and a collection if it is not filed in
+/// one. The other item is then deleted. Both changes are made in this
+/// request, so the change feed records them under the same request ID.
+/// Needs write access to both items.
+#[utoipa::path(
+    post,
+    path = "/api/v1/items/{id}/merge",
+    tag = "items",
+    params(("id" = String, Path, description = "Item ID")),
+    request_body = MergeItemsRequest,
+    responses(
+        (status = 200, description = "Items merged", body = MergeReport),
+        (status = 403, description = "Caller cannot write to both items", body = ErrorResponse),
+        (status = 404, description = "Item not found", body = ErrorResponse),
+        (status = 409, description = "The item changed during the merge, another merge of either item did not finish in time, or either item needs approval to change", body = ErrorResponse),
+        (status = 422, description = "Validation error, or the merged metadata breaks a schema", body = ErrorResponse),
+        (status = 423, description = "Either item is locked by another principal", body = ErrorResponse),
+    ),
+)]
+pub async fn merge_items(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path(id): Path<String>,
+    ValidatedJson(request): ValidatedJson<MergeItemsRequest>,
+) -> AppResult<Json<MergeReport>> {
+    if request.source_id == id {
+        return Err(AppError::ValidationError(
+            "source_id: an item cannot be merged into itself".to_string(),
+        ));
+    }
+    // Merges touching either item, on any replica, wait for this one
+    let (target_lock, source_lock) =
+        (format!("items/{id}"), format!("items/{}", request.source_id));
+    let locks = state
+        .locks
+        .acquire_all(&[&target_lock, &source_lock])
+        .await?;
+    let target = state.repo.get(&id).await?;
+    let source = state.repo.get(&request.source_id).await?;
+    for item in [&target, &source] {
+        policy::authorize(
+            state.access.as_ref(),
+            state.collections.as_ref(),
+            item,
+            claims.as_ref(),
+            Action::Write,
+        )
+        .await?;
+        checkout::ensure_unlocked(state.item_locks.as_ref(), &item.id, claims.as_ref()).await?;
+        if needs_approval(&state, &item.id).await? {
+            return Err(DatabaseError::Conflict(format!(
+                "Changes to item {} need approval, so it cannot be merged",
+                item.id
+            ))
+            .into());
+        }
+    }
+
+    let ctx = HookContext {
+        claims: claims.as_ref(),
+    };
+    let mut update = merge::merge_update(&target, &source);
+    state.hooks.before_update(ctx, &target, &mut update).await?;
+    state.hooks.before_delete(ctx, &source).await?;
+    if let Some(metadata) = &update.metadata {
+        state.schemas.validate(metadata)?;
+    }
+    state.custom_fields.validate_update(&update.custom_fields)?;
+
+    let item = state.repo.update(&id, update, Some(target.version)).await?;
+    let existing = state.access.list_grants(&id).await?;
+    let mut grants = 0;
+    for grant in state.access.list_grants(&source.id).await? {
+        if existing.iter().all(|other| other.grantee != grant.grantee) {
+            state
+                .access
+                .grant(&id, grant.grantee, grant.permission, grant.granted_by)
+                .await?;
+            grants += 1;
+        }
+    }
+    if state.collections.item_collection(&id).await?.is_none() {
+        if let Some(collection_id) = state.collections.item_collection(&source.id).await? {
+            state
+                .collections
+                .file_item(&id, Some(&collection_id))
+                .await?;
+        }
+    }
+
+    remove_item(&state, &source.id).await?;
+    state.hooks.after_update(ctx, &item).await;
+    state.hooks.after_delete(ctx, &source).await;
+    for lock in locks {
+        lock.release().await;
+    }
+
+    Ok(Json(MergeReport {
+        item,
+        merged_from: source.id,
+        grants,
+    }))
+}
+
+// ===== PUBLISHING HANDLERS =====
+
+/// Publish a draft, or schedule it to be published
+///
+/// Without `publish_at`, or with a time already past, the item is published
+/// at once; otherwise it stays a draft until the publishing job runs after
+/// that time. Publishing a published item changes nothing. Drafts whose
+/// changes need approval are published through a change request, and cannot
+/// be scheduled.
+#[utoipa::path(
+    post,
+    path = "/api/v1/items/{id}/publish",
+    tag = "items",
+    params(("id" = String, Path, description = "Item ID")),
+    request_body = PublishItemRequest,
+    responses(
+        (status = 200, description = "The published or scheduled item", body = Item),
+        (status = 202, description = "Publication held for approval", body = ChangeRequest),
+        (status = 401, description = "Publication needs approval, which requires authentication", body = ErrorResponse),
+        (status = 403, description = "Caller cannot modify the item", body = ErrorResponse),
+        (status = 404, description = "Item not found", body = ErrorResponse),
+        (status = 409, description = "The draft needs approval to be published, so cannot be scheduled", body = ErrorResponse),
+        (status = 422, description = "Validation error", body = ErrorResponse),
+        (status = 423, description = "Item is locked by another principal", body = ErrorResponse),
+    ),
+)]
+pub async fn publish_item(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    representation: Representation,
+    Path(id): Path<String>,
+    ValidatedJson(request): ValidatedJson<PublishItemRequest>,
+) -> AppResult<Response> {
+    let item = state.repo.get(&id).await?;
+    policy::authorize(
+        state.access.as_ref(),
+        state.collections.as_ref(),
+        &item,
+        claims.as_ref(),
+        Action::Write,
+    )
+    .await?;
+    checkout::ensure_unlocked(state.item_locks.as_ref(), &id, claims.as_ref()).await?;
+
+    if item.is_draft() && needs_approval(&state, &id).await? {
+        let now = state.clock.now();
+        if request.publish_at.is_some_and(|at| at > now) {
+            return Err(DatabaseError::Conflict(
+                "Publishing this draft needs approval, so it cannot be scheduled".to_string(),
+            )
+            .into());
+        }
+        let change =
+            request_change(&state, claims.as_ref(), &item, ChangeAction::Publish, None).await?;
+        return Ok((StatusCode::ACCEPTED, Json(change)).into_response());
+    }
+
+    let item = if item.is_draft() {
+        let update = publishing::publication(request.publish_at, state.clock.now());
+        let updated = state.repo.update(&id, update, Some(item.version)).await?;
+        if !updated.is_draft() {
+            track_item_published("request");
+        }
+        updated
+    } else {
+        item
+    };
+    let etag = conflicts::etag(&item);
+    Ok(([(ETAG, etag)], representation.item(item)).into_response())
+}
+
+// ===== LOCK HANDLERS =====
+
+/// Lock an item for editing, or extend the caller's lock on it
+///
+/// While the lock lasts, updates, deletes and merges of the item by anyone
+/// but its owner are refused with 423. Locks expire after `ttl_seconds`, or
+/// `ITEM_LOCK_DEFAULT_TTL_SECONDS` when it is not given; owners renew them
+/// by locking again before then.
+#[utoipa::path(
+    post,
+    path = "/api/v1/items/{id}/lock",
+    tag = "items",
+    params(("id" = String, Path, description = "Item ID")),
+    request_body = LockItemRequest,
+    responses(
+        (status = 200, description = "The caller's lock on the item", body = ItemLock),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Caller cannot modify the item", body = ErrorResponse),
+        (status = 404, description = "Item not found", body = ErrorResponse),
+        (status = 422, description = "Validation error", body = ErrorResponse),
+        (status = 423, description = "Item is locked by another principal", body = ErrorResponse),
+    ),
+)]
+pub async fn lock_item(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path(id): Path<String>,
+    ValidatedJson(request): ValidatedJson<LockItemRequest>,
+) -> AppResult<Json<ItemLock>> {
+    let claims = claims.ok_or_else(|| {
+        AppError::Unauthorized("Locking an item requires a principal".to_string())
+    })?;
+    let item = state.repo.get(&id).await?;
+    policy::authorize(
+        state.access.as_ref(),
+        state.collections.as_ref(),
+        &item,
+        Some(&claims),
+        Action::Write,
+    )
+    .await?;
+
+    let config = &state.config.item_locks;
+    let ttl = request.ttl_seconds.unwrap_or(config.default_ttl_seconds);
+    if ttl > config.max_ttl_seconds {
+        return Err(AppError::ValidationError(format!(
+            "ttl_seconds: must be at most {}",
+            config.max_ttl_seconds
+        )));
+    }
+    let now = state.clock.now();
+    let lock = state
+        .item_locks
+        .acquire(ItemLock {
+            item_id: id,
+            owner: claims.sub.clone(),
+            acquired_at: now,
+            expires_at: now + chrono::Duration::seconds(ttl as i64),
+        })
+        .await?;
+    if lock.owner != claims.sub {
+        return Err(checkout::locked(&lock));
+    }
+    Ok(Json(lock))
+}
+
+/// Who holds the lock on an item
+#[utoipa::path(
+    get,
+    path = "/api/v1/items/{id}/lock",
+    tag = "items",
+    params(("id" = String, Path, description = "Item ID")),
+    responses(
+        (status = 200, description = "The lock on the item", body = ItemLock),
+        (status = 403, description = "Caller cannot read the item", body = ErrorResponse),
+        (status = 404, description = "Item not found, or not locked", body = ErrorResponse),
+    ),
+)]
+pub async fn get_item_lock(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path(id): Path<String>,
+) -> AppResult<Json<ItemLock>> {
+    let item = state.repo.get(&id).await?;
+    policy::authorize(
+        state.access.as_ref(),
+        state.collections.as_ref(),
+        &item,
+        claims.as_ref(),
+        Action::Read,
+    )
+    .await?;
+    let lock = state
+        .item_locks
+        .get(&id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Item {id} is not locked")))?;
+    Ok(Json(lock))
+}
+
+/// Release the lock on an item
+///
+/// Only the lock's owner may release it, or an administrator, who can break
+/// a lock someone abandoned before it expires.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/items/{id}/lock",
+    tag = "items",
+    params(("id" = String, Path, description = "Item ID")),
+    responses(
+        (status = 204, description = "The item is not locked"),
+        (status = 403, description = "Caller cannot modify the item", body = ErrorResponse),
+        (status = 404, description = "Item not found", body = ErrorResponse),
+        (status = 423, description = "Item is locked by another principal", body = ErrorResponse),
+    ),
+)]
+pub async fn unlock_item(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path(id): Path<String>,
+) -> AppResult<StatusCode> {
+    let item = state.repo.get(&id).await?;
+    policy::authorize(
+        state.access.as_ref(),
+        state.collections.as_ref(),
+        &item,
+        claims.as_ref(),
+        Action::Write,
+    )
+    .await?;
+    if !claims.as_ref().is_some_and(Claims::is_admin) {
+        checkout::ensure_unlocked(state.item_locks.as_ref(), &id, claims.as_ref()).await?;
+    }
+    state.item_locks.release(&id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ===== CHANGE REQUEST HANDLERS =====
+
+/// Whether changes to item `id` are held for approval
+async fn needs_approval(state: &SharedState, id: &str) -> AppResult<bool> {
+    Ok(state
+        .approvals
+        .requires_approval(state.collections.as_ref(), id)
+        .await?)
+}
+
+/// Hold a change to `item` for approval, telling those who may approve it
+async fn request_change(
+    state: &SharedState,
+    claims: Option<&Claims>,
+    item: &Item,
+    action: ChangeAction,
+    update: Option<UpdateItemRequest>,
+) -> AppResult<ChangeRequest> {
+    let claims = claims.ok_or_else(|| {
+        AppError::Unauthorized(
+            "Changing this item needs approval, which requires a principal".to_string(),
+        )
+    })?;
+    let change = ChangeRequest::new(
+        item,
+        action,
+        update,
+        &claims.sub,
+        state.approvals.required(),
+        state.clock.now(),
+    );
+    let change = state.change_requests.create(change).await?;
+    track_change_request(ChangeStatus::Pending.as_str());
+    tracing::info!(change = %change.id, item = %item.id, requested_by = %claims.sub, "Change requested");
+
+    if let Some(inbox) = &state.inbox {
+        let approvers =
+            approvals::approvers(state.access.as_ref(), state.collections.as_ref(), item).await?;
+        if let Err(e) = inbox
+            .add(change.approval_requested(&item.name, &approvers))
+            .await
+        {
+            tracing::warn!(change = %change.id, "Failed to notify approvers: {}", e);
+        }
+    }
+    Ok(change)
+}
+
+/// Whether the caller may approve or reject changes to `item`: its owner,
+/// administrators, and principals granted write access, even to unowned
+/// items; only administrators decide on changes to items that are gone
+async fn may_decide(state: &SharedState, claims: &Claims, item: Option<&Item>) -> AppResult<bool> {
+    if claims.is_admin() {
+        return Ok(true);
+    }
+    let Some(item) = item else {
+        return Ok(false);
+    };
+    policy::holds(
+        state.access.as_ref(),
+        state.collections.as_ref(),
+        item,
+        claims,
+        Permission::Write,
+    )
+    .await
+}
+
+/// The item a change request is for, unless it was deleted
+async fn changed_item(state: &SharedState, change: &ChangeRequest) -> AppResult<Option<Item>> {
+    match state.repo.get(&change.item_id).await {
+        Ok(item) => Ok(Some(item)),
+        Err(DatabaseError::NotFound) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Change request `id` and its item, if the caller made the request or may
+/// decide on it; other requests are not found
+async fn visible_change(
+    state: &SharedState,
+    claims: &Claims,
+    id: &str,
+) -> AppResult<(ChangeRequest, Option<Item>)> {
+    let not_found = || AppError::NotFound(format!("Change request {id} not found"));
+    let change = match state.change_requests.get(id).await {
+        Ok(change) => change,
+        Err(DatabaseError::NotFound) => return Err(not_found()),
+        Err(e) => return Err(e.into()),
+    };
+    let item = changed_item(state, &change).await?;
+    if change.requested_by == claims.sub || may_decide(state, claims, item.as_ref()).await? {
+        Ok((change, item))
+    } else {
+        Err(not_found())
+    }
+}
+
+/// Make an approved change, resolving its request as applied, or as failed
+/// when the item changed or was deleted since the change was requested
+async fn apply_change(
+    state: &SharedState,
+    claims: &Claims,
+    change: &ChangeRequest,
+    item: Option<&Item>,
+) -> AppResult<ChangeRequest> {
+    let ctx = HookContext {
+        claims: Some(claims),
+    };
+    let outcome = match item {
+        None => Err("The item no longer exists".to_string()),
+        Some(item) if item.version != change.base_version => {
+            Err(format!("The item changed since version {}", change.base_version))
+        }
+        Some(item) => match change.action {
+            ChangeAction::Update | ChangeAction::Publish => {
+                let update = match change.action {
+                    ChangeAction::Publish => publishing::publication(None, state.clock.now()),
+                    _ => change.update.clone().unwrap_or_default(),
+                };
+                match state
+                    .repo
+                    .update(&item.id, update, Some(change.base_version))
+                    .await
+                {
+                    Ok(updated) => {
+                        if item.is_draft() && !updated.is_draft() {
+                            track_item_published("approval");
+                        }
+                        state.hooks.after_update(ctx, &updated).await;
+                        Ok(())
+                    }
+                    Err(DatabaseError::VersionMismatch { .. }) => {
+                        Err(format!("The item changed since version {}", change.base_version))
+                    }
+                    Err(DatabaseError::NotFound) => Err("The item no longer exists".to_string()),
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            ChangeAction::Delete => match remove_item(state, &item.id).await {
+                Ok(()) => {
+                    state.hooks.after_delete(ctx, item).await;
+                    Ok(())
+                }
+                Err(DatabaseError::NotFound) => Err("The item no longer exists".to_string()),
+                Err(e) => return Err(e.into()),
+            },
+        },
+    };
+
+    let (status, reason) = match outcome {
+        Ok(()) => (ChangeStatus::Applied, None),
+        Err(reason) => (ChangeStatus::Failed, Some(reason)),
+    };
+    let change = state
+        .change_requests
+        .resolve(&change.id, status, &claims.sub, reason)
+        .await?;
+    track_change_request(status.as_str());
+    Ok(change)
+}
+
+/// Tell the requester how their change request was resolved, unless they
+/// resolved it themselves
+async fn notify_requester(state: &SharedState, change: &ChangeRequest, item: Option<&Item>) {
+    let Some(inbox) = &state.inbox else {
+        return;
+    };
+    if change.resolved_by.as_deref() == Some(change.requested_by.as_str()) {
+        return;
+    }
+    let item_name = item.map_or(change.item_id.as_str(), |item| item.name.as_str());
+    if let Err(e) = inbox.add(vec![change.resolved(item_name)]).await {
+        tracing::warn!(change = %change.id, "Failed to notify the requester: {}", e);
+    }
+}
+
+/// Query parameters for listing change requests
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ChangeRequestsQuery {
+    /// Only list requests in this state
+    pub status: Option<ChangeStatus>,
+
+    /// Page size, at most 100
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+
+    #[serde(default)]
+    pub offset: usize,
+}
+
+/// List change requests the caller made or may decide on, newest first
+///
+/// Administrators see every change request.
+#[utoipa::path(
+    get,
+    path = "/api/v1/changes",
+    tag = "changes",
+    params(ChangeRequestsQuery),
+    responses(
+        (status = 200, description = "A page of change requests", body = ChangeRequestList),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 422, description = "Invalid limit", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn list_changes(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Query(query): Query<ChangeRequestsQuery>,
+) -> AppResult<Json<ChangeRequestList>> {
+    let claims = authenticated(claims)?;
+    if !(1..=MAX_PAGE_LIMIT).contains(&query.limit) {
+        return Err(AppError::ValidationError(format!(
+            "limit: must be between 1 and {MAX_PAGE_LIMIT}"
+        )));
+    }
+
+    let mut decides: HashMap<String, bool> = HashMap::new();
+    let mut changes = Vec::new();
+    for change in state.change_requests.list(query.status).await? {
+        let visible = if change.requested_by == claims.sub || claims.is_admin() {
+            true
+        } else if let Some(decides) = decides.get(&change.item_id) {
+            *decides
+        } else {
+            let item = changed_item(&state, &change).await?;
+            let decides_item = may_decide(&state, &claims, item.as_ref()).await?;
+            decides.insert(change.item_id.clone(), decides_item);
+            decides_item
+        };
+        if visible {
+            changes.push(change);
+        }
+    }
+
+    let total = changes.len();
+    Ok(Json(ChangeRequestList {
+        changes: changes
+            .into_iter()
+            .skip(query.offset)
+            .take(query.limit)
+            .collect(),
+        total,
+        limit: query.limit,
+        offset: query.offset,
+    }))
+}
+
+/// Get a change request the caller made or may decide on
+#[utoipa::path(
+    get,
+    path = "/api/v1/changes/{id}",
+    tag = "changes",
+    params(("id" = String, Path, description = "Change request ID")),
+    responses(
+        (status = 200, description = "The change request", body = ChangeRequest),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 404, description = "Change request not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn get_change(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path(id): Path<String>,
+) -> AppResult<Json<ChangeRequest>> {
+    let claims = authenticated(claims)?;
+    let (change, _) = visible_change(&state, &claims, &id).await?;
+    Ok(Json(change))
+}
+
+/// Approve a change request
+///
+/// Needs write access to the item, and cannot be done by the principal who
+/// requested the change. The approval that brings the request to its
+/// required approvals applies the change: the request is then `applied`, or
+/// `failed` if the item changed or was deleted since it was requested.
+#[utoipa::path(
+    post,
+    path = "/api/v1/changes/{id}/approve",
+    tag = "changes",
+    params(("id" = String, Path, description = "Change request ID")),
+    responses(
+        (status = 200, description = "The approved change request", body = ChangeRequest),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Caller requested the change, or cannot modify the item", body = ErrorResponse),
+        (status = 404, description = "Change request not found", body = ErrorResponse),
+        (status = 409, description = "Change request is no longer pending, or the caller already approved it", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn approve_change(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path(id): Path<String>,
+) -> AppResult<Json<ChangeRequest>> {
+    let claims = authenticated(claims)?;
+    let (change, item) = visible_change(&state, &claims, &id).await?;
+    if change.requested_by == claims.sub {
+        return Err(AppError::Forbidden(
+            "Change requests cannot be approved by the principal who made them".to_string(),
+        ));
+    }
+    if !may_decide(&state, &claims, item.as_ref()).await? {
+        return Err(AppError::Forbidden("You do not have write access to this item".to_string()));
+    }
+
+    let approval = Approval {
+        principal: claims.sub.clone(),
+        approved_at: state.clock.now(),
+    };
+    let change = state.change_requests.approve(&id, approval).await?;
+    tracing::info!(change = %id, approved_by = %claims.sub, "Change approved");
+    // Approvals are recorded one at a time, so only one of them completes the request
+    if change.approvals.len() != change.required_approvals as usize {
+        return Ok(Json(change));
+    }
+    let change = apply_change(&state, &claims, &change, item.as_ref()).await?;
+    notify_requester(&state, &change, item.as_ref()).await;
+    Ok(Json(change))
+}
+
+/// Reject a change request, or withdraw one the caller made
+#[utoipa::path(
+    post,
+    path = "/api/v1/changes/{id}/reject",
+    tag = "changes",
+    params(("id" = String, Path, description = "Change request ID")),
+    request_body = RejectChangeRequest,
+    responses(
+        (status = 200, description = "The rejected change request", body = ChangeRequest),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 404, description = "Change request not found", body = ErrorResponse),
+        (status = 409, description = "Change request is no longer pending", body = ErrorResponse),
+        (status = 422, description = "Validation error", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn reject_change(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path(id): Path<String>,
+    ValidatedJson(request): ValidatedJson<RejectChangeRequest>,
+) -> AppResult<Json<ChangeRequest>> {
+    let claims = authenticated(claims)?;
+    let (_, item) = visible_change(&state, &claims, &id).await?;
+    let change = state
+        .change_requests
+        .resolve(&id, ChangeStatus::Rejected, &claims.sub, request.reason)
+        .await?;
+    track_change_request(ChangeStatus::Rejected.as_str());
+    tracing::info!(change = %id, rejected_by = %claims.sub, "Change rejected");
+    notify_requester(&state, &change, item.as_ref()).await;
+    Ok(Json(change))
+}
+
+// ===== VIEW HANDLERS =====
+
+/// Query parameters for reading a view
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ViewQuery {
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+
+    /// Items of the view to skip
+    #[serde(default)]
+    pub offset: usize,
+}
+
+/// Read a page of a materialized list view
+///
+/// Views are list queries defined in configuration and answered from memory.
+/// `refreshed_at`, `age_seconds` and `stale` tell how current the answer is.
+#[utoipa::path(
+    get,
+    path = "/api/v1/views/{name}",
+    tag = "items",
+    params(
+        ("name" = String, Path, description = "View name"),
+        ViewQuery,
+    ),
+    responses(
+        (status = 200, description = "A page of the view", body = ViewResponse),
+        (status = 400, description = "Invalid limit or offset", body = ErrorResponse),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Views cover every owner's items and require the admin role", body = ErrorResponse),
+        (status = 404, description = "View not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn get_view(
+    State(state): State<SharedState>,
+    _admin: AdminUser,
+    Path(name): Path<String>,
+    Query(query): Query<ViewQuery>,
+) -> AppResult<Json<ViewResponse>> {
+    let page = Page::offset(query.limit, query.offset)?;
+    let not_found = || AppError::NotFound(format!("View {name} not found"));
+    let views = state.views.as_ref().ok_or_else(not_found)?;
+    let snapshot = views
+        .get(&name, state.repo.as_ref())
+        .await?
+        .ok_or_else(not_found)?;
+
+    Ok(Json(ViewResponse {
+        items: snapshot
+            .items
+            .iter()
+            .skip(page.skipped())
+            .take(page.limit())
+            .cloned()
+            .collect(),
+        total: snapshot.total,
+        kept: snapshot.items.len(),
+        limit: page.limit(),
+        offset: page.skipped(),
+        refreshed_at: snapshot.refreshed_at,
+        age_seconds: views.age(&snapshot).as_secs(),
+        stale: snapshot.is_stale(),
+        name,
+    }))
+}
+
+// ===== CUSTOM FIELD HANDLERS =====
+
+fn custom_field_not_found(name: &str) -> AppError {
+    AppError::NotFound(format!("Custom field {name} not found"))
+}
+
+/// List the custom fields defined for the tenant's items
+#[utoipa::path(
+    get,
+    path = "/api/v1/fields",
+    tag = "fields",
+    responses(
+        (status = 200, description = "Custom field definitions", body = [CustomFieldDefinition]),
+    ),
+)]
+pub async fn list_custom_fields(
+    State(state): State<SharedState>,
+) -> AppResult<Json<Vec<CustomFieldDefinition>>> {
+    Ok(Json(state.custom_fields.list()?))
+}
+
+/// JSON Schema of the tenant's custom fields
+///
+/// The `custom_fields` object items carry, as the OpenAPI document would
+/// describe it for the tenant.
+#[utoipa::path(
+    get,
+    path = "/api/v1/fields/schema",
+    tag = "fields",
+    responses(
+        (status = 200, description = "JSON Schema of `custom_fields`", body = Object),
+    ),
+)]
+pub async fn custom_fields_schema(
+    State(state): State<SharedState>,
+) -> AppResult<Json<serde_json::Value>> {
+    Ok(Json(state.custom_fields.json_schema(false)?))
+}
+
+/// Define a custom field, or replace its definition
+///
+/// Values items already hold are not checked against a new definition.
+#[utoipa::path(
+    put,
+    path = "/api/v1/fields/{name}",
+    tag = "fields",
+    params(
+        ("name" = String, Path, description = "Field name"),
+    ),
+    request_body = DefineCustomFieldRequest,
+    responses(
+        (status = 200, description = "Field defined", body = CustomFieldDefinition),
+        (status = 400, description = "Invalid field name", body = ErrorResponse),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required, or too many fields", body = ErrorResponse),
+        (status = 422, description = "Invalid definition", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn define_custom_field(
+    State(state): State<SharedState>,
+    AdminUser(claims): AdminUser,
+    Path(name): Path<String>,
+    Json(request): Json<DefineCustomFieldRequest>,
+) -> AppResult<Json<CustomFieldDefinition>> {
+    let field = state.custom_fields.define(&name, request)?;
+    tracing::info!(field = %field.name, changed_by = %claims.sub, "Custom field defined");
+    Ok(Json(field))
+}
+
+/// Get a custom field definition
+#[utoipa::path(
+    get,
+    path = "/api/v1/fields/{name}",
+    tag = "fields",
+    params(
+        ("name" = String, Path, description = "Field name"),
+    ),
+    responses(
+        (status = 200, description = "The field definition", body = CustomFieldDefinition),
+        (status = 404, description = "Field not found", body = ErrorResponse),
+    ),
+)]
+pub async fn get_custom_field(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> AppResult<Json<CustomFieldDefinition>> {
+    state
+        .custom_fields
+        .get(&name)?
+        .map(Json)
+        .ok_or_else(|| custom_field_not_found(&name))
+}
+
+/// Delete a custom field definition
+///
+/// Items keep the values they hold until they clear them, but can no longer
+/// set the field.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/fields/{name}",
+    tag = "fields",
+    params(
+        ("name" = String, Path, description = "Field name"),
+    ),
+    responses(
+        (status = 204, description = "Field deleted"),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 404, description = "Field not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn delete_custom_field(
+    State(state): State<SharedState>,
+    AdminUser(claims): AdminUser,
+    Path(name): Path<String>,
+) -> AppResult<StatusCode> {
+    if !state.custom_fields.remove(&name)? {
+        return Err(custom_field_not_found(&name));
+    }
+    tracing::info!(field = %name, changed_by = %claims.sub, "Custom field deleted");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ===== METADATA SCHEMA HANDLERS =====
+
+/// Query parameters choosing the scope of a metadata schema
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct SchemaScopeQuery {
+    /// The global schema rather than the tenant's own
+    #[serde(default)]
+    pub global: bool,
+}
+
+fn schema_not_found(name: &str) -> AppError {
+    AppError::NotFound(format!("Schema {name} not found"))
+}
+
+/// List the metadata schemas item metadata is held to
+///
+/// The latest version of each schema in effect for the tenant: its own, and
+/// the global ones it has no schema of the same name for.
+#[utoipa::path(
+    get,
+    path = "/api/v1/schemas",
+    tag = "schemas",
+    responses(
+        (status = 200, description = "Schemas in effect", body = [MetadataSchema]),
+    ),
+)]
+pub async fn list_schemas(
+    State(state): State<SharedState>,
+) -> AppResult<Json<Vec<MetadataSchema>>> {
+    Ok(Json(state.schemas.list()?))
+}
+
+/// Register a new version of a metadata schema
+///
+/// Items created from now on, and updates setting metadata, must satisfy
+/// it; existing items are not checked again.
+#[utoipa::path(
+    put,
+    path = "/api/v1/schemas/{name}",
+    tag = "schemas",
+    params(
+        ("name" = String, Path, description = "Schema name"),
+    ),
+    request_body = RegisterSchemaRequest,
+    responses(
+        (status = 201, description = "Schema version registered", body = MetadataSchema),
+        (status = 400, description = "Invalid schema name", body = ErrorResponse),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 422, description = "Invalid or unsupported schema", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn register_schema(
+    State(state): State<SharedState>,
+    AdminUser(claims): AdminUser,
+    Path(name): Path<String>,
+    Json(request): Json<RegisterSchemaRequest>,
+) -> AppResult<(StatusCode, Json<MetadataSchema>)> {
+    let schema = state
+        .schemas
+        .register(&name, request, Some(claims.sub.clone()))?;
+    tracing::info!(
+        schema = %schema.name,
+        version = schema.version,
+        changed_by = %claims.sub,
+        "Metadata schema registered"
+    );
+    Ok((StatusCode::CREATED, Json(schema)))
+}
+
+/// Get the latest version of a metadata schema
+#[utoipa::path(
+    get,
+    path = "/api/v1/schemas/{name}",
+    tag = "schemas",
+    params(
+        ("name" = String, Path, description = "Schema name"),
+        SchemaScopeQuery,
+    ),
+    responses(
+        (status = 200, description = "Latest version of the schema", body = MetadataSchema),
+        (status = 404, description = "Schema not found", body = ErrorResponse),
+    ),
+)]
+pub async fn get_schema(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Query(query): Query<SchemaScopeQuery>,
+) -> AppResult<Json<MetadataSchema>> {
+    state
+        .schemas
+        .versions(&name, query.global)?
+        .and_then(|mut versions| versions.pop())
+        .map(Json)
+        .ok_or_else(|| schema_not_found(&name))
+}
+
+/// List every version of a metadata schema, oldest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/schemas/{name}/versions",
+    tag = "schemas",
+    params(
+        ("name" = String, Path, description = "Schema name"),
+        SchemaScopeQuery,
+    ),
+    responses(
+        (status = 200, description = "Versions of the schema", body = [MetadataSchema]),
+        (status = 404, description = "Schema not found", body = ErrorResponse),
+    ),
+)]
+pub async fn list_schema_versions(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Query(query): Query<SchemaScopeQuery>,
+) -> AppResult<Json<Vec<MetadataSchema>>> {
+    state
+        .schemas
+        .versions(&name, query.global)?
+        .map(Json)
+        .ok_or_else(|| schema_not_found(&name))
+}
+
+/// Get one version of a metadata schema
+#[utoipa::path(
+    get,
+    path = "/api/v1/schemas/{name}/versions/{version}",
+    tag = "schemas",
+    params(
+        ("name" = String, Path, description = "Schema name"),
+        ("version" = u32, Path, description = "Schema version"),
+        SchemaScopeQuery,
+    ),
+    responses(
+        (status = 200, description = "The schema version", body = MetadataSchema),
+        (status = 404, description = "Schema or version not found", body = ErrorResponse),
+    ),
+)]
+pub async fn get_schema_version(
+    State(state): State<SharedState>,
+    Path((name, version)): Path<(String, u32)>,
+    Query(query): Query<SchemaScopeQuery>,
+) -> AppResult<Json<MetadataSchema>> {
+    state
+        .schemas
+        .versions(&name, query.global)?
+        .and_then(|versions| {
+            versions
+                .into_iter()
+                .find(|schema| schema.version == version)
+        })
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound(format!("Schema {name} has no version {version}")))
+}
+
+/// Delete a metadata schema and all its versions
+///
+/// Items are no longer held to it. A global schema of the same name, if
+/// any, applies to the tenant again.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/schemas/{name}",
+    tag = "schemas",
+    params(
+        ("name" = String, Path, description = "Schema name"),
+        SchemaScopeQuery,
+    ),
+    responses(
+        (status = 204, description = "Schema deleted"),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 404, description = "Schema not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn delete_schema(
+    State(state): State<SharedState>,
+    AdminUser(claims): AdminUser,
+    Path(name): Path<String>,
+    Query(query): Query<SchemaScopeQuery>,
+) -> AppResult<StatusCode> {
+    if !state.schemas.remove(&name, query.global)? {
+        return Err(schema_not_found(&name));
+    }
+    tracing::info!(schema = %name, changed_by = %claims.sub, "Metadata schema deleted");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ===== ACCESS CONTROL HANDLERS =====
+
+/// Grant another principal or role access to an item
+#[utoipa::path(
+    post,
+    path = "/api/v1/items/{id}/permissions",
+    tag = "items",
+    params(
+        ("id" = String, Path, description = "Item ID")
+    ),
+    request_body = GrantPermissionRequest,
+    responses(
+        (status = 201, description = "Access granted", body = AccessGrant),
+        (status = 400, description = "Bad request", body = ErrorResponse),
+        (status = 403, description = "Caller cannot manage access to the item", body = ErrorResponse),
+        (status = 404, description = "Item not found", body = ErrorResponse),
+        (status = 422, description = "Validation error", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+)]
+pub async fn grant_permission(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path(id): Path<String>,
+    ValidatedJson(request): ValidatedJson<GrantPermissionRequest>,
+) -> AppResult<impl IntoResponse> {
+    let item = state.repo.get(&id).await?;
+    policy::authorize(
+        state.access.as_ref(),
+        state.collections.as_ref(),
+        &item,
+        claims.as_ref(),
+        Action::Share,
+    )
+    .await?;
+
+    let granted_by = claims.map(|claims| claims.sub);
+    let grant = state
+        .access
+        .grant(&id, request.grantee, request.permission, granted_by)
+        .await?;
+    Ok((StatusCode::CREATED, Json(grant)))
+}
+
+/// List access grants on an item
+#[utoipa::path(
+    get,
+    path = "/api/v1/items/{id}/permissions",
+    tag = "items",
+    params(
+        ("id" = String, Path, description = "Item ID")
+    ),
+    responses(
+        (status = 200, description = "Access grants retrieved successfully", body = [AccessGrant]),
+        (status = 403, description = "Caller cannot manage access to the item", body = ErrorResponse),
+        (status = 404, description = "Item not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+)]
+pub async fn list_permissions(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path(id): Path<String>,
+) -> AppResult<impl IntoResponse> {
+    let item = state.repo.get(&id).await?;
+    policy::authorize(
+        state.access.as_ref(),
+        state.collections.as_ref(),
+        &item,
+        claims.as_ref(),
+        Action::Share,
+    )
+    .await?;
+
+    let grants = state.access.list_grants(&id).await?;
+    Ok(Json(grants))
+}
+
+/// Revoke an access grant on an item
+#[utoipa::path(
+    delete,
+    path = "/api/v1/items/{id}/permissions/{grant_id}",
+    tag = "items",
+    params(
+        ("id" = String, Path, description = "Item ID"),
+        ("grant_id" = String, Path, description = "Access grant ID")
+    ),
+    responses(
+        (status = 204, description = "Access revoked"),
+        (status = 403, description = "Caller cannot manage access to the item", body = ErrorResponse),
+        (status = 404, description = "Item or grant not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+)]
+pub async fn revoke_permission(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path((id, grant_id)): Path<(String, String)>,
+) -> AppResult<impl IntoResponse> {
+    let item = state.repo.get(&id).await?;
+    policy::authorize(
+        state.access.as_ref(),
+        state.collections.as_ref(),
+        &item,
+        claims.as_ref(),
+        Action::Share,
+    )
+    .await?;
+
+    state.access.revoke(&id, &grant_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ===== ATTACHMENT HANDLERS =====
+
+fn attachments(state: &SharedState) -> AppResult<&Arc<Attachments>> {
+    state
+        .attachments
+        .as_ref()
+        .ok_or_else(|| AppError::NotFound("Attachments are not configured".to_string()))
+}
+
+/// Presign the upload of a file to an item
+///
+/// The file is uploaded straight to S3 by sending `PUT` to the returned URL
+/// with the returned headers, before `expires_at`. The attachment is
+/// recorded once the upload is confirmed at `confirm_url`.
+#[utoipa::path(
+    post,
+    path = "/api/v1/items/{id}/attachments/presign",
+    tag = "items",
+    params(("id" = String, Path, description = "Item ID")),
+    request_body = PresignUploadRequest,
+    responses(
+        (status = 200, description = "Upload URL and confirmation path", body = PresignedUpload),
+        (status = 403, description = "Caller cannot modify the item", body = ErrorResponse),
+        (status = 404, description = "Item not found, or attachments are not configured", body = ErrorResponse),
+        (status = 422, description = "Validation error", body = ErrorResponse),
+    ),
+)]
+pub async fn presign_attachment(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path(id): Path<String>,
+    Json(request): Json<PresignUploadRequest>,
+) -> AppResult<Json<PresignedUpload>> {
+    let attachments = attachments(&state)?;
+    let item = state.repo.get(&id).await?;
+    policy::authorize(
+        state.access.as_ref(),
+        state.collections.as_ref(),
+        &item,
+        claims.as_ref(),
+        Action::Write,
+    )
+    .await?;
+
+    let upload = attachments.presign(&id, request, claims.map(|claims| claims.sub))?;
+    Ok(Json(upload))
+}
+
+/// Record an attachment once its presigned upload has completed
+///
+/// The object is looked up in the bucket first, and must have the presigned
+/// size. With a scanner configured, the attachment is recorded as `pending`
+/// and scanned in the background. Confirming an upload again answers with
+/// the attachment recorded the first time.
+#[utoipa::path(
+    post,
+    path = "/api/v1/items/{id}/attachments/{upload_id}/confirm",
+    tag = "items",
+    params(
+        ("id" = String, Path, description = "Item ID"),
+        ("upload_id" = String, Path, description = "Upload ID returned when presigning"),
+    ),
+    responses(
+        (status = 201, description = "Attachment recorded", body = Attachment),
+        (status = 200, description = "Attachment already recorded", body = Attachment),
+        (status = 403, description = "Caller cannot modify the item", body = ErrorResponse),
+        (status = 404, description = "Item or upload not found", body = ErrorResponse),
+        (status = 409, description = "Upload has not completed or does not match", body = ErrorResponse),
+        (status = 503, description = "Attachment storage is unavailable", body = ErrorResponse),
+    ),
+)]
+pub async fn confirm_attachment(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path((id, upload_id)): Path<(String, String)>,
+) -> AppResult<impl IntoResponse> {
+    let attachments = attachments(&state)?;
+    let item = state.repo.get(&id).await?;
+    policy::authorize(
+        state.access.as_ref(),
+        state.collections.as_ref(),
+        &item,
+        claims.as_ref(),
+        Action::Write,
+    )
+    .await?;
+
+    let (attachment, created) = attachments.confirm(&id, &upload_id).await?;
+    let status = if created {
+        StatusCode::CREATED
+    } else {
+        StatusCode::OK
+    };
+    Ok((status, Json(attachment)))
+}
+
+/// List an item's attachments, oldest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/items/{id}/attachments",
+    tag = "items",
+    params(("id" = String, Path, description = "Item ID")),
+    responses(
+        (status = 200, description = "Confirmed attachments", body = [Attachment]),
+        (status = 403, description = "Caller cannot read the item", body = ErrorResponse),
+        (status = 404, description = "Item not found, or attachments are not configured", body = ErrorResponse),
+    ),
+)]
+pub async fn list_attachments(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path(id): Path<String>,
+) -> AppResult<Json<Vec<Attachment>>> {
+    let attachments = attachments(&state)?;
+    let item = state.repo.get(&id).await?;
+    policy::authorize(
+        state.access.as_ref(),
+        state.collections.as_ref(),
+        &item,
+        claims.as_ref(),
+        Action::Read,
+    )
+    .await?;
+    Ok(Json(attachments.list(&id)?))
+}
+
+/// How long the URLs thumbnail requests are redirected to are valid
+const THUMBNAIL_URL_EXPIRY: chrono::Duration = chrono::Duration::minutes(15);
+
+/// Query parameters for fetching an attachment thumbnail
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ThumbnailQuery {
+    /// Width in pixels, one of `ATTACHMENT_THUMBNAIL_SIZES`; defaults to the
+    /// smallest rendered
+    pub size: Option<u32>,
+}
+
+/// Fetch a thumbnail of an image attachment
+///
+/// Redirects to a presigned URL of the thumbnail in the bucket, which the
+/// redirect may be cached for as long as it stays valid.
+#[utoipa::path(
+    get,
+    path = "/api/v1/items/{id}/attachments/{upload_id}/thumbnail",
+    tag = "items",
+    params(
+        ("id" = String, Path, description = "Item ID"),
+        ("upload_id" = String, Path, description = "Attachment ID"),
+        ThumbnailQuery,
+    ),
+    responses(
+        (status = 302, description = "Redirect to the thumbnail"),
+        (status = 403, description = "Caller cannot read the item", body = ErrorResponse),
+        (status = 404, description = "Item, attachment or thumbnail not found", body = ErrorResponse),
+    ),
+)]
+pub async fn get_attachment_thumbnail(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path((id, upload_id)): Path<(String, String)>,
+    Query(query): Query<ThumbnailQuery>,
+) -> AppResult<Response> {
+    let attachments = attachments(&state)?;
+    let item = state.repo.get(&id).await?;
+    policy::authorize(
+        state.access.as_ref(),
+        state.collections.as_ref(),
+        &item,
+        claims.as_ref(),
+        Action::Read,
+    )
+    .await?;
+
+    let not_found = || AppError::NotFound(format!("Attachment {upload_id} has no such thumbnail"));
+    let attachment = attachments
+        .list(&id)?
+        .into_iter()
+        .find(|attachment| attachment.id == upload_id)
+        .ok_or_else(|| AppError::NotFound(format!("Attachment {upload_id} not found")))?;
+    let size = query
+        .size
+        .or_else(|| attachment.thumbnails.iter().min().copied())
+        .ok_or_else(not_found)?;
+    let url = attachments
+        .thumbnail_url(&attachment, size, THUMBNAIL_URL_EXPIRY)
+        .ok_or_else(not_found)?;
+    // Cached for less than the URL is valid, so a cached redirect still works
+    let max_age = THUMBNAIL_URL_EXPIRY.num_seconds() * 2 / 3;
+    Ok((
+        StatusCode::FOUND,
+        [
+            (LOCATION, url),
+            (CACHE_CONTROL, format!("private, max-age={max_age}")),
+        ],
+    )
+        .into_response())
+}
+
+// ===== COMMENT HANDLERS =====
+
+/// Comment `comment_id` on item `id`, unless it was deleted
+async fn live_comment(state: &SharedState, id: &str, comment_id: &str) -> AppResult<Comment> {
+    match state.comments.get(id, comment_id).await {
+        Ok(comment) if comment.deleted_at.is_none() => Ok(comment),
+        Ok(_) | Err(DatabaseError::NotFound) => {
+            Err(AppError::NotFound(format!("Comment {comment_id} not found")))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Query parameters for listing comments
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct CommentsQuery {
+    /// Page size, at most 100
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+
+    #[serde(default)]
+    pub offset: usize,
+
+    /// Include deleted comments (the item's owner and administrators only)
+    #[serde(default)]
+    pub include_deleted: bool,
+}
+
+/// List an item's comments, oldest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/items/{id}/comments",
+    tag = "items",
+    params(("id" = String, Path, description = "Item ID"), CommentsQuery),
+    responses(
+        (status = 200, description = "A page of the item's comments", body = CommentList),
+        (status = 403, description = "Caller cannot read the item, or see its deleted comments", body = ErrorResponse),
+        (status = 404, description = "Item not found", body = ErrorResponse),
+        (status = 422, description = "Invalid limit", body = ErrorResponse),
+    ),
+)]
+pub async fn list_comments(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path(id): Path<String>,
+    Query(query): Query<CommentsQuery>,
+) -> AppResult<Json<CommentList>> {
+    let item = state.repo.get(&id).await?;
+    let action = if query.include_deleted {
+        Action::Share
+    } else {
+        Action::Read
+    };
+    policy::authorize(
+        state.access.as_ref(),
+        state.collections.as_ref(),
+        &item,
+        claims.as_ref(),
+        action,
+    )
+    .await?;
+    if !(1..=100).contains(&query.limit) {
+        return Err(AppError::ValidationError("limit: must be between 1 and 100".to_string()));
+    }
+    let (comments, total) = state
+        .comments
+        .list(&id, query.include_deleted, query.limit, query.offset)
+        .await?;
+    Ok(Json(CommentList {
+        comments,
+        total,
+        limit: query.limit,
+        offset: query.offset,
+    }))
+}
+
+/// Comment on an item
+///
+/// Anyone who can read the item can comment on it; the comment is
+/// attributed to the caller.
+#[utoipa::path(
+    post,
+    path = "/api/v1/items/{id}/comments",
+    tag = "items",
+    params(("id" = String, Path, description = "Item ID")),
+    request_body = CommentRequest,
+    responses(
+        (status = 201, description = "Comment created", body = Comment),
+        (status = 403, description = "Caller cannot read the item", body = ErrorResponse),
+        (status = 404, description = "Item not found", body = ErrorResponse),
+        (status = 422, description = "Validation error", body = ErrorResponse),
+    ),
+)]
+pub async fn create_comment(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path(id): Path<String>,
+    ValidatedJson(request): ValidatedJson<CommentRequest>,
+) -> AppResult<impl IntoResponse> {
+    let item = state.repo.get(&id).await?;
+    policy::authorize(
+        state.access.as_ref(),
+        state.collections.as_ref(),
+        &item,
+        claims.as_ref(),
+        Action::Read,
+    )
+    .await?;
+
+    let author = claims.map(|claims| claims.sub);
+    let comment = Comment::new(&id, author, request.body, state.clock.now());
+    let comment = state.comments.create(comment).await?;
+    state
+        .events
+        .publish_comment(CommentEvent::new(CommentEventType::Created, &comment));
+    Ok((StatusCode::CREATED, Json(comment)))
+}
+
+/// Get a comment on an item
+#[utoipa::path(
+    get,
+    path = "/api/v1/items/{id}/comments/{comment_id}",
+    tag = "items",
+    params(
+        ("id" = String, Path, description = "Item ID"),
+        ("comment_id" = String, Path, description = "Comment ID"),
+    ),
+    responses(
+        (status = 200, description = "The comment", body = Comment),
+        (status = 403, description = "Caller cannot read the item", body = ErrorResponse),
+        (status = 404, description = "Item or comment not found", body = ErrorResponse),
+    ),
+)]
+pub async fn get_comment(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path((id, comment_id)): Path<(String, String)>,
+) -> AppResult<Json<Comment>> {
+    let item = state.repo.get(&id).await?;
+    policy::authorize(
+        state.access.as_ref(),
+        state.collections.as_ref(),
+        &item,
+        claims.as_ref(),
+        Action::Read,
+    )
+    .await?;
+    Ok(Json(live_comment(&state, &id, &comment_id).await?))
+}
+
+/// Edit a comment; only its author can
+#[utoipa::path(
+    put,
+    path = "/api/v1/items/{id}/comments/{comment_id}",
+    tag = "items",
+    params(
+        ("id" = String, Path, description = "Item ID"),
+        ("comment_id" = String, Path, description = "Comment ID"),
+    ),
+    request_body = CommentRequest,
+    responses(
+        (status = 200, description = "Comment edited", body = Comment),
+        (status = 403, description = "Caller is not the comment's author", body = ErrorResponse),
+        (status = 404, description = "Item or comment not found", body = ErrorResponse),
+        (status = 422, description = "Validation error", body = ErrorResponse),
+    ),
+)]
+pub async fn update_comment(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path((id, comment_id)): Path<(String, String)>,
+    ValidatedJson(request): ValidatedJson<CommentRequest>,
+) -> AppResult<Json<Comment>> {
+    let item = state.repo.get(&id).await?;
+    policy::authorize(
+        state.access.as_ref(),
+        state.collections.as_ref(),
+        &item,
+        claims.as_ref(),
+        Action::Read,
+    )
+    .await?;
+    let comment = live_comment(&state, &id, &comment_id).await?;
+    let caller = claims.as_ref().map(|claims| &claims.sub);
+    if comment.author.is_none() || comment.author.as_ref() != caller {
+        return Err(AppError::Forbidden("Only the author can edit a comment".to_string()));
+    }
+
+    let comment = state
+        .comments
+        .update(&id, &comment_id, request.body)
+        .await?;
+    state
+        .events
+        .publish_comment(CommentEvent::new(CommentEventType::Updated, &comment));
+    Ok(Json(comment))
+}
+
+/// Delete a comment
+///
+/// Its author, the item's owner and administrators can delete a comment.
+/// Deleted comments are kept, and listed only when asked for.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/items/{id}/comments/{comment_id}",
+    tag = "items",
+    params(
+        ("id" = String, Path, description = "Item ID"),
+        ("comment_id" = String, Path, description = "Comment ID"),
+    ),
+    responses(
+        (status = 204, description = "Comment deleted"),
+        (status = 403, description = "Caller cannot delete the comment", body = ErrorResponse),
+        (status = 404, description = "Item or comment not found", body = ErrorResponse),
+    ),
+)]
+pub async fn delete_comment(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path((id, comment_id)): Path<(String, String)>,
+) -> AppResult<StatusCode> {
+    let item = state.repo.get(&id).await?;
+    policy::authorize(
+        state.access.as_ref(),
+        state.collections.as_ref(),
+        &item,
+        claims.as_ref(),
+        Action::Read,
+    )
+    .await?;
+    let comment = live_comment(&state, &id, &comment_id).await?;
+    let is_author = comment.author.is_some()
+        && comment.author.as_ref() == claims.as_ref().map(|claims| &claims.sub);
+    if !is_author {
+        policy::authorize(
+            state.access.as_ref(),
+            state.collections.as_ref(),
+            &item,
+            claims.as_ref(),
+            Action::Share,
+        )
+        .await?;
+    }
+
+    let comment = state.comments.delete(&id, &comment_id).await?;
+    state
+        .events
+        .publish_comment(CommentEvent::new(CommentEventType::Deleted, &comment));
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ===== COLLECTION HANDLERS =====
+
+/// Collection `id` of the current tenant
+async fn collection(state: &SharedState, id: &str) -> AppResult<ItemCollection> {
+    match state.collections.get(id).await {
+        Ok(collection) => Ok(collection),
+        Err(DatabaseError::NotFound) => {
+            Err(AppError::NotFound(format!("Collection {id} not found")))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Query parameters for listing collections
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct CollectionsQuery {
+    /// List the collections directly under this one instead of the root
+    /// collections
+    pub parent_id: Option<String>,
+}
+
+/// List root collections, or the collections directly under one
+///
+/// Root collections are those the caller owns; administrators see all of
+/// them.
+#[utoipa::path(
+    get,
+    path = "/api/v1/collections",
+    tag = "collections",
+    params(CollectionsQuery),
+    responses(
+        (status = 200, description = "Collections, by name", body = Vec<ItemCollection>),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Caller cannot read the parent collection", body = ErrorResponse),
+        (status = 404, description = "Parent collection not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn list_collections(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Query(query): Query<CollectionsQuery>,
+) -> AppResult<Json<Vec<ItemCollection>>> {
+    let claims = authenticated(claims)?;
+    let Some(parent_id) = query.parent_id else {
+        let roots = state.collections.children(None).await?;
+        return Ok(Json(
+            roots
+                .into_iter()
+                .filter(|root| claims.is_admin() || root.owner.as_ref() == Some(&claims.sub))
+                .collect(),
+        ));
+    };
+
+    let parent = collection(&state, &parent_id).await?;
+    policy::authorize_collection(state.access.as_ref(), &parent, Some(&claims), Action::Read)
+        .await?;
+    Ok(Json(state.collections.children(Some(&parent_id)).await?))
+}
+
+/// Create a collection
+///
+/// Nesting a collection needs write access to its parent; the caller owns
+/// the new collection.
+#[utoipa::path(
+    post,
+    path = "/api/v1/collections",
+    tag = "collections",
+    request_body = CreateCollectionRequest,
+    responses(
+        (status = 201, description = "Collection created", body = ItemCollection),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Caller cannot write to the parent collection", body = ErrorResponse),
+        (status = 404, description = "Parent collection not found", body = ErrorResponse),
+        (status = 409, description = "The parent has a collection of the same name", body = ErrorResponse),
+        (status = 422, description = "Validation error", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn create_collection(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    ValidatedJson(request): ValidatedJson<CreateCollectionRequest>,
+) -> AppResult<impl IntoResponse> {
+    let claims = authenticated(claims)?;
+    let parent = match &request.parent_id {
+        Some(parent_id) => {
+            let parent = collection(&state, parent_id).await?;
+            policy::authorize_collection(
+                state.access.as_ref(),
+                &parent,
+                Some(&claims),
+                Action::Write,
+            )
+            .await?;
+            Some(parent)
+        }
+        None => None,
+    };
+
+    let created =
+        ItemCollection::new(request.name, parent.as_ref(), Some(claims.sub), state.clock.now());
+    let created = state.collections.create(created).await?;
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+/// Get a collection
+#[utoipa::path(
+    get,
+    path = "/api/v1/collections/{id}",
+    tag = "collections",
+    params(("id" = String, Path, description = "Collection ID")),
+    responses(
+        (status = 200, description = "The collection", body = ItemCollection),
+        (status = 403, description = "Caller cannot read the collection", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse),
+    ),
+)]
+pub async fn get_collection(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path(id): Path<String>,
+) -> AppResult<Json<ItemCollection>> {
+    let found = collection(&state, &id).await?;
+    policy::authorize_collection(state.access.as_ref(), &found, claims.as_ref(), Action::Read)
+        .await?;
+    Ok(Json(found))
+}
+
+/// Delete an empty collection
+#[utoipa::path(
+    delete,
+    path = "/api/v1/collections/{id}",
+    tag = "collections",
+    params(("id" = String, Path, description = "Collection ID")),
+    responses(
+        (status = 204, description = "Collection deleted"),
+        (status = 403, description = "Caller cannot manage the collection", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse),
+        (status = 409, description = "The collection still holds collections or items", body = ErrorResponse),
+    ),
+)]
+pub async fn delete_collection(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path(id): Path<String>,
+) -> AppResult<StatusCode> {
+    let found = collection(&state, &id).await?;
+    policy::authorize_collection(state.access.as_ref(), &found, claims.as_ref(), Action::Share)
+        .await?;
+    state.collections.delete(&id).await?;
+    state.access.revoke_all(&id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Move a collection, with everything below it
+///
+/// Needs the right to manage the collection and write access to its new
+/// parent. Grants inherited from the old parent no longer apply. Only
+/// administrators can move a collection whose items need approval to change
+/// to where they would not.
+#[utoipa::path(
+    post,
+    path = "/api/v1/collections/{id}/move",
+    tag = "collections",
+    params(("id" = String, Path, description = "Collection ID")),
+    request_body = MoveCollectionRequest,
+    responses(
+        (status = 200, description = "Collection moved", body = ItemCollection),
+        (status = 403, description = "Caller cannot manage the collection, write to the new parent, or move it out of where changes need approval", body = ErrorResponse),
+        (status = 404, description = "Collection or new parent not found", body = ErrorResponse),
+        (status = 409, description = "The new parent is the collection itself or below it, or has a collection of the same name", body = ErrorResponse),
+    ),
+)]
+pub async fn move_collection(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path(id): Path<String>,
+    ValidatedJson(request): ValidatedJson<MoveCollectionRequest>,
+) -> AppResult<Json<ItemCollection>> {
+    let found = collection(&state, &id).await?;
+    policy::authorize_collection(state.access.as_ref(), &found, claims.as_ref(), Action::Share)
+        .await?;
+    let parent = match &request.parent_id {
+        Some(parent_id) => Some(collection(&state, parent_id).await?),
+        None => None,
+    };
+    if let Some(parent) = &parent {
+        policy::authorize_collection(state.access.as_ref(), parent, claims.as_ref(), Action::Write)
+            .await?;
+    }
+    // Otherwise moving it would escape the approvals its items need
+    if state.approvals.governs(&found)
+        && !state.approvals.governs_under(&found, parent.as_ref())
+        && !claims.as_ref().is_some_and(Claims::is_admin)
+    {
+        return Err(AppError::Forbidden(
+            "Only administrators can move collections out of where changes need approval"
+                .to_string(),
+        ));
+    }
+
+    let moved = state
+        .collections
+        .move_to(&id, request.parent_id.as_deref())
+        .await?;
+    Ok(Json(moved))
+}
+
+/// Query parameters for paging through a collection's contents
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct CollectionPageQuery {
+    /// Page size, at most 100
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+
+    #[serde(default)]
+    pub offset: usize,
+
+    /// Include items filed anywhere below the collection (items only)
+    #[serde(default)]
+    pub recursive: bool,
+}
+
+impl CollectionPageQuery {
+    fn check(&self) -> AppResult<()> {
+        if (1..=100).contains(&self.limit) {
+            Ok(())
+        } else {
+            Err(AppError::ValidationError("limit: must be between 1 and 100".to_string()))
+        }
+    }
+}
+
+/// List the collections below a collection, at any depth
+///
+/// Collections are listed shallowest first, and by name at each depth.
+#[utoipa::path(
+    get,
+    path = "/api/v1/collections/{id}/descendants",
+    tag = "collections",
+    params(("id" = String, Path, description = "Collection ID"), CollectionPageQuery),
+    responses(
+        (status = 200, description = "A page of the collection's subtree", body = CollectionList),
+        (status = 403, description = "Caller cannot read the collection", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse),
+        (status = 422, description = "Invalid limit", body = ErrorResponse),
+    ),
+)]
+pub async fn list_collection_descendants(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path(id): Path<String>,
+    Query(query): Query<CollectionPageQuery>,
+) -> AppResult<Json<CollectionList>> {
+    let found = collection(&state, &id).await?;
+    policy::authorize_collection(state.access.as_ref(), &found, claims.as_ref(), Action::Read)
+        .await?;
+    query.check()?;
+    let (collections, total) = state
+        .collections
+        .descendants(&id, query.limit, query.offset)
+        .await?;
+    Ok(Json(CollectionList {
+        collections,
+        total,
+        limit: query.limit,
+        offset: query.offset,
+    }))
+}
+
+/// List the items filed in a collection, in the order they were filed
+#[utoipa::path(
+    get,
+    path = "/api/v1/collections/{id}/items",
+    tag = "collections",
+    params(("id" = String, Path, description = "Collection ID"), CollectionPageQuery),
+    responses(
+        (status = 200, description = "A page of the collection's items", body = CollectionItems),
+        (status = 403, description = "Caller cannot read the collection", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse),
+        (status = 422, description = "Invalid limit", body = ErrorResponse),
+    ),
+)]
+pub async fn list_collection_items(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path(id): Path<String>,
+    Query(query): Query<CollectionPageQuery>,
+) -> AppResult<Json<CollectionItems>> {
+    let found = collection(&state, &id).await?;
+    policy::authorize_collection(state.access.as_ref(), &found, claims.as_ref(), Action::Read)
+        .await?;
+    query.check()?;
+    let (item_ids, total) = state
+        .collections
+        .items(&id, query.recursive, query.limit, query.offset)
+        .await?;
+
+    // Drafts are left out for those who cannot edit them
+    let mut items = Vec::with_capacity(item_ids.len());
+    for item_id in item_ids {
+        let item = match state.repo.get(&item_id).await {
+            Ok(item) => item,
+            Err(DatabaseError::NotFound) => continue,
+            Err(e) => return Err(e.into()),
+        };
+        if item.is_draft() {
+            let editable = policy::authorize(
+                state.access.as_ref(),
+                state.collections.as_ref(),
+                &item,
+                claims.as_ref(),
+                Action::Write,
+            )
+            .await;
+            match editable {
+                Ok(()) => {}
+                Err(AppError::Forbidden(_)) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        items.push(item);
+    }
+    Ok(Json(CollectionItems {
+        collection: found,
+        items,
+        total,
+        limit: query.limit,
+        offset: query.offset,
+    }))
+}
+
+/// Grant access to a collection and everything below it
+#[utoipa::path(
+    post,
+    path = "/api/v1/collections/{id}/permissions",
+    tag = "collections",
+    params(("id" = String, Path, description = "Collection ID")),
+    request_body = GrantPermissionRequest,
+    responses(
+        (status = 201, description = "Access granted", body = AccessGrant),
+        (status = 403, description = "Caller cannot manage the collection", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse),
+        (status = 422, description = "Validation error", body = ErrorResponse),
+    ),
+)]
+pub async fn grant_collection_permission(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path(id): Path<String>,
+    ValidatedJson(request): ValidatedJson<GrantPermissionRequest>,
+) -> AppResult<impl IntoResponse> {
+    let found = collection(&state, &id).await?;
+    policy::authorize_collection(state.access.as_ref(), &found, claims.as_ref(), Action::Share)
+        .await?;
+
+    let granted_by = claims.map(|claims| claims.sub);
+    let grant = state
+        .access
+        .grant(&id, request.grantee, request.permission, granted_by)
+        .await?;
+    Ok((StatusCode::CREATED, Json(grant)))
+}
+
+/// List access grants on a collection
+///
+/// Grants inherited from the collections above it are listed on those.
+#[utoipa::path(
+    get,
+    path = "/api/v1/collections/{id}/permissions",
+    tag = "collections",
+    params(("id" = String, Path, description = "Collection ID")),
+    responses(
+        (status = 200, description = "Access grants on the collection", body = [AccessGrant]),
+        (status = 403, description = "Caller cannot manage the collection", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse),
+    ),
+)]
+pub async fn list_collection_permissions(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path(id): Path<String>,
+) -> AppResult<Json<Vec<AccessGrant>>> {
+    let found = collection(&state, &id).await?;
+    policy::authorize_collection(state.access.as_ref(), &found, claims.as_ref(), Action::Share)
+        .await?;
+    Ok(Json(state.access.list_grants(&id).await?))
+}
+
+/// Revoke an access grant on a collection
+#[utoipa::path(
+    delete,
+    path = "/api/v1/collections/{id}/permissions/{grant_id}",
+    tag = "collections",
+    params(
+        ("id" = String, Path, description = "Collection ID"),
+        ("grant_id" = String, Path, description = "Access grant ID"),
+    ),
+    responses(
+        (status = 204, description = "Access revoked"),
+        (status = 403, description = "Caller cannot manage the collection", body = ErrorResponse),
+        (status = 404, description = "Collection or grant not found", body = ErrorResponse),
+    ),
+)]
+pub async fn revoke_collection_permission(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path((id, grant_id)): Path<(String, String)>,
+) -> AppResult<StatusCode> {
+    let found = collection(&state, &id).await?;
+    policy::authorize_collection(state.access.as_ref(), &found, claims.as_ref(), Action::Share)
+        .await?;
+    state.access.revoke(&id, &grant_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Get the collection an item is filed in
+#[utoipa::path(
+    get,
+    path = "/api/v1/items/{id}/collection",
+    tag = "items",
+    params(("id" = String, Path, description = "Item ID")),
+    responses(
+        (status = 200, description = "The item's collection, if any", body = CollectionPlacement),
+        (status = 403, description = "Caller cannot read the item", body = ErrorResponse),
+        (status = 404, description = "Item not found", body = ErrorResponse),
+    ),
+)]
+pub async fn get_item_collection(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path(id): Path<String>,
+) -> AppResult<Json<CollectionPlacement>> {
+    let item = state.repo.get(&id).await?;
+    policy::authorize(
+        state.access.as_ref(),
+        state.collections.as_ref(),
+        &item,
+        claims.as_ref(),
+        Action::Read,
+    )
+    .await?;
+    let collection_id = state.collections.item_collection(&id).await?;
+    Ok(Json(CollectionPlacement {
+        item_id: id,
+        collection_id,
+    }))
+}
+
+/// File an item in a collection, or take it out of its collection
+///
+/// Needs write access to the item and to the collection. The item takes on
+/// the grants of the collection and those above it, in place of its old
+/// collection's. Only administrators refile items that need approval.
+#[utoipa::path(
+    put,
+    path = "/api/v1/items/{id}/collection",
+    tag = "items",
+    params(("id" = String, Path, description = "Item ID")),
+    request_body = FileItemRequest,
+    responses(
+        (status = 200, description = "Item filed", body = CollectionPlacement),
+        (status = 403, description = "Caller cannot write to the item or the collection, or the item needs approval to change and the caller is not an administrator", body = ErrorResponse),
+        (status = 404, description = "Item or collection not found", body = ErrorResponse),
+    ),
+)]
+pub async fn file_item(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path(id): Path<String>,
+    ValidatedJson(request): ValidatedJson<FileItemRequest>,
+) -> AppResult<Json<CollectionPlacement>> {
+    let item = state.repo.get(&id).await?;
+    policy::authorize(
+        state.access.as_ref(),
+        state.collections.as_ref(),
+        &item,
+        claims.as_ref(),
+        Action::Write,
+    )
+    .await?;
+    // Otherwise filing it elsewhere would escape the approvals it needs
+    if !claims.as_ref().is_some_and(Claims::is_admin) && needs_approval(&state, &id).await? {
+        return Err(AppError::Forbidden(
+            "Only administrators can refile items that need approval to change".to_string(),
+        ));
+    }
+    if let Some(collection_id) = &request.collection_id {
+        let target = collection(&state, collection_id).await?;
+        policy::authorize_collection(
+            state.access.as_ref(),
+            &target,
+            claims.as_ref(),
+            Action::Write,
+        )
+        .await?;
+    }
+
+    state
+        .collections
+        .file_item(&id, request.collection_id.as_deref())
+        .await?;
+    Ok(Json(CollectionPlacement {
+        item_id: id,
+        collection_id: request.collection_id,
+    }))
+}
+
+// ===== SHARING HANDLERS =====
+
+fn shares(state: &SharedState) -> AppResult<&Arc<ShareLinks>> {
+    state
+        .shares
+        .as_ref()
+        .ok_or_else(|| AppError::NotFound("Sharing is not configured".to_string()))
+}
+
+/// Share an item through a signed, expiring link
+///
+/// Anyone holding the returned token can read the item at `url` without
+/// authenticating, until the share expires or is revoked. The token is only
+/// returned here.
+#[utoipa::path(
+    post,
+    path = "/api/v1/items/{id}/share",
+    tag = "items",
+    params(("id" = String, Path, description = "Item ID")),
+    request_body = CreateShareRequest,
+    responses(
+        (status = 201, description = "Share created", body = CreatedShare),
+        (status = 403, description = "Caller cannot manage access to the item", body = ErrorResponse),
+        (status = 404, description = "Item not found, or sharing is not configured", body = ErrorResponse),
+        (status = 422, description = "Validation error", body = ErrorResponse),
+    ),
+)]
+pub async fn create_share(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path(id): Path<String>,
+    Json(request): Json<CreateShareRequest>,
+) -> AppResult<impl IntoResponse> {
+    let shares = shares(&state)?;
+    let item = state.repo.get(&id).await?;
+    policy::authorize(
+        state.access.as_ref(),
+        state.collections.as_ref(),
+        &item,
+        claims.as_ref(),
+        Action::Share,
+    )
+    .await?;
+
+    let created = shares.create(&id, request, claims.map(|claims| claims.sub))?;
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+/// List an item's unexpired shares, newest first, with how often each was opened
+#[utoipa::path(
+    get,
+    path = "/api/v1/items/{id}/share",
+    tag = "items",
+    params(("id" = String, Path, description = "Item ID")),
+    responses(
+        (status = 200, description = "Shares of the item", body = [Share]),
+        (status = 403, description = "Caller cannot manage access to the item", body = ErrorResponse),
+        (status = 404, description = "Item not found, or sharing is not configured", body = ErrorResponse),
+    ),
+)]
+pub async fn list_shares(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path(id): Path<String>,
+) -> AppResult<Json<Vec<Share>>> {
+    let shares = shares(&state)?;
+    let item = state.repo.get(&id).await?;
+    policy::authorize(
+        state.access.as_ref(),
+        state.collections.as_ref(),
+        &item,
+        claims.as_ref(),
+        Action::Share,
+    )
+    .await?;
+    Ok(Json(shares.list(&id)?))
+}
+
+/// Revoke a share, so its token no longer opens the item
+#[utoipa::path(
+    delete,
+    path = "/api/v1/items/{id}/share/{share_id}",
+    tag = "items",
+    params(
+        ("id" = String, Path, description = "Item ID"),
+        ("share_id" = String, Path, description = "Share ID"),
+    ),
+    responses(
+        (status = 200, description = "Share revoked", body = Share),
+        (status = 403, description = "Caller cannot manage access to the item", body = ErrorResponse),
+        (status = 404, description = "Item or share not found", body = ErrorResponse),
+    ),
+)]
+pub async fn revoke_share(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path((id, share_id)): Path<(String, String)>,
+) -> AppResult<Json<Share>> {
+    let shares = shares(&state)?;
+    let item = state.repo.get(&id).await?;
+    policy::authorize(
+        state.access.as_ref(),
+        state.collections.as_ref(),
+        &item,
+        claims.as_ref(),
+        Action::Share,
+    )
+    .await?;
+    Ok(Json(shares.revoke(&id, &share_id)?))
+}
+
+/// Read a shared item, without authenticating
+///
+/// Every successful read counts as an access of the share. Attachments, when
+/// the share includes them, come with download URLs valid for a few minutes.
+#[utoipa::path(
+    get,
+    path = "/share/{token}",
+    tag = "sharing",
+    params(("token" = String, Path, description = "Share token")),
+    responses(
+        (status = 200, description = "The shared item", body = SharedItem),
+        (status = 404, description = "Share link is invalid, expired or revoked, or the item is gone", body = ErrorResponse),
+    ),
+)]
+pub async fn open_share(
+    State(state): State<SharedState>,
+    Path(token): Path<String>,
+) -> AppResult<Json<SharedItem>> {
+    let shares = shares(&state)?;
+    let share = shares.open(&token)?;
+    let tenant = share.tenant_id().map(str::to_string);
+    let item = with_optional_tenant(tenant, state.repo.get(&share.item_id)).await?;
+
+    let attachments = share
+        .include_attachments
+        .then(|| -> AppResult<Vec<SharedAttachment>> {
+            let Some(attachments) = &state.attachments else {
+                return Ok(Vec::new());
+            };
+            let expiry = shares.download_expiry(&share);
+            Ok(attachments
+                .list(&item.id)?
+                .into_iter()
+                .filter(Attachment::is_downloadable)
+                .map(|attachment| {
+                    let url = attachments.download_url(&attachment, expiry);
+                    SharedAttachment::new(attachment, url)
+                })
+                .collect())
+        })
+        .transpose()?;
+    Ok(Json(SharedItem {
+        item,
+        attachments,
+        expires_at: share.expires_at,
+    }))
+}
+
+// ===== NOTIFICATION INBOX HANDLERS =====
+
+fn inbox(state: &SharedState) -> AppResult<&Arc<dyn NotificationRepository>> {
+    state
+        .inbox
+        .as_ref()
+        .ok_or_else(|| AppError::NotFound("Notification inbox is not enabled".to_string()))
+}
+
+/// Claims of callers reaching their own records; anonymous callers have none
+fn authenticated(claims: Option<Claims>) -> AppResult<Claims> {
+    claims.ok_or_else(|| AppError::Unauthorized("Authentication required".to_string()))
+}
+
+/// Query parameters for listing notifications
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct NotificationsQuery {
+    /// Only list unread notifications
+    #[serde(default)]
+    pub unread: bool,
+
+    /// Page size, at most 100
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+
+    #[serde(default)]
+    pub offset: usize,
+}
+
+/// List the caller's notifications, newest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/notifications",
+    tag = "notifications",
+    params(NotificationsQuery),
+    responses(
+        (status = 200, description = "A page of the caller's notifications", body = NotificationList),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 404, description = "Notification inbox is not enabled", body = ErrorResponse),
+        (status = 422, description = "Invalid limit", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn list_notifications(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Query(query): Query<NotificationsQuery>,
+) -> AppResult<Json<NotificationList>> {
+    let inbox = inbox(&state)?;
+    let recipient = authenticated(claims)?.sub;
+    if !(1..=100).contains(&query.limit) {
+        return Err(AppError::ValidationError("limit: must be between 1 and 100".to_string()));
+    }
+    let (notifications, total) = inbox
+        .list(&recipient, query.unread, query.limit, query.offset)
+        .await?;
+    Ok(Json(NotificationList {
+        notifications,
+        total,
+        unread: inbox.unread_count(&recipient).await?,
+        limit: query.limit,
+        offset: query.offset,
+    }))
+}
+
+/// Count the caller's unread notifications
+#[utoipa::path(
+    get,
+    path = "/api/v1/notifications/unread-count",
+    tag = "notifications",
+    responses(
+        (status = 200, description = "Unread notifications", body = UnreadCount),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 404, description = "Notification inbox is not enabled", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn unread_notification_count(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+) -> AppResult<Json<UnreadCount>> {
+    let inbox = inbox(&state)?;
+    let recipient = authenticated(claims)?.sub;
+    Ok(Json(UnreadCount {
+        unread: inbox.unread_count(&recipient).await?,
+    }))
+}
+
+/// Mark one of the caller's notifications read
+#[utoipa::path(
+    post,
+    path = "/api/v1/notifications/{id}/read",
+    tag = "notifications",
+    params(("id" = String, Path, description = "Notification ID")),
+    responses(
+        (status = 200, description = "Notification marked read", body = Notification),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 404, description = "Notification not found, or the inbox is not enabled", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn mark_notification_read(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path(id): Path<String>,
+) -> AppResult<Json<Notification>> {
+    let inbox = inbox(&state)?;
+    let recipient = authenticated(claims)?.sub;
+    match inbox.mark_read(&recipient, &id).await {
+        Ok(notification) => Ok(Json(notification)),
+        Err(DatabaseError::NotFound) => {
+            Err(AppError::NotFound(format!("Notification {id} not found")))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Mark all of the caller's notifications read
+#[utoipa::path(
+    post,
+    path = "/api/v1/notifications/read",
+    tag = "notifications",
+    responses(
+        (status = 200, description = "How many notifications were marked read", body = MarkedRead),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 404, description = "Notification inbox is not enabled", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn mark_all_notifications_read(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+) -> AppResult<Json<MarkedRead>> {
+    let inbox = inbox(&state)?;
+    let recipient = authenticated(claims)?.sub;
+    Ok(Json(MarkedRead {
+        marked: inbox.mark_all_read(&recipient).await?,
+    }))
+}
+
+// ===== SAVED SEARCH HANDLERS =====
+
+/// The caller's saved search `id`; other principals' searches are not found
+async fn owned_search(state: &SharedState, claims: &Claims, id: &str) -> AppResult<SavedSearch> {
+    match state.saved_searches.get(id).await {
+        Ok(search) if search.owner == claims.sub => Ok(search),
+        Ok(_) | Err(DatabaseError::NotFound) => {
+            Err(AppError::NotFound(format!("Saved search {id} not found")))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Save a named search to run again
+///
+/// `filter` and `orderby` take the item list's OData `$filter` and
+/// `$orderby`. With `notify` or `webhook`, new items matching the search are
+/// announced in the caller's inbox or sent to that webhook.
+#[utoipa::path(
+    post,
+    path = "/api/v1/saved-searches",
+    tag = "saved-searches",
+    request_body = CreateSavedSearchRequest,
+    responses(
+        (status = 201, description = "Search saved", body = SavedSearch),
+        (status = 400, description = "Invalid filter or sort order", body = ErrorResponse),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Searching all items requires the admin role, or too many searches", body = ErrorResponse),
+        (status = 409, description = "The caller has a search of that name", body = ErrorResponse),
+        (status = 422, description = "Validation error", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn create_saved_search(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    ValidatedJson(request): ValidatedJson<CreateSavedSearchRequest>,
+) -> AppResult<impl IntoResponse> {
+    let claims = authenticated(claims)?;
+    list_filter(&state, Some(&claims), request.all)?;
+    if request.notify && state.inbox.is_none() {
+        return Err(AppError::ValidationError(
+            "notify: the notification inbox is not enabled".to_string(),
+        ));
+    }
+    if let Some(webhook) = &request.webhook {
+        if !state
+            .webhooks
+            .as_ref()
+            .is_some_and(|webhooks| webhooks.has_endpoint(webhook))
+        {
+            return Err(AppError::ValidationError(format!(
+                "webhook: no webhook {webhook} is configured"
+            )));
+        }
+    }
+
+    let search = SavedSearch::new(&claims.sub, request, state.clock.now());
+    state
+        .custom_fields
+        .check_conditions(&search.conditions()?)?;
+    search.order()?;
+    let max = state.config.saved_searches.max_per_principal;
+    if state.saved_searches.list(&claims.sub).await?.len() >= max {
+        return Err(AppError::Forbidden(format!("Saved search limit of {max} reached")));
+    }
+    let search = state.saved_searches.create(search).await?;
+    Ok((StatusCode::CREATED, Json(search)))
+}
+
+/// List the caller's saved searches, by name
+#[utoipa::path(
+    get,
+    path = "/api/v1/saved-searches",
+    tag = "saved-searches",
+    responses(
+        (status = 200, description = "The caller's saved searches", body = [SavedSearch]),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn list_saved_searches(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+) -> AppResult<Json<Vec<SavedSearch>>> {
+    let claims = authenticated(claims)?;
+    Ok(Json(state.saved_searches.list(&claims.sub).await?))
+}
+
+/// Get one of the caller's saved searches
+#[utoipa::path(
+    get,
+    path = "/api/v1/saved-searches/{id}",
+    tag = "saved-searches",
+    params(("id" = String, Path, description = "Saved search ID")),
+    responses(
+        (status = 200, description = "The saved search", body = SavedSearch),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 404, description = "Saved search not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn get_saved_search(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path(id): Path<String>,
+) -> AppResult<Json<SavedSearch>> {
+    let claims = authenticated(claims)?;
+    Ok(Json(owned_search(&state, &claims, &id).await?))
+}
+
+/// Delete one of the caller's saved searches, ending its subscription
+#[utoipa::path(
+    delete,
+    path = "/api/v1/saved-searches/{id}",
+    tag = "saved-searches",
+    params(("id" = String, Path, description = "Saved search ID")),
+    responses(
+        (status = 204, description = "Saved search deleted"),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 404, description = "Saved search not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn delete_saved_search(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path(id): Path<String>,
+) -> AppResult<StatusCode> {
+    let claims = authenticated(claims)?;
+    owned_search(&state, &claims, &id).await?;
+    state.saved_searches.delete(&id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Query parameters for a saved search's results
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct SavedSearchResultsQuery {
+    /// Page size, at most 100
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+
+    /// Items to skip, at most 10000
+    #[serde(default)]
+    pub offset: usize,
+}
+
+/// Run one of the caller's saved searches
+///
+/// The search runs with the caller's current access, so a search of all
+/// items is refused once the caller is no longer an administrator.
+#[utoipa::path(
+    get,
+    path = "/api/v1/saved-searches/{id}/results",
+    tag = "saved-searches",
+    params(
+        ("id" = String, Path, description = "Saved search ID"),
+        SavedSearchResultsQuery,
+    ),
+    responses(
+        (status = 200, description = "A page of the search's results", body = SavedSearchResults),
+        (status = 400, description = "Invalid page, or the search names a field that no longer exists", body = ErrorResponse),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Searching all items requires the admin role", body = ErrorResponse),
+        (status = 404, description = "Saved search not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn saved_search_results(
+    State(state): State<SharedState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    Path(id): Path<String>,
+    Query(query): Query<SavedSearchResultsQuery>,
+) -> AppResult<Json<SavedSearchResults>> {
+    let claims = authenticated(claims)?;
+    let search = owned_search(&state, &claims, &id).await?;
+    let filter =
+        list_filter(&state, Some(&claims), search.all)?.with_conditions(search.conditions()?);
+    state.custom_fields.check_conditions(&filter.conditions)?;
+    let page = Page::offset(query.limit, query.offset)?.ordered_by(search.order()?)?;
+    let (items, total) = state.repo.list_with_total(&filter, &page).await?;
+    Ok(Json(SavedSearchResults {
+        search,
+        items,
+        total,
+        limit: page.limit(),
+        offset: page.skipped(),
+    }))
+}
+
+// ===== WEBHOOK HANDLERS =====
+
+fn webhooks(state: &SharedState) -> AppResult<&Arc<WebhookPublisher>> {
+    state
+        .webhooks
+        .as_ref()
+        .ok_or_else(|| AppError::NotFound("Webhooks are not configured".to_string()))
+}
+
+/// Recent deliveries to a webhook, newest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/webhooks/{id}/deliveries",
+    tag = "webhooks",
+    params(("id" = String, Path, description = "Webhook ID")),
+    responses(
+        (status = 200, description = "Recent deliveries", body = Vec<WebhookDelivery>),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 404, description = "Webhook not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn list_webhook_deliveries(
+    State(state): State<SharedState>,
+    _admin: AdminUser,
+    Path(id): Path<String>,
+) -> AppResult<Json<Vec<WebhookDelivery>>> {
+    let deliveries = webhooks(&state)?.deliveries(&id)?;
+    Ok(Json(deliveries))
+}
+
+/// Send a recent delivery to its webhook again
+///
+/// The event is sent under its original `webhook-id` with a new timestamp and
+/// signature, so consumers that already processed it can drop it. Answers
+/// with the delivery after the attempt, whether or not the endpoint accepted
+/// it.
+#[utoipa::path(
+    post,
+    path = "/api/v1/webhooks/{id}/deliveries/{delivery}/redeliver",
+    tag = "webhooks",
+    params(
+        ("id" = String, Path, description = "Webhook ID"),
+        ("delivery" = String, Path, description = "Delivery ID"),
+    ),
+    responses(
+        (status = 200, description = "Delivery after the attempt", body = WebhookDelivery),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 404, description = "Webhook or delivery not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn redeliver_webhook(
+    State(state): State<SharedState>,
+    _admin: AdminUser,
+    Path((id, delivery)): Path<(String, String)>,
+) -> AppResult<Json<WebhookDelivery>> {
+    let delivery = webhooks(&state)?.redeliver(&id, &delivery).await?;
+    Ok(Json(delivery))
+}
+
+// ===== ADMIN HANDLERS =====
+
+/// Header carrying the backup ID, which is also the restore confirmation token
+pub const BACKUP_ID_HEADER: &str = "x-backup-id";
+
+/// Stream a gzip-compressed export of all repositories
+#[utoipa::path(
+    post,
+    path = "/admin/v1/backup",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Backup archive (gzip-compressed NDJSON)", content_type = "application/gzip",
+            headers(("x-backup-id" = String, description = "Backup ID, required to restore this archive"))),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn create_backup(
+    State(state): State<SharedState>,
+    AdminUser(claims): AdminUser,
+) -> AppResult<impl IntoResponse> {
+    let (backup_id, archive) = backup::export(state.repo.clone(), state.access.clone());
+    tracing::info!(%backup_id, requested_by = %claims.sub, "Backup started");
+
+    let disposition = format!("attachment; filename=\"ferrous-backup-{backup_id}.ndjson.gz\"");
+    Ok((
+        [
+            (CONTENT_TYPE, "application/gzip".to_string()),
+            (CONTENT_DISPOSITION, disposition),
+            (BACKUP_ID_HEADER.parse().expect("valid header name"), backup_id),
+        ],
+        Body::from_stream(ReaderStream::new(archive)),
+    ))
+}
+
+/// Query parameters for restoring a backup
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct RestoreQuery {
+    /// Must equal the ID of the backup being restored
+    pub confirm: Option<String>,
+}
+
+/// Restore a backup produced by `POST /admin/v1/backup`
+#[utoipa::path(
+    post,
+    path = "/admin/v1/restore",
+    tag = "admin",
+    params(RestoreQuery),
+    request_body(content = Vec<u8>, description = "Backup archive", content_type = "application/gzip"),
+    responses(
+        (status = 200, description = "Backup restored", body = RestoreReport),
+        (status = 400, description = "Missing or mismatched confirmation, or invalid archive", body = ErrorResponse),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn restore_backup(
+    State(state): State<SharedState>,
+    AdminUser(claims): AdminUser,
+    Query(query): Query<RestoreQuery>,
+    body: Body,
+) -> AppResult<impl IntoResponse> {
+    let confirm = query.confirm.ok_or_else(|| {
+        AppError::BadRequest(
+            "Restoring replaces items with the same IDs; pass ?confirm=<backup ID> to proceed"
+                .to_string(),
+        )
+    })?;
+    tracing::warn!(backup_id = %confirm, requested_by = %claims.sub, "Restore started");
+
+    let archive = StreamReader::new(body.into_data_stream().map_err(std::io::Error::other));
+    let report =
+        backup::restore(archive, state.repo.as_ref(), state.access.as_ref(), &confirm).await?;
+    Ok(Json(report))
+}
+
+/// Anonymize or erase all data associated with a principal
+#[utoipa::path(
+    post,
+    path = "/admin/v1/privacy/erasures",
+    tag = "admin",
+    request_body = ErasureRequest,
+    responses(
+        (status = 200, description = "Data erased; the report is signed", body = SignedErasureReport),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 422, description = "Validation error", body = ErrorResponse),
+        (status = 503, description = "Erasure signing key not configured", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn erase_principal_data(
+    State(state): State<SharedState>,
+    AdminUser(claims): AdminUser,
+    ValidatedJson(request): ValidatedJson<ErasureRequest>,
+) -> AppResult<impl IntoResponse> {
+    // Refuse before touching data so every erasure has a signed report
+    let signer = state.erasure_signer.as_ref().ok_or_else(|| {
+        AppError::ServiceUnavailable("Data erasure requires ERASURE_SIGNING_KEY".to_string())
+    })?;
+
+    let report = privacy::erase_principal(&state, &request, &claims.sub).await?;
+    let signed = signer.sign(report).map_err(AppError::InternalServerError)?;
+    Ok(Json(signed))
+}
+
+/// Provision a tenant and issue its API key
+#[utoipa::path(
+    post,
+    path = "/admin/v1/tenants",
+    tag = "admin",
+    request_body = CreateTenantRequest,
+    responses(
+        (status = 201, description = "Tenant provisioned; the API key is not shown again", body = ProvisionedTenant),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 409, description = "Tenant already exists", body = ErrorResponse),
+        (status = 422, description = "Validation error", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn create_tenant(
+    State(state): State<SharedState>,
+    AdminUser(claims): AdminUser,
+    ValidatedJson(request): ValidatedJson<CreateTenantRequest>,
+) -> AppResult<impl IntoResponse> {
+    let api_key = generate_api_key(&request.id);
+    let now = Utc::now();
+    let tenant = Tenant {
+        id: request.id,
+        name: request.name.trim().to_string(),
+        status: TenantStatus::Active,
+        quotas: request.quotas,
+        api_key_hash: hash_api_key(&api_key),
+        created_at: now,
+        updated_at: now,
+    };
+
+    let tenants = state.tenants.repository();
+    let tenant = tenants.create(tenant).await?;
+    if let Err(e) = state.repo.provision_tenant(&tenant.id).await {
+        // Leave no record of a tenant whose storage does not exist
+        if let Err(cleanup) = tenants.delete(&tenant.id).await {
+            tracing::warn!(tenant = %tenant.id, "Failed to remove unprovisioned tenant: {}", cleanup);
+        }
+        return Err(e.into());
+    }
+    state.tenants.invalidate(&tenant.id);
+
+    tracing::info!(tenant = %tenant.id, provisioned_by = %claims.sub, "Tenant provisioned");
+    Ok((StatusCode::CREATED, Json(ProvisionedTenant { tenant, api_key })))
+}
+
+/// List provisioned tenants
+#[utoipa::path(
+    get,
+    path = "/admin/v1/tenants",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Tenants ordered by ID", body = Vec<Tenant>),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn list_tenants(
+    State(state): State<SharedState>,
+    _admin: AdminUser,
+) -> AppResult<impl IntoResponse> {
+    Ok(Json(state.tenants.repository().list().await?))
+}
+
+/// Get a tenant
+#[utoipa::path(
+    get,
+    path = "/admin/v1/tenants/{id}",
+    tag = "admin",
+    params(
+        ("id" = String, Path, description = "Tenant ID")
+    ),
+    responses(
+        (status = 200, description = "Tenant retrieved successfully", body = Tenant),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 404, description = "Tenant not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn get_tenant(
+    State(state): State<SharedState>,
+    _admin: AdminUser,
+    Path(id): Path<String>,
+) -> AppResult<impl IntoResponse> {
+    Ok(Json(state.tenants.repository().get(&id).await?))
+}
+
+/// Update a tenant's name, status, or quotas
+#[utoipa::path(
+    put,
+    path = "/admin/v1/tenants/{id}",
+    tag = "admin",
+    params(
+        ("id" = String, Path, description = "Tenant ID")
+    ),
+    request_body = UpdateTenantRequest,
+    responses(
+        (status = 200, description = "Tenant updated successfully", body = Tenant),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 404, description = "Tenant not found", body = ErrorResponse),
+        (status = 422, description = "Validation error", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn update_tenant(
+    State(state): State<SharedState>,
+    AdminUser(claims): AdminUser,
+    Path(id): Path<String>,
+    ValidatedJson(mut request): ValidatedJson<UpdateTenantRequest>,
+) -> AppResult<impl IntoResponse> {
+    request.name = request.name.map(|name| name.trim().to_string());
+    let tenant = state.tenants.repository().update(&id, request).await?;
+    state.tenants.invalidate(&id);
+
+    tracing::info!(tenant = %id, status = ?tenant.status, updated_by = %claims.sub, "Tenant updated");
+    Ok(Json(tenant))
+}
+
+/// Deprovision a tenant, deleting all of its items
+#[utoipa::path(
+    delete,
+    path = "/admin/v1/tenants/{id}",
+    tag = "admin",
+    params(
+        ("id" = String, Path, description = "Tenant ID")
+    ),
+    responses(
+        (status = 204, description = "Tenant and its data deleted"),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 404, description = "Tenant not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn delete_tenant(
+    State(state): State<SharedState>,
+    AdminUser(claims): AdminUser,
+    Path(id): Path<String>,
+) -> AppResult<impl IntoResponse> {
+    let tenants = state.tenants.repository();
+    tenants.get(&id).await?;
+
+    // Suspend first so no new items are written while the data is dropped
+    let suspend = UpdateTenantRequest {
+        status: Some(TenantStatus::Suspended),
+        ..UpdateTenantRequest::default()
+    };
+    tenants.update(&id, suspend).await?;
+    state.tenants.invalidate(&id);
+
+    let dropped = state.repo.drop_tenant(&id).await?;
+    for item_id in &dropped {
+        state.access.revoke_all(item_id).await?;
+        state.collections.forget_item(item_id).await?;
+        if let Some(attachments) = &state.attachments {
+            attachments.forget_item(item_id)?;
+        }
+    }
+    tenants.delete(&id).await?;
+    state.tenants.invalidate(&id);
+
+    tracing::warn!(tenant = %id, items = dropped.len(), deleted_by = %claims.sub, "Tenant deprovisioned");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Effective configuration of this instance, with secrets redacted
+#[utoipa::path(
+    get,
+    path = "/admin/v1/config",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Effective configuration", body = serde_json::Value),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn get_config(State(state): State<SharedState>, _admin: AdminUser) -> impl IntoResponse {
+    Json(state.config.redacted())
+}
+
+fn log_filter(state: &SharedState) -> AppResult<&Arc<LogFilter>> {
+    state.log_filter.as_ref().ok_or_else(|| {
+        AppError::ServiceUnavailable("The log filter cannot be changed at runtime".to_string())
+    })
+}
+
+/// Log filter in effect on this instance
+#[utoipa::path(
+    get,
+    path = "/admin/v1/log-level",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Current log filter", body = LogFilterStatus),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 503, description = "Log filter is not adjustable", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn get_log_level(
+    State(state): State<SharedState>,
+    _admin: AdminUser,
+) -> AppResult<Json<LogFilterStatus>> {
+    Ok(Json(log_filter(&state)?.status()))
+}
+
+/// Replace the log filter until a TTL elapses, without restarting
+#[utoipa::path(
+    put,
+    path = "/admin/v1/log-level",
+    tag = "admin",
+    request_body = SetLogFilterRequest,
+    responses(
+        (status = 200, description = "Log filter changed", body = LogFilterStatus),
+        (status = 400, description = "Invalid filter or TTL", body = ErrorResponse),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 503, description = "Log filter is not adjustable", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn set_log_level(
+    State(state): State<SharedState>,
+    AdminUser(claims): AdminUser,
+    Json(request): Json<SetLogFilterRequest>,
+) -> AppResult<Json<LogFilterStatus>> {
+    let ttl = request.ttl_seconds.unwrap_or(DEFAULT_FILTER_TTL_SECONDS);
+    if ttl == 0 || ttl > MAX_FILTER_TTL_SECONDS {
+        return Err(AppError::BadRequest(format!(
+            "ttl_seconds must be between 1 and {MAX_FILTER_TTL_SECONDS}"
+        )));
+    }
+
+    let status = log_filter(&state)?
+        .set(&request.filter, Duration::from_secs(ttl))
+        .map_err(AppError::BadRequest)?;
+    tracing::warn!(filter = %status.filter, ttl_seconds = ttl, changed_by = %claims.sub, "Log filter changed at runtime");
+    Ok(Json(status))
+}
+
+/// Restore the log filter the instance was started with
+#[utoipa::path(
+    delete,
+    path = "/admin/v1/log-level",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Log filter restored", body = LogFilterStatus),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 503, description = "Log filter is not adjustable", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn reset_log_level(
+    State(state): State<SharedState>,
+    _admin: AdminUser,
+) -> AppResult<Json<LogFilterStatus>> {
+    Ok(Json(log_filter(&state)?.reset()))
+}
+
+fn fault_injector(state: &SharedState) -> AppResult<&Arc<FaultInjector>> {
+    state
+        .chaos
+        .as_ref()
+        .ok_or_else(|| AppError::NotFound("Fault injection is disabled".to_string()))
+}
+
+/// Fault injection rules in effect on this instance
+#[utoipa::path(
+    get,
+    path = "/admin/v1/chaos",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Fault rules", body = Vec<FaultRule>),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 404, description = "Fault injection is disabled", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn get_chaos_rules(
+    State(state): State<SharedState>,
+    _admin: AdminUser,
+) -> AppResult<Json<Vec<FaultRule>>> {
+    Ok(Json(fault_injector(&state)?.rules()))
+}
+
+/// Replace the fault injection rules of this instance
+#[utoipa::path(
+    put,
+    path = "/admin/v1/chaos",
+    tag = "admin",
+    request_body = Vec<FaultRule>,
+    responses(
+        (status = 200, description = "Fault rules replaced", body = Vec<FaultRule>),
+        (status = 400, description = "Invalid rule", body = ErrorResponse),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 404, description = "Fault injection is disabled", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn set_chaos_rules(
+    State(state): State<SharedState>,
+    AdminUser(claims): AdminUser,
+    Json(rules): Json<Vec<FaultRule>>,
+) -> AppResult<Json<Vec<FaultRule>>> {
+    let injector = fault_injector(&state)?;
+    injector.set_rules(rules)?;
+    let rules = injector.rules();
+    tracing::warn!(rules = rules.len(), changed_by = %claims.sub, "Fault injection rules changed");
+    Ok(Json(rules))
+}
+
+/// Stop injecting faults by rule on this instance
+#[utoipa::path(
+    delete,
+    path = "/admin/v1/chaos",
+    tag = "admin",
+    responses(
+        (status = 204, description = "Fault rules removed"),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 404, description = "Fault injection is disabled", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn clear_chaos_rules(
+    State(state): State<SharedState>,
+    _admin: AdminUser,
+) -> AppResult<StatusCode> {
+    fault_injector(&state)?.set_rules(Vec::new())?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn mock_registry(state: &SharedState) -> AppResult<&Arc<MockRegistry>> {
+    state
+        .mocks
+        .as_ref()
+        .ok_or_else(|| AppError::NotFound("Mock endpoints are disabled".to_string()))
+}
+
+/// Mock endpoints registered on this instance
+#[utoipa::path(
+    get,
+    path = "/admin/v1/mocks",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Mock endpoints", body = Vec<MockEndpoint>),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 404, description = "Mock endpoints are disabled", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn list_mocks(
+    State(state): State<SharedState>,
+    _admin: AdminUser,
+) -> AppResult<Json<Vec<MockEndpoint>>> {
+    Ok(Json(mock_registry(&state)?.endpoints()))
+}
+
+/// Register a mock endpoint on this instance
+///
+/// The mock answers matching requests with its canned response from now on,
+/// replacing any mock for the same method and path.
+#[utoipa::path(
+    post,
+    path = "/admin/v1/mocks",
+    tag = "admin",
+    request_body = MockEndpointRequest,
+    responses(
+        (status = 201, description = "Mock endpoint registered", body = MockEndpoint),
+        (status = 400, description = "Invalid mock endpoint", body = ErrorResponse),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 404, description = "Mock endpoints are disabled", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn create_mock(
+    State(state): State<SharedState>,
+    AdminUser(claims): AdminUser,
+    Json(request): Json<MockEndpointRequest>,
+) -> AppResult<(StatusCode, Json<MockEndpoint>)> {
+    let mock = mock_registry(&state)?.register(request)?;
+    tracing::info!(
+        method = %mock.method,
+        path = %mock.path,
+        changed_by = %claims.sub,
+        "Mock endpoint registered"
+    );
+    Ok((StatusCode::CREATED, Json(mock)))
+}
+
+/// Remove a mock endpoint from this instance
+#[utoipa::path(
+    delete,
+    path = "/admin/v1/mocks/{id}",
+    tag = "admin",
+    params(("id" = String, Path, description = "Mock endpoint ID")),
+    responses(
+        (status = 204, description = "Mock endpoint removed"),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 404, description = "Mock endpoint not found, or mocks are disabled", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn delete_mock(
+    State(state): State<SharedState>,
+    _admin: AdminUser,
+    Path(id): Path<String>,
+) -> AppResult<StatusCode> {
+    if !mock_registry(&state)?.remove(&id) {
+        return Err(AppError::NotFound(format!("Mock endpoint {id} not found")));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Remove every mock endpoint from this instance
+#[utoipa::path(
+    delete,
+    path = "/admin/v1/mocks",
+    tag = "admin",
+    responses(
+        (status = 204, description = "Mock endpoints removed"),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 404, description = "Mock endpoints are disabled", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn clear_mocks(
+    State(state): State<SharedState>,
+    _admin: AdminUser,
+) -> AppResult<StatusCode> {
+    mock_registry(&state)?.clear();
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Query parameters for listing dead letters
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct DeadLettersQuery {
+    /// Only list work from this source
+    pub source: Option<DeadLetterSource>,
+}
+
+/// Work that exhausted its retries, oldest first
+#[utoipa::path(
+    get,
+    path = "/admin/v1/dead-letters",
+    tag = "admin",
+    params(DeadLettersQuery),
+    responses(
+        (status = 200, description = "Dead letters, without payloads", body = Vec<DeadLetterSummary>),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn list_dead_letters(
+    State(state): State<SharedState>,
+    _admin: AdminUser,
+    Query(query): Query<DeadLettersQuery>,
+) -> AppResult<Json<Vec<DeadLetterSummary>>> {
+    Ok(Json(state.dead_letters.list(query.source)?))
+}
+
+/// A dead letter with its payload
+#[utoipa::path(
+    get,
+    path = "/admin/v1/dead-letters/{id}",
+    tag = "admin",
+    params(("id" = String, Path, description = "Dead letter ID")),
+    responses(
+        (status = 200, description = "Dead letter", body = DeadLetter),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 404, description = "Dead letter not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn get_dead_letter(
+    State(state): State<SharedState>,
+    _admin: AdminUser,
+    Path(id): Path<String>,
+) -> AppResult<Json<DeadLetter>> {
+    state
+        .dead_letters
+        .get(&id)?
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound(format!("Dead letter {id} not found")))
+}
+
+/// Try a dead letter's work again
+///
+/// The letter leaves the queue if the work succeeds; otherwise it stays, with
+/// the new error recorded.
+#[utoipa::path(
+    post,
+    path = "/admin/v1/dead-letters/{id}/requeue",
+    tag = "admin",
+    params(("id" = String, Path, description = "Dead letter ID")),
+    responses(
+        (status = 204, description = "Work succeeded and the letter was removed"),
+        (status = 400, description = "Work from this source cannot be retried here", body = ErrorResponse),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 404, description = "Dead letter not found", body = ErrorResponse),
+        (status = 503, description = "Work failed again", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn requeue_dead_letter(
+    State(state): State<SharedState>,
+    _admin: AdminUser,
+    Path(id): Path<String>,
+) -> AppResult<StatusCode> {
+    state.dead_letters.requeue(&id).await.map_err(|e| match e {
+        RequeueError::NotFound(_) => AppError::NotFound(e.to_string()),
+        RequeueError::Unsupported(_) => AppError::BadRequest(e.to_string()),
+        RequeueError::Failed(_) => AppError::ServiceUnavailable(e.to_string()),
+        RequeueError::Database(e) => e.into(),
+    })?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Drop a dead letter without retrying it
+#[utoipa::path(
+    delete,
+    path = "/admin/v1/dead-letters/{id}",
+    tag = "admin",
+    params(("id" = String, Path, description = "Dead letter ID")),
+    responses(
+        (status = 204, description = "Dead letter removed"),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 404, description = "Dead letter not found", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn discard_dead_letter(
+    State(state): State<SharedState>,
+    _admin: AdminUser,
+    Path(id): Path<String>,
+) -> AppResult<StatusCode> {
+    state
+        .dead_letters
+        .discard(&id)?
+        .ok_or_else(|| AppError::NotFound(format!("Dead letter {id} not found")))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Send historical outbox events to a webhook or the event broker again
+///
+/// Events are replayed in sequence order under their original IDs, up to
+/// `limit` per request; continue with `after_sequence` set to the reported
+/// `last_sequence` while `has_more` is true. Only events retention has not
+/// purged can be replayed.
+#[utoipa::path(
+    post,
+    path = "/admin/v1/events/replay",
+    tag = "admin",
+    request_body = ReplayRequest,
+    responses(
+        (status = 200, description = "Events replayed; a refused event stops the replay", body = ReplayReport),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 404, description = "Webhook or event broker not configured", body = ErrorResponse),
+        (status = 422, description = "Validation error", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn replay_events(
+    State(state): State<SharedState>,
+    AdminUser(claims): AdminUser,
+    ValidatedJson(request): ValidatedJson<ReplayRequest>,
+) -> AppResult<Json<ReplayReport>> {
+    let sink = match request.sink {
+        ReplaySinkKind::Webhook => {
+            let id = request.webhook.as_deref().ok_or_else(|| {
+                AppError::ValidationError("webhook: required for the webhook sink".to_string())
+            })?;
+            let webhooks = state
+                .webhooks
+                .as_deref()
+                .filter(|webhooks| webhooks.has_endpoint(id))
+                .ok_or_else(|| AppError::NotFound(format!("Webhook {id} not found")))?;
+            ReplaySink::Webhook(webhooks, id)
+        }
+        ReplaySinkKind::Broker => ReplaySink::Broker(
+            state
+                .publisher
+                .as_deref()
+                .ok_or_else(|| AppError::NotFound("No event broker is configured".to_string()))?,
+        ),
+    };
+    tracing::info!(
+        requested_by = %claims.sub,
+        after_sequence = request.after_sequence,
+        "Event replay started"
+    );
+    let report = replay::replay(state.repo.as_ref(), &sink, &request).await?;
+    Ok(Json(report))
+}
+
+/// Run a read-only query over items, for investigations without database
+/// access
+///
+/// Only `SELECT` from `items` is accepted, with `WHERE` conditions joined by
+/// `AND`, `ORDER BY` and `LIMIT`; statements are translated into repository
+/// queries rather than sent to the database. Values are bound from `params`.
+/// Results stop at `ADMIN_QUERY_MAX_ROWS` rows, queries are cancelled after
+/// `ADMIN_QUERY_TIMEOUT_MS`, and every query is logged with who ran it.
+#[utoipa::path(
+    post,
+    path = "/admin/v1/query",
+    tag = "admin",
+    request_body = QueryRequest,
+    responses(
+        (status = 200, description = "Query rows", body = QueryResult),
+        (status = 400, description = "Statement not allowed or invalid", body = ErrorResponse),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 404, description = "Admin queries are disabled", body = ErrorResponse),
+        (status = 504, description = "Query ran past its time limit", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn run_admin_query(
+    State(state): State<SharedState>,
+    AdminUser(claims): AdminUser,
+    ValidatedJson(request): ValidatedJson<QueryRequest>,
+) -> AppResult<Json<QueryResult>> {
+    let config = &state.config.admin_query;
+    if !config.enabled {
+        return Err(AppError::NotFound("Admin queries are disabled".to_string()));
+    }
+    let query = sql::parse(&request.sql, &request.params).inspect_err(|e| {
+        tracing::warn!(requested_by = %claims.sub, sql = %request.sql, "Admin query refused: {}", e);
+    })?;
+
+    let started = std::time::Instant::now();
+    let timeout = std::time::Duration::from_millis(config.timeout_ms);
+    let result =
+        match tokio::time::timeout(timeout, sql::run(state.repo.as_ref(), &query, config.max_rows))
+            .await
+        {
+            Ok(result) => result,
+            Err(_) => Err(DatabaseError::DeadlineExceeded),
+        };
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+    match &result {
+        Ok(rows) => tracing::info!(
+            requested_by = %claims.sub,
+            sql = %request.sql,
+            rows = rows.row_count,
+            truncated = rows.truncated,
+            elapsed_ms,
+            "Admin query ran"
+        ),
+        Err(e) => tracing::warn!(
+            requested_by = %claims.sub,
+            sql = %request.sql,
+            elapsed_ms,
+            "Admin query failed: {}",
+            e
+        ),
+    }
+    Ok(Json(result?))
+}
+
+fn dual_write(state: &SharedState) -> AppResult<&Arc<DualWriteRepository>> {
+    state
+        .dual_write
+        .as_ref()
+        .ok_or_else(|| AppError::NotFound("Dual writes are disabled".to_string()))
+}
+
+/// Writes the secondary backend has not applied
+#[utoipa::path(
+    get,
+    path = "/admin/v1/dual-write",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Dual write divergences", body = DualWriteReport),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 404, description = "Dual writes are disabled", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn get_dual_write_report(
+    State(state): State<SharedState>,
+    _admin: AdminUser,
+) -> AppResult<Json<DualWriteReport>> {
+    Ok(Json(dual_write(&state)?.report()?))
+}
+
+/// Copy the divergent items from the primary backend to the secondary again
+///
+/// Answers with the divergences left, such as those of bulk changes or of
+/// items the secondary still failed to take.
+#[utoipa::path(
+    post,
+    path = "/admin/v1/dual-write/reconcile",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Divergences left after reconciling", body = DualWriteReport),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 404, description = "Dual writes are disabled", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn reconcile_dual_write(
+    State(state): State<SharedState>,
+    _admin: AdminUser,
+) -> AppResult<Json<DualWriteReport>> {
+    Ok(Json(dual_write(&state)?.reconcile().await?))
+}
+
+/// Inventory of the crates linked into this binary, in CycloneDX JSON
+#[utoipa::path(
+    get,
+    path = "/admin/v1/sbom",
+    tag = "admin",
+    responses(
+        (status = 200, description = "CycloneDX 1.5 software bill of materials", body = Object, content_type = "application/vnd.cyclonedx+json"),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn get_sbom(_admin: AdminUser) -> impl IntoResponse {
+    ([(CONTENT_TYPE, sbom::SBOM_CONTENT_TYPE)], sbom::SBOM)
+}
+
+/// Crates linked into this binary, grouped by license
+#[utoipa::path(
+    get,
+    path = "/admin/v1/sbom/licenses",
+    tag = "admin",
+    responses(
+        (status = 200, description = "License report", body = LicenseReport),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn get_license_report(_admin: AdminUser) -> Json<LicenseReport> {
+    Json(sbom::LICENSE_REPORT.clone())
+}
+
+// ===== PROFILING HANDLERS =====
+
+/// Query parameters for a CPU profile
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ProfileQuery {
+    /// Seconds to profile for (default 10, at most `PROFILING_MAX_SECONDS`)
+    pub seconds: Option<u64>,
+}
+
+fn profiling_enabled(state: &SharedState) -> AppResult<()> {
+    if state.config.profiling.enabled {
+        Ok(())
+    } else {
+        Err(AppError::NotFound("Profiling is disabled".to_string()))
+    }
+}
+
+/// CPU time used by each thread of this instance over a window
+#[utoipa::path(
+    get,
+    path = "/debug/pprof/profile",
+    tag = "admin",
+    params(ProfileQuery),
+    responses(
+        (status = 200, description = "CPU time by thread", body = CpuProfile),
+        (status = 400, description = "Invalid profile duration", body = ErrorResponse),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 404, description = "Profiling is disabled", body = ErrorResponse),
+        (status = 503, description = "CPU profiling is not supported on this platform", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn cpu_profile(
+    State(state): State<SharedState>,
+    _admin: AdminUser,
+    Query(query): Query<ProfileQuery>,
+) -> AppResult<Json<CpuProfile>> {
+    profiling_enabled(&state)?;
+    let max = state.config.profiling.max_profile_seconds;
+    let seconds = query.seconds.unwrap_or(10);
+    if seconds == 0 || seconds > max {
+        return Err(AppError::BadRequest(format!(
+            "Profile duration must be between 1 and {max} seconds"
+        )));
+    }
+
+    profiling::cpu_profile(Duration::from_secs(seconds))
+        .await
+        .map(Json)
+        .map_err(|e| AppError::ServiceUnavailable(format!("CPU profiling is unavailable: {e}")))
+}
+
+/// Heap allocations counted since this instance started
+#[utoipa::path(
+    get,
+    path = "/debug/pprof/heap",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Heap allocation counts", body = HeapProfile),
+        (status = 401, description = "Authentication required", body = ErrorResponse),
+        (status = 403, description = "Administrator role required", body = ErrorResponse),
+        (status = 404, description = "Profiling is disabled", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub async fn heap_profile(
+    State(state): State<SharedState>,
+    _admin: AdminUser,
+) -> AppResult<Json<HeapProfile>> {
+    profiling_enabled(&state)?;
+    Ok(Json(HeapProfile::current()))
 }
 
 // ===== METRICS HANDLER =====
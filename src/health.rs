@@ -0,0 +1,237 @@
+use chrono::{DateTime, Utc};
+use futures_util::future::join_all;
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+use tokio::task::JoinHandle;
+use utoipa::ToSchema;
+
+use crate::{
+    config::{HealthConfig, SyntheticCheckConfig},
+    handlers::HealthStatus,
+    http_client::HttpClient,
+};
+
+/// Outcome of the latest run of one synthetic check
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CheckHealth {
+    #[schema(example = "payments")]
+    pub name: String,
+    pub healthy: bool,
+    /// Whether a failure makes the service unhealthy rather than degraded
+    pub critical: bool,
+    pub response_time_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub checked_at: DateTime<Utc>,
+}
+
+/// Measurements the overall health status is derived from
+#[derive(Debug, Clone, Copy)]
+pub struct HealthSignals<'a> {
+    pub db_connected: bool,
+    pub db_response_time_ms: Option<u64>,
+    pub broker_connected: bool,
+    pub memory_usage_percent: f32,
+    pub checks: &'a [CheckHealth],
+}
+
+/// Health thresholds and synthetic checks from configuration
+///
+/// Checks run in the background so the health endpoint never waits on a
+/// dependency; it reports the latest results (none until the first run).
+pub struct HealthMonitor {
+    config: HealthConfig,
+    http: HttpClient,
+    results: RwLock<Vec<CheckHealth>>,
+}
+
+impl HealthMonitor {
+    pub fn new(config: HealthConfig) -> Self {
+        Self {
+            config,
+            http: HttpClient::default(),
+            results: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Probe checks with the shared outbound client
+    #[must_use]
+    pub fn with_http_client(mut self, http: HttpClient) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Latest synthetic check results
+    pub fn checks(&self) -> Vec<CheckHealth> {
+        self.results
+            .read()
+            .map(|results| results.clone())
+            .unwrap_or_default()
+    }
+
+    /// Run every synthetic check once, concurrently
+    pub async fn run_checks(&self) {
+        let results = join_all(self.config.checks.iter().map(|check| self.probe(check))).await;
+        if let Ok(mut latest) = self.results.write() {
+            *latest = results;
+        }
+    }
+
+    async fn probe(&self, check: &SyntheticCheckConfig) -> CheckHealth {
+        let start = Instant::now();
+        let request = self
+            .http
+            .get(&check.url)
+            .timeout(Duration::from_millis(self.config.check_timeout_ms));
+        let error = match self.http.send(request).await {
+            Ok(response) if response.status().is_success() => None,
+            Ok(response) => Some(format!("responded with {}", response.status())),
+            Err(e) if e.is_timeout() => Some("timed out".to_string()),
+            Err(e) => Some(format!("request failed: {e}")),
+        };
+
+        CheckHealth {
+            name: check.name.clone(),
+            healthy: error.is_none(),
+            critical: check.critical,
+            response_time_ms: error.is_none().then(|| start.elapsed().as_millis() as u64),
+            error,
+            checked_at: Utc::now(),
+        }
+    }
+
+    /// Run the synthetic checks on the configured interval until aborted
+    pub fn spawn(self: Arc<Self>) -> Option<JoinHandle<()>> {
+        if self.config.checks.is_empty() {
+            return None;
+        }
+        Some(tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(Duration::from_secs(self.config.check_interval_seconds));
+            loop {
+                interval.tick().await;
+                self.run_checks().await;
+            }
+        }))
+    }
+
+    /// Overall status for the given measurements
+    pub fn status(&self, signals: &HealthSignals<'_>) -> HealthStatus {
+        let config = &self.config;
+        let exceeds = |value: Option<u64>, threshold: Option<u64>| matches!((value, threshold), (Some(value), Some(threshold)) if value > threshold);
+        let failed = |critical: bool| {
+            signals
+                .checks
+                .iter()
+                .any(|check| !check.healthy && check.critical == critical)
+        };
+
+        if !signals.db_connected
+            || exceeds(signals.db_response_time_ms, config.db_unhealthy_ms)
+            || config
+                .memory_unhealthy_percent
+                .is_some_and(|threshold| signals.memory_usage_percent > threshold)
+            || failed(true)
+        {
+            HealthStatus::Unhealthy
+        } else if !signals.broker_connected
+            || exceeds(signals.db_response_time_ms, config.db_degraded_ms)
+            || signals.memory_usage_percent > config.memory_degraded_percent
+            || failed(false)
+        {
+            HealthStatus::Degraded
+        } else {
+            HealthStatus::Healthy
+        }
+    }
+}
+
+impl Default for HealthMonitor {
+    fn default() -> Self {
+        Self::new(HealthConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signals(checks: &[CheckHealth]) -> HealthSignals<'_> {
+        HealthSignals {
+            db_connected: true,
+            db_response_time_ms: Some(5),
+            broker_connected: true,
+            memory_usage_percent: 50.0,
+            checks,
+        }
+    }
+
+    fn check(name: &str, url: String, critical: bool) -> SyntheticCheckConfig {
+        SyntheticCheckConfig {
+            name: name.to_string(),
+            url,
+            critical,
+        }
+    }
+
+    #[test]
+    fn test_thresholds() {
+        let monitor = HealthMonitor::new(HealthConfig {
+            memory_unhealthy_percent: Some(95.0),
+            db_degraded_ms: Some(100),
+            db_unhealthy_ms: Some(1000),
+            ..HealthConfig::default()
+        });
+        assert_eq!(monitor.status(&signals(&[])), HealthStatus::Healthy);
+
+        let slow = HealthSignals {
+            db_response_time_ms: Some(250),
+            ..signals(&[])
+        };
+        assert_eq!(monitor.status(&slow), HealthStatus::Degraded);
+        let stalled = HealthSignals {
+            db_response_time_ms: Some(1500),
+            ..signals(&[])
+        };
+        assert_eq!(monitor.status(&stalled), HealthStatus::Unhealthy);
+
+        let memory = |memory_usage_percent| HealthSignals {
+            memory_usage_percent,
+            ..signals(&[])
+        };
+        assert_eq!(monitor.status(&memory(92.0)), HealthStatus::Degraded);
+        assert_eq!(monitor.status(&memory(97.0)), HealthStatus::Unhealthy);
+    }
+
+    #[tokio::test]
+    async fn test_synthetic_checks() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = axum::Router::new().route("/ping", axum::routing::get(|| async { "pong" }));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let monitor = HealthMonitor::new(HealthConfig {
+            checks: vec![
+                check("ping", format!("http://{addr}/ping"), true),
+                check("missing", format!("http://{addr}/missing"), false),
+            ],
+            ..HealthConfig::default()
+        });
+        assert!(monitor.checks().is_empty());
+
+        monitor.run_checks().await;
+        let checks = monitor.checks();
+        assert!(checks[0].healthy);
+        assert!(!checks[1].healthy);
+        assert_eq!(checks[1].error.as_deref(), Some("responded with 404 Not Found"));
+        assert_eq!(monitor.status(&signals(&checks)), HealthStatus::Degraded);
+
+        // A failed critical check makes the service unhealthy
+        let mut checks = checks;
+        checks[0].healthy = false;
+        assert_eq!(monitor.status(&signals(&checks)), HealthStatus::Unhealthy);
+    }
+}
@@ -0,0 +1,386 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::{future::Future, sync::Arc, time::Duration};
+
+use crate::{
+    analytics::{AnalyticsBucket, AnalyticsRange},
+    db::{DatabaseResult, EventFilter, ItemFilter, ItemRepository},
+    events::OutboxEvent,
+    metrics::track_hedged_read,
+    models::{CreateItemRequest, Item, ItemField, UpdateItemRequest},
+    pagination::Page,
+};
+
+/// Sends a second attempt of a slow read and answers with the first success
+///
+/// A `get`, `get_by_slug`, or `list` (with or without its total) that has
+/// not finished after `delay` is also sent to `secondary`, a replica or the
+/// primary itself; whichever attempt succeeds first answers, and the other is
+/// dropped. Other calls go to the primary alone, as sending a write twice
+/// could apply it twice.
+pub struct HedgedRepository {
+    primary: Arc<dyn ItemRepository>,
+    secondary: Arc<dyn ItemRepository>,
+    delay: Duration,
+    backend: String,
+}
+
+/// Which attempt of a hedged read answered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HedgeWinner {
+    Primary,
+    Hedge,
+    /// Both attempts failed
+    Neither,
+}
+
+impl HedgeWinner {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Primary => "primary",
+            Self::Hedge => "hedge",
+            Self::Neither => "neither",
+        }
+    }
+}
+
+impl HedgedRepository {
+    pub fn new(
+        primary: Arc<dyn ItemRepository>,
+        secondary: Arc<dyn ItemRepository>,
+        delay: Duration,
+        backend: &str,
+    ) -> Self {
+        Self {
+            primary,
+            secondary,
+            delay,
+            backend: backend.to_string(),
+        }
+    }
+
+    async fn read<T, H>(
+        &self,
+        operation: &str,
+        primary: impl Future<Output = DatabaseResult<T>>,
+        hedge: impl FnOnce() -> H,
+    ) -> DatabaseResult<T>
+    where
+        H: Future<Output = DatabaseResult<T>>,
+    {
+        tokio::pin!(primary);
+        if let Ok(result) = tokio::time::timeout(self.delay, &mut primary).await {
+            return result;
+        }
+
+        let hedge = hedge();
+        tokio::pin!(hedge);
+        let (winner, result) = tokio::select! {
+            result = &mut primary => match result {
+                Ok(value) => (HedgeWinner::Primary, Ok(value)),
+                Err(_) => match hedge.await {
+                    Ok(value) => (HedgeWinner::Hedge, Ok(value)),
+                    Err(e) => (HedgeWinner::Neither, Err(e)),
+                },
+            },
+            result = &mut hedge => match result {
+                Ok(value) => (HedgeWinner::Hedge, Ok(value)),
+                Err(_) => match primary.await {
+                    Ok(value) => (HedgeWinner::Primary, Ok(value)),
+                    Err(e) => (HedgeWinner::Neither, Err(e)),
+                },
+            },
+        };
+        track_hedged_read(&self.backend, operation, winner.as_str());
+        result
+    }
+}
+
+#[async_trait]
+impl ItemRepository for HedgedRepository {
+    async fn create(
+        &self,
+        request: CreateItemRequest,
+        owner_id: Option<String>,
+    ) -> DatabaseResult<Item> {
+        self.primary.create(request, owner_id).await
+    }
+
+    async fn get(&self, id: &str) -> DatabaseResult<Item> {
+        self.read("get", self.primary.get(id), || self.secondary.get(id))
+            .await
+    }
+
+    async fn get_by_slug(&self, slug: &str) -> DatabaseResult<Item> {
+        self.read("get_by_slug", self.primary.get_by_slug(slug), || {
+            self.secondary.get_by_slug(slug)
+        })
+        .await
+    }
+
+    async fn get_by_external_id(
+        &self,
+        source: Option<&str>,
+        external_id: &str,
+    ) -> DatabaseResult<Item> {
+        self.read(
+            "get_by_external_id",
+            self.primary.get_by_external_id(source, external_id),
+            || self.secondary.get_by_external_id(source, external_id),
+        )
+        .await
+    }
+
+    async fn update(
+        &self,
+        id: &str,
+        request: UpdateItemRequest,
+        expected_version: Option<u64>,
+    ) -> DatabaseResult<Item> {
+        self.primary.update(id, request, expected_version).await
+    }
+
+    async fn changed_since(&self, id: &str, version: u64) -> DatabaseResult<Vec<ItemField>> {
+        self.primary.changed_since(id, version).await
+    }
+
+    async fn delete(&self, id: &str) -> DatabaseResult<()> {
+        self.primary.delete(id).await
+    }
+
+    async fn list(&self, filter: &ItemFilter, page: &Page) -> DatabaseResult<Vec<Arc<Item>>> {
+        self.read("list", self.primary.list(filter, page), || self.secondary.list(filter, page))
+            .await
+    }
+
+    async fn count(&self, filter: &ItemFilter) -> DatabaseResult<usize> {
+        self.primary.count(filter).await
+    }
+
+    async fn list_with_total(
+        &self,
+        filter: &ItemFilter,
+        page: &Page,
+    ) -> DatabaseResult<(Vec<Arc<Item>>, usize)> {
+        self.read("list_with_total", self.primary.list_with_total(filter, page), || {
+            self.secondary.list_with_total(filter, page)
+        })
+        .await
+    }
+
+    async fn health_check(&self) -> DatabaseResult<()> {
+        self.primary.health_check().await
+    }
+
+    async fn reassign_owner(
+        &self,
+        owner_id: &str,
+        replacement: &str,
+    ) -> DatabaseResult<(usize, usize)> {
+        self.primary.reassign_owner(owner_id, replacement).await
+    }
+
+    async fn restore(&self, item: Item) -> DatabaseResult<Item> {
+        self.primary.restore(item).await
+    }
+
+    async fn provision_tenant(&self, tenant: &str) -> DatabaseResult<()> {
+        self.primary.provision_tenant(tenant).await
+    }
+
+    async fn drop_tenant(&self, tenant: &str) -> DatabaseResult<Vec<String>> {
+        self.primary.drop_tenant(tenant).await
+    }
+
+    async fn pending_events(&self, limit: usize) -> DatabaseResult<Vec<OutboxEvent>> {
+        self.primary.pending_events(limit).await
+    }
+
+    async fn event_history(
+        &self,
+        filter: &EventFilter,
+        after: u64,
+        limit: usize,
+    ) -> DatabaseResult<Vec<OutboxEvent>> {
+        self.primary.event_history(filter, after, limit).await
+    }
+
+    async fn pending_event_count(&self) -> DatabaseResult<usize> {
+        self.primary.pending_event_count().await
+    }
+
+    async fn mark_events_published(&self, sequences: &[u64]) -> DatabaseResult<()> {
+        self.primary.mark_events_published(sequences).await
+    }
+
+    async fn mark_event_failed(&self, sequence: u64, error: &str) -> DatabaseResult<()> {
+        self.primary.mark_event_failed(sequence, error).await
+    }
+
+    async fn purge_published_events(
+        &self,
+        cutoff: DateTime<Utc>,
+        dry_run: bool,
+    ) -> DatabaseResult<usize> {
+        self.primary.purge_published_events(cutoff, dry_run).await
+    }
+
+    async fn event_histogram(
+        &self,
+        filter: &ItemFilter,
+        range: &AnalyticsRange,
+    ) -> DatabaseResult<Vec<AnalyticsBucket>> {
+        self.primary.event_histogram(filter, range).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::InMemoryRepository, metrics::DATABASE_HEDGED_READS};
+
+    /// Repository answering reads after a fixed delay
+    struct Slow {
+        inner: InMemoryRepository,
+        delay: Duration,
+    }
+
+    #[async_trait]
+    impl ItemRepository for Slow {
+        async fn create(
+            &self,
+            request: CreateItemRequest,
+            owner_id: Option<String>,
+        ) -> DatabaseResult<Item> {
+            self.inner.create(request, owner_id).await
+        }
+        async fn get(&self, id: &str) -> DatabaseResult<Item> {
+            tokio::time::sleep(self.delay).await;
+            self.inner.get(id).await
+        }
+        async fn get_by_slug(&self, slug: &str) -> DatabaseResult<Item> {
+            self.inner.get_by_slug(slug).await
+        }
+        async fn get_by_external_id(
+            &self,
+            source: Option<&str>,
+            external_id: &str,
+        ) -> DatabaseResult<Item> {
+            self.inner.get_by_external_id(source, external_id).await
+        }
+        async fn update(
+            &self,
+            id: &str,
+            request: UpdateItemRequest,
+            expected_version: Option<u64>,
+        ) -> DatabaseResult<Item> {
+            self.inner.update(id, request, expected_version).await
+        }
+        async fn changed_since(&self, id: &str, version: u64) -> DatabaseResult<Vec<ItemField>> {
+            self.inner.changed_since(id, version).await
+        }
+        async fn delete(&self, id: &str) -> DatabaseResult<()> {
+            self.inner.delete(id).await
+        }
+        async fn list(&self, filter: &ItemFilter, page: &Page) -> DatabaseResult<Vec<Arc<Item>>> {
+            self.inner.list(filter, page).await
+        }
+        async fn count(&self, filter: &ItemFilter) -> DatabaseResult<usize> {
+            self.inner.count(filter).await
+        }
+        async fn health_check(&self) -> DatabaseResult<()> {
+            Ok(())
+        }
+        async fn reassign_owner(&self, owner: &str, to: &str) -> DatabaseResult<(usize, usize)> {
+            self.inner.reassign_owner(owner, to).await
+        }
+        async fn restore(&self, item: Item) -> DatabaseResult<Item> {
+            self.inner.restore(item).await
+        }
+        async fn provision_tenant(&self, tenant: &str) -> DatabaseResult<()> {
+            self.inner.provision_tenant(tenant).await
+        }
+        async fn drop_tenant(&self, tenant: &str) -> DatabaseResult<Vec<String>> {
+            self.inner.drop_tenant(tenant).await
+        }
+        async fn pending_events(&self, limit: usize) -> DatabaseResult<Vec<OutboxEvent>> {
+            self.inner.pending_events(limit).await
+        }
+        async fn event_history(
+            &self,
+            filter: &EventFilter,
+            after: u64,
+            limit: usize,
+        ) -> DatabaseResult<Vec<OutboxEvent>> {
+            self.inner.event_history(filter, after, limit).await
+        }
+        async fn pending_event_count(&self) -> DatabaseResult<usize> {
+            self.inner.pending_event_count().await
+        }
+        async fn mark_events_published(&self, sequences: &[u64]) -> DatabaseResult<()> {
+            self.inner.mark_events_published(sequences).await
+        }
+        async fn mark_event_failed(&self, sequence: u64, error: &str) -> DatabaseResult<()> {
+            self.inner.mark_event_failed(sequence, error).await
+        }
+        async fn purge_published_events(
+            &self,
+            cutoff: DateTime<Utc>,
+            dry_run: bool,
+        ) -> DatabaseResult<usize> {
+            self.inner.purge_published_events(cutoff, dry_run).await
+        }
+        async fn event_histogram(
+            &self,
+            filter: &ItemFilter,
+            range: &AnalyticsRange,
+        ) -> DatabaseResult<Vec<AnalyticsBucket>> {
+            self.inner.event_histogram(filter, range).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_reads_are_answered_by_the_hedge() {
+        let slow = Arc::new(Slow {
+            inner: InMemoryRepository::new(),
+            delay: Duration::from_secs(5),
+        });
+        let request = CreateItemRequest {
+            name: "Hedged".to_string(),
+            description: None,
+            metadata: None,
+            custom_fields: Default::default(),
+            status: Default::default(),
+            publish_at: None,
+            external_id: None,
+            source: None,
+        };
+        let item = slow.create(request, None).await.unwrap();
+        let replica = Arc::new(InMemoryRepository::new());
+        replica.restore(item.clone()).await.unwrap();
+
+        let repo = HedgedRepository::new(slow, replica, Duration::from_millis(10), "test-hedge");
+        let won = || {
+            DATABASE_HEDGED_READS
+                .with_label_values(&["test-hedge", "get", "hedge"])
+                .get()
+        };
+        let before = won();
+        let started = std::time::Instant::now();
+        assert_eq!(repo.get(&item.id).await.unwrap().name, "Hedged");
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(won(), before + 1);
+
+        // Fast reads are never hedged
+        let listed = repo
+            .list(&ItemFilter::default(), &Page::first(10).unwrap())
+            .await;
+        assert_eq!(listed.unwrap().len(), 1);
+        assert_eq!(
+            DATABASE_HEDGED_READS
+                .with_label_values(&["test-hedge", "list", "primary"])
+                .get(),
+            0
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use crate::{
+    error::AppError,
+    middleware::auth::Claims,
+    models::{CreateItemRequest, Item, UpdateItemRequest},
+};
+
+/// Why a hook refused a change, reported to the client as a structured error
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum HookRejection {
+    /// A field's value is not acceptable (422, with the field in the details)
+    #[error("{field}: {message}")]
+    Invalid { field: String, message: String },
+    /// The caller may not make this change (403)
+    #[error("{0}")]
+    Forbidden(String),
+    /// The change conflicts with existing data (409)
+    #[error("{0}")]
+    Conflict(String),
+}
+
+impl HookRejection {
+    pub fn invalid(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::Invalid {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl From<HookRejection> for AppError {
+    fn from(rejection: HookRejection) -> Self {
+        match rejection {
+            // Rendered as `field: message`, which the error response splits into
+            // field-level validation details
+            rejection @ HookRejection::Invalid { .. } => {
+                AppError::ValidationError(rejection.to_string())
+            }
+            HookRejection::Forbidden(message) => AppError::Forbidden(message),
+            HookRejection::Conflict(message) => {
+                AppError::DatabaseError(crate::db::DatabaseError::Conflict(message))
+            }
+        }
+    }
+}
+
+/// Who is making a change
+#[derive(Debug, Clone, Copy)]
+pub struct HookContext<'a> {
+    /// The authenticated caller, if any
+    pub claims: Option<&'a Claims>,
+}
+
+/// Custom behavior around item changes made through the items API
+///
+/// `before_*` hooks run after authorization and may adjust the request or veto
+/// the change; `after_*` hooks run once the change is stored and cannot undo
+/// it, so their failures are theirs to log. Every method defaults to doing
+/// nothing. Removals by retention or data erasure do not run hooks.
+#[async_trait]
+pub trait ItemHooks: Send + Sync {
+    async fn before_create(
+        &self,
+        _ctx: HookContext<'_>,
+        _request: &mut CreateItemRequest,
+    ) -> Result<(), HookRejection> {
+        Ok(())
+    }
+
+    async fn after_create(&self, _ctx: HookContext<'_>, _item: &Item) {}
+
+    async fn before_update(
+        &self,
+        _ctx: HookContext<'_>,
+        _existing: &Item,
+        _request: &mut UpdateItemRequest,
+    ) -> Result<(), HookRejection> {
+        Ok(())
+    }
+
+    async fn after_update(&self, _ctx: HookContext<'_>, _item: &Item) {}
+
+    async fn before_delete(
+        &self,
+        _ctx: HookContext<'_>,
+        _existing: &Item,
+    ) -> Result<(), HookRejection> {
+        Ok(())
+    }
+
+    async fn after_delete(&self, _ctx: HookContext<'_>, _item: &Item) {}
+}
+
+/// Registered hooks, run in registration order
+///
+/// The first `before_*` rejection stops the change and skips later hooks.
+#[derive(Clone, Default)]
+pub struct ItemHookChain {
+    hooks: Vec<Arc<dyn ItemHooks>>,
+}
+
+impl ItemHookChain {
+    pub fn register(&mut self, hooks: Arc<dyn ItemHooks>) {
+        self.hooks.push(hooks);
+    }
+
+    pub async fn before_create(
+        &self,
+        ctx: HookContext<'_>,
+        request: &mut CreateItemRequest,
+    ) -> Result<(), HookRejection> {
+        for hooks in &self.hooks {
+            hooks.before_create(ctx, request).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn after_create(&self, ctx: HookContext<'_>, item: &Item) {
+        for hooks in &self.hooks {
+            hooks.after_create(ctx, item).await;
+        }
+    }
+
+    pub async fn before_update(
+        &self,
+        ctx: HookContext<'_>,
+        existing: &Item,
+        request: &mut UpdateItemRequest,
+    ) -> Result<(), HookRejection> {
+        for hooks in &self.hooks {
+            hooks.before_update(ctx, existing, request).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn after_update(&self, ctx: HookContext<'_>, item: &Item) {
+        for hooks in &self.hooks {
+            hooks.after_update(ctx, item).await;
+        }
+    }
+
+    pub async fn before_delete(
+        &self,
+        ctx: HookContext<'_>,
+        existing: &Item,
+    ) -> Result<(), HookRejection> {
+        for hooks in &self.hooks {
+            hooks.before_delete(ctx, existing).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn after_delete(&self, ctx: HookContext<'_>, item: &Item) {
+        for hooks in &self.hooks {
+            hooks.after_delete(ctx, item).await;
+        }
+    }
+}
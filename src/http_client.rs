@@ -0,0 +1,576 @@
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    redirect, Client, ClientBuilder, IntoUrl, Method, Proxy, RequestBuilder, Response, StatusCode,
+    Url,
+};
+use std::{
+    error::Error,
+    future::Future,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+use tracing::{debug, warn};
+
+use crate::{
+    config::{EgressConfig, HttpClientConfig},
+    middleware::observability::X_REQUEST_ID,
+};
+
+/// Redirects followed before a request fails, as in reqwest's default policy
+const MAX_REDIRECTS: usize = 10;
+
+tokio::task_local! {
+    /// ID of the request being handled, set by the request ID middleware
+    static REQUEST_ID: String;
+}
+
+/// Request ID of the current task, if it runs on behalf of a request
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Run `future` with `request_id` forwarded on its outbound requests
+pub async fn with_request_id<F: Future>(request_id: String, future: F) -> F::Output {
+    REQUEST_ID.scope(request_id, future).await
+}
+
+/// Why an outbound request failed
+#[derive(Debug, thiserror::Error)]
+pub enum HttpError {
+    #[error("destination not allowed: {0}")]
+    Blocked(String),
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+}
+
+impl HttpError {
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, Self::Request(e) if e.is_timeout())
+    }
+
+    /// The error without the URL it was for, when the URL is a secret
+    pub fn without_url(self) -> Self {
+        match self {
+            Self::Request(e) => Self::Request(e.without_url()),
+            blocked => blocked,
+        }
+    }
+}
+
+/// A destination rejected by the egress policy
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+struct Blocked(String);
+
+/// Destinations a restricted client may connect to
+///
+/// Only public addresses are reachable, over the allowed schemes. Allowlisted
+/// host names and IP addresses may use any scheme and address; allowlisted
+/// CIDR ranges make the private addresses in them reachable.
+#[derive(Debug)]
+pub struct EgressPolicy {
+    schemes: Vec<String>,
+    hosts: Vec<String>,
+    networks: Vec<(IpAddr, u8)>,
+}
+
+impl EgressPolicy {
+    pub fn new(config: &EgressConfig) -> Result<Self, String> {
+        let mut hosts = Vec::new();
+        let mut networks = Vec::new();
+        for entry in &config.allowed_hosts {
+            if let Some((address, prefix)) = entry.split_once('/') {
+                let address: IpAddr = address
+                    .parse()
+                    .map_err(|_| format!("Invalid egress allowlist range: {entry}"))?;
+                let prefix = prefix
+                    .parse()
+                    .ok()
+                    .filter(|prefix| *prefix <= max_prefix(address))
+                    .ok_or_else(|| format!("Invalid egress allowlist range: {entry}"))?;
+                networks.push((address.to_canonical(), prefix));
+            } else if let Ok(address) = entry.parse::<IpAddr>() {
+                networks.push((address.to_canonical(), max_prefix(address.to_canonical())));
+            } else {
+                hosts.push(entry.to_ascii_lowercase());
+            }
+        }
+
+        Ok(Self {
+            schemes: config.allowed_schemes.clone(),
+            hosts,
+            networks,
+        })
+    }
+
+    fn host_allowlisted(&self, host: &str) -> bool {
+        self.hosts
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(host))
+    }
+
+    fn address_allowlisted(&self, address: IpAddr) -> bool {
+        let address = address.to_canonical();
+        self.networks
+            .iter()
+            .any(|(network, prefix)| in_network(address, *network, *prefix))
+    }
+
+    /// Whether a resolved address may be connected to
+    fn address_allowed(&self, address: IpAddr) -> bool {
+        is_public(address) || self.address_allowlisted(address)
+    }
+
+    /// Check the scheme and, for IP literals, the address of a URL
+    ///
+    /// Host names are checked when they are resolved, so the address that was
+    /// checked is the one connected to.
+    fn check_url(&self, url: &Url) -> Result<(), Blocked> {
+        let Some(host) = url.host_str() else {
+            return Err(Blocked(format!("{url} has no host")));
+        };
+        let address = host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+            .ok();
+        if address.is_none() && self.host_allowlisted(host) {
+            return Ok(());
+        }
+        if address.is_some_and(|address| self.address_allowlisted(address)) {
+            return Ok(());
+        }
+
+        if !self.schemes.iter().any(|scheme| scheme == url.scheme()) {
+            return Err(Blocked(format!("scheme {} is not allowed", url.scheme())));
+        }
+        match address {
+            Some(address) if !is_public(address) => {
+                Err(Blocked(format!("{address} is not a public address")))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Resolves host names, dropping addresses the egress policy does not allow
+struct PolicyResolver(Arc<EgressPolicy>);
+
+impl Resolve for PolicyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let policy = self.0.clone();
+        Box::pin(async move {
+            let host = name.as_str();
+            let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host, 0)).await?.collect();
+            if policy.host_allowlisted(host) {
+                return Ok(Box::new(addresses.into_iter()) as Addrs);
+            }
+
+            let allowed: Vec<SocketAddr> = addresses
+                .into_iter()
+                .filter(|address| policy.address_allowed(address.ip()))
+                .collect();
+            if allowed.is_empty() {
+                return Err(
+                    Box::new(Blocked(format!("{host} does not resolve to a public address")))
+                        as Box<dyn Error + Send + Sync>,
+                );
+            }
+            Ok(Box::new(allowed.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Whether `address` is reachable from the internet
+fn is_public(address: IpAddr) -> bool {
+    match address.to_canonical() {
+        IpAddr::V4(address) => {
+            let [a, b, ..] = address.octets();
+            !(address.is_private()
+                || address.is_loopback()
+                || address.is_link_local()
+                || address.is_unspecified()
+                || address.is_broadcast()
+                || address.is_multicast()
+                || address.is_documentation()
+                // "This network", shared address space (CGNAT) and reserved
+                || a == 0
+                || (a == 100 && (64..128).contains(&b))
+                || a >= 240)
+        }
+        IpAddr::V6(address) => {
+            let first = address.segments()[0];
+            !(address.is_loopback()
+                || address.is_unspecified()
+                || address.is_multicast()
+                // Unique local and link-local
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+fn max_prefix(address: IpAddr) -> u8 {
+    if address.is_ipv4() {
+        32
+    } else {
+        128
+    }
+}
+
+fn in_network(address: IpAddr, network: IpAddr, prefix: u8) -> bool {
+    match (address, network) {
+        (IpAddr::V4(address), IpAddr::V4(network)) => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+            u32::from(address) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(address), IpAddr::V6(network)) => {
+            let mask = u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+            u128::from(address) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
+
+/// The client every outbound HTTP call goes through
+///
+/// Connections are pooled across callers, and every request carries the
+/// configured user agent and the `X-Request-Id` of the request it is made on
+/// behalf of, so downstream logs can be correlated with ours. Idempotent
+/// requests are retried with exponential backoff after connection errors and
+/// 502, 503 and 504 responses; other requests are sent once.
+///
+/// A restricted client additionally enforces an [`EgressPolicy`] on every
+/// request and redirect, for URLs that may not point at trusted
+/// infrastructure.
+#[derive(Clone)]
+pub struct HttpClient {
+    client: Client,
+    egress: Option<Arc<EgressPolicy>>,
+    max_retries: u32,
+    retry_backoff: Duration,
+}
+
+impl HttpClient {
+    pub fn new(config: &HttpClientConfig) -> Result<Self, String> {
+        let mut builder = Self::builder(config);
+        if let Some(proxy) = &config.proxy {
+            builder = builder.proxy(Proxy::all(proxy).map_err(|e| format!("Invalid proxy: {e}"))?);
+        }
+        Self::build(config, builder, None)
+    }
+
+    /// A client that only reaches destinations allowed by `egress`
+    ///
+    /// Host names are resolved by the client itself so every address can be
+    /// checked before connecting, which means requests never go through a
+    /// proxy; with the policy disabled this is the same as [`HttpClient::new`].
+    pub fn restricted(config: &HttpClientConfig, egress: &EgressConfig) -> Result<Self, String> {
+        if !egress.enabled {
+            return Self::new(config);
+        }
+        if config.proxy.is_some() {
+            warn!("HTTP_CLIENT_PROXY is not used for requests subject to the egress policy");
+        }
+
+        let policy = Arc::new(EgressPolicy::new(egress)?);
+        let redirects = policy.clone();
+        let builder = Self::builder(config)
+            .no_proxy()
+            .dns_resolver(Arc::new(PolicyResolver(policy.clone())))
+            .redirect(redirect::Policy::custom(move |attempt| {
+                if attempt.previous().len() >= MAX_REDIRECTS {
+                    attempt.error("too many redirects")
+                } else if let Err(e) = redirects.check_url(attempt.url()) {
+                    attempt.error(e)
+                } else {
+                    attempt.follow()
+                }
+            }));
+        Self::build(config, builder, Some(policy))
+    }
+
+    fn builder(config: &HttpClientConfig) -> ClientBuilder {
+        Client::builder()
+            .connect_timeout(Duration::from_millis(config.connect_timeout_ms))
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .pool_idle_timeout(Duration::from_secs(config.pool_idle_timeout_seconds))
+            .pool_max_idle_per_host(config.pool_max_idle_per_host)
+            .user_agent(&config.user_agent)
+    }
+
+    fn build(
+        config: &HttpClientConfig,
+        builder: ClientBuilder,
+        egress: Option<Arc<EgressPolicy>>,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            client: builder.build().map_err(|e| e.to_string())?,
+            egress,
+            max_retries: config.max_retries,
+            retry_backoff: Duration::from_millis(config.retry_backoff_ms),
+        })
+    }
+
+    pub fn get(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    pub fn post(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.client.post(url)
+    }
+
+    pub fn head(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.client.head(url)
+    }
+
+    pub fn put(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.client.put(url)
+    }
+
+    /// Send a request built with this client, retrying if it is idempotent
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response, HttpError> {
+        let mut request = request.build()?;
+        if let Some(policy) = &self.egress {
+            if let Err(blocked) = policy.check_url(request.url()) {
+                warn!(url = %request.url(), "Blocked outbound request: {}", blocked);
+                return Err(HttpError::Blocked(blocked.0));
+            }
+        }
+        if let Some(request_id) = current_request_id() {
+            if let Ok(value) = request_id.parse() {
+                request.headers_mut().insert(X_REQUEST_ID.clone(), value);
+            }
+        }
+
+        let retries = if is_idempotent(request.method()) {
+            self.max_retries
+        } else {
+            0
+        };
+        let mut attempt = 0;
+        loop {
+            // Streaming bodies cannot be replayed, so they are sent only once
+            let Some(retry) = (attempt < retries).then(|| request.try_clone()).flatten() else {
+                return self.client.execute(request).await.map_err(request_error);
+            };
+
+            match self.client.execute(retry).await.map_err(request_error) {
+                Ok(response) if !is_retryable_status(response.status()) => return Ok(response),
+                Ok(response) => debug!(
+                    url = %request.url(),
+                    attempt,
+                    "Retrying after {}", response.status()
+                ),
+                Err(HttpError::Request(e)) if e.is_connect() || e.is_timeout() => {
+                    debug!(url = %request.url(), attempt, "Retrying after error: {}", e)
+                }
+                Err(e) => return Err(e),
+            }
+
+            tokio::time::sleep(self.retry_backoff * 2u32.saturating_pow(attempt)).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// Unwrap policy violations, which the resolver and redirect policy can only
+/// report wrapped in a request error
+fn request_error(e: reqwest::Error) -> HttpError {
+    let mut source = e.source();
+    while let Some(error) = source {
+        if let Some(blocked) = error.downcast_ref::<Blocked>() {
+            warn!("Blocked outbound request: {}", blocked);
+            return HttpError::Blocked(blocked.0.clone());
+        }
+        source = error.source();
+    }
+    HttpError::Request(e)
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        Self::new(&HttpClientConfig::default()).expect("default HTTP client configuration is valid")
+    }
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS
+    )
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::State, http::HeaderMap, response::Redirect, routing::get, Router};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    };
+
+    #[derive(Clone, Default)]
+    struct Upstream {
+        calls: Arc<AtomicUsize>,
+        request_ids: Arc<Mutex<Vec<Option<String>>>>,
+    }
+
+    /// Responds 503 to the first two requests, then 200
+    async fn spawn_flaky_upstream(upstream: Upstream) -> String {
+        let handler = |State(upstream): State<Upstream>, headers: HeaderMap| async move {
+            upstream.request_ids.lock().unwrap().push(
+                headers
+                    .get("x-request-id")
+                    .map(|id| id.to_str().unwrap().to_string()),
+            );
+            match upstream.calls.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => StatusCode::SERVICE_UNAVAILABLE,
+                _ => StatusCode::OK,
+            }
+        };
+        let app = Router::new()
+            .route("/", get(handler).post(handler))
+            .with_state(upstream);
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}/")
+    }
+
+    fn client() -> HttpClient {
+        HttpClient::new(&HttpClientConfig {
+            retry_backoff_ms: 1,
+            ..HttpClientConfig::default()
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_idempotent_requests_are_retried() {
+        let upstream = Upstream::default();
+        let url = spawn_flaky_upstream(upstream.clone()).await;
+        let client = client();
+
+        let response = with_request_id("req-1".to_string(), client.send(client.get(&url)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(upstream.calls.load(Ordering::SeqCst), 3);
+        assert!(upstream
+            .request_ids
+            .lock()
+            .unwrap()
+            .iter()
+            .all(|id| id.as_deref() == Some("req-1")));
+    }
+
+    #[tokio::test]
+    async fn test_other_requests_are_sent_once() {
+        let upstream = Upstream::default();
+        let url = spawn_flaky_upstream(upstream.clone()).await;
+        let client = client();
+
+        let response = client.send(client.post(&url)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(upstream.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(upstream.request_ids.lock().unwrap()[0], None);
+    }
+
+    fn egress(schemes: &[&str], hosts: &[&str]) -> EgressConfig {
+        EgressConfig {
+            enabled: true,
+            allowed_schemes: schemes.iter().map(|s| s.to_string()).collect(),
+            allowed_hosts: hosts.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_egress_policy_destinations() {
+        let policy =
+            EgressPolicy::new(&egress(&["https"], &["idp.internal", "10.1.0.0/16"])).unwrap();
+        let check = |url: &str| policy.check_url(&url.parse().unwrap()).is_ok();
+
+        assert!(check("https://idp.example.com/jwks"));
+        assert!(check("https://93.184.216.34/jwks"));
+        assert!(!check("http://idp.example.com/jwks"));
+        assert!(!check("file:///etc/passwd"));
+        for private in [
+            "https://127.0.0.1/",
+            "https://10.0.0.1/",
+            "https://169.254.169.254/latest/meta-data",
+            "https://100.64.0.1/",
+            "https://0.0.0.0/",
+            "https://[::1]/",
+            "https://[fd00::1]/",
+            "https://[fe80::1]/",
+            "https://[::ffff:127.0.0.1]/",
+        ] {
+            assert!(!check(private), "{private} should be blocked");
+        }
+
+        // Allowlisted destinations may use any scheme
+        assert!(check("http://idp.internal/jwks"));
+        assert!(check("http://10.1.2.3/jwks"));
+        assert!(!check("http://10.2.0.1/jwks"));
+        assert!(policy.address_allowed("10.1.255.255".parse().unwrap()));
+        assert!(!policy.address_allowed("192.168.1.1".parse().unwrap()));
+
+        assert!(EgressPolicy::new(&egress(&["https"], &["10.0.0.0/33"])).is_err());
+        assert!(EgressPolicy::new(&egress(&["https"], &["not-an-ip/8"])).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_restricted_client_blocks_private_destinations() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let app = Router::new().route("/", get(|| async { "ok" })).route(
+            "/redirect",
+            get(move || async move { Redirect::temporary(&format!("http://127.0.0.1:{port}/")) }),
+        );
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let config = HttpClientConfig::default();
+        let client = HttpClient::restricted(&config, &egress(&["http"], &[])).unwrap();
+        for url in [
+            format!("http://127.0.0.1:{port}/"),
+            format!("http://localhost:{port}/"),
+        ] {
+            let result = client.send(client.get(&url)).await;
+            assert!(matches!(result, Err(HttpError::Blocked(_))), "{url}: {result:?}");
+        }
+
+        // An allowlisted host cannot redirect to a destination that is not
+        let client = HttpClient::restricted(&config, &egress(&["http"], &["localhost"])).unwrap();
+        let response = client
+            .send(client.get(format!("http://localhost:{port}/")))
+            .await;
+        assert_eq!(response.unwrap().status(), StatusCode::OK);
+        let result = client
+            .send(client.get(format!("http://localhost:{port}/redirect")))
+            .await;
+        assert!(matches!(result, Err(HttpError::Blocked(_))), "{result:?}");
+
+        let client = HttpClient::restricted(
+            &config,
+            &EgressConfig {
+                enabled: false,
+                ..EgressConfig::default()
+            },
+        )
+        .unwrap();
+        let response = client
+            .send(client.get(format!("http://localhost:{port}/")))
+            .await;
+        assert_eq!(response.unwrap().status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,253 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    db::{AccessRepository, DatabaseError, NotificationRepository},
+    events::{EventPublisher, ItemEventType, OutboxEvent},
+    models::{Grantee, Permission},
+};
+
+/// Why a notification went to its recipient
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationReason {
+    /// The recipient owns the item
+    Owner,
+    /// The item was shared with the recipient through a permission grant
+    Grantee,
+    /// The item is new and matches one of the recipient's saved searches
+    SavedSearch,
+    /// The recipient may approve a change someone requested to the item
+    Approval,
+    /// A change the recipient requested was applied, rejected or failed
+    ChangeRequest,
+}
+
+/// A change someone else made to an item the recipient owns or can access
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Notification {
+    #[schema(example = "0b7e6a52-8d1f-4a3e-9c55-2f1d6e8b9a10")]
+    pub id: String,
+    /// Subject of the principal the notification is for
+    #[schema(example = "user-456")]
+    pub recipient: String,
+    /// Outbox event the notification is about (for change request
+    /// notifications, the change request and its outcome)
+    pub event_id: String,
+    pub event_type: ItemEventType,
+    pub item_id: String,
+    /// Name of the item after the change (before it, for deletions)
+    #[schema(example = "Example Item")]
+    pub item_name: String,
+    /// Subject of the principal that made the change
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = "user-123")]
+    pub actor: Option<String>,
+    pub reason: NotificationReason,
+    /// Saved search the item matched, for `saved_search` notifications
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub saved_search_id: Option<String>,
+    /// Change request the notification is about, for `approval` and
+    /// `change_request` notifications
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change_request_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_at: Option<DateTime<Utc>>,
+    /// Tenant the item belongs to; notifications are only listed within it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+}
+
+/// A page of the caller's notifications
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct NotificationList {
+    pub notifications: Vec<Notification>,
+    /// Notifications matching the query
+    pub total: usize,
+    /// Unread notifications, whatever the query
+    pub unread: usize,
+    pub limit: usize,
+    pub offset: usize,
+}
+
+/// How many of the caller's notifications are unread
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UnreadCount {
+    pub unread: usize,
+}
+
+/// Notifications marked read by a request
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MarkedRead {
+    pub marked: usize,
+}
+
+/// Feeds the notification inbox from item events
+///
+/// Each change notifies the item's owner and the principals it was shared
+/// with through grants, except whoever made the change; changes to drafts
+/// only notify those granted write access. Role grants are not
+/// expanded, since a role's members are not known here. Redelivered events
+/// do not notify anyone twice.
+pub struct InboxPublisher {
+    notifications: Arc<dyn NotificationRepository>,
+    access: Arc<dyn AccessRepository>,
+}
+
+impl InboxPublisher {
+    pub fn new(
+        notifications: Arc<dyn NotificationRepository>,
+        access: Arc<dyn AccessRepository>,
+    ) -> Self {
+        Self {
+            notifications,
+            access,
+        }
+    }
+
+    /// Who should hear about `event`, and why
+    async fn recipients(
+        &self,
+        event: &OutboxEvent,
+    ) -> Result<Vec<(String, NotificationReason)>, DatabaseError> {
+        let grants = match self.access.list_grants(&event.item_id).await {
+            Ok(grants) => grants,
+            Err(DatabaseError::NotFound) => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        let owner = event
+            .item
+            .owner_id
+            .clone()
+            .map(|owner| (owner, NotificationReason::Owner));
+        // Drafts are only seen by those who can edit them
+        let draft = event.item.is_draft();
+        let grantees = grants
+            .into_iter()
+            .filter(|grant| !draft || grant.permission.allows(Permission::Write))
+            .filter_map(|grant| match grant.grantee {
+                Grantee::Principal(principal) => Some((principal, NotificationReason::Grantee)),
+                Grantee::Role(_) => None,
+            });
+
+        let mut recipients: Vec<(String, NotificationReason)> = Vec::new();
+        for (recipient, reason) in owner.into_iter().chain(grantees) {
+            if event.actor.as_ref() != Some(&recipient)
+                && !recipients
+                    .iter()
+                    .any(|(existing, _)| *existing == recipient)
+            {
+                recipients.push((recipient, reason));
+            }
+        }
+        Ok(recipients)
+    }
+}
+
+#[async_trait]
+impl EventPublisher for InboxPublisher {
+    fn name(&self) -> &'static str {
+        "inbox"
+    }
+
+    async fn publish(&self, event: &OutboxEvent) -> Result<(), String> {
+        let recipients = self.recipients(event).await.map_err(|e| e.to_string())?;
+        if recipients.is_empty() {
+            return Ok(());
+        }
+        let notifications = recipients
+            .into_iter()
+            .map(|(recipient, reason)| Notification {
+                id: Uuid::new_v4().to_string(),
+                recipient,
+                event_id: event.id.clone(),
+                event_type: event.event_type,
+                item_id: event.item_id.clone(),
+                item_name: event.item.name.clone(),
+                actor: event.actor.clone(),
+                reason,
+                saved_search_id: None,
+                change_request_id: None,
+                created_at: event.occurred_at,
+                read_at: None,
+                tenant_id: event.item.tenant_id.clone(),
+            })
+            .collect();
+        self.notifications
+            .add(notifications)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    async fn health_check(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{InMemoryAccessRepository, InMemoryNotificationRepository},
+        models::Item,
+    };
+
+    fn event(actor: &str) -> OutboxEvent {
+        let item = Item {
+            id: "item-1".to_string(),
+            name: "Widget".to_string(),
+            slug: "widget".to_string(),
+            external_id: None,
+            source: None,
+            description: None,
+            metadata: None,
+            custom_fields: Default::default(),
+            owner_id: Some("alice".to_string()),
+            tenant_id: None,
+            status: Default::default(),
+            publish_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            version: 2,
+        };
+        let mut event = OutboxEvent::new(1, ItemEventType::Updated, item);
+        event.actor = Some(actor.to_string());
+        event
+    }
+
+    #[tokio::test]
+    async fn test_changes_notify_everyone_but_the_actor() {
+        let notifications = Arc::new(InMemoryNotificationRepository::new());
+        let access = Arc::new(InMemoryAccessRepository::new());
+        for grantee in [
+            Grantee::Principal("bob".to_string()),
+            Grantee::Principal("carol".to_string()),
+            Grantee::Role("editors".to_string()),
+        ] {
+            access
+                .grant("item-1", grantee, Permission::Write, None)
+                .await
+                .unwrap();
+        }
+        let inbox = InboxPublisher::new(notifications.clone(), access);
+
+        let event = event("bob");
+        inbox.publish(&event).await.unwrap();
+        // A redelivery changes nothing
+        inbox.publish(&event).await.unwrap();
+
+        let (alice, _) = notifications.list("alice", false, 10, 0).await.unwrap();
+        assert_eq!(alice.len(), 1);
+        assert_eq!(alice[0].reason, NotificationReason::Owner);
+        assert_eq!(alice[0].actor.as_deref(), Some("bob"));
+        let (carol, _) = notifications.list("carol", false, 10, 0).await.unwrap();
+        assert_eq!(carol[0].reason, NotificationReason::Grantee);
+        assert_eq!(notifications.unread_count("bob").await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,138 @@
+use axum::{
+    body::{Body, Bytes},
+    http::{header::CONTENT_TYPE, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use futures_util::{stream, StreamExt};
+use serde::Serialize;
+
+/// Typical serialized size of one item, used to size buffers up front
+pub const ITEM_SIZE_HINT: usize = 320;
+
+/// Responses with more array elements than this are streamed in chunks
+/// instead of being serialized into one buffer
+pub const STREAM_THRESHOLD: usize = 50;
+
+/// Array elements serialized per streamed chunk
+const STREAM_CHUNK_LEN: usize = 16;
+
+/// Serialize `value` into a buffer allocated once with `capacity` bytes
+pub fn to_vec_with_capacity<T: Serialize + ?Sized>(
+    value: &T,
+    capacity: usize,
+) -> serde_json::Result<Vec<u8>> {
+    let mut buffer = Vec::with_capacity(capacity);
+    serde_json::to_writer(&mut buffer, value)?;
+    Ok(buffer)
+}
+
+/// JSON response serialized into a pre-sized buffer
+///
+/// `axum::Json` starts from a small buffer and grows it by doubling, which
+/// copies a large body several times.
+pub struct SizedJson<T> {
+    value: T,
+    capacity: usize,
+}
+
+impl<T> SizedJson<T> {
+    pub fn new(value: T, capacity: usize) -> Self {
+        Self { value, capacity }
+    }
+}
+
+impl<T: Serialize> IntoResponse for SizedJson<T> {
+    fn into_response(self) -> Response {
+        match to_vec_with_capacity(&self.value, self.capacity) {
+            Ok(body) => json_response(Body::from(body)),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    }
+}
+
+/// Stream the JSON object `{"<field>": [elements...], <fields of trailer>}`
+///
+/// Elements are serialized lazily, a chunk at a time, as the client reads the
+/// body, so the full document is never held in memory. `trailer` must
+/// serialize to a JSON object.
+pub fn stream_object_with_array<T, R>(field: &str, elements: Vec<T>, trailer: &R) -> Response
+where
+    T: Serialize + Send + Sync + 'static,
+    R: Serialize,
+{
+    let mut prefix = b"{".to_vec();
+    // Writing a string cannot fail
+    serde_json::to_writer(&mut prefix, field).ok();
+    prefix.extend_from_slice(b":[");
+
+    let suffix = match serde_json::to_vec(trailer) {
+        Ok(trailer) if trailer.len() > 2 => [b"],".as_slice(), &trailer[1..]].concat(),
+        Ok(_) => b"]}".to_vec(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+
+    let chunks = elements.len().div_ceil(STREAM_CHUNK_LEN);
+    let body = stream::iter(0..chunks).map(move |chunk| {
+        let start = chunk * STREAM_CHUNK_LEN;
+        let end = (start + STREAM_CHUNK_LEN).min(elements.len());
+        let mut buffer = Vec::with_capacity((end - start) * ITEM_SIZE_HINT);
+        for (index, element) in elements[start..end].iter().enumerate() {
+            if start + index > 0 {
+                buffer.push(b',');
+            }
+            serde_json::to_writer(&mut buffer, element)?;
+        }
+        Ok::<_, serde_json::Error>(Bytes::from(buffer))
+    });
+
+    let body = stream::once(async { Ok(Bytes::from(prefix)) })
+        .chain(body)
+        .chain(stream::once(async { Ok(Bytes::from(suffix)) }));
+    json_response(Body::from_stream(body))
+}
+
+fn json_response(body: Body) -> Response {
+    let mut response = Response::new(body);
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    #[derive(Serialize)]
+    struct Trailer {
+        total: usize,
+    }
+
+    async fn body_json(response: Response) -> Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_streamed_object_matches_buffered() {
+        for len in [0, 1, STREAM_CHUNK_LEN, STREAM_CHUNK_LEN * 3 + 1] {
+            let elements: Vec<usize> = (0..len).collect();
+            let response =
+                stream_object_with_array("items", elements.clone(), &Trailer { total: 7 });
+            assert_eq!(body_json(response).await, json!({ "items": elements, "total": 7 }));
+        }
+
+        let response = stream_object_with_array("items", vec![1], &json!({}));
+        assert_eq!(body_json(response).await, json!({ "items": [1] }));
+    }
+
+    #[tokio::test]
+    async fn test_sized_json() {
+        let response = SizedJson::new(json!({ "a": 1 }), 64).into_response();
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+        assert_eq!(body_json(response).await, json!({ "a": 1 }));
+    }
+}
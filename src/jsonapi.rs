@@ -0,0 +1,310 @@
+use axum::{
+    extract::FromRequestParts,
+    http::{
+        header::{ACCEPT, CONTENT_TYPE},
+        request::Parts,
+        HeaderMap, HeaderValue,
+    },
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::{collections::BTreeMap, convert::Infallible};
+
+use crate::{
+    handlers::{ListQuery, ListResponse},
+    links::{item_links, list_href, list_links, Links},
+    models::Item,
+};
+
+/// Media type of JSON:API documents
+pub const JSON_API: &str = "application/vnd.api+json";
+
+/// Resource type of items
+const ITEM_TYPE: &str = "items";
+
+/// Resource type of the principals items are owned by
+const PRINCIPAL_TYPE: &str = "principals";
+
+/// Whether `headers` ask for JSON:API responses
+pub fn accepts_json_api(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(is_json_api)
+}
+
+/// Whether the request body is a JSON:API document
+pub fn sends_json_api(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(is_json_api)
+}
+
+fn is_json_api(media_type: &str) -> bool {
+    media_type
+        .split(';')
+        .next()
+        .is_some_and(|essence| essence.trim().eq_ignore_ascii_case(JSON_API))
+}
+
+/// Whether a request negotiated the JSON:API representation, by listing
+/// `application/vnd.api+json` in `Accept`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JsonApi(pub bool);
+
+impl<S> FromRequestParts<S> for JsonApi
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self(accepts_json_api(&parts.headers)))
+    }
+}
+
+/// Body of a JSON:API request, as in `{"data": {"type": "items", "attributes": {...}}}`
+#[derive(Debug, Deserialize)]
+pub struct RequestDocument<T> {
+    pub data: RequestResource<T>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RequestResource<T> {
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    pub attributes: T,
+}
+
+/// A JSON:API document
+#[derive(Debug, Serialize)]
+pub struct Document<D> {
+    pub data: D,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub links: BTreeMap<&'static str, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
+}
+
+/// An item as a JSON:API resource object
+#[derive(Debug, Serialize)]
+pub struct Resource {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub id: String,
+    pub attributes: Map<String, Value>,
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub relationships: Map<String, Value>,
+    pub links: BTreeMap<&'static str, String>,
+}
+
+impl Resource {
+    pub fn item(item: &Item) -> Self {
+        // Every other field is an attribute, so new fields show up without
+        // changes here
+        let mut attributes = match serde_json::to_value(item) {
+            Ok(Value::Object(attributes)) => attributes,
+            _ => Map::new(),
+        };
+        attributes.remove("id");
+        let mut relationships = Map::new();
+        if let Some(Value::String(owner)) = attributes.remove("owner_id") {
+            relationships.insert(
+                "owner".to_string(),
+                json!({ "data": { "type": PRINCIPAL_TYPE, "id": owner } }),
+            );
+        }
+
+        Self {
+            kind: ITEM_TYPE,
+            id: item.id.clone(),
+            attributes,
+            relationships,
+            links: hrefs(item_links(item)),
+        }
+    }
+}
+
+fn hrefs(links: Links) -> BTreeMap<&'static str, String> {
+    links
+        .into_iter()
+        .map(|(rel, link)| (rel, link.href))
+        .collect()
+}
+
+impl<D: Serialize> IntoResponse for Document<D> {
+    fn into_response(self) -> Response {
+        let mut response = Json(self).into_response();
+        response
+            .headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static(JSON_API));
+        response
+    }
+}
+
+/// Document holding one item
+pub fn item_document(item: &Item) -> Document<Resource> {
+    let resource = Resource::item(item);
+    let links = resource
+        .links
+        .get("self")
+        .map(|href| BTreeMap::from([("self", href.clone())]))
+        .unwrap_or_default();
+    Document {
+        data: resource,
+        links,
+        meta: None,
+    }
+}
+
+/// Document holding a page of items, with the page's links and totals
+pub fn list_document(query: &ListQuery, list: &ListResponse) -> Document<Vec<Resource>> {
+    let mut links = hrefs(list_links(query, list.next_cursor.as_deref()));
+    // Creation is an action, not a page
+    links.remove("create");
+    links.insert("first", list_href(query, None, 0));
+    Document {
+        data: list
+            .items
+            .iter()
+            .map(|item| {
+                let mut resource = Resource::item(&item.item);
+                // `$select` makes for sparse attributes
+                if let Some(select) = &item.select {
+                    resource
+                        .attributes
+                        .retain(|name, _| select.iter().any(|field| field.name() == name));
+                }
+                resource
+            })
+            .collect(),
+        links,
+        meta: Some(match list.total {
+            Some(total) => json!({ "total": total, "limit": list.limit, "offset": list.offset }),
+            None => json!({ "limit": list.limit, "offset": list.offset }),
+        }),
+    }
+}
+
+/// Rewrite an error body into a JSON:API error document
+///
+/// Field validation errors become one error object each, pointing at the
+/// attribute; anything else becomes a single error object.
+pub fn error_document(status: u16, error: &Value) -> Value {
+    let code = error.get("error").cloned().unwrap_or(Value::Null);
+    let meta = error
+        .get("request_id")
+        .map(|id| json!({ "request_id": id }));
+    let object = |detail: &Value, pointer: Option<String>| {
+        let mut object = json!({
+            "status": status.to_string(),
+            "code": code,
+            "title": axum::http::StatusCode::from_u16(status)
+                .ok()
+                .and_then(|status| status.canonical_reason()),
+            "detail": detail,
+        });
+        if let Some(pointer) = pointer {
+            object["source"] = json!({ "pointer": pointer });
+        }
+        if let Some(meta) = &meta {
+            object["meta"] = meta.clone();
+        }
+        object
+    };
+
+    let field_errors = error
+        .pointer("/details/validation_errors")
+        .and_then(Value::as_array)
+        .filter(|errors| !errors.is_empty());
+    let errors: Vec<Value> = match field_errors {
+        Some(field_errors) => field_errors
+            .iter()
+            .map(|field_error| {
+                let field = field_error["field"].as_str().unwrap_or_default();
+                object(&field_error["message"], Some(format!("/data/attributes/{field}")))
+            })
+            .collect(),
+        None => vec![object(error.get("message").unwrap_or(&Value::Null), None)],
+    };
+    json!({ "errors": errors })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(owner: Option<&str>) -> Item {
+        Item {
+            id: "item-1".to_string(),
+            name: "Item".to_string(),
+            slug: "item".to_string(),
+            external_id: None,
+            source: None,
+            description: None,
+            metadata: None,
+            custom_fields: Default::default(),
+            owner_id: owner.map(str::to_string),
+            tenant_id: None,
+            status: Default::default(),
+            publish_at: None,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            version: 1,
+        }
+    }
+
+    #[test]
+    fn test_items_become_resource_objects() {
+        let document = serde_json::to_value(item_document(&item(Some("user-1")))).unwrap();
+        let data = &document["data"];
+        assert_eq!(data["type"], "items");
+        assert_eq!(data["id"], "item-1");
+        assert_eq!(data["attributes"]["name"], "Item");
+        assert!(data["attributes"].get("id").is_none());
+        assert!(data["attributes"].get("owner_id").is_none());
+        assert_eq!(data["relationships"]["owner"]["data"]["id"], "user-1");
+        assert_eq!(document["links"]["self"], "/api/v1/items/item-1");
+
+        let anonymous = serde_json::to_value(Resource::item(&item(None))).unwrap();
+        assert!(anonymous.get("relationships").is_none());
+    }
+
+    #[test]
+    fn test_field_errors_point_at_attributes() {
+        let error = json!({
+            "error": "VALIDATION_ERROR",
+            "message": "Validation failed",
+            "details": { "validation_errors": [
+                { "field": "name", "message": "Name is required" },
+            ] },
+            "request_id": "req-1",
+        });
+        let document = error_document(422, &error);
+        let object = &document["errors"][0];
+        assert_eq!(object["status"], "422");
+        assert_eq!(object["code"], "VALIDATION_ERROR");
+        assert_eq!(object["source"]["pointer"], "/data/attributes/name");
+        assert_eq!(object["meta"]["request_id"], "req-1");
+
+        let document = error_document(404, &json!({ "error": "NOT_FOUND", "message": "Gone" }));
+        assert_eq!(document["errors"][0]["detail"], "Gone");
+        assert_eq!(document["errors"][0]["title"], "Not Found");
+    }
+
+    #[test]
+    fn test_media_type_is_matched_without_parameters() {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_static("text/html, application/vnd.api+json"));
+        assert!(accepts_json_api(&headers));
+        headers.insert(ACCEPT, HeaderValue::from_static("application/json"));
+        assert!(!accepts_json_api(&headers));
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/vnd.api+json; ext=x"));
+        assert!(sends_json_api(&headers));
+    }
+}
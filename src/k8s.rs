@@ -0,0 +1,280 @@
+use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat, Utc};
+use reqwest::{Certificate, Client, StatusCode};
+use serde_json::{json, Value};
+use std::{fs, path::PathBuf, time::Duration};
+use tracing::{field, info_span, Span};
+
+use crate::{
+    config::{KubernetesConfig, LeaderElectionConfig},
+    leadership::Lease,
+};
+
+/// Credentials Kubernetes mounts into every pod with a service account
+const SERVICE_ACCOUNT: &str = "/var/run/secrets/kubernetes.io/serviceaccount";
+
+/// Span naming the pod, namespace and node this replica runs in, for request
+/// spans to nest under, or no span outside Kubernetes
+pub fn pod_span(config: &KubernetesConfig) -> Span {
+    let fields = [
+        ("name", &config.pod_name),
+        ("namespace", &config.pod_namespace),
+        ("node", &config.node_name),
+    ];
+    if fields.iter().all(|(_, value)| value.is_none()) {
+        return Span::none();
+    }
+    let span =
+        info_span!("pod", name = field::Empty, namespace = field::Empty, node = field::Empty);
+    for (name, value) in fields {
+        if let Some(value) = value {
+            span.record(name, value.as_str());
+        }
+    }
+    span
+}
+
+/// A `coordination.k8s.io/v1` Lease, claimed through the API server with the
+/// pod's service account
+///
+/// The account needs `get`, `create` and `update` on `leases` in the pod's
+/// namespace. Updates carry the `resourceVersion` read, so when two replicas
+/// claim an expired lease at once the API server accepts only one of them.
+pub struct KubernetesLease {
+    client: Client,
+    /// The namespace's lease collection
+    url: String,
+    name: String,
+    namespace: String,
+    token: PathBuf,
+}
+
+impl KubernetesLease {
+    /// The configured lease in the pod's namespace, reached through the API
+    /// server Kubernetes announces to every pod
+    pub fn in_cluster(
+        config: &LeaderElectionConfig,
+        kubernetes: &KubernetesConfig,
+    ) -> Result<Self, String> {
+        let host = std::env::var("KUBERNETES_SERVICE_HOST")
+            .map_err(|_| "KUBERNETES_SERVICE_HOST is not set; not running in a pod?")?;
+        let port = std::env::var("KUBERNETES_SERVICE_PORT").unwrap_or_else(|_| "443".to_string());
+        let host = if host.contains(':') {
+            format!("[{host}]")
+        } else {
+            host
+        };
+        let account = PathBuf::from(SERVICE_ACCOUNT);
+        let namespace = match &kubernetes.pod_namespace {
+            Some(namespace) => namespace.clone(),
+            None => fs::read_to_string(account.join("namespace"))
+                .map_err(|e| format!("Cannot read the pod's namespace: {e}"))?
+                .trim()
+                .to_string(),
+        };
+        let ca = fs::read(account.join("ca.crt"))
+            .map_err(|e| format!("Cannot read the cluster CA certificate: {e}"))?;
+        let client = Client::builder()
+            .add_root_certificate(Certificate::from_pem(&ca).map_err(|e| e.to_string())?)
+            .timeout(Duration::from_secs(5))
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(Self::new(
+            client,
+            &format!("https://{host}:{port}"),
+            &namespace,
+            &config.lease_name,
+            account.join("token"),
+        ))
+    }
+
+    pub fn new(client: Client, api: &str, namespace: &str, name: &str, token: PathBuf) -> Self {
+        Self {
+            client,
+            url: format!(
+                "{}/apis/coordination.k8s.io/v1/namespaces/{namespace}/leases",
+                api.trim_end_matches('/')
+            ),
+            name: name.to_string(),
+            namespace: namespace.to_string(),
+            token,
+        }
+    }
+
+    /// The service account token, read for every call since the kubelet
+    /// rotates it
+    fn token(&self) -> Result<String, String> {
+        fs::read_to_string(&self.token)
+            .map(|token| token.trim().to_string())
+            .map_err(|e| format!("Cannot read the service account token: {e}"))
+    }
+
+    async fn get(&self) -> Result<Option<Value>, String> {
+        let response = self
+            .client
+            .get(format!("{}/{}", self.url, self.name))
+            .bearer_auth(self.token()?)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => {
+                response.json().await.map(Some).map_err(|e| e.to_string())
+            }
+            status => Err(format!("Reading lease {} failed: {status}", self.name)),
+        }
+    }
+
+    /// Create or replace the lease, returning false if another replica
+    /// changed it first
+    async fn write(&self, lease: &Value, create: bool) -> Result<bool, String> {
+        let request = if create {
+            self.client.post(&self.url)
+        } else {
+            self.client.put(format!("{}/{}", self.url, self.name))
+        };
+        let response = request
+            .bearer_auth(self.token()?)
+            .json(lease)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        match response.status() {
+            StatusCode::CONFLICT => Ok(false),
+            status if status.is_success() => Ok(true),
+            status => Err(format!("Writing lease {} failed: {status}", self.name)),
+        }
+    }
+}
+
+#[async_trait]
+impl Lease for KubernetesLease {
+    async fn try_acquire(&self, holder: &str, duration: Duration) -> Result<bool, String> {
+        match self.get().await? {
+            None => {
+                let lease = json!({
+                    "apiVersion": "coordination.k8s.io/v1",
+                    "kind": "Lease",
+                    "metadata": { "name": self.name, "namespace": self.namespace },
+                    "spec": { "leaseTransitions": 0 },
+                });
+                let lease = claim(&lease, holder, duration, Utc::now()).unwrap_or(lease);
+                self.write(&lease, true).await
+            }
+            Some(lease) => match claim(&lease, holder, duration, Utc::now()) {
+                Some(lease) => self.write(&lease, false).await,
+                None => Ok(false),
+            },
+        }
+    }
+
+    async fn release(&self, holder: &str) -> Result<(), String> {
+        let Some(mut lease) = self.get().await? else {
+            return Ok(());
+        };
+        if lease["spec"]["holderIdentity"].as_str() != Some(holder) {
+            return Ok(());
+        }
+        // Expired a second after release, as client-go leaves a lease
+        lease["spec"]["holderIdentity"] = Value::Null;
+        lease["spec"]["leaseDurationSeconds"] = json!(1);
+        lease["spec"]["renewTime"] = json!(micro_time(Utc::now()));
+        self.write(&lease, false).await.map(|_| ())
+    }
+}
+
+/// `lease` updated to be held by `holder` for `duration` from `now`, or
+/// `None` if another holder's claim has not expired
+fn claim(lease: &Value, holder: &str, duration: Duration, now: DateTime<Utc>) -> Option<Value> {
+    let spec = &lease["spec"];
+    let current = spec["holderIdentity"]
+        .as_str()
+        .filter(|current| !current.is_empty());
+    let expires = spec["renewTime"]
+        .as_str()
+        .and_then(|renewed| DateTime::parse_from_rfc3339(renewed).ok())
+        .map(|renewed| {
+            renewed.with_timezone(&Utc)
+                + chrono::Duration::seconds(spec["leaseDurationSeconds"].as_i64().unwrap_or(0))
+        });
+    let mut lease = lease.clone();
+    let time = micro_time(now);
+    match current {
+        Some(current) if current == holder => {}
+        Some(_) if expires.is_some_and(|expires| expires > now) => return None,
+        previous => {
+            lease["spec"]["acquireTime"] = json!(time);
+            if previous.is_some() {
+                let transitions = spec["leaseTransitions"].as_i64().unwrap_or(0);
+                lease["spec"]["leaseTransitions"] = json!(transitions + 1);
+            }
+        }
+    }
+    lease["spec"]["holderIdentity"] = json!(holder);
+    lease["spec"]["leaseDurationSeconds"] = json!(duration.as_secs());
+    lease["spec"]["renewTime"] = json!(time);
+    Some(lease)
+}
+
+/// Kubernetes `MicroTime`: RFC 3339 in UTC with microseconds
+fn micro_time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn held_by(holder: &str, renewed: DateTime<Utc>) -> Value {
+        json!({
+            "metadata": { "name": "ferrous", "resourceVersion": "42" },
+            "spec": {
+                "holderIdentity": holder,
+                "leaseDurationSeconds": 15,
+                "acquireTime": micro_time(renewed),
+                "renewTime": micro_time(renewed),
+                "leaseTransitions": 3,
+            },
+        })
+    }
+
+    #[test]
+    fn test_claims_respect_unexpired_leases() {
+        let now = Utc::now();
+        let duration = Duration::from_secs(15);
+        let lease = held_by("pod-a", now - chrono::Duration::seconds(10));
+
+        assert!(claim(&lease, "pod-b", duration, now).is_none());
+
+        // The holder renews without a transition
+        let renewed = claim(&lease, "pod-a", duration, now).unwrap();
+        assert_eq!(renewed["spec"]["renewTime"], json!(micro_time(now)));
+        assert_eq!(renewed["spec"]["acquireTime"], lease["spec"]["acquireTime"]);
+        assert_eq!(renewed["spec"]["leaseTransitions"], json!(3));
+        assert_eq!(renewed["metadata"]["resourceVersion"], json!("42"));
+    }
+
+    #[test]
+    fn test_expired_and_released_leases_can_be_taken() {
+        let now = Utc::now();
+        let duration = Duration::from_secs(15);
+        let lease = held_by("pod-a", now - chrono::Duration::seconds(20));
+
+        let taken = claim(&lease, "pod-b", duration, now).unwrap();
+        assert_eq!(taken["spec"]["holderIdentity"], json!("pod-b"));
+        assert_eq!(taken["spec"]["acquireTime"], json!(micro_time(now)));
+        assert_eq!(taken["spec"]["leaseTransitions"], json!(4));
+
+        let mut released = held_by("pod-a", now);
+        released["spec"]["holderIdentity"] = Value::Null;
+        let taken = claim(&released, "pod-b", duration, now).unwrap();
+        assert_eq!(taken["spec"]["holderIdentity"], json!("pod-b"));
+        assert_eq!(taken["spec"]["leaseTransitions"], json!(3));
+    }
+
+    #[test]
+    fn test_pod_span_is_only_created_in_a_pod() {
+        assert!(pod_span(&KubernetesConfig::default()).is_none());
+    }
+}
@@ -0,0 +1,296 @@
+use async_trait::async_trait;
+use std::{
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+use tokio::{sync::watch, task::JoinHandle, time::Instant};
+use tracing::{info, warn};
+
+use crate::{db::LeaseRepository, metrics::LEADER_ELECTION_LEADER};
+
+/// A lock with an expiry that replicas compete for; whoever holds it leads
+#[async_trait]
+pub trait Lease: Send + Sync {
+    /// Take the lease for `holder` if it is free or has expired, or extend it
+    /// if `holder` already has it, returning whether `holder` now holds it
+    async fn try_acquire(&self, holder: &str, duration: Duration) -> Result<bool, String>;
+
+    /// Give the lease up, if `holder` has it, so another replica need not wait
+    /// for it to expire
+    async fn release(&self, holder: &str) -> Result<(), String>;
+}
+
+/// A lease kept as a row in the configured database, for replicas sharing it
+/// outside Kubernetes
+pub struct DatabaseLease {
+    repo: Arc<dyn LeaseRepository>,
+    name: String,
+}
+
+impl DatabaseLease {
+    pub fn new(repo: Arc<dyn LeaseRepository>, name: impl Into<String>) -> Self {
+        Self {
+            repo,
+            name: name.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl Lease for DatabaseLease {
+    async fn try_acquire(&self, holder: &str, duration: Duration) -> Result<bool, String> {
+        self.repo
+            .try_acquire(&self.name, holder, duration)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    async fn release(&self, holder: &str) -> Result<(), String> {
+        self.repo
+            .release(&self.name, holder)
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Name a replica competes for leases under: its pod name in Kubernetes, or
+/// its host name and a suffix unique to the process elsewhere
+pub fn holder_identity(pod_name: Option<&str>) -> String {
+    if let Some(pod_name) = pod_name {
+        return pod_name.to_string();
+    }
+    let host = sysinfo::System::host_name().unwrap_or_else(|| "ferrous".to_string());
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    format!("{host}-{}", &suffix[..8])
+}
+
+/// Whether this replica currently leads, for jobs that must run on only one
+#[derive(Clone)]
+pub struct Leadership {
+    leader: watch::Receiver<bool>,
+}
+
+impl Leadership {
+    /// Leadership of a replica that runs alone
+    pub fn always() -> Self {
+        let (_, leader) = watch::channel(true);
+        Self { leader }
+    }
+
+    pub fn is_leader(&self) -> bool {
+        *self.leader.borrow()
+    }
+}
+
+impl Default for Leadership {
+    fn default() -> Self {
+        Self::always()
+    }
+}
+
+/// Keeps competing for a lease, and renews it while this replica holds it
+///
+/// The lease is renewed every third of its duration. A replica that cannot
+/// reach the lease keeps leading until two thirds of the duration have passed
+/// since its last renewal, then steps down well before another replica could
+/// take over.
+pub struct LeaderElector {
+    lease: Arc<dyn Lease>,
+    holder: String,
+    duration: Duration,
+    leader: watch::Sender<bool>,
+    renewed: Mutex<Option<Instant>>,
+}
+
+impl LeaderElector {
+    pub fn new(lease: Arc<dyn Lease>, holder: impl Into<String>, duration: Duration) -> Self {
+        let (leader, _) = watch::channel(false);
+        Self {
+            lease,
+            holder: holder.into(),
+            duration,
+            leader,
+            renewed: Mutex::new(None),
+        }
+    }
+
+    /// Identity this replica holds the lease under
+    pub fn holder(&self) -> &str {
+        &self.holder
+    }
+
+    pub fn leadership(&self) -> Leadership {
+        Leadership {
+            leader: self.leader.subscribe(),
+        }
+    }
+
+    /// Compete for the lease until aborted
+    pub fn spawn(self: Arc<Self>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.duration / 3);
+            loop {
+                interval.tick().await;
+                self.campaign().await;
+            }
+        })
+    }
+
+    /// Try once to take or renew the lease, returning whether this replica leads
+    pub async fn campaign(&self) -> bool {
+        let leading = match self.lease.try_acquire(&self.holder, self.duration).await {
+            Ok(held) => {
+                *self.renewed() = held.then(Instant::now);
+                held
+            }
+            Err(e) => {
+                warn!(holder = %self.holder, "Failed to renew the leader lease: {}", e);
+                self.renewed()
+                    .is_some_and(|renewed| renewed.elapsed() < self.duration * 2 / 3)
+            }
+        };
+        self.set_leader(leading);
+        leading
+    }
+
+    /// Stop leading and release the lease
+    pub async fn resign(&self) {
+        let leading = self.leader.send_replace(false);
+        LEADER_ELECTION_LEADER.set(0);
+        *self.renewed() = None;
+        if leading {
+            if let Err(e) = self.lease.release(&self.holder).await {
+                warn!(holder = %self.holder, "Failed to release the leader lease: {}", e);
+            }
+        }
+    }
+
+    /// When the lease was last renewed; a poisoned lock still holds a usable
+    /// time, so it is recovered rather than stopping the election loop
+    fn renewed(&self) -> MutexGuard<'_, Option<Instant>> {
+        self.renewed.lock().unwrap_or_else(|poisoned| {
+            warn!(holder = %self.holder, "Recovered the poisoned leader lease renewal lock");
+            self.renewed.clear_poison();
+            poisoned.into_inner()
+        })
+    }
+
+    fn set_leader(&self, leading: bool) {
+        if self.leader.send_replace(leading) != leading {
+            LEADER_ELECTION_LEADER.set(i64::from(leading));
+            if leading {
+                info!(holder = %self.holder, "Became the leader");
+            } else {
+                info!(holder = %self.holder, "Stopped being the leader");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    /// A lease held in memory, whose clock the test controls
+    #[derive(Default)]
+    struct TestLease {
+        held: Mutex<Option<(String, Instant)>>,
+        unreachable: AtomicBool,
+    }
+
+    #[async_trait]
+    impl Lease for TestLease {
+        async fn try_acquire(&self, holder: &str, duration: Duration) -> Result<bool, String> {
+            if self.unreachable.load(Ordering::SeqCst) {
+                return Err("connection refused".to_string());
+            }
+            let mut held = self.held.lock().unwrap();
+            let now = Instant::now();
+            match &*held {
+                Some((current, expires)) if current != holder && *expires > now => Ok(false),
+                _ => {
+                    *held = Some((holder.to_string(), now + duration));
+                    Ok(true)
+                }
+            }
+        }
+
+        async fn release(&self, holder: &str) -> Result<(), String> {
+            let mut held = self.held.lock().unwrap();
+            if held.as_ref().is_some_and(|(current, _)| current == holder) {
+                *held = None;
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_one_replica_leads_until_it_resigns() {
+        let lease = Arc::new(TestLease::default());
+        let duration = Duration::from_secs(15);
+        let first = LeaderElector::new(lease.clone(), "pod-a", duration);
+        let second = LeaderElector::new(lease.clone(), "pod-b", duration);
+        let (a, b) = (first.leadership(), second.leadership());
+
+        assert!(first.campaign().await);
+        assert!(!second.campaign().await);
+        assert!(a.is_leader() && !b.is_leader());
+
+        // Renewing keeps the lease past its original expiry
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert!(first.campaign().await);
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert!(!second.campaign().await);
+
+        first.resign().await;
+        assert!(!a.is_leader());
+        assert!(second.campaign().await);
+        assert!(b.is_leader());
+    }
+
+    #[tokio::test]
+    async fn test_replicas_sharing_a_database_elect_one_leader() {
+        use crate::db::InMemoryLeaseRepository;
+
+        let repo: Arc<dyn LeaseRepository> = Arc::new(InMemoryLeaseRepository::new());
+        let duration = Duration::from_secs(15);
+        let electors: Vec<_> = ["host-a", "host-b", "host-c"]
+            .into_iter()
+            .map(|holder| {
+                let lease = Arc::new(DatabaseLease::new(repo.clone(), "scheduler"));
+                LeaderElector::new(lease, holder, duration)
+            })
+            .collect();
+
+        let mut leading = Vec::new();
+        for elector in &electors {
+            leading.push(elector.campaign().await);
+        }
+        assert_eq!(leading, [true, false, false]);
+
+        electors[0].resign().await;
+        assert!(electors[2].campaign().await);
+        assert!(!electors[1].campaign().await);
+    }
+
+    #[test]
+    fn test_holder_identity_is_unique_outside_kubernetes() {
+        assert_eq!(holder_identity(Some("ferrous-7d9f")), "ferrous-7d9f");
+        assert_ne!(holder_identity(None), holder_identity(None));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_leader_steps_down_before_an_unreachable_lease_expires() {
+        let lease = Arc::new(TestLease::default());
+        let elector = LeaderElector::new(lease.clone(), "pod-a", Duration::from_secs(15));
+        assert!(elector.campaign().await);
+
+        lease.unreachable.store(true, Ordering::SeqCst);
+        tokio::time::advance(Duration::from_secs(5)).await;
+        assert!(elector.campaign().await);
+        tokio::time::advance(Duration::from_secs(5)).await;
+        assert!(!elector.campaign().await);
+        assert!(!elector.leadership().is_leader());
+    }
+}
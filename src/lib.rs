@@ -1,11 +1,74 @@
+pub mod access_log;
+pub mod analytics;
+pub mod approvals;
+pub mod attachments;
+pub mod auth;
+pub mod backup;
+pub mod build_info;
+pub mod cdc;
+pub mod checkout;
+pub mod client;
+pub mod clock;
+pub mod collections;
+pub mod comments;
+pub mod concurrency;
 pub mod config;
+pub mod conflicts;
+pub mod context;
+pub mod custom_fields;
 pub mod db;
+pub mod dead_letters;
+pub mod deprecation;
+pub mod diagnostics;
+pub mod discovery;
+pub mod dual_write;
+pub mod duplicates;
 pub mod error;
+pub mod events;
 pub mod handlers;
+pub mod health;
+pub mod hedging;
+pub mod hooks;
+pub mod http_client;
+pub mod inbox;
+pub mod json;
+pub mod jsonapi;
+pub mod k8s;
+pub mod leadership;
+pub mod links;
+pub mod locks;
+pub mod log_filter;
+pub mod merge;
 pub mod metrics;
 pub mod middleware;
+pub mod migration;
 pub mod models;
+pub mod notifications;
+pub mod odata;
 pub mod openapi;
+pub mod outbound_auth;
+pub mod pagination;
+pub mod policy;
+pub mod privacy;
+pub mod profiling;
+pub mod publishing;
+pub mod query;
+pub mod retention;
 pub mod routes;
+pub mod saved_searches;
+pub mod sbom;
+pub mod scanning;
+pub mod schemas;
+pub mod shadow;
+pub mod sharing;
+pub mod shutdown;
+pub mod slow_log;
+pub mod slug;
+pub mod sql;
 pub mod state;
+pub mod stats;
+pub mod system;
+pub mod tenancy;
+pub mod thumbnails;
 pub mod validation;
+pub mod views;
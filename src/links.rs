@@ -0,0 +1,309 @@
+use axum::{
+    extract::FromRequestParts,
+    http::{header::ACCEPT, request::Parts, HeaderMap},
+};
+use once_cell::sync::Lazy;
+use serde::{ser::Error as _, Serialize, Serializer};
+use serde_json::{Map, Value};
+use std::{borrow::Borrow, collections::BTreeMap, convert::Infallible};
+use utoipa::{OpenApi, ToSchema};
+
+use crate::{
+    handlers::ListQuery, models::Item, openapi::ApiDoc, query::Selection, state::SharedState,
+};
+
+/// `Accept` profile asking for `_links`, as in `application/json; profile="links"`
+pub const LINKS_PROFILE: &str = "links";
+
+const ITEMS_PATH: &str = "/api/v1/items";
+const ITEM_PATH: &str = "/api/v1/items/{id}";
+
+/// A link to a related resource
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct Link {
+    pub href: String,
+    #[schema(example = "GET")]
+    pub method: &'static str,
+}
+
+/// Links by relation, serialized as `_links`
+pub type Links = BTreeMap<&'static str, Link>;
+
+/// A relation and the documented route it links to
+struct Route {
+    rel: &'static str,
+    method: &'static str,
+    template: String,
+}
+
+/// Routes an item links to, read from the OpenAPI document so that only
+/// routes this build serves are linked
+static ITEM_ROUTES: Lazy<Vec<Route>> = Lazy::new(|| {
+    let spec = ApiDoc::openapi();
+    let mut routes = Vec::new();
+    let mut add = |rel, method, template: String, present: bool| {
+        if present {
+            routes.push(Route {
+                rel,
+                method,
+                template,
+            });
+        }
+    };
+    if let Some(item) = spec.paths.paths.get(ITEM_PATH) {
+        add("self", "GET", ITEM_PATH.to_string(), item.get.is_some());
+        add("update", "PUT", ITEM_PATH.to_string(), item.put.is_some());
+        add("delete", "DELETE", ITEM_PATH.to_string(), item.delete.is_some());
+    }
+    for rel in ["revisions", "attachments", "permissions"] {
+        let template = format!("{ITEM_PATH}/{rel}");
+        let present = spec
+            .paths
+            .paths
+            .get(&template)
+            .is_some_and(|path| path.get.is_some());
+        add(rel, "GET", template, present);
+    }
+    routes
+});
+
+/// Whether items can be created, per the OpenAPI document
+static CAN_CREATE: Lazy<bool> = Lazy::new(|| {
+    ApiDoc::openapi()
+        .paths
+        .paths
+        .get(ITEMS_PATH)
+        .is_some_and(|path| path.post.is_some())
+});
+
+/// Whether a request gets `_links` in its responses
+///
+/// Links are included when `LINKS_ENABLED` is set, or when the client asks
+/// for them with the `links` profile in `Accept`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hypermedia(pub bool);
+
+impl FromRequestParts<SharedState> for Hypermedia {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &SharedState,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(Self(state.config.links.enabled || accepts_links_profile(&parts.headers)))
+    }
+}
+
+impl Hypermedia {
+    /// `item` with its links, when they are wanted
+    pub fn item<T: Borrow<Item>>(self, item: T) -> Linked<T> {
+        let links = self.0.then(|| item_links(item.borrow()));
+        Linked {
+            item,
+            links,
+            select: None,
+        }
+    }
+
+    /// Links of a page of items, when they are wanted
+    pub fn list(self, query: &ListQuery, next_cursor: Option<&str>) -> Option<Links> {
+        self.0.then(|| list_links(query, next_cursor))
+    }
+}
+
+fn accepts_links_profile(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .flat_map(|range| range.split(';').skip(1))
+        .filter_map(|param| param.split_once('='))
+        .filter(|(name, _)| name.trim().eq_ignore_ascii_case("profile"))
+        .any(|(_, value)| {
+            value
+                .trim()
+                .trim_matches('"')
+                .split_whitespace()
+                .any(|profile| profile == LINKS_PROFILE)
+        })
+}
+
+/// Links of `item`
+pub fn item_links(item: &Item) -> Links {
+    ITEM_ROUTES
+        .iter()
+        .map(|route| {
+            let link = Link {
+                href: route.template.replace("{id}", &item.id),
+                method: route.method,
+            };
+            (route.rel, link)
+        })
+        .collect()
+}
+
+/// Link to the items page `query` asks for, at `cursor` or else `offset`
+pub fn list_href(query: &ListQuery, cursor: Option<&str>, offset: usize) -> String {
+    let mut href = format!("{ITEMS_PATH}?limit={}", query.page_limit());
+    match cursor {
+        Some(cursor) => href.push_str(&format!("&cursor={cursor}")),
+        None if offset > 0 => href.push_str(&format!("&offset={offset}")),
+        None => {}
+    }
+    if query.all {
+        href.push_str("&all=true");
+    }
+    if !query.include_total {
+        href.push_str("&include_total=false");
+    }
+    let options = [
+        ("external_id", &query.external_id),
+        ("source", &query.source),
+        ("$filter", &query.filter),
+        ("$orderby", &query.orderby),
+        ("$select", &query.select),
+    ];
+    for (name, value) in options {
+        if let Some(value) = value {
+            href.push_str(&format!("&{name}={}", encode_component(value)));
+        }
+    }
+    href
+}
+
+/// Percent-encode everything but unreserved characters
+fn encode_component(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char);
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+/// Links of a page of items: the page itself, the next one, and creation
+pub fn list_links(query: &ListQuery, next_cursor: Option<&str>) -> Links {
+    let mut links = Links::new();
+    let link = |href, method| Link { href, method };
+    let current = list_href(query, query.cursor.as_deref(), query.page_offset());
+    links.insert("self", link(current, "GET"));
+    if let Some(cursor) = next_cursor {
+        links.insert("next", link(list_href(query, Some(cursor), 0), "GET"));
+    }
+    if *CAN_CREATE {
+        links.insert("create", link(ITEMS_PATH.to_string(), "POST"));
+    }
+    links
+}
+
+/// A resource serialized with its `_links`, or as-is without them
+#[derive(Debug, Clone)]
+pub struct Linked<T> {
+    pub item: T,
+    pub links: Option<Links>,
+    /// Fields to serialize; every field when unset
+    pub select: Option<Selection>,
+}
+
+impl<T> Linked<T> {
+    /// The resource serialized with only the `select` fields, when set
+    pub fn selecting(mut self, select: Option<Selection>) -> Self {
+        self.select = select;
+        self
+    }
+}
+
+impl<T: Serialize> Serialize for Linked<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if let Some(select) = &self.select {
+            let mut fields = match serde_json::to_value(&self.item).map_err(S::Error::custom)? {
+                Value::Object(fields) => fields,
+                _ => Map::new(),
+            };
+            fields.retain(|name, _| select.iter().any(|field| field.name() == name));
+            if let Some(links) = &self.links {
+                let links = serde_json::to_value(links).map_err(S::Error::custom)?;
+                fields.insert("_links".to_string(), links);
+            }
+            return fields.serialize(serializer);
+        }
+
+        #[derive(Serialize)]
+        struct WithLinks<'a, T> {
+            #[serde(flatten)]
+            item: &'a T,
+            #[serde(rename = "_links")]
+            links: &'a Links,
+        }
+
+        match &self.links {
+            // Flattening is slower, so only pay for it when there are links
+            Some(links) => WithLinks {
+                item: &self.item,
+                links,
+            }
+            .serialize(serializer),
+            None => self.item.serialize(serializer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_links_profile_is_read_from_accept() {
+        let accept = |value: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(ACCEPT, HeaderValue::from_static(value));
+            accepts_links_profile(&headers)
+        };
+        assert!(accept("application/json; profile=\"links\""));
+        assert!(accept("text/html, application/json;profile=links"));
+        assert!(accept("application/json; profile=\"other links\""));
+        assert!(!accept("application/json"));
+        assert!(!accept("application/json; profile=\"hypermedia\""));
+    }
+
+    #[test]
+    fn test_item_links_follow_documented_routes() {
+        let item = Item {
+            id: "item-1".to_string(),
+            name: "Item".to_string(),
+            slug: "item".to_string(),
+            external_id: None,
+            source: None,
+            description: None,
+            metadata: None,
+            custom_fields: Default::default(),
+            owner_id: None,
+            tenant_id: None,
+            status: Default::default(),
+            publish_at: None,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            version: 1,
+        };
+        let links = item_links(&item);
+        assert_eq!(links["self"].href, "/api/v1/items/item-1");
+        assert_eq!(links["update"].method, "PUT");
+        assert_eq!(links["delete"].method, "DELETE");
+        assert_eq!(links["permissions"].href, "/api/v1/items/item-1/permissions");
+        assert_eq!(links["attachments"].href, "/api/v1/items/item-1/attachments");
+        // Not served by this build, so not linked
+        assert!(!links.contains_key("revisions"));
+
+        let json = serde_json::to_value(Hypermedia(true).item(&item)).unwrap();
+        assert_eq!(json["id"], "item-1");
+        assert_eq!(json["_links"]["self"]["method"], "GET");
+        let json = serde_json::to_value(Hypermedia(false).item(&item)).unwrap();
+        assert!(json.get("_links").is_none());
+    }
+}
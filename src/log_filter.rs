@@ -0,0 +1,196 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tracing::{info, warn};
+use tracing_subscriber::{reload, EnvFilter};
+use utoipa::ToSchema;
+
+/// How long a runtime filter lasts before it reverts, by default
+pub const DEFAULT_FILTER_TTL_SECONDS: u64 = 900;
+
+/// Longest a runtime filter may last before it reverts
+pub const MAX_FILTER_TTL_SECONDS: u64 = 86_400;
+
+type ApplyFilter = Box<dyn Fn(EnvFilter) -> Result<(), String> + Send + Sync>;
+
+/// Request to replace the log filter for a while
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetLogFilterRequest {
+    /// `RUST_LOG`-style directives, such as `ferrous=trace,tower_http=debug`
+    #[schema(example = "ferrous=trace")]
+    pub filter: String,
+    /// Seconds until the configured filter is restored (default 900, at most 86400)
+    pub ttl_seconds: Option<u64>,
+}
+
+/// The log filter in effect
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LogFilterStatus {
+    pub filter: String,
+    /// Filter the service was started with, restored when the TTL elapses
+    pub default_filter: String,
+    /// When the filter reverts, for a filter set at runtime
+    pub reverts_at: Option<DateTime<Utc>>,
+}
+
+struct Active {
+    filter: String,
+    reverts_at: Option<DateTime<Utc>>,
+    /// Bumped on every change, so an outdated revert leaves a newer filter alone
+    generation: u64,
+}
+
+/// The log output filter, adjustable while the service runs
+///
+/// A filter set at runtime always comes with a TTL, after which the filter the
+/// service was started with is restored, so a forgotten `trace` filter cannot
+/// flood the logs indefinitely.
+pub struct LogFilter {
+    default: String,
+    active: Mutex<Active>,
+    apply: ApplyFilter,
+}
+
+impl LogFilter {
+    /// A filter changed by calling `apply`, starting from `default`
+    pub fn new(
+        default: impl Into<String>,
+        apply: impl Fn(EnvFilter) -> Result<(), String> + Send + Sync + 'static,
+    ) -> Self {
+        let default = default.into();
+        Self {
+            active: Mutex::new(Active {
+                filter: default.clone(),
+                reverts_at: None,
+                generation: 0,
+            }),
+            default,
+            apply: Box::new(apply),
+        }
+    }
+
+    /// A filter changed through a `reload` layer's handle
+    pub fn from_handle<S: 'static>(
+        default: impl Into<String>,
+        handle: reload::Handle<EnvFilter, S>,
+    ) -> Self {
+        Self::new(default, move |filter| handle.reload(filter).map_err(|e| e.to_string()))
+    }
+
+    pub fn status(&self) -> LogFilterStatus {
+        let active = self.lock();
+        LogFilterStatus {
+            filter: active.filter.clone(),
+            default_filter: self.default.clone(),
+            reverts_at: active.reverts_at,
+        }
+    }
+
+    /// Apply `filter` until `ttl` elapses, then restore the default
+    ///
+    /// Fails without changing anything if `filter` is not valid.
+    pub fn set(self: &Arc<Self>, filter: &str, ttl: Duration) -> Result<LogFilterStatus, String> {
+        let parsed = EnvFilter::try_new(filter).map_err(|e| format!("Invalid log filter: {e}"))?;
+        let generation = {
+            let mut active = self.lock();
+            (self.apply)(parsed)?;
+            active.filter = filter.to_string();
+            active.reverts_at = chrono::Duration::from_std(ttl)
+                .ok()
+                .map(|ttl| Utc::now() + ttl);
+            active.generation += 1;
+            active.generation
+        };
+        info!(filter, ttl_seconds = ttl.as_secs(), "Log filter changed");
+
+        let log_filter = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(ttl).await;
+            log_filter.revert(Some(generation));
+        });
+        Ok(self.status())
+    }
+
+    /// Restore the default filter now
+    pub fn reset(&self) -> LogFilterStatus {
+        self.revert(None);
+        self.status()
+    }
+
+    /// Restore the default, unless the filter changed again after `generation`
+    fn revert(&self, generation: Option<u64>) {
+        let mut active = self.lock();
+        if generation.is_some_and(|generation| generation != active.generation) {
+            return;
+        }
+        if active.reverts_at.is_none() {
+            return;
+        }
+        // The default was valid at startup, so it parses again
+        let default = EnvFilter::try_new(&self.default).unwrap_or_default();
+        if let Err(e) = (self.apply)(default) {
+            warn!("Failed to restore the log filter: {}", e);
+            return;
+        }
+        active.filter = self.default.clone();
+        active.reverts_at = None;
+        active.generation += 1;
+        info!(filter = %self.default, "Log filter restored");
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Active> {
+        self.active.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recording() -> (Arc<LogFilter>, Arc<Mutex<Vec<String>>>) {
+        let applied = Arc::new(Mutex::new(Vec::new()));
+        let log = applied.clone();
+        let filter = LogFilter::new("ferrous=info", move |filter| {
+            log.lock().unwrap().push(filter.to_string());
+            Ok(())
+        });
+        (Arc::new(filter), applied)
+    }
+
+    #[tokio::test]
+    async fn test_filter_reverts_after_ttl() {
+        let (filter, applied) = recording();
+
+        let status = filter
+            .set("ferrous=trace", Duration::from_millis(50))
+            .unwrap();
+        assert_eq!(status.filter, "ferrous=trace");
+        assert!(status.reverts_at.is_some());
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let status = filter.status();
+        assert_eq!(status.filter, "ferrous=info");
+        assert_eq!(status.reverts_at, None);
+        assert_eq!(*applied.lock().unwrap(), ["ferrous=trace", "ferrous=info"]);
+    }
+
+    #[tokio::test]
+    async fn test_newer_filter_outlives_earlier_ttl() {
+        let (filter, _) = recording();
+
+        filter
+            .set("ferrous=debug", Duration::from_millis(50))
+            .unwrap();
+        filter
+            .set("ferrous=trace", Duration::from_secs(60))
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(filter.status().filter, "ferrous=trace");
+
+        assert!(filter.set("ferrous=[", Duration::from_secs(10)).is_err());
+        assert_eq!(filter.reset().filter, "ferrous=info");
+    }
+}
@@ -1,11 +1,60 @@
 use ferrous::{
-    config::Config, db::create_repository, handlers::APP_START_TIME, metrics, middleware, routes,
+    access_log::AccessLog,
+    approvals::Approvals,
+    attachments::Attachments,
+    auth::JwtValidator,
+    build_info::BUILD_INFO,
+    cdc::CdcExporter,
+    config::{parse_database_url, Config, LeaderElectionBackend},
+    db::{
+        create_access_repository, create_change_request_repository, create_collection_repository,
+        create_comment_repository, create_dual_write_repository, create_item_lock_repository,
+        create_lease_repository, create_notification_repository, create_repository,
+        create_saved_search_repository, create_tenant_repository,
+    },
+    dead_letters::{DeadLetterQueue, DeadLetterSource},
+    diagnostics,
+    duplicates::DuplicateGuard,
+    events::{create_publisher, EventPublisher, OutboxDispatcher, WebhookPublisher},
+    handlers::APP_START_TIME,
+    health::HealthMonitor,
+    http_client::HttpClient,
+    inbox::InboxPublisher,
+    k8s::{self, KubernetesLease},
+    leadership::{holder_identity, DatabaseLease, LeaderElector, Leadership, Lease},
+    locks::DistributedLock,
+    log_filter::LogFilter,
+    metrics, middleware,
+    middleware::{chaos::FaultInjector, mocks::MockRegistry},
+    migration,
+    notifications::{Alerter, Notifier},
+    outbound_auth::TokenProvider,
+    privacy::ErasureSigner,
+    profiling::{self, CountingAllocator},
+    publishing::PublishJob,
+    retention::RetentionJob,
+    routes,
+    saved_searches::SavedSearchPublisher,
+    sharing::ShareLinks,
+    shutdown::ShutdownCoordinator,
     state::AppState,
+    tenancy::{with_optional_tenant, TenantDirectory},
+    validation::ValidationRules,
+    views::ListViews,
 };
-use std::{net::SocketAddr, time::Instant};
-use tokio::signal;
+use std::{
+    future::IntoFuture,
+    net::SocketAddr,
+    sync::{atomic::Ordering, Arc},
+    time::{Duration, Instant},
+};
+use tokio::{signal, sync::oneshot};
 use tracing::{error, info, warn};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use tracing_subscriber::{layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter, Layer};
+
+/// Counts allocations for `/debug/pprof/heap` once profiling is enabled
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -18,6 +67,22 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Load .env file if it exists
     dotenvy::dotenv().ok();
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        [] => {}
+        ["config", "check"] => return check_config().await,
+        ["migrate", options @ ..] => return migrate(options).await,
+        _ => {
+            eprintln!("Usage: ferrous [config check | migrate --to <database-url> [--batch-size <n>] [--tenant <id>] [--no-verify]]");
+            return Err(format!("Unknown command: {}", args.join(" ")).into());
+        }
+    }
+
     // Load and validate configuration
     let config = match Config::load() {
         Ok(cfg) => {
@@ -38,27 +103,329 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Removed secrets validation - use external tools for secrets management
 
-    // Initialize tracing with configuration
-    tracing_subscriber::registry()
-        .with(
-            config
-                .logging
-                .rust_log
-                .parse::<tracing_subscriber::EnvFilter>()
-                .unwrap_or_else(|_| "ferrous=debug,tower_http=debug".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
+    if config.profiling.enabled {
+        profiling::enable_heap_counting();
+    }
+
+    // Initialize tracing with configuration. The filter applies to log output
+    // only, so the tokio-console layer still sees the runtime's own events.
+    let default_filter = match config.logging.rust_log.parse::<EnvFilter>() {
+        Ok(_) => config.logging.rust_log.clone(),
+        Err(_) => "ferrous=debug,tower_http=debug".to_string(),
+    };
+    let (filter, filter_handle) = reload::Layer::new(EnvFilter::new(&default_filter));
+    let registry = tracing_subscriber::registry();
+    #[cfg(feature = "console")]
+    let registry = registry.with(
+        console_subscriber::ConsoleLayer::builder()
+            .server_addr(config.logging.tokio_console_bind.parse::<SocketAddr>()?)
+            .spawn(),
+    );
+    registry
+        .with(tracing_subscriber::fmt::layer().with_filter(filter))
         .init();
+    #[cfg(feature = "console")]
+    info!("tokio-console listening on {}", config.logging.tokio_console_bind);
+    info!("{}", *BUILD_INFO);
+    let pod = k8s::pod_span(&config.kubernetes);
+    if !pod.is_none() {
+        pod.in_scope(|| info!("Running in Kubernetes"));
+    }
 
     // Initialize repository
-    let repo = create_repository(&config);
+    let (repo, dual_write) = create_dual_write_repository(&config);
+    if let Some(secondary) = &config.database.secondary_type {
+        info!("Applying writes to the {} secondary backend as well", secondary);
+    }
     info!("Repository initialized successfully");
 
+    // Every outbound call shares one client, and its connection pool
+    let http = HttpClient::new(&config.http_client)?;
+    // Key sets may be hosted anywhere, so their URLs are held to the egress policy
+    let egress_http = HttpClient::restricted(&config.http_client, &config.egress)?;
+
+    // Connect to the external event broker, if configured
+    let tokens = TokenProvider::from_config(&config.outbound_auth)
+        .map(|tokens| Arc::new(tokens.with_http_client(http.clone())));
+    let publisher = match create_publisher(&config.events, tokens, &http).await {
+        Ok(publisher) => publisher,
+        Err(e) => {
+            error!("Failed to initialize event publisher: {}", e);
+            return Err(e.into());
+        }
+    };
+    if let Some(publisher) = &publisher {
+        info!("Publishing item events to {}", publisher.name());
+    }
+    // Work that exhausts its retries is kept here for operators
+    let dead_letters = Arc::new(DeadLetterQueue::default());
+
+    // Webhook URLs point outside the deployment, like key sets
+    let webhooks = WebhookPublisher::from_config(&config.webhooks, egress_http.clone())
+        .map(|webhooks| webhooks.with_dead_letters(dead_letters.clone()));
+    if webhooks.is_some() {
+        info!("Delivering item events to {} webhooks", config.webhooks.endpoints.len());
+    }
+
+    // Open the access log, if configured, before accepting traffic
+    let access_log = match AccessLog::start(&config.logging) {
+        Ok(access_log) => access_log,
+        Err(e) => {
+            error!("Failed to open access log: {}", e);
+            return Err(e.into());
+        }
+    };
+
+    // Leases back leader election and, unless Redis holds them, locks
+    let leases = create_lease_repository(&config);
+    let locks = match DistributedLock::from_config(&config.locks, leases.clone()) {
+        Ok(locks) => locks,
+        Err(e) => {
+            error!("Failed to set up distributed locks: {}", e);
+            return Err(e.into());
+        }
+    };
+    info!("Distributed locks kept in the {} backend", locks.backend());
+
     // Create shared application state
-    let state = AppState::shared(repo);
+    let mut state = AppState::new(repo)
+        .with_config(config.clone())
+        .with_auth(JwtValidator::new(&config.auth).with_http_client(egress_http))
+        .with_access(create_access_repository(&config))
+        .with_collections(create_collection_repository(&config))
+        .with_publisher(publisher)
+        .with_webhooks(webhooks)
+        .with_dead_letters(dead_letters)
+        .with_dual_write(dual_write)
+        .with_locks(locks)
+        .with_erasure_signer(
+            config
+                .privacy
+                .erasure_signing_key
+                .as_deref()
+                .map(ErasureSigner::new),
+        )
+        .with_tenants(TenantDirectory::new(create_tenant_repository(&config), &config.tenancy))
+        .with_health(HealthMonitor::new(config.health.clone()).with_http_client(http.clone()))
+        .with_http_client(http)
+        .with_access_log(access_log)
+        .with_log_filter(LogFilter::from_handle(default_filter, filter_handle))
+        .with_validation_rules(ValidationRules::from_config(
+            &config.item_validation,
+            &config.sanitization,
+        )?);
+    let attachments = Attachments::from_config(&config.attachments, state.http.clone())?
+        .map(|attachments| attachments.with_clock(state.clock.clone()));
+    if let Some(attachments) = &attachments {
+        info!("Accepting item attachments uploaded to S3");
+        if let Some(scanner) = attachments.scanner() {
+            info!(scanner, "Scanning attachments for malware");
+        }
+        if let Some(sizes) = attachments.thumbnail_sizes() {
+            info!(?sizes, "Rendering thumbnails of image attachments");
+        }
+    }
+    state = state.with_attachments(attachments);
+    let shares = ShareLinks::from_config(&config.sharing)
+        .map(|shares| shares.with_clock(state.clock.clone()));
+    state = state.with_share_links(shares);
+    let notifier = Notifier::from_config(&config.notifications)?
+        .map(|notifier| Arc::new(notifier.with_dead_letters(state.dead_letters.clone())));
+    if let Some(notifier) = &notifier {
+        state
+            .dead_letters
+            .register(DeadLetterSource::Notification, notifier.clone())?;
+    }
+    let alerter =
+        Alerter::from_config(&config.notifications, notifier.clone(), state.http.clone())?;
+    state = state.with_notifications(notifier);
+    if config.inbox.enabled {
+        info!("Serving the in-app notification inbox");
+        state = state.with_inbox(Some(create_notification_repository(&config)));
+    }
+    state = state.with_saved_searches(create_saved_search_repository(&config));
+    state = state.with_comments(create_comment_repository(&config));
+    state = state.with_item_locks(create_item_lock_repository(&config));
+    if !config.approvals.collections.is_empty() {
+        info!(
+            "Changes to items in {} collections need {} approvals",
+            config.approvals.collections.len(),
+            config.approvals.required_approvals
+        );
+        state = state
+            .with_approvals(Approvals::from_config(&config.approvals))
+            .with_change_requests(create_change_request_repository(&config));
+    }
+    if let Some(seconds) = config.duplicates.window_seconds {
+        let guard =
+            DuplicateGuard::new(Duration::from_secs(seconds)).with_clock(state.clock.clone());
+        state = state.with_duplicate_guard(guard);
+    }
+    if !config.views.definitions.is_empty() {
+        let views = ListViews::from_config(&config.views)?.with_clock(state.clock.clone());
+        state = state.with_views(views);
+    }
+    if config.chaos.enabled {
+        warn!("Fault injection is enabled; requests may be delayed, failed or dropped");
+        state = state.with_chaos(FaultInjector::default());
+    }
+    if config.mocks.enabled {
+        if config.security.strict_mode {
+            warn!("MOCKS_ENABLED is ignored in strict mode; mock endpoints are disabled");
+        } else {
+            warn!("Mock endpoints are enabled; registered mocks answer ahead of real routes");
+            state = state.with_mocks(MockRegistry::default());
+        }
+    }
+    let state = state.into_shared();
+
+    // Check that tokens can be verified before accepting traffic
+    if let Err(e) = state.auth.verify_setup().await {
+        if config.auth.fail_closed {
+            error!("Authentication unavailable, protected routes will return 503: {}", e);
+        } else {
+            warn!("Authentication misconfigured, requests will be served unauthenticated: {}", e);
+        }
+    }
+
+    // Subsystems register here as they start, and are stopped in reverse
+    let mut shutdown = ShutdownCoordinator::new();
+
+    // Choose the replica that runs singleton jobs, when replicas share a database
+    let lease: Option<Arc<dyn Lease>> = match config.leader_election.backend {
+        LeaderElectionBackend::None => None,
+        LeaderElectionBackend::Kubernetes => {
+            match KubernetesLease::in_cluster(&config.leader_election, &config.kubernetes) {
+                Ok(lease) => Some(Arc::new(lease)),
+                Err(e) => {
+                    error!("Failed to set up leader election: {}", e);
+                    return Err(e.into());
+                }
+            }
+        }
+        LeaderElectionBackend::Database => {
+            Some(Arc::new(DatabaseLease::new(leases, &config.leader_election.lease_name)))
+        }
+    };
+    let leadership = match lease {
+        None => Leadership::always(),
+        Some(lease) => {
+            let holder = holder_identity(config.kubernetes.pod_name.as_deref());
+            let duration = Duration::from_secs(config.leader_election.lease_duration_seconds);
+            let elector = Arc::new(LeaderElector::new(lease, holder, duration));
+            info!(
+                "Competing for lease {} as {}",
+                config.leader_election.lease_name,
+                elector.holder()
+            );
+            let leadership = elector.leadership();
+            let election = elector.clone().spawn();
+            shutdown.register("leader election", async move {
+                election.abort();
+                elector.resign().await;
+            });
+            leadership
+        }
+    };
+
+    // Start publishing outbox events to the in-process bus and any external broker
+    let mut publishers: Vec<Arc<dyn EventPublisher>> = vec![Arc::new(state.events.clone())];
+    publishers.extend(state.publisher.clone());
+    if let Some(webhooks) = &state.webhooks {
+        publishers.push(webhooks.clone());
+        state
+            .dead_letters
+            .register(DeadLetterSource::Webhook, webhooks.clone())?;
+    }
+    if let Some(inbox) = &state.inbox {
+        publishers.push(Arc::new(InboxPublisher::new(inbox.clone(), state.access.clone())));
+    }
+    publishers.push(Arc::new(SavedSearchPublisher::new(
+        state.saved_searches.clone(),
+        state.inbox.clone(),
+        state.webhooks.clone(),
+    )));
+    let dispatcher = OutboxDispatcher::new(state.repo.clone(), publishers, &config.events)
+        .with_dead_letters(state.dead_letters.clone())
+        .with_leadership(leadership.clone());
+    state
+        .dead_letters
+        .register(DeadLetterSource::Outbox, Arc::new(dispatcher.clone()))?;
+    let dispatching = dispatcher.clone().spawn();
+    shutdown.register("outbox dispatcher", async move {
+        dispatching.abort();
+        // Deliver the events written by the last requests
+        if dispatcher.is_leader() {
+            if let Err(e) = dispatcher.dispatch_once().await {
+                warn!("Final outbox dispatch failed: {}", e);
+            }
+        }
+    });
+    info!("Outbox dispatcher started");
+
+    // Sample host resource usage for the health endpoint
+    let sampler = state
+        .system
+        .clone()
+        .spawn(Duration::from_secs(config.health.system_sample_interval_seconds));
+    shutdown.abort_on_shutdown("system sampler", sampler);
+
+    // Run synthetic health checks against dependencies, if any are configured
+    if let Some(health_checks) = state.health.clone().spawn() {
+        shutdown.abort_on_shutdown("health checks", health_checks);
+    }
+
+    // Send notification emails, and alert operators when things go wrong
+    if let Some(notifier) = &state.notifications {
+        if let Some(worker) = notifier.clone().spawn() {
+            shutdown.abort_on_shutdown("notification sender", worker);
+        }
+    }
+    if let Some(alerter) = alerter {
+        let interval = Duration::from_secs(config.notifications.check_interval_seconds);
+        let alerts = Arc::new(alerter).spawn(state.clone(), interval);
+        shutdown.abort_on_shutdown("alerts", alerts);
+    }
+
+    // Keep list views current with item changes
+    if let Some(views) = &state.views {
+        info!("Maintaining {} list views", config.views.definitions.len());
+        let maintenance = views.clone().spawn(&state.events, state.repo.clone());
+        shutdown.abort_on_shutdown("list views", maintenance);
+    }
+
+    // Start applying data retention policies
+    if config.retention.enabled {
+        info!("Retention job started (dry run: {})", config.retention.dry_run);
+        let retention = RetentionJob::from_config(state.repo.clone(), &config.retention)
+            .with_leadership(leadership.clone())
+            .spawn();
+        shutdown.abort_on_shutdown("retention job", retention);
+    }
+
+    // Publish drafts once their scheduled time comes
+    let publishing = PublishJob::new(
+        state.repo.clone(),
+        state.tenants.clone(),
+        Duration::from_secs(config.publishing.interval_seconds),
+    )
+    .with_clock(state.clock.clone())
+    .with_leadership(leadership.clone())
+    .with_approvals(state.approvals.clone(), state.collections.clone())
+    .spawn();
+    shutdown.abort_on_shutdown("publishing job", publishing);
+
+    // Start exporting item changes to object storage
+    if let Some(exporter) =
+        CdcExporter::from_config(&config.cdc_export, state.repo.clone(), state.http.clone())?
+    {
+        info!("Exporting item changes to S3 every {}s", config.cdc_export.interval_seconds);
+        let export = exporter.with_leadership(leadership.clone()).spawn();
+        shutdown.abort_on_shutdown("CDC export", export);
+    }
 
     // Build application with routes and middleware
-    let app = middleware::add_middleware(routes::create_routes(state));
+    let app = middleware::add_middleware(routes::create_routes(state.clone()), &state);
 
     // Configure socket address from validated config
     let addr = SocketAddr::from(([0, 0, 0, 0], config.server.port));
@@ -75,23 +442,150 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     info!("Server is ready to accept connections");
 
-    // Create the server with configured shutdown
-    let shutdown_config = config.shutdown.clone();
-    let server =
-        axum::serve(listener, app).with_graceful_shutdown(shutdown_signal(shutdown_config));
-
-    // Run the server
+    // Run the server until a shutdown signal, then stop it before anything
+    // its requests depend on
+    let (stop, stopped) = oneshot::channel::<()>();
+    let mut server = tokio::spawn(
+        axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+            .with_graceful_shutdown(async {
+                let _ = stopped.await;
+            })
+            .into_future(),
+    );
     info!("Server running. Press Ctrl+C to initiate graceful shutdown");
 
-    if let Err(e) = server.await {
-        error!("Server error: {}", e);
-        return Err(format!("Server failed: {}", e).into());
+    tokio::select! {
+        result = &mut server => {
+            let e = match result {
+                Ok(Err(e)) => e.to_string(),
+                Err(e) => e.to_string(),
+                Ok(Ok(())) => "stopped unexpectedly".to_string(),
+            };
+            error!("Server error: {}", e);
+            return Err(format!("Server failed: {}", e).into());
+        }
+        () = shutdown_signal(config.shutdown.clone()) => {}
+    }
+
+    // Keep serving while load balancers notice readiness failing
+    state.draining.store(true, Ordering::Relaxed);
+    let drain = Duration::from_secs(config.shutdown.drain_delay_seconds);
+    if !drain.is_zero() {
+        info!("Draining for {} seconds before shutting down", drain.as_secs());
+        tokio::time::sleep(drain).await;
     }
 
+    shutdown.register("http server", async move {
+        let _ = stop.send(());
+        match server.await {
+            Ok(Err(e)) => error!("Server error while shutting down: {}", e),
+            Err(e) => error!("Server task failed: {}", e),
+            Ok(Ok(())) => {}
+        }
+    });
+    let timeout = Duration::from_secs(config.shutdown.timeout_seconds);
+    let unfinished = shutdown.shutdown(timeout).await;
+    if !unfinished.is_empty() {
+        warn!("Shut down without waiting for: {}", unfinished.join(", "));
+        return Ok(());
+    }
     info!("Server has shut down successfully");
     Ok(())
 }
 
+/// Validate configuration and connect to every declared backend without serving
+async fn check_config() -> Result<(), Box<dyn std::error::Error>> {
+    let (config, checks) = match diagnostics::check_config().await {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Configuration is invalid: {}", e);
+            return Err(e.into());
+        }
+    };
+
+    println!("Configuration is valid (database: {})", config.database.db_type);
+    for check in &checks {
+        println!("{check}");
+    }
+
+    let failed = checks.iter().filter(|check| check.result.is_err()).count();
+    if failed > 0 {
+        return Err(format!("{failed} backend check(s) failed").into());
+    }
+    Ok(())
+}
+
+/// Copy the configured backend's data to the one at `--to`
+async fn migrate(options: &[&str]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut target_url = None;
+    let mut batch_size = migration::DEFAULT_BATCH_SIZE;
+    let mut tenant = None;
+    let mut verify = true;
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        match (*option, options.next()) {
+            ("--to", Some(url)) => target_url = Some(*url),
+            ("--batch-size", Some(size)) => batch_size = size.parse()?,
+            ("--tenant", Some(id)) => tenant = Some(id.to_string()),
+            ("--no-verify", next) => {
+                verify = false;
+                if let Some(next) = next {
+                    return Err(format!("Unexpected argument: {next}").into());
+                }
+            }
+            (option, _) => return Err(format!("Invalid option: {option}").into()),
+        }
+    }
+    let target_url = target_url.ok_or("Missing --to <database-url>")?;
+
+    let mut config = Config::load()?;
+    // Neither side mirrors reads or writes while copying
+    config.database.shadow_type = None;
+    config.database.shadow_url = None;
+    config.database.secondary_type = None;
+    config.database.secondary_url = None;
+    let mut target_config = config.clone();
+    let (db_type, url) = parse_database_url("--to", target_url)?;
+    target_config.database.db_type = db_type;
+    target_config.database.convex_deployment_url = url;
+    target_config.database.convex_replica_url = None;
+    target_config.database.hedge_after_ms = None;
+
+    let source = migration::Backend {
+        items: create_repository(&config),
+        access: create_access_repository(&config),
+    };
+    let target = migration::Backend {
+        items: create_repository(&target_config),
+        access: create_access_repository(&target_config),
+    };
+    println!("Migrating {} to {}", config.database.db_type, target_config.database.db_type);
+    let report = with_optional_tenant(
+        tenant,
+        migration::migrate(&source, &target, batch_size, verify, |progress| {
+            println!("{:?}: {}/{} items", progress.phase, progress.items, progress.total)
+        }),
+    )
+    .await?;
+
+    println!("Copied {} items and {} grants", report.items, report.grants);
+    if !report.verified {
+        return Ok(());
+    }
+    if !report.is_consistent() {
+        println!(
+            "Verification failed: {} missing ({}), {} different ({})",
+            report.missing_count,
+            report.missing.join(", "),
+            report.mismatched_count,
+            report.mismatched.join(", ")
+        );
+        return Err("Target does not match the source".into());
+    }
+    println!("Verified: target matches the source");
+    Ok(())
+}
+
 /// Handle shutdown signals
 async fn shutdown_signal(shutdown_config: ferrous::config::ShutdownConfig) {
     let ctrl_c = async {
@@ -121,7 +615,7 @@ async fn shutdown_signal(shutdown_config: ferrous::config::ShutdownConfig) {
     }
 
     warn!(
-        "Shutdown signal received, waiting up to {} seconds for existing connections to close...",
+        "Shutdown signal received, waiting up to {} seconds for connections and subsystems to finish...",
         shutdown_config.timeout_seconds
     );
 }
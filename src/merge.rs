@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::models::{Item, UpdateItemRequest};
+
+/// Request to merge another item into an item
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct MergeItemsRequest {
+    /// Item to merge in; it is deleted once merged
+    #[validate(length(min = 1, message = "source_id must not be empty"))]
+    #[schema(example = "7a1c9e2b-3d4f-4a5b-8c6d-7e8f9a0b1c2d")]
+    pub source_id: String,
+}
+
+/// Outcome of a merge
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MergeReport {
+    /// The item after the merge
+    pub item: Item,
+    /// ID of the item merged in, which no longer exists
+    pub merged_from: String,
+    /// Access grants carried over from the merged item
+    pub grants: usize,
+}
+
+/// Update bringing `source`'s data into `target`
+///
+/// Nothing of `target` is lost: its description is only filled in when
+/// missing, metadata objects are merged key by key with `target` winning
+/// and arrays gaining the values only `source` has, and custom fields are
+/// only added where `target` has none.
+pub fn merge_update(target: &Item, source: &Item) -> UpdateItemRequest {
+    let description = match (&target.description, &source.description) {
+        (None, Some(description)) => Some(description.clone()),
+        _ => None,
+    };
+    let metadata = match (&target.metadata, &source.metadata) {
+        (Some(target), Some(source)) => Some(merge_json(target, source)),
+        (None, Some(source)) => Some(source.clone()),
+        _ => None,
+    };
+    let custom_fields = source
+        .custom_fields
+        .iter()
+        .filter(|(name, _)| !target.custom_fields.contains_key(*name))
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+
+    UpdateItemRequest {
+        description,
+        metadata,
+        custom_fields,
+        ..UpdateItemRequest::default()
+    }
+}
+
+fn merge_json(target: &Value, source: &Value) -> Value {
+    match (target, source) {
+        (Value::Object(target), Value::Object(source)) => {
+            let mut merged = target.clone();
+            for (key, value) in source {
+                let combined = match target.get(key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => value.clone(),
+                };
+                merged.insert(key.clone(), combined);
+            }
+            Value::Object(merged)
+        }
+        (Value::Array(target), Value::Array(source)) => {
+            let mut merged = target.clone();
+            for value in source {
+                if !merged.contains(value) {
+                    merged.push(value.clone());
+                }
+            }
+            Value::Array(merged)
+        }
+        (target, _) => target.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use serde_json::json;
+
+    fn item(description: Option<&str>, metadata: Value, fields: Value) -> Item {
+        Item {
+            id: "item-1".to_string(),
+            name: "Widget".to_string(),
+            slug: "widget".to_string(),
+            external_id: None,
+            source: None,
+            description: description.map(str::to_string),
+            metadata: Some(metadata),
+            custom_fields: serde_json::from_value(fields).unwrap(),
+            owner_id: None,
+            tenant_id: None,
+            status: Default::default(),
+            publish_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            version: 1,
+        }
+    }
+
+    #[test]
+    fn test_merge_keeps_the_target_and_adds_the_rest() {
+        let target = item(
+            None,
+            json!({ "color": "red", "tags": ["a", "b"], "links": { "docs": "/t" } }),
+            json!({ "priority": 1 }),
+        );
+        let source = item(
+            Some("From the copy"),
+            json!({ "color": "blue", "tags": ["b", "c"], "links": { "shop": "/s" } }),
+            json!({ "priority": 3, "sku": "W-1" }),
+        );
+
+        let update = merge_update(&target, &source);
+        assert_eq!(update.description.as_deref(), Some("From the copy"));
+        assert_eq!(
+            update.metadata,
+            Some(json!({
+                "color": "red",
+                "tags": ["a", "b", "c"],
+                "links": { "docs": "/t", "shop": "/s" }
+            }))
+        );
+        assert_eq!(update.custom_fields.len(), 1);
+        assert_eq!(update.custom_fields["sku"], "W-1");
+        assert!(update.name.is_none());
+    }
+}
@@ -1,9 +1,14 @@
 use once_cell::sync::Lazy;
 use prometheus::{
-    register_counter_vec, register_histogram_vec, register_int_counter_vec, register_int_gauge,
-    CounterVec, Encoder, HistogramVec, IntCounterVec, IntGauge, TextEncoder,
+    register_counter_vec, register_gauge_vec, register_histogram_vec, register_int_counter_vec,
+    register_int_gauge, register_int_gauge_vec, CounterVec, Encoder, GaugeVec, HistogramVec,
+    IntCounterVec, IntGauge, IntGaugeVec, TextEncoder,
+};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{Mutex, RwLock},
+    time::{Duration, Instant},
 };
-use std::time::Instant;
 
 /// HTTP request duration histogram
 pub static HTTP_REQUEST_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
@@ -25,6 +30,64 @@ pub static HTTP_REQUEST_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
     .expect("Failed to register HTTP request counter")
 });
 
+/// Bucket bounds of the body size histograms: 64 bytes to 16 MiB
+fn body_size_buckets() -> Vec<f64> {
+    prometheus::exponential_buckets(64.0, 4.0, 10).expect("Invalid body size buckets")
+}
+
+/// HTTP request body size histogram
+pub static HTTP_REQUEST_SIZE: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "http_request_size_bytes",
+        "HTTP request body size in bytes",
+        &["method", "endpoint", "tenant"],
+        body_size_buckets()
+    )
+    .expect("Failed to register HTTP request size metric")
+});
+
+/// HTTP response body size histogram
+pub static HTTP_RESPONSE_SIZE: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "http_response_size_bytes",
+        "HTTP response body size in bytes",
+        &["method", "endpoint", "tenant"],
+        body_size_buckets()
+    )
+    .expect("Failed to register HTTP response size metric")
+});
+
+/// HTTP responses by status class, so 4xx and 5xx can be told apart without
+/// matching on individual statuses
+pub static HTTP_RESPONSES_BY_CLASS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "http_responses_total",
+        "Total number of HTTP responses by status class",
+        &["endpoint", "class"]
+    )
+    .expect("Failed to register HTTP responses by class counter")
+});
+
+/// Requests by Apdex satisfaction (satisfied, tolerating, frustrated)
+pub static HTTP_APDEX_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "http_apdex_requests_total",
+        "Total number of HTTP requests by Apdex satisfaction",
+        &["endpoint", "satisfaction"]
+    )
+    .expect("Failed to register HTTP Apdex counter")
+});
+
+/// Share of requests answered with 5xx over the rolling error window
+pub static HTTP_ERROR_RATIO: Lazy<GaugeVec> = Lazy::new(|| {
+    register_gauge_vec!(
+        "http_error_ratio",
+        "Ratio of 5xx responses to requests over the last five minutes",
+        &["endpoint"]
+    )
+    .expect("Failed to register HTTP error ratio gauge")
+});
+
 /// Database query duration histogram
 pub static DATABASE_QUERY_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
     register_histogram_vec!(
@@ -69,17 +132,386 @@ pub static DATABASE_CONNECTIONS: Lazy<IntGauge> = Lazy::new(|| {
         .expect("Failed to register database connections gauge")
 });
 
+/// Adaptive concurrency limit of each database backend
+pub static DATABASE_CONCURRENCY_LIMIT: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "database_concurrency_limit",
+        "Concurrent calls allowed to a database backend",
+        &["backend"]
+    )
+    .expect("Failed to register database concurrency limit gauge")
+});
+
+/// Calls in flight to each concurrency-limited database backend
+pub static DATABASE_CALLS_IN_FLIGHT: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "database_calls_in_flight",
+        "Calls in flight to a database backend",
+        &["backend"]
+    )
+    .expect("Failed to register database calls in flight gauge")
+});
+
+/// Calls refused because a database backend's concurrency limit stayed full
+pub static DATABASE_CALLS_SHED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "database_calls_shed_total",
+        "Calls refused while a database backend was at its concurrency limit",
+        &["backend"]
+    )
+    .expect("Failed to register database calls shed counter")
+});
+
+/// Reads that were hedged, by the attempt that answered
+pub static DATABASE_HEDGED_READS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "database_hedged_reads_total",
+        "Reads sent a second time after exceeding the hedging delay",
+        &["backend", "operation", "winner"]
+    )
+    .expect("Failed to register database hedged reads counter")
+});
+
+/// Reads mirrored to a shadow backend, by how its answer compared
+pub static DATABASE_SHADOW_READS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "database_shadow_reads_total",
+        "Reads mirrored to the shadow backend and compared with the primary's answer",
+        &["backend", "operation", "outcome"]
+    )
+    .expect("Failed to register database shadow reads counter")
+});
+
+/// Writes the secondary backend failed to apply while dual writing
+pub static DATABASE_DUAL_WRITE_DIVERGENCES: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "database_dual_write_divergences_total",
+        "Writes applied to the primary backend that the secondary backend failed to apply",
+        &["backend", "operation"]
+    )
+    .expect("Failed to register database dual write divergences counter")
+});
+
+/// Outbox events handed to publishers, by outcome
+pub static OUTBOX_EVENTS_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "outbox_events_total",
+        "Total number of outbox events dispatched",
+        &["status"]
+    )
+    .expect("Failed to register outbox events counter")
+});
+
+/// Outbox events waiting to be published
+pub static OUTBOX_PENDING_EVENTS: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!("outbox_pending_events", "Number of unpublished outbox events")
+        .expect("Failed to register outbox pending events gauge")
+});
+
+/// Event deliveries to each publisher, by outcome
+pub static EVENT_PUBLISH_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "event_publish_total",
+        "Total number of event deliveries to publishers",
+        &["publisher", "status"]
+    )
+    .expect("Failed to register event publish counter")
+});
+
+/// Historical events replayed to each sink, by outcome
+pub static EVENT_REPLAY_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "event_replay_total",
+        "Total number of historical events replayed to sinks",
+        &["sink", "status"]
+    )
+    .expect("Failed to register event replay counter")
+});
+
+/// Event delivery latency per publisher
+pub static EVENT_PUBLISH_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "event_publish_duration_seconds",
+        "Event delivery duration in seconds",
+        &["publisher"]
+    )
+    .expect("Failed to register event publish duration metric")
+});
+
+/// Records removed (or, in dry-run mode, matched) by retention policies
+pub static RETENTION_PURGED_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "retention_purged_records_total",
+        "Total number of records purged by retention policies",
+        &["target", "mode"]
+    )
+    .expect("Failed to register retention purged counter")
+});
+
+/// Drafts published, by whether a request or their schedule published them
+pub static ITEMS_PUBLISHED_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "items_published_total",
+        "Total number of draft items published",
+        &["trigger"]
+    )
+    .expect("Failed to register items published counter")
+});
+
+/// Change requests made and resolved, by outcome
+pub static CHANGE_REQUESTS_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "change_requests_total",
+        "Total number of change requests made and resolved",
+        &["outcome"]
+    )
+    .expect("Failed to register change requests counter")
+});
+
+/// Item changes exported to object storage, and the files written
+pub static CDC_EXPORTED_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "cdc_exported_total",
+        "Total number of item changes and files exported to object storage",
+        &["kind"]
+    )
+    .expect("Failed to register CDC exported counter")
+});
+
+/// Outbox sequence of the last change exported to object storage
+pub static CDC_EXPORT_SEQUENCE: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "cdc_export_sequence",
+        "Outbox sequence of the last change exported to object storage"
+    )
+    .expect("Failed to register CDC export sequence gauge")
+});
+
+/// Authentication decisions by outcome and token issuer
+pub static AUTH_DECISIONS_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "auth_decisions_total",
+        "Total number of bearer token authentication decisions",
+        &["outcome", "issuer"]
+    )
+    .expect("Failed to register auth decisions counter")
+});
+
+/// Outbound access token requests by result (cached, fetched, stale, failed)
+pub static OUTBOUND_TOKEN_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "outbound_token_requests_total",
+        "Total number of access tokens requested for outbound calls",
+        &["result"]
+    )
+    .expect("Failed to register outbound token counter")
+});
+
+/// Time taken to obtain a token from the token endpoint
+pub static OUTBOUND_TOKEN_FETCH_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "outbound_token_fetch_duration_seconds",
+        "Token endpoint request duration in seconds",
+        &["status"]
+    )
+    .expect("Failed to register outbound token fetch duration metric")
+});
+
+/// Access log lines dropped because the writer fell behind
+pub static ACCESS_LOG_DROPPED_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "access_log_dropped_lines_total",
+        "Total number of access log lines dropped because the writer fell behind",
+        &[]
+    )
+    .expect("Failed to register access log dropped counter")
+});
+
+/// Requests slower than the slow request threshold
+pub static HTTP_SLOW_REQUESTS_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "http_slow_requests_total",
+        "Total number of requests slower than the slow request threshold",
+        &["endpoint"]
+    )
+    .expect("Failed to register slow requests counter")
+});
+
+/// Repository calls slower than the slow query threshold
+pub static DATABASE_SLOW_QUERIES_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "database_slow_queries_total",
+        "Total number of repository calls slower than the slow query threshold",
+        &["operation", "repository"]
+    )
+    .expect("Failed to register slow queries counter")
+});
+
+/// Faults injected into requests, by kind
+pub static CHAOS_FAULTS_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "chaos_faults_injected_total",
+        "Total number of faults injected into requests",
+        &["fault"]
+    )
+    .expect("Failed to register chaos faults counter")
+});
+
+/// Calls to deprecated endpoints, by method and route
+pub static DEPRECATED_CALLS_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "http_deprecated_requests_total",
+        "Total number of requests to deprecated endpoints",
+        &["method", "path"]
+    )
+    .expect("Failed to register deprecated requests counter")
+});
+
+/// Item submissions answered with an item created moments before
+pub static DUPLICATE_SUBMISSIONS_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "items_duplicate_submissions_total",
+        "Total number of duplicate item submissions answered with the earlier item",
+        &[] as &[&str]
+    )
+    .expect("Failed to register duplicate submissions counter")
+});
+
+/// Repository calls skipped or abandoned because the request's deadline passed
+pub static DATABASE_CANCELLED_OPERATIONS_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "database_operations_cancelled_total",
+        "Total number of repository calls cancelled by request deadlines",
+        &["operation", "repository"]
+    )
+    .expect("Failed to register cancelled operations counter")
+});
+
+/// Work waiting in the dead letter queue
+pub static DEAD_LETTER_QUEUE_DEPTH: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!("dead_letter_queue_depth", "Number of entries in the dead letter queue")
+        .expect("Failed to register dead letter queue depth gauge")
+});
+
+/// Whether this replica holds the leader lease and runs singleton jobs
+pub static LEADER_ELECTION_LEADER: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "leader_election_leader",
+        "Whether this replica is the leader (1) or not (0)"
+    )
+    .expect("Failed to register leader election gauge")
+});
+
+/// Distributed lock attempts by backend and outcome
+pub static DISTRIBUTED_LOCKS_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "distributed_locks_total",
+        "Total number of distributed lock attempts by backend and outcome",
+        &["backend", "outcome"]
+    )
+    .expect("Failed to register distributed locks counter")
+});
+
+/// How long distributed locks were held before release
+pub static DISTRIBUTED_LOCK_HOLD_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "distributed_lock_hold_duration_seconds",
+        "Time distributed locks were held in seconds",
+        &["backend"]
+    )
+    .expect("Failed to register distributed lock hold duration metric")
+});
+
+/// Work that exhausted its retries, by source
+pub static DEAD_LETTERS_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "dead_letters_total",
+        "Total number of entries added to the dead letter queue",
+        &["source"]
+    )
+    .expect("Failed to register dead letters counter")
+});
+
+/// Attachments scanned for malware, by scanner and result
+pub static ATTACHMENT_SCANS_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "attachment_scans_total",
+        "Total number of attachments scanned for malware",
+        &["scanner", "result"]
+    )
+    .expect("Failed to register attachment scans counter")
+});
+
+/// Notifications by channel, kind and outcome
+pub static NOTIFICATIONS_COUNTER: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "notifications_total",
+        "Total number of notifications by channel, kind and outcome",
+        &["channel", "kind", "outcome"]
+    )
+    .expect("Failed to register notifications counter")
+});
+
+/// Time taken to hand a notification to the mail server or chat service
+pub static NOTIFICATION_SEND_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "notification_send_duration_seconds",
+        "Notification send duration in seconds",
+        &["provider", "status"]
+    )
+    .expect("Failed to register notification send duration metric")
+});
+
 /// Initialize all metrics (called at startup to ensure registration)
 pub fn init_metrics() {
     // Force lazy initialization and ensure metrics are registered
     Lazy::force(&HTTP_REQUEST_DURATION);
     Lazy::force(&HTTP_REQUEST_COUNTER);
+    Lazy::force(&HTTP_RESPONSES_BY_CLASS);
+    Lazy::force(&HTTP_APDEX_COUNTER);
+    Lazy::force(&HTTP_ERROR_RATIO);
+    Lazy::force(&HTTP_REQUEST_SIZE);
+    Lazy::force(&HTTP_RESPONSE_SIZE);
     Lazy::force(&DATABASE_QUERY_DURATION);
     Lazy::force(&DATABASE_QUERY_COUNTER);
     Lazy::force(&ITEMS_CREATED_COUNTER);
     Lazy::force(&ITEMS_UPDATED_COUNTER);
     Lazy::force(&ITEMS_DELETED_COUNTER);
     Lazy::force(&DATABASE_CONNECTIONS);
+    Lazy::force(&DATABASE_CONCURRENCY_LIMIT);
+    Lazy::force(&DATABASE_CALLS_IN_FLIGHT);
+    Lazy::force(&DATABASE_CALLS_SHED);
+    Lazy::force(&DATABASE_HEDGED_READS);
+    Lazy::force(&DATABASE_SHADOW_READS);
+    Lazy::force(&DATABASE_DUAL_WRITE_DIVERGENCES);
+    Lazy::force(&OUTBOX_EVENTS_COUNTER);
+    Lazy::force(&OUTBOX_PENDING_EVENTS);
+    Lazy::force(&EVENT_PUBLISH_COUNTER);
+    Lazy::force(&EVENT_PUBLISH_DURATION);
+    Lazy::force(&EVENT_REPLAY_COUNTER);
+    Lazy::force(&RETENTION_PURGED_COUNTER);
+    Lazy::force(&ITEMS_PUBLISHED_COUNTER);
+    Lazy::force(&CHANGE_REQUESTS_COUNTER);
+    Lazy::force(&CDC_EXPORTED_COUNTER);
+    Lazy::force(&CDC_EXPORT_SEQUENCE);
+    Lazy::force(&AUTH_DECISIONS_COUNTER);
+    Lazy::force(&OUTBOUND_TOKEN_COUNTER);
+    Lazy::force(&OUTBOUND_TOKEN_FETCH_DURATION);
+    Lazy::force(&ACCESS_LOG_DROPPED_COUNTER);
+    Lazy::force(&HTTP_SLOW_REQUESTS_COUNTER);
+    Lazy::force(&DATABASE_SLOW_QUERIES_COUNTER);
+    Lazy::force(&CHAOS_FAULTS_COUNTER);
+    Lazy::force(&DEPRECATED_CALLS_COUNTER);
+    Lazy::force(&DUPLICATE_SUBMISSIONS_COUNTER);
+    Lazy::force(&DATABASE_CANCELLED_OPERATIONS_COUNTER);
+    Lazy::force(&DEAD_LETTER_QUEUE_DEPTH);
+    Lazy::force(&DEAD_LETTERS_COUNTER);
+    Lazy::force(&ATTACHMENT_SCANS_COUNTER);
+    Lazy::force(&LEADER_ELECTION_LEADER);
+    Lazy::force(&DISTRIBUTED_LOCKS_COUNTER);
+    Lazy::force(&DISTRIBUTED_LOCK_HOLD_DURATION);
+    Lazy::force(&NOTIFICATIONS_COUNTER);
+    Lazy::force(&NOTIFICATION_SEND_DURATION);
 }
 
 /// Timer for measuring durations
@@ -97,6 +529,10 @@ impl Timer {
     pub fn elapsed_seconds(&self) -> f64 {
         self.start.elapsed().as_secs_f64()
     }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
 }
 
 impl Default for Timer {
@@ -127,9 +563,269 @@ pub fn track_database_query(operation: &str, repository: &str, success: bool, du
         .inc();
 }
 
+/// Endpoint label shared by requests beyond the unmatched endpoint cap
+pub const OTHER_ENDPOINT: &str = "other";
+
+/// Distinct labels recorded for requests that matched no route
+pub const MAX_UNMATCHED_ENDPOINTS: usize = 50;
+
+/// Endpoint labels of the HTTP metrics, bounded so that arbitrary request
+/// paths cannot create unbounded series
+///
+/// Route templates are always recorded and form the allowlist. The paths of
+/// requests that matched no route have ID-like segments collapsed to `{id}`,
+/// which often turns them back into a route template; beyond that, only the
+/// first `max_unmatched` distinct paths get their own label and the rest are
+/// counted as [`OTHER_ENDPOINT`].
+pub struct EndpointLabels {
+    routes: RwLock<HashSet<String>>,
+    unmatched: RwLock<HashSet<String>>,
+    max_unmatched: usize,
+}
+
+impl EndpointLabels {
+    pub fn new(max_unmatched: usize) -> Self {
+        Self {
+            routes: RwLock::new(HashSet::new()),
+            unmatched: RwLock::new(HashSet::new()),
+            max_unmatched,
+        }
+    }
+
+    /// Label for a request, from the route it matched or else its path
+    pub fn label(&self, route: Option<&str>, path: &str) -> String {
+        if let Some(route) = route {
+            let known = self
+                .routes
+                .read()
+                .is_ok_and(|routes| routes.contains(route));
+            if !known {
+                if let Ok(mut routes) = self.routes.write() {
+                    routes.insert(route.to_string());
+                }
+            }
+            return route.to_string();
+        }
+
+        let path = normalize_path(path);
+        let seen = |set: &RwLock<HashSet<String>>| set.read().is_ok_and(|set| set.contains(&path));
+        if seen(&self.routes) || seen(&self.unmatched) {
+            return path;
+        }
+        match self.unmatched.write() {
+            Ok(mut unmatched) if unmatched.len() < self.max_unmatched => {
+                unmatched.insert(path.clone());
+                path
+            }
+            _ => OTHER_ENDPOINT.to_string(),
+        }
+    }
+}
+
+/// Endpoint labels of requests handled by this process
+pub static ENDPOINT_LABELS: Lazy<EndpointLabels> =
+    Lazy::new(|| EndpointLabels::new(MAX_UNMATCHED_ENDPOINTS));
+
+/// Tenant label of requests served outside a tenant scope
+pub const NO_TENANT: &str = "none";
+
+/// Tenant label shared by tenants beyond the tenant label cap
+pub const OTHER_TENANT: &str = "other";
+
+/// Distinct tenants given their own label
+pub const MAX_TENANT_LABELS: usize = 100;
+
+/// Tenant labels of the HTTP metrics
+///
+/// Any valid tenant ID may be sent when provisioning is not required, so only
+/// the first `max` distinct tenants get their own label and the rest are
+/// counted as [`OTHER_TENANT`].
+pub struct TenantLabels {
+    tenants: RwLock<HashSet<String>>,
+    max: usize,
+}
+
+impl TenantLabels {
+    pub fn new(max: usize) -> Self {
+        Self {
+            tenants: RwLock::new(HashSet::new()),
+            max,
+        }
+    }
+
+    pub fn label(&self, tenant: Option<&str>) -> String {
+        let Some(tenant) = tenant else {
+            return NO_TENANT.to_string();
+        };
+        if self
+            .tenants
+            .read()
+            .is_ok_and(|tenants| tenants.contains(tenant))
+        {
+            return tenant.to_string();
+        }
+        match self.tenants.write() {
+            Ok(mut tenants) if tenants.len() < self.max => {
+                tenants.insert(tenant.to_string());
+                tenant.to_string()
+            }
+            _ => OTHER_TENANT.to_string(),
+        }
+    }
+}
+
+/// Tenant labels of requests handled by this process
+pub static TENANT_LABELS: Lazy<TenantLabels> = Lazy::new(|| TenantLabels::new(MAX_TENANT_LABELS));
+
+/// Replace path segments that look like IDs with `{id}`
+///
+/// Numbers, UUIDs, long hex strings and other long opaque segments are
+/// treated as IDs.
+pub fn normalize_path(path: &str) -> String {
+    path.split('/')
+        .map(|segment| if is_id_like(segment) { "{id}" } else { segment })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn is_id_like(segment: &str) -> bool {
+    !segment.is_empty()
+        && (segment.bytes().all(|b| b.is_ascii_digit())
+            || uuid::Uuid::try_parse(segment).is_ok()
+            || (segment.len() >= 16 && segment.bytes().all(|b| b.is_ascii_hexdigit()))
+            || segment.len() > 32)
+}
+
+/// Response time under which a request counts as satisfied for Apdex; up to
+/// four times this it is tolerating, and beyond that frustrated
+pub const APDEX_THRESHOLD_SECONDS: f64 = 0.5;
+
+/// Apdex satisfaction of a request; server errors are always frustrated
+pub fn apdex_satisfaction(status: u16, duration: f64) -> &'static str {
+    if status >= 500 || duration > 4.0 * APDEX_THRESHOLD_SECONDS {
+        "frustrated"
+    } else if duration > APDEX_THRESHOLD_SECONDS {
+        "tolerating"
+    } else {
+        "satisfied"
+    }
+}
+
+/// Requests and 5xx responses in the rolling error window
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ErrorCounts {
+    pub requests: u64,
+    pub server_errors: u64,
+}
+
+impl ErrorCounts {
+    /// Share of requests that failed with 5xx; zero without requests
+    pub fn ratio(&self) -> f64 {
+        if self.requests == 0 {
+            0.0
+        } else {
+            self.server_errors as f64 / self.requests as f64
+        }
+    }
+}
+
+struct ErrorSlot {
+    started: Instant,
+    counts: HashMap<String, ErrorCounts>,
+}
+
+/// Per-endpoint request and 5xx counts over a rolling window
+///
+/// The window is split into slots, and the oldest slot is dropped as time
+/// moves on, so the counts always cover between `window - slot` and `window`.
+pub struct ErrorRates {
+    window: Duration,
+    slot: Duration,
+    slots: Mutex<VecDeque<ErrorSlot>>,
+}
+
+impl ErrorRates {
+    pub fn new(window: Duration, slots: u32) -> Self {
+        Self {
+            window,
+            slot: window / slots.max(1),
+            slots: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Count a response, returning the endpoint's counts including it
+    pub fn record(&self, endpoint: &str, status: u16) -> ErrorCounts {
+        let Ok(mut slots) = self.slots.lock() else {
+            return ErrorCounts::default();
+        };
+        let now = Instant::now();
+        self.expire(&mut slots, now);
+        if slots
+            .back()
+            .is_none_or(|slot| now.duration_since(slot.started) >= self.slot)
+        {
+            slots.push_back(ErrorSlot {
+                started: now,
+                counts: HashMap::new(),
+            });
+        }
+
+        if let Some(slot) = slots.back_mut() {
+            let counts = slot.counts.entry(endpoint.to_string()).or_default();
+            counts.requests += 1;
+            if status >= 500 {
+                counts.server_errors += 1;
+            }
+        }
+        Self::sum(&slots, Some(endpoint))
+    }
+
+    /// Counts across all endpoints
+    pub fn total(&self) -> ErrorCounts {
+        let Ok(mut slots) = self.slots.lock() else {
+            return ErrorCounts::default();
+        };
+        self.expire(&mut slots, Instant::now());
+        Self::sum(&slots, None)
+    }
+
+    fn expire(&self, slots: &mut VecDeque<ErrorSlot>, now: Instant) {
+        while slots
+            .front()
+            .is_some_and(|slot| now.duration_since(slot.started) >= self.window)
+        {
+            slots.pop_front();
+        }
+    }
+
+    fn sum(slots: &VecDeque<ErrorSlot>, endpoint: Option<&str>) -> ErrorCounts {
+        let mut total = ErrorCounts::default();
+        let counts = slots.iter().flat_map(|slot| {
+            slot.counts
+                .iter()
+                .filter(|(label, _)| endpoint.is_none_or(|endpoint| endpoint == label.as_str()))
+                .map(|(_, counts)| counts)
+        });
+        for counts in counts {
+            total.requests += counts.requests;
+            total.server_errors += counts.server_errors;
+        }
+        total
+    }
+}
+
+/// Error counts of requests handled by this process over the last five minutes
+pub static ERROR_RATES: Lazy<ErrorRates> =
+    Lazy::new(|| ErrorRates::new(Duration::from_secs(300), 10));
+
 /// Track HTTP request
 pub fn track_http_request(method: &str, endpoint: &str, status: u16, duration: f64) {
     let status_str = status.to_string();
+    let class = format!("{}xx", status / 100);
 
     HTTP_REQUEST_DURATION
         .with_label_values(&[method, endpoint, &status_str])
@@ -138,6 +834,62 @@ pub fn track_http_request(method: &str, endpoint: &str, status: u16, duration: f
     HTTP_REQUEST_COUNTER
         .with_label_values(&[method, endpoint, &status_str])
         .inc();
+
+    HTTP_RESPONSES_BY_CLASS
+        .with_label_values(&[endpoint, &class])
+        .inc();
+
+    HTTP_APDEX_COUNTER
+        .with_label_values(&[endpoint, apdex_satisfaction(status, duration)])
+        .inc();
+
+    HTTP_ERROR_RATIO
+        .with_label_values(&[endpoint])
+        .set(ERROR_RATES.record(endpoint, status).ratio());
+}
+
+/// Track the body sizes of an HTTP request and its response
+///
+/// Bodies of unknown length, such as streamed responses, are not observed.
+pub fn track_http_body_sizes(
+    method: &str,
+    endpoint: &str,
+    tenant: &str,
+    request: Option<u64>,
+    response: Option<u64>,
+) {
+    let labels = [method, endpoint, tenant];
+    if let Some(size) = request {
+        HTTP_REQUEST_SIZE
+            .with_label_values(&labels)
+            .observe(size as f64);
+    }
+    if let Some(size) = response {
+        HTTP_RESPONSE_SIZE
+            .with_label_values(&labels)
+            .observe(size as f64);
+    }
+}
+
+/// Track a read sent a second time, by which attempt answered
+pub fn track_hedged_read(backend: &str, operation: &str, winner: &str) {
+    DATABASE_HEDGED_READS
+        .with_label_values(&[backend, operation, winner])
+        .inc();
+}
+
+/// Track a read mirrored to the shadow backend, by how the answers compared
+pub fn track_shadow_read(backend: &str, operation: &str, outcome: &str) {
+    DATABASE_SHADOW_READS
+        .with_label_values(&[backend, operation, outcome])
+        .inc();
+}
+
+/// Track a write the secondary backend failed to apply
+pub fn track_dual_write_divergence(backend: &str, operation: &str) {
+    DATABASE_DUAL_WRITE_DIVERGENCES
+        .with_label_values(&[backend, operation])
+        .inc();
 }
 
 /// Track business metrics
@@ -158,3 +910,252 @@ pub fn track_item_deleted() {
         .with_label_values(&[] as &[&str])
         .inc();
 }
+
+/// Track the outcome of an outbox dispatch round
+pub fn track_outbox_dispatch(published: usize, failed: usize) {
+    OUTBOX_EVENTS_COUNTER
+        .with_label_values(&["published"])
+        .inc_by(published as u64);
+    OUTBOX_EVENTS_COUNTER
+        .with_label_values(&["failed"])
+        .inc_by(failed as u64);
+}
+
+/// Track a single event delivery to a publisher
+pub fn track_event_publish(publisher: &str, success: bool, duration: f64) {
+    let status = if success { "success" } else { "error" };
+
+    EVENT_PUBLISH_DURATION
+        .with_label_values(&[publisher])
+        .observe(duration);
+
+    EVENT_PUBLISH_COUNTER
+        .with_label_values(&[publisher, status])
+        .inc();
+}
+
+/// Track an event replayed to `sink`
+pub fn track_event_replay(sink: &str, success: bool) {
+    let status = if success { "success" } else { "error" };
+    EVENT_REPLAY_COUNTER
+        .with_label_values(&[sink, status])
+        .inc();
+}
+
+/// Track a draft published, by `request` or on `schedule`
+pub fn track_item_published(trigger: &str) {
+    ITEMS_PUBLISHED_COUNTER.with_label_values(&[trigger]).inc();
+}
+
+/// Track a change request `pending` approval, or `applied`, `rejected` or `failed`
+pub fn track_change_request(outcome: &str) {
+    CHANGE_REQUESTS_COUNTER.with_label_values(&[outcome]).inc();
+}
+
+/// Track a file of `changes` item changes exported, up to outbox `sequence`
+pub fn track_cdc_export(changes: usize, sequence: u64) {
+    CDC_EXPORTED_COUNTER
+        .with_label_values(&["changes"])
+        .inc_by(changes as u64);
+    CDC_EXPORTED_COUNTER.with_label_values(&["files"]).inc();
+    CDC_EXPORT_SEQUENCE.set(i64::try_from(sequence).unwrap_or(i64::MAX));
+}
+
+/// Track records purged by a retention policy
+pub fn track_retention_purge(target: &str, records: usize, dry_run: bool) {
+    let mode = if dry_run { "dry_run" } else { "purged" };
+
+    RETENTION_PURGED_COUNTER
+        .with_label_values(&[target, mode])
+        .inc_by(records as u64);
+}
+
+/// Track the outcome of authenticating a request
+pub fn track_auth_decision(outcome: &str, issuer: &str) {
+    AUTH_DECISIONS_COUNTER
+        .with_label_values(&[outcome, issuer])
+        .inc();
+}
+
+/// Track how an outbound access token was obtained
+pub fn track_outbound_token(result: &str) {
+    OUTBOUND_TOKEN_COUNTER.with_label_values(&[result]).inc();
+}
+
+/// Track a request to the outbound token endpoint
+pub fn track_outbound_token_fetch(success: bool, duration: f64) {
+    let status = if success { "success" } else { "error" };
+
+    OUTBOUND_TOKEN_FETCH_DURATION
+        .with_label_values(&[status])
+        .observe(duration);
+}
+
+/// Track an access log line dropped instead of written
+pub fn track_access_log_dropped() {
+    ACCESS_LOG_DROPPED_COUNTER
+        .with_label_values(&[] as &[&str])
+        .inc();
+}
+
+/// Track a request that exceeded the slow request threshold
+pub fn track_slow_request(endpoint: &str) {
+    HTTP_SLOW_REQUESTS_COUNTER
+        .with_label_values(&[endpoint])
+        .inc();
+}
+
+/// Track a repository call that exceeded the slow query threshold
+pub fn track_slow_query(operation: &str, repository: &str) {
+    DATABASE_SLOW_QUERIES_COUNTER
+        .with_label_values(&[operation, repository])
+        .inc();
+}
+
+/// Track a fault injected into a request
+pub fn track_fault_injected(fault: &str) {
+    CHAOS_FAULTS_COUNTER.with_label_values(&[fault]).inc();
+}
+
+/// Track a call to a deprecated endpoint
+pub fn track_deprecated_call(method: &str, path: &str) {
+    DEPRECATED_CALLS_COUNTER
+        .with_label_values(&[method, path])
+        .inc();
+}
+
+/// Track a duplicate item submission answered with the earlier item
+pub fn track_duplicate_submission() {
+    DUPLICATE_SUBMISSIONS_COUNTER
+        .with_label_values(&[] as &[&str])
+        .inc();
+}
+
+/// Track work added to the dead letter queue
+pub fn track_dead_letter(source: &str) {
+    DEAD_LETTERS_COUNTER.with_label_values(&[source]).inc();
+}
+
+/// Track what became of a notification: `sent`, `retried`, `failed` or
+/// `dropped`
+pub fn track_notification(channel: &str, kind: &str, outcome: &str) {
+    NOTIFICATIONS_COUNTER
+        .with_label_values(&[channel, kind, outcome])
+        .inc();
+}
+
+/// Track one attempt at sending a notification
+pub fn track_notification_send(provider: &str, success: bool, duration: f64) {
+    let status = if success { "success" } else { "error" };
+
+    NOTIFICATION_SEND_DURATION
+        .with_label_values(&[provider, status])
+        .observe(duration);
+}
+
+/// Track a distributed lock attempt: `acquired`, `contended`, `timed_out`,
+/// `failed`, or `expired` for a lock released after its expiry
+pub fn track_lock(backend: &str, outcome: &str) {
+    DISTRIBUTED_LOCKS_COUNTER
+        .with_label_values(&[backend, outcome])
+        .inc();
+}
+
+/// Track how long a distributed lock was held
+pub fn track_lock_held(backend: &str, seconds: f64) {
+    DISTRIBUTED_LOCK_HOLD_DURATION
+        .with_label_values(&[backend])
+        .observe(seconds);
+}
+
+/// Track a repository call cancelled by the request's deadline
+pub fn track_cancelled_operation(operation: &str, repository: &str) {
+    DATABASE_CANCELLED_OPERATIONS_COUNTER
+        .with_label_values(&[operation, repository])
+        .inc();
+}
+
+/// Track an attachment found `clean` or `infected` by `scanner`, or an
+/// `error` scanning it
+pub fn track_attachment_scan(scanner: &str, result: &str) {
+    ATTACHMENT_SCANS_COUNTER
+        .with_label_values(&[scanner, result])
+        .inc();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_normalization() {
+        assert_eq!(normalize_path("/api/v1/items"), "/api/v1/items");
+        assert_eq!(
+            normalize_path("/api/v1/items/0b5e4a8e-3c2f-4f6a-9d1e-2a7b8c9d0e1f/permissions/42"),
+            "/api/v1/items/{id}/permissions/{id}"
+        );
+        assert_eq!(normalize_path("/blobs/5d41402abc4b2a76b9719d911017c592"), "/blobs/{id}");
+        assert_eq!(normalize_path(&format!("/t/{}", "x".repeat(40))), "/t/{id}");
+        assert_eq!(
+            normalize_path("/api/v1/items/slug/blue-widget"),
+            "/api/v1/items/slug/blue-widget"
+        );
+    }
+
+    #[test]
+    fn test_apdex_satisfaction() {
+        assert_eq!(apdex_satisfaction(200, 0.1), "satisfied");
+        assert_eq!(apdex_satisfaction(404, 1.0), "tolerating");
+        assert_eq!(apdex_satisfaction(200, 2.5), "frustrated");
+        assert_eq!(apdex_satisfaction(503, 0.01), "frustrated");
+    }
+
+    #[test]
+    fn test_error_rates_roll_over() {
+        let rates = ErrorRates::new(Duration::from_millis(200), 2);
+        rates.record("/api/v1/items", 200);
+        rates.record("/api/v1/items", 404);
+        let counts = rates.record("/api/v1/items", 500);
+        assert_eq!(
+            counts,
+            ErrorCounts {
+                requests: 3,
+                server_errors: 1
+            }
+        );
+        assert_eq!(rates.record("/health", 200).ratio(), 0.0);
+        assert_eq!(rates.total().requests, 4);
+
+        std::thread::sleep(Duration::from_millis(250));
+        assert_eq!(rates.total(), ErrorCounts::default());
+        assert_eq!(rates.record("/api/v1/items", 502).ratio(), 1.0);
+    }
+
+    #[test]
+    fn test_endpoint_labels_are_capped() {
+        let labels = EndpointLabels::new(2);
+        assert_eq!(
+            labels.label(Some("/api/v1/items/{id}"), "/api/v1/items/1"),
+            "/api/v1/items/{id}"
+        );
+
+        // An unmatched path that normalizes to a known route uses the route
+        assert_eq!(labels.label(None, "/api/v1/items/7"), "/api/v1/items/{id}");
+
+        assert_eq!(labels.label(None, "/wp-login.php"), "/wp-login.php");
+        assert_eq!(labels.label(None, "/.env"), "/.env");
+        assert_eq!(labels.label(None, "/.git/config"), OTHER_ENDPOINT);
+        // Paths already labelled keep their label, and routes are never capped
+        assert_eq!(labels.label(None, "/.env"), "/.env");
+        assert_eq!(labels.label(Some("/health"), "/health"), "/health");
+    }
+
+    #[test]
+    fn test_tenant_labels_are_capped() {
+        let labels = TenantLabels::new(1);
+        assert_eq!(labels.label(None), NO_TENANT);
+        assert_eq!(labels.label(Some("acme")), "acme");
+        assert_eq!(labels.label(Some("globex")), OTHER_TENANT);
+        assert_eq!(labels.label(Some("acme")), "acme");
+    }
+}
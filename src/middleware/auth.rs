@@ -2,37 +2,60 @@ use axum::{
     extract::{FromRequestParts, Request},
     http::{header, request::Parts, StatusCode},
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Response},
 };
-use jsonwebtoken::{decode, DecodingKey, Validation};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::sync::Arc;
 
-/// Simple JWT claims
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::{auth::JwtValidator, error::AppError};
+
+/// Role granting access to administrative operations
+pub const ADMIN_ROLE: &str = "admin";
+
+/// Verified JWT claims, available to handlers through the auth extractors
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
     pub exp: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    /// Audiences, given in the token as a string or a list
+    #[serde(
+        default,
+        deserialize_with = "one_or_many",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub aud: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iat: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<u64>,
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// OAuth scopes, space-separated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// Any other claims in the token
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
-/// Simple auth configuration
-#[derive(Clone)]
-pub struct AuthConfig {
-    pub enabled: bool,
-    pub jwt_secret: Option<String>,
-}
-
-impl AuthConfig {
-    pub fn from_env() -> Self {
-        let enabled = std::env::var("AUTH_ENABLED")
-            .map(|v| v.parse().unwrap_or(false))
-            .unwrap_or(false);
+impl Claims {
+    /// Whether the principal holds the given role
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
 
-        let jwt_secret = std::env::var("JWT_SECRET").ok();
+    /// Whether the token was granted the given OAuth scope
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scope
+            .as_deref()
+            .is_some_and(|scopes| scopes.split_whitespace().any(|s| s == scope))
+    }
 
-        Self {
-            enabled,
-            jwt_secret,
-        }
+    /// Whether the principal is an administrator
+    pub fn is_admin(&self) -> bool {
+        self.has_role(ADMIN_ROLE)
     }
 }
 
@@ -55,6 +78,30 @@ where
     }
 }
 
+/// Administrator extractor, rejecting anonymous (401) and non-admin (403) callers
+pub struct AdminUser(pub Claims);
+
+impl<S> FromRequestParts<S> for AdminUser
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let claims = parts
+            .extensions
+            .get::<Claims>()
+            .cloned()
+            .ok_or_else(|| AppError::Unauthorized("Authentication required".to_string()))?;
+
+        if !claims.is_admin() {
+            return Err(AppError::Forbidden("Administrator role required".to_string()));
+        }
+
+        Ok(AdminUser(claims))
+    }
+}
+
 /// Optional authenticated user extractor
 pub struct OptionalAuthUser(pub Option<Claims>);
 
@@ -69,34 +116,87 @@ where
     }
 }
 
-/// Simple JWT authentication middleware
-pub async fn auth_middleware(mut req: Request, next: Next, config: AuthConfig) -> Response {
+/// Whether a path is protected by authentication (health, metrics and
+/// documentation never are)
+fn is_protected(path: &str) -> bool {
+    path.starts_with("/api/") || path.starts_with("/admin/") || path.starts_with("/debug/")
+}
+
+/// JWT authentication middleware
+///
+/// Requests with a valid bearer token carry its `Claims` in their extensions;
+/// requests without one (or with an invalid one) continue unauthenticated.
+/// A fail-closed validator instead answers protected routes with 503 while
+/// tokens cannot be verified at all.
+pub async fn auth_middleware(
+    mut req: Request,
+    next: Next,
+    validator: Arc<JwtValidator>,
+) -> Response {
     // Skip if auth is disabled
-    if !config.enabled {
+    if !validator.enabled() {
         return next.run(req).await;
     }
 
-    // Extract token from Authorization header
-    if let Some(auth_header) = req.headers().get(header::AUTHORIZATION) {
-        if let Ok(auth_str) = auth_header.to_str() {
-            if let Some(token) = auth_str.strip_prefix("Bearer ") {
-                // Simple JWT validation
-                if let Some(secret) = &config.jwt_secret {
-                    let key = DecodingKey::from_secret(secret.as_bytes());
-                    let validation = Validation::default();
-
-                    if let Ok(token_data) = decode::<Claims>(token, &key, &validation) {
-                        // Add claims to request extensions
-                        req.extensions_mut().insert(token_data.claims);
-                    }
-                }
+    let fail_closed = validator.fail_closed() && is_protected(req.uri().path());
+    if fail_closed {
+        if let Some(reason) = validator.check_available().await {
+            tracing::warn!("Refusing request while authentication is unavailable: {}", reason);
+            return unavailable();
+        }
+    }
+
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    // Anonymous requests to unprotected routes (health checks, metrics
+    // scrapes) are not authentication decisions worth counting
+    if token.is_some() || is_protected(req.uri().path()) {
+        match validator.authenticate(token).await {
+            Some(Ok(claims)) => {
+                req.extensions_mut().insert(claims);
+            }
+            Some(Err(e)) if fail_closed && e.is_unavailable() => {
+                tracing::warn!("Refusing request, token could not be verified: {}", e);
+                return unavailable();
             }
+            Some(Err(_)) | None => {}
         }
     }
 
     next.run(req).await
 }
 
+/// Reject anonymous requests, for route groups requiring authentication
+pub async fn require_auth_middleware(req: Request, next: Next) -> Response {
+    if req.extensions().get::<Claims>().is_none() {
+        return AppError::Unauthorized("Authentication required".to_string()).into_response();
+    }
+    next.run(req).await
+}
+
+fn unavailable() -> Response {
+    AppError::ServiceUnavailable("Authentication is temporarily unavailable".to_string())
+        .into_response()
+}
+
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match Option::<OneOrMany>::deserialize(deserializer)? {
+        None => Vec::new(),
+        Some(OneOrMany::One(aud)) => vec![aud],
+        Some(OneOrMany::Many(aud)) => aud,
+    })
+}
+
 /// Middleware to require authentication for specific routes
 pub async fn require_auth(req: Request, next: Next) -> Result<Response, StatusCode> {
     // Check if claims exist in extensions (set by auth middleware)
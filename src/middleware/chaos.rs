@@ -0,0 +1,196 @@
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{HeaderName, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    str::FromStr,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tracing::debug;
+use utoipa::ToSchema;
+
+use super::observability::random_fraction;
+use crate::{error::AppError, metrics::track_fault_injected};
+
+/// Header requesting a fault for one request: `latency=<ms>`, `error=<status>`
+/// or `drop`
+pub static X_CHAOS_FAULT: HeaderName = HeaderName::from_static("x-chaos-fault");
+
+/// Longest latency a fault may add
+pub const MAX_FAULT_LATENCY_MS: u64 = 60_000;
+
+/// A failure injected into a request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Fault {
+    /// Delay the request before handling it
+    Latency { ms: u64 },
+    /// Answer with this status instead of handling the request
+    Error { status: u16 },
+    /// Abort the connection after the response headers
+    Drop,
+}
+
+impl Fault {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Latency { .. } => "latency",
+            Self::Error { .. } => "error",
+            Self::Drop => "drop",
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::Latency { ms } if *ms > MAX_FAULT_LATENCY_MS => {
+                Err(format!("Latency must be at most {MAX_FAULT_LATENCY_MS} ms"))
+            }
+            Self::Error { status } if !(400..=599).contains(status) => {
+                Err("Error status must be between 400 and 599".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl FromStr for Fault {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fault = match s.trim().split_once('=') {
+            Some(("latency", ms)) => Self::Latency {
+                ms: ms.parse().map_err(|_| "latency must be milliseconds")?,
+            },
+            Some(("error", status)) => Self::Error {
+                status: status.parse().map_err(|_| "error must be a status code")?,
+            },
+            None if s.trim() == "drop" => Self::Drop,
+            _ => return Err("expected latency=<ms>, error=<status> or drop".to_string()),
+        };
+        fault.validate()?;
+        Ok(fault)
+    }
+}
+
+/// Injects `fault` into a share of the requests it matches
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FaultRule {
+    /// Requests whose path starts with this are affected
+    #[serde(default = "default_path_prefix")]
+    pub path_prefix: String,
+    /// Only requests with this method are affected, when set
+    #[serde(default)]
+    pub method: Option<String>,
+    /// Share of matching requests affected, 0 to 100
+    pub percentage: f64,
+    pub fault: Fault,
+}
+
+fn default_path_prefix() -> String {
+    "/".to_string()
+}
+
+impl FaultRule {
+    fn matches(&self, req: &Request) -> bool {
+        req.uri().path().starts_with(&self.path_prefix)
+            && self
+                .method
+                .as_deref()
+                .is_none_or(|method| req.method().as_str().eq_ignore_ascii_case(method))
+    }
+}
+
+/// Fault rules in effect, changed through the admin API
+///
+/// Faults are never injected into `/admin/` routes or health probes, so
+/// injection can always be turned off again and instances are not restarted
+/// by their orchestrator.
+#[derive(Debug, Default)]
+pub struct FaultInjector {
+    rules: RwLock<Vec<FaultRule>>,
+}
+
+impl FaultInjector {
+    pub fn rules(&self) -> Vec<FaultRule> {
+        self.rules.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Replace every rule
+    pub fn set_rules(&self, rules: Vec<FaultRule>) -> Result<(), AppError> {
+        for rule in &rules {
+            if !(0.0..=100.0).contains(&rule.percentage) {
+                return Err(AppError::BadRequest(
+                    "percentage must be between 0 and 100".to_string(),
+                ));
+            }
+            rule.fault.validate().map_err(AppError::BadRequest)?;
+        }
+        *self.rules.write().unwrap_or_else(|e| e.into_inner()) = rules;
+        Ok(())
+    }
+
+    /// Fault to inject into `req`: the one it asks for by header, or else the
+    /// first matching rule that fires
+    fn fault_for(&self, req: &Request) -> Result<Option<Fault>, String> {
+        if let Some(value) = req.headers().get(&X_CHAOS_FAULT) {
+            let value = value.to_str().map_err(|_| "invalid header value")?;
+            return value.parse().map(Some);
+        }
+        let rules = self.rules.read().unwrap_or_else(|e| e.into_inner());
+        Ok(rules
+            .iter()
+            .find(|rule| rule.matches(req) && random_fraction() * 100.0 < rule.percentage)
+            .map(|rule| rule.fault.clone()))
+    }
+}
+
+fn is_exempt(path: &str) -> bool {
+    path.starts_with("/admin/") || path.starts_with("/health")
+}
+
+/// Fault injection middleware - delays, fails or drops requests as the
+/// injector's rules or the `X-Chaos-Fault` header ask, when enabled
+pub async fn chaos_middleware(
+    req: Request,
+    next: Next,
+    injector: Option<Arc<FaultInjector>>,
+) -> Response {
+    let Some(injector) = injector.filter(|_| !is_exempt(req.uri().path())) else {
+        return next.run(req).await;
+    };
+
+    let fault = match injector.fault_for(&req) {
+        Ok(fault) => fault,
+        Err(e) => {
+            return AppError::BadRequest(format!("Invalid {X_CHAOS_FAULT} header: {e}"))
+                .into_response()
+        }
+    };
+    let Some(fault) = fault else {
+        return next.run(req).await;
+    };
+
+    debug!(fault = fault.name(), path = %req.uri().path(), "Injecting fault");
+    track_fault_injected(fault.name());
+    match fault {
+        Fault::Latency { ms } => {
+            tokio::time::sleep(Duration::from_millis(ms)).await;
+            next.run(req).await
+        }
+        Fault::Error { status } => {
+            let status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+            (status, "Fault injected").into_response()
+        }
+        // A body that fails makes the server abort the connection mid-response
+        Fault::Drop => Response::new(Body::from_stream(futures_util::stream::once(async {
+            Err::<axum::body::Bytes, _>(std::io::Error::other(
+                "connection dropped by fault injection",
+            ))
+        }))),
+    }
+}
@@ -0,0 +1,60 @@
+use axum::{
+    extract::Request,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::time::Duration;
+
+use crate::{
+    clock::SharedClock,
+    context::RequestContext,
+    error::{AppError, AppResult},
+    middleware::{auth::Claims, observability::RequestId},
+    tenancy::current_tenant,
+};
+
+/// Header in which clients say how long they will wait, in milliseconds
+pub const REQUEST_TIMEOUT_HEADER: &str = "x-request-timeout";
+
+/// Run the rest of the stack in the request's [`RequestContext`]
+///
+/// Sits inside the auth and tenancy middleware, so the caller and tenant are
+/// known by the time it runs. The deadline is the shorter of `timeout` and the
+/// client's `X-Request-Timeout`, measured by `clock`.
+pub async fn request_context_middleware(
+    req: Request,
+    next: Next,
+    timeout: Option<Duration>,
+    clock: SharedClock,
+) -> Response {
+    let timeout = match requested_timeout(&req) {
+        Ok(requested) => [timeout, requested].into_iter().flatten().min(),
+        Err(e) => return e.into_response(),
+    };
+    let context = RequestContext {
+        request_id: req.extensions().get::<RequestId>().map(|id| id.0.clone()),
+        tenant: current_tenant(),
+        principal: req
+            .extensions()
+            .get::<Claims>()
+            .map(|claims| claims.sub.clone()),
+        deadline: timeout.map(|timeout| clock.instant() + timeout),
+        ..Default::default()
+    }
+    .with_clock(clock);
+    context.scope(next.run(req)).await
+}
+
+fn requested_timeout(req: &Request) -> AppResult<Option<Duration>> {
+    let Some(value) = req.headers().get(REQUEST_TIMEOUT_HEADER) else {
+        return Ok(None);
+    };
+    value
+        .to_str()
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .map(|ms| Some(Duration::from_millis(ms)))
+        .ok_or_else(|| {
+            AppError::BadRequest("X-Request-Timeout must be a number of milliseconds".to_string())
+        })
+}
@@ -0,0 +1,123 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::header::{CONTENT_LENGTH, CONTENT_TYPE},
+    middleware::Next,
+    response::Response,
+};
+use serde_json::{json, Map, Value};
+
+use crate::middleware::observability::RequestId;
+
+/// Response envelope middleware - wraps successful JSON responses as
+/// `{"data": <resource>, "meta": {...}}`
+///
+/// Added to every route group but the probes, so orchestrators and scrapers
+/// keep reading bare responses. Errors, JSON:API documents and other content
+/// types pass through untouched.
+pub async fn envelope_middleware(req: Request, next: Next) -> Response {
+    let request_id = req.extensions().get::<RequestId>().map(|id| id.0.clone());
+    let response = next.run(req).await;
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .is_some_and(|value| value.as_bytes().starts_with(b"application/json"));
+    if !is_json || !response.status().is_success() {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = to_bytes(body, usize::MAX).await else {
+        parts.headers.remove(CONTENT_LENGTH);
+        return Response::from_parts(parts, Body::empty());
+    };
+    let Ok(data) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    let mut meta = Map::new();
+    if let Some(request_id) = request_id {
+        meta.insert("request_id".to_string(), Value::String(request_id));
+    }
+    let envelope = json!({ "data": data, "meta": meta }).to_string();
+    parts.headers.remove(CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(envelope))
+}
+
+/// Wrap the JSON success responses `spec` documents for enveloped routes the
+/// way [`envelope_middleware`] wraps them when served
+pub fn document(spec: &mut Value) {
+    let Some(paths) = spec.get_mut("paths").and_then(Value::as_object_mut) else {
+        return;
+    };
+    let enveloped = paths.iter_mut().filter(|(path, _)| {
+        ["/api/v1/", "/admin/", "/share/"]
+            .iter()
+            .any(|p| path.starts_with(p))
+    });
+    for (_, operations) in enveloped {
+        let responses = operations
+            .as_object_mut()
+            .into_iter()
+            .flat_map(|operations| operations.values_mut())
+            .filter_map(|operation| operation.get_mut("responses")?.as_object_mut());
+        for responses in responses {
+            for (_, response) in responses
+                .iter_mut()
+                .filter(|(code, _)| code.starts_with('2'))
+            {
+                let Some(schema) = response.pointer_mut("/content/application~1json/schema") else {
+                    continue;
+                };
+                *schema = json!({
+                    "type": "object",
+                    "required": ["data", "meta"],
+                    "properties": {
+                        "data": schema.take(),
+                        "meta": {
+                            "type": "object",
+                            "properties": {
+                                "request_id": {
+                                    "type": "string",
+                                    "description": "ID of the request, as in `X-Request-Id`"
+                                }
+                            }
+                        }
+                    }
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_documented_responses_are_enveloped() {
+        let item = json!({ "$ref": "#/components/schemas/Item" });
+        let operation = |schema: &Value| {
+            json!({ "get": { "responses": {
+                "200": { "content": { "application/json": { "schema": schema } } },
+                "404": { "content": { "application/json": { "schema": { "$ref": "#/components/schemas/ErrorResponse" } } } }
+            } } })
+        };
+        let mut spec = json!({ "paths": {
+            "/api/v1/items/{id}": operation(&item),
+            "/health": operation(&item),
+        } });
+
+        document(&mut spec);
+
+        let responses = &spec["paths"]["/api/v1/items/{id}"]["get"]["responses"];
+        let schema = &responses["200"]["content"]["application/json"]["schema"];
+        assert_eq!(schema["properties"]["data"], item);
+        assert!(schema["properties"]["meta"].is_object());
+        let error = &responses["404"]["content"]["application/json"]["schema"];
+        assert!(error.get("$ref").is_some());
+        // Probes are not enveloped
+        let probe = &spec["paths"]["/health"]["get"]["responses"]["200"];
+        assert_eq!(probe["content"]["application/json"]["schema"], item);
+    }
+}
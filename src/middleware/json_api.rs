@@ -0,0 +1,50 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{
+        header::{CONTENT_LENGTH, CONTENT_TYPE},
+        HeaderValue,
+    },
+    middleware::Next,
+    response::Response,
+};
+
+use crate::jsonapi::{accepts_json_api, error_document, sends_json_api, JSON_API};
+
+/// Largest error body rewritten; error bodies are built by the service and
+/// far smaller
+const MAX_ERROR_BODY_BYTES: usize = 64 * 1024;
+
+/// JSON:API error middleware - rewrites error responses into JSON:API error
+/// documents for clients that accept or send JSON:API
+///
+/// Sits outside authentication and tenancy, so their errors are rewritten
+/// too. Other responses pass through untouched.
+pub async fn json_api_error_middleware(req: Request, next: Next) -> Response {
+    let negotiated = accepts_json_api(req.headers()) || sends_json_api(req.headers());
+    let response = next.run(req).await;
+    let status = response.status();
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .is_some_and(|value| value.as_bytes().starts_with(b"application/json"));
+    if !negotiated || !is_json || !(status.is_client_error() || status.is_server_error()) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = to_bytes(body, MAX_ERROR_BODY_BYTES).await else {
+        parts.headers.remove(CONTENT_LENGTH);
+        return Response::from_parts(parts, Body::empty());
+    };
+    let Ok(error) = serde_json::from_slice(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    let document = error_document(status.as_u16(), &error).to_string();
+    parts.headers.remove(CONTENT_LENGTH);
+    parts
+        .headers
+        .insert(CONTENT_TYPE, HeaderValue::from_static(JSON_API));
+    Response::from_parts(parts, Body::from(document))
+}
@@ -0,0 +1,135 @@
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{
+        self,
+        header::{ACCESS_CONTROL_REQUEST_METHOD, ALLOW, CONTENT_LENGTH},
+        HeaderValue, Method, StatusCode,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Utc;
+use futures_util::future::Either;
+use std::task::{Context, Poll};
+use tower::{util::Oneshot, Layer, Service, ServiceExt};
+use tower_http::cors::{Cors, CorsLayer};
+
+use crate::{
+    error::{ErrorCode, ErrorResponse},
+    middleware::observability::X_REQUEST_ID,
+};
+
+/// Method handling middleware - answers `OPTIONS` with the methods a route
+/// supports, and gives 405 responses the structured error body
+///
+/// The router already knows which methods each route has: it answers others
+/// with an empty 405 listing them in `Allow`. That response has been through
+/// the rest of the stack by the time it gets here, so its headers are kept.
+/// CORS preflight requests are answered by the CORS layer instead.
+pub async fn method_middleware(req: Request, next: Next) -> Response {
+    let method = req.method().clone();
+    let response = next.run(req).await;
+    if response.status() != StatusCode::METHOD_NOT_ALLOWED {
+        return response;
+    }
+
+    let allow = allowed_methods(&response);
+    let (mut parts, _) = response.into_parts();
+    parts.headers.remove(CONTENT_LENGTH);
+    if let Ok(value) = HeaderValue::from_str(&allow) {
+        parts.headers.insert(ALLOW, value);
+    }
+    if method == Method::OPTIONS {
+        parts.status = StatusCode::NO_CONTENT;
+        return Response::from_parts(parts, Body::empty());
+    }
+
+    let error = ErrorResponse {
+        error: ErrorCode::MethodNotAllowed,
+        message: format!("{method} is not supported here; use one of {allow}"),
+        details: None,
+        timestamp: Utc::now(),
+        request_id: parts
+            .headers
+            .get(&X_REQUEST_ID)
+            .and_then(|id| id.to_str().ok())
+            .map(str::to_string),
+    };
+    (parts, Json(error)).into_response()
+}
+
+/// CORS that leaves `OPTIONS` requests other than preflights to the router
+///
+/// `CorsLayer` answers every `OPTIONS` request as a preflight, so on its own
+/// clients probing a route for its methods would never see `Allow`.
+#[derive(Clone)]
+pub struct PreflightCorsLayer {
+    cors: CorsLayer,
+}
+
+impl PreflightCorsLayer {
+    pub fn new(cors: CorsLayer) -> Self {
+        Self { cors }
+    }
+}
+
+impl<S: Clone> Layer<S> for PreflightCorsLayer {
+    type Service = PreflightCors<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        PreflightCors {
+            cors: self.cors.layer(inner.clone()),
+            inner,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct PreflightCors<S> {
+    cors: Cors<S>,
+    inner: S,
+}
+
+impl<S, B, ResBody> Service<http::Request<B>> for PreflightCors<S>
+where
+    S: Service<http::Request<B>, Response = http::Response<ResBody>> + Clone,
+    ResBody: Default,
+{
+    type Response = http::Response<ResBody>;
+    type Error = S::Error;
+    type Future =
+        Either<<Cors<S> as Service<http::Request<B>>>::Future, Oneshot<S, http::Request<B>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.cors.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let preflight = req.method() != Method::OPTIONS
+            || req.headers().contains_key(ACCESS_CONTROL_REQUEST_METHOD);
+        if preflight {
+            Either::Left(self.cors.call(req))
+        } else {
+            Either::Right(self.inner.clone().oneshot(req))
+        }
+    }
+}
+
+/// The router's `Allow` list, with `OPTIONS` added
+fn allowed_methods(response: &Response) -> String {
+    let mut methods: Vec<&str> = response
+        .headers()
+        .get_all(ALLOW)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|method| !method.is_empty())
+        .collect();
+    if !methods.contains(&"OPTIONS") {
+        methods.push("OPTIONS");
+    }
+    methods.join(", ")
+}
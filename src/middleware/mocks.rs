@@ -0,0 +1,278 @@
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+#[allow(unused_imports)] // Used in #[schema(example = json!({...}))] attributes
+use serde_json::json;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tracing::debug;
+use utoipa::ToSchema;
+
+use crate::error::AppError;
+
+/// Longest latency a mock endpoint may add
+pub const MAX_MOCK_LATENCY_MS: u64 = 60_000;
+
+/// Most mock endpoints registered at once
+pub const MAX_MOCK_ENDPOINTS: usize = 100;
+
+/// A canned response served for a method and path, as registered
+#[derive(Debug, Clone, PartialEq, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "method": "GET",
+    "path": "/api/v1/orders/{id}",
+    "status": 200,
+    "body": { "id": "order-1", "total": 42 },
+    "latency_ms": 250
+}))]
+pub struct MockEndpointRequest {
+    pub method: String,
+    /// Path to answer; a `{name}` segment matches any single segment
+    pub path: String,
+    #[serde(default = "default_status")]
+    pub status: u16,
+    /// Extra response headers
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// JSON body of the response, none when unset
+    #[serde(default)]
+    pub body: Option<serde_json::Value>,
+    /// Delay before answering
+    #[serde(default)]
+    pub latency_ms: u64,
+}
+
+fn default_status() -> u16 {
+    200
+}
+
+/// A registered mock endpoint
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct MockEndpoint {
+    pub id: String,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    pub headers: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<serde_json::Value>,
+    pub latency_ms: u64,
+}
+
+impl MockEndpointRequest {
+    fn validate(&self) -> Result<(), String> {
+        Method::from_bytes(self.method.as_bytes()).map_err(|_| "Invalid method".to_string())?;
+        if !self.path.starts_with('/') {
+            return Err("Path must start with /".to_string());
+        }
+        if is_exempt(&self.path) {
+            return Err("Admin and health routes cannot be mocked".to_string());
+        }
+        if !(200..=599).contains(&self.status) {
+            return Err("Status must be between 200 and 599".to_string());
+        }
+        if self.latency_ms > MAX_MOCK_LATENCY_MS {
+            return Err(format!("Latency must be at most {MAX_MOCK_LATENCY_MS} ms"));
+        }
+        for (name, value) in &self.headers {
+            HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| format!("Invalid header name: {name}"))?;
+            HeaderValue::from_str(value).map_err(|_| format!("Invalid value for header {name}"))?;
+        }
+        Ok(())
+    }
+}
+
+impl MockEndpoint {
+    fn matches(&self, method: &Method, path: &str) -> bool {
+        if !method.as_str().eq_ignore_ascii_case(&self.method) {
+            return false;
+        }
+        let mut expected = self.path.trim_end_matches('/').split('/');
+        let mut actual = path.trim_end_matches('/').split('/');
+        loop {
+            match (expected.next(), actual.next()) {
+                (None, None) => return true,
+                (Some(pattern), Some(segment)) => {
+                    let wildcard = pattern.starts_with('{') && pattern.ends_with('}');
+                    let matched = if wildcard {
+                        !segment.is_empty()
+                    } else {
+                        pattern == segment
+                    };
+                    if !matched {
+                        return false;
+                    }
+                }
+                _ => return false,
+            }
+        }
+    }
+
+    fn response(&self) -> Response {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::OK);
+        let mut response = match &self.body {
+            Some(body) => (status, Json(body.clone())).into_response(),
+            None => status.into_response(),
+        };
+        for (name, value) in &self.headers {
+            if let (Ok(name), Ok(value)) =
+                (HeaderName::from_bytes(name.as_bytes()), HeaderValue::from_str(value))
+            {
+                response.headers_mut().insert(name, value);
+            }
+        }
+        response
+    }
+}
+
+/// Mock endpoints registered through the admin API, for developing clients
+/// against endpoints that do not exist yet
+///
+/// Mocks answer ahead of the real routes, so they can also stand in for those.
+/// They are kept in memory on one instance and never apply to `/admin/` routes
+/// or health probes.
+#[derive(Debug, Default)]
+pub struct MockRegistry {
+    endpoints: RwLock<Vec<MockEndpoint>>,
+}
+
+impl MockRegistry {
+    pub fn endpoints(&self) -> Vec<MockEndpoint> {
+        self.endpoints
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Register a mock endpoint, replacing any for the same method and path
+    pub fn register(&self, request: MockEndpointRequest) -> Result<MockEndpoint, AppError> {
+        request.validate().map_err(AppError::BadRequest)?;
+        let endpoint = MockEndpoint {
+            id: uuid::Uuid::new_v4().to_string(),
+            method: request.method.to_ascii_uppercase(),
+            path: request.path,
+            status: request.status,
+            headers: request.headers,
+            body: request.body,
+            latency_ms: request.latency_ms,
+        };
+
+        let mut endpoints = self.endpoints.write().unwrap_or_else(|e| e.into_inner());
+        endpoints.retain(|e| (&e.method, &e.path) != (&endpoint.method, &endpoint.path));
+        if endpoints.len() >= MAX_MOCK_ENDPOINTS {
+            return Err(AppError::BadRequest(format!(
+                "At most {MAX_MOCK_ENDPOINTS} mock endpoints may be registered"
+            )));
+        }
+        endpoints.push(endpoint.clone());
+        Ok(endpoint)
+    }
+
+    /// Remove a mock endpoint, returning whether it existed
+    pub fn remove(&self, id: &str) -> bool {
+        let mut endpoints = self.endpoints.write().unwrap_or_else(|e| e.into_inner());
+        let before = endpoints.len();
+        endpoints.retain(|endpoint| endpoint.id != id);
+        endpoints.len() != before
+    }
+
+    pub fn clear(&self) {
+        self.endpoints
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+
+    fn find(&self, method: &Method, path: &str) -> Option<MockEndpoint> {
+        self.endpoints
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .find(|endpoint| endpoint.matches(method, path))
+            .cloned()
+    }
+}
+
+fn is_exempt(path: &str) -> bool {
+    path.starts_with("/admin/") || path.starts_with("/health")
+}
+
+/// Mock endpoint middleware - answers requests matching a registered mock
+/// with its canned response, when mocks are enabled
+pub async fn mock_middleware(
+    req: Request,
+    next: Next,
+    registry: Option<Arc<MockRegistry>>,
+) -> Response {
+    let mock = registry
+        .filter(|_| !is_exempt(req.uri().path()))
+        .and_then(|registry| registry.find(req.method(), req.uri().path()));
+    let Some(mock) = mock else {
+        return next.run(req).await;
+    };
+
+    debug!(mock = %mock.id, path = %req.uri().path(), "Serving mock endpoint");
+    if mock.latency_ms > 0 {
+        tokio::time::sleep(Duration::from_millis(mock.latency_ms)).await;
+    }
+    mock.response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, path: &str) -> MockEndpointRequest {
+        MockEndpointRequest {
+            method: method.to_string(),
+            path: path.to_string(),
+            status: 200,
+            headers: BTreeMap::new(),
+            body: None,
+            latency_ms: 0,
+        }
+    }
+
+    #[test]
+    fn test_paths_match_by_segment() {
+        let registry = MockRegistry::default();
+        registry
+            .register(request("get", "/api/v1/orders/{id}"))
+            .unwrap();
+
+        assert!(registry.find(&Method::GET, "/api/v1/orders/42").is_some());
+        assert!(registry.find(&Method::GET, "/api/v1/orders/42/").is_some());
+        assert!(registry.find(&Method::POST, "/api/v1/orders/42").is_none());
+        assert!(registry.find(&Method::GET, "/api/v1/orders").is_none());
+        assert!(registry
+            .find(&Method::GET, "/api/v1/orders/42/lines")
+            .is_none());
+    }
+
+    #[test]
+    fn test_register_replaces_and_validates() {
+        let registry = MockRegistry::default();
+        let first = registry.register(request("GET", "/api/v1/orders")).unwrap();
+        let second = registry.register(request("GET", "/api/v1/orders")).unwrap();
+        assert_eq!(registry.endpoints(), vec![second.clone()]);
+        assert!(!registry.remove(&first.id));
+        assert!(registry.remove(&second.id));
+
+        assert!(registry
+            .register(request("GET", "/admin/v1/config"))
+            .is_err());
+        assert!(registry.register(request("GET", "orders")).is_err());
+        let mut slow = request("GET", "/api/v1/orders");
+        slow.latency_ms = MAX_MOCK_LATENCY_MS + 1;
+        assert!(registry.register(slow).is_err());
+    }
+}
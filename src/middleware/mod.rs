@@ -1,53 +1,267 @@
 pub mod auth;
+pub mod chaos;
+pub mod context;
+pub mod envelope;
 pub mod error;
+pub mod json_api;
+pub mod methods;
+pub mod mocks;
 pub mod observability;
+pub mod paths;
 pub mod rate_limit;
 pub mod security;
+pub mod tenancy;
 pub mod version;
 
 #[cfg(test)]
 mod tests;
 
 use axum::{middleware, Router};
-use tower::ServiceBuilder;
+use std::{sync::Arc, time::Duration};
 use tower_http::cors::CorsLayer;
-use tower_http::trace::{DefaultMakeSpan, DefaultOnResponse, TraceLayer};
-use tracing::Level;
 
-/// Add all middleware layers to the application
+use crate::{config::ResponseEnvelope, state::SharedState};
+
+/// A layer of the middleware stack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layer {
+    Cors,
+    SecurityHeaders,
+    RequestId,
+    AccessLog,
+    Metrics,
+    SlowRequests,
+    DebugTiming,
+    FaultInjection,
+    MockEndpoints,
+    JsonApiErrors,
+    Versioning,
+    Auth,
+    Tenancy,
+    RequestContext,
+    // Route group layers
+    Envelope,
+    RateLimit,
+    RequireAuth,
+}
+
+/// Routes grouped by the middleware they need inside the global stack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteGroup {
+    /// Health probes, metrics and documentation, never rate limited so
+    /// orchestrators and scrapers are not throttled
+    Probes,
+    /// The item API
+    Api,
+    /// Administration and profiling endpoints
+    Admin,
+    /// Share links, opened by whoever holds a token, so rate limited but
+    /// never requiring authentication
+    Shares,
+}
+
+/// Layers the stack for `state` is built from, outermost first
 ///
-/// The middleware is organized into three main layers:
+/// The layers are organized into three groups:
 /// 1. Security - CORS, security headers, CSP
-/// 2. Observability - Request ID, tracing, metrics
-/// 3. API features - Rate limiting, authentication, versioning
-pub fn add_middleware(app: Router) -> Router {
-    // Load configurations
-    let auth_config = auth::AuthConfig::from_env();
-    let rate_limit_config = rate_limit::RateLimitConfig::from_env();
-    let rate_limiter = rate_limit::RateLimiter::new(rate_limit_config);
-
-    app.layer(
-        ServiceBuilder::new()
-            // Layer 1: Security (outermost)
-            .layer(CorsLayer::permissive())
-            .layer(middleware::from_fn(security::security_headers))
-            // Layer 2: Observability
-            .layer(
-                TraceLayer::new_for_http()
-                    .make_span_with(DefaultMakeSpan::new().level(Level::INFO))
-                    .on_response(DefaultOnResponse::new().level(Level::INFO)),
-            )
-            .layer(middleware::from_fn(observability::request_id_middleware))
-            .layer(middleware::from_fn(observability::metrics_middleware))
-            // Layer 3: API features
-            .layer(middleware::from_fn(version::version_middleware))
-            .layer(middleware::from_fn(move |req, next| {
+/// 2. Observability - Request ID, tracing, access log, metrics, slow requests,
+///    debug timing
+/// 3. API features - Fault injection, mock endpoints, JSON:API errors,
+///    versioning, authentication, tenancy, and the request context handed to
+///    repositories
+///
+/// Layers for features the configuration turns off are left out, so requests
+/// do not pass through middleware that would only hand them on. Rate limiting
+/// and required authentication depend on the route, see [`group_layers`].
+pub fn layers(state: &SharedState) -> Vec<Layer> {
+    let config = &state.config;
+    let logging = &config.logging;
+    [
+        (Layer::Cors, true),
+        (Layer::SecurityHeaders, true),
+        (Layer::RequestId, true),
+        (Layer::AccessLog, state.access_log.is_some()),
+        (Layer::Metrics, true),
+        (Layer::SlowRequests, logging.slow_request_threshold_ms.is_some()),
+        (Layer::DebugTiming, observability::DebugTiming::new(logging).enabled()),
+        (Layer::FaultInjection, state.chaos.is_some()),
+        (Layer::MockEndpoints, state.mocks.is_some()),
+        (Layer::JsonApiErrors, true),
+        (Layer::Versioning, true),
+        (Layer::Auth, state.auth.enabled()),
+        (Layer::Tenancy, config.tenancy.enabled),
+        (Layer::RequestContext, true),
+    ]
+    .into_iter()
+    .filter_map(|(layer, enabled)| enabled.then_some(layer))
+    .collect()
+}
+
+/// Layers added to the routes of `group`, outermost first, inside the global
+/// stack (so after authentication and tenancy)
+pub fn group_layers(group: RouteGroup, state: &SharedState) -> Vec<Layer> {
+    let rate_limited = state.config.rate_limit.enabled;
+    let enveloped = state.config.server.response_envelope == ResponseEnvelope::Data;
+    match group {
+        RouteGroup::Probes => Vec::new(),
+        RouteGroup::Api => [
+            (Layer::Envelope, enveloped),
+            (Layer::RateLimit, rate_limited),
+            (Layer::RequireAuth, state.auth.required()),
+        ]
+        .into_iter()
+        .filter_map(|(layer, enabled)| enabled.then_some(layer))
+        .collect(),
+        RouteGroup::Admin | RouteGroup::Shares => [
+            (Layer::Envelope, enveloped),
+            (Layer::RateLimit, rate_limited),
+        ]
+        .into_iter()
+        .filter_map(|(layer, enabled)| enabled.then_some(layer))
+        .collect(),
+    }
+}
+
+/// Add the layers of `group` to its routes
+///
+/// `rate_limiter` is shared by every group, so a client has one budget across
+/// them.
+pub fn add_group_middleware(
+    routes: Router<SharedState>,
+    group: RouteGroup,
+    state: &SharedState,
+    rate_limiter: &rate_limit::RateLimiter,
+) -> Router<SharedState> {
+    group_layers(group, state)
+        .into_iter()
+        .rev()
+        .fold(routes, |routes, layer| add_layer(routes, layer, state, rate_limiter))
+}
+
+/// Add the middleware layers `state` needs to the application
+///
+/// Middleware settings come from the configuration held by `state`; see
+/// [`layers`] for the order.
+pub fn add_middleware(app: Router, state: &SharedState) -> Router {
+    // Rate limits are applied by route group, with the limiter they share
+    let rate_limiter = rate_limit::RateLimiter::new(state.config.rate_limit.clone());
+    // Each layer wraps the ones added before it, so start with the innermost
+    let app = layers(state)
+        .into_iter()
+        .rev()
+        .fold(app, |app, layer| add_layer(app, layer, state, &rate_limiter));
+    // The router sets `Allow` on its 405s after every layer added to it has
+    // run, so method handling goes around the router as a whole
+    let app = Router::new()
+        .fallback_service(app)
+        .layer(middleware::from_fn(methods::method_middleware));
+    // Paths are normalized before the router they are meant for sees them
+    let normalizer = Arc::new(paths::PathNormalizer::new(&state.config.server));
+    if !normalizer.enabled() {
+        return app;
+    }
+    app.layer(middleware::from_fn(move |req, next| {
+        let normalizer = normalizer.clone();
+        paths::path_middleware(req, next, normalizer)
+    }))
+}
+
+fn add_layer<S>(
+    app: Router<S>,
+    layer: Layer,
+    state: &SharedState,
+    rate_limiter: &rate_limit::RateLimiter,
+) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let config = &state.config;
+    match layer {
+        Layer::Cors => app.layer(methods::PreflightCorsLayer::new(CorsLayer::permissive())),
+        Layer::SecurityHeaders => {
+            let security_config = config.security.clone();
+            app.layer(middleware::from_fn(move |req, next| {
+                let config = security_config.clone();
+                security::security_headers(req, next, config)
+            }))
+        }
+        Layer::RequestId => {
+            let sampler = Arc::new(observability::TraceSampler::new(&config.logging));
+            let pod = crate::k8s::pod_span(&config.kubernetes);
+            app.layer(middleware::from_fn(move |req, next| {
+                let (sampler, pod) = (sampler.clone(), pod.clone());
+                observability::request_id_middleware(req, next, sampler, pod)
+            }))
+        }
+        Layer::AccessLog => {
+            let access_log = state.access_log.clone();
+            app.layer(middleware::from_fn(move |req, next| {
+                let access_log = access_log.clone();
+                observability::access_log_middleware(req, next, access_log)
+            }))
+        }
+        Layer::Metrics => app.layer(middleware::from_fn(observability::metrics_middleware)),
+        Layer::SlowRequests => {
+            let slow_request = config
+                .logging
+                .slow_request_threshold_ms
+                .map(Duration::from_millis);
+            app.layer(middleware::from_fn(move |req, next| {
+                observability::slow_request_middleware(req, next, slow_request)
+            }))
+        }
+        Layer::DebugTiming => {
+            let debug_timing = Arc::new(observability::DebugTiming::new(&config.logging));
+            app.layer(middleware::from_fn(move |req, next| {
+                let timing = debug_timing.clone();
+                observability::debug_timing_middleware(req, next, timing)
+            }))
+        }
+        Layer::FaultInjection => {
+            let chaos = state.chaos.clone();
+            app.layer(middleware::from_fn(move |req, next| {
+                let injector = chaos.clone();
+                chaos::chaos_middleware(req, next, injector)
+            }))
+        }
+        Layer::MockEndpoints => {
+            let mocks = state.mocks.clone();
+            app.layer(middleware::from_fn(move |req, next| {
+                let registry = mocks.clone();
+                mocks::mock_middleware(req, next, registry)
+            }))
+        }
+        Layer::JsonApiErrors => app.layer(middleware::from_fn(json_api::json_api_error_middleware)),
+        Layer::Versioning => app.layer(middleware::from_fn(version::version_middleware)),
+        Layer::Envelope => app.layer(middleware::from_fn(envelope::envelope_middleware)),
+        Layer::RateLimit => {
+            let rate_limiter = rate_limiter.clone();
+            app.layer(middleware::from_fn(move |req, next| {
                 let limiter = rate_limiter.clone();
                 rate_limit::rate_limit_middleware(req, next, limiter)
             }))
-            .layer(middleware::from_fn(move |req, next| {
-                let config = auth_config.clone();
-                auth::auth_middleware(req, next, config)
-            })),
-    )
+        }
+        Layer::RequireAuth => app.layer(middleware::from_fn(auth::require_auth_middleware)),
+        Layer::Auth => {
+            let validator = state.auth.clone();
+            app.layer(middleware::from_fn(move |req, next| {
+                let validator = validator.clone();
+                auth::auth_middleware(req, next, validator)
+            }))
+        }
+        Layer::Tenancy => {
+            let tenancy_config = config.tenancy.clone();
+            app.layer(middleware::from_fn(move |req, next| {
+                let config = tenancy_config.clone();
+                tenancy::tenancy_middleware(req, next, config)
+            }))
+        }
+        Layer::RequestContext => {
+            let request_timeout = config.server.request_timeout_ms.map(Duration::from_millis);
+            let clock = state.clock.clone();
+            app.layer(middleware::from_fn(move |req, next| {
+                context::request_context_middleware(req, next, request_timeout, clock.clone())
+            }))
+        }
+    }
 }
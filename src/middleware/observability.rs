@@ -1,17 +1,37 @@
-use crate::metrics::{track_http_request, Timer};
+use crate::{
+    access_log::{AccessLog, AccessLogEntry},
+    config::LoggingConfig,
+    http_client::with_request_id,
+    metrics::{
+        track_http_body_sizes, track_http_request, track_slow_request, Timer, ENDPOINT_LABELS,
+        TENANT_LABELS,
+    },
+    slow_log::with_query_breakdown,
+    tenancy::ServedTenant,
+};
 use axum::{
-    body::Body,
-    extract::{MatchedPath, Request},
-    http::{HeaderName, HeaderValue, StatusCode},
+    body::{Body, HttpBody},
+    extract::{ConnectInfo, MatchedPath, Request},
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode},
     middleware::Next,
     response::Response,
 };
-use tracing::{info_span, Instrument};
+use sha2::{Digest, Sha256};
+use std::{
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tracing::{info, info_span, warn, Instrument, Span};
 use uuid::Uuid;
 
 /// Header name for request ID
 pub static X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
 
+/// Header requesting (on the request) and reporting (on the response)
+/// repository call timings
+pub static X_DEBUG_TIMING: HeaderName = HeaderName::from_static("x-debug-timing");
+
 /// Request ID extractor for use in handlers
 #[derive(Clone, Debug)]
 pub struct RequestId(pub String);
@@ -36,8 +56,62 @@ where
     }
 }
 
-/// Request ID middleware - generates or propagates request IDs
-pub async fn request_id_middleware(mut req: Request, next: Next) -> Response {
+/// Decides which requests are traced
+///
+/// A traced request runs in a `request` span and logs its completion. Other
+/// requests run without a span and log nothing of their own, except that
+/// failures (5xx) are still logged when `trace_errors` is set.
+#[derive(Debug, Clone)]
+pub struct TraceSampler {
+    ratio: f64,
+    errors: bool,
+    force_header: Option<HeaderName>,
+}
+
+impl TraceSampler {
+    pub fn new(config: &LoggingConfig) -> Self {
+        Self {
+            ratio: config.trace_sample_ratio,
+            errors: config.trace_errors,
+            force_header: config
+                .trace_force_header
+                .as_deref()
+                .and_then(|header| HeaderName::try_from(header).ok()),
+        }
+    }
+
+    /// Whether a request with these headers is traced
+    pub fn sample(&self, headers: &HeaderMap) -> bool {
+        let forced = self
+            .force_header
+            .as_ref()
+            .and_then(|header| headers.get(header))
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value == "1" || value.eq_ignore_ascii_case("true"));
+        forced || self.ratio >= 1.0 || (self.ratio > 0.0 && random_fraction() < self.ratio)
+    }
+}
+
+impl Default for TraceSampler {
+    fn default() -> Self {
+        Self::new(&LoggingConfig::default())
+    }
+}
+
+/// Uniformly distributed in `[0, 1)`, from the random bits of a v4 UUID
+pub(crate) fn random_fraction() -> f64 {
+    let (_, random) = Uuid::new_v4().as_u64_pair();
+    (random & ((1 << 53) - 1)) as f64 / (1u64 << 53) as f64
+}
+
+/// Request ID middleware - generates or propagates request IDs, and traces
+/// the requests chosen by `sampler` in spans nested under `pod`
+pub async fn request_id_middleware(
+    mut req: Request,
+    next: Next,
+    sampler: Arc<TraceSampler>,
+    pod: Span,
+) -> Response {
     // Check if request already has a request ID
     let request_id = if let Some(existing_id) = req.headers().get(&X_REQUEST_ID) {
         existing_id.to_str().unwrap_or_default().to_string()
@@ -49,16 +123,41 @@ pub async fn request_id_middleware(mut req: Request, next: Next) -> Response {
     // Add request ID to request extensions
     req.extensions_mut().insert(RequestId(request_id.clone()));
 
-    // Create span with request ID for structured logging
-    let span = info_span!(
-        "request",
-        request_id = %request_id,
-        method = %req.method(),
-        uri = %req.uri(),
-    );
+    // Create span with request ID for structured logging, if traced
+    let sampled = sampler.sample(req.headers());
+    let (method, uri) = (req.method().clone(), req.uri().clone());
+    let span = if sampled {
+        info_span!(
+            parent: &pod,
+            "request",
+            request_id = %request_id,
+            method = %method,
+            uri = %uri,
+        )
+    } else {
+        Span::none()
+    };
 
-    // Process request within the span
-    let mut response = next.run(req).instrument(span).await;
+    // Process request within the span, forwarding the ID on outbound calls
+    let start = Instant::now();
+    let mut response = with_request_id(request_id.clone(), next.run(req))
+        .instrument(span.clone())
+        .await;
+
+    let status = response.status().as_u16();
+    let latency_ms = start.elapsed().as_millis() as u64;
+    if sampled {
+        span.in_scope(|| {
+            if response.status().is_server_error() {
+                warn!(status, latency_ms, "Request failed");
+            } else {
+                info!(status, latency_ms, "Request completed");
+            }
+        });
+    } else if sampler.errors && response.status().is_server_error() {
+        // Not traced, so the request's details go on the event itself
+        pod.in_scope(|| warn!(%request_id, %method, %uri, status, latency_ms, "Request failed"));
+    }
 
     // Add request ID to response headers
     if let Ok(header_value) = HeaderValue::from_str(&request_id) {
@@ -70,16 +169,181 @@ pub async fn request_id_middleware(mut req: Request, next: Next) -> Response {
     response
 }
 
+/// Access log middleware - records each request in the access log, if one
+/// is configured
+pub async fn access_log_middleware(
+    req: Request,
+    next: Next,
+    access_log: Option<Arc<AccessLog>>,
+) -> Response {
+    let Some(access_log) = access_log else {
+        return next.run(req).await;
+    };
+
+    let referer = header_string(req.headers(), header::REFERER);
+    let user_agent = header_string(req.headers(), header::USER_AGENT);
+    let remote_addr = super::rate_limit::forwarded_client_ip(&req)
+        .or_else(|| {
+            req.extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip())
+        })
+        .map(|ip| ip.to_string());
+    let request_id = req
+        .extensions()
+        .get::<RequestId>()
+        .map(|RequestId(id)| id.clone())
+        .unwrap_or_default();
+    let (method, uri, version) = (req.method().to_string(), req.uri().to_string(), req.version());
+    let time = chrono::Utc::now();
+
+    let start = Instant::now();
+    let response = next.run(req).await;
+
+    access_log.record(&AccessLogEntry {
+        time,
+        remote_addr,
+        method,
+        uri,
+        protocol: format!("{version:?}"),
+        status: response.status().as_u16(),
+        bytes: header_string(response.headers(), header::CONTENT_LENGTH)
+            .and_then(|value| value.parse().ok()),
+        referer,
+        user_agent,
+        duration_ms: start.elapsed().as_millis() as u64,
+        request_id,
+    });
+    response
+}
+
+/// Slow request middleware - logs requests that take longer than `threshold`,
+/// with a breakdown of the repository calls they made
+pub async fn slow_request_middleware(
+    req: Request,
+    next: Next,
+    threshold: Option<Duration>,
+) -> Response {
+    let Some(threshold) = threshold else {
+        return next.run(req).await;
+    };
+
+    let request_id = req
+        .extensions()
+        .get::<RequestId>()
+        .map(|RequestId(id)| id.clone())
+        .unwrap_or_default();
+    let method = req.method().clone();
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string());
+    let path = req.uri().path().to_string();
+
+    let start = Instant::now();
+    let (response, queries) = with_query_breakdown(next.run(req)).await;
+    let elapsed = start.elapsed();
+
+    if elapsed > threshold {
+        let endpoint = ENDPOINT_LABELS.label(route.as_deref(), &path);
+        track_slow_request(&endpoint);
+        warn!(
+            %request_id,
+            %method,
+            route = route.as_deref().unwrap_or(&path),
+            status = response.status().as_u16(),
+            duration_ms = elapsed.as_millis() as u64,
+            db_calls = queries.calls(),
+            db_ms = queries.total().as_millis() as u64,
+            db_breakdown = %queries,
+            "Slow request"
+        );
+    }
+    response
+}
+
+/// Decides which responses report repository call timings
+#[derive(Debug, Clone, Default)]
+pub struct DebugTiming {
+    always: bool,
+    token: Option<String>,
+}
+
+impl DebugTiming {
+    pub fn new(config: &LoggingConfig) -> Self {
+        Self {
+            always: config.debug_timing,
+            token: config.debug_timing_token.clone(),
+        }
+    }
+
+    /// Whether timings are reported for any request
+    pub fn enabled(&self) -> bool {
+        self.always || self.token.is_some()
+    }
+
+    /// Whether timings are reported for a request with these headers
+    pub fn requested(&self, headers: &HeaderMap) -> bool {
+        let Some(token) = &self.token else {
+            return self.always;
+        };
+        // Digests are compared so the comparison takes the same time however
+        // much of the token matches
+        let sent = headers
+            .get(&X_DEBUG_TIMING)
+            .map(|value| Sha256::digest(value.as_bytes()));
+        self.always || sent == Some(Sha256::digest(token.as_bytes()))
+    }
+}
+
+/// Debug timing middleware - reports the request's repository calls in
+/// `X-Debug-Timing`, for the requests chosen by `timing`
+///
+/// The header reads like `total=25ms db=3/17ms (items.count=1/3ms,
+/// items.get=2/14ms)`: the request duration, then repository calls and time
+/// spent in them, overall and per operation.
+pub async fn debug_timing_middleware(
+    req: Request,
+    next: Next,
+    timing: Arc<DebugTiming>,
+) -> Response {
+    if !timing.requested(req.headers()) {
+        return next.run(req).await;
+    }
+
+    let start = Instant::now();
+    let (mut response, queries) = with_query_breakdown(next.run(req)).await;
+    let report = format!(
+        "total={}ms db={}/{}ms ({queries})",
+        start.elapsed().as_millis(),
+        queries.calls(),
+        queries.total().as_millis(),
+    );
+    if let Ok(value) = HeaderValue::from_str(&report) {
+        response.headers_mut().insert(X_DEBUG_TIMING.clone(), value);
+    }
+    response
+}
+
+fn header_string(headers: &HeaderMap, name: header::HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
 /// Metrics middleware - tracks HTTP request metrics
 pub async fn metrics_middleware(req: Request<Body>, next: Next) -> Result<Response, StatusCode> {
     let timer = Timer::new();
     let method = req.method().to_string();
-    let path = req
-        .extensions()
-        .get::<MatchedPath>()
-        .map(|p| p.as_str())
-        .unwrap_or("unknown")
-        .to_string();
+    let path = ENDPOINT_LABELS.label(
+        req.extensions()
+            .get::<MatchedPath>()
+            .map(MatchedPath::as_str),
+        req.uri().path(),
+    );
+
+    let request_size = body_size(req.body(), req.headers());
 
     let response = next.run(req).await;
     let status = response.status().as_u16();
@@ -87,6 +351,23 @@ pub async fn metrics_middleware(req: Request<Body>, next: Next) -> Result<Respon
 
     // Track the request
     track_http_request(&method, &path, status, duration);
+    let tenant = TENANT_LABELS.label(
+        response
+            .extensions()
+            .get::<ServedTenant>()
+            .map(|tenant| tenant.0.as_str()),
+    );
+    let response_size = body_size(response.body(), response.headers());
+    track_http_body_sizes(&method, &path, &tenant, request_size, response_size);
 
     Ok(response)
 }
+
+/// Size of a body, if known before it is read
+fn body_size(body: &Body, headers: &HeaderMap) -> Option<u64> {
+    body.size_hint().exact().or_else(|| {
+        headers
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok()?.parse().ok())
+    })
+}
@@ -0,0 +1,192 @@
+use axum::{
+    extract::Request,
+    http::{header::LOCATION, uri::PathAndQuery, StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+use utoipa::OpenApi;
+
+use crate::{
+    config::{PathNormalization, ServerConfig},
+    openapi::ApiDoc,
+};
+
+/// Brings request paths to the form routes are declared in, ahead of routing
+///
+/// Trailing slashes are dropped from every path but `/`, and leading ones
+/// collapse into one, so a redirect never points at `//host`, which clients
+/// follow to another host. With case
+/// insensitivity on, the fixed segments of the paths in the OpenAPI document
+/// are matched regardless of case, while parameters such as IDs keep theirs;
+/// where several paths match, the one with the most fixed segments wins.
+pub struct PathNormalizer {
+    mode: PathNormalization,
+    /// Segments of each documented path, `None` for parameters
+    templates: Vec<Vec<Option<String>>>,
+}
+
+impl PathNormalizer {
+    pub fn new(config: &ServerConfig) -> Self {
+        let templates = if config.case_insensitive_paths {
+            ApiDoc::openapi()
+                .paths
+                .paths
+                .keys()
+                .map(|path| {
+                    path.split('/')
+                        .map(|segment| {
+                            let parameter = segment.starts_with('{') && segment.ends_with('}');
+                            (!parameter).then(|| segment.to_string())
+                        })
+                        .collect()
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        Self {
+            mode: config.path_normalization,
+            templates,
+        }
+    }
+
+    /// Whether paths are normalized at all
+    pub fn enabled(&self) -> bool {
+        self.mode != PathNormalization::Off
+    }
+
+    /// `path` in the form of its route, or `None` when it already is
+    pub fn normalize(&self, path: &str) -> Option<String> {
+        if !path.starts_with('/') {
+            return None;
+        }
+        let trimmed = match path.trim_matches('/') {
+            "" => "/".to_string(),
+            trimmed => format!("/{trimmed}"),
+        };
+        let segments: Vec<&str> = trimmed.split('/').collect();
+
+        let template = self
+            .templates
+            .iter()
+            .filter(|template| {
+                template.len() == segments.len()
+                    && template.iter().zip(&segments).all(|(fixed, segment)| {
+                        fixed
+                            .as_ref()
+                            .is_none_or(|fixed| fixed.eq_ignore_ascii_case(segment))
+                    })
+            })
+            .max_by_key(|template| template.iter().flatten().count());
+        let normalized = match template {
+            Some(template) => template
+                .iter()
+                .zip(&segments)
+                .map(|(fixed, segment)| fixed.as_deref().unwrap_or(segment))
+                .collect::<Vec<_>>()
+                .join("/"),
+            None => trimmed,
+        };
+
+        (normalized != path).then_some(normalized)
+    }
+}
+
+/// Path normalization middleware - rewrites or redirects requests whose path
+/// is not in its route's form, so proxies adding a trailing slash or clients
+/// capitalizing a segment do not end up with a 404
+pub async fn path_middleware(
+    mut req: Request,
+    next: Next,
+    normalizer: Arc<PathNormalizer>,
+) -> Response {
+    let Some(path) = normalizer.normalize(req.uri().path()) else {
+        return next.run(req).await;
+    };
+    let target = match req.uri().query() {
+        Some(query) => format!("{path}?{query}"),
+        None => path,
+    };
+
+    if normalizer.mode == PathNormalization::Redirect {
+        return (StatusCode::PERMANENT_REDIRECT, [(LOCATION, target)]).into_response();
+    }
+
+    let mut parts = req.uri().clone().into_parts();
+    if let Ok(path_and_query) = PathAndQuery::try_from(target) {
+        parts.path_and_query = Some(path_and_query);
+        if let Ok(uri) = Uri::from_parts(parts) {
+            *req.uri_mut() = uri;
+        }
+    }
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalizer(case_insensitive_paths: bool) -> PathNormalizer {
+        PathNormalizer::new(&ServerConfig {
+            case_insensitive_paths,
+            ..ServerConfig::default()
+        })
+    }
+
+    #[test]
+    fn test_trailing_slashes_are_dropped() {
+        let normalizer = normalizer(false);
+        assert_eq!(normalizer.normalize("/api/v1/items/").as_deref(), Some("/api/v1/items"));
+        assert_eq!(normalizer.normalize("/api/v1/items"), None);
+        assert_eq!(normalizer.normalize("/"), None);
+        assert_eq!(normalizer.normalize("//").as_deref(), Some("/"));
+        // Without case insensitivity, case is left alone
+        assert_eq!(normalizer.normalize("/API/v1/Items"), None);
+    }
+
+    #[test]
+    fn test_leading_slashes_collapse() {
+        let normalizer = normalizer(false);
+        assert_eq!(normalizer.normalize("//evil.example/").as_deref(), Some("/evil.example"));
+        assert_eq!(normalizer.normalize("///evil.example").as_deref(), Some("/evil.example"));
+        assert_eq!(normalizer.normalize("//api/v1/items").as_deref(), Some("/api/v1/items"));
+    }
+
+    #[tokio::test]
+    async fn test_redirects_stay_on_this_host() {
+        use axum::{body::Body, middleware, Router};
+        use tower::ServiceExt;
+
+        let normalizer = Arc::new(PathNormalizer::new(&ServerConfig {
+            path_normalization: PathNormalization::Redirect,
+            ..ServerConfig::default()
+        }));
+        let app = Router::new().layer(middleware::from_fn(move |req, next| {
+            path_middleware(req, next, normalizer.clone())
+        }));
+
+        for path in ["//evil.example/", "///evil.example"] {
+            let request = Request::builder().uri(path).body(Body::empty()).unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+            assert_eq!(response.headers()[LOCATION], "/evil.example");
+        }
+    }
+
+    #[test]
+    fn test_fixed_segments_match_regardless_of_case() {
+        let normalizer = normalizer(true);
+        assert_eq!(
+            normalizer.normalize("/API/V1/Items/AbC-123/").as_deref(),
+            Some("/api/v1/items/AbC-123")
+        );
+        // A fixed segment wins over a parameter in the same place
+        assert_eq!(
+            normalizer.normalize("/api/v1/items/STATS").as_deref(),
+            Some("/api/v1/items/stats")
+        );
+        assert_eq!(normalizer.normalize("/Unknown/Path"), None);
+    }
+}
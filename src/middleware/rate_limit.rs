@@ -13,45 +13,17 @@ use std::{
 };
 use tokio::sync::Mutex;
 
-/// Simple rate limiter configuration
-#[derive(Clone)]
-pub struct RateLimitConfig {
-    pub requests_per_minute: u32,
-    pub enabled: bool,
-}
-
-impl Default for RateLimitConfig {
-    fn default() -> Self {
-        Self {
-            requests_per_minute: 1000, // Permissive default
-            enabled: true,
-        }
-    }
-}
-
-impl RateLimitConfig {
-    pub fn from_env() -> Self {
-        let enabled = std::env::var("RATE_LIMIT_ENABLED")
-            .map(|v| v.parse().unwrap_or(true))
-            .unwrap_or(true);
-
-        let requests_per_minute = std::env::var("RATE_LIMIT_PER_MINUTE")
-            .ok()
-            .and_then(|v| v.parse().ok())
-            .unwrap_or(1000);
-
-        Self {
-            requests_per_minute,
-            enabled,
-        }
-    }
-}
+use crate::{
+    clock::{system_clock, SharedClock},
+    config::RateLimitConfig,
+};
 
 /// Simple in-memory rate limiter
 #[derive(Clone)]
 pub struct RateLimiter {
     windows: Arc<Mutex<HashMap<IpAddr, (u32, Instant)>>>,
     config: RateLimitConfig,
+    clock: SharedClock,
 }
 
 impl RateLimiter {
@@ -59,21 +31,26 @@ impl RateLimiter {
         Self {
             windows: Arc::new(Mutex::new(HashMap::new())),
             config,
+            clock: system_clock(),
         }
     }
 
+    /// Measure windows with `clock` instead of the system clock
+    #[must_use]
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
     async fn check_rate_limit(&self, ip: IpAddr) -> Result<(u32, u32, Instant), StatusCode> {
+        let limit = self.config.max_requests;
+        let window_duration = Duration::from_secs(self.config.window_seconds);
         if !self.config.enabled {
-            return Ok((
-                self.config.requests_per_minute,
-                self.config.requests_per_minute,
-                Instant::now() + Duration::from_secs(60),
-            ));
+            return Ok((limit, limit, self.clock.instant() + window_duration));
         }
 
         let mut windows = self.windows.lock().await;
-        let now = Instant::now();
-        let window_duration = Duration::from_secs(60);
+        let now = self.clock.instant();
 
         let (count, reset_at) = windows.entry(ip).or_insert((0, now + window_duration));
 
@@ -83,13 +60,18 @@ impl RateLimiter {
             *reset_at = now + window_duration;
         }
 
-        if *count >= self.config.requests_per_minute {
+        if *count >= limit {
             return Err(StatusCode::TOO_MANY_REQUESTS);
         }
 
         *count += 1;
-        let remaining = self.config.requests_per_minute - *count;
-        Ok((self.config.requests_per_minute, remaining, *reset_at))
+        let remaining = limit - *count;
+        Ok((limit, remaining, *reset_at))
+    }
+
+    /// Seconds a limited client should wait before retrying
+    fn retry_after(&self) -> u64 {
+        self.config.window_seconds
     }
 }
 
@@ -114,7 +96,9 @@ pub async fn rate_limit_middleware(
                 HeaderValue::from_str(&remaining.to_string()).unwrap(),
             );
 
-            let reset_seconds = reset_at.duration_since(Instant::now()).as_secs();
+            let reset_seconds = reset_at
+                .saturating_duration_since(rate_limiter.clock.instant())
+                .as_secs();
             headers.insert(
                 "X-RateLimit-Reset",
                 HeaderValue::from_str(&reset_seconds.to_string()).unwrap(),
@@ -136,7 +120,7 @@ pub async fn rate_limit_middleware(
 
             response
                 .headers_mut()
-                .insert("Retry-After", HeaderValue::from_static("60"));
+                .insert("Retry-After", HeaderValue::from(rate_limiter.retry_after()));
 
             response
         }
@@ -146,12 +130,18 @@ pub async fn rate_limit_middleware(
 
 /// Extract client IP from request headers
 fn extract_client_ip(req: &Request) -> IpAddr {
+    // Default to localhost
+    forwarded_client_ip(req).unwrap_or_else(|| "127.0.0.1".parse().unwrap())
+}
+
+/// Client IP reported by a proxy in X-Forwarded-For or X-Real-IP
+pub(crate) fn forwarded_client_ip(req: &Request) -> Option<IpAddr> {
     // Try X-Forwarded-For header first
     if let Some(forwarded) = req.headers().get("x-forwarded-for") {
         if let Ok(forwarded_str) = forwarded.to_str() {
             if let Some(ip_str) = forwarded_str.split(',').next() {
                 if let Ok(ip) = ip_str.trim().parse::<IpAddr>() {
-                    return ip;
+                    return Some(ip);
                 }
             }
         }
@@ -161,11 +151,10 @@ fn extract_client_ip(req: &Request) -> IpAddr {
     if let Some(real_ip) = req.headers().get("x-real-ip") {
         if let Ok(ip_str) = real_ip.to_str() {
             if let Ok(ip) = ip_str.parse::<IpAddr>() {
-                return ip;
+                return Some(ip);
             }
         }
     }
 
-    // Default to localhost
-    "127.0.0.1".parse().unwrap()
+    None
 }
@@ -5,8 +5,19 @@ use axum::{
     response::Response,
 };
 
+use crate::config::SecurityConfig;
+
+/// Content Security Policy for development builds, allowing inline scripts
+const DEV_CSP: &str = "default-src 'self'; script-src 'self' 'unsafe-inline' 'unsafe-eval'; style-src 'self' 'unsafe-inline'; img-src * data:; font-src *; connect-src *";
+
+/// Content Security Policy for production and strict mode
+const STRICT_CSP: &str = "default-src 'self'; script-src 'self'; style-src 'self'; img-src 'self' data: https:; font-src 'self'; connect-src 'self'; frame-ancestors 'none'; base-uri 'self'; form-action 'self'";
+
 /// Add security headers to responses
-pub async fn security_headers(req: Request, next: Next) -> Response {
+pub async fn security_headers(req: Request, next: Next, config: SecurityConfig) -> Response {
+    // Release builds are always strict
+    let strict = config.strict_mode || !cfg!(debug_assertions);
+
     let mut response = next.run(req).await;
     let headers = response.headers_mut();
 
@@ -23,19 +34,17 @@ pub async fn security_headers(req: Request, next: Next) -> Response {
         HeaderValue::from_static("geolocation=(), microphone=(), camera=()"),
     );
 
-    // Content Security Policy - simplified for development
-    let csp = if cfg!(debug_assertions) {
-        "default-src 'self'; script-src 'self' 'unsafe-inline' 'unsafe-eval'; style-src 'self' 'unsafe-inline'; img-src * data:; font-src *; connect-src *"
-    } else {
-        "default-src 'self'; script-src 'self'; style-src 'self'; img-src 'self' data: https:; font-src 'self'; connect-src 'self'; frame-ancestors 'none'; base-uri 'self'; form-action 'self'"
-    };
+    // Content Security Policy - relaxed for development unless overridden
+    let csp = config
+        .csp
+        .as_deref()
+        .unwrap_or(if strict { STRICT_CSP } else { DEV_CSP });
 
     if let Ok(csp_value) = HeaderValue::from_str(csp) {
         headers.insert(header::CONTENT_SECURITY_POLICY, csp_value);
     }
 
-    // HSTS in production only
-    if !cfg!(debug_assertions) {
+    if strict {
         headers.insert(
             header::STRICT_TRANSPORT_SECURITY,
             HeaderValue::from_static("max-age=31536000; includeSubDomains"),
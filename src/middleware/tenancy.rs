@@ -0,0 +1,233 @@
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::IntoResponse,
+    response::Response,
+};
+
+use crate::{
+    config::TenancyConfig,
+    error::{AppError, AppResult},
+    models::TenantStatus,
+    state::SharedState,
+    tenancy::{
+        api_key_tenant, current_tenant, hash_api_key, is_valid_tenant_id, with_tenant,
+        ServedTenant, API_KEY_HEADER,
+    },
+};
+
+/// Whether requests to `path` operate on tenant data
+///
+/// Health, metrics, and documentation endpoints serve the whole deployment, as
+/// does the tenant provisioning API.
+pub fn is_tenant_scoped(path: &str) -> bool {
+    (path.starts_with("/api/") || path.starts_with("/admin/"))
+        && !path.starts_with("/admin/v1/tenants")
+}
+
+/// Resolve the request's tenant and run the rest of the stack in its scope
+///
+/// The tenant comes from the tenant header, else from the tenant API key, else
+/// from the configured default. The key itself is verified by
+/// [`tenant_lookup_middleware`].
+pub async fn tenancy_middleware(req: Request, next: Next, config: TenancyConfig) -> Response {
+    if !config.enabled || !is_tenant_scoped(req.uri().path()) {
+        return next.run(req).await;
+    }
+
+    match resolve_tenant(&req, &config) {
+        Ok(tenant) => {
+            let mut response = with_tenant(tenant.clone(), next.run(req)).await;
+            response.extensions_mut().insert(ServedTenant(tenant));
+            response
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+fn resolve_tenant(req: &Request, config: &TenancyConfig) -> AppResult<String> {
+    let key_tenant = match req.headers().get(API_KEY_HEADER) {
+        Some(value) => Some(
+            value
+                .to_str()
+                .ok()
+                .and_then(api_key_tenant)
+                .ok_or_else(|| AppError::Unauthorized("Invalid API key".to_string()))?,
+        ),
+        None => None,
+    };
+
+    match req.headers().get(config.header.as_str()) {
+        Some(value) => {
+            let tenant = value
+                .to_str()
+                .ok()
+                .filter(|tenant| is_valid_tenant_id(tenant))
+                .ok_or_else(|| AppError::BadRequest(format!("Invalid {} header", config.header)))?;
+            if key_tenant.is_some_and(|key_tenant| key_tenant != tenant) {
+                return Err(AppError::Forbidden("API key belongs to another tenant".to_string()));
+            }
+            Ok(tenant.to_string())
+        }
+        None => key_tenant
+            .map(str::to_string)
+            .or_else(|| config.default_tenant.clone())
+            .ok_or_else(|| AppError::BadRequest(format!("Missing {} header", config.header))),
+    }
+}
+
+/// Check the current tenant against the tenant directory
+///
+/// Requests for suspended tenants, with a wrong API key, or over the tenant's
+/// rate limit are rejected; so are unprovisioned tenants if provisioning is
+/// required. A provisioned tenant is added to the request extensions for
+/// quota checks in handlers. Requests outside a tenant scope pass through.
+pub async fn tenant_lookup_middleware(
+    State(state): State<SharedState>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let Some(tenant_id) = current_tenant() else {
+        return next.run(req).await;
+    };
+
+    let tenant = match state.tenants.lookup(&tenant_id).await {
+        Ok(tenant) => tenant,
+        Err(e) => return AppError::from(e).into_response(),
+    };
+    let api_key = req.headers().get(API_KEY_HEADER);
+
+    let Some(tenant) = tenant else {
+        if api_key.is_some() {
+            return AppError::Unauthorized("Invalid API key".to_string()).into_response();
+        }
+        if state.tenants.requires_provisioning() {
+            return AppError::Forbidden(format!("Tenant {tenant_id} is not provisioned"))
+                .into_response();
+        }
+        return next.run(req).await;
+    };
+
+    if tenant.status == TenantStatus::Suspended {
+        return AppError::Forbidden(format!("Tenant {tenant_id} is suspended")).into_response();
+    }
+    if let Some(key) = api_key {
+        let valid = key
+            .to_str()
+            .is_ok_and(|key| hash_api_key(key) == tenant.api_key_hash);
+        if !valid {
+            return AppError::Unauthorized("Invalid API key".to_string()).into_response();
+        }
+    }
+    if !state.tenants.check_rate_limit(&tenant) {
+        return AppError::TooManyRequests(format!("Rate limit for tenant {tenant_id} exceeded"))
+            .into_response();
+    }
+
+    req.extensions_mut().insert(tenant);
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::StatusCode, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn app(config: TenancyConfig) -> Router {
+        let echo = || async { current_tenant().unwrap_or_else(|| "none".to_string()) };
+        Router::new()
+            .route("/api/v1/items", get(echo))
+            .route("/health", get(echo))
+            .layer(axum::middleware::from_fn(move |req, next| {
+                tenancy_middleware(req, next, config.clone())
+            }))
+    }
+
+    async fn call(app: Router, uri: &str, tenant: Option<&str>) -> (StatusCode, String) {
+        let mut request = Request::builder().uri(uri);
+        if let Some(tenant) = tenant {
+            request = request.header("x-tenant-id", tenant);
+        }
+        let response = app
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8_lossy(&body).to_string())
+    }
+
+    #[tokio::test]
+    async fn test_tenant_resolution() {
+        let config = TenancyConfig {
+            enabled: true,
+            ..TenancyConfig::default()
+        };
+
+        let (status, body) = call(app(config.clone()), "/api/v1/items", Some("acme")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "acme");
+
+        let (status, _) = call(app(config.clone()), "/api/v1/items", None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _) = call(app(config.clone()), "/api/v1/items", Some("../etc")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // Deployment-wide endpoints never require a tenant
+        let (status, body) = call(app(config.clone()), "/health", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "none");
+
+        let with_default = TenancyConfig {
+            default_tenant: Some("public".to_string()),
+            ..config
+        };
+        let (_, body) = call(app(with_default), "/api/v1/items", None).await;
+        assert_eq!(body, "public");
+    }
+
+    #[tokio::test]
+    async fn test_tenant_resolved_from_api_key() {
+        let config = TenancyConfig {
+            enabled: true,
+            ..TenancyConfig::default()
+        };
+        let key = crate::tenancy::generate_api_key("acme");
+        let request = |tenant: Option<&str>, key: &str| {
+            let mut request = Request::builder()
+                .uri("/api/v1/items")
+                .header(API_KEY_HEADER, key);
+            if let Some(tenant) = tenant {
+                request = request.header("x-tenant-id", tenant);
+            }
+            request.body(Body::empty()).unwrap()
+        };
+
+        let response = app(config.clone())
+            .oneshot(request(None, &key))
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, "acme");
+
+        let response = app(config.clone())
+            .oneshot(request(Some("globex"), &key))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = app(config)
+            .oneshot(request(None, "not-a-key"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        assert!(!is_tenant_scoped("/admin/v1/tenants/acme"));
+    }
+}
@@ -92,3 +92,318 @@ async fn test_response_modification_in_middleware() {
 
     assert_eq!(response.status(), StatusCode::ACCEPTED);
 }
+
+#[test]
+fn test_trace_sampling() {
+    use super::observability::TraceSampler;
+    use crate::config::LoggingConfig;
+    use axum::http::HeaderMap;
+
+    let sampler = |trace_sample_ratio| {
+        TraceSampler::new(&LoggingConfig {
+            trace_sample_ratio,
+            ..LoggingConfig::default()
+        })
+    };
+    let none = HeaderMap::new();
+    let mut forced = HeaderMap::new();
+    forced.insert("x-trace", "1".parse().unwrap());
+
+    assert!((0..100).all(|_| sampler(1.0).sample(&none)));
+    assert!((0..100).all(|_| !sampler(0.0).sample(&none)));
+    assert!(sampler(0.0).sample(&forced));
+
+    let sampled = (0..2000).filter(|_| sampler(0.5).sample(&none)).count();
+    assert!((800..1200).contains(&sampled), "sampled {sampled} of 2000");
+}
+
+#[tokio::test]
+async fn test_access_log_records_requests() {
+    use super::observability::access_log_middleware;
+    use crate::{
+        access_log::AccessLog,
+        config::{AccessLogFormat, LoggingConfig},
+    };
+    use std::sync::Arc;
+
+    let dir = std::env::temp_dir().join(format!("ferrous-access-{}", uuid::Uuid::new_v4()));
+    let path = dir.join("access.log");
+    let access_log = AccessLog::start(&LoggingConfig {
+        access_log_path: Some(path.to_string_lossy().into_owned()),
+        access_log_format: AccessLogFormat::Json,
+        ..LoggingConfig::default()
+    })
+    .unwrap()
+    .map(Arc::new);
+
+    let app = Router::new()
+        .route("/items", axum::routing::get(|| async { "[]" }))
+        .layer(middleware::from_fn(move |req, next| {
+            access_log_middleware(req, next, access_log.clone())
+        }));
+    let request = Request::builder()
+        .uri("/items?limit=5")
+        .header("x-forwarded-for", "203.0.113.7")
+        .header("user-agent", "test-agent")
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(app.oneshot(request).await.unwrap().status(), StatusCode::OK);
+
+    // Lines are written in the background
+    let mut contents = String::new();
+    for _ in 0..50 {
+        contents = std::fs::read_to_string(&path).unwrap();
+        if !contents.is_empty() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    let line: serde_json::Value = serde_json::from_str(contents.trim_end()).unwrap();
+    assert_eq!(line["remote_addr"], "203.0.113.7");
+    assert_eq!(line["uri"], "/items?limit=5");
+    assert_eq!(line["status"], 200);
+    assert_eq!(line["user_agent"], "test-agent");
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_slow_requests_are_counted() {
+    use super::observability::slow_request_middleware;
+    use crate::metrics::HTTP_SLOW_REQUESTS_COUNTER;
+    use std::time::Duration;
+
+    let app = Router::new()
+        .route(
+            "/slow-test",
+            axum::routing::get(|| async {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                "done"
+            }),
+        )
+        .route("/fast-test", axum::routing::get(|| async { "done" }))
+        .layer(middleware::from_fn(|req, next| {
+            slow_request_middleware(req, next, Some(Duration::from_millis(10)))
+        }));
+    let slow = || {
+        HTTP_SLOW_REQUESTS_COUNTER
+            .with_label_values(&["/slow-test"])
+            .get()
+    };
+    let fast = || {
+        HTTP_SLOW_REQUESTS_COUNTER
+            .with_label_values(&["/fast-test"])
+            .get()
+    };
+    let (slow_before, fast_before) = (slow(), fast());
+
+    for uri in ["/slow-test", "/fast-test"] {
+        let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+        assert_eq!(app.clone().oneshot(request).await.unwrap().status(), StatusCode::OK);
+    }
+
+    assert_eq!(slow(), slow_before + 1);
+    assert_eq!(fast(), fast_before);
+}
+
+#[tokio::test]
+async fn test_body_sizes_are_observed_per_tenant() {
+    use super::observability::metrics_middleware;
+    use crate::{
+        metrics::{HTTP_REQUEST_SIZE, HTTP_RESPONSE_SIZE},
+        tenancy::ServedTenant,
+    };
+    use axum::response::IntoResponse;
+
+    let app = Router::new()
+        .route(
+            "/size-test",
+            axum::routing::post(|body: String| async move {
+                let mut response = body.repeat(2).into_response();
+                response
+                    .extensions_mut()
+                    .insert(ServedTenant("size-tenant".to_string()));
+                response
+            }),
+        )
+        .layer(middleware::from_fn(metrics_middleware));
+    let request = Request::builder()
+        .method("POST")
+        .uri("/size-test")
+        .body(Body::from("hello"))
+        .unwrap();
+    assert_eq!(app.oneshot(request).await.unwrap().status(), StatusCode::OK);
+
+    let labels = ["POST", "/size-test", "size-tenant"];
+    let request_size = HTTP_REQUEST_SIZE.with_label_values(&labels);
+    let response_size = HTTP_RESPONSE_SIZE.with_label_values(&labels);
+    assert_eq!((request_size.get_sample_count(), request_size.get_sample_sum()), (1, 5.0));
+    assert_eq!((response_size.get_sample_count(), response_size.get_sample_sum()), (1, 10.0));
+}
+
+#[tokio::test]
+async fn test_debug_timing_requires_token() {
+    use super::observability::{debug_timing_middleware, DebugTiming};
+    use crate::{config::LoggingConfig, slow_log::record_query};
+    use std::{sync::Arc, time::Duration};
+
+    let timing = Arc::new(DebugTiming::new(&LoggingConfig {
+        debug_timing_token: Some("s3cret".to_string()),
+        ..LoggingConfig::default()
+    }));
+    let app = Router::new()
+        .route(
+            "/",
+            axum::routing::get(|| async {
+                record_query("get", "items", Duration::from_millis(4), None);
+                record_query("get", "items", Duration::from_millis(2), None);
+                "done"
+            }),
+        )
+        .layer(middleware::from_fn(move |req, next| {
+            debug_timing_middleware(req, next, timing.clone())
+        }));
+    let timing_header = |token: Option<&str>| {
+        let app = app.clone();
+        let mut request = Request::builder().uri("/");
+        if let Some(token) = token {
+            request = request.header("x-debug-timing", token);
+        }
+        async move {
+            let response = app
+                .oneshot(request.body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            response
+                .headers()
+                .get("x-debug-timing")
+                .map(|value| value.to_str().unwrap().to_string())
+        }
+    };
+
+    assert_eq!(timing_header(None).await, None);
+    assert_eq!(timing_header(Some("guess")).await, None);
+    let report = timing_header(Some("s3cret")).await.unwrap();
+    assert!(report.contains("db=2/6ms (items.get=2/6ms)"), "{report}");
+}
+
+#[tokio::test]
+async fn test_fault_injection() {
+    use super::chaos::{chaos_middleware, Fault, FaultInjector, FaultRule};
+    use std::sync::Arc;
+
+    let injector = Arc::new(FaultInjector::default());
+    let chaos = injector.clone();
+    let app = Router::new()
+        .route("/api/v1/items", axum::routing::get(|| async { "items" }))
+        .route("/admin/v1/chaos", axum::routing::get(|| async { "rules" }))
+        .layer(middleware::from_fn(move |req, next| {
+            chaos_middleware(req, next, Some(chaos.clone()))
+        }));
+    let status = |uri: &str, fault: Option<&str>| {
+        let mut request = Request::builder().uri(uri);
+        if let Some(fault) = fault {
+            request = request.header("x-chaos-fault", fault);
+        }
+        let app = app.clone();
+        async move {
+            app.oneshot(request.body(Body::empty()).unwrap())
+                .await
+                .unwrap()
+                .status()
+        }
+    };
+
+    assert_eq!(status("/api/v1/items", None).await, StatusCode::OK);
+    assert_eq!(
+        status("/api/v1/items", Some("error=503")).await,
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+    assert_eq!(status("/api/v1/items", Some("latency=1")).await, StatusCode::OK);
+    assert_eq!(status("/api/v1/items", Some("explode")).await, StatusCode::BAD_REQUEST);
+
+    injector
+        .set_rules(vec![FaultRule {
+            path_prefix: "/".to_string(),
+            method: Some("GET".to_string()),
+            percentage: 100.0,
+            fault: Fault::Error { status: 500 },
+        }])
+        .unwrap();
+    assert_eq!(status("/api/v1/items", None).await, StatusCode::INTERNAL_SERVER_ERROR);
+    // Admin routes stay reachable, so injection can be turned off again
+    assert_eq!(status("/admin/v1/chaos", Some("error=500")).await, StatusCode::OK);
+
+    let invalid = FaultRule {
+        path_prefix: "/".to_string(),
+        method: None,
+        percentage: 150.0,
+        fault: Fault::Drop,
+    };
+    assert!(injector.set_rules(vec![invalid]).is_err());
+    assert_eq!(injector.rules().len(), 1);
+}
+
+#[tokio::test]
+async fn test_stack_leaves_out_disabled_layers() {
+    use super::{group_layers, layers, Layer, RouteGroup};
+    use crate::{config::Config, db::InMemoryRepository, state::AppState};
+    use std::sync::Arc;
+
+    let mut config = Config::default();
+    config.rate_limit.enabled = false;
+    config.logging.slow_request_threshold_ms = None;
+    let minimal = AppState::new(Arc::new(InMemoryRepository::new()))
+        .with_config(config)
+        .into_shared();
+    assert_eq!(
+        layers(&minimal),
+        vec![
+            Layer::Cors,
+            Layer::SecurityHeaders,
+            Layer::RequestId,
+            Layer::Metrics,
+            Layer::JsonApiErrors,
+            Layer::Versioning,
+            Layer::RequestContext,
+        ]
+    );
+
+    let mut config = Config::default();
+    config.tenancy.enabled = true;
+    config.logging.debug_timing_token = Some("s3cret".to_string());
+    let state = AppState::new(Arc::new(InMemoryRepository::new()))
+        .with_config(config)
+        .with_mocks(super::mocks::MockRegistry::default())
+        .into_shared();
+    let layers = layers(&state);
+    for layer in [
+        Layer::SlowRequests,
+        Layer::DebugTiming,
+        Layer::MockEndpoints,
+        Layer::Tenancy,
+    ] {
+        assert!(layers.contains(&layer), "{layer:?} missing");
+    }
+    assert!(!layers.contains(&Layer::Auth));
+    assert!(!layers.contains(&Layer::FaultInjection));
+
+    // Rate limits apply by route group, and never to probes
+    assert_eq!(group_layers(RouteGroup::Api, &state), vec![Layer::RateLimit]);
+    assert_eq!(group_layers(RouteGroup::Admin, &state), vec![Layer::RateLimit]);
+    assert_eq!(group_layers(RouteGroup::Shares, &state), vec![Layer::RateLimit]);
+    assert!(group_layers(RouteGroup::Probes, &state).is_empty());
+    assert!(group_layers(RouteGroup::Api, &minimal).is_empty());
+
+    // Without the rate limit layer, responses carry no rate limit headers
+    let app = super::add_middleware(
+        Router::new().route("/", axum::routing::get(|| async { "Hello" })),
+        &minimal,
+    );
+    let response = app
+        .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("x-ratelimit-limit").is_none());
+    assert!(response.headers().get("x-request-id").is_some());
+}
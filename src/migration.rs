@@ -0,0 +1,288 @@
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::{
+    db::{AccessRepository, DatabaseError, DatabaseResult, ItemFilter, ItemRepository},
+    models::Item,
+    pagination::{Page, MAX_PAGE_LIMIT},
+};
+
+/// Items copied per batch unless told otherwise
+pub const DEFAULT_BATCH_SIZE: usize = MAX_PAGE_LIMIT;
+
+/// Most item IDs a report lists per kind of discrepancy
+const MAX_REPORTED_IDS: usize = 20;
+
+/// One side of a migration: an item backend and the access grants beside it
+#[derive(Clone)]
+pub struct Backend {
+    pub items: Arc<dyn ItemRepository>,
+    pub access: Arc<dyn AccessRepository>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MigrationPhase {
+    Copy,
+    Verify,
+}
+
+/// How far a migration has come, reported after every batch
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct MigrationProgress {
+    pub phase: MigrationPhase,
+    /// Items handled so far in this phase
+    pub items: usize,
+    /// Items the source held when the migration started
+    pub total: usize,
+}
+
+/// Summary of a completed migration
+#[derive(Debug, Default, Serialize)]
+pub struct MigrationReport {
+    pub items: usize,
+    pub grants: usize,
+    /// Whether the verification pass ran
+    pub verified: bool,
+    /// Source items the target does not have, up to 20 IDs
+    pub missing: Vec<String>,
+    pub missing_count: usize,
+    /// Items whose copy differs from the source, up to 20 IDs
+    pub mismatched: Vec<String>,
+    pub mismatched_count: usize,
+}
+
+impl MigrationReport {
+    /// Whether verification found the target to hold what the source does
+    pub fn is_consistent(&self) -> bool {
+        self.missing_count == 0 && self.mismatched_count == 0
+    }
+}
+
+/// Copy every item of the current tenant, with its access grants, from
+/// `source` to `target`, then optionally [`verify`] the copy
+///
+/// Items keep their IDs, versions and timestamps and are written without
+/// outbox events, as a restore would, so running a migration again updates
+/// what changed since. Items are read page by page, so writes made to the
+/// source while it runs may or may not be copied; stop writes, or run it again
+/// right before cutover.
+pub async fn migrate(
+    source: &Backend,
+    target: &Backend,
+    batch_size: usize,
+    verify_copy: bool,
+    mut progress: impl FnMut(MigrationProgress),
+) -> DatabaseResult<MigrationReport> {
+    let total = source.items.count(&ItemFilter::default()).await?;
+    let mut report = MigrationReport::default();
+
+    let mut batches = Batches::new(batch_size)?;
+    while let Some(batch) = batches.next(source.items.as_ref()).await? {
+        for item in batch {
+            let grants = source.access.list_grants(&item.id).await?;
+            target.items.restore(Item::clone(&item)).await?;
+            for grant in grants {
+                target.access.restore_grant(grant).await?;
+                report.grants += 1;
+            }
+            report.items += 1;
+        }
+        progress(MigrationProgress {
+            phase: MigrationPhase::Copy,
+            items: report.items,
+            total,
+        });
+    }
+
+    if verify_copy {
+        verify(source, target, batch_size, total, &mut report, progress).await?;
+    }
+    Ok(report)
+}
+
+/// Read every item of `source` back from `target`, recording in `report` the
+/// ones missing or different there
+pub async fn verify(
+    source: &Backend,
+    target: &Backend,
+    batch_size: usize,
+    total: usize,
+    report: &mut MigrationReport,
+    mut progress: impl FnMut(MigrationProgress),
+) -> DatabaseResult<()> {
+    report.verified = true;
+    let mut checked = 0;
+    let mut batches = Batches::new(batch_size)?;
+    while let Some(batch) = batches.next(source.items.as_ref()).await? {
+        for item in batch {
+            match target.items.get(&item.id).await {
+                Err(DatabaseError::NotFound) => {
+                    report.missing_count += 1;
+                    if report.missing.len() < MAX_REPORTED_IDS {
+                        report.missing.push(item.id.clone());
+                    }
+                }
+                Ok(copy) if !same(&item, &copy) => {
+                    report.mismatched_count += 1;
+                    if report.mismatched.len() < MAX_REPORTED_IDS {
+                        report.mismatched.push(item.id.clone());
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => return Err(e),
+            }
+            checked += 1;
+        }
+        progress(MigrationProgress {
+            phase: MigrationPhase::Verify,
+            items: checked,
+            total,
+        });
+    }
+    Ok(())
+}
+
+/// Pages through the current tenant's items in creation order
+struct Batches {
+    filter: ItemFilter,
+    size: usize,
+    page: Option<Page>,
+}
+
+impl Batches {
+    fn new(batch_size: usize) -> DatabaseResult<Self> {
+        let size = batch_size.clamp(1, MAX_PAGE_LIMIT);
+        let page = Page::first(size).map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        Ok(Self {
+            filter: ItemFilter::default(),
+            size,
+            page: Some(page),
+        })
+    }
+
+    async fn next(&mut self, repo: &dyn ItemRepository) -> DatabaseResult<Option<Vec<Arc<Item>>>> {
+        let Some(page) = self.page.take() else {
+            return Ok(None);
+        };
+        let batch = repo.list(&self.filter, &page).await?;
+        if batch.len() == self.size {
+            if let Some(last) = batch.last() {
+                self.page = Some(page.next_after(last));
+            }
+        }
+        Ok((!batch.is_empty()).then_some(batch))
+    }
+}
+
+/// Whether two items hold the same data, compared in their serialized form
+fn same(expected: &Item, actual: &Item) -> bool {
+    serde_json::to_value(expected).ok() == serde_json::to_value(actual).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{InMemoryAccessRepository, InMemoryRepository},
+        models::{CreateItemRequest, Grantee, Permission, UpdateItemRequest},
+    };
+
+    fn backend() -> Backend {
+        Backend {
+            items: Arc::new(InMemoryRepository::new()),
+            access: Arc::new(InMemoryAccessRepository::new()),
+        }
+    }
+
+    async fn seeded(count: usize) -> (Backend, Arc<Item>) {
+        let source = backend();
+        for i in 0..count {
+            let request = CreateItemRequest {
+                name: format!("Item {i}"),
+                description: None,
+                metadata: None,
+                custom_fields: Default::default(),
+                status: Default::default(),
+                publish_at: None,
+                external_id: None,
+                source: None,
+            };
+            source.items.create(request, None).await.unwrap();
+        }
+        let first = source
+            .items
+            .list(&ItemFilter::default(), &Page::first(1).unwrap())
+            .await
+            .unwrap()
+            .remove(0);
+        (source, first)
+    }
+
+    #[tokio::test]
+    async fn test_migration_copies_items_and_grants() {
+        let (source, first) = seeded(7).await;
+        source
+            .access
+            .grant(&first.id, Grantee::Principal("bob".to_string()), Permission::Read, None)
+            .await
+            .unwrap();
+        let target = backend();
+
+        let mut reported = Vec::new();
+        let report = migrate(&source, &target, 3, true, |progress| {
+            reported.push((progress.phase, progress.items, progress.total))
+        })
+        .await
+        .unwrap();
+
+        assert_eq!((report.items, report.grants), (7, 1));
+        assert!(report.verified && report.is_consistent());
+        assert_eq!(
+            reported,
+            [
+                (MigrationPhase::Copy, 3, 7),
+                (MigrationPhase::Copy, 6, 7),
+                (MigrationPhase::Copy, 7, 7),
+                (MigrationPhase::Verify, 3, 7),
+                (MigrationPhase::Verify, 6, 7),
+                (MigrationPhase::Verify, 7, 7),
+            ]
+        );
+        let copy = target.items.get(&first.id).await.unwrap();
+        assert!(same(&first, &copy));
+        assert_eq!(target.access.list_grants(&first.id).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_verification_finds_missing_and_changed_items() {
+        let (source, first) = seeded(4).await;
+        let target = backend();
+        migrate(&source, &target, 2, false, |_| {}).await.unwrap();
+
+        let update = UpdateItemRequest {
+            name: Some("Drifted".to_string()),
+            ..Default::default()
+        };
+        target.items.update(&first.id, update, None).await.unwrap();
+        let request = CreateItemRequest {
+            name: "Not yet copied".to_string(),
+            description: None,
+            metadata: None,
+            custom_fields: Default::default(),
+            status: Default::default(),
+            publish_at: None,
+            external_id: None,
+            source: None,
+        };
+        let late = source.items.create(request, None).await.unwrap();
+
+        let mut report = MigrationReport::default();
+        verify(&source, &target, 2, 5, &mut report, |_| {})
+            .await
+            .unwrap();
+        assert!(!report.is_consistent());
+        assert_eq!(report.mismatched, [first.id.as_str()]);
+        assert_eq!(report.missing, [late.id]);
+    }
+}
@@ -1,17 +1,25 @@
 use serde::{Deserialize, Serialize};
 #[allow(unused_imports)] // Used in #[schema(example = json!({...}))] attributes
 use serde_json::json;
+use std::collections::BTreeMap;
 use utoipa::ToSchema;
 use validator::Validate;
 
+use crate::validation::Sanitizer;
+
 /// Represents an item in the system
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[schema(example = json!({
     "id": "550e8400-e29b-41d4-a716-446655440000",
     "name": "Example Item",
+    "slug": "example-item",
     "description": "This is an example item",
+    "metadata": { "color": "blue" },
+    "owner_id": "user-123",
+    "status": "published",
     "created_at": "2024-01-01T00:00:00Z",
-    "updated_at": "2024-01-01T00:00:00Z"
+    "updated_at": "2024-01-01T00:00:00Z",
+    "version": 1
 }))]
 pub struct Item {
     /// Unique identifier for the item
@@ -22,10 +30,53 @@ pub struct Item {
     #[schema(example = "Example Item")]
     pub name: String,
 
+    /// URL-safe identifier derived from the name, unique within a tenant
+    #[schema(example = "example-item")]
+    pub slug: String,
+
+    /// Identifier the item has in the system it came from, unique within a
+    /// tenant and source
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = "4711")]
+    pub external_id: Option<String>,
+
+    /// System the item is synchronized from, such as `crm`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = "crm")]
+    pub source: Option<String>,
+
     /// Optional description of the item
     #[schema(example = "This is an example item")]
     pub description: Option<String>,
 
+    /// Free-form JSON, held to the metadata schemas registered for the tenant
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub metadata: Option<serde_json::Value>,
+
+    /// Values of the custom fields the tenant defines, by field name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[schema(value_type = Object)]
+    pub custom_fields: BTreeMap<String, serde_json::Value>,
+
+    /// Subject of the principal that created the item (absent for anonymous items)
+    #[schema(example = "user-123")]
+    pub owner_id: Option<String>,
+
+    /// Tenant the item belongs to (absent when multi-tenancy is disabled)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = "acme")]
+    pub tenant_id: Option<String>,
+
+    /// Whether the item is a draft, seen only by those who can edit it
+    #[serde(default)]
+    pub status: ItemStatus,
+
+    /// When a draft is due to be published
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = "2024-01-02T09:00:00Z")]
+    pub publish_at: Option<chrono::DateTime<chrono::Utc>>,
+
     /// Timestamp when the item was created
     #[schema(example = "2024-01-01T00:00:00Z")]
     pub created_at: chrono::DateTime<chrono::Utc>,
@@ -33,10 +84,68 @@ pub struct Item {
     /// Timestamp when the item was last updated
     #[schema(example = "2024-01-01T00:00:00Z")]
     pub updated_at: chrono::DateTime<chrono::Utc>,
+
+    /// Revision number, starting at 1 and incremented by every update; sent as the `ETag`
+    #[serde(default = "first_version")]
+    #[schema(example = 1)]
+    pub version: u64,
+}
+
+const fn first_version() -> u64 {
+    1
+}
+
+impl Item {
+    /// Whether the item is a draft
+    pub fn is_draft(&self) -> bool {
+        self.status == ItemStatus::Draft
+    }
+
+    /// Set the custom fields named in `changes`, clearing those set to `null`
+    pub fn set_custom_fields(&mut self, changes: &BTreeMap<String, serde_json::Value>) {
+        for (name, value) in changes {
+            if value.is_null() {
+                self.custom_fields.remove(name);
+            } else {
+                self.custom_fields.insert(name.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// Publication state of an item
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemStatus {
+    /// Visible only to the owner and principals who can edit the item
+    Draft,
+    /// Visible to everyone with read access
+    #[default]
+    Published,
+}
+
+impl ItemStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Draft => "draft",
+            Self::Published => "published",
+        }
+    }
+}
+
+/// Item field a client can change
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemField {
+    /// The name, and the slug when it is regenerated
+    Name,
+    Description,
+    Metadata,
+    CustomFields,
 }
 
 /// Request to create a new item
-#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+#[derive(Debug, Default, Serialize, Deserialize, Validate, ToSchema)]
 #[schema(example = json!({
     "name": "New Item",
     "description": "Description of the new item"
@@ -55,10 +164,51 @@ pub struct CreateItemRequest {
     #[validate(length(max = 1000, message = "Description must not exceed 1000 characters"))]
     #[schema(example = "Description of the new item", max_length = 1000)]
     pub description: Option<String>,
+
+    /// Free-form JSON, held to the metadata schemas registered for the tenant
+    #[serde(default)]
+    #[schema(value_type = Option<Object>)]
+    pub metadata: Option<serde_json::Value>,
+
+    /// Values of the tenant's custom fields, by field name
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    pub custom_fields: BTreeMap<String, serde_json::Value>,
+
+    /// Create the item as a draft; items are published by default
+    #[serde(default)]
+    pub status: ItemStatus,
+
+    /// Publish the draft at this time (drafts only)
+    #[serde(default)]
+    #[schema(example = "2024-01-02T09:00:00Z")]
+    pub publish_at: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// Identifier the item has in the system it came from, unique within a
+    /// tenant and source (1-255 characters); it cannot be changed later
+    #[serde(default)]
+    #[validate(length(
+        min = 1,
+        max = 255,
+        message = "External ID must be between 1 and 255 characters"
+    ))]
+    #[schema(example = "4711", min_length = 1, max_length = 255)]
+    pub external_id: Option<String>,
+
+    /// System the item is synchronized from (1-100 characters); it cannot
+    /// be changed later
+    #[serde(default)]
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "Source must be between 1 and 100 characters"
+    ))]
+    #[schema(example = "crm", min_length = 1, max_length = 100)]
+    pub source: Option<String>,
 }
 
 /// Request to update an existing item
-#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Validate, ToSchema)]
 #[schema(example = json!({
     "name": "Updated Item Name",
     "description": "Updated description"
@@ -77,36 +227,312 @@ pub struct UpdateItemRequest {
     #[validate(length(max = 1000, message = "Description must not exceed 1000 characters"))]
     #[schema(example = "Updated description", max_length = 1000)]
     pub description: Option<String>,
+
+    /// New metadata, replacing the current metadata as a whole
+    #[serde(default)]
+    #[schema(value_type = Option<Object>)]
+    pub metadata: Option<serde_json::Value>,
+
+    /// Custom field values to set, by field name; `null` clears a field and
+    /// fields not named keep their values
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    pub custom_fields: BTreeMap<String, serde_json::Value>,
+
+    /// Regenerate the slug from the new name (slugs are stable across renames by default)
+    #[serde(default)]
+    #[schema(example = false)]
+    pub regenerate_slug: bool,
+
+    /// Status to move the item to; set when publishing, not by clients
+    #[serde(skip)]
+    pub status: Option<ItemStatus>,
+
+    /// Replace when a draft is due to be published, clearing it with
+    /// `Some(None)`; set when publishing, not by clients
+    #[serde(skip)]
+    pub publish_at: Option<Option<chrono::DateTime<chrono::Utc>>>,
 }
 
 impl CreateItemRequest {
     /// Sanitize the request data
-    pub fn sanitize(mut self) -> Self {
-        self.name = self.name.trim().to_string();
-        self.description = self.description.and_then(|d| {
-            let trimmed = d.trim();
-            if trimmed.is_empty() {
-                None
-            } else {
-                Some(trimmed.to_string())
-            }
-        });
+    pub fn sanitize(mut self, sanitizer: &Sanitizer) -> Self {
+        self.name = sanitizer.clean(&self.name);
+        self.description = sanitizer.clean_optional(self.description);
         self
     }
 }
 
 impl UpdateItemRequest {
     /// Sanitize the request data
-    pub fn sanitize(mut self) -> Self {
-        self.name = self.name.map(|n| n.trim().to_string());
-        self.description = self.description.and_then(|d| {
-            let trimmed = d.trim();
-            if trimmed.is_empty() {
-                None
-            } else {
-                Some(trimmed.to_string())
-            }
-        });
+    pub fn sanitize(mut self, sanitizer: &Sanitizer) -> Self {
+        self.name = self.name.map(|n| sanitizer.clean(&n));
+        self.description = sanitizer.clean_optional(self.description);
         self
     }
+
+    /// Whether applying the request would leave `item` as it is
+    pub fn is_unchanged(&self, item: &Item) -> bool {
+        !self.regenerate_slug
+            && self.name.as_ref().is_none_or(|name| *name == item.name)
+            && (self.description.is_none() || self.description == item.description)
+            && (self.metadata.is_none() || self.metadata == item.metadata)
+            && self.custom_fields.iter().all(|(name, value)| {
+                item.custom_fields.get(name) == Some(value).filter(|value| !value.is_null())
+            })
+            && self.status.is_none_or(|status| status == item.status)
+            && self
+                .publish_at
+                .is_none_or(|publish_at| publish_at == item.publish_at)
+    }
+
+    /// Fields this request sets, whether or not they differ from the item
+    pub fn fields(&self) -> Vec<ItemField> {
+        let mut fields = Vec::new();
+        if self.name.is_some() || self.regenerate_slug {
+            fields.push(ItemField::Name);
+        }
+        if self.description.is_some() {
+            fields.push(ItemField::Description);
+        }
+        if self.metadata.is_some() {
+            fields.push(ItemField::Metadata);
+        }
+        if !self.custom_fields.is_empty() {
+            fields.push(ItemField::CustomFields);
+        }
+        fields
+    }
+}
+
+/// Level of access granted on an item
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Permission {
+    /// View the item
+    Read,
+    /// View and modify the item
+    Write,
+}
+
+impl Permission {
+    /// Whether this permission satisfies a required permission
+    pub fn allows(self, required: Self) -> bool {
+        self == Self::Write || required == Self::Read
+    }
+}
+
+/// Principal or role an access grant applies to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Grantee {
+    /// A single principal, identified by its `sub` claim
+    Principal(String),
+    /// Every principal holding the role
+    Role(String),
+}
+
+/// Access granted to another principal or role on a specific item
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "id": "7c9e6679-7425-40de-944b-e07fc1f90ae7",
+    "item_id": "550e8400-e29b-41d4-a716-446655440000",
+    "grantee": { "principal": "user-456" },
+    "permission": "read",
+    "granted_by": "user-123",
+    "created_at": "2024-01-01T00:00:00Z"
+}))]
+pub struct AccessGrant {
+    /// Unique identifier for the grant
+    pub id: String,
+
+    /// Item the grant applies to
+    pub item_id: String,
+
+    /// Principal or role receiving access
+    pub grantee: Grantee,
+
+    /// Level of access granted
+    pub permission: Permission,
+
+    /// Subject of the principal that created the grant
+    pub granted_by: Option<String>,
+
+    /// Timestamp when the grant was created
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Request to grant access on an item
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+#[schema(example = json!({
+    "grantee": { "role": "editors" },
+    "permission": "write"
+}))]
+pub struct GrantPermissionRequest {
+    /// Principal or role receiving access
+    #[validate(custom(function = "validate_grantee"))]
+    pub grantee: Grantee,
+
+    /// Level of access to grant
+    pub permission: Permission,
+}
+
+fn validate_grantee(grantee: &Grantee) -> Result<(), validator::ValidationError> {
+    let (Grantee::Principal(name) | Grantee::Role(name)) = grantee;
+    crate::validation::validate_not_empty(name)
+}
+
+/// How a principal's data is removed
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ErasureMode {
+    /// Keep the principal's items but replace their owner with an unlinkable pseudonym
+    Anonymize,
+    /// Delete the principal's items
+    Erase,
+}
+
+/// Request to anonymize or erase all data associated with a principal
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+#[schema(example = json!({
+    "principal": "user-123",
+    "mode": "anonymize"
+}))]
+pub struct ErasureRequest {
+    /// Subject (`owner_id`) whose data is removed
+    #[validate(custom(function = "crate::validation::validate_not_empty"))]
+    pub principal: String,
+
+    pub mode: ErasureMode,
+}
+
+/// Lifecycle state of a tenant
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TenantStatus {
+    /// Requests for the tenant are served
+    #[default]
+    Active,
+    /// Requests for the tenant are rejected; its data is kept
+    Suspended,
+}
+
+/// Limits enforced on a tenant's usage
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, Validate, ToSchema)]
+#[schema(example = json!({
+    "max_items": 10000,
+    "requests_per_minute": 600
+}))]
+pub struct TenantQuotas {
+    /// Maximum number of items the tenant may store
+    #[validate(range(min = 1))]
+    pub max_items: Option<usize>,
+
+    /// Maximum requests per minute across all of the tenant's clients
+    #[validate(range(min = 1))]
+    pub requests_per_minute: Option<u32>,
+}
+
+/// A provisioned tenant
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "id": "acme",
+    "name": "Acme Corp",
+    "status": "active",
+    "quotas": { "max_items": 10000, "requests_per_minute": 600 },
+    "created_at": "2024-01-01T00:00:00Z",
+    "updated_at": "2024-01-01T00:00:00Z"
+}))]
+pub struct Tenant {
+    /// Tenant ID sent in the tenant header
+    #[schema(example = "acme")]
+    pub id: String,
+
+    /// Display name of the tenant
+    #[schema(example = "Acme Corp")]
+    pub name: String,
+
+    pub status: TenantStatus,
+
+    pub quotas: TenantQuotas,
+
+    /// SHA-256 hash of the tenant's API key; the key itself is never stored
+    #[serde(skip)]
+    pub api_key_hash: String,
+
+    /// Timestamp when the tenant was provisioned
+    pub created_at: chrono::DateTime<chrono::Utc>,
+
+    /// Timestamp when the tenant was last updated
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Request to provision a tenant
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+#[schema(example = json!({
+    "id": "acme",
+    "name": "Acme Corp",
+    "quotas": { "max_items": 10000, "requests_per_minute": 600 }
+}))]
+pub struct CreateTenantRequest {
+    /// Lowercase letters, digits, `-` and `_`; becomes the schema or database name
+    #[validate(custom(function = "validate_tenant_id"))]
+    pub id: String,
+
+    #[validate(
+        length(
+            min = 1,
+            max = 100,
+            message = "Name must be between 1 and 100 characters"
+        ),
+        custom(function = "crate::validation::validate_not_empty")
+    )]
+    pub name: String,
+
+    #[serde(default)]
+    #[validate(nested)]
+    pub quotas: TenantQuotas,
+}
+
+fn validate_tenant_id(id: &str) -> Result<(), validator::ValidationError> {
+    if crate::tenancy::is_valid_tenant_id(id) {
+        return Ok(());
+    }
+    let mut error = validator::ValidationError::new("tenant_id");
+    error.message = Some(std::borrow::Cow::Borrowed(
+        "Must be 1-63 lowercase letters, digits, '-' or '_', starting with a letter or digit",
+    ));
+    Err(error)
+}
+
+/// Request to update a tenant; omitted fields are left unchanged
+#[derive(Debug, Default, Serialize, Deserialize, Validate, ToSchema)]
+#[schema(example = json!({
+    "status": "suspended"
+}))]
+pub struct UpdateTenantRequest {
+    #[validate(
+        length(
+            min = 1,
+            max = 100,
+            message = "Name must be between 1 and 100 characters"
+        ),
+        custom(function = "crate::validation::validate_not_empty")
+    )]
+    pub name: Option<String>,
+
+    pub status: Option<TenantStatus>,
+
+    #[validate(nested)]
+    pub quotas: Option<TenantQuotas>,
+}
+
+/// A newly provisioned tenant together with its API key
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProvisionedTenant {
+    pub tenant: Tenant,
+
+    /// API key for the tenant, shown only in this response
+    #[schema(example = "ftk_acme.3f9c2a...")]
+    pub api_key: String,
 }
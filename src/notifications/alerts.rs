@@ -0,0 +1,353 @@
+use chrono::{DateTime, Utc};
+use std::{path::Path, sync::Arc, time::Duration};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use super::{ChatProvider, ChatWebhook, NotificationKind, Notifier, Templates};
+use crate::{
+    config::NotificationsConfig,
+    handlers::{health_status, HealthStatus},
+    http_client::HttpClient,
+    metrics::{track_notification, ErrorCounts, ERROR_RATES},
+    state::SharedState,
+};
+
+type Vars = Vec<(&'static str, String)>;
+
+/// What alert conditions are judged from
+#[derive(Debug, Clone, Copy)]
+pub struct Observation {
+    pub dead_letters: usize,
+    pub health: HealthStatus,
+    pub errors: ErrorCounts,
+}
+
+/// A condition's announced state moving from `previous` to `current`, where
+/// `None` means the condition is clear
+struct Transition {
+    current: Option<&'static str>,
+    previous: Option<&'static str>,
+}
+
+/// What channels were last told about one condition
+#[derive(Default)]
+struct Condition {
+    announced: Option<&'static str>,
+    last_alerted: Option<DateTime<Utc>>,
+}
+
+impl Condition {
+    /// The transition to announce for the condition now being `current`
+    ///
+    /// A condition is announced once when it starts, again if its state
+    /// changes, and once when it clears. After it clears it stays quiet for
+    /// `cooldown` since the last alert, so a flapping condition does not
+    /// flood the channels.
+    fn update(
+        &mut self,
+        current: Option<&'static str>,
+        now: DateTime<Utc>,
+        cooldown: chrono::Duration,
+    ) -> Option<Transition> {
+        let previous = self.announced;
+        match (current, previous) {
+            (Some(_), None)
+                if self
+                    .last_alerted
+                    .is_some_and(|alerted| now - alerted < cooldown) =>
+            {
+                None
+            }
+            (Some(_), _) if current != previous => {
+                self.announced = current;
+                self.last_alerted = Some(now);
+                Some(Transition { current, previous })
+            }
+            (None, Some(_)) => {
+                self.announced = None;
+                Some(Transition { current, previous })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Alert conditions, and what has been announced about each
+pub struct Alerts {
+    dead_letter_threshold: usize,
+    error_rate_threshold: Option<f64>,
+    error_rate_min_requests: u64,
+    cooldown: chrono::Duration,
+    dead_letters: Condition,
+    health: Condition,
+    error_rate: Condition,
+}
+
+impl Alerts {
+    pub fn new(config: &NotificationsConfig) -> Self {
+        Self {
+            dead_letter_threshold: config.dead_letter_threshold,
+            error_rate_threshold: config.error_rate_threshold,
+            error_rate_min_requests: config.error_rate_min_requests,
+            cooldown: chrono::Duration::seconds(
+                i64::try_from(config.alert_cooldown_seconds).unwrap_or(i64::MAX),
+            ),
+            dead_letters: Condition::default(),
+            health: Condition::default(),
+            error_rate: Condition::default(),
+        }
+    }
+
+    /// Notifications due for the latest observation
+    pub fn observe(
+        &mut self,
+        now: DateTime<Utc>,
+        observation: &Observation,
+    ) -> Vec<(NotificationKind, Vars)> {
+        let service = || ("service", env!("CARGO_PKG_NAME").to_string());
+        let mut due = Vec::new();
+
+        let over = observation.dead_letters > self.dead_letter_threshold;
+        if let Some(Transition {
+            current: Some(_), ..
+        }) = self
+            .dead_letters
+            .update(over.then_some("over"), now, self.cooldown)
+        {
+            due.push((
+                NotificationKind::DeadLetters,
+                vec![
+                    service(),
+                    ("count", observation.dead_letters.to_string()),
+                    ("threshold", self.dead_letter_threshold.to_string()),
+                ],
+            ));
+        }
+
+        let status =
+            (observation.health != HealthStatus::Healthy).then(|| status_name(observation.health));
+        if let Some(Transition { current, previous }) =
+            self.health.update(status, now, self.cooldown)
+        {
+            let kind = if current.is_some() {
+                NotificationKind::HealthDegraded
+            } else {
+                NotificationKind::HealthRecovered
+            };
+            due.push((
+                kind,
+                vec![
+                    service(),
+                    ("status", current.unwrap_or("healthy").to_string()),
+                    ("previous", previous.unwrap_or("healthy").to_string()),
+                ],
+            ));
+        }
+
+        let errors = observation.errors;
+        let spiking = self.error_rate_threshold.is_some_and(|threshold| {
+            errors.requests >= self.error_rate_min_requests && errors.ratio() > threshold
+        });
+        if let Some(Transition {
+            current: Some(_), ..
+        }) = self
+            .error_rate
+            .update(spiking.then_some("over"), now, self.cooldown)
+        {
+            let threshold = self.error_rate_threshold.unwrap_or_default();
+            due.push((
+                NotificationKind::ErrorRate,
+                vec![
+                    service(),
+                    ("percent", format!("{:.1}", errors.ratio() * 100.0)),
+                    ("requests", errors.requests.to_string()),
+                    ("server_errors", errors.server_errors.to_string()),
+                    ("threshold_percent", format!("{:.1}", threshold * 100.0)),
+                ],
+            ));
+        }
+        due
+    }
+}
+
+fn status_name(status: HealthStatus) -> &'static str {
+    match status {
+        HealthStatus::Healthy => "healthy",
+        HealthStatus::Degraded => "degraded",
+        HealthStatus::Unhealthy => "unhealthy",
+    }
+}
+
+/// Watches the service and alerts operators by email and in chat channels
+pub struct Alerter {
+    config: NotificationsConfig,
+    templates: Templates,
+    email: Option<Arc<Notifier>>,
+    chat: Vec<ChatWebhook>,
+    http: HttpClient,
+}
+
+impl Alerter {
+    /// Alerts sent through `email` and the configured chat webhooks; `None`
+    /// when there is neither
+    pub fn from_config(
+        config: &NotificationsConfig,
+        email: Option<Arc<Notifier>>,
+        http: HttpClient,
+    ) -> Result<Option<Self>, String> {
+        let chat: Vec<_> = config
+            .slack_webhook_urls
+            .iter()
+            .map(|url| ChatWebhook::new(ChatProvider::Slack, url))
+            .chain(
+                config
+                    .teams_webhook_urls
+                    .iter()
+                    .map(|url| ChatWebhook::new(ChatProvider::Teams, url)),
+            )
+            .collect();
+        if email.is_none() && chat.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            config: config.clone(),
+            templates: Templates::load(config.templates_dir.as_deref().map(Path::new))?,
+            email,
+            chat,
+            http,
+        }))
+    }
+
+    /// Check alert conditions every `interval` until aborted
+    pub fn spawn(self: Arc<Self>, state: SharedState, interval: Duration) -> JoinHandle<()> {
+        info!(
+            "Alerting by email: {}, to {} chat channels",
+            self.email.is_some(),
+            self.chat.len()
+        );
+        tokio::spawn(async move {
+            let mut alerts = Alerts::new(&self.config);
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                let dead_letters = match state.dead_letters.len() {
+                    Ok(dead_letters) => dead_letters,
+                    Err(e) => {
+                        warn!("Skipped alert checks: {}", e);
+                        continue;
+                    }
+                };
+                let observation = Observation {
+                    dead_letters,
+                    health: health_status(&state).await,
+                    errors: ERROR_RATES.total(),
+                };
+                for (kind, vars) in alerts.observe(state.clock.now(), &observation) {
+                    self.send(kind, &vars).await;
+                }
+            }
+        })
+    }
+
+    /// Send a `kind` alert to every channel
+    pub async fn send(&self, kind: NotificationKind, vars: &[(&str, String)]) {
+        let (subject, body) = self.templates.get(kind).render(vars);
+        info!(kind = kind.as_str(), "Alerting operators: {}", subject);
+        for webhook in &self.chat {
+            let channel = webhook.provider.as_str();
+            match webhook.post(&self.http, &subject, &body).await {
+                Ok(()) => track_notification(channel, kind.as_str(), "sent"),
+                Err(e) => {
+                    warn!(kind = kind.as_str(), "Failed to post alert: {}", e);
+                    track_notification(channel, kind.as_str(), "failed");
+                }
+            }
+        }
+        if let Some(email) = &self.email {
+            email.notify_admins(kind, subject, body);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn observation(dead_letters: usize, health: HealthStatus) -> Observation {
+        Observation {
+            dead_letters,
+            health,
+            errors: ErrorCounts::default(),
+        }
+    }
+
+    fn kinds(due: &[(NotificationKind, Vars)]) -> Vec<NotificationKind> {
+        due.iter().map(|(kind, _)| *kind).collect()
+    }
+
+    #[test]
+    fn test_alerts_fire_when_conditions_change() {
+        let mut alerts = Alerts::new(&NotificationsConfig::default());
+        let now = Utc::now();
+        assert!(alerts
+            .observe(now, &observation(10, HealthStatus::Healthy))
+            .is_empty());
+
+        let due = alerts.observe(now, &observation(11, HealthStatus::Degraded));
+        assert_eq!(
+            kinds(&due),
+            [
+                NotificationKind::DeadLetters,
+                NotificationKind::HealthDegraded
+            ]
+        );
+        assert!(alerts
+            .observe(now, &observation(12, HealthStatus::Degraded))
+            .is_empty());
+
+        // Getting worse is announced right away
+        let due = alerts.observe(now, &observation(12, HealthStatus::Unhealthy));
+        assert!(due[0].1.contains(&("previous", "degraded".to_string())));
+
+        let due = alerts.observe(now, &observation(3, HealthStatus::Healthy));
+        assert_eq!(kinds(&due), [NotificationKind::HealthRecovered]);
+        assert!(due[0].1.contains(&("previous", "unhealthy".to_string())));
+    }
+
+    #[test]
+    fn test_flapping_conditions_wait_out_the_cooldown() {
+        let mut alerts = Alerts::new(&NotificationsConfig::default());
+        let start = Utc::now();
+        let at = |minutes| start + chrono::Duration::minutes(minutes);
+        let unhealthy = observation(0, HealthStatus::Unhealthy);
+        let healthy = observation(0, HealthStatus::Healthy);
+
+        assert_eq!(alerts.observe(at(0), &unhealthy).len(), 1);
+        assert_eq!(alerts.observe(at(1), &healthy).len(), 1);
+        // Within 15 minutes of the last alert
+        assert!(alerts.observe(at(2), &unhealthy).is_empty());
+        assert!(alerts.observe(at(3), &healthy).is_empty());
+        assert!(alerts.observe(at(4), &unhealthy).is_empty());
+        // Still unhealthy once the cooldown is over
+        assert_eq!(kinds(&alerts.observe(at(15), &unhealthy)), [NotificationKind::HealthDegraded]);
+    }
+
+    #[test]
+    fn test_error_rate_alerts_need_enough_requests() {
+        let mut alerts = Alerts::new(&NotificationsConfig::default());
+        let now = Utc::now();
+        let errors = |requests, server_errors| Observation {
+            errors: ErrorCounts {
+                requests,
+                server_errors,
+            },
+            ..observation(0, HealthStatus::Healthy)
+        };
+
+        assert!(alerts.observe(now, &errors(10, 10)).is_empty());
+        assert!(alerts.observe(now, &errors(100, 10)).is_empty());
+        let due = alerts.observe(now, &errors(100, 25));
+        assert_eq!(kinds(&due), [NotificationKind::ErrorRate]);
+        assert!(due[0].1.contains(&("percent", "25.0".to_string())));
+    }
+}
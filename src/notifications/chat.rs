@@ -0,0 +1,83 @@
+use serde_json::{json, Value};
+use std::time::Instant;
+
+use crate::{http_client::HttpClient, metrics::track_notification_send};
+
+/// Chat service an incoming webhook belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatProvider {
+    Slack,
+    Teams,
+}
+
+impl ChatProvider {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Slack => "slack",
+            Self::Teams => "teams",
+        }
+    }
+
+    /// Request body posting `subject` and `body` as one message
+    fn payload(self, subject: &str, body: &str) -> Value {
+        match self {
+            Self::Slack => json!({ "text": format!("*{subject}*\n{body}") }),
+            // Teams renders the text as Markdown, where single newlines
+            // do not break lines
+            Self::Teams => json!({
+                "@type": "MessageCard",
+                "@context": "https://schema.org/extensions",
+                "summary": subject,
+                "title": subject,
+                "text": body.trim_end().replace('\n', "\n\n"),
+            }),
+        }
+    }
+}
+
+/// An incoming webhook that posts messages to a chat channel
+#[derive(Debug, Clone)]
+pub struct ChatWebhook {
+    pub provider: ChatProvider,
+    url: String,
+}
+
+impl ChatWebhook {
+    pub fn new(provider: ChatProvider, url: impl Into<String>) -> Self {
+        Self {
+            provider,
+            url: url.into(),
+        }
+    }
+
+    /// Post a message; the error never includes the URL, which is a secret
+    pub async fn post(&self, http: &HttpClient, subject: &str, body: &str) -> Result<(), String> {
+        let provider = self.provider.as_str();
+        let start = Instant::now();
+        let request = http
+            .post(&self.url)
+            .json(&self.provider.payload(subject, body));
+        let result = match http.send(request).await {
+            Ok(response) if response.status().is_success() => Ok(()),
+            Ok(response) => Err(format!("{provider} responded with {}", response.status())),
+            Err(e) => Err(format!("{provider} request failed: {}", e.without_url())),
+        };
+        track_notification_send(provider, result.is_ok(), start.elapsed().as_secs_f64());
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payloads_suit_each_provider() {
+        let slack = ChatProvider::Slack.payload("ferrous is unhealthy", "Check /health.\n");
+        assert_eq!(slack, json!({ "text": "*ferrous is unhealthy*\nCheck /health.\n" }));
+
+        let teams = ChatProvider::Teams.payload("ferrous is unhealthy", "One.\nTwo.\n");
+        assert_eq!(teams["title"], "ferrous is unhealthy");
+        assert_eq!(teams["text"], "One.\n\nTwo.");
+    }
+}
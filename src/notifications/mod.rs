@@ -0,0 +1,447 @@
+//! Notifications for operators, by email and in chat channels
+//!
+//! Alerts are rendered from templates and sent to every configured channel.
+//! Emails are queued; a background worker hands them to the mail provider,
+//! retrying failures with backoff before dead-lettering them.
+
+pub mod alerts;
+pub mod chat;
+pub mod smtp;
+
+pub use alerts::Alerter;
+pub use chat::{ChatProvider, ChatWebhook};
+pub use smtp::SmtpMailer;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::{sync::mpsc, task::JoinHandle};
+use tracing::warn;
+
+use crate::{
+    config::NotificationsConfig,
+    dead_letters::{DeadLetterQueue, DeadLetterSource, Retry},
+    metrics::{track_notification, track_notification_send},
+};
+
+/// An email ready to be sent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Email {
+    pub to: Vec<String>,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Delivers emails; implemented by each mail provider
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn send(&self, email: &Email) -> Result<(), String>;
+}
+
+/// What a notification is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NotificationKind {
+    /// The dead letter queue grew past its alert threshold
+    DeadLetters,
+    /// The service stopped reporting healthy
+    HealthDegraded,
+    /// The service reports healthy again
+    HealthRecovered,
+    /// Server errors rose past their alert threshold
+    ErrorRate,
+}
+
+impl NotificationKind {
+    pub const ALL: [Self; 4] = [
+        Self::DeadLetters,
+        Self::HealthDegraded,
+        Self::HealthRecovered,
+        Self::ErrorRate,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::DeadLetters => "dead_letters",
+            Self::HealthDegraded => "health_degraded",
+            Self::HealthRecovered => "health_recovered",
+            Self::ErrorRate => "error_rate",
+        }
+    }
+
+    fn default_template(self) -> &'static str {
+        match self {
+            Self::DeadLetters => {
+                "Subject: {{count}} dead letters on {{service}}\n\n\
+                 The dead letter queue on {{service}} holds {{count}} letters, \
+                 more than the alert threshold of {{threshold}}.\n\n\
+                 Inspect and requeue them at /admin/v1/dead-letters.\n"
+            }
+            Self::HealthDegraded => {
+                "Subject: {{service}} is {{status}}\n\n\
+                 {{service}} reports {{status}}, previously {{previous}}.\n\n\
+                 See /health for the failing checks.\n"
+            }
+            Self::HealthRecovered => {
+                "Subject: {{service}} has recovered\n\n\
+                 {{service}} reports healthy again, previously {{previous}}.\n"
+            }
+            Self::ErrorRate => {
+                "Subject: {{service}} is failing {{percent}}% of requests\n\n\
+                 {{server_errors}} of the last {{requests}} requests to {{service}} \
+                 failed with server errors, more than the alert threshold of \
+                 {{threshold_percent}}%.\n"
+            }
+        }
+    }
+}
+
+/// A subject and body with `{{name}}` placeholders
+///
+/// Templates are written as a `Subject:` line, a blank line and the body.
+#[derive(Debug, Clone)]
+pub struct Template {
+    subject: String,
+    body: String,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let source = source.replace("\r\n", "\n");
+        let (subject, body) = source
+            .split_once("\n\n")
+            .ok_or("expected a subject line, a blank line and the body")?;
+        let subject = subject
+            .strip_prefix("Subject:")
+            .ok_or("the first line must start with `Subject:`")?
+            .trim();
+        if subject.is_empty() {
+            return Err("the subject is empty".to_string());
+        }
+        Ok(Self {
+            subject: subject.to_string(),
+            body: body.to_string(),
+        })
+    }
+
+    /// Subject and body with placeholders filled from `vars`; unknown
+    /// placeholders are left as written
+    pub fn render(&self, vars: &[(&str, String)]) -> (String, String) {
+        let fill = |text: &str| {
+            vars.iter().fold(text.to_string(), |text, (name, value)| {
+                text.replace(&format!("{{{{{name}}}}}"), value)
+            })
+        };
+        (fill(&self.subject), fill(&self.body))
+    }
+}
+
+/// Templates for every kind of notification
+#[derive(Debug, Clone)]
+pub struct Templates(HashMap<NotificationKind, Template>);
+
+impl Templates {
+    /// The built-in templates, replaced by any `<kind>.txt` in `dir`
+    pub fn load(dir: Option<&Path>) -> Result<Self, String> {
+        let mut templates = HashMap::new();
+        for kind in NotificationKind::ALL {
+            let custom = match dir {
+                Some(dir) => {
+                    let path = dir.join(format!("{}.txt", kind.as_str()));
+                    match std::fs::read_to_string(&path) {
+                        Ok(source) => Some((path, source)),
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                        Err(e) => return Err(format!("Failed to read {}: {e}", path.display())),
+                    }
+                }
+                None => None,
+            };
+            let template = match custom {
+                Some((path, source)) => Template::parse(&source)
+                    .map_err(|e| format!("Invalid template {}: {e}", path.display()))?,
+                None => Template::parse(kind.default_template()).expect("built-in template"),
+            };
+            templates.insert(kind, template);
+        }
+        Ok(Self(templates))
+    }
+
+    pub fn get(&self, kind: NotificationKind) -> &Template {
+        &self.0[&kind]
+    }
+}
+
+/// An email waiting for the worker
+struct Queued {
+    kind: NotificationKind,
+    email: Email,
+}
+
+/// Sends emails in the background
+///
+/// Sending never holds up the caller: emails wait in a bounded queue and are
+/// dropped, and counted, when it is full. Each email is tried up to
+/// `NOTIFY_MAX_ATTEMPTS` times with doubling delays, then dead-lettered so
+/// an operator can requeue it. The queue is in memory, so emails still
+/// waiting at shutdown are lost.
+pub struct Notifier {
+    mailer: Arc<dyn Mailer>,
+    admin_recipients: Vec<String>,
+    max_attempts: u32,
+    retry_backoff: Duration,
+    dead_letters: Option<Arc<DeadLetterQueue>>,
+    sender: mpsc::Sender<Queued>,
+    receiver: Mutex<Option<mpsc::Receiver<Queued>>>,
+}
+
+impl Notifier {
+    /// Notifier sending through the configured SMTP server; `None` when no
+    /// server is configured
+    pub fn from_config(config: &NotificationsConfig) -> Result<Option<Self>, String> {
+        let Some(mailer) = SmtpMailer::from_config(config) else {
+            return Ok(None);
+        };
+        Ok(Some(Self::new(Arc::new(mailer), config)))
+    }
+
+    pub fn new(mailer: Arc<dyn Mailer>, config: &NotificationsConfig) -> Self {
+        let (sender, receiver) = mpsc::channel(config.queue_capacity.max(1));
+        Self {
+            mailer,
+            admin_recipients: config.admin_recipients.clone(),
+            max_attempts: config.max_attempts.max(1),
+            retry_backoff: Duration::from_millis(config.retry_backoff_ms),
+            dead_letters: None,
+            sender,
+            receiver: Mutex::new(Some(receiver)),
+        }
+    }
+
+    /// Dead-letter emails that exhaust their attempts into `dead_letters`
+    #[must_use]
+    pub fn with_dead_letters(mut self, dead_letters: Arc<DeadLetterQueue>) -> Self {
+        self.dead_letters = Some(dead_letters);
+        self
+    }
+
+    /// Queue a `kind` email to the admin recipients
+    pub fn notify_admins(&self, kind: NotificationKind, subject: String, body: String) {
+        let to = self.admin_recipients.clone();
+        self.queue(kind, Email { to, subject, body });
+    }
+
+    /// Queue a `kind` email
+    pub fn queue(&self, kind: NotificationKind, email: Email) {
+        if email.to.is_empty() {
+            return;
+        }
+        if let Err(e) = self.sender.try_send(Queued { kind, email }) {
+            let queued = match e {
+                mpsc::error::TrySendError::Full(queued)
+                | mpsc::error::TrySendError::Closed(queued) => queued,
+            };
+            warn!(
+                kind = kind.as_str(),
+                "Notification queue is full; dropped {:?}", queued.email.subject
+            );
+            track_notification("email", kind.as_str(), "dropped");
+        }
+    }
+
+    /// Send queued emails until aborted; `None` if the worker already runs
+    pub fn spawn(self: Arc<Self>) -> Option<JoinHandle<()>> {
+        // The receiver is only ever taken, so a poisoned lock still holds it
+        let mut receiver = self
+            .receiver
+            .lock()
+            .unwrap_or_else(|poisoned| {
+                warn!("Recovered the poisoned notification queue lock");
+                poisoned.into_inner()
+            })
+            .take()?;
+        Some(tokio::spawn(async move {
+            while let Some(queued) = receiver.recv().await {
+                self.deliver(queued).await;
+            }
+        }))
+    }
+
+    async fn deliver(&self, Queued { kind, email }: Queued) {
+        let mut backoff = self.retry_backoff;
+        let mut attempt = 1;
+        let error = loop {
+            match self.send(&email).await {
+                Ok(()) => {
+                    track_notification("email", kind.as_str(), "sent");
+                    return;
+                }
+                Err(e) if attempt >= self.max_attempts => break e,
+                Err(e) => {
+                    warn!(kind = kind.as_str(), attempt, "Failed to send notification: {}", e);
+                    track_notification("email", kind.as_str(), "retried");
+                    tokio::time::sleep(backoff).await;
+                    backoff = backoff.saturating_mul(2);
+                    attempt += 1;
+                }
+            }
+        };
+
+        track_notification("email", kind.as_str(), "failed");
+        let Some(dead_letters) = &self.dead_letters else {
+            warn!(kind = kind.as_str(), "Gave up on notification {:?}: {}", email.subject, error);
+            return;
+        };
+        if let Err(e) = dead_letters.push(
+            DeadLetterSource::Notification,
+            format!("{} email {:?}", kind.as_str(), email.subject),
+            attempt,
+            error,
+            serde_json::to_value(&email).unwrap_or_default(),
+        ) {
+            warn!(
+                kind = kind.as_str(),
+                "Failed to dead-letter notification {:?}: {}", email.subject, e
+            );
+        }
+    }
+
+    async fn send(&self, email: &Email) -> Result<(), String> {
+        let start = Instant::now();
+        let result = self.mailer.send(email).await;
+        track_notification_send(self.mailer.name(), result.is_ok(), start.elapsed().as_secs_f64());
+        result
+    }
+}
+
+/// Emails requeued from the dead letter queue are sent once more
+#[async_trait]
+impl Retry for Notifier {
+    async fn retry(&self, payload: &serde_json::Value) -> Result<(), String> {
+        let email: Email =
+            serde_json::from_value(payload.clone()).map_err(|e| format!("invalid email: {e}"))?;
+        self.send(&email).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Fails the first `failures` sends, then records the rest
+    #[derive(Default)]
+    struct FakeMailer {
+        failures: AtomicU32,
+        sent: Mutex<Vec<Email>>,
+    }
+
+    #[async_trait]
+    impl Mailer for FakeMailer {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        async fn send(&self, email: &Email) -> Result<(), String> {
+            if self
+                .failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok()
+            {
+                return Err("421 try again later".to_string());
+            }
+            self.sent.lock().unwrap().push(email.clone());
+            Ok(())
+        }
+    }
+
+    fn config() -> NotificationsConfig {
+        NotificationsConfig {
+            admin_recipients: vec!["ops@example.com".to_string()],
+            max_attempts: 2,
+            retry_backoff_ms: 1,
+            ..NotificationsConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_templates_fill_placeholders() {
+        let template =
+            Template::parse("Subject: {{service}} is {{status}}\n\nNow {{status}}; {{other}}.")
+                .unwrap();
+        let (subject, body) = template.render(&[
+            ("service", "ferrous".to_string()),
+            ("status", "degraded".to_string()),
+        ]);
+        assert_eq!(subject, "ferrous is degraded");
+        assert_eq!(body, "Now degraded; {{other}}.");
+
+        assert!(Template::parse("no subject\n\nbody").is_err());
+        assert!(Template::parse("Subject: only a subject").is_err());
+
+        let dir = std::env::temp_dir().join(format!("ferrous-templates-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("health_recovered.txt"), "Subject: All good\n\nBack.").unwrap();
+        let templates = Templates::load(Some(&dir)).unwrap();
+        assert_eq!(templates.get(NotificationKind::HealthRecovered).subject, "All good");
+        assert!(templates
+            .get(NotificationKind::HealthDegraded)
+            .subject
+            .contains("{{status}}"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_failed_emails_are_retried_then_dead_lettered() {
+        let mailer = Arc::new(FakeMailer::default());
+        // Both attempts at the first email fail, and the first at the second
+        mailer.failures.store(3, Ordering::SeqCst);
+        let dead_letters = Arc::new(DeadLetterQueue::default());
+        let notifier = Arc::new(
+            Notifier::new(mailer.clone(), &config()).with_dead_letters(dead_letters.clone()),
+        );
+        dead_letters
+            .register(DeadLetterSource::Notification, notifier.clone())
+            .unwrap();
+        let worker = notifier.clone().spawn().unwrap();
+        assert!(notifier.clone().spawn().is_none());
+
+        notifier.queue(
+            NotificationKind::HealthDegraded,
+            Email {
+                to: vec!["oncall@example.com".to_string()],
+                subject: "ferrous is degraded".to_string(),
+                body: String::new(),
+            },
+        );
+        notifier.notify_admins(
+            NotificationKind::HealthRecovered,
+            "ferrous has recovered".to_string(),
+            String::new(),
+        );
+        for _ in 0..200 {
+            if mailer.sent.lock().unwrap().len() == 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        let sent = mailer.sent.lock().unwrap().clone();
+        assert_eq!(sent[0].to, ["ops@example.com"]);
+        assert_eq!(sent[0].subject, "ferrous has recovered");
+        let letters = dead_letters
+            .list(Some(DeadLetterSource::Notification))
+            .unwrap();
+        assert_eq!(letters[0].attempts, 2);
+
+        dead_letters.requeue(&letters[0].id).await.unwrap();
+        assert!(dead_letters.is_empty().unwrap());
+        assert_eq!(mailer.sent.lock().unwrap()[1].to, ["oncall@example.com"]);
+        worker.abort();
+    }
+}
@@ -0,0 +1,357 @@
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Utc;
+use std::time::Duration;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufStream},
+    net::TcpStream,
+    time::timeout,
+};
+use tokio_native_tls::{native_tls, TlsConnector};
+
+use super::{Email, Mailer};
+use crate::config::{NotificationsConfig, SmtpTls};
+
+/// Longest reply line accepted from the server
+const MAX_REPLY_LINE: usize = 4096;
+
+/// Streams an SMTP session can run over, encrypted or not
+trait Connection: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Connection for T {}
+
+/// Sends email through an SMTP server, one session per email
+///
+/// Sessions authenticate with `AUTH PLAIN` when credentials are configured,
+/// which is only done over TLS unless TLS is turned off entirely.
+pub struct SmtpMailer {
+    host: String,
+    port: u16,
+    tls: SmtpTls,
+    credentials: Option<(String, String)>,
+    from: String,
+    timeout: Duration,
+}
+
+impl SmtpMailer {
+    /// Mailer for the configured server; `None` when no server is configured
+    pub fn from_config(config: &NotificationsConfig) -> Option<Self> {
+        Some(Self {
+            host: config.smtp_host.clone()?,
+            port: config.smtp_port,
+            tls: config.smtp_tls,
+            credentials: config
+                .smtp_username
+                .clone()
+                .zip(config.smtp_password.clone()),
+            from: config.from.clone()?,
+            timeout: Duration::from_millis(config.timeout_ms),
+        })
+    }
+
+    async fn session(&self, email: &Email) -> Result<(), String> {
+        let tcp = TcpStream::connect((self.host.as_str(), self.port))
+            .await
+            .map_err(|e| format!("failed to connect to {}:{}: {e}", self.host, self.port))?;
+        let stream: Box<dyn Connection> = match self.tls {
+            SmtpTls::Tls => Box::new(self.encrypt(tcp).await?),
+            SmtpTls::None | SmtpTls::StartTls => Box::new(tcp),
+        };
+        let mut session = Session {
+            stream: BufStream::new(stream),
+            timeout: self.timeout,
+        };
+        session.expect(220).await?;
+        let hello = format!("EHLO {}", hello_name(&self.from));
+        let mut extensions = session.command(&hello, 250).await?;
+
+        if self.tls == SmtpTls::StartTls {
+            if !supports(&extensions, "STARTTLS") {
+                return Err("server does not offer STARTTLS".to_string());
+            }
+            session.command("STARTTLS", 220).await?;
+            let stream: Box<dyn Connection> =
+                Box::new(self.encrypt(session.stream.into_inner()).await?);
+            session.stream = BufStream::new(stream);
+            extensions = session.command(&hello, 250).await?;
+        }
+
+        if let Some((username, password)) = &self.credentials {
+            if !supports(&extensions, "AUTH") {
+                return Err("server does not offer authentication".to_string());
+            }
+            let token = STANDARD.encode(format!("\0{username}\0{password}"));
+            session.command(&format!("AUTH PLAIN {token}"), 235).await?;
+        }
+
+        session
+            .command(&format!("MAIL FROM:<{}>", self.from), 250)
+            .await?;
+        for recipient in &email.to {
+            session
+                .command(&format!("RCPT TO:<{recipient}>"), 250)
+                .await?;
+        }
+        session.command("DATA", 354).await?;
+        let message = format_message(&self.from, email);
+        session.send(&format!("{message}\r\n.")).await?;
+        session.expect(250).await?;
+        // The email is accepted; a failed goodbye does not change that
+        let _ = session.command("QUIT", 221).await;
+        Ok(())
+    }
+
+    async fn encrypt<S>(&self, stream: S) -> Result<tokio_native_tls::TlsStream<S>, String>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let connector = native_tls::TlsConnector::new().map_err(|e| e.to_string())?;
+        TlsConnector::from(connector)
+            .connect(&self.host, stream)
+            .await
+            .map_err(|e| format!("TLS handshake with {} failed: {e}", self.host))
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    fn name(&self) -> &'static str {
+        "smtp"
+    }
+
+    async fn send(&self, email: &Email) -> Result<(), String> {
+        self.session(email).await
+    }
+}
+
+struct Session {
+    stream: BufStream<Box<dyn Connection>>,
+    timeout: Duration,
+}
+
+impl Session {
+    async fn send(&mut self, line: &str) -> Result<(), String> {
+        let write = async {
+            self.stream.write_all(line.as_bytes()).await?;
+            self.stream.write_all(b"\r\n").await?;
+            self.stream.flush().await
+        };
+        timeout(self.timeout, write)
+            .await
+            .map_err(|_| "timed out writing to the server".to_string())?
+            .map_err(|e| e.to_string())
+    }
+
+    /// Read a reply, which must have status `code`, and return its text
+    async fn expect(&mut self, code: u16) -> Result<String, String> {
+        let mut text = String::new();
+        loop {
+            let mut line = String::new();
+            let read = timeout(self.timeout, self.stream.read_line(&mut line))
+                .await
+                .map_err(|_| "timed out waiting for the server".to_string())?
+                .map_err(|e| e.to_string())?;
+            if read == 0 {
+                return Err("server closed the connection".to_string());
+            }
+            if line.len() > MAX_REPLY_LINE || line.len() < 4 {
+                return Err(format!("malformed reply: {}", line.trim_end()));
+            }
+            let status: u16 = line[..3]
+                .parse()
+                .map_err(|_| format!("malformed reply: {}", line.trim_end()))?;
+            text.push_str(line[4..].trim_end());
+            text.push('\n');
+            // `250-` continues a reply, `250 ` ends it
+            if line.as_bytes()[3] == b'-' {
+                continue;
+            }
+            return if status == code {
+                Ok(text)
+            } else {
+                Err(format!("server answered {status} {}", text.trim_end()))
+            };
+        }
+    }
+
+    async fn command(&mut self, command: &str, code: u16) -> Result<String, String> {
+        self.send(command).await?;
+        self.expect(code).await.map_err(|e| {
+            // Never echo credentials into errors and logs
+            let verb = command.split(' ').next().unwrap_or_default();
+            format!("{verb}: {e}")
+        })
+    }
+}
+
+/// Whether an `EHLO` reply lists the extension `name`
+fn supports(extensions: &str, name: &str) -> bool {
+    extensions
+        .lines()
+        .any(|line| line.split_whitespace().next() == Some(name))
+}
+
+/// Name to greet the server with: the sender's domain
+fn hello_name(from: &str) -> &str {
+    from.rsplit_once('@')
+        .map_or("localhost", |(_, domain)| domain)
+}
+
+/// A header value without line breaks, encoded per RFC 2047 unless ASCII
+fn header_value(value: &str) -> String {
+    let value: String = value.chars().filter(|c| !c.is_control()).collect();
+    if value.is_ascii() {
+        value
+    } else {
+        format!("=?UTF-8?B?{}?=", STANDARD.encode(value))
+    }
+}
+
+/// `email` as an RFC 5322 message with a base64 text body, which never
+/// needs dot-stuffing
+fn format_message(from: &str, email: &Email) -> String {
+    let body = STANDARD.encode(email.body.replace('\n', "\r\n"));
+    let mut lines = vec![
+        format!("From: {from}"),
+        format!("To: {}", email.to.join(", ")),
+        format!("Subject: {}", header_value(&email.subject)),
+        format!("Date: {}", Utc::now().to_rfc2822()),
+        format!("Message-ID: <{}@{}>", uuid::Uuid::new_v4(), hello_name(from)),
+        "MIME-Version: 1.0".to_string(),
+        "Content-Type: text/plain; charset=utf-8".to_string(),
+        "Content-Transfer-Encoding: base64".to_string(),
+        String::new(),
+    ];
+    lines.extend(
+        body.as_bytes()
+            .chunks(76)
+            .map(|chunk| String::from_utf8_lossy(chunk).into_owned()),
+    );
+    lines.join("\r\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{io::BufReader, net::TcpListener};
+
+    /// A server answering each command with the next scripted reply,
+    /// returning the commands it received
+    async fn server(
+        replies: &'static [&'static str],
+    ) -> (u16, tokio::task::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let task = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut socket = BufReader::new(socket);
+            socket.write_all(b"220 test ESMTP\r\n").await.unwrap();
+            let mut received = Vec::new();
+            let mut replies = replies.iter();
+            let mut in_data = false;
+            loop {
+                let mut line = String::new();
+                if socket.read_line(&mut line).await.unwrap() == 0 {
+                    return received;
+                }
+                let line = line.trim_end().to_string();
+                if in_data {
+                    in_data = line != ".";
+                    if in_data {
+                        continue;
+                    }
+                } else {
+                    in_data = line == "DATA";
+                }
+                received.push(line);
+                let reply = replies.next().unwrap();
+                socket
+                    .write_all(format!("{reply}\r\n").as_bytes())
+                    .await
+                    .unwrap();
+            }
+        });
+        (port, task)
+    }
+
+    fn mailer(port: u16) -> SmtpMailer {
+        SmtpMailer::from_config(&NotificationsConfig {
+            smtp_host: Some("127.0.0.1".to_string()),
+            smtp_port: port,
+            smtp_tls: SmtpTls::None,
+            smtp_username: Some("user".to_string()),
+            smtp_password: Some("pass".to_string()),
+            from: Some("alerts@example.com".to_string()),
+            ..NotificationsConfig::default()
+        })
+        .unwrap()
+    }
+
+    fn email() -> Email {
+        Email {
+            to: vec![
+                "ops@example.com".to_string(),
+                "oncall@example.com".to_string(),
+            ],
+            subject: "Health degraded".to_string(),
+            body: "The service is degraded.\n.\nCheck /health.".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_emails_are_sent_in_one_session() {
+        let (port, server) = server(&[
+            "250-test\r\n250-AUTH PLAIN\r\n250 8BITMIME",
+            "235 ok",
+            "250 ok",
+            "250 ok",
+            "250 ok",
+            "354 go ahead",
+            "250 queued",
+            "221 bye",
+        ])
+        .await;
+        mailer(port).send(&email()).await.unwrap();
+
+        let received = server.await.unwrap();
+        assert_eq!(
+            received,
+            [
+                "EHLO example.com",
+                &format!("AUTH PLAIN {}", STANDARD.encode("\0user\0pass")),
+                "MAIL FROM:<alerts@example.com>",
+                "RCPT TO:<ops@example.com>",
+                "RCPT TO:<oncall@example.com>",
+                "DATA",
+                ".",
+                "QUIT",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_refused_commands_fail_the_send() {
+        let (port, _server) = server(&["250 AUTH PLAIN", "535 bad credentials"]).await;
+        let error = mailer(port).send(&email()).await.unwrap_err();
+        assert_eq!(error, "AUTH: server answered 535 bad credentials");
+    }
+
+    #[test]
+    fn test_messages_encode_subjects_and_bodies() {
+        let email = Email {
+            subject: "Zustand geändert\r\nBcc: someone@example.com".to_string(),
+            ..email()
+        };
+        let message = format_message("alerts@example.com", &email);
+        assert!(message.contains("To: ops@example.com, oncall@example.com\r\n"));
+        assert!(!message.contains("\r\nBcc:"));
+        assert!(message.contains("Subject: =?UTF-8?B?"));
+        let body = message
+            .split("\r\n\r\n")
+            .nth(1)
+            .unwrap()
+            .replace("\r\n", "");
+        let decoded = String::from_utf8(STANDARD.decode(body).unwrap()).unwrap();
+        assert_eq!(decoded, "The service is degraded.\r\n.\r\nCheck /health.");
+    }
+}
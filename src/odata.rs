@@ -0,0 +1,353 @@
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+
+use crate::{
+    db::ItemFilter,
+    error::AppError,
+    handlers::ListQuery,
+    pagination::Page,
+    query::{
+        Comparison, Condition, Decimal, FieldValue, FilterField, ListOptions, QueryField, Sort,
+        ValueKind,
+    },
+};
+
+/// Why OData query options could not be understood
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ODataError {
+    #[error("unknown field `{0}`")]
+    Field(String),
+    #[error("unsupported $filter operator `{0}`")]
+    Operator(String),
+    #[error("invalid value `{value}` for field `{field}`")]
+    Value { field: String, value: String },
+    #[error("$filter only supports conditions joined by `and`")]
+    Or,
+    #[error("invalid $filter near `{0}`")]
+    Syntax(String),
+    #[error("invalid $orderby direction `{0}`; use asc or desc")]
+    Direction(String),
+}
+
+impl From<ODataError> for AppError {
+    fn from(error: ODataError) -> Self {
+        AppError::BadRequest(error.to_string())
+    }
+}
+
+/// Translate the list query, OData options included, into [`ListOptions`]
+///
+/// `$top` and `$skip` take precedence over `limit` and `offset`; `filter` is
+/// narrowed by `$filter`.
+pub fn list_options(query: &ListQuery, filter: ItemFilter) -> Result<ListOptions, AppError> {
+    let conditions = query.filter.as_deref().map(parse_filter).transpose()?;
+    let order = query.orderby.as_deref().map(parse_orderby).transpose()?;
+    let select = query.select.as_deref().map(parse_select).transpose()?;
+
+    let page = Page::from_query(query.page_limit(), query.page_offset(), query.cursor.as_deref())?
+        .ordered_by(order.unwrap_or_default())?;
+    Ok(ListOptions {
+        filter: filter.with_conditions(conditions.unwrap_or_default()),
+        page,
+        select: select.map(Into::into),
+    })
+}
+
+/// Parse `$filter`: comparisons such as `name eq 'Widget'` and the functions
+/// `contains`, `startswith` and `endswith`, joined by `and`
+///
+/// Custom fields are named `custom_fields/<name>`; whether they are defined
+/// is for the caller to check, as definitions differ by tenant.
+pub fn parse_filter(filter: &str) -> Result<Vec<Condition>, ODataError> {
+    let mut tokens = tokenize(filter)?.into_iter();
+    let mut conditions = Vec::new();
+    loop {
+        conditions.push(condition(&mut tokens)?);
+        match tokens.next() {
+            None => return Ok(conditions),
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case("and") => {}
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case("or") => {
+                return Err(ODataError::Or);
+            }
+            Some(token) => return Err(ODataError::Syntax(token.to_string())),
+        }
+    }
+}
+
+/// Parse `$orderby`: fields, each optionally followed by `asc` or `desc`
+pub fn parse_orderby(orderby: &str) -> Result<Vec<Sort>, ODataError> {
+    orderby
+        .split(',')
+        .map(|key| {
+            let mut words = key.split_whitespace();
+            let field = field(words.next().unwrap_or_default())?;
+            let descending = match words.next() {
+                None => false,
+                Some(direction) if direction.eq_ignore_ascii_case("asc") => false,
+                Some(direction) if direction.eq_ignore_ascii_case("desc") => true,
+                Some(direction) => return Err(ODataError::Direction(direction.to_string())),
+            };
+            match words.next() {
+                Some(extra) => Err(ODataError::Syntax(extra.to_string())),
+                None => Ok(Sort { field, descending }),
+            }
+        })
+        .collect()
+}
+
+/// Parse `$select`: a comma-separated list of fields
+pub fn parse_select(select: &str) -> Result<Vec<QueryField>, ODataError> {
+    select.split(',').map(|name| field(name.trim())).collect()
+}
+
+fn field(name: &str) -> Result<QueryField, ODataError> {
+    QueryField::parse(name).ok_or_else(|| ODataError::Field(name.to_string()))
+}
+
+fn filter_field(name: &str) -> Result<FilterField, ODataError> {
+    match name.strip_prefix("custom_fields/") {
+        Some(custom) if !custom.is_empty() => Ok(FilterField::Custom(custom.to_string())),
+        _ => field(name).map(FilterField::Item),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Text(String),
+    Open,
+    Close,
+    Comma,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Word(word) => f.write_str(word),
+            Self::Text(text) => write!(f, "'{}'", text.replace('\'', "''")),
+            Self::Open => f.write_str("("),
+            Self::Close => f.write_str(")"),
+            Self::Comma => f.write_str(","),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, ODataError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            ',' => tokens.push(Token::Comma),
+            '\'' => {
+                // Quotes inside strings are doubled
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\'') if chars.peek() == Some(&'\'') => {
+                            chars.next();
+                            text.push('\'');
+                        }
+                        Some('\'') => break,
+                        Some(c) => text.push(c),
+                        None => return Err(ODataError::Syntax(format!("'{text}"))),
+                    }
+                }
+                tokens.push(Token::Text(text));
+            }
+            c => {
+                let mut word = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '(' | ')' | ',' | '\'') {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn condition(tokens: &mut impl Iterator<Item = Token>) -> Result<Condition, ODataError> {
+    let mut next = || {
+        tokens
+            .next()
+            .ok_or_else(|| ODataError::Syntax("end of $filter".to_string()))
+    };
+    let first = match next()? {
+        Token::Word(word) => word,
+        token => return Err(ODataError::Syntax(token.to_string())),
+    };
+
+    let function = match first.to_ascii_lowercase().as_str() {
+        "contains" => Some(Comparison::Contains),
+        "startswith" => Some(Comparison::StartsWith),
+        "endswith" => Some(Comparison::EndsWith),
+        _ => None,
+    };
+    if let Some(comparison) = function {
+        // contains(field, 'text')
+        let (open, name, comma, value, close) = (next()?, next()?, next()?, next()?, next()?);
+        return match (open, name, comma, value, close) {
+            (Token::Open, Token::Word(name), Token::Comma, value, Token::Close) => {
+                let field = filter_field(&name)?;
+                let value = literal(&field, value)?;
+                if !matches!(value, FieldValue::Text(_)) {
+                    return Err(ODataError::Operator(first));
+                }
+                Ok(Condition {
+                    field,
+                    comparison,
+                    value,
+                })
+            }
+            _ => Err(ODataError::Syntax(first)),
+        };
+    }
+
+    let field = filter_field(&first)?;
+    let comparison = match next()? {
+        Token::Word(operator) => match operator.to_ascii_lowercase().as_str() {
+            "eq" => Comparison::Eq,
+            "ne" => Comparison::Ne,
+            "gt" => Comparison::Gt,
+            "ge" => Comparison::Ge,
+            "lt" => Comparison::Lt,
+            "le" => Comparison::Le,
+            _ => return Err(ODataError::Operator(operator)),
+        },
+        token => return Err(ODataError::Syntax(token.to_string())),
+    };
+    let value = literal(&field, next()?)?;
+    Ok(Condition {
+        field,
+        comparison,
+        value,
+    })
+}
+
+/// A literal compared with `field`, typed by the field
+///
+/// Custom field literals are typed by how they are written: quoted text
+/// (dates included, which compare as text), numbers, `true` or `false`.
+fn literal(field: &FilterField, token: Token) -> Result<FieldValue, ODataError> {
+    let invalid = |value: String| ODataError::Value {
+        field: field.to_string(),
+        value,
+    };
+    let field = match field {
+        FilterField::Item(field) => *field,
+        FilterField::Custom(_) => {
+            return match token {
+                Token::Word(word) if word == "null" => Ok(FieldValue::Null),
+                Token::Word(word) if word == "true" => Ok(FieldValue::Bool(true)),
+                Token::Word(word) if word == "false" => Ok(FieldValue::Bool(false)),
+                Token::Word(number) => number
+                    .parse()
+                    .ok()
+                    .filter(|n: &f64| n.is_finite())
+                    .map(|n| FieldValue::Decimal(Decimal(n)))
+                    .ok_or_else(|| invalid(number)),
+                Token::Text(text) => Ok(FieldValue::Text(text)),
+                token => Err(invalid(token.to_string())),
+            };
+        }
+    };
+    match (field.kind(), token) {
+        (_, Token::Word(word)) if word == "null" => Ok(FieldValue::Null),
+        (ValueKind::Text, Token::Text(text)) => Ok(FieldValue::Text(text)),
+        (ValueKind::Number, Token::Word(number)) => number
+            .parse()
+            .map(FieldValue::Number)
+            .map_err(|_| invalid(number)),
+        // Datetimes may be written bare, as in OData, or quoted
+        (ValueKind::Time, Token::Word(time) | Token::Text(time)) => {
+            DateTime::parse_from_rfc3339(&time)
+                .map(|time| FieldValue::Time(time.with_timezone(&Utc)))
+                .map_err(|_| invalid(time))
+        }
+        (_, token) => Err(invalid(token.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_translates_into_conditions() {
+        let conditions =
+            parse_filter("name eq 'O''Brien' and version ge 2 and startswith(slug, 'o-')").unwrap();
+        assert_eq!(
+            conditions,
+            vec![
+                Condition {
+                    field: QueryField::Name.into(),
+                    comparison: Comparison::Eq,
+                    value: FieldValue::Text("O'Brien".to_string()),
+                },
+                Condition {
+                    field: QueryField::Version.into(),
+                    comparison: Comparison::Ge,
+                    value: FieldValue::Number(2),
+                },
+                Condition {
+                    field: QueryField::Slug.into(),
+                    comparison: Comparison::StartsWith,
+                    value: FieldValue::Text("o-".to_string()),
+                },
+            ]
+        );
+
+        let created = parse_filter("created_at lt 2024-01-01T00:00:00Z").unwrap();
+        assert!(matches!(created[0].value, FieldValue::Time(_)));
+        assert_eq!(parse_filter("description eq null").unwrap()[0].value, FieldValue::Null);
+
+        let custom =
+            parse_filter("custom_fields/size gt 2.5 and custom_fields/rush eq true").unwrap();
+        assert_eq!(custom[0].field, FilterField::Custom("size".to_string()));
+        assert_eq!(custom[0].value, FieldValue::Decimal(Decimal(2.5)));
+        assert_eq!(custom[1].value, FieldValue::Bool(true));
+    }
+
+    #[test]
+    fn test_unsupported_filters_are_rejected() {
+        assert_eq!(parse_filter("name eq 'a' or name eq 'b'"), Err(ODataError::Or));
+        assert_eq!(parse_filter("color eq 'red'"), Err(ODataError::Field("color".to_string())));
+        assert_eq!(parse_filter("name like 'a'"), Err(ODataError::Operator("like".to_string())));
+        assert!(matches!(parse_filter("version eq 'two'"), Err(ODataError::Value { .. })));
+        assert!(matches!(parse_filter("name eq 'open"), Err(ODataError::Syntax(_))));
+        assert!(matches!(
+            parse_filter("custom_fields/size eq big"),
+            Err(ODataError::Value { .. })
+        ));
+        assert!(matches!(parse_filter("name eq"), Err(ODataError::Syntax(_))));
+    }
+
+    #[test]
+    fn test_orderby_and_select_name_fields() {
+        assert_eq!(
+            parse_orderby("name desc, created_at").unwrap(),
+            vec![
+                Sort {
+                    field: QueryField::Name,
+                    descending: true,
+                },
+                Sort {
+                    field: QueryField::CreatedAt,
+                    descending: false,
+                },
+            ]
+        );
+        assert_eq!(
+            parse_orderby("name sideways"),
+            Err(ODataError::Direction("sideways".to_string()))
+        );
+        assert_eq!(parse_select("id, name").unwrap(), vec![QueryField::Id, QueryField::Name]);
+    }
+}
@@ -1,9 +1,66 @@
 use crate::{
-    error::{ErrorCode, ErrorDetails, ErrorResponse, ValidationError},
-    handlers::{DatabaseHealth, HealthResponse, HealthStatus, ListResponse, SystemHealth},
-    models::{CreateItemRequest, Item, UpdateItemRequest},
+    analytics::{AnalyticsBucket, AnalyticsResponse, Granularity},
+    approvals::{
+        Approval, ChangeAction, ChangeRequest, ChangeRequestList, ChangeStatus, RejectChangeRequest,
+    },
+    attachments::{Attachment, PresignUploadRequest, PresignedUpload},
+    backup::RestoreReport,
+    build_info::BuildInfo,
+    checkout::{ItemLock, LockItemRequest},
+    collections::{
+        CollectionItems, CollectionList, CollectionPlacement, CreateCollectionRequest,
+        FileItemRequest, ItemCollection, MoveCollectionRequest,
+    },
+    comments::{Comment, CommentList, CommentRequest},
+    config::ResponseEnvelope,
+    conflicts::{FieldDiff, MergeStrategy, UpdateConflict},
+    custom_fields::{
+        CustomFieldDefinition, CustomFieldType, CustomFields, DefineCustomFieldRequest,
+    },
+    dead_letters::{DeadLetter, DeadLetterSource, DeadLetterSummary},
+    deprecation::Deprecations,
+    discovery::{ApiIndex, ApiVersion, Collection},
+    dual_write::{Divergence, DualWriteReport},
+    duplicates::{DuplicateList, PossibleDuplicate},
+    error::{AppResult, ErrorCode, ErrorDetails, ErrorResponse, ValidationError},
+    events::{
+        replay::{ReplayFailure, ReplayReport, ReplayRequest, ReplaySinkKind},
+        webhook::WebhookDelivery,
+        Change, CloudEvent, ItemEventType,
+    },
+    handlers::{
+        ChangesResponse, ContainerHealth, DatabaseHealth, ErrorRateHealth, EventBrokerHealth,
+        HealthResponse, HealthStatus, ListResponse, SystemHealth,
+    },
+    health::CheckHealth,
+    inbox::{MarkedRead, Notification, NotificationList, NotificationReason, UnreadCount},
+    log_filter::{LogFilterStatus, SetLogFilterRequest},
+    merge::{MergeItemsRequest, MergeReport},
+    middleware::{
+        chaos::{Fault, FaultRule},
+        envelope,
+        mocks::{MockEndpoint, MockEndpointRequest},
+    },
+    models::{
+        AccessGrant, CreateItemRequest, CreateTenantRequest, ErasureMode, ErasureRequest,
+        GrantPermissionRequest, Grantee, Item, ItemField, ItemStatus, Permission,
+        ProvisionedTenant, Tenant, TenantQuotas, TenantStatus, UpdateItemRequest,
+        UpdateTenantRequest,
+    },
+    privacy::{ErasureReport, SignedErasureReport},
+    profiling::{CpuProfile, HeapProfile, ThreadCpu},
+    publishing::PublishItemRequest,
+    saved_searches::{CreateSavedSearchRequest, SavedSearch, SavedSearchResults},
+    sbom::{LicenseReport, LicenseUsage},
+    scanning::ScanStatus,
+    schemas::{MetadataSchema, RegisterSchemaRequest, SchemaScope},
+    sharing::{CreateShareRequest, CreatedShare, Share, SharedAttachment, SharedItem},
+    sql::{QueryRequest, QueryResult},
+    stats::{CreationCounts, ItemStatsResponse, OwnerCount},
+    views::ViewResponse,
 };
-use axum::{response::IntoResponse, routing::get, Json, Router};
+use axum::{extract::State, routing::get, Json, Router};
+use std::sync::Arc;
 use utoipa::{
     openapi::security::{Http, HttpAuthScheme, SecurityScheme},
     Modify, OpenApi,
@@ -32,11 +89,110 @@ use utoipa::{
         crate::handlers::health_check,
         crate::handlers::liveness,
         crate::handlers::readiness,
+        crate::handlers::version,
+        crate::handlers::api_index,
         crate::handlers::list_items,
+        crate::handlers::item_changes,
+        crate::handlers::item_stats,
+        crate::handlers::item_analytics,
+        crate::handlers::get_view,
+        crate::handlers::list_custom_fields,
+        crate::handlers::custom_fields_schema,
+        crate::handlers::define_custom_field,
+        crate::handlers::get_custom_field,
+        crate::handlers::delete_custom_field,
+        crate::handlers::list_schemas,
+        crate::handlers::register_schema,
+        crate::handlers::get_schema,
+        crate::handlers::list_schema_versions,
+        crate::handlers::get_schema_version,
+        crate::handlers::delete_schema,
         crate::handlers::get_item,
+        crate::handlers::get_item_by_slug,
         crate::handlers::create_item,
+        crate::handlers::upsert_item,
         crate::handlers::update_item,
         crate::handlers::delete_item,
+        crate::handlers::find_duplicates,
+        crate::handlers::merge_items,
+        crate::handlers::publish_item,
+        crate::handlers::lock_item,
+        crate::handlers::get_item_lock,
+        crate::handlers::unlock_item,
+        crate::handlers::grant_permission,
+        crate::handlers::list_permissions,
+        crate::handlers::revoke_permission,
+        crate::handlers::presign_attachment,
+        crate::handlers::confirm_attachment,
+        crate::handlers::list_attachments,
+        crate::handlers::get_attachment_thumbnail,
+        crate::handlers::list_comments,
+        crate::handlers::create_comment,
+        crate::handlers::get_comment,
+        crate::handlers::update_comment,
+        crate::handlers::delete_comment,
+        crate::handlers::get_item_collection,
+        crate::handlers::file_item,
+        crate::handlers::list_collections,
+        crate::handlers::create_collection,
+        crate::handlers::get_collection,
+        crate::handlers::delete_collection,
+        crate::handlers::move_collection,
+        crate::handlers::list_collection_descendants,
+        crate::handlers::list_collection_items,
+        crate::handlers::grant_collection_permission,
+        crate::handlers::list_collection_permissions,
+        crate::handlers::revoke_collection_permission,
+        crate::handlers::create_share,
+        crate::handlers::list_shares,
+        crate::handlers::revoke_share,
+        crate::handlers::open_share,
+        crate::handlers::list_notifications,
+        crate::handlers::unread_notification_count,
+        crate::handlers::mark_notification_read,
+        crate::handlers::mark_all_notifications_read,
+        crate::handlers::list_changes,
+        crate::handlers::get_change,
+        crate::handlers::approve_change,
+        crate::handlers::reject_change,
+        crate::handlers::create_saved_search,
+        crate::handlers::list_saved_searches,
+        crate::handlers::get_saved_search,
+        crate::handlers::delete_saved_search,
+        crate::handlers::saved_search_results,
+        crate::handlers::list_webhook_deliveries,
+        crate::handlers::redeliver_webhook,
+        crate::handlers::create_backup,
+        crate::handlers::restore_backup,
+        crate::handlers::erase_principal_data,
+        crate::handlers::create_tenant,
+        crate::handlers::list_tenants,
+        crate::handlers::get_tenant,
+        crate::handlers::update_tenant,
+        crate::handlers::delete_tenant,
+        crate::handlers::get_config,
+        crate::handlers::get_log_level,
+        crate::handlers::set_log_level,
+        crate::handlers::reset_log_level,
+        crate::handlers::get_chaos_rules,
+        crate::handlers::set_chaos_rules,
+        crate::handlers::clear_chaos_rules,
+        crate::handlers::list_mocks,
+        crate::handlers::create_mock,
+        crate::handlers::delete_mock,
+        crate::handlers::clear_mocks,
+        crate::handlers::list_dead_letters,
+        crate::handlers::get_dead_letter,
+        crate::handlers::requeue_dead_letter,
+        crate::handlers::discard_dead_letter,
+        crate::handlers::replay_events,
+        crate::handlers::run_admin_query,
+        crate::handlers::get_dual_write_report,
+        crate::handlers::reconcile_dual_write,
+        crate::handlers::get_sbom,
+        crate::handlers::get_license_report,
+        crate::handlers::cpu_profile,
+        crate::handlers::heap_profile,
     ),
     components(
         schemas(
@@ -44,13 +200,136 @@ use utoipa::{
             Item,
             CreateItemRequest,
             UpdateItemRequest,
+            ItemField,
+            UpdateConflict,
+            FieldDiff,
+            MergeStrategy,
             ListResponse,
+            Change,
+            ChangesResponse,
+            ItemStatsResponse,
+            CreationCounts,
+            OwnerCount,
+            AnalyticsResponse,
+            AnalyticsBucket,
+            Granularity,
+            ViewResponse,
+            CustomFieldDefinition,
+            CustomFieldType,
+            DefineCustomFieldRequest,
+            MetadataSchema,
+            RegisterSchemaRequest,
+            SchemaScope,
+            DuplicateList,
+            PossibleDuplicate,
+            MergeItemsRequest,
+            MergeReport,
+            ItemStatus,
+            PublishItemRequest,
+            ItemLock,
+            LockItemRequest,
+            AccessGrant,
+            GrantPermissionRequest,
+            Grantee,
+            Permission,
+            Attachment,
+            ScanStatus,
+            PresignUploadRequest,
+            PresignedUpload,
+            Comment,
+            CommentRequest,
+            CommentList,
+            Share,
+            CreateShareRequest,
+            CreatedShare,
+            SharedItem,
+            SharedAttachment,
+
+            // Collections
+            ItemCollection,
+            CreateCollectionRequest,
+            MoveCollectionRequest,
+            FileItemRequest,
+            CollectionPlacement,
+            CollectionList,
+            CollectionItems,
+
+            // Notifications
+            Notification,
+            NotificationReason,
+            NotificationList,
+            UnreadCount,
+            MarkedRead,
+
+            // Change requests
+            ChangeRequest,
+            ChangeAction,
+            ChangeStatus,
+            Approval,
+            ChangeRequestList,
+            RejectChangeRequest,
+
+            // Saved searches
+            SavedSearch,
+            CreateSavedSearchRequest,
+            SavedSearchResults,
+
+            // Admin
+            RestoreReport,
+            ErasureRequest,
+            ErasureMode,
+            ErasureReport,
+            SignedErasureReport,
+            Tenant,
+            TenantStatus,
+            TenantQuotas,
+            CreateTenantRequest,
+            UpdateTenantRequest,
+            ProvisionedTenant,
+            LogFilterStatus,
+            SetLogFilterRequest,
+            FaultRule,
+            MockEndpoint,
+            MockEndpointRequest,
+            Fault,
+            DeadLetter,
+            DeadLetterSummary,
+            DualWriteReport,
+            Divergence,
+            LicenseReport,
+            LicenseUsage,
+            DeadLetterSource,
+            ReplayRequest,
+            ReplaySinkKind,
+            ReplayReport,
+            ReplayFailure,
+            QueryRequest,
+            QueryResult,
+            CpuProfile,
+            ThreadCpu,
+            HeapProfile,
+
+            // Events
+            CloudEvent<Item>,
+            CloudEvent<Comment>,
+            ItemEventType,
+            WebhookDelivery,
+
+            // Discovery
+            ApiIndex,
+            ApiVersion,
+            Collection,
 
             // Health
             HealthResponse,
+            BuildInfo,
             HealthStatus,
             DatabaseHealth,
+            EventBrokerHealth,
             SystemHealth,
+            ContainerHealth,
+            ErrorRateHealth,
+            CheckHealth,
 
             // Errors
             ErrorResponse,
@@ -62,7 +341,18 @@ use utoipa::{
     modifiers(&SecurityAddon),
     tags(
         (name = "health", description = "Health check endpoints"),
+        (name = "discovery", description = "API index, generated from the documented routes"),
         (name = "items", description = "Item management endpoints"),
+        (name = "fields", description = "Typed custom fields tenants define for their items (changes require the admin role)"),
+        (name = "schemas", description = "JSON Schemas item metadata must satisfy (changes require the admin role)"),
+        (name = "collections", description = "Folders items are filed in; grants on a collection apply to everything below it"),
+        (name = "sharing", description = "Items read through share links, without authenticating"),
+        (name = "notifications", description = "The caller's inbox of changes others made to items they own or can access"),
+        (name = "changes", description = "Changes to items in designated collections, held until enough principals approve them"),
+        (name = "saved-searches", description = "Named item searches the caller saved, and subscriptions to new items matching them"),
+        (name = "webhooks", description = "Deliveries of item events to the configured webhooks (require the admin role)"),
+        (name = "admin", description = "Administrative endpoints (require the admin role)"),
+        (name = "events", description = "Item change events are emitted as CloudEvents 1.0 (schema `CloudEvent_Item`) with types com.ferrous.item.created, com.ferrous.item.updated and com.ferrous.item.deleted; comment changes are announced on the in-process bus as CloudEvents (schema `CloudEvent_Comment`) with types com.ferrous.comment.created, com.ferrous.comment.updated and com.ferrous.comment.deleted"),
     ),
 )]
 pub struct ApiDoc;
@@ -82,11 +372,34 @@ impl Modify for SecurityAddon {
 }
 
 /// Create documentation routes
-pub fn create_docs_routes() -> Router {
-    Router::new().route("/openapi.json", get(openapi_json_handler))
+///
+/// The document is served outside any tenant, so it describes the custom
+/// fields defined without multi-tenancy; tenants read their own from
+/// `/api/v1/fields/schema`.
+pub fn create_docs_routes(state: DocsState) -> Router {
+    Router::new()
+        .route("/openapi.json", get(openapi_json_handler))
+        .with_state(state)
 }
 
-/// Serve the OpenAPI JSON spec
-async fn openapi_json_handler() -> impl IntoResponse {
-    Json(ApiDoc::openapi())
+/// What the served document describes beyond the routes themselves
+#[derive(Clone)]
+pub struct DocsState {
+    pub custom_fields: Arc<CustomFields>,
+    pub envelope: ResponseEnvelope,
+    pub deprecations: Arc<Deprecations>,
+}
+
+/// Serve the OpenAPI JSON spec, with the custom fields in the item schemas,
+/// deprecated operations marked and responses in the configured envelope
+async fn openapi_json_handler(
+    State(state): State<DocsState>,
+) -> AppResult<Json<serde_json::Value>> {
+    let mut spec = serde_json::to_value(ApiDoc::openapi()).unwrap_or_default();
+    state.custom_fields.document(&mut spec)?;
+    state.deprecations.document(&mut spec);
+    if state.envelope == ResponseEnvelope::Data {
+        envelope::document(&mut spec);
+    }
+    Ok(Json(spec))
 }
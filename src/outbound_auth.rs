@@ -0,0 +1,285 @@
+use reqwest::{header::AUTHORIZATION, RequestBuilder};
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+use crate::{
+    config::OutboundAuthConfig,
+    http_client::HttpClient,
+    metrics::{track_outbound_token, track_outbound_token_fetch, Timer},
+};
+
+/// Lifetime assumed for tokens issued without `expires_in`
+const DEFAULT_TOKEN_LIFETIME: Duration = Duration::from_secs(300);
+
+/// Why an access token could not be obtained
+#[derive(Debug, thiserror::Error)]
+pub enum TokenError {
+    #[error("token request failed: {0}")]
+    Request(String),
+    #[error("token endpoint responded with {status}: {body}")]
+    Rejected { status: u16, body: String },
+    #[error("invalid token response: {0}")]
+    InvalidResponse(String),
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    expires_in: Option<u64>,
+}
+
+struct CachedToken {
+    access_token: String,
+    expires_at: Instant,
+}
+
+/// Obtains access tokens with the OAuth client credentials grant, for calls
+/// this service makes to downstream APIs
+///
+/// A token is reused until `refresh_before` ahead of its expiry, then replaced
+/// by the next caller. Concurrent callers wait for a single token request
+/// instead of each making their own. If the token endpoint fails while the
+/// cached token is still valid, the cached token keeps being used.
+pub struct TokenProvider {
+    http: HttpClient,
+    token_url: String,
+    client_id: String,
+    client_secret: String,
+    scope: Option<String>,
+    audience: Option<String>,
+    refresh_before: Duration,
+    cached: Mutex<Option<CachedToken>>,
+}
+
+impl TokenProvider {
+    pub fn new(
+        token_url: impl Into<String>,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+    ) -> Self {
+        Self {
+            http: HttpClient::default(),
+            token_url: token_url.into(),
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            scope: None,
+            audience: None,
+            refresh_before: Duration::from_secs(60),
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// Provider for the configured credentials, if outbound auth is configured
+    pub fn from_config(config: &OutboundAuthConfig) -> Option<Self> {
+        let provider = Self::new(
+            config.token_url.clone()?,
+            config.client_id.clone()?,
+            config.client_secret.clone()?,
+        );
+        Some(Self {
+            scope: config.scope.clone(),
+            audience: config.audience.clone(),
+            refresh_before: Duration::from_secs(config.refresh_before_seconds),
+            ..provider
+        })
+    }
+
+    /// Request tokens with the shared outbound client
+    #[must_use]
+    pub fn with_http_client(mut self, http: HttpClient) -> Self {
+        self.http = http;
+        self
+    }
+
+    #[must_use]
+    pub fn with_scope(mut self, scope: impl Into<String>) -> Self {
+        self.scope = Some(scope.into());
+        self
+    }
+
+    #[must_use]
+    pub fn with_refresh_before(mut self, refresh_before: Duration) -> Self {
+        self.refresh_before = refresh_before;
+        self
+    }
+
+    /// A valid access token, from the cache or the token endpoint
+    pub async fn token(&self) -> Result<String, TokenError> {
+        let mut cached = self.cached.lock().await;
+        let now = Instant::now();
+
+        if let Some(token) = cached.as_ref() {
+            if now + self.refresh_before < token.expires_at {
+                track_outbound_token("cached");
+                return Ok(token.access_token.clone());
+            }
+        }
+
+        match self.fetch().await {
+            Ok(token) => {
+                track_outbound_token("fetched");
+                let access_token = token.access_token.clone();
+                *cached = Some(token);
+                Ok(access_token)
+            }
+            Err(e) => match cached.as_ref().filter(|token| now < token.expires_at) {
+                Some(token) => {
+                    warn!("Token refresh failed, using cached token until it expires: {}", e);
+                    track_outbound_token("stale");
+                    Ok(token.access_token.clone())
+                }
+                None => {
+                    track_outbound_token("failed");
+                    Err(e)
+                }
+            },
+        }
+    }
+
+    /// Add a bearer token to an outbound request
+    pub async fn authorize(&self, request: RequestBuilder) -> Result<RequestBuilder, TokenError> {
+        let token = self.token().await?;
+        Ok(request.header(AUTHORIZATION, format!("Bearer {token}")))
+    }
+
+    /// Drop the cached token, after a downstream API rejected it
+    pub async fn invalidate(&self) {
+        *self.cached.lock().await = None;
+    }
+
+    async fn fetch(&self) -> Result<CachedToken, TokenError> {
+        let timer = Timer::new();
+        let result = self.request_token().await;
+        track_outbound_token_fetch(result.is_ok(), timer.elapsed_seconds());
+        result
+    }
+
+    async fn request_token(&self) -> Result<CachedToken, TokenError> {
+        let mut form = vec![("grant_type", "client_credentials")];
+        if let Some(scope) = &self.scope {
+            form.push(("scope", scope));
+        }
+        if let Some(audience) = &self.audience {
+            form.push(("audience", audience));
+        }
+
+        let requested_at = Instant::now();
+        let request = self
+            .http
+            .post(&self.token_url)
+            .basic_auth(&self.client_id, Some(&self.client_secret))
+            .form(&form);
+        let response = self
+            .http
+            .send(request)
+            .await
+            .map_err(|e| TokenError::Request(e.to_string()))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(TokenError::Rejected {
+                status: status.as_u16(),
+                body,
+            });
+        }
+
+        let token: TokenResponse = response
+            .json()
+            .await
+            .map_err(|e| TokenError::InvalidResponse(e.to_string()))?;
+        let lifetime = token
+            .expires_in
+            .map_or(DEFAULT_TOKEN_LIFETIME, Duration::from_secs);
+        debug!(expires_in = lifetime.as_secs(), "Obtained outbound access token");
+
+        // Expiry counts from when the request was sent, not when it returned
+        Ok(CachedToken {
+            access_token: token.access_token,
+            expires_at: requested_at + lifetime,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::State, http::HeaderMap, routing::post, Form, Json, Router};
+    use serde_json::json;
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    async fn spawn_token_endpoint(issued: Arc<AtomicUsize>) -> String {
+        let app = Router::new()
+            .route(
+                "/token",
+                post(
+                    |State(issued): State<Arc<AtomicUsize>>,
+                     headers: HeaderMap,
+                     Form(form): Form<HashMap<String, String>>| async move {
+                        assert!(headers[AUTHORIZATION]
+                            .to_str()
+                            .unwrap()
+                            .starts_with("Basic "));
+                        assert_eq!(form["grant_type"], "client_credentials");
+                        let n = issued.fetch_add(1, Ordering::SeqCst) + 1;
+                        Json(json!({
+                            "access_token": format!("token-{n}"),
+                            "token_type": "Bearer",
+                            "expires_in": 3600,
+                        }))
+                    },
+                ),
+            )
+            .with_state(issued);
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}/token")
+    }
+
+    #[tokio::test]
+    async fn test_tokens_are_cached_until_refresh_window() {
+        let issued = Arc::new(AtomicUsize::new(0));
+        let url = spawn_token_endpoint(issued.clone()).await;
+        let provider = TokenProvider::new(url, "ferrous", "secret").with_scope("items:read");
+
+        assert_eq!(provider.token().await.unwrap(), "token-1");
+        assert_eq!(provider.token().await.unwrap(), "token-1");
+        assert_eq!(issued.load(Ordering::SeqCst), 1);
+
+        provider.invalidate().await;
+        assert_eq!(provider.token().await.unwrap(), "token-2");
+
+        // A token inside the refresh window is replaced before it expires
+        let provider = provider.with_refresh_before(Duration::from_secs(3600));
+        assert_eq!(provider.token().await.unwrap(), "token-3");
+    }
+
+    #[tokio::test]
+    async fn test_endpoint_failure_falls_back_to_valid_token() {
+        let issued = Arc::new(AtomicUsize::new(0));
+        let url = spawn_token_endpoint(issued).await;
+        let provider = TokenProvider::new(url, "ferrous", "secret")
+            .with_refresh_before(Duration::from_secs(3600));
+        assert_eq!(provider.token().await.unwrap(), "token-1");
+
+        let provider = TokenProvider {
+            token_url: "http://127.0.0.1:1/token".to_string(),
+            ..provider
+        };
+        assert_eq!(provider.token().await.unwrap(), "token-1");
+
+        provider.invalidate().await;
+        assert!(matches!(provider.token().await, Err(TokenError::Request(_))));
+    }
+}
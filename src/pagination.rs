@@ -0,0 +1,238 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use std::fmt;
+
+use crate::{error::AppError, models::Item, query::Sort};
+
+/// Items in a page when the caller does not say
+pub const DEFAULT_PAGE_LIMIT: usize = 20;
+
+/// Most items one page may hold
+pub const MAX_PAGE_LIMIT: usize = 100;
+
+/// Furthest a page may start by offset; deeper pages are reached by cursor,
+/// which backends can seek to instead of scanning past every skipped item
+pub const MAX_PAGE_OFFSET: usize = 10_000;
+
+/// Why page bounds were rejected
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PageError {
+    #[error("limit must be between 1 and {MAX_PAGE_LIMIT}")]
+    Limit,
+    #[error("offset must be at most {MAX_PAGE_OFFSET}; use a cursor for deeper pages")]
+    Offset,
+    #[error("cursor cannot be combined with offset")]
+    CursorWithOffset,
+    #[error("invalid cursor")]
+    Cursor,
+    #[error("cursor cannot be combined with a sort order; use an offset")]
+    CursorWithOrder,
+}
+
+impl From<PageError> for AppError {
+    fn from(error: PageError) -> Self {
+        AppError::BadRequest(error.to_string())
+    }
+}
+
+/// Position of an item in list order: creation time, with the ID breaking ties
+///
+/// Encoded as an opaque string for clients, who pass back the cursor of the
+/// last item they saw to get the page after it.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Cursor {
+    pub created_at: DateTime<Utc>,
+    pub id: String,
+}
+
+impl Cursor {
+    /// Cursor just past `item`
+    pub fn after(item: &Item) -> Self {
+        Self {
+            created_at: item.created_at,
+            id: item.id.clone(),
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        let timestamp = self.created_at.timestamp_nanos_opt().unwrap_or_default();
+        URL_SAFE_NO_PAD.encode(format!("{timestamp}:{}", self.id))
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self, PageError> {
+        let decoded = URL_SAFE_NO_PAD
+            .decode(cursor)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or(PageError::Cursor)?;
+        let (timestamp, id) = decoded.split_once(':').ok_or(PageError::Cursor)?;
+        let timestamp = timestamp.parse().map_err(|_| PageError::Cursor)?;
+        if id.is_empty() {
+            return Err(PageError::Cursor);
+        }
+        Ok(Self {
+            created_at: DateTime::from_timestamp_nanos(timestamp),
+            id: id.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.encode())
+    }
+}
+
+/// Where a page starts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PageStart {
+    /// After skipping this many items
+    Offset(usize),
+    /// Just past the item at this cursor
+    After(Cursor),
+}
+
+/// Validated bounds of one page of a listing
+///
+/// Handlers build pages from client input and repositories accept only
+/// pages, so every backend sees the same bounds: a limit of 1 to
+/// [`MAX_PAGE_LIMIT`] and either an offset of at most [`MAX_PAGE_OFFSET`] or a
+/// cursor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page {
+    limit: usize,
+    start: PageStart,
+    /// Sort keys, when not in list order
+    order: Vec<Sort>,
+}
+
+impl Page {
+    /// The first `limit` items
+    pub fn first(limit: usize) -> Result<Self, PageError> {
+        Self::offset(limit, 0)
+    }
+
+    /// `limit` items after skipping `offset`
+    pub fn offset(limit: usize, offset: usize) -> Result<Self, PageError> {
+        if offset > MAX_PAGE_OFFSET {
+            return Err(PageError::Offset);
+        }
+        Self::new(limit, PageStart::Offset(offset))
+    }
+
+    /// `limit` items just past `cursor`
+    pub fn after(limit: usize, cursor: Cursor) -> Result<Self, PageError> {
+        Self::new(limit, PageStart::After(cursor))
+    }
+
+    /// A page from list query parameters, where `cursor` is as encoded
+    pub fn from_query(
+        limit: usize,
+        offset: usize,
+        cursor: Option<&str>,
+    ) -> Result<Self, PageError> {
+        match cursor {
+            Some(_) if offset > 0 => Err(PageError::CursorWithOffset),
+            Some(cursor) => Self::after(limit, Cursor::decode(cursor)?),
+            None => Self::offset(limit, offset),
+        }
+    }
+
+    fn new(limit: usize, start: PageStart) -> Result<Self, PageError> {
+        if !(1..=MAX_PAGE_LIMIT).contains(&limit) {
+            return Err(PageError::Limit);
+        }
+        Ok(Self {
+            limit,
+            start,
+            order: Vec::new(),
+        })
+    }
+
+    /// The same page of the items sorted by `order` instead of list order
+    ///
+    /// Cursors are positions in list order, so sorted pages start at an
+    /// offset.
+    pub fn ordered_by(mut self, order: Vec<Sort>) -> Result<Self, PageError> {
+        if matches!(self.start, PageStart::After(_)) && !order.is_empty() {
+            return Err(PageError::CursorWithOrder);
+        }
+        self.order = order;
+        Ok(self)
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    pub fn start(&self) -> &PageStart {
+        &self.start
+    }
+
+    /// Sort keys; empty in list order
+    pub fn order(&self) -> &[Sort] {
+        &self.order
+    }
+
+    /// Items skipped before the page, which is 0 for a cursor
+    pub fn skipped(&self) -> usize {
+        match self.start {
+            PageStart::Offset(offset) => offset,
+            PageStart::After(_) => 0,
+        }
+    }
+
+    /// Page of the same size just past `last`, in list order
+    pub fn next_after(&self, last: &Item) -> Self {
+        Self {
+            limit: self.limit,
+            start: PageStart::After(Cursor::after(last)),
+            order: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_bounds() {
+        assert!(Page::offset(MAX_PAGE_LIMIT, MAX_PAGE_OFFSET).is_ok());
+        assert_eq!(Page::first(0), Err(PageError::Limit));
+        assert_eq!(Page::first(MAX_PAGE_LIMIT + 1), Err(PageError::Limit));
+        assert_eq!(Page::offset(10, MAX_PAGE_OFFSET + 1), Err(PageError::Offset));
+        assert_eq!(Page::from_query(10, 5, Some("abc")), Err(PageError::CursorWithOffset));
+        assert_eq!(Page::from_query(10, 0, Some("not a cursor")), Err(PageError::Cursor));
+
+        let order = vec![Sort {
+            field: crate::query::QueryField::Name,
+            descending: false,
+        }];
+        assert!(Page::offset(10, 20)
+            .unwrap()
+            .ordered_by(order.clone())
+            .is_ok());
+        let cursor = Cursor {
+            created_at: Utc::now(),
+            id: "item".to_string(),
+        };
+        assert_eq!(
+            Page::after(10, cursor).unwrap().ordered_by(order),
+            Err(PageError::CursorWithOrder)
+        );
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = Cursor {
+            created_at: Utc::now(),
+            id: "550e8400-e29b-41d4-a716-446655440000".to_string(),
+        };
+        assert_eq!(Cursor::decode(&cursor.encode()), Ok(cursor.clone()));
+
+        let page = Page::from_query(10, 0, Some(&cursor.encode())).unwrap();
+        assert_eq!(page.start(), &PageStart::After(cursor));
+        assert_eq!(page.skipped(), 0);
+    }
+}
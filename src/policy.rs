@@ -0,0 +1,391 @@
+use crate::{
+    collections::ItemCollection,
+    db::{AccessRepository, CollectionRepository, DatabaseError},
+    error::{AppError, AppResult},
+    middleware::auth::Claims,
+    models::{Grantee, Item, Permission},
+};
+
+/// Operation a caller wants to perform on an item
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Read,
+    Write,
+    /// Manage who else has access (owner and admins only)
+    Share,
+}
+
+/// Decide whether a caller may perform `action` on `item`
+///
+/// Unowned items and administrators are unrestricted, owners can do anything
+/// with their items, and everyone else needs a matching access grant, on the
+/// item or on a collection it is filed in or below. Reading a draft takes a
+/// grant to write it.
+pub async fn authorize(
+    access: &dyn AccessRepository,
+    collections: &dyn CollectionRepository,
+    item: &Item,
+    claims: Option<&Claims>,
+    action: Action,
+) -> AppResult<()> {
+    let Some(owner_id) = item.owner_id.as_deref() else {
+        return Ok(());
+    };
+
+    let Some(claims) = claims else {
+        return Err(denied(action));
+    };
+
+    if claims.sub == owner_id || claims.is_admin() {
+        return Ok(());
+    }
+
+    let Some(required) = required(action) else {
+        return Err(denied(action));
+    };
+    let required = if item.is_draft() {
+        Permission::Write
+    } else {
+        required
+    };
+
+    let resources = item_resources(collections, item).await?;
+    if granted(access, &resources, claims, required).await? {
+        Ok(())
+    } else if item.is_draft() && action == Action::Read {
+        Err(AppError::Forbidden(
+            "This item is a draft, visible only to its editors".to_string(),
+        ))
+    } else {
+        Err(denied(action))
+    }
+}
+
+/// Whether the caller owns `item`, is an administrator, or is granted
+/// `permission` on it or a collection it is filed in or below
+///
+/// Unlike [`authorize`], unowned items are not open to everyone: only
+/// administrators and explicit grants count.
+pub async fn holds(
+    access: &dyn AccessRepository,
+    collections: &dyn CollectionRepository,
+    item: &Item,
+    claims: &Claims,
+    permission: Permission,
+) -> AppResult<bool> {
+    if claims.is_admin() || item.owner_id.as_deref() == Some(claims.sub.as_str()) {
+        return Ok(true);
+    }
+    let resources = item_resources(collections, item).await?;
+    granted(access, &resources, claims, permission).await
+}
+
+/// The item and the collections it is filed in or below, whose grants apply
+/// to it
+async fn item_resources(
+    collections: &dyn CollectionRepository,
+    item: &Item,
+) -> AppResult<Vec<String>> {
+    let mut resources = vec![item.id.clone()];
+    if let Some(collection_id) = collections.item_collection(&item.id).await? {
+        match collections.get(&collection_id).await {
+            Ok(collection) => resources.extend(collection.lineage()),
+            Err(DatabaseError::NotFound) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(resources)
+}
+
+/// Decide whether a caller may perform `action` on `collection`
+///
+/// Works like item authorization: grants on a collection or any collection
+/// above it apply.
+pub async fn authorize_collection(
+    access: &dyn AccessRepository,
+    collection: &ItemCollection,
+    claims: Option<&Claims>,
+    action: Action,
+) -> AppResult<()> {
+    let Some(owner) = collection.owner.as_deref() else {
+        return Ok(());
+    };
+
+    let Some(claims) = claims else {
+        return Err(denied_collection(action));
+    };
+
+    if claims.sub == owner || claims.is_admin() {
+        return Ok(());
+    }
+
+    let Some(required) = required(action) else {
+        return Err(denied_collection(action));
+    };
+
+    if granted(access, &collection.lineage(), claims, required).await? {
+        Ok(())
+    } else {
+        Err(denied_collection(action))
+    }
+}
+
+/// Permission a grant needs for `action`; none can for sharing
+fn required(action: Action) -> Option<Permission> {
+    match action {
+        Action::Read => Some(Permission::Read),
+        Action::Write => Some(Permission::Write),
+        Action::Share => None,
+    }
+}
+
+/// Whether a grant on any of `resources` gives the caller `required`
+async fn granted(
+    access: &dyn AccessRepository,
+    resources: &[String],
+    claims: &Claims,
+    required: Permission,
+) -> AppResult<bool> {
+    for resource in resources {
+        let granted = access
+            .list_grants(resource)
+            .await?
+            .into_iter()
+            .filter(|grant| applies_to(&grant.grantee, claims))
+            .any(|grant| grant.permission.allows(required));
+        if granted {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn applies_to(grantee: &Grantee, claims: &Claims) -> bool {
+    match grantee {
+        Grantee::Principal(sub) => *sub == claims.sub,
+        Grantee::Role(role) => claims.has_role(role),
+    }
+}
+
+fn denied(action: Action) -> AppError {
+    let message = match action {
+        Action::Read => "You do not have read access to this item",
+        Action::Write => "You do not have write access to this item",
+        Action::Share => "Only the owner can manage access to this item",
+    };
+    AppError::Forbidden(message.to_string())
+}
+
+fn denied_collection(action: Action) -> AppError {
+    let message = match action {
+        Action::Read => "You do not have read access to this collection",
+        Action::Write => "You do not have write access to this collection",
+        Action::Share => "Only the owner can manage this collection",
+    };
+    AppError::Forbidden(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{InMemoryAccessRepository, InMemoryCollectionRepository},
+        models::ItemStatus,
+    };
+    use chrono::Utc;
+
+    fn item_owned_by(owner: Option<&str>) -> Item {
+        Item {
+            id: "item-1".to_string(),
+            name: "Item".to_string(),
+            slug: "item".to_string(),
+            external_id: None,
+            source: None,
+            description: None,
+            metadata: None,
+            custom_fields: Default::default(),
+            owner_id: owner.map(str::to_string),
+            tenant_id: None,
+            status: ItemStatus::Published,
+            publish_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            version: 1,
+        }
+    }
+
+    fn claims(sub: &str, roles: &[&str]) -> Claims {
+        Claims {
+            sub: sub.to_string(),
+            exp: usize::MAX,
+            roles: roles.iter().map(|role| (*role).to_string()).collect(),
+            ..Claims::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_owner_and_admin_are_unrestricted() {
+        let access = InMemoryAccessRepository::new();
+        let collections = InMemoryCollectionRepository::new();
+        let item = item_owned_by(Some("alice"));
+
+        for action in [Action::Read, Action::Write, Action::Share] {
+            assert!(authorize(&access, &collections, &item, Some(&claims("alice", &[])), action)
+                .await
+                .is_ok());
+            assert!(authorize(
+                &access,
+                &collections,
+                &item,
+                Some(&claims("root", &["admin"])),
+                action
+            )
+            .await
+            .is_ok());
+            assert!(authorize(&access, &collections, &item, None, action)
+                .await
+                .is_err());
+        }
+
+        let unowned = item_owned_by(None);
+        assert!(authorize(&access, &collections, &unowned, None, Action::Write)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_holding_unowned_items_takes_a_grant() {
+        let access = InMemoryAccessRepository::new();
+        let collections = InMemoryCollectionRepository::new();
+        let item = item_owned_by(None);
+        let bob = claims("bob", &[]);
+
+        assert!(!holds(&access, &collections, &item, &bob, Permission::Write)
+            .await
+            .unwrap());
+        assert!(holds(
+            &access,
+            &collections,
+            &item,
+            &claims("root", &["admin"]),
+            Permission::Write
+        )
+        .await
+        .unwrap());
+        access
+            .grant(&item.id, Grantee::Principal("bob".to_string()), Permission::Write, None)
+            .await
+            .unwrap();
+        assert!(holds(&access, &collections, &item, &bob, Permission::Write)
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_grants_are_enforced() {
+        let access = InMemoryAccessRepository::new();
+        let collections = InMemoryCollectionRepository::new();
+        let item = item_owned_by(Some("alice"));
+        let bob = claims("bob", &[]);
+        let editor = claims("carol", &["editors"]);
+
+        assert!(authorize(&access, &collections, &item, Some(&bob), Action::Read)
+            .await
+            .is_err());
+
+        access
+            .grant(&item.id, Grantee::Principal("bob".to_string()), Permission::Read, None)
+            .await
+            .unwrap();
+        access
+            .grant(&item.id, Grantee::Role("editors".to_string()), Permission::Write, None)
+            .await
+            .unwrap();
+
+        assert!(authorize(&access, &collections, &item, Some(&bob), Action::Read)
+            .await
+            .is_ok());
+        assert!(authorize(&access, &collections, &item, Some(&bob), Action::Write)
+            .await
+            .is_err());
+        assert!(authorize(&access, &collections, &item, Some(&editor), Action::Write)
+            .await
+            .is_ok());
+        assert!(authorize(&access, &collections, &item, Some(&editor), Action::Share)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_drafts_are_read_only_by_editors() {
+        let access = InMemoryAccessRepository::new();
+        let collections = InMemoryCollectionRepository::new();
+        let mut item = item_owned_by(Some("alice"));
+        item.status = ItemStatus::Draft;
+        let reader = claims("bob", &[]);
+        let editor = claims("carol", &[]);
+        access
+            .grant(&item.id, Grantee::Principal("bob".to_string()), Permission::Read, None)
+            .await
+            .unwrap();
+        access
+            .grant(&item.id, Grantee::Principal("carol".to_string()), Permission::Write, None)
+            .await
+            .unwrap();
+
+        assert!(authorize(&access, &collections, &item, Some(&reader), Action::Read)
+            .await
+            .is_err());
+        assert!(authorize(&access, &collections, &item, Some(&editor), Action::Read)
+            .await
+            .is_ok());
+
+        item.status = ItemStatus::Published;
+        assert!(authorize(&access, &collections, &item, Some(&reader), Action::Read)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_collection_grants_apply_below_them() {
+        let access = InMemoryAccessRepository::new();
+        let collections = InMemoryCollectionRepository::new();
+        let item = item_owned_by(Some("alice"));
+        let bob = claims("bob", &[]);
+
+        let root =
+            ItemCollection::new("Shared".to_string(), None, Some("alice".to_string()), Utc::now());
+        let nested = ItemCollection::new(
+            "Nested".to_string(),
+            Some(&root),
+            Some("alice".to_string()),
+            Utc::now(),
+        );
+        collections.create(root.clone()).await.unwrap();
+        collections.create(nested.clone()).await.unwrap();
+        collections
+            .file_item(&item.id, Some(&nested.id))
+            .await
+            .unwrap();
+        access
+            .grant(&root.id, Grantee::Principal("bob".to_string()), Permission::Read, None)
+            .await
+            .unwrap();
+
+        assert!(authorize_collection(&access, &nested, Some(&bob), Action::Read)
+            .await
+            .is_ok());
+        assert!(authorize(&access, &collections, &item, Some(&bob), Action::Read)
+            .await
+            .is_ok());
+        assert!(authorize(&access, &collections, &item, Some(&bob), Action::Write)
+            .await
+            .is_err());
+
+        // Taken out of the collection, the item is no longer shared
+        collections.file_item(&item.id, None).await.unwrap();
+        assert!(authorize(&access, &collections, &item, Some(&bob), Action::Read)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,452 @@
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use utoipa::ToSchema;
+
+use crate::{
+    db::{DatabaseError, ItemFilter},
+    error::AppResult,
+    models::{ErasureMode, ErasureRequest},
+    pagination::Page,
+    state::AppState,
+};
+
+/// Prefix of the pseudonymous owner assigned to anonymized items
+pub const ANONYMIZED_OWNER_PREFIX: &str = "anonymized:";
+
+/// Items deleted per batch in erase mode
+const ERASE_BATCH_SIZE: usize = 100;
+
+/// Record of what an erasure removed, for the data subject and auditors
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ErasureReport {
+    pub erasure_id: String,
+    /// Subject whose data was removed
+    #[schema(example = "user-123")]
+    pub principal: String,
+    pub mode: ErasureMode,
+    /// Items reassigned to a pseudonym (anonymize) or deleted (erase)
+    pub items: usize,
+    /// Outbox event snapshots rewritten to drop the principal
+    pub events: usize,
+    /// Grants to the principal that were removed
+    pub grants_removed: usize,
+    /// Grants whose `granted_by` referenced the principal
+    pub grants_scrubbed: usize,
+    /// Inbox notifications to the principal that were removed
+    #[serde(default)]
+    pub notifications_removed: usize,
+    /// Inbox notifications whose `actor` was the principal
+    #[serde(default)]
+    pub notifications_scrubbed: usize,
+    /// Saved searches the principal owned, which were deleted
+    #[serde(default)]
+    pub saved_searches: usize,
+    /// Comments whose `author` was the principal; the comments are kept
+    #[serde(default)]
+    pub comments_scrubbed: usize,
+    /// Collections the principal owned, reassigned to the pseudonym
+    #[serde(default)]
+    pub collections: usize,
+    /// Change requests the principal made, approved or resolved, which now
+    /// name the pseudonym instead
+    #[serde(default)]
+    pub change_requests: usize,
+    /// Check-out locks the principal held, which were released
+    #[serde(default)]
+    pub locks_released: usize,
+    /// Share links whose `created_by` was the principal; the links keep working
+    #[serde(default)]
+    pub shares_scrubbed: usize,
+    /// Attachments whose `created_by` was the principal; the files are kept
+    #[serde(default)]
+    pub attachments_scrubbed: usize,
+    /// Metadata schema versions whose `created_by` was the principal
+    #[serde(default)]
+    pub schemas_scrubbed: usize,
+    /// Administrator who requested the erasure
+    pub performed_by: String,
+    pub completed_at: DateTime<Utc>,
+}
+
+/// Erasure report together with its detached signature
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SignedErasureReport {
+    pub report: ErasureReport,
+    /// HS256 JWS whose payload is `report`
+    pub signature: String,
+}
+
+/// Signs erasure reports so they can later be shown to be authentic
+#[derive(Clone)]
+pub struct ErasureSigner {
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+}
+
+impl ErasureSigner {
+    pub fn new(secret: &str) -> Self {
+        Self {
+            encoding_key: EncodingKey::from_secret(secret.as_bytes()),
+            decoding_key: DecodingKey::from_secret(secret.as_bytes()),
+        }
+    }
+
+    pub fn sign(&self, report: ErasureReport) -> Result<SignedErasureReport, String> {
+        let signature = encode(&Header::new(Algorithm::HS256), &report, &self.encoding_key)
+            .map_err(|e| e.to_string())?;
+        Ok(SignedErasureReport { report, signature })
+    }
+
+    /// Check a signature and return the report it covers
+    pub fn verify(&self, signature: &str) -> Result<ErasureReport, String> {
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.required_spec_claims.clear();
+        validation.validate_exp = false;
+
+        decode::<ErasureReport>(signature, &self.decoding_key, &validation)
+            .map(|data| data.claims)
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Remove every reference to a principal from the repositories
+///
+/// Ownership is reassigned to a random pseudonym first, so that in erase mode
+/// the deletion events published for the principal's items no longer carry
+/// their identity. Collections stay with the pseudonym in both modes, as
+/// they may hold other principals' items, and so do change requests, whose
+/// history other principals rely on.
+pub async fn erase_principal(
+    state: &AppState,
+    request: &ErasureRequest,
+    performed_by: &str,
+) -> AppResult<ErasureReport> {
+    let erasure_id = uuid::Uuid::new_v4().to_string();
+    let pseudonym = format!("{ANONYMIZED_OWNER_PREFIX}{}", uuid::Uuid::new_v4());
+    let (repo, access) = (state.repo.as_ref(), state.access.as_ref());
+
+    let (mut items, events) = repo.reassign_owner(&request.principal, &pseudonym).await?;
+
+    if request.mode == ErasureMode::Erase {
+        items = 0;
+        let filter = ItemFilter::owned_by(pseudonym.as_str());
+        loop {
+            let batch = repo.list(&filter, &Page::first(ERASE_BATCH_SIZE)?).await?;
+            if batch.is_empty() {
+                break;
+            }
+            for item in batch {
+                match repo.delete(&item.id).await {
+                    Ok(()) | Err(DatabaseError::NotFound) => {}
+                    Err(e) => return Err(e.into()),
+                }
+                access.revoke_all(&item.id).await?;
+                state.collections.forget_item(&item.id).await?;
+                if let Some(attachments) = &state.attachments {
+                    attachments.forget_item(&item.id)?;
+                }
+                items += 1;
+            }
+        }
+    }
+
+    let (grants_removed, grants_scrubbed) = access.forget_principal(&request.principal).await?;
+    let (notifications_removed, notifications_scrubbed) = match &state.inbox {
+        Some(inbox) => inbox.forget_principal(&request.principal).await?,
+        None => (0, 0),
+    };
+    let saved_searches = state
+        .saved_searches
+        .forget_principal(&request.principal)
+        .await?;
+    let comments_scrubbed = state.comments.forget_principal(&request.principal).await?;
+    let collections = state
+        .collections
+        .reassign_owner(&request.principal, &pseudonym)
+        .await?;
+    let change_requests = state
+        .change_requests
+        .reassign_principal(&request.principal, &pseudonym)
+        .await?;
+    let locks_released = state
+        .item_locks
+        .release_owned_by(&request.principal)
+        .await?;
+    let shares_scrubbed = match &state.shares {
+        Some(shares) => shares.forget_principal(&request.principal)?,
+        None => 0,
+    };
+    let attachments_scrubbed = match &state.attachments {
+        Some(attachments) => attachments.forget_principal(&request.principal)?,
+        None => 0,
+    };
+    let schemas_scrubbed = state.schemas.forget_principal(&request.principal)?;
+
+    info!(
+        %erasure_id,
+        mode = ?request.mode,
+        items,
+        events,
+        grants_removed,
+        grants_scrubbed,
+        notifications_removed,
+        notifications_scrubbed,
+        saved_searches,
+        comments_scrubbed,
+        collections,
+        change_requests,
+        locks_released,
+        shares_scrubbed,
+        attachments_scrubbed,
+        schemas_scrubbed,
+        %performed_by,
+        "Principal data erased"
+    );
+
+    Ok(ErasureReport {
+        erasure_id,
+        principal: request.principal.clone(),
+        mode: request.mode,
+        items,
+        events,
+        grants_removed,
+        grants_scrubbed,
+        notifications_removed,
+        notifications_scrubbed,
+        saved_searches,
+        comments_scrubbed,
+        collections,
+        change_requests,
+        locks_released,
+        shares_scrubbed,
+        attachments_scrubbed,
+        schemas_scrubbed,
+        performed_by: performed_by.to_string(),
+        completed_at: Utc::now(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        approvals::{Approval, ChangeAction, ChangeRequest},
+        attachments::{Attachments, PresignUploadRequest},
+        checkout::ItemLock,
+        collections::ItemCollection,
+        config::{AttachmentsConfig, SharingConfig},
+        db::InMemoryRepository,
+        models::{CreateItemRequest, Grantee, Permission},
+        schemas::RegisterSchemaRequest,
+        sharing::{CreateShareRequest, ShareLinks},
+    };
+    use std::sync::Arc;
+
+    /// Attachments in a bucket holding an 11 byte object under every key
+    async fn attachments() -> Attachments {
+        let bucket =
+            axum::Router::new().route("/{*key}", axum::routing::get(|| async { "hello world" }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, bucket).await.unwrap() });
+        let config = AttachmentsConfig {
+            bucket: Some("attachments".to_string()),
+            endpoint: Some(endpoint),
+            access_key_id: Some("key".to_string()),
+            secret_access_key: Some("secret".to_string()),
+            ..AttachmentsConfig::default()
+        };
+        Attachments::from_config(&config, Default::default())
+            .unwrap()
+            .unwrap()
+    }
+
+    /// Attach a file to `item_id` as uploaded by `created_by`
+    async fn attach(state: &AppState, item_id: &str, created_by: &str) {
+        let attachments = state.attachments.as_ref().unwrap();
+        let request = PresignUploadRequest {
+            file_name: "notes.txt".to_string(),
+            content_type: None,
+            size: 11,
+        };
+        let upload = attachments
+            .presign(item_id, request, Some(created_by.to_string()))
+            .unwrap();
+        attachments
+            .confirm(item_id, &upload.upload_id)
+            .await
+            .unwrap();
+    }
+
+    async fn seed() -> (AppState, String) {
+        let sharing = SharingConfig {
+            signing_key: Some("secret".to_string()),
+            ..SharingConfig::default()
+        };
+        let state = AppState::new(Arc::new(InMemoryRepository::new()))
+            .with_share_links(ShareLinks::from_config(&sharing))
+            .with_attachments(Some(attachments().await));
+        let (repo, access) = (state.repo.as_ref(), state.access.as_ref());
+        let mut shared_id = String::new();
+
+        for owner in ["alice", "alice", "bob"] {
+            let request = CreateItemRequest {
+                name: format!("{owner}'s item"),
+                description: None,
+                metadata: None,
+                custom_fields: Default::default(),
+                status: Default::default(),
+                publish_at: None,
+                external_id: None,
+                source: None,
+            };
+            let item = repo.create(request, Some(owner.to_string())).await.unwrap();
+            if owner == "bob" {
+                shared_id = item.id;
+            }
+        }
+
+        // Bob shared an item with Alice
+        access
+            .grant(
+                &shared_id,
+                Grantee::Principal("alice".to_string()),
+                Permission::Read,
+                Some("bob".to_string()),
+            )
+            .await
+            .unwrap();
+
+        (state, shared_id)
+    }
+
+    fn request(mode: ErasureMode) -> ErasureRequest {
+        ErasureRequest {
+            principal: "alice".to_string(),
+            mode,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_anonymize_keeps_items_without_owner_identity() {
+        let (state, shared_id) = seed().await;
+        let folder =
+            ItemCollection::new("Notes".to_string(), None, Some("alice".to_string()), Utc::now());
+        state.collections.create(folder.clone()).await.unwrap();
+        let shared = state.repo.get(&shared_id).await.unwrap();
+        let change =
+            ChangeRequest::new(&shared, ChangeAction::Delete, None, "alice", 1, Utc::now());
+        state.change_requests.create(change.clone()).await.unwrap();
+        let approval = Approval {
+            principal: "bob".to_string(),
+            approved_at: Utc::now(),
+        };
+        state
+            .change_requests
+            .approve(&change.id, approval)
+            .await
+            .unwrap();
+        let lock = ItemLock {
+            item_id: shared_id.clone(),
+            owner: "alice".to_string(),
+            acquired_at: Utc::now(),
+            expires_at: Utc::now() + chrono::Duration::hours(1),
+        };
+        state.item_locks.acquire(lock).await.unwrap();
+        let shares = state.shares.as_ref().unwrap();
+        let share = shares
+            .create(&shared_id, CreateShareRequest::default(), Some("alice".to_string()))
+            .unwrap()
+            .share;
+        attach(&state, &shared_id, "alice").await;
+        let schema = RegisterSchemaRequest {
+            schema: serde_json::json!({ "type": "object" }),
+            global: true,
+        };
+        state
+            .schemas
+            .register("notes", schema, Some("alice".to_string()))
+            .unwrap();
+
+        let report = erase_principal(&state, &request(ErasureMode::Anonymize), "root")
+            .await
+            .unwrap();
+
+        assert_eq!(report.items, 2);
+        assert_eq!(report.events, 2);
+        assert_eq!(report.grants_removed, 1);
+        assert_eq!(report.collections, 1);
+        assert_eq!(report.change_requests, 1);
+        let change = state.change_requests.get(&change.id).await.unwrap();
+        assert!(change.requested_by.starts_with(ANONYMIZED_OWNER_PREFIX));
+        assert_eq!(change.approvals[0].principal, "bob");
+        assert_eq!(report.locks_released, 1);
+        assert!(state.item_locks.get(&shared_id).await.unwrap().is_none());
+        assert_eq!(report.shares_scrubbed, 1);
+        let shared = shares.list(&shared_id).unwrap();
+        assert_eq!(shared[0].id, share.id);
+        assert_eq!(shared[0].created_by, None);
+        assert_eq!(report.attachments_scrubbed, 1);
+        let attached = state
+            .attachments
+            .as_ref()
+            .unwrap()
+            .list(&shared_id)
+            .unwrap();
+        assert_eq!(attached[0].created_by, None);
+        assert_eq!(report.schemas_scrubbed, 1);
+        let versions = state.schemas.versions("notes", true).unwrap().unwrap();
+        assert_eq!(versions[0].created_by, None);
+        let folder = state.collections.get(&folder.id).await.unwrap();
+        assert!(folder.owner.unwrap().starts_with(ANONYMIZED_OWNER_PREFIX));
+        let (repo, access) = (state.repo.as_ref(), state.access.as_ref());
+        let filter = ItemFilter::owned_by("alice");
+        assert_eq!(repo.count(&filter).await.unwrap(), 0);
+        assert_eq!(repo.count(&ItemFilter::default()).await.unwrap(), 3);
+        assert!(access.list_grants(&shared_id).await.unwrap().is_empty());
+
+        let pending = repo.pending_events(10).await.unwrap();
+        assert!(pending
+            .iter()
+            .all(|event| event.item.owner_id.as_deref() != Some("alice")));
+    }
+
+    #[tokio::test]
+    async fn test_erase_deletes_items() {
+        let (state, _) = seed().await;
+        let owned = state
+            .repo
+            .list(&ItemFilter::owned_by("alice"), &Page::first(10).unwrap())
+            .await
+            .unwrap();
+        attach(&state, &owned[0].id, "alice").await;
+
+        let report = erase_principal(&state, &request(ErasureMode::Erase), "root")
+            .await
+            .unwrap();
+
+        assert_eq!(report.items, 2);
+        assert_eq!(state.repo.count(&ItemFilter::default()).await.unwrap(), 1);
+        let attachments = state.attachments.as_ref().unwrap();
+        assert!(attachments.list(&owned[0].id).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_signed_report_verifies() {
+        let (state, _) = seed().await;
+        let report = erase_principal(&state, &request(ErasureMode::Anonymize), "root")
+            .await
+            .unwrap();
+
+        let signer = ErasureSigner::new("secret");
+        let signed = signer.sign(report).unwrap();
+        let verified = signer.verify(&signed.signature).unwrap();
+        assert_eq!(verified.erasure_id, signed.report.erasure_id);
+
+        assert!(ErasureSigner::new("other")
+            .verify(&signed.signature)
+            .is_err());
+    }
+}
@@ -0,0 +1,240 @@
+use serde::Serialize;
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::Duration,
+};
+use utoipa::ToSchema;
+
+static COUNTING: AtomicBool = AtomicBool::new(false);
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static DEALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+static FREED_BYTES: AtomicU64 = AtomicU64::new(0);
+static PEAK_LIVE_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// System allocator that counts allocations for the heap profile
+///
+/// The server binary installs it as the global allocator. Nothing is counted
+/// until [`enable_heap_counting`] is called, so while profiling is disabled
+/// each allocation only pays for one relaxed load.
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            counted_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            counted_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        counted_free(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = System.realloc(ptr, layout, new_size);
+        if !new.is_null() {
+            counted_free(layout.size());
+            counted_alloc(new_size);
+        }
+        new
+    }
+}
+
+fn counted_alloc(size: usize) {
+    if COUNTING.load(Ordering::Relaxed) {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        let allocated = ALLOCATED_BYTES.fetch_add(size as u64, Ordering::Relaxed) + size as u64;
+        let live = allocated.saturating_sub(FREED_BYTES.load(Ordering::Relaxed));
+        PEAK_LIVE_BYTES.fetch_max(live, Ordering::Relaxed);
+    }
+}
+
+fn counted_free(size: usize) {
+    if COUNTING.load(Ordering::Relaxed) {
+        DEALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        FREED_BYTES.fetch_add(size as u64, Ordering::Relaxed);
+    }
+}
+
+/// Start counting allocations made through [`CountingAllocator`]
+pub fn enable_heap_counting() {
+    COUNTING.store(true, Ordering::Relaxed);
+}
+
+/// Heap allocations counted since counting was enabled
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct HeapProfile {
+    /// Whether allocations are being counted; every count is zero otherwise
+    pub counting: bool,
+    pub allocations: u64,
+    pub deallocations: u64,
+    pub allocated_bytes: u64,
+    pub freed_bytes: u64,
+    /// Bytes allocated and not yet freed
+    pub live_bytes: u64,
+    pub peak_live_bytes: u64,
+}
+
+impl HeapProfile {
+    pub fn current() -> Self {
+        let allocated_bytes = ALLOCATED_BYTES.load(Ordering::Relaxed);
+        let freed_bytes = FREED_BYTES.load(Ordering::Relaxed);
+        Self {
+            counting: COUNTING.load(Ordering::Relaxed),
+            allocations: ALLOCATIONS.load(Ordering::Relaxed),
+            deallocations: DEALLOCATIONS.load(Ordering::Relaxed),
+            allocated_bytes,
+            freed_bytes,
+            // Memory allocated before counting started may be freed after,
+            // so frees can outnumber allocations
+            live_bytes: allocated_bytes.saturating_sub(freed_bytes),
+            peak_live_bytes: PEAK_LIVE_BYTES.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// CPU time used by threads sharing a name
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ThreadCpu {
+    /// Thread name, such as `tokio-runtime-w` (truncated by the kernel)
+    pub name: String,
+    pub threads: usize,
+    pub cpu_seconds: f64,
+    /// Share of the process's CPU time over the profile
+    pub share: f64,
+}
+
+/// CPU time used by the process over a window, by thread name
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CpuProfile {
+    pub seconds: f64,
+    pub cpu_seconds: f64,
+    /// Average number of cores kept busy
+    pub cores: f64,
+    /// Busiest first
+    pub threads: Vec<ThreadCpu>,
+}
+
+/// Measure the CPU time each thread uses over `duration`
+///
+/// Reads per-thread scheduler statistics from `/proc`, so it only works on
+/// Linux. Threads that exit during the window are not counted.
+pub async fn cpu_profile(duration: Duration) -> io::Result<CpuProfile> {
+    let start = read_thread_times().await?;
+    tokio::time::sleep(duration).await;
+    let end = read_thread_times().await?;
+
+    let mut by_name: BTreeMap<String, (usize, Duration)> = BTreeMap::new();
+    for (tid, (name, time)) in end {
+        let before = start.get(&tid).map_or(Duration::ZERO, |(_, time)| *time);
+        let entry = by_name.entry(name).or_default();
+        entry.0 += 1;
+        entry.1 += time.saturating_sub(before);
+    }
+
+    let cpu_seconds: f64 = by_name.values().map(|(_, time)| time.as_secs_f64()).sum();
+    let mut threads: Vec<ThreadCpu> = by_name
+        .into_iter()
+        .map(|(name, (threads, time))| ThreadCpu {
+            name,
+            threads,
+            cpu_seconds: time.as_secs_f64(),
+            share: if cpu_seconds > 0.0 {
+                time.as_secs_f64() / cpu_seconds
+            } else {
+                0.0
+            },
+        })
+        .collect();
+    threads.sort_by(|a, b| b.cpu_seconds.total_cmp(&a.cpu_seconds));
+
+    Ok(CpuProfile {
+        seconds: duration.as_secs_f64(),
+        cpu_seconds,
+        cores: cpu_seconds / duration.as_secs_f64().max(f64::EPSILON),
+        threads,
+    })
+}
+
+/// Name and CPU time of each thread in the process, by thread ID
+async fn read_thread_times() -> io::Result<HashMap<u64, (String, Duration)>> {
+    // Reading /proc may block briefly
+    tokio::task::spawn_blocking(|| {
+        let mut times = HashMap::new();
+        for entry in fs::read_dir("/proc/self/task")? {
+            let path = entry?.path();
+            let Some(tid) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.parse().ok())
+            else {
+                continue;
+            };
+            // A thread may exit between listing and reading
+            let (Ok(name), Ok(schedstat)) = (
+                fs::read_to_string(path.join("comm")),
+                fs::read_to_string(path.join("schedstat")),
+            ) else {
+                continue;
+            };
+            // The first field is time spent on the CPU, in nanoseconds
+            let Some(nanos) = schedstat
+                .split_whitespace()
+                .next()
+                .and_then(|nanos| nanos.parse().ok())
+            else {
+                continue;
+            };
+            times.insert(tid, (name.trim().to_string(), Duration::from_nanos(nanos)));
+        }
+        Ok(times)
+    })
+    .await
+    .map_err(io::Error::other)?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_cpu_profile_attributes_busy_threads() {
+        let busy = std::thread::Builder::new()
+            .name("profile-busy".to_string())
+            .spawn(|| {
+                let start = std::time::Instant::now();
+                let mut n = 0u64;
+                while start.elapsed() < Duration::from_millis(300) {
+                    n = n.wrapping_add(1);
+                }
+                n
+            })
+            .unwrap();
+
+        let profile = cpu_profile(Duration::from_millis(200)).await.unwrap();
+        busy.join().unwrap();
+
+        let thread = profile
+            .threads
+            .iter()
+            .find(|thread| thread.name == "profile-busy")
+            .unwrap();
+        assert!(thread.cpu_seconds > 0.05, "{profile:?}");
+        assert!(profile.cpu_seconds >= thread.cpu_seconds);
+    }
+}
@@ -0,0 +1,315 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::{
+    approvals::Approvals,
+    clock::{system_clock, SharedClock},
+    db::{CollectionRepository, DatabaseError, DatabaseResult, ItemFilter, ItemRepository},
+    leadership::Leadership,
+    metrics::track_item_published,
+    models::{ItemStatus, UpdateItemRequest},
+    pagination::Page,
+    query::{Comparison, Condition, FieldValue, QueryField},
+    tenancy::{with_optional_tenant, TenantDirectory},
+};
+
+/// Most due drafts published per tenant and page
+const PUBLISH_BATCH: usize = 100;
+
+/// Request to publish a draft, now or at a later time
+#[derive(Debug, Default, Serialize, Deserialize, Validate, ToSchema)]
+#[schema(example = json!({ "publish_at": "2024-01-02T09:00:00Z" }))]
+pub struct PublishItemRequest {
+    /// Publish at this time instead of now; a time already past publishes now
+    pub publish_at: Option<DateTime<Utc>>,
+}
+
+/// The update that publishes an item at `publish_at`, or now when that is
+/// absent or not after `now`
+pub fn publication(publish_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> UpdateItemRequest {
+    match publish_at.filter(|at| *at > now) {
+        Some(at) => UpdateItemRequest {
+            publish_at: Some(Some(at)),
+            ..UpdateItemRequest::default()
+        },
+        None => UpdateItemRequest {
+            status: Some(ItemStatus::Published),
+            publish_at: Some(None),
+            ..UpdateItemRequest::default()
+        },
+    }
+}
+
+/// Drafts whose `publish_at` is at or before `now`
+fn due(now: DateTime<Utc>) -> ItemFilter {
+    ItemFilter::default().with_conditions(vec![
+        Condition {
+            field: QueryField::Status.into(),
+            comparison: Comparison::Eq,
+            value: FieldValue::Text(ItemStatus::Draft.as_str().to_string()),
+        },
+        Condition {
+            field: QueryField::PublishAt.into(),
+            comparison: Comparison::Le,
+            value: FieldValue::Time(now),
+        },
+    ])
+}
+
+/// Periodically publishes drafts whose scheduled time has come
+///
+/// Drafts are published outside any tenant and in each provisioned tenant;
+/// drafts of tenants that were never provisioned are not seen. Drafts whose
+/// changes need approval are never published on schedule: their schedule is
+/// cleared, and they are published through a change request instead.
+pub struct PublishJob {
+    repo: Arc<dyn ItemRepository>,
+    tenants: Arc<TenantDirectory>,
+    interval: std::time::Duration,
+    clock: SharedClock,
+    leadership: Leadership,
+    approvals: Option<(Arc<Approvals>, Arc<dyn CollectionRepository>)>,
+}
+
+impl PublishJob {
+    pub fn new(
+        repo: Arc<dyn ItemRepository>,
+        tenants: Arc<TenantDirectory>,
+        interval: std::time::Duration,
+    ) -> Self {
+        Self {
+            repo,
+            tenants,
+            interval,
+            clock: system_clock(),
+            leadership: Leadership::always(),
+            approvals: None,
+        }
+    }
+
+    /// Leave drafts whose changes need approval, as filed in `collections`,
+    /// unpublished
+    #[must_use]
+    pub fn with_approvals(
+        mut self,
+        approvals: Arc<Approvals>,
+        collections: Arc<dyn CollectionRepository>,
+    ) -> Self {
+        self.approvals = Some((approvals, collections));
+        self
+    }
+
+    /// Decide what is due by `clock` instead of the system clock
+    #[must_use]
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Publish only while this replica leads
+    #[must_use]
+    pub fn with_leadership(mut self, leadership: Leadership) -> Self {
+        self.leadership = leadership;
+        self
+    }
+
+    /// Run the job until the task is aborted
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.interval);
+            loop {
+                interval.tick().await;
+                if !self.leadership.is_leader() {
+                    continue;
+                }
+                match self.run_once().await {
+                    Ok(0) => {}
+                    Ok(published) => info!("Published {} scheduled drafts", published),
+                    Err(e) => warn!("Publishing scheduled drafts failed: {}", e),
+                }
+            }
+        })
+    }
+
+    /// Publish every draft that is due, returning how many were published
+    pub async fn run_once(&self) -> DatabaseResult<usize> {
+        let mut tenants = vec![None];
+        tenants.extend(
+            self.tenants
+                .repository()
+                .list()
+                .await?
+                .into_iter()
+                .map(|tenant| Some(tenant.id)),
+        );
+
+        let mut published = 0;
+        for tenant in tenants {
+            published += with_optional_tenant(tenant, self.publish_due()).await?;
+        }
+        Ok(published)
+    }
+
+    async fn publish_due(&self) -> DatabaseResult<usize> {
+        let now = self.clock.now();
+        let filter = due(now);
+        let page =
+            Page::first(PUBLISH_BATCH).map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        let mut published = 0;
+        loop {
+            let drafts = self.repo.list(&filter, &page).await?;
+            let mut skipped = 0;
+            for draft in &drafts {
+                let approval = self.needs_approval(&draft.id).await?;
+                let update = if approval {
+                    UpdateItemRequest {
+                        publish_at: Some(None),
+                        ..UpdateItemRequest::default()
+                    }
+                } else {
+                    publication(None, now)
+                };
+                // An edit since the draft was listed wins; it is due again next run
+                match self
+                    .repo
+                    .update(&draft.id, update, Some(draft.version))
+                    .await
+                {
+                    Ok(_) if approval => {
+                        warn!(item = %draft.id, "Unscheduled a draft that needs approval to be published");
+                    }
+                    Ok(_) => {
+                        track_item_published("schedule");
+                        published += 1;
+                    }
+                    Err(DatabaseError::VersionMismatch { .. } | DatabaseError::NotFound) => {
+                        skipped += 1;
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            if drafts.len() < PUBLISH_BATCH || skipped > 0 {
+                return Ok(published);
+            }
+        }
+    }
+
+    async fn needs_approval(&self, id: &str) -> DatabaseResult<bool> {
+        match &self.approvals {
+            Some((approvals, collections)) => {
+                approvals.requires_approval(collections.as_ref(), id).await
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        clock::{Clock, ManualClock},
+        db::InMemoryRepository,
+        models::CreateItemRequest,
+    };
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_due_drafts_are_published() {
+        let clock = Arc::new(ManualClock::default());
+        let shared: SharedClock = clock.clone();
+        let repo: Arc<dyn ItemRepository> =
+            Arc::new(InMemoryRepository::new().with_clock(shared.clone()));
+        let draft = |name: &str, publish_at| CreateItemRequest {
+            name: name.to_string(),
+            status: ItemStatus::Draft,
+            publish_at,
+            ..CreateItemRequest::default()
+        };
+        let soon = repo
+            .create(draft("Soon", Some(clock.now() + chrono::Duration::seconds(30))), None)
+            .await
+            .unwrap();
+        let unscheduled = repo.create(draft("Whenever", None), None).await.unwrap();
+        let job = PublishJob::new(
+            repo.clone(),
+            Arc::new(TenantDirectory::default()),
+            Duration::from_secs(60),
+        )
+        .with_clock(shared);
+
+        assert_eq!(job.run_once().await.unwrap(), 0);
+        clock.advance(Duration::from_secs(31));
+        assert_eq!(job.run_once().await.unwrap(), 1);
+
+        let published = repo.get(&soon.id).await.unwrap();
+        assert_eq!(published.status, ItemStatus::Published);
+        assert_eq!(published.publish_at, None);
+        assert!(repo.get(&unscheduled.id).await.unwrap().is_draft());
+        assert_eq!(job.run_once().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_drafts_needing_approval_are_unscheduled() {
+        use crate::{
+            collections::ItemCollection, config::ApprovalConfig, db::InMemoryCollectionRepository,
+        };
+
+        let clock = Arc::new(ManualClock::default());
+        let shared: SharedClock = clock.clone();
+        let repo: Arc<dyn ItemRepository> =
+            Arc::new(InMemoryRepository::new().with_clock(shared.clone()));
+        let collections = Arc::new(InMemoryCollectionRepository::new());
+        let contracts = ItemCollection::new("Contracts".to_string(), None, None, clock.now());
+        collections.create(contracts.clone()).await.unwrap();
+        let approvals = Approvals::from_config(&ApprovalConfig {
+            collections: vec![contracts.id.clone()],
+            required_approvals: 1,
+        });
+        let draft = repo
+            .create(
+                CreateItemRequest {
+                    name: "Contract".to_string(),
+                    status: ItemStatus::Draft,
+                    publish_at: Some(clock.now() + chrono::Duration::seconds(30)),
+                    ..CreateItemRequest::default()
+                },
+                None,
+            )
+            .await
+            .unwrap();
+        collections
+            .file_item(&draft.id, Some(&contracts.id))
+            .await
+            .unwrap();
+        let job = PublishJob::new(
+            repo.clone(),
+            Arc::new(TenantDirectory::default()),
+            Duration::from_secs(60),
+        )
+        .with_clock(shared)
+        .with_approvals(Arc::new(approvals), collections);
+
+        clock.advance(Duration::from_secs(31));
+        assert_eq!(job.run_once().await.unwrap(), 0);
+        let unscheduled = repo.get(&draft.id).await.unwrap();
+        assert!(unscheduled.is_draft());
+        assert_eq!(unscheduled.publish_at, None);
+    }
+
+    #[test]
+    fn test_past_times_publish_now() {
+        let now = Utc::now();
+        let later = now + chrono::Duration::hours(1);
+        assert_eq!(publication(Some(later), now).publish_at, Some(Some(later)));
+        assert_eq!(publication(Some(later), now).status, None);
+        let past = publication(Some(now - chrono::Duration::hours(1)), now);
+        assert_eq!(past.status, Some(ItemStatus::Published));
+        assert_eq!(past.publish_at, Some(None));
+    }
+}
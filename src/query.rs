@@ -0,0 +1,347 @@
+use chrono::{DateTime, Utc};
+use std::{cmp::Ordering, fmt, sync::Arc};
+
+use crate::{db::ItemFilter, models::Item, pagination::Page};
+
+/// Item field a list query can filter, sort, or select on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QueryField {
+    Id,
+    Name,
+    Slug,
+    ExternalId,
+    Source,
+    Description,
+    OwnerId,
+    TenantId,
+    Status,
+    PublishAt,
+    CreatedAt,
+    UpdatedAt,
+    Version,
+}
+
+impl QueryField {
+    pub const ALL: [QueryField; 13] = [
+        Self::Id,
+        Self::Name,
+        Self::Slug,
+        Self::ExternalId,
+        Self::Source,
+        Self::Description,
+        Self::OwnerId,
+        Self::TenantId,
+        Self::Status,
+        Self::PublishAt,
+        Self::CreatedAt,
+        Self::UpdatedAt,
+        Self::Version,
+    ];
+
+    /// The field as it is named in item JSON
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Id => "id",
+            Self::Name => "name",
+            Self::Slug => "slug",
+            Self::ExternalId => "external_id",
+            Self::Source => "source",
+            Self::Description => "description",
+            Self::OwnerId => "owner_id",
+            Self::TenantId => "tenant_id",
+            Self::Status => "status",
+            Self::PublishAt => "publish_at",
+            Self::CreatedAt => "created_at",
+            Self::UpdatedAt => "updated_at",
+            Self::Version => "version",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|field| field.name() == name)
+    }
+
+    /// Kind of value the field holds
+    pub fn kind(self) -> ValueKind {
+        match self {
+            Self::PublishAt | Self::CreatedAt | Self::UpdatedAt => ValueKind::Time,
+            Self::Version => ValueKind::Number,
+            _ => ValueKind::Text,
+        }
+    }
+
+    /// Whether the field may be absent
+    pub fn optional(self) -> bool {
+        matches!(
+            self,
+            Self::ExternalId
+                | Self::Source
+                | Self::Description
+                | Self::OwnerId
+                | Self::TenantId
+                | Self::PublishAt
+        )
+    }
+
+    pub fn value(self, item: &Item) -> FieldValue {
+        let text =
+            |value: &Option<String>| value.clone().map_or(FieldValue::Null, FieldValue::Text);
+        match self {
+            Self::Id => FieldValue::Text(item.id.clone()),
+            Self::Name => FieldValue::Text(item.name.clone()),
+            Self::Slug => FieldValue::Text(item.slug.clone()),
+            Self::ExternalId => text(&item.external_id),
+            Self::Source => text(&item.source),
+            Self::Description => text(&item.description),
+            Self::OwnerId => text(&item.owner_id),
+            Self::TenantId => text(&item.tenant_id),
+            Self::Status => FieldValue::Text(item.status.as_str().to_string()),
+            Self::PublishAt => item.publish_at.map_or(FieldValue::Null, FieldValue::Time),
+            Self::CreatedAt => FieldValue::Time(item.created_at),
+            Self::UpdatedAt => FieldValue::Time(item.updated_at),
+            Self::Version => FieldValue::Number(item.version),
+        }
+    }
+}
+
+impl fmt::Display for QueryField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// What a condition tests: an item field or a tenant-defined custom field
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterField {
+    Item(QueryField),
+    /// Named `custom_fields/<name>` in `$filter`
+    Custom(String),
+}
+
+impl FilterField {
+    pub fn value(&self, item: &Item) -> FieldValue {
+        match self {
+            Self::Item(field) => field.value(item),
+            Self::Custom(name) => match item.custom_fields.get(name) {
+                Some(serde_json::Value::String(text)) => FieldValue::Text(text.clone()),
+                Some(serde_json::Value::Number(n)) => {
+                    FieldValue::Decimal(Decimal(n.as_f64().unwrap_or_default()))
+                }
+                Some(serde_json::Value::Bool(flag)) => FieldValue::Bool(*flag),
+                _ => FieldValue::Null,
+            },
+        }
+    }
+}
+
+impl From<QueryField> for FilterField {
+    fn from(field: QueryField) -> Self {
+        Self::Item(field)
+    }
+}
+
+impl fmt::Display for FilterField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Item(field) => field.fmt(f),
+            Self::Custom(name) => write!(f, "custom_fields/{name}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    Text,
+    Time,
+    Number,
+}
+
+/// Value of a field; absent fields are `Null`, which sorts first
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FieldValue {
+    Null,
+    Text(String),
+    Time(DateTime<Utc>),
+    Number(u64),
+    /// Custom field booleans
+    Bool(bool),
+    /// Custom field numbers
+    Decimal(Decimal),
+}
+
+/// A number that may have a fraction, totally ordered so values can be sorted
+#[derive(Debug, Clone, Copy)]
+pub struct Decimal(pub f64);
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Decimal {}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Contains,
+    StartsWith,
+    EndsWith,
+}
+
+/// A condition on one field of an item
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    pub field: FilterField,
+    pub comparison: Comparison,
+    pub value: FieldValue,
+}
+
+impl Condition {
+    pub fn matches(&self, item: &Item) -> bool {
+        let actual = self.field.value(item);
+        // Custom fields hold whatever was stored, so kinds may differ
+        let ordered = |accept: fn(Ordering) -> bool| {
+            actual != FieldValue::Null
+                && std::mem::discriminant(&actual) == std::mem::discriminant(&self.value)
+                && accept(actual.cmp(&self.value))
+        };
+        let text = |accept: fn(&str, &str) -> bool| match (&actual, &self.value) {
+            (FieldValue::Text(actual), FieldValue::Text(value)) => accept(actual, value),
+            _ => false,
+        };
+        match self.comparison {
+            Comparison::Eq => actual == self.value,
+            Comparison::Ne => actual != self.value,
+            Comparison::Gt => ordered(Ordering::is_gt),
+            Comparison::Ge => ordered(Ordering::is_ge),
+            Comparison::Lt => ordered(Ordering::is_lt),
+            Comparison::Le => ordered(Ordering::is_le),
+            Comparison::Contains => text(|actual, value| actual.contains(value)),
+            Comparison::StartsWith => text(|actual, value| actual.starts_with(value)),
+            Comparison::EndsWith => text(|actual, value| actual.ends_with(value)),
+        }
+    }
+}
+
+/// A sort key of a list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sort {
+    pub field: QueryField,
+    pub descending: bool,
+}
+
+/// Compare items by `order`, falling back to list order (creation time, then
+/// ID) so that pages are stable
+pub fn compare(order: &[Sort], a: &Item, b: &Item) -> Ordering {
+    order
+        .iter()
+        .map(|sort| {
+            let ordering = sort.field.value(a).cmp(&sort.field.value(b));
+            if sort.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        })
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or_else(|| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)))
+}
+
+/// Fields to include in each listed item; every field when unset
+pub type Selection = Arc<[QueryField]>;
+
+/// A list query in structured form: which items, in which order, which page,
+/// and which of their fields
+///
+/// Built from the list endpoint's query parameters, OData options included
+/// (see [`crate::odata`]), so repositories only see filters and pages.
+#[derive(Debug, Clone)]
+pub struct ListOptions {
+    pub filter: ItemFilter,
+    pub page: Page,
+    pub select: Option<Selection>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(name: &str, version: u64) -> Item {
+        Item {
+            id: name.to_string(),
+            name: name.to_string(),
+            slug: name.to_string(),
+            external_id: None,
+            source: None,
+            description: None,
+            metadata: None,
+            custom_fields: Default::default(),
+            owner_id: None,
+            tenant_id: None,
+            status: Default::default(),
+            publish_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            version,
+        }
+    }
+
+    #[test]
+    fn test_conditions_compare_values_of_the_same_kind() {
+        let condition = |field: QueryField, comparison, value| Condition {
+            field: field.into(),
+            comparison,
+            value,
+        };
+        let item = item("Widget", 3);
+        let name = |value: &str| FieldValue::Text(value.to_string());
+
+        assert!(condition(QueryField::Name, Comparison::Eq, name("Widget")).matches(&item));
+        assert!(condition(QueryField::Name, Comparison::StartsWith, name("Wid")).matches(&item));
+        assert!(!condition(QueryField::Name, Comparison::Contains, name("gadget")).matches(&item));
+        assert!(
+            condition(QueryField::Version, Comparison::Ge, FieldValue::Number(3)).matches(&item)
+        );
+        assert!(
+            !condition(QueryField::Version, Comparison::Gt, FieldValue::Number(3)).matches(&item)
+        );
+        // Absent fields equal null and are never greater or less than anything
+        assert!(condition(QueryField::Description, Comparison::Eq, FieldValue::Null).matches(&item));
+        assert!(!condition(QueryField::Description, Comparison::Lt, name("z")).matches(&item));
+    }
+
+    #[test]
+    fn test_compare_applies_sort_keys_in_order() {
+        let (a, b) = (item("a", 2), item("b", 2));
+        let by_name_desc = [Sort {
+            field: QueryField::Name,
+            descending: true,
+        }];
+        assert_eq!(compare(&by_name_desc, &a, &b), Ordering::Greater);
+
+        let by_version = [Sort {
+            field: QueryField::Version,
+            descending: false,
+        }];
+        // Ties fall back to creation order
+        assert_eq!(compare(&by_version, &a, &b), (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+    }
+}
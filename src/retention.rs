@@ -0,0 +1,233 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::{
+    config::RetentionConfig,
+    db::{DatabaseResult, ItemRepository},
+    leadership::Leadership,
+    metrics::track_retention_purge,
+};
+
+/// Kind of record a retention policy applies to
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RetentionTarget {
+    /// Outbox events that every publisher has already accepted
+    PublishedEvents,
+}
+
+impl RetentionTarget {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::PublishedEvents => "published_events",
+        }
+    }
+}
+
+/// Delete records of one kind once they are older than `max_age`
+#[derive(Debug, Clone, Copy)]
+pub struct RetentionPolicy {
+    pub target: RetentionTarget,
+    pub max_age: Duration,
+}
+
+/// Outcome of applying one policy
+#[derive(Debug, Clone, Serialize)]
+pub struct PurgeResult {
+    pub target: RetentionTarget,
+    pub cutoff: DateTime<Utc>,
+    /// Records deleted, or that would have been deleted in dry-run mode
+    pub records: usize,
+}
+
+/// Outcome of one retention run across all policies
+#[derive(Debug, Clone, Serialize)]
+pub struct RetentionReport {
+    pub dry_run: bool,
+    pub results: Vec<PurgeResult>,
+}
+
+impl RetentionReport {
+    pub fn total_records(&self) -> usize {
+        self.results.iter().map(|result| result.records).sum()
+    }
+}
+
+/// Periodically applies retention policies to the repository
+pub struct RetentionJob {
+    repo: Arc<dyn ItemRepository>,
+    policies: Vec<RetentionPolicy>,
+    dry_run: bool,
+    interval: std::time::Duration,
+    leadership: Leadership,
+}
+
+impl RetentionJob {
+    pub fn new(
+        repo: Arc<dyn ItemRepository>,
+        policies: Vec<RetentionPolicy>,
+        dry_run: bool,
+        interval: std::time::Duration,
+    ) -> Self {
+        Self {
+            repo,
+            policies,
+            dry_run,
+            interval,
+            leadership: Leadership::always(),
+        }
+    }
+
+    /// Purge only while this replica leads
+    #[must_use]
+    pub fn with_leadership(mut self, leadership: Leadership) -> Self {
+        self.leadership = leadership;
+        self
+    }
+
+    /// Build the job with the policies enabled in configuration
+    pub fn from_config(repo: Arc<dyn ItemRepository>, config: &RetentionConfig) -> Self {
+        let policies = vec![RetentionPolicy {
+            target: RetentionTarget::PublishedEvents,
+            max_age: Duration::days(i64::from(config.published_events_days)),
+        }];
+
+        Self::new(
+            repo,
+            policies,
+            config.dry_run,
+            std::time::Duration::from_secs(config.interval_seconds),
+        )
+    }
+
+    /// Run the job until the task is aborted
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.interval);
+            loop {
+                interval.tick().await;
+                if !self.leadership.is_leader() {
+                    continue;
+                }
+                if let Err(e) = self.run_once().await {
+                    warn!("Retention run failed: {}", e);
+                }
+            }
+        })
+    }
+
+    /// Apply every policy once
+    pub async fn run_once(&self) -> DatabaseResult<RetentionReport> {
+        let now = Utc::now();
+        let mut results = Vec::with_capacity(self.policies.len());
+
+        for policy in &self.policies {
+            let cutoff = now - policy.max_age;
+            let records = match policy.target {
+                RetentionTarget::PublishedEvents => {
+                    self.repo
+                        .purge_published_events(cutoff, self.dry_run)
+                        .await?
+                }
+            };
+
+            track_retention_purge(policy.target.as_str(), records, self.dry_run);
+            if self.dry_run {
+                info!(
+                    policy = policy.target.as_str(),
+                    %cutoff,
+                    "Retention dry run: {} records would be purged", records
+                );
+            } else if records > 0 {
+                info!(
+                    policy = policy.target.as_str(),
+                    %cutoff,
+                    "Retention purged {} records", records
+                );
+            }
+
+            results.push(PurgeResult {
+                target: policy.target,
+                cutoff,
+                records,
+            });
+        }
+
+        Ok(RetentionReport {
+            dry_run: self.dry_run,
+            results,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::InMemoryRepository, models::CreateItemRequest};
+
+    async fn repo_with_events(published: usize, pending: usize) -> Arc<dyn ItemRepository> {
+        let repo: Arc<dyn ItemRepository> = Arc::new(InMemoryRepository::new());
+        for i in 0..published + pending {
+            let request = CreateItemRequest {
+                name: format!("Item {i}"),
+                description: None,
+                metadata: None,
+                custom_fields: Default::default(),
+                status: Default::default(),
+                publish_at: None,
+                external_id: None,
+                source: None,
+            };
+            repo.create(request, None).await.unwrap();
+        }
+
+        let sequences: Vec<u64> = repo
+            .pending_events(published)
+            .await
+            .unwrap()
+            .iter()
+            .map(|event| event.sequence)
+            .collect();
+        repo.mark_events_published(&sequences).await.unwrap();
+        repo
+    }
+
+    fn job(repo: Arc<dyn ItemRepository>, dry_run: bool) -> RetentionJob {
+        let policies = vec![RetentionPolicy {
+            target: RetentionTarget::PublishedEvents,
+            max_age: Duration::zero(),
+        }];
+        RetentionJob::new(repo, policies, dry_run, std::time::Duration::from_secs(60))
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_reports_without_deleting() {
+        let repo = repo_with_events(3, 1).await;
+
+        let report = job(repo.clone(), true).run_once().await.unwrap();
+        assert!(report.dry_run);
+        assert_eq!(report.total_records(), 3);
+
+        let report = job(repo, true).run_once().await.unwrap();
+        assert_eq!(report.total_records(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_purges_only_expired_published_events() {
+        let repo = repo_with_events(3, 2).await;
+
+        let report = job(repo.clone(), false).run_once().await.unwrap();
+        assert_eq!(report.total_records(), 3);
+        assert_eq!(repo.pending_event_count().await.unwrap(), 2);
+
+        // Nothing left to purge; pending events are never touched
+        let report = job(repo.clone(), false).run_once().await.unwrap();
+        assert_eq!(report.total_records(), 0);
+
+        let retained = RetentionJob::from_config(repo, &RetentionConfig::default());
+        assert_eq!(retained.run_once().await.unwrap().total_records(), 0);
+    }
+}
@@ -1,26 +1,215 @@
-use crate::{handlers::*, openapi, state::SharedState};
-use axum::{routing::get, Router};
+use crate::{
+    deprecation::deprecation_middleware,
+    handlers::*,
+    middleware::{
+        add_group_middleware, rate_limit::RateLimiter, tenancy::tenant_lookup_middleware,
+        RouteGroup,
+    },
+    openapi,
+    state::SharedState,
+};
+use axum::{
+    extract::DefaultBodyLimit,
+    routing::{delete, get, post, put},
+    Extension, Router,
+};
 
 pub fn create_routes(state: SharedState) -> Router {
-    // Create stateful routes
-    let api_routes = Router::new()
+    // Every group draws on the same rate limit budget
+    let rate_limiter =
+        RateLimiter::new(state.config.rate_limit.clone()).with_clock(state.clock.clone());
+    let group = |routes, group| add_group_middleware(routes, group, &state, &rate_limiter);
+
+    let probe_routes = Router::new()
         // Health endpoints
         .route("/", get(health_check))
         .route("/health", get(health_check))
         .route("/health/live", get(liveness))
         .route("/health/ready", get(readiness))
+        .route("/version", get(version))
+        // API index, public like the documentation it links to
+        .route("/api", get(api_index))
         // Metrics endpoint
-        .route("/metrics", get(metrics_handler))
-        // API endpoints
-        .route("/api/v1/items", get(list_items).post(create_item))
+        .route("/metrics", get(metrics_handler));
+
+    // Only routes taking item bodies are held to the body size limit
+    let body_limit = DefaultBodyLimit::max(state.config.server.max_body_bytes);
+    let api_routes = Router::new()
+        .route("/api/v1/items", get(list_items).merge(post(create_item).layer(body_limit)))
+        .route("/api/v1/items/changes", get(item_changes))
+        .route("/api/v1/items/stats", get(item_stats))
+        .route("/api/v1/items/analytics", get(item_analytics))
+        .route("/api/v1/views/{name}", get(get_view))
+        .route("/api/v1/fields", get(list_custom_fields))
+        .route("/api/v1/fields/schema", get(custom_fields_schema))
+        .route(
+            "/api/v1/fields/{name}",
+            get(get_custom_field)
+                .delete(delete_custom_field)
+                .merge(put(define_custom_field).layer(body_limit)),
+        )
+        .route("/api/v1/schemas", get(list_schemas))
+        .route(
+            "/api/v1/schemas/{name}",
+            get(get_schema)
+                .delete(delete_schema)
+                .merge(put(register_schema).layer(body_limit)),
+        )
+        .route("/api/v1/schemas/{name}/versions", get(list_schema_versions))
+        .route("/api/v1/schemas/{name}/versions/{version}", get(get_schema_version))
         .route(
             "/api/v1/items/{id}",
-            get(get_item).put(update_item).delete(delete_item),
+            get(get_item)
+                .delete(delete_item)
+                .merge(put(update_item).layer(body_limit)),
+        )
+        .route("/api/v1/items/slug/{slug}", get(get_item_by_slug))
+        .route("/api/v1/items/by-external-id/{external_id}", put(upsert_item).layer(body_limit))
+        .route("/api/v1/items/{id}/duplicates", get(find_duplicates))
+        .route("/api/v1/items/{id}/merge", post(merge_items).layer(body_limit))
+        .route("/api/v1/items/{id}/publish", post(publish_item).layer(body_limit))
+        .route(
+            "/api/v1/items/{id}/lock",
+            get(get_item_lock)
+                .delete(unlock_item)
+                .merge(post(lock_item).layer(body_limit)),
+        )
+        .route(
+            "/api/v1/items/{id}/permissions",
+            get(list_permissions).merge(post(grant_permission).layer(body_limit)),
         )
+        .route("/api/v1/items/{id}/permissions/{grant_id}", delete(revoke_permission))
+        .route("/api/v1/items/{id}/attachments", get(list_attachments))
+        .route(
+            "/api/v1/items/{id}/attachments/presign",
+            post(presign_attachment).layer(body_limit),
+        )
+        .route("/api/v1/items/{id}/attachments/{upload_id}/confirm", post(confirm_attachment))
+        .route(
+            "/api/v1/items/{id}/attachments/{upload_id}/thumbnail",
+            get(get_attachment_thumbnail),
+        )
+        .route(
+            "/api/v1/items/{id}/comments",
+            get(list_comments).merge(post(create_comment).layer(body_limit)),
+        )
+        .route(
+            "/api/v1/items/{id}/comments/{comment_id}",
+            get(get_comment)
+                .delete(delete_comment)
+                .merge(put(update_comment).layer(body_limit)),
+        )
+        .route(
+            "/api/v1/items/{id}/collection",
+            get(get_item_collection).merge(put(file_item).layer(body_limit)),
+        )
+        .route(
+            "/api/v1/collections",
+            get(list_collections).merge(post(create_collection).layer(body_limit)),
+        )
+        .route("/api/v1/collections/{id}", get(get_collection).delete(delete_collection))
+        .route("/api/v1/collections/{id}/move", post(move_collection).layer(body_limit))
+        .route("/api/v1/collections/{id}/descendants", get(list_collection_descendants))
+        .route("/api/v1/collections/{id}/items", get(list_collection_items))
+        .route(
+            "/api/v1/collections/{id}/permissions",
+            get(list_collection_permissions)
+                .merge(post(grant_collection_permission).layer(body_limit)),
+        )
+        .route(
+            "/api/v1/collections/{id}/permissions/{grant_id}",
+            delete(revoke_collection_permission),
+        )
+        .route(
+            "/api/v1/items/{id}/share",
+            get(list_shares).merge(post(create_share).layer(body_limit)),
+        )
+        .route("/api/v1/items/{id}/share/{share_id}", delete(revoke_share))
+        .route("/api/v1/notifications", get(list_notifications))
+        .route("/api/v1/notifications/unread-count", get(unread_notification_count))
+        .route("/api/v1/notifications/read", post(mark_all_notifications_read))
+        .route("/api/v1/notifications/{id}/read", post(mark_notification_read))
+        .route("/api/v1/changes", get(list_changes))
+        .route("/api/v1/changes/{id}", get(get_change))
+        .route("/api/v1/changes/{id}/approve", post(approve_change))
+        .route("/api/v1/changes/{id}/reject", post(reject_change).layer(body_limit))
+        .route(
+            "/api/v1/saved-searches",
+            get(list_saved_searches).merge(post(create_saved_search).layer(body_limit)),
+        )
+        .route("/api/v1/saved-searches/{id}", get(get_saved_search).delete(delete_saved_search))
+        .route("/api/v1/saved-searches/{id}/results", get(saved_search_results))
+        .route("/api/v1/webhooks/{id}/deliveries", get(list_webhook_deliveries))
+        .route("/api/v1/webhooks/{id}/deliveries/{delivery}/redeliver", post(redeliver_webhook));
+
+    let share_routes = Router::new().route("/share/{token}", get(open_share));
+
+    let admin_routes = Router::new()
+        .route("/admin/v1/backup", post(create_backup))
+        .route("/admin/v1/restore", post(restore_backup))
+        .route("/admin/v1/privacy/erasures", post(erase_principal_data))
+        .route("/admin/v1/config", get(get_config))
+        .route(
+            "/admin/v1/log-level",
+            get(get_log_level).put(set_log_level).delete(reset_log_level),
+        )
+        .route(
+            "/admin/v1/chaos",
+            get(get_chaos_rules).put(set_chaos_rules).delete(clear_chaos_rules),
+        )
+        .route(
+            "/admin/v1/mocks",
+            get(list_mocks).post(create_mock).delete(clear_mocks),
+        )
+        .route("/admin/v1/mocks/{id}", delete(delete_mock))
+        .route("/admin/v1/dead-letters", get(list_dead_letters))
+        .route(
+            "/admin/v1/dead-letters/{id}",
+            get(get_dead_letter).delete(discard_dead_letter),
+        )
+        .route("/admin/v1/dead-letters/{id}/requeue", post(requeue_dead_letter))
+        .route("/admin/v1/events/replay", post(replay_events))
+        .route("/admin/v1/query", post(run_admin_query))
+        .route("/admin/v1/dual-write", get(get_dual_write_report))
+        .route("/admin/v1/dual-write/reconcile", post(reconcile_dual_write))
+        .route("/admin/v1/sbom", get(get_sbom))
+        .route("/admin/v1/sbom/licenses", get(get_license_report))
+        .route("/admin/v1/tenants", get(list_tenants).post(create_tenant))
+        .route(
+            "/admin/v1/tenants/{id}",
+            get(get_tenant).put(update_tenant).delete(delete_tenant),
+        )
+        // Profiling endpoints
+        .route("/debug/pprof/profile", get(cpu_profile))
+        .route("/debug/pprof/heap", get(heap_profile));
+
+    // Documentation routes only need what the document describes
+    let docs_routes = openapi::create_docs_routes(openapi::DocsState {
+        custom_fields: state.custom_fields.clone(),
+        envelope: state.config.server.response_envelope,
+        deprecations: state.deprecations.clone(),
+    });
+    let mut stateful_routes = Router::new()
+        .merge(group(probe_routes, RouteGroup::Probes))
+        .merge(group(api_routes, RouteGroup::Api))
+        .merge(group(share_routes, RouteGroup::Shares))
+        .merge(group(admin_routes, RouteGroup::Admin));
+    // Deprecated endpoints are known by their route, so this follows routing
+    if !state.deprecations.is_empty() {
+        let deprecations = state.deprecations.clone();
+        stateful_routes =
+            stateful_routes.route_layer(axum::middleware::from_fn(move |req, next| {
+                deprecation_middleware(req, next, deprecations.clone())
+            }));
+    }
+    let stateful_routes = stateful_routes
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            tenant_lookup_middleware,
+        ))
+        // Request bodies are checked against the deployment's extra rules
+        .layer(Extension(state.validation.clone()))
         .with_state(state);
 
-    // Merge documentation routes (they don't need state)
-    Router::new()
-        .merge(openapi::create_docs_routes())
-        .merge(api_routes)
+    Router::new().merge(docs_routes).merge(stateful_routes)
 }
@@ -0,0 +1,319 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    db::{ItemFilter, NotificationRepository, SavedSearchRepository},
+    events::{EventPublisher, ItemEvent, ItemEventType, OutboxEvent, WebhookPublisher},
+    inbox::{Notification, NotificationReason},
+    models::Item,
+    odata::{self, ODataError},
+    query::{Condition, Sort},
+    tenancy::{current_tenant, with_optional_tenant},
+};
+
+/// CloudEvents `type` of the events sent to a saved search's webhook
+pub const SAVED_SEARCH_MATCHED: &str = "com.ferrous.saved_search.matched";
+
+/// A named list query a principal can run again, and be told about new
+/// items matching it
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SavedSearch {
+    #[schema(example = "4d3c2b1a-0f9e-8d7c-6b5a-493827161504")]
+    pub id: String,
+    /// Subject of the principal the search belongs to
+    #[schema(example = "user-123")]
+    pub owner: String,
+    #[schema(example = "Open widgets")]
+    pub name: String,
+    /// OData `$filter` the results match
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "startswith(name, 'Widget')")]
+    pub filter: Option<String>,
+    /// OData `$orderby` the results are sorted by
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "updated_at desc")]
+    pub orderby: Option<String>,
+    /// Search every owner's items rather than the owner's own (administrators only)
+    pub all: bool,
+    /// Notify the owner in their inbox about new matching items
+    pub notify: bool,
+    /// Webhook new matching items are sent to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "crm")]
+    pub webhook: Option<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+}
+
+impl SavedSearch {
+    /// The current tenant's search `request`, saved by `owner`
+    pub fn new(owner: &str, request: CreateSavedSearchRequest, now: DateTime<Utc>) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            owner: owner.to_string(),
+            name: request.name,
+            filter: request.filter,
+            orderby: request.orderby,
+            all: request.all,
+            notify: request.notify,
+            webhook: request.webhook,
+            created_at: now,
+            tenant_id: current_tenant(),
+        }
+    }
+
+    /// Conditions of the search's `$filter`
+    pub fn conditions(&self) -> Result<Vec<Condition>, ODataError> {
+        Ok(self
+            .filter
+            .as_deref()
+            .map(odata::parse_filter)
+            .transpose()?
+            .unwrap_or_default())
+    }
+
+    /// Sort order of the search's `$orderby`
+    pub fn order(&self) -> Result<Vec<Sort>, ODataError> {
+        Ok(self
+            .orderby
+            .as_deref()
+            .map(odata::parse_orderby)
+            .transpose()?
+            .unwrap_or_default())
+    }
+
+    /// Whether anyone is told about new items matching the search
+    pub fn is_subscribed(&self) -> bool {
+        self.notify || self.webhook.is_some()
+    }
+
+    /// Whether `item` is among the search's results
+    pub fn matches(&self, item: &Item) -> bool {
+        let Ok(conditions) = self.conditions() else {
+            return false;
+        };
+        let scope = if self.all {
+            ItemFilter::default()
+        } else {
+            ItemFilter::owned_by(self.owner.as_str())
+        };
+        scope.with_conditions(conditions).matches(item)
+    }
+}
+
+/// Request to save a search
+#[derive(Debug, Clone, Default, Deserialize, Validate, ToSchema)]
+pub struct CreateSavedSearchRequest {
+    /// Name of the search, unique among the caller's searches (1-100 characters)
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "Name must be between 1 and 100 characters"
+    ))]
+    #[schema(example = "Open widgets", min_length = 1, max_length = 100)]
+    pub name: String,
+    /// OData `$filter`, as accepted by the item list
+    pub filter: Option<String>,
+    /// OData `$orderby`, as accepted by the item list
+    pub orderby: Option<String>,
+    /// Search every owner's items (administrators only)
+    #[serde(default)]
+    pub all: bool,
+    /// Notify the caller in their inbox about new matching items; needs the
+    /// notification inbox
+    #[serde(default)]
+    pub notify: bool,
+    /// ID of a configured webhook to send new matching items to
+    pub webhook: Option<String>,
+}
+
+/// A page of a saved search's results
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SavedSearchResults {
+    pub search: SavedSearch,
+    #[schema(value_type = Vec<Item>)]
+    pub items: Vec<Arc<Item>>,
+    /// Items matching the search
+    pub total: usize,
+    pub limit: usize,
+    pub offset: usize,
+}
+
+/// Tells saved searches' subscribers about new items matching them
+///
+/// Only created items are matched. Owners are not notified about items they
+/// created themselves, though their webhooks still receive them; an item
+/// matching several of an owner's searches notifies them once. Webhook
+/// deliveries are CloudEvents of type `com.ferrous.saved_search.matched`
+/// whose `subject` is the search ID, sent under an ID derived from the
+/// change so retries can be recognized.
+pub struct SavedSearchPublisher {
+    searches: Arc<dyn SavedSearchRepository>,
+    inbox: Option<Arc<dyn NotificationRepository>>,
+    webhooks: Option<Arc<WebhookPublisher>>,
+}
+
+impl SavedSearchPublisher {
+    pub fn new(
+        searches: Arc<dyn SavedSearchRepository>,
+        inbox: Option<Arc<dyn NotificationRepository>>,
+        webhooks: Option<Arc<WebhookPublisher>>,
+    ) -> Self {
+        Self {
+            searches,
+            inbox,
+            webhooks,
+        }
+    }
+
+    fn notification(event: &OutboxEvent, search: &SavedSearch) -> Notification {
+        Notification {
+            id: Uuid::new_v4().to_string(),
+            recipient: search.owner.clone(),
+            event_id: event.id.clone(),
+            event_type: event.event_type,
+            item_id: event.item_id.clone(),
+            item_name: event.item.name.clone(),
+            actor: event.actor.clone(),
+            reason: NotificationReason::SavedSearch,
+            saved_search_id: Some(search.id.clone()),
+            change_request_id: None,
+            created_at: event.occurred_at,
+            read_at: None,
+            tenant_id: event.item.tenant_id.clone(),
+        }
+    }
+
+    fn matched_event(event: &OutboxEvent, search: &SavedSearch) -> ItemEvent {
+        let mut matched = ItemEvent::from(event);
+        matched.id = format!("{}:{}", event.id, search.id);
+        matched.event_type = SAVED_SEARCH_MATCHED.to_string();
+        matched.subject = Some(search.id.clone());
+        matched
+    }
+}
+
+#[async_trait]
+impl EventPublisher for SavedSearchPublisher {
+    fn name(&self) -> &'static str {
+        "saved_searches"
+    }
+
+    async fn publish(&self, event: &OutboxEvent) -> Result<(), String> {
+        if event.event_type != ItemEventType::Created {
+            return Ok(());
+        }
+        let tenant = event.item.tenant_id.clone();
+        let searches = with_optional_tenant(tenant, self.searches.subscriptions())
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let mut notifications = Vec::new();
+        let mut errors = Vec::new();
+        for search in searches.iter().filter(|search| search.matches(&event.item)) {
+            if search.notify && event.actor.as_ref() != Some(&search.owner) {
+                notifications.push(Self::notification(event, search));
+            }
+            if let (Some(webhook), Some(webhooks)) = (&search.webhook, &self.webhooks) {
+                if let Err(e) = webhooks
+                    .deliver(webhook, &Self::matched_event(event, search))
+                    .await
+                {
+                    errors.push(e);
+                }
+            }
+        }
+        if let (Some(inbox), false) = (&self.inbox, notifications.is_empty()) {
+            inbox.add(notifications).await.map_err(|e| e.to_string())?;
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+
+    async fn health_check(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{InMemoryNotificationRepository, InMemorySavedSearchRepository};
+
+    fn item(name: &str, owner: &str) -> Item {
+        Item {
+            id: format!("{name}-1"),
+            name: name.to_string(),
+            slug: name.to_lowercase(),
+            external_id: None,
+            source: None,
+            description: None,
+            metadata: None,
+            custom_fields: Default::default(),
+            owner_id: Some(owner.to_string()),
+            tenant_id: None,
+            status: Default::default(),
+            publish_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            version: 1,
+        }
+    }
+
+    fn search(owner: &str, filter: &str, all: bool) -> SavedSearch {
+        let request = CreateSavedSearchRequest {
+            name: filter.to_string(),
+            filter: Some(filter.to_string()),
+            all,
+            notify: true,
+            ..Default::default()
+        };
+        SavedSearch::new(owner, request, Utc::now())
+    }
+
+    #[test]
+    fn test_searches_match_within_their_scope() {
+        let widgets = search("alice", "startswith(name, 'Widget')", false);
+        assert!(widgets.matches(&item("Widget", "alice")));
+        assert!(!widgets.matches(&item("Gadget", "alice")));
+        // Only the owner's items, unless the search spans all of them
+        assert!(!widgets.matches(&item("Widget", "bob")));
+        assert!(search("admin", "startswith(name, 'Widget')", true).matches(&item("Widget", "bob")));
+    }
+
+    #[tokio::test]
+    async fn test_new_matching_items_notify_subscribers() {
+        let searches = Arc::new(InMemorySavedSearchRepository::new());
+        let inbox = Arc::new(InMemoryNotificationRepository::new());
+        for search in [
+            search("admin", "startswith(name, 'Widget')", true),
+            search("admin", "contains(name, 'dge')", true),
+            search("carol", "startswith(name, 'Widget')", true),
+        ] {
+            searches.create(search).await.unwrap();
+        }
+        let publisher = SavedSearchPublisher::new(searches, Some(inbox.clone()), None);
+
+        let mut created = OutboxEvent::new(1, ItemEventType::Created, item("Widget", "bob"));
+        created.actor = Some("carol".to_string());
+        publisher.publish(&created).await.unwrap();
+        let mut updated = OutboxEvent::new(2, ItemEventType::Updated, item("Widget", "bob"));
+        updated.actor = Some("bob".to_string());
+        publisher.publish(&updated).await.unwrap();
+
+        let (notifications, _) = inbox.list("admin", false, 10, 0).await.unwrap();
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(notifications[0].reason, NotificationReason::SavedSearch);
+        // Carol created the item herself
+        assert_eq!(inbox.unread_count("carol").await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,116 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+/// CycloneDX 1.5 inventory of the crates linked into this binary, with their
+/// licenses and checksums, generated by `build.rs`
+pub const SBOM: &str = include_str!(concat!(env!("OUT_DIR"), "/sbom.json"));
+
+/// Media type of [`SBOM`]
+pub const SBOM_CONTENT_TYPE: &str = "application/vnd.cyclonedx+json; version=1.5";
+
+/// Crates linked into the binary, grouped by license
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LicenseReport {
+    /// Licenses in declared SPDX expression form, most used first
+    pub licenses: Vec<LicenseUsage>,
+    /// Crates that declare no license expression, as `name@version`
+    pub undeclared: Vec<String>,
+}
+
+/// Crates declaring one license expression
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LicenseUsage {
+    #[schema(example = "MIT OR Apache-2.0")]
+    pub expression: String,
+    /// As `name@version`
+    #[schema(example = json!(["serde@1.0.219"]))]
+    pub components: Vec<String>,
+}
+
+/// Licenses of the embedded inventory, read once as it never changes
+pub static LICENSE_REPORT: Lazy<LicenseReport> = Lazy::new(|| {
+    let sbom: Value = serde_json::from_str(SBOM).unwrap_or_default();
+    license_report(&sbom)
+});
+
+fn license_report(sbom: &Value) -> LicenseReport {
+    let mut licenses: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut undeclared = Vec::new();
+    for component in sbom["components"].as_array().into_iter().flatten() {
+        let name = format!(
+            "{}@{}",
+            component["name"].as_str().unwrap_or_default(),
+            component["version"].as_str().unwrap_or_default()
+        );
+        let expressions: Vec<&str> = component["licenses"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|license| license["expression"].as_str())
+            .collect();
+        if expressions.is_empty() {
+            undeclared.push(name);
+            continue;
+        }
+        for expression in expressions {
+            licenses
+                .entry(expression.to_string())
+                .or_default()
+                .push(name.clone());
+        }
+    }
+
+    let mut licenses: Vec<LicenseUsage> = licenses
+        .into_iter()
+        .map(|(expression, components)| LicenseUsage {
+            expression,
+            components,
+        })
+        .collect();
+    licenses.sort_by(|a, b| b.components.len().cmp(&a.components.len()));
+    LicenseReport {
+        licenses,
+        undeclared,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_components_are_grouped_by_license() {
+        let sbom = json!({ "components": [
+            { "name": "a", "version": "1.0.0", "licenses": [{ "expression": "MIT" }] },
+            { "name": "b", "version": "0.2.0", "licenses": [{ "expression": "Apache-2.0" }] },
+            { "name": "c", "version": "3.1.0", "licenses": [{ "expression": "MIT" }] },
+            { "name": "d", "version": "0.1.0" },
+        ] });
+
+        let report = license_report(&sbom);
+
+        assert_eq!(report.licenses[0].expression, "MIT");
+        assert_eq!(report.licenses[0].components, ["a@1.0.0", "c@3.1.0"]);
+        assert_eq!(report.licenses[1].components, ["b@0.2.0"]);
+        assert_eq!(report.undeclared, ["d@0.1.0"]);
+    }
+
+    #[test]
+    fn test_embedded_inventory_lists_dependencies() {
+        let sbom: Value = serde_json::from_str(SBOM).unwrap();
+        assert_eq!(sbom["bomFormat"], "CycloneDX");
+        let names: Vec<&str> = sbom["components"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|component| component["name"].as_str())
+            .collect();
+        assert!(names.contains(&"axum"));
+        // Development dependencies are not linked into the binary
+        assert!(!names.contains(&"criterion"));
+    }
+}
@@ -0,0 +1,219 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::{sync::Arc, time::Duration};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    time::timeout,
+};
+use utoipa::ToSchema;
+
+use crate::{config::AttachmentsConfig, http_client::HttpClient};
+
+/// Largest chunk of a file streamed to clamd at once
+const CLAMAV_CHUNK_BYTES: usize = 64 * 1024;
+
+/// How long clamd may take to take a file and answer
+const CLAMAV_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Where an attachment is in malware scanning
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanStatus {
+    /// Not scanned yet, or the scan failed; not served for download
+    Pending,
+    Clean,
+    /// Malware was found; never served for download
+    Infected,
+}
+
+impl ScanStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Clean => "clean",
+            Self::Infected => "infected",
+        }
+    }
+}
+
+/// What a scanner found in a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Clean,
+    /// Malware was found, named by the scanner's signature
+    Infected(String),
+}
+
+/// Scans attachment contents for malware
+#[async_trait]
+pub trait Scanner: Send + Sync {
+    /// Name of the scanner in logs and metrics
+    fn name(&self) -> &'static str;
+
+    /// Scan `contents`; fails when the scanner cannot give a verdict
+    async fn scan(&self, contents: &[u8]) -> Result<Verdict, String>;
+}
+
+/// The scanner configured for attachments, if any
+pub fn from_config(config: &AttachmentsConfig, http: HttpClient) -> Option<Arc<dyn Scanner>> {
+    if let Some(addr) = &config.clamav_addr {
+        return Some(Arc::new(ClamavScanner::new(addr)));
+    }
+    config
+        .scan_url
+        .as_ref()
+        .map(|url| Arc::new(HttpScanner::new(url, http)) as Arc<dyn Scanner>)
+}
+
+/// Scans files with a clamd daemon over TCP, using its `INSTREAM` command
+pub struct ClamavScanner {
+    /// `host:port` clamd listens at
+    addr: String,
+}
+
+impl ClamavScanner {
+    pub fn new(addr: &str) -> Self {
+        Self {
+            addr: addr.to_string(),
+        }
+    }
+
+    async fn instream(&self, contents: &[u8]) -> std::io::Result<Vec<u8>> {
+        let mut stream = TcpStream::connect(&self.addr).await?;
+        stream.write_all(b"zINSTREAM\0").await?;
+        for chunk in contents.chunks(CLAMAV_CHUNK_BYTES) {
+            // Chunks are at most 64 KiB, so their length fits
+            let length = u32::try_from(chunk.len()).unwrap_or(u32::MAX);
+            stream.write_all(&length.to_be_bytes()).await?;
+            stream.write_all(chunk).await?;
+        }
+        stream.write_all(&0u32.to_be_bytes()).await?;
+        let mut reply = Vec::new();
+        stream.read_to_end(&mut reply).await?;
+        Ok(reply)
+    }
+}
+
+/// The verdict in a clamd reply such as `stream: OK` or
+/// `stream: Eicar-Signature FOUND`
+fn clamav_verdict(reply: &[u8]) -> Result<Verdict, String> {
+    let reply = String::from_utf8_lossy(reply);
+    let reply = reply.trim_end_matches(['\0', '\n']);
+    let result = reply.strip_prefix("stream: ").unwrap_or(reply);
+    if result == "OK" {
+        Ok(Verdict::Clean)
+    } else if let Some(signature) = result.strip_suffix(" FOUND") {
+        Ok(Verdict::Infected(signature.to_string()))
+    } else {
+        Err(format!("clamd answered {reply:?}"))
+    }
+}
+
+#[async_trait]
+impl Scanner for ClamavScanner {
+    fn name(&self) -> &'static str {
+        "clamav"
+    }
+
+    async fn scan(&self, contents: &[u8]) -> Result<Verdict, String> {
+        let reply = timeout(CLAMAV_TIMEOUT, self.instream(contents))
+            .await
+            .map_err(|_| "clamd timed out".to_string())?
+            .map_err(|e| e.to_string())?;
+        clamav_verdict(&reply)
+    }
+}
+
+/// Answer of an HTTP scanning service
+#[derive(Debug, Deserialize)]
+struct HttpScanResult {
+    infected: bool,
+    signature: Option<String>,
+}
+
+/// Scans files by posting them to an HTTP service, which answers
+/// `{"infected": false}`, or `{"infected": true, "signature": "..."}`
+pub struct HttpScanner {
+    url: String,
+    http: HttpClient,
+}
+
+impl HttpScanner {
+    pub fn new(url: &str, http: HttpClient) -> Self {
+        Self {
+            url: url.to_string(),
+            http,
+        }
+    }
+}
+
+#[async_trait]
+impl Scanner for HttpScanner {
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    async fn scan(&self, contents: &[u8]) -> Result<Verdict, String> {
+        let request = self
+            .http
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+            .body(contents.to_vec());
+        let response = self.http.send(request).await.map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("scanner answered {}", response.status()));
+        }
+        let result: HttpScanResult = response.json().await.map_err(|e| e.to_string())?;
+        Ok(if result.infected {
+            Verdict::Infected(result.signature.unwrap_or_default())
+        } else {
+            Verdict::Clean
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_clamav_replies_give_verdicts() {
+        assert_eq!(clamav_verdict(b"stream: OK\0"), Ok(Verdict::Clean));
+        assert_eq!(
+            clamav_verdict(b"stream: Win.Test.EICAR_HDB-1 FOUND\0"),
+            Ok(Verdict::Infected("Win.Test.EICAR_HDB-1".to_string()))
+        );
+        assert!(clamav_verdict(b"INSTREAM size limit exceeded. ERROR\0").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_files_are_streamed_to_clamd_in_chunks() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let clamd = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut command = [0; 10];
+            stream.read_exact(&mut command).await.unwrap();
+            assert_eq!(&command, b"zINSTREAM\0");
+            let mut received = Vec::new();
+            loop {
+                let length = stream.read_u32().await.unwrap() as usize;
+                if length == 0 {
+                    break;
+                }
+                let mut chunk = vec![0; length];
+                stream.read_exact(&mut chunk).await.unwrap();
+                received.extend(chunk);
+            }
+            stream.write_all(b"stream: OK\0").await.unwrap();
+            received
+        });
+
+        let contents = vec![7; CLAMAV_CHUNK_BYTES + 1];
+        let verdict = ClamavScanner::new(&addr).scan(&contents).await;
+        assert_eq!(verdict, Ok(Verdict::Clean));
+        assert_eq!(clamd.await.unwrap(), contents);
+    }
+}
@@ -0,0 +1,664 @@
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+#[allow(unused_imports)] // Used in #[schema(example = json!({...}))] attributes
+use serde_json::json;
+use serde_json::{Map, Value};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::{Arc, RwLock},
+};
+use utoipa::ToSchema;
+
+use crate::{
+    db::DatabaseError, error::AppError, tenancy::current_tenant, views::is_valid_view_name,
+};
+
+/// Keywords that describe a schema without constraining values
+const ANNOTATIONS: &[&str] = &[
+    "$schema",
+    "$id",
+    "$comment",
+    "title",
+    "description",
+    "default",
+    "examples",
+];
+
+/// JSON type a schema can require
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JsonType {
+    Null,
+    Boolean,
+    Object,
+    Array,
+    Number,
+    Integer,
+    String,
+}
+
+impl JsonType {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "null" => Self::Null,
+            "boolean" => Self::Boolean,
+            "object" => Self::Object,
+            "array" => Self::Array,
+            "number" => Self::Number,
+            "integer" => Self::Integer,
+            "string" => Self::String,
+            _ => return None,
+        })
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Null => "null",
+            Self::Boolean => "boolean",
+            Self::Object => "object",
+            Self::Array => "array",
+            Self::Number => "number",
+            Self::Integer => "integer",
+            Self::String => "string",
+        }
+    }
+
+    fn matches(self, value: &Value) -> bool {
+        match (self, value) {
+            (Self::Null, Value::Null)
+            | (Self::Boolean, Value::Bool(_))
+            | (Self::Object, Value::Object(_))
+            | (Self::Array, Value::Array(_))
+            | (Self::Number, Value::Number(_))
+            | (Self::String, Value::String(_)) => true,
+            (Self::Integer, Value::Number(n)) => {
+                n.is_i64() || n.is_u64() || n.as_f64().is_some_and(|n| n.fract() == 0.0)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// What a schema allows for properties it does not list
+#[derive(Debug, Default)]
+enum Additional {
+    #[default]
+    Allowed,
+    Forbidden,
+    Schema(Box<CompiledSchema>),
+}
+
+/// A metadata schema checked and ready to validate values
+///
+/// Supports the JSON Schema keywords most metadata needs: `type`, `enum`,
+/// `const`, `properties`, `required`, `additionalProperties`, `items`,
+/// `minItems`, `maxItems`, `minLength`, `maxLength`, `pattern`, `minimum`,
+/// `maximum`, `exclusiveMinimum` and `exclusiveMaximum`, plus `true` and
+/// `false` schemas. Schemas using other keywords are refused rather than
+/// enforced partially.
+#[derive(Debug, Default)]
+pub struct CompiledSchema {
+    /// `false` schemas allow nothing
+    reject_all: bool,
+    types: Option<Vec<JsonType>>,
+    allowed: Option<Vec<Value>>,
+    constant: Option<Value>,
+    properties: BTreeMap<String, CompiledSchema>,
+    required: Vec<String>,
+    additional: Additional,
+    items: Option<Box<CompiledSchema>>,
+    min_items: Option<usize>,
+    max_items: Option<usize>,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    pattern: Option<Regex>,
+    minimum: Option<f64>,
+    maximum: Option<f64>,
+    exclusive_minimum: Option<f64>,
+    exclusive_maximum: Option<f64>,
+}
+
+/// Why a value does not satisfy a schema
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolation {
+    /// Where in the item the value is, such as `metadata.tags[0]`
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+impl CompiledSchema {
+    /// Check `schema`, naming the first problem found by its location
+    pub fn compile(schema: &Value) -> Result<Self, String> {
+        Self::compile_at(schema, "#")
+    }
+
+    fn compile_at(schema: &Value, at: &str) -> Result<Self, String> {
+        let object = match schema {
+            Value::Bool(true) => return Ok(Self::default()),
+            Value::Bool(false) => {
+                return Ok(Self {
+                    reject_all: true,
+                    ..Self::default()
+                })
+            }
+            Value::Object(object) => object,
+            _ => return Err(format!("{at}: a schema must be an object or a boolean")),
+        };
+
+        let mut compiled = Self::default();
+        for (keyword, value) in object {
+            let here = format!("{at}/{keyword}");
+            match keyword.as_str() {
+                "type" => compiled.types = Some(types(value, &here)?),
+                "enum" => match value {
+                    Value::Array(values) if !values.is_empty() => {
+                        compiled.allowed = Some(values.clone());
+                    }
+                    _ => return Err(format!("{here}: must be a non-empty array")),
+                },
+                "const" => compiled.constant = Some(value.clone()),
+                "properties" => {
+                    let Value::Object(properties) = value else {
+                        return Err(format!("{here}: must be an object"));
+                    };
+                    for (name, schema) in properties {
+                        let schema = Self::compile_at(schema, &format!("{here}/{name}"))?;
+                        compiled.properties.insert(name.clone(), schema);
+                    }
+                }
+                "required" => {
+                    compiled.required = value
+                        .as_array()
+                        .and_then(|names| {
+                            names
+                                .iter()
+                                .map(|name| name.as_str().map(str::to_string))
+                                .collect()
+                        })
+                        .ok_or_else(|| format!("{here}: must be an array of property names"))?;
+                }
+                "additionalProperties" => {
+                    compiled.additional = match value {
+                        Value::Bool(true) => Additional::Allowed,
+                        Value::Bool(false) => Additional::Forbidden,
+                        schema => Additional::Schema(Box::new(Self::compile_at(schema, &here)?)),
+                    };
+                }
+                "items" => compiled.items = Some(Box::new(Self::compile_at(value, &here)?)),
+                "minItems" => compiled.min_items = Some(count(value, &here)?),
+                "maxItems" => compiled.max_items = Some(count(value, &here)?),
+                "minLength" => compiled.min_length = Some(count(value, &here)?),
+                "maxLength" => compiled.max_length = Some(count(value, &here)?),
+                "pattern" => {
+                    let pattern = value
+                        .as_str()
+                        .ok_or_else(|| format!("{here}: must be a string"))?;
+                    compiled.pattern = Some(
+                        Regex::new(pattern).map_err(|e| format!("{here}: invalid pattern: {e}"))?,
+                    );
+                }
+                "minimum" => compiled.minimum = Some(number(value, &here)?),
+                "maximum" => compiled.maximum = Some(number(value, &here)?),
+                "exclusiveMinimum" => compiled.exclusive_minimum = Some(number(value, &here)?),
+                "exclusiveMaximum" => compiled.exclusive_maximum = Some(number(value, &here)?),
+                keyword if ANNOTATIONS.contains(&keyword) => {}
+                keyword => return Err(format!("{at}: unsupported keyword {keyword}")),
+            }
+        }
+        Ok(compiled)
+    }
+
+    /// Every way `value`, found at `path`, fails the schema
+    pub fn validate(&self, value: &Value, path: &str) -> Vec<SchemaViolation> {
+        let mut violations = Vec::new();
+        self.check(value, path, &mut violations);
+        violations
+    }
+
+    fn check(&self, value: &Value, path: &str, violations: &mut Vec<SchemaViolation>) {
+        violations.extend(
+            self.messages(value)
+                .into_iter()
+                .map(|message| SchemaViolation {
+                    path: path.to_string(),
+                    message,
+                }),
+        );
+        if self.reject_all || !self.has_type_of(value) {
+            return;
+        }
+        match value {
+            Value::Array(values) => {
+                if let Some(items) = &self.items {
+                    for (i, value) in values.iter().enumerate() {
+                        items.check(value, &format!("{path}[{i}]"), violations);
+                    }
+                }
+            }
+            Value::Object(object) => self.check_object(object, path, violations),
+            _ => {}
+        }
+    }
+
+    fn has_type_of(&self, value: &Value) -> bool {
+        self.types
+            .as_ref()
+            .is_none_or(|types| types.iter().any(|t| t.matches(value)))
+    }
+
+    /// How `value` itself, leaving its items and properties aside, fails
+    fn messages(&self, value: &Value) -> Vec<String> {
+        if self.reject_all {
+            return vec!["No value is allowed here".to_string()];
+        }
+        // A value of the wrong type cannot meet the other constraints
+        if let Some(types) = self.types.as_ref().filter(|_| !self.has_type_of(value)) {
+            let names: Vec<_> = types.iter().map(|t| t.as_str()).collect();
+            return vec![format!("Must be of type {}", names.join(" or "))];
+        }
+
+        let mut messages = Vec::new();
+        if let Some(allowed) = self
+            .allowed
+            .as_ref()
+            .filter(|allowed| !allowed.contains(value))
+        {
+            let allowed: Vec<_> = allowed.iter().map(Value::to_string).collect();
+            messages.push(format!("Must be one of {}", allowed.join(", ")));
+        }
+        if let Some(constant) = self.constant.as_ref().filter(|constant| *constant != value) {
+            messages.push(format!("Must be {constant}"));
+        }
+        match value {
+            Value::String(text) => {
+                let length = text.chars().count();
+                if let Some(min) = self.min_length.filter(|min| length < *min) {
+                    messages.push(format!("Must be at least {min} characters"));
+                }
+                if let Some(max) = self.max_length.filter(|max| length > *max) {
+                    messages.push(format!("Must be at most {max} characters"));
+                }
+                if let Some(pattern) = self.pattern.as_ref().filter(|p| !p.is_match(text)) {
+                    messages.push(format!("Must match {}", pattern.as_str()));
+                }
+            }
+            Value::Number(n) => {
+                let n = n.as_f64().unwrap_or_default();
+                if let Some(min) = self.minimum.filter(|min| n < *min) {
+                    messages.push(format!("Must be at least {min}"));
+                }
+                if let Some(max) = self.maximum.filter(|max| n > *max) {
+                    messages.push(format!("Must be at most {max}"));
+                }
+                if let Some(min) = self.exclusive_minimum.filter(|min| n <= *min) {
+                    messages.push(format!("Must be greater than {min}"));
+                }
+                if let Some(max) = self.exclusive_maximum.filter(|max| n >= *max) {
+                    messages.push(format!("Must be less than {max}"));
+                }
+            }
+            Value::Array(values) => {
+                if let Some(min) = self.min_items.filter(|min| values.len() < *min) {
+                    messages.push(format!("Must have at least {min} items"));
+                }
+                if let Some(max) = self.max_items.filter(|max| values.len() > *max) {
+                    messages.push(format!("Must have at most {max} items"));
+                }
+            }
+            Value::Object(_) | Value::Null | Value::Bool(_) => {}
+        }
+        messages
+    }
+
+    fn check_object(
+        &self,
+        object: &Map<String, Value>,
+        path: &str,
+        violations: &mut Vec<SchemaViolation>,
+    ) {
+        for name in &self.required {
+            if !object.contains_key(name) {
+                violations.push(SchemaViolation {
+                    path: format!("{path}.{name}"),
+                    message: "Is required".to_string(),
+                });
+            }
+        }
+        for (name, value) in object {
+            let at = format!("{path}.{name}");
+            match (self.properties.get(name), &self.additional) {
+                (Some(schema), _) => schema.check(value, &at, violations),
+                (None, Additional::Schema(schema)) => schema.check(value, &at, violations),
+                (None, Additional::Forbidden) => violations.push(SchemaViolation {
+                    path: at,
+                    message: "Is not an allowed property".to_string(),
+                }),
+                (None, Additional::Allowed) => {}
+            }
+        }
+    }
+}
+
+fn types(value: &Value, at: &str) -> Result<Vec<JsonType>, String> {
+    let names = match value {
+        Value::String(name) => vec![name.as_str()],
+        Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    let types: Option<Vec<_>> = names.into_iter().map(JsonType::parse).collect();
+    match types {
+        Some(types) if !types.is_empty() => Ok(types),
+        _ => Err(format!("{at}: must name JSON types")),
+    }
+}
+
+fn count(value: &Value, at: &str) -> Result<usize, String> {
+    value
+        .as_u64()
+        .and_then(|n| usize::try_from(n).ok())
+        .ok_or_else(|| format!("{at}: must be a non-negative integer"))
+}
+
+fn number(value: &Value, at: &str) -> Result<f64, String> {
+    value
+        .as_f64()
+        .ok_or_else(|| format!("{at}: must be a number"))
+}
+
+/// Who a schema applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SchemaScope {
+    /// Items of the tenant that registered it
+    Tenant,
+    /// Items of every tenant without a schema of the same name
+    Global,
+}
+
+/// One version of a registered metadata schema
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[schema(example = json!({
+    "name": "product",
+    "version": 2,
+    "scope": "tenant",
+    "schema": {
+        "type": "object",
+        "properties": { "color": { "enum": ["red", "blue"] } },
+        "required": ["color"]
+    },
+    "created_at": "2024-01-01T00:00:00Z",
+    "created_by": "admin-1"
+}))]
+pub struct MetadataSchema {
+    pub name: String,
+    /// Starts at 1 and increases with every registration under the name
+    pub version: u32,
+    pub scope: SchemaScope,
+    /// The JSON Schema document
+    #[schema(value_type = Object)]
+    pub schema: Value,
+    pub created_at: DateTime<Utc>,
+    /// Subject of the principal that registered the version
+    pub created_by: Option<String>,
+}
+
+/// Request to register a new version of a metadata schema
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "schema": {
+        "type": "object",
+        "properties": { "color": { "enum": ["red", "blue"] } },
+        "required": ["color"]
+    }
+}))]
+pub struct RegisterSchemaRequest {
+    /// The JSON Schema document
+    #[schema(value_type = Object)]
+    pub schema: Value,
+    /// Apply the schema to every tenant rather than the caller's
+    #[serde(default)]
+    pub global: bool,
+}
+
+/// Versions registered under one name in one scope, oldest first
+type Versions = Vec<(MetadataSchema, Arc<CompiledSchema>)>;
+
+/// Metadata schemas registered at runtime, by tenant and name
+///
+/// Registering a schema under a name already in use adds a version; item
+/// metadata is held to the latest version of every schema in effect for its
+/// tenant, which are the tenant's own and the global ones it has no schema
+/// of the same name for. Older versions are kept for reference. Without
+/// multi-tenancy every schema is global.
+#[derive(Default)]
+pub struct SchemaRegistry {
+    /// Keyed by tenant, `None` for global schemas
+    schemas: RwLock<HashMap<Option<String>, BTreeMap<String, Versions>>>,
+}
+
+impl SchemaRegistry {
+    fn scope(global: bool) -> Option<String> {
+        if global {
+            None
+        } else {
+            current_tenant()
+        }
+    }
+
+    /// Register `schema` as the next version of `name`, for the current
+    /// tenant or every tenant
+    pub fn register(
+        &self,
+        name: &str,
+        request: RegisterSchemaRequest,
+        created_by: Option<String>,
+    ) -> Result<MetadataSchema, AppError> {
+        if !is_valid_view_name(name) {
+            return Err(AppError::BadRequest(
+                "Schema names use lowercase letters, digits, - and _".to_string(),
+            ));
+        }
+        let compiled = CompiledSchema::compile(&request.schema)
+            .map_err(|message| AppError::ValidationError(format!("schema: {message}")))?;
+        let tenant = Self::scope(request.global);
+        let scope = if tenant.is_some() {
+            SchemaScope::Tenant
+        } else {
+            SchemaScope::Global
+        };
+
+        let mut schemas = self.schemas.write().map_err(|_| DatabaseError::LockError)?;
+        let versions = schemas
+            .entry(tenant)
+            .or_default()
+            .entry(name.to_string())
+            .or_default();
+        let version = versions.last().map_or(1, |(schema, _)| schema.version + 1);
+        let schema = MetadataSchema {
+            name: name.to_string(),
+            version,
+            scope,
+            schema: request.schema,
+            created_at: Utc::now(),
+            created_by,
+        };
+        versions.push((schema.clone(), Arc::new(compiled)));
+        Ok(schema)
+    }
+
+    /// Latest version of every schema in effect for the current tenant
+    pub fn list(&self) -> Result<Vec<MetadataSchema>, AppError> {
+        Ok(self
+            .in_effect()?
+            .into_iter()
+            .map(|(schema, _)| schema)
+            .collect())
+    }
+
+    fn in_effect(&self) -> Result<Vec<(MetadataSchema, Arc<CompiledSchema>)>, AppError> {
+        let schemas = self.schemas.read().map_err(|_| DatabaseError::LockError)?;
+        let mut scopes = vec![None];
+        // Tenant schemas are read last, so they replace global ones
+        if let Some(tenant) = current_tenant() {
+            scopes.push(Some(tenant));
+        }
+        let mut latest = BTreeMap::new();
+        for tenant in &scopes {
+            for (name, versions) in schemas.get(tenant).into_iter().flatten() {
+                if let Some(version) = versions.last() {
+                    latest.insert(name.clone(), version.clone());
+                }
+            }
+        }
+        Ok(latest.into_values().collect())
+    }
+
+    /// Every version of `name` in the current tenant's scope, or the global
+    /// one, oldest first
+    pub fn versions(
+        &self,
+        name: &str,
+        global: bool,
+    ) -> Result<Option<Vec<MetadataSchema>>, AppError> {
+        let schemas = self.schemas.read().map_err(|_| DatabaseError::LockError)?;
+        Ok(schemas
+            .get(&Self::scope(global))
+            .and_then(|names| names.get(name))
+            .map(|versions| versions.iter().map(|(schema, _)| schema.clone()).collect()))
+    }
+
+    /// Forget every version of `name`, so items are no longer held to it;
+    /// whether there was one
+    pub fn remove(&self, name: &str, global: bool) -> Result<bool, AppError> {
+        let mut schemas = self.schemas.write().map_err(|_| DatabaseError::LockError)?;
+        Ok(schemas
+            .get_mut(&Self::scope(global))
+            .and_then(|names| names.remove(name))
+            .is_some())
+    }
+
+    /// Clear `principal` as the `created_by` of every schema version, in
+    /// every scope, returning how many versions named them
+    pub fn forget_principal(&self, principal: &str) -> Result<usize, AppError> {
+        let mut schemas = self.schemas.write().map_err(|_| DatabaseError::LockError)?;
+        let mut scrubbed = 0;
+        for (schema, _) in schemas
+            .values_mut()
+            .flat_map(BTreeMap::values_mut)
+            .flatten()
+        {
+            if schema.created_by.as_deref() == Some(principal) {
+                schema.created_by = None;
+                scrubbed += 1;
+            }
+        }
+        Ok(scrubbed)
+    }
+
+    /// Check `metadata` against every schema in effect for the current
+    /// tenant, reporting each violation as `path: message` in the message of
+    /// a validation error
+    pub fn validate(&self, metadata: &Value) -> Result<(), AppError> {
+        let violations: Vec<_> = self
+            .in_effect()?
+            .iter()
+            .flat_map(|(_, schema)| schema.validate(metadata, "metadata"))
+            .map(|violation| violation.to_string())
+            .collect();
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::ValidationError(violations.join("\n")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tenancy::with_tenant;
+
+    fn register(registry: &SchemaRegistry, name: &str, schema: Value, global: bool) -> u32 {
+        let request = RegisterSchemaRequest { schema, global };
+        registry.register(name, request, None).unwrap().version
+    }
+
+    #[test]
+    fn test_values_are_checked_against_the_schema() {
+        let schema = CompiledSchema::compile(&json!({
+            "type": "object",
+            "properties": {
+                "color": { "enum": ["red", "blue"] },
+                "size": { "type": "integer", "minimum": 1 },
+                "tags": { "type": "array", "items": { "type": "string", "maxLength": 3 } }
+            },
+            "required": ["color"],
+            "additionalProperties": false
+        }))
+        .unwrap();
+
+        let valid = json!({ "color": "red", "size": 2, "tags": ["a"] });
+        assert!(schema.validate(&valid, "metadata").is_empty());
+
+        let invalid = json!({ "size": 0, "tags": ["long", 1], "extra": true });
+        let violations: Vec<_> = schema
+            .validate(&invalid, "metadata")
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            violations,
+            [
+                "metadata.color: Is required",
+                "metadata.size: Must be at least 1",
+                "metadata.tags[0]: Must be at most 3 characters",
+                "metadata.tags[1]: Must be of type string",
+                "metadata.extra: Is not an allowed property",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unsupported_schemas_are_refused() {
+        let error = CompiledSchema::compile(&json!({ "oneOf": [] })).unwrap_err();
+        assert_eq!(error, "#: unsupported keyword oneOf");
+        let error = CompiledSchema::compile(&json!({ "properties": { "a": { "type": "text" } } }))
+            .unwrap_err();
+        assert_eq!(error, "#/properties/a/type: must name JSON types");
+        assert!(CompiledSchema::compile(&json!({ "title": "Anything", "$schema": "x" })).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_tenant_schemas_replace_global_ones_of_the_same_name() {
+        let registry = SchemaRegistry::default();
+        register(&registry, "color", json!({ "required": ["color"] }), true);
+        register(&registry, "size", json!({ "required": ["size"] }), true);
+
+        with_tenant("acme".to_string(), async {
+            let version = register(&registry, "color", json!({ "required": ["hue"] }), false);
+            assert_eq!(version, 1);
+            assert_eq!(register(&registry, "color", json!({ "required": ["shade"] }), false), 2);
+
+            let error = registry.validate(&json!({})).unwrap_err();
+            let AppError::ValidationError(message) = error else {
+                panic!("expected a validation error");
+            };
+            assert_eq!(message, "metadata.shade: Is required\nmetadata.size: Is required");
+            assert_eq!(registry.versions("color", false).unwrap().unwrap().len(), 2);
+        })
+        .await;
+
+        // Other tenants only see the global schemas
+        with_tenant("globex".to_string(), async {
+            assert!(registry.validate(&json!({ "color": 1, "size": 1 })).is_ok());
+        })
+        .await;
+    }
+}
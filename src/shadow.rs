@@ -0,0 +1,389 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::{future::Future, sync::Arc};
+use tracing::{debug, warn};
+
+use crate::{
+    analytics::{AnalyticsBucket, AnalyticsRange},
+    context::RequestContext,
+    db::{DatabaseError, DatabaseResult, EventFilter, ItemFilter, ItemRepository},
+    events::OutboxEvent,
+    metrics::track_shadow_read,
+    middleware::observability::random_fraction,
+    models::{CreateItemRequest, Item, ItemField, UpdateItemRequest},
+    pagination::Page,
+    tenancy::with_optional_tenant,
+};
+
+/// Most differences logged for one mismatched read
+const MAX_LOGGED_DIFFERENCES: usize = 10;
+
+/// Mirrors a share of reads to a second backend and compares the answers
+///
+/// Callers are always answered by `primary`. A sampled `get`, `get_by_slug`,
+/// `list`, `list_with_total` or `count` is then sent to `shadow` in the
+/// background, in the caller's tenant, and the two answers are compared: a
+/// mismatch is logged with where the answers differ, and every comparison
+/// is counted. Writes only go to `primary`, so the shadow is expected to be
+/// kept in step by other means, such as a migration or dual writes.
+pub struct ShadowRepository {
+    primary: Arc<dyn ItemRepository>,
+    shadow: Arc<dyn ItemRepository>,
+    /// Share of reads mirrored, between 0 and 1
+    sample_rate: f64,
+    backend: String,
+}
+
+/// How a shadow read compared with the primary one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShadowOutcome {
+    Match,
+    Mismatch,
+    /// The shadow backend failed to answer
+    Error,
+}
+
+impl ShadowOutcome {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Match => "match",
+            Self::Mismatch => "mismatch",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// What a read answered, for comparison
+#[derive(Debug, Clone, PartialEq)]
+enum Answer {
+    Found(Value),
+    NotFound,
+}
+
+impl ShadowRepository {
+    pub fn new(
+        primary: Arc<dyn ItemRepository>,
+        shadow: Arc<dyn ItemRepository>,
+        percent: f64,
+        backend: &str,
+    ) -> Self {
+        Self {
+            primary,
+            shadow,
+            sample_rate: percent / 100.0,
+            backend: backend.to_string(),
+        }
+    }
+
+    /// Answer with `result`, mirroring the read with `mirror` if sampled
+    fn read<T, F>(
+        &self,
+        operation: &'static str,
+        result: DatabaseResult<T>,
+        mirror: impl FnOnce(Arc<dyn ItemRepository>) -> F,
+    ) -> DatabaseResult<T>
+    where
+        T: Serialize + Send + 'static,
+        F: Future<Output = DatabaseResult<T>> + Send + 'static,
+    {
+        let sampled = self.sample_rate >= 1.0
+            || (self.sample_rate > 0.0 && random_fraction() < self.sample_rate);
+        // A primary failure leaves nothing to compare with
+        let Some(expected) = sampled.then(|| answer(&result)).flatten() else {
+            return result;
+        };
+
+        let shadow = mirror(self.shadow.clone());
+        let backend = self.backend.clone();
+        let context = RequestContext::current();
+        tokio::spawn(with_optional_tenant(context.tenant.clone(), async move {
+            let outcome = match shadow.await {
+                Err(DatabaseError::NotFound) => compare(&expected, &Answer::NotFound),
+                Ok(value) => match answer(&Ok(value)) {
+                    Some(actual) => compare(&expected, &actual),
+                    None => (ShadowOutcome::Error, Vec::new()),
+                },
+                Err(e) => {
+                    debug!(operation, error = %e, "Shadow read failed");
+                    (ShadowOutcome::Error, Vec::new())
+                }
+            };
+            if let (ShadowOutcome::Mismatch, differences) = &outcome {
+                warn!(
+                    %backend,
+                    operation,
+                    request_id = context.request_id.as_deref().unwrap_or_default(),
+                    ?differences,
+                    "Shadow read differs from primary"
+                );
+            }
+            track_shadow_read(&backend, operation, outcome.0.as_str());
+        }));
+        result
+    }
+}
+
+/// `result` for comparison, or `None` for a failure other than not found
+fn answer<T: Serialize>(result: &DatabaseResult<T>) -> Option<Answer> {
+    match result {
+        Ok(value) => serde_json::to_value(value).ok().map(Answer::Found),
+        Err(DatabaseError::NotFound) => Some(Answer::NotFound),
+        Err(_) => None,
+    }
+}
+
+fn compare(expected: &Answer, actual: &Answer) -> (ShadowOutcome, Vec<String>) {
+    let differences = match (expected, actual) {
+        (Answer::Found(expected), Answer::Found(actual)) => {
+            let mut differences = Vec::new();
+            differ(expected, actual, &mut String::new(), &mut differences);
+            differences
+        }
+        (Answer::NotFound, Answer::NotFound) => Vec::new(),
+        _ => vec![String::new()],
+    };
+    if differences.is_empty() {
+        (ShadowOutcome::Match, differences)
+    } else {
+        (ShadowOutcome::Mismatch, differences)
+    }
+}
+
+/// Add the JSON pointers below `path` where `expected` and `actual` differ
+fn differ(expected: &Value, actual: &Value, path: &mut String, differences: &mut Vec<String>) {
+    if differences.len() >= MAX_LOGGED_DIFFERENCES || expected == actual {
+        return;
+    }
+    let mut descend = |key: &str, expected: &Value, actual: &Value| {
+        let len = path.len();
+        path.push('/');
+        path.push_str(&key.replace('~', "~0").replace('/', "~1"));
+        differ(expected, actual, path, differences);
+        path.truncate(len);
+    };
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            for key in expected
+                .keys()
+                .chain(actual.keys().filter(|k| !expected.contains_key(*k)))
+            {
+                let missing = Value::Null;
+                let (e, a) = (expected.get(key), actual.get(key));
+                descend(key, e.unwrap_or(&missing), a.unwrap_or(&missing));
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) if expected.len() == actual.len() => {
+            for (index, (e, a)) in expected.iter().zip(actual).enumerate() {
+                descend(&index.to_string(), e, a);
+            }
+        }
+        _ => differences.push(path.clone()),
+    }
+}
+
+#[async_trait]
+impl ItemRepository for ShadowRepository {
+    async fn create(
+        &self,
+        request: CreateItemRequest,
+        owner_id: Option<String>,
+    ) -> DatabaseResult<Item> {
+        self.primary.create(request, owner_id).await
+    }
+
+    async fn get(&self, id: &str) -> DatabaseResult<Item> {
+        let result = self.primary.get(id).await;
+        let id = id.to_string();
+        self.read("get", result, |shadow| async move { shadow.get(&id).await })
+    }
+
+    async fn get_by_slug(&self, slug: &str) -> DatabaseResult<Item> {
+        let result = self.primary.get_by_slug(slug).await;
+        let slug = slug.to_string();
+        self.read("get_by_slug", result, |shadow| async move { shadow.get_by_slug(&slug).await })
+    }
+
+    async fn get_by_external_id(
+        &self,
+        source: Option<&str>,
+        external_id: &str,
+    ) -> DatabaseResult<Item> {
+        let result = self.primary.get_by_external_id(source, external_id).await;
+        let source = source.map(str::to_string);
+        let external_id = external_id.to_string();
+        self.read("get_by_external_id", result, |shadow| async move {
+            shadow
+                .get_by_external_id(source.as_deref(), &external_id)
+                .await
+        })
+    }
+
+    async fn update(
+        &self,
+        id: &str,
+        request: UpdateItemRequest,
+        expected_version: Option<u64>,
+    ) -> DatabaseResult<Item> {
+        self.primary.update(id, request, expected_version).await
+    }
+
+    async fn changed_since(&self, id: &str, version: u64) -> DatabaseResult<Vec<ItemField>> {
+        self.primary.changed_since(id, version).await
+    }
+
+    async fn delete(&self, id: &str) -> DatabaseResult<()> {
+        self.primary.delete(id).await
+    }
+
+    async fn list(&self, filter: &ItemFilter, page: &Page) -> DatabaseResult<Vec<Arc<Item>>> {
+        let result = self.primary.list(filter, page).await;
+        let (filter, page) = (filter.clone(), page.clone());
+        self.read("list", result, |shadow| async move { shadow.list(&filter, &page).await })
+    }
+
+    async fn count(&self, filter: &ItemFilter) -> DatabaseResult<usize> {
+        let result = self.primary.count(filter).await;
+        let filter = filter.clone();
+        self.read("count", result, |shadow| async move { shadow.count(&filter).await })
+    }
+
+    async fn list_with_total(
+        &self,
+        filter: &ItemFilter,
+        page: &Page,
+    ) -> DatabaseResult<(Vec<Arc<Item>>, usize)> {
+        let result = self.primary.list_with_total(filter, page).await;
+        let (filter, page) = (filter.clone(), page.clone());
+        self.read("list_with_total", result, |shadow| async move {
+            shadow.list_with_total(&filter, &page).await
+        })
+    }
+
+    async fn health_check(&self) -> DatabaseResult<()> {
+        self.primary.health_check().await
+    }
+
+    async fn reassign_owner(
+        &self,
+        owner_id: &str,
+        replacement: &str,
+    ) -> DatabaseResult<(usize, usize)> {
+        self.primary.reassign_owner(owner_id, replacement).await
+    }
+
+    async fn restore(&self, item: Item) -> DatabaseResult<Item> {
+        self.primary.restore(item).await
+    }
+
+    async fn provision_tenant(&self, tenant: &str) -> DatabaseResult<()> {
+        self.primary.provision_tenant(tenant).await
+    }
+
+    async fn drop_tenant(&self, tenant: &str) -> DatabaseResult<Vec<String>> {
+        self.primary.drop_tenant(tenant).await
+    }
+
+    async fn pending_events(&self, limit: usize) -> DatabaseResult<Vec<OutboxEvent>> {
+        self.primary.pending_events(limit).await
+    }
+
+    async fn event_history(
+        &self,
+        filter: &EventFilter,
+        after: u64,
+        limit: usize,
+    ) -> DatabaseResult<Vec<OutboxEvent>> {
+        self.primary.event_history(filter, after, limit).await
+    }
+
+    async fn pending_event_count(&self) -> DatabaseResult<usize> {
+        self.primary.pending_event_count().await
+    }
+
+    async fn mark_events_published(&self, sequences: &[u64]) -> DatabaseResult<()> {
+        self.primary.mark_events_published(sequences).await
+    }
+
+    async fn mark_event_failed(&self, sequence: u64, error: &str) -> DatabaseResult<()> {
+        self.primary.mark_event_failed(sequence, error).await
+    }
+
+    async fn purge_published_events(
+        &self,
+        cutoff: DateTime<Utc>,
+        dry_run: bool,
+    ) -> DatabaseResult<usize> {
+        self.primary.purge_published_events(cutoff, dry_run).await
+    }
+
+    async fn event_histogram(
+        &self,
+        filter: &ItemFilter,
+        range: &AnalyticsRange,
+    ) -> DatabaseResult<Vec<AnalyticsBucket>> {
+        self.primary.event_histogram(filter, range).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::InMemoryRepository, metrics::DATABASE_SHADOW_READS};
+    use serde_json::json;
+    use std::time::Duration;
+
+    #[test]
+    fn test_differences_are_json_pointers() {
+        let expected = json!({ "name": "Widget", "tags": ["a", "b"], "meta": { "a/b": 1 } });
+        let actual = json!({ "name": "Gadget", "tags": ["a", "c"], "meta": {}, "extra": true });
+        let (outcome, differences) =
+            compare(&Answer::Found(expected.clone()), &Answer::Found(actual));
+        assert_eq!(outcome, ShadowOutcome::Mismatch);
+        assert_eq!(differences, ["/name", "/tags/1", "/meta/a~1b", "/extra"]);
+
+        let (outcome, _) = compare(&Answer::Found(expected), &Answer::NotFound);
+        assert_eq!(outcome, ShadowOutcome::Mismatch);
+        let (outcome, _) = compare(&Answer::NotFound, &Answer::NotFound);
+        assert_eq!(outcome, ShadowOutcome::Match);
+    }
+
+    #[tokio::test]
+    async fn test_reads_are_compared_with_the_shadow() {
+        let primary = Arc::new(InMemoryRepository::new());
+        let request = CreateItemRequest {
+            name: "Mirrored".to_string(),
+            description: None,
+            metadata: None,
+            custom_fields: Default::default(),
+            status: Default::default(),
+            publish_at: None,
+            external_id: None,
+            source: None,
+        };
+        let item = primary.create(request, None).await.unwrap();
+        let shadow = Arc::new(InMemoryRepository::new());
+        let repo = ShadowRepository::new(primary, shadow.clone(), 100.0, "test-shadow");
+        let compared = |operation: &'static str, outcome: &'static str| async move {
+            for _ in 0..100 {
+                let count = DATABASE_SHADOW_READS
+                    .with_label_values(&["test-shadow", operation, outcome])
+                    .get();
+                if count > 0 {
+                    return true;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            false
+        };
+
+        // The shadow has not caught up: callers still get the primary's answer
+        assert_eq!(repo.get(&item.id).await.unwrap().name, "Mirrored");
+        assert!(compared("get", "mismatch").await);
+
+        shadow.restore(item.clone()).await.unwrap();
+        assert_eq!(repo.count(&ItemFilter::default()).await.unwrap(), 1);
+        assert!(compared("count", "match").await);
+    }
+}
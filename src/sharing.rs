@@ -0,0 +1,339 @@
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::RwLock};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    attachments::Attachment,
+    clock::{system_clock, SharedClock},
+    config::SharingConfig,
+    db::DatabaseError,
+    error::AppError,
+    models::Item,
+    tenancy::current_tenant,
+};
+
+/// Path share tokens are redeemed at
+pub const SHARE_PATH: &str = "/share";
+
+/// Longest an attachment download URL handed out through a share is valid
+const DOWNLOAD_EXPIRY: Duration = Duration::minutes(15);
+
+fn seconds(value: u64) -> Duration {
+    Duration::seconds(i64::try_from(value).unwrap_or(i64::MAX))
+}
+
+/// A link granting anonymous read access to one item until it expires or
+/// is revoked
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Share {
+    pub id: String,
+    pub item_id: String,
+    /// Whether the item's attachments can be downloaded through the share
+    pub include_attachments: bool,
+    pub created_at: DateTime<Utc>,
+    /// Subject of the caller that created the share
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_by: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<DateTime<Utc>>,
+    /// Times the share has been opened
+    pub access_count: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_accessed_at: Option<DateTime<Utc>>,
+    /// Tenant the item is read under when the share is opened
+    #[serde(skip)]
+    tenant_id: Option<String>,
+}
+
+impl Share {
+    pub fn tenant_id(&self) -> Option<&str> {
+        self.tenant_id.as_deref()
+    }
+}
+
+/// Request to share an item
+#[derive(Debug, Clone, Default, Deserialize, ToSchema)]
+pub struct CreateShareRequest {
+    /// Lifetime of the share; defaults to `SHARE_DEFAULT_EXPIRY_SECONDS`
+    pub expires_in_seconds: Option<u64>,
+    /// Let the share download the item's attachments too
+    #[serde(default)]
+    pub include_attachments: bool,
+}
+
+/// A new share and the token that opens it, which is only shown once
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreatedShare {
+    pub share: Share,
+    pub token: String,
+    /// Path anyone can read the item at without authenticating
+    pub url: String,
+}
+
+/// An attachment as seen through a share
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SharedAttachment {
+    pub file_name: String,
+    pub content_type: String,
+    pub size: u64,
+    /// Presigned URL valid for a few minutes
+    pub download_url: String,
+}
+
+/// The item a share grants access to
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SharedItem {
+    pub item: Item,
+    /// Present when the share includes attachments; those still being
+    /// scanned or found infected are left out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attachments: Option<Vec<SharedAttachment>>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl SharedAttachment {
+    pub fn new(attachment: Attachment, download_url: String) -> Self {
+        Self {
+            file_name: attachment.file_name,
+            content_type: attachment.content_type,
+            size: attachment.size,
+            download_url,
+        }
+    }
+}
+
+/// Claims of a share token, an HS256 JWT
+#[derive(Debug, Serialize, Deserialize)]
+struct ShareClaims {
+    /// Share ID
+    sid: String,
+    exp: i64,
+}
+
+/// Signed, expiring share tokens and the shares they open
+///
+/// Tokens carry their share's ID and expiry under a signature, so forged or
+/// expired tokens are turned away without a lookup; the share itself records
+/// revocation and how often it was opened. Shares are held in memory and
+/// forgotten once they expire.
+pub struct ShareLinks {
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    default_expiry: Duration,
+    max_expiry: u64,
+    clock: SharedClock,
+    shares: RwLock<HashMap<String, Share>>,
+}
+
+impl ShareLinks {
+    /// Shares signed with the configured key; `None` when sharing is off
+    pub fn from_config(config: &SharingConfig) -> Option<Self> {
+        let secret = config.signing_key.as_deref()?;
+        Some(Self {
+            encoding_key: EncodingKey::from_secret(secret.as_bytes()),
+            decoding_key: DecodingKey::from_secret(secret.as_bytes()),
+            default_expiry: seconds(config.default_expiry_seconds),
+            max_expiry: config.max_expiry_seconds,
+            clock: system_clock(),
+            shares: RwLock::default(),
+        })
+    }
+
+    /// Date shares with `clock` instead of the system clock
+    #[must_use]
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Share the item `item_id`, read under the current tenant
+    pub fn create(
+        &self,
+        item_id: &str,
+        request: CreateShareRequest,
+        created_by: Option<String>,
+    ) -> Result<CreatedShare, AppError> {
+        let expiry = match request.expires_in_seconds {
+            None => self.default_expiry,
+            Some(expiry) if (1..=self.max_expiry).contains(&expiry) => seconds(expiry),
+            Some(_) => {
+                return Err(AppError::ValidationError(format!(
+                    "expires_in_seconds: must be between 1 and {}",
+                    self.max_expiry
+                )))
+            }
+        };
+        let now = self.clock.now();
+        let share = Share {
+            id: Uuid::new_v4().to_string(),
+            item_id: item_id.to_string(),
+            include_attachments: request.include_attachments,
+            created_at: now,
+            created_by,
+            expires_at: now + expiry,
+            revoked_at: None,
+            access_count: 0,
+            last_accessed_at: None,
+            tenant_id: current_tenant(),
+        };
+        let claims = ShareClaims {
+            sid: share.id.clone(),
+            exp: share.expires_at.timestamp(),
+        };
+        let token = encode(&Header::new(Algorithm::HS256), &claims, &self.encoding_key)
+            .map_err(|e| AppError::InternalServerError(format!("Failed to sign share: {e}")))?;
+
+        let mut shares = self.shares.write().map_err(|_| DatabaseError::LockError)?;
+        shares.retain(|_, share| share.expires_at > now);
+        shares.insert(share.id.clone(), share.clone());
+        Ok(CreatedShare {
+            share,
+            url: format!("{SHARE_PATH}/{token}"),
+            token,
+        })
+    }
+
+    /// Unexpired shares of `item_id`, newest first
+    pub fn list(&self, item_id: &str) -> Result<Vec<Share>, AppError> {
+        let now = self.clock.now();
+        let mut shares: Vec<_> = self
+            .shares
+            .read()
+            .map_err(|_| DatabaseError::LockError)?
+            .values()
+            .filter(|share| share.item_id == item_id && share.expires_at > now)
+            .cloned()
+            .collect();
+        shares.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        Ok(shares)
+    }
+
+    /// Stop the share `share_id` of `item_id` from opening
+    pub fn revoke(&self, item_id: &str, share_id: &str) -> Result<Share, AppError> {
+        let now = self.clock.now();
+        let mut shares = self.shares.write().map_err(|_| DatabaseError::LockError)?;
+        let share = shares
+            .get_mut(share_id)
+            .filter(|share| share.item_id == item_id && share.expires_at > now)
+            .ok_or_else(|| AppError::NotFound(format!("Share {share_id} not found")))?;
+        share.revoked_at.get_or_insert(now);
+        Ok(share.clone())
+    }
+
+    /// The share `token` opens, counting the access
+    pub fn open(&self, token: &str) -> Result<Share, AppError> {
+        let invalid = || AppError::NotFound("Share link is invalid".to_string());
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.required_spec_claims.clear();
+        // Checked against the service clock below
+        validation.validate_exp = false;
+        let claims = decode::<ShareClaims>(token, &self.decoding_key, &validation)
+            .map_err(|_| invalid())?
+            .claims;
+
+        let now = self.clock.now();
+        let expired = || AppError::NotFound("Share link has expired".to_string());
+        if claims.exp <= now.timestamp() {
+            return Err(expired());
+        }
+        let mut shares = self.shares.write().map_err(|_| DatabaseError::LockError)?;
+        let share = shares.get_mut(&claims.sid).ok_or_else(invalid)?;
+        if share.expires_at <= now {
+            return Err(expired());
+        }
+        if share.revoked_at.is_some() {
+            return Err(AppError::NotFound("Share link has been revoked".to_string()));
+        }
+        share.access_count += 1;
+        share.last_accessed_at = Some(now);
+        Ok(share.clone())
+    }
+
+    /// Clear `principal` as the creator of shares, in every tenant; returns
+    /// how many
+    pub fn forget_principal(&self, principal: &str) -> Result<usize, AppError> {
+        let mut shares = self.shares.write().map_err(|_| DatabaseError::LockError)?;
+        let mut scrubbed = 0;
+        for share in shares.values_mut() {
+            if share.created_by.as_deref() == Some(principal) {
+                share.created_by = None;
+                scrubbed += 1;
+            }
+        }
+        Ok(scrubbed)
+    }
+
+    /// How long attachment download URLs for `share` should be valid
+    pub fn download_expiry(&self, share: &Share) -> Duration {
+        (share.expires_at - self.clock.now()).min(DOWNLOAD_EXPIRY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use std::sync::Arc;
+
+    fn shares(clock: Arc<ManualClock>) -> ShareLinks {
+        let config = SharingConfig {
+            signing_key: Some("secret".to_string()),
+            ..SharingConfig::default()
+        };
+        ShareLinks::from_config(&config).unwrap().with_clock(clock)
+    }
+
+    #[test]
+    fn test_tokens_open_their_share_until_it_expires() {
+        let clock = Arc::new(ManualClock::default());
+        let shares = shares(clock.clone());
+        let request = CreateShareRequest {
+            expires_in_seconds: Some(60),
+            ..CreateShareRequest::default()
+        };
+        let created = shares.create("item-1", request, None).unwrap();
+        assert_eq!(created.url, format!("/share/{}", created.token));
+
+        assert_eq!(shares.open(&created.token).unwrap().access_count, 1);
+        assert_eq!(shares.open(&created.token).unwrap().access_count, 2);
+        assert_eq!(shares.list("item-1").unwrap()[0].access_count, 2);
+
+        // Signed with another key
+        let other = SharingConfig {
+            signing_key: Some("other".to_string()),
+            ..SharingConfig::default()
+        };
+        let forged = ShareLinks::from_config(&other)
+            .unwrap()
+            .create("item-1", CreateShareRequest::default(), None)
+            .unwrap();
+        assert!(shares.open(&forged.token).is_err());
+        assert!(shares.open("not-a-token").is_err());
+
+        clock.advance(std::time::Duration::from_secs(60));
+        assert!(shares.open(&created.token).is_err());
+        assert!(shares.list("item-1").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_revoked_shares_no_longer_open() {
+        let shares = shares(Arc::new(ManualClock::default()));
+        let created = shares
+            .create("item-1", CreateShareRequest::default(), None)
+            .unwrap();
+        assert!(shares.revoke("item-2", &created.share.id).is_err());
+        let revoked = shares.revoke("item-1", &created.share.id).unwrap();
+        assert!(revoked.revoked_at.is_some());
+        assert!(shares.open(&created.token).is_err());
+
+        let request = CreateShareRequest {
+            expires_in_seconds: Some(365 * 24 * 60 * 60),
+            ..CreateShareRequest::default()
+        };
+        assert!(shares.create("item-1", request, None).is_err());
+    }
+}
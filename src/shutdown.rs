@@ -0,0 +1,95 @@
+use std::{future::Future, pin::Pin, time::Duration};
+use tokio::{task::JoinHandle, time::Instant};
+use tracing::{info, warn};
+
+type Hook = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Stops subsystems in order when the process shuts down
+///
+/// Subsystems register a shutdown hook as they start, after whatever they
+/// depend on, and hooks run in reverse order: the HTTP server, registered
+/// last, stops taking requests before the outbox dispatcher it feeds makes
+/// its final pass. Hooks share one timeout; a hook still running when it
+/// expires is abandoned, and the rest are only polled once, which is enough
+/// for hooks that just abort a task.
+#[derive(Default)]
+pub struct ShutdownCoordinator {
+    hooks: Vec<(&'static str, Hook)>,
+}
+
+impl ShutdownCoordinator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `hook` at shutdown, before the hooks registered earlier
+    pub fn register(
+        &mut self,
+        name: &'static str,
+        hook: impl Future<Output = ()> + Send + 'static,
+    ) {
+        self.hooks.push((name, Box::pin(hook)));
+    }
+
+    /// Abort a background task at shutdown
+    pub fn abort_on_shutdown(&mut self, name: &'static str, task: JoinHandle<()>) {
+        self.register(name, async move { task.abort() });
+    }
+
+    /// Run every hook within `timeout`, returning the names of those that did
+    /// not finish in time
+    pub async fn shutdown(self, timeout: Duration) -> Vec<&'static str> {
+        let deadline = Instant::now() + timeout;
+        let mut unfinished = Vec::new();
+        for (name, hook) in self.hooks.into_iter().rev() {
+            let started = Instant::now();
+            match tokio::time::timeout_at(deadline, hook).await {
+                Ok(()) => {
+                    info!(subsystem = name, elapsed = ?started.elapsed(), "Subsystem shut down")
+                }
+                Err(_) => {
+                    warn!(subsystem = name, "Subsystem did not shut down within the timeout");
+                    unfinished.push(name);
+                }
+            }
+        }
+        unfinished
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[tokio::test]
+    async fn test_hooks_run_in_reverse_registration_order() {
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut coordinator = ShutdownCoordinator::new();
+        for name in ["database", "dispatcher", "server"] {
+            let order = order.clone();
+            coordinator.register(name, async move { order.lock().unwrap().push(name) });
+        }
+
+        let unfinished = coordinator.shutdown(Duration::from_secs(1)).await;
+        assert!(unfinished.is_empty());
+        assert_eq!(*order.lock().unwrap(), vec!["server", "dispatcher", "database"]);
+    }
+
+    #[tokio::test]
+    async fn test_slow_hook_is_abandoned_at_the_timeout() {
+        let task = tokio::spawn(std::future::pending::<()>());
+        let aborted = task.abort_handle();
+        let mut coordinator = ShutdownCoordinator::new();
+        coordinator.abort_on_shutdown("sampler", task);
+        coordinator.register("server", std::future::pending());
+
+        let started = Instant::now();
+        let unfinished = coordinator.shutdown(Duration::from_millis(50)).await;
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(unfinished, vec!["server"]);
+        // Hooks after the slow one still get to abort their tasks
+        tokio::task::yield_now().await;
+        assert!(aborted.is_finished());
+    }
+}
@@ -0,0 +1,136 @@
+use std::{collections::BTreeMap, fmt, future::Future, sync::Mutex, time::Duration};
+use tracing::warn;
+
+use crate::{http_client::current_request_id, metrics::track_slow_query};
+
+tokio::task_local! {
+    /// Repository calls made by the request being handled
+    static QUERIES: Mutex<QueryBreakdown>;
+}
+
+/// Calls and time spent per repository operation while handling a request
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct QueryBreakdown {
+    /// `(calls, total duration)` keyed by `repository.operation`
+    operations: BTreeMap<String, (u32, Duration)>,
+}
+
+impl QueryBreakdown {
+    fn add(&mut self, operation: &str, repository: &str, duration: Duration) {
+        let entry = self
+            .operations
+            .entry(format!("{repository}.{operation}"))
+            .or_default();
+        entry.0 += 1;
+        entry.1 += duration;
+    }
+
+    fn merge(&mut self, other: &Self) {
+        for (operation, (calls, duration)) in &other.operations {
+            let entry = self.operations.entry(operation.clone()).or_default();
+            entry.0 += calls;
+            entry.1 += *duration;
+        }
+    }
+
+    /// Number of repository calls
+    pub fn calls(&self) -> u32 {
+        self.operations.values().map(|(calls, _)| calls).sum()
+    }
+
+    /// Time spent in repository calls
+    pub fn total(&self) -> Duration {
+        self.operations
+            .values()
+            .map(|(_, duration)| *duration)
+            .sum()
+    }
+}
+
+/// Formats as `items.get=2/14ms, items.count=1/3ms` (calls/total time)
+impl fmt::Display for QueryBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (operation, (calls, duration))) in self.operations.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{operation}={calls}/{}ms", duration.as_millis())?;
+        }
+        Ok(())
+    }
+}
+
+/// Run `future`, collecting the repository calls it makes
+///
+/// Only calls made on the same task are collected; tasks spawned by `future`
+/// do not inherit the scope. Scopes nest: calls collected by an inner scope
+/// are also counted by the scope around it.
+pub async fn with_query_breakdown<F: Future>(future: F) -> (F::Output, QueryBreakdown) {
+    let (output, breakdown) = QUERIES
+        .scope(Mutex::default(), async {
+            let output = future.await;
+            let breakdown = QUERIES.with(|queries| std::mem::take(&mut *lock(queries)));
+            (output, breakdown)
+        })
+        .await;
+    let _ = QUERIES.try_with(|queries| lock(queries).merge(&breakdown));
+    (output, breakdown)
+}
+
+/// Record a repository call in the current request's breakdown, and log it if
+/// it took longer than `threshold`
+pub fn record_query(
+    operation: &str,
+    repository: &str,
+    duration: Duration,
+    threshold: Option<Duration>,
+) {
+    let _ = QUERIES.try_with(|queries| lock(queries).add(operation, repository, duration));
+
+    if threshold.is_some_and(|threshold| duration > threshold) {
+        track_slow_query(operation, repository);
+        warn!(
+            request_id = current_request_id().as_deref().unwrap_or("-"),
+            operation,
+            repository,
+            duration_ms = duration.as_millis() as u64,
+            "Slow repository call"
+        );
+    }
+}
+
+fn lock(queries: &Mutex<QueryBreakdown>) -> std::sync::MutexGuard<'_, QueryBreakdown> {
+    // A panic while holding the lock cannot leave the breakdown inconsistent
+    queries.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_breakdown_collects_calls_in_scope() {
+        let ms = Duration::from_millis;
+        let ((), breakdown) = with_query_breakdown(async {
+            record_query("get", "items", ms(10), None);
+            record_query("get", "items", ms(4), None);
+            record_query("count", "items", ms(3), Some(ms(1)));
+        })
+        .await;
+
+        assert_eq!(breakdown.calls(), 3);
+        assert_eq!(breakdown.total(), ms(17));
+        assert_eq!(breakdown.to_string(), "items.count=1/3ms, items.get=2/14ms");
+
+        // Calls outside a scope are only checked against the threshold
+        record_query("get", "items", ms(10), None);
+
+        let ((_, inner), outer) = with_query_breakdown(async {
+            record_query("count", "items", ms(2), None);
+            with_query_breakdown(async { record_query("get", "items", ms(5), None) }).await
+        })
+        .await;
+        assert_eq!(inner.calls(), 1);
+        assert_eq!(outer.calls(), 2);
+    }
+}
@@ -0,0 +1,77 @@
+/// Maximum length of a generated slug (excluding any collision suffix)
+pub const MAX_SLUG_LENGTH: usize = 100;
+
+/// Slug used when a name contains no URL-safe characters
+const FALLBACK_SLUG: &str = "item";
+
+/// Generate a URL-safe slug from an item name
+///
+/// Lowercases ASCII letters and digits, collapses every other run of characters
+/// into a single hyphen, and trims leading/trailing hyphens.
+pub fn slugify(name: &str) -> String {
+    let mut slug = String::with_capacity(name.len().min(MAX_SLUG_LENGTH));
+    let mut pending_hyphen = false;
+
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            if pending_hyphen && !slug.is_empty() {
+                slug.push('-');
+            }
+            pending_hyphen = false;
+            slug.push(c.to_ascii_lowercase());
+        } else {
+            pending_hyphen = true;
+        }
+
+        if slug.len() >= MAX_SLUG_LENGTH {
+            break;
+        }
+    }
+
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        FALLBACK_SLUG.to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+/// Resolve slug collisions by appending a numeric suffix (`-2`, `-3`, ...)
+///
+/// `is_taken` should return true when a candidate is already used by another item.
+pub fn unique_slug(base: &str, is_taken: impl Fn(&str) -> bool) -> String {
+    if !is_taken(base) {
+        return base.to_string();
+    }
+
+    (2..)
+        .map(|n| format!("{base}-{n}"))
+        .find(|candidate| !is_taken(candidate))
+        .expect("unbounded suffix search always yields a candidate")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Hello World"), "hello-world");
+        assert_eq!(slugify("  Rust & Axum: 2024!  "), "rust-axum-2024");
+        assert_eq!(slugify("already-a-slug"), "already-a-slug");
+        assert_eq!(slugify("***"), "item");
+    }
+
+    #[test]
+    fn test_slugify_truncates() {
+        let long_name = "a".repeat(300);
+        assert_eq!(slugify(&long_name).len(), MAX_SLUG_LENGTH);
+    }
+
+    #[test]
+    fn test_unique_slug() {
+        let taken = ["widget", "widget-2"];
+        assert_eq!(unique_slug("gadget", |s| taken.contains(&s)), "gadget");
+        assert_eq!(unique_slug("widget", |s| taken.contains(&s)), "widget-3");
+    }
+}
@@ -0,0 +1,647 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::{
+    db::{DatabaseResult, ItemFilter, ItemRepository},
+    error::AppError,
+    pagination::{Page, MAX_PAGE_LIMIT},
+    query::{Comparison, Condition, Decimal, FieldValue, FilterField, QueryField, Sort, ValueKind},
+};
+
+/// The one table read-only queries can read
+const TABLE: &str = "items";
+
+/// Why a read-only query was refused
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SqlError {
+    #[error("only SELECT statements are allowed, not `{0}`")]
+    Statement(String),
+    #[error("unknown table `{0}`; only `items` can be queried")]
+    Table(String),
+    #[error("unknown column `{0}`")]
+    Column(String),
+    #[error("invalid value `{value}` for column `{column}`")]
+    Value { column: String, value: String },
+    #[error("no value given for parameter ${0}")]
+    Parameter(usize),
+    #[error("{0} is not supported")]
+    Unsupported(String),
+    #[error("invalid query near `{0}`")]
+    Syntax(String),
+}
+
+impl From<SqlError> for AppError {
+    fn from(error: SqlError) -> Self {
+        AppError::BadRequest(error.to_string())
+    }
+}
+
+/// What a query returns for each row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Projection {
+    /// `SELECT *`: every item field
+    All,
+    /// `SELECT COUNT(*)`: one row with the number of matching items
+    Count,
+    Columns(Vec<FilterField>),
+}
+
+/// A parsed read-only query over items
+///
+/// Statements are not sent to a database: they are translated into the
+/// filters and sort orders list requests use, so they run against whichever
+/// backend is configured and cannot write, join, or read other tables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqlQuery {
+    pub projection: Projection,
+    pub conditions: Vec<Condition>,
+    pub order: Vec<Sort>,
+    pub limit: Option<usize>,
+}
+
+/// A read-only query over items, with values for its `$n` parameters
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[schema(example = json!({
+    "sql": "SELECT id, name, updated_at FROM items WHERE owner_id = $1 ORDER BY updated_at DESC LIMIT 20",
+    "params": ["user-123"]
+}))]
+pub struct QueryRequest {
+    #[validate(length(min = 1, max = 10000))]
+    pub sql: String,
+    /// Values bound to `$1`, `$2`, ... in order
+    #[serde(default)]
+    #[schema(value_type = Vec<Object>)]
+    pub params: Vec<Value>,
+}
+
+/// Rows a query returned
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "columns": ["id", "name"],
+    "rows": [["a1b2c3d4", "Widget"]],
+    "row_count": 1,
+    "truncated": false
+}))]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    /// One array of values per row, in the order of `columns`
+    #[schema(value_type = Vec<Vec<Object>>)]
+    pub rows: Vec<Vec<Value>>,
+    pub row_count: usize,
+    /// Whether rows were left out because the query reached the row limit
+    pub truncated: bool,
+}
+
+/// Parse `sql`, a `SELECT` from `items`, binding `$1`, `$2`, ... to `params`
+///
+/// Supported: a column list, `*` or `COUNT(*)`; `WHERE` conditions joined by
+/// `AND`, comparing a column with `=`, `<>`, `<`, `<=`, `>`, `>=`, `LIKE`
+/// (with `%` only at either end) or `IS [NOT] NULL`; `ORDER BY` item columns;
+/// and `LIMIT`. Custom fields are named `custom_fields.<name>`.
+pub fn parse(sql: &str, params: &[Value]) -> Result<SqlQuery, SqlError> {
+    let mut parser = Parser {
+        tokens: tokenize(sql)?,
+        position: 0,
+        params,
+    };
+    parser.query()
+}
+
+/// Run `query`, returning at most `max_rows` rows
+pub async fn run(
+    repo: &dyn ItemRepository,
+    query: &SqlQuery,
+    max_rows: usize,
+) -> DatabaseResult<QueryResult> {
+    let filter = ItemFilter::default().with_conditions(query.conditions.clone());
+    let columns: Vec<FilterField> = match &query.projection {
+        Projection::Count => {
+            let count = repo.count(&filter).await?;
+            return Ok(QueryResult {
+                columns: vec!["count".to_string()],
+                rows: vec![vec![Value::from(count)]],
+                row_count: 1,
+                truncated: false,
+            });
+        }
+        Projection::All => QueryField::ALL.into_iter().map(FilterField::Item).collect(),
+        Projection::Columns(columns) => columns.clone(),
+    };
+
+    let wanted = query.limit.unwrap_or(usize::MAX).min(max_rows);
+    // One row past the limit tells whether the limit cut the result short
+    let fetch = if query.limit.is_some_and(|limit| limit <= max_rows) {
+        wanted
+    } else {
+        wanted + 1
+    };
+    let mut items = Vec::new();
+    while items.len() < fetch {
+        let chunk = (fetch - items.len()).min(MAX_PAGE_LIMIT);
+        let page = Page::offset(chunk, items.len())
+            .and_then(|page| page.ordered_by(query.order.clone()))
+            .map_err(|e| crate::db::DatabaseError::QueryError(e.to_string()))?;
+        let listed = repo.list(&filter, &page).await?;
+        let done = listed.len() < chunk;
+        items.extend(listed);
+        if done {
+            break;
+        }
+    }
+    let truncated = items.len() > wanted;
+    items.truncate(wanted);
+
+    let rows: Vec<Vec<Value>> = items
+        .iter()
+        .map(|item| {
+            columns
+                .iter()
+                .map(|column| json(column.value(item)))
+                .collect()
+        })
+        .collect();
+    Ok(QueryResult {
+        columns: columns.iter().map(column_name).collect(),
+        row_count: rows.len(),
+        rows,
+        truncated,
+    })
+}
+
+fn column_name(column: &FilterField) -> String {
+    match column {
+        FilterField::Item(field) => field.name().to_string(),
+        FilterField::Custom(name) => format!("custom_fields.{name}"),
+    }
+}
+
+fn json(value: FieldValue) -> Value {
+    match value {
+        FieldValue::Null => Value::Null,
+        FieldValue::Text(text) => Value::String(text),
+        FieldValue::Time(time) => Value::String(time.to_rfc3339_opts(SecondsFormat::Millis, true)),
+        FieldValue::Number(number) => Value::from(number),
+        FieldValue::Bool(flag) => Value::Bool(flag),
+        FieldValue::Decimal(Decimal(number)) => Value::from(number),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Text(String),
+    Number(String),
+    Parameter(usize),
+    Symbol(&'static str),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Word(word) | Self::Number(word) => f.write_str(word),
+            Self::Text(text) => write!(f, "'{}'", text.replace('\'', "''")),
+            Self::Parameter(index) => write!(f, "${index}"),
+            Self::Symbol(symbol) => f.write_str(symbol),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, SqlError> {
+    const SYMBOLS: [&str; 12] = [
+        "<>", "!=", "<=", ">=", "=", "<", ">", "*", ",", "(", ")", ";",
+    ];
+    let mut tokens = Vec::new();
+    let mut rest = input;
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+        } else if rest.starts_with("--") || rest.starts_with("/*") {
+            return Err(SqlError::Unsupported("comments".to_string()));
+        } else if c == '\'' {
+            // Quotes inside strings are doubled
+            let mut text = String::new();
+            let mut chars = rest[1..].char_indices().peekable();
+            let end = loop {
+                match chars.next() {
+                    Some((_, '\'')) if chars.peek().is_some_and(|(_, c)| *c == '\'') => {
+                        chars.next();
+                        text.push('\'');
+                    }
+                    Some((i, '\'')) => break i + 2,
+                    Some((_, c)) => text.push(c),
+                    None => return Err(SqlError::Syntax(format!("'{text}"))),
+                }
+            };
+            tokens.push(Token::Text(text));
+            rest = &rest[end..];
+        } else if let Some(symbol) = SYMBOLS.iter().find(|symbol| rest.starts_with(**symbol)) {
+            tokens.push(Token::Symbol(symbol));
+            rest = &rest[symbol.len()..];
+        } else {
+            let end = rest
+                .find(|c: char| c.is_whitespace() || "'<>!=*,();".contains(c))
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            let token = if let Some(index) = word.strip_prefix('$') {
+                match index.parse() {
+                    Ok(index) if index > 0 => Token::Parameter(index),
+                    _ => return Err(SqlError::Syntax(word.to_string())),
+                }
+            } else if word.starts_with(|c: char| c.is_ascii_digit() || c == '-') {
+                Token::Number(word.to_string())
+            } else if word
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+            {
+                Token::Word(word.to_string())
+            } else {
+                return Err(SqlError::Syntax(word.to_string()));
+            };
+            tokens.push(token);
+            rest = &rest[end..];
+        }
+    }
+    Ok(tokens)
+}
+
+/// A literal or bound parameter, before it is typed by its column
+enum Literal {
+    Null,
+    Bool(bool),
+    Text(String),
+    Number(String),
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    params: &'a [Value],
+}
+
+impl Parser<'_> {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn keyword(&mut self, keyword: &str) -> Result<(), SqlError> {
+        match self.next() {
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword) => Ok(()),
+            Some(token) => Err(SqlError::Syntax(token.to_string())),
+            None => Err(SqlError::Syntax(format!("end of query; expected {keyword}"))),
+        }
+    }
+
+    fn symbol(&mut self, symbol: &str) -> Result<(), SqlError> {
+        match self.next() {
+            Some(Token::Symbol(found)) if found == symbol => Ok(()),
+            Some(token) => Err(SqlError::Syntax(token.to_string())),
+            None => Err(SqlError::Syntax(format!("end of query; expected {symbol}"))),
+        }
+    }
+
+    fn query(&mut self) -> Result<SqlQuery, SqlError> {
+        match self.next() {
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case("select") => {}
+            Some(token) => return Err(SqlError::Statement(token.to_string().to_uppercase())),
+            None => return Err(SqlError::Syntax("empty query".to_string())),
+        }
+        let projection = self.projection()?;
+
+        self.keyword("from")?;
+        match self.next() {
+            Some(Token::Word(table)) if table.eq_ignore_ascii_case(TABLE) => {}
+            Some(token) => return Err(SqlError::Table(token.to_string())),
+            None => return Err(SqlError::Syntax("end of query; expected a table".to_string())),
+        }
+
+        let mut conditions = Vec::new();
+        if self.peek_keyword("where") {
+            self.next();
+            loop {
+                conditions.push(self.condition()?);
+                if self.peek_keyword("or") {
+                    return Err(SqlError::Unsupported("OR".to_string()));
+                }
+                if !self.peek_keyword("and") {
+                    break;
+                }
+                self.next();
+            }
+        }
+
+        let mut order = Vec::new();
+        if self.peek_keyword("order") {
+            self.next();
+            self.keyword("by")?;
+            loop {
+                let name = self.word()?;
+                let field =
+                    QueryField::parse(&name.to_ascii_lowercase()).ok_or(SqlError::Column(name))?;
+                let descending = if self.peek_keyword("desc") {
+                    self.next();
+                    true
+                } else {
+                    if self.peek_keyword("asc") {
+                        self.next();
+                    }
+                    false
+                };
+                order.push(Sort { field, descending });
+                if self.peek() != Some(&Token::Symbol(",")) {
+                    break;
+                }
+                self.next();
+            }
+        }
+
+        let mut limit = None;
+        if self.peek_keyword("limit") {
+            self.next();
+            limit = match self.next() {
+                Some(Token::Number(number)) => Some(
+                    number
+                        .parse()
+                        .map_err(|_| SqlError::Syntax(number.clone()))?,
+                ),
+                Some(token) => return Err(SqlError::Syntax(token.to_string())),
+                None => return Err(SqlError::Syntax("end of query; expected a limit".to_string())),
+            };
+        }
+
+        if self.peek() == Some(&Token::Symbol(";")) {
+            self.next();
+        }
+        match self.next() {
+            None => Ok(SqlQuery {
+                projection,
+                conditions,
+                order,
+                limit,
+            }),
+            Some(Token::Word(word))
+                if ["join", "group", "having", "union", "offset", "for"]
+                    .iter()
+                    .any(|clause| word.eq_ignore_ascii_case(clause)) =>
+            {
+                Err(SqlError::Unsupported(word.to_uppercase()))
+            }
+            Some(token) => Err(SqlError::Syntax(token.to_string())),
+        }
+    }
+
+    fn word(&mut self) -> Result<String, SqlError> {
+        match self.next() {
+            Some(Token::Word(word)) => Ok(word),
+            Some(token) => Err(SqlError::Syntax(token.to_string())),
+            None => Err(SqlError::Syntax("end of query; expected a column".to_string())),
+        }
+    }
+
+    fn column(&mut self) -> Result<FilterField, SqlError> {
+        let name = self.word()?;
+        match name.strip_prefix("custom_fields.") {
+            Some(custom) if !custom.is_empty() => Ok(FilterField::Custom(custom.to_string())),
+            _ => QueryField::parse(&name.to_ascii_lowercase())
+                .map(FilterField::Item)
+                .ok_or(SqlError::Column(name)),
+        }
+    }
+
+    fn projection(&mut self) -> Result<Projection, SqlError> {
+        if self.peek() == Some(&Token::Symbol("*")) {
+            self.next();
+            return Ok(Projection::All);
+        }
+        if self.peek_keyword("count") {
+            self.next();
+            self.symbol("(")?;
+            self.symbol("*")?;
+            self.symbol(")")?;
+            return Ok(Projection::Count);
+        }
+        let mut columns = vec![self.column()?];
+        while self.peek() == Some(&Token::Symbol(",")) {
+            self.next();
+            columns.push(self.column()?);
+        }
+        Ok(Projection::Columns(columns))
+    }
+
+    fn condition(&mut self) -> Result<Condition, SqlError> {
+        let field = self.column()?;
+        if self.peek_keyword("is") {
+            self.next();
+            let comparison = if self.peek_keyword("not") {
+                self.next();
+                Comparison::Ne
+            } else {
+                Comparison::Eq
+            };
+            self.keyword("null")?;
+            return Ok(Condition {
+                field,
+                comparison,
+                value: FieldValue::Null,
+            });
+        }
+        if self.peek_keyword("like") {
+            self.next();
+            let Literal::Text(pattern) = self.literal()? else {
+                return Err(SqlError::Unsupported("LIKE with a value other than text".to_string()));
+            };
+            return like(field, &pattern);
+        }
+        let comparison = match self.next() {
+            Some(Token::Symbol("=")) => Comparison::Eq,
+            Some(Token::Symbol("<>" | "!=")) => Comparison::Ne,
+            Some(Token::Symbol("<")) => Comparison::Lt,
+            Some(Token::Symbol("<=")) => Comparison::Le,
+            Some(Token::Symbol(">")) => Comparison::Gt,
+            Some(Token::Symbol(">=")) => Comparison::Ge,
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case("in") => {
+                return Err(SqlError::Unsupported("IN".to_string()));
+            }
+            Some(token) => return Err(SqlError::Syntax(token.to_string())),
+            None => return Err(SqlError::Syntax("end of query; expected an operator".to_string())),
+        };
+        let literal = self.literal()?;
+        let value = typed(&field, literal)?;
+        Ok(Condition {
+            field,
+            comparison,
+            value,
+        })
+    }
+
+    fn literal(&mut self) -> Result<Literal, SqlError> {
+        match self.next() {
+            Some(Token::Text(text)) => Ok(Literal::Text(text)),
+            Some(Token::Number(number)) => Ok(Literal::Number(number)),
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case("null") => Ok(Literal::Null),
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case("true") => Ok(Literal::Bool(true)),
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case("false") => {
+                Ok(Literal::Bool(false))
+            }
+            Some(Token::Parameter(index)) => match self.params.get(index - 1) {
+                None => Err(SqlError::Parameter(index)),
+                Some(Value::Null) => Ok(Literal::Null),
+                Some(Value::Bool(flag)) => Ok(Literal::Bool(*flag)),
+                Some(Value::String(text)) => Ok(Literal::Text(text.clone())),
+                Some(Value::Number(number)) => Ok(Literal::Number(number.to_string())),
+                Some(value) => Err(SqlError::Value {
+                    column: format!("${index}"),
+                    value: value.to_string(),
+                }),
+            },
+            Some(token) => Err(SqlError::Syntax(token.to_string())),
+            None => Err(SqlError::Syntax("end of query; expected a value".to_string())),
+        }
+    }
+}
+
+/// `field LIKE pattern`, for patterns with `%` at either end or neither
+fn like(field: FilterField, pattern: &str) -> Result<Condition, SqlError> {
+    let (starts, inner) = match pattern.strip_prefix('%') {
+        Some(inner) => (false, inner),
+        None => (true, pattern),
+    };
+    let (ends, inner) = match inner.strip_suffix('%') {
+        Some(inner) => (false, inner),
+        None => (true, inner),
+    };
+    if inner.contains('%') {
+        return Err(SqlError::Unsupported(format!("LIKE pattern '{pattern}'")));
+    }
+    let comparison = match (starts, ends) {
+        (true, true) => Comparison::Eq,
+        (true, false) => Comparison::StartsWith,
+        (false, true) => Comparison::EndsWith,
+        (false, false) => Comparison::Contains,
+    };
+    Ok(Condition {
+        field,
+        comparison,
+        value: FieldValue::Text(inner.to_string()),
+    })
+}
+
+/// `literal` as a value of `field`'s kind
+fn typed(field: &FilterField, literal: Literal) -> Result<FieldValue, SqlError> {
+    let invalid = |value: String| SqlError::Value {
+        column: column_name(field),
+        value,
+    };
+    let kind = match field {
+        FilterField::Item(field) => field.kind(),
+        FilterField::Custom(_) => {
+            return match literal {
+                Literal::Null => Ok(FieldValue::Null),
+                Literal::Bool(flag) => Ok(FieldValue::Bool(flag)),
+                Literal::Text(text) => Ok(FieldValue::Text(text)),
+                Literal::Number(number) => number
+                    .parse()
+                    .ok()
+                    .filter(|n: &f64| n.is_finite())
+                    .map(|n| FieldValue::Decimal(Decimal(n)))
+                    .ok_or_else(|| invalid(number)),
+            };
+        }
+    };
+    match (kind, literal) {
+        (_, Literal::Null) => Ok(FieldValue::Null),
+        (ValueKind::Text, Literal::Text(text)) => Ok(FieldValue::Text(text)),
+        (ValueKind::Number, Literal::Number(number)) => number
+            .parse()
+            .map(FieldValue::Number)
+            .map_err(|_| invalid(number)),
+        (ValueKind::Time, Literal::Text(time)) => DateTime::parse_from_rfc3339(&time)
+            .map(|time| FieldValue::Time(time.with_timezone(&Utc)))
+            .map_err(|_| invalid(time)),
+        (_, Literal::Text(value) | Literal::Number(value)) => Err(invalid(value)),
+        (_, Literal::Bool(flag)) => Err(invalid(flag.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_selects_translate_into_conditions() {
+        let query = parse(
+            "SELECT id, name FROM items WHERE owner_id = $1 AND version >= 2 \
+             AND name LIKE 'Wid%' AND description IS NOT NULL ORDER BY created_at DESC LIMIT 10;",
+            &[json!("user-1")],
+        )
+        .unwrap();
+        assert_eq!(
+            query.projection,
+            Projection::Columns(vec![QueryField::Id.into(), QueryField::Name.into()])
+        );
+        assert_eq!(
+            query.conditions,
+            vec![
+                Condition {
+                    field: QueryField::OwnerId.into(),
+                    comparison: Comparison::Eq,
+                    value: FieldValue::Text("user-1".to_string()),
+                },
+                Condition {
+                    field: QueryField::Version.into(),
+                    comparison: Comparison::Ge,
+                    value: FieldValue::Number(2),
+                },
+                Condition {
+                    field: QueryField::Name.into(),
+                    comparison: Comparison::StartsWith,
+                    value: FieldValue::Text("Wid".to_string()),
+                },
+                Condition {
+                    field: QueryField::Description.into(),
+                    comparison: Comparison::Ne,
+                    value: FieldValue::Null,
+                },
+            ]
+        );
+        assert_eq!(
+            query.order,
+            vec![Sort {
+                field: QueryField::CreatedAt,
+                descending: true,
+            }]
+        );
+        assert_eq!(query.limit, Some(10));
+    }
+
+    #[test]
+    fn test_only_reads_of_items_are_allowed() {
+        let refused = |sql: &str| parse(sql, &[]).unwrap_err();
+        assert_eq!(refused("DELETE FROM items"), SqlError::Statement("DELETE".to_string()));
+        assert_eq!(refused("SELECT * FROM users"), SqlError::Table("users".to_string()));
+        assert!(matches!(refused("SELECT * FROM items; DROP TABLE items"), SqlError::Syntax(_)));
+        assert!(matches!(
+            refused("SELECT * FROM items WHERE name = 'a' OR 1 = 1"),
+            SqlError::Unsupported(_)
+        ));
+        assert!(matches!(refused("SELECT * FROM items -- comment"), SqlError::Unsupported(_)));
+        assert_eq!(refused("SELECT * FROM items WHERE id = $2"), SqlError::Parameter(2));
+        // Parameters are values, never SQL
+        let query = parse("SELECT * FROM items WHERE name = $1", &[json!("x' OR '1'='1")]).unwrap();
+        assert_eq!(query.conditions[0].value, FieldValue::Text("x' OR '1'='1".to_string()));
+    }
+}
@@ -1,18 +1,401 @@
-use crate::db::ItemRepository;
-use std::sync::Arc;
+use crate::{
+    access_log::AccessLog,
+    approvals::Approvals,
+    attachments::Attachments,
+    auth::JwtValidator,
+    clock::{system_clock, SharedClock},
+    config::Config,
+    custom_fields::CustomFields,
+    db::{
+        AccessRepository, ChangeRequestRepository, CollectionRepository, CommentRepository,
+        InMemoryAccessRepository, InMemoryChangeRequestRepository, InMemoryCollectionRepository,
+        InMemoryCommentRepository, InMemoryItemLockRepository, InMemorySavedSearchRepository,
+        ItemLockRepository, ItemRepository, NotificationRepository, SavedSearchRepository,
+    },
+    dead_letters::DeadLetterQueue,
+    deprecation::Deprecations,
+    dual_write::DualWriteRepository,
+    duplicates::DuplicateGuard,
+    events::{EventBus, EventPublisher, WebhookPublisher},
+    health::HealthMonitor,
+    hooks::{ItemHookChain, ItemHooks},
+    http_client::HttpClient,
+    locks::DistributedLock,
+    log_filter::LogFilter,
+    middleware::{chaos::FaultInjector, mocks::MockRegistry},
+    notifications::Notifier,
+    privacy::ErasureSigner,
+    schemas::SchemaRegistry,
+    sharing::ShareLinks,
+    stats::{ItemStats, StatsRepository},
+    system::SystemSampler,
+    tenancy::TenantDirectory,
+    validation::{Sanitizer, ValidationRules},
+    views::ListViews,
+};
+use std::sync::{atomic::AtomicBool, Arc};
 
 pub type SharedState = Arc<AppState>;
 
 pub struct AppState {
+    /// Effective configuration, as reported by the admin API
+    pub config: Arc<Config>,
+    /// Verifies bearer tokens for the auth middleware
+    pub auth: Arc<JwtValidator>,
     pub repo: Arc<dyn ItemRepository>,
+    /// Item statistics, kept up to date by a wrapper around `repo`
+    pub stats: Arc<ItemStats>,
+    pub access: Arc<dyn AccessRepository>,
+    /// Folders items are filed in, whose grants apply to the items below them
+    pub collections: Arc<dyn CollectionRepository>,
+    pub events: EventBus,
+    /// External broker receiving outbox events, if configured
+    pub publisher: Option<Arc<dyn EventPublisher>>,
+    /// Webhooks receiving outbox events, if configured
+    pub webhooks: Option<Arc<WebhookPublisher>>,
+    /// Work that exhausted its retries
+    pub dead_letters: Arc<DeadLetterQueue>,
+    /// Signs data erasure reports; erasure is unavailable without it
+    pub erasure_signer: Option<ErasureSigner>,
+    /// Provisioned tenants, looked up by the tenancy middleware
+    pub tenants: Arc<TenantDirectory>,
+    /// Host resource usage reported by the health endpoint
+    pub system: Arc<SystemSampler>,
+    /// Health thresholds and synthetic check results
+    pub health: Arc<HealthMonitor>,
+    /// Client for outbound HTTP calls made by handlers
+    pub http: HttpClient,
+    /// Request log written to a file, if configured
+    pub access_log: Option<Arc<AccessLog>>,
+    /// Log output filter, when it can be changed at runtime
+    pub log_filter: Option<Arc<LogFilter>>,
+    /// Fault injection, when enabled outside production
+    pub chaos: Option<Arc<FaultInjector>>,
+    /// Mock endpoints registered at runtime, when enabled outside production
+    pub mocks: Option<Arc<MockRegistry>>,
+    /// Dual writes to a secondary backend, when configured
+    pub dual_write: Option<Arc<DualWriteRepository>>,
+    /// Time source for middleware such as the rate limiter
+    pub clock: SharedClock,
+    /// Custom behavior around item changes
+    pub hooks: ItemHookChain,
+    /// Rules request bodies are checked against on top of the models' own
+    pub validation: Arc<ValidationRules>,
+    /// Detects repeated item submissions, when enabled
+    pub duplicates: Option<Arc<DuplicateGuard>>,
+    /// Materialized list views, when any are configured
+    pub views: Option<Arc<ListViews>>,
+    /// JSON Schemas item metadata must satisfy
+    pub schemas: Arc<SchemaRegistry>,
+    /// Typed fields tenants define for their items
+    pub custom_fields: Arc<CustomFields>,
+    /// Item attachments stored in S3, when a bucket is configured
+    pub attachments: Option<Arc<Attachments>>,
+    /// Links sharing single items anonymously, when a signing key is set
+    pub shares: Option<Arc<ShareLinks>>,
+    /// Email notifications, when an SMTP server is configured
+    pub notifications: Option<Arc<Notifier>>,
+    /// In-app notification inbox, when enabled
+    pub inbox: Option<Arc<dyn NotificationRepository>>,
+    /// Searches principals saved to run again
+    pub saved_searches: Arc<dyn SavedSearchRepository>,
+    /// Comments on items
+    pub comments: Arc<dyn CommentRepository>,
+    /// Check-out locks principals hold on items they are editing
+    pub item_locks: Arc<dyn ItemLockRepository>,
+    /// Which items need approval to change
+    pub approvals: Arc<Approvals>,
+    /// Changes waiting for, or resolved after, approval
+    pub change_requests: Arc<dyn ChangeRequestRepository>,
+    /// Endpoints announced as deprecated
+    pub deprecations: Arc<Deprecations>,
+    /// Set once shutdown begins, so readiness fails while requests still flow
+    pub draining: AtomicBool,
+    /// Locks serializing critical sections across replicas
+    pub locks: Arc<DistributedLock>,
 }
 
 impl AppState {
     pub fn new(repo: Arc<dyn ItemRepository>) -> Self {
-        Self { repo }
+        let stats = Arc::new(ItemStats::default());
+        Self {
+            config: Arc::new(Config::default()),
+            auth: Arc::new(JwtValidator::default()),
+            repo: Arc::new(StatsRepository::new(repo, stats.clone())),
+            stats,
+            access: Arc::new(InMemoryAccessRepository::new()),
+            collections: Arc::new(InMemoryCollectionRepository::new()),
+            events: EventBus::default(),
+            publisher: None,
+            webhooks: None,
+            dead_letters: Arc::new(DeadLetterQueue::default()),
+            erasure_signer: None,
+            tenants: Arc::new(TenantDirectory::default()),
+            system: Arc::new(SystemSampler::new()),
+            health: Arc::new(HealthMonitor::default()),
+            http: HttpClient::default(),
+            access_log: None,
+            log_filter: None,
+            chaos: None,
+            mocks: None,
+            dual_write: None,
+            clock: system_clock(),
+            hooks: ItemHookChain::default(),
+            validation: Arc::new(ValidationRules::sanitizing(Sanitizer::default())),
+            duplicates: None,
+            views: None,
+            schemas: Arc::new(SchemaRegistry::default()),
+            custom_fields: Arc::new(CustomFields::default()),
+            attachments: None,
+            shares: None,
+            notifications: None,
+            inbox: None,
+            saved_searches: Arc::new(InMemorySavedSearchRepository::new()),
+            comments: Arc::new(InMemoryCommentRepository::new()),
+            item_locks: Arc::new(InMemoryItemLockRepository::new()),
+            approvals: Arc::new(Approvals::default()),
+            change_requests: Arc::new(InMemoryChangeRequestRepository::new()),
+            deprecations: Arc::new(Deprecations::default()),
+            draining: AtomicBool::new(false),
+            locks: Arc::new(DistributedLock::default()),
+        }
     }
 
     pub fn shared(repo: Arc<dyn ItemRepository>) -> SharedState {
         Arc::new(Self::new(repo))
     }
+
+    /// Record the configuration the service was started with
+    #[must_use]
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.config = Arc::new(config);
+        self
+    }
+
+    /// Replace the bearer token validator
+    #[must_use]
+    pub fn with_auth(mut self, auth: JwtValidator) -> Self {
+        self.auth = Arc::new(auth);
+        self
+    }
+
+    /// Replace the access control repository
+    #[must_use]
+    pub fn with_access(mut self, access: Arc<dyn AccessRepository>) -> Self {
+        self.access = access;
+        self
+    }
+
+    /// Replace the collection repository
+    #[must_use]
+    pub fn with_collections(mut self, collections: Arc<dyn CollectionRepository>) -> Self {
+        self.collections = collections;
+        self
+    }
+
+    /// Attach an external event broker publisher
+    #[must_use]
+    pub fn with_publisher(mut self, publisher: Option<Arc<dyn EventPublisher>>) -> Self {
+        self.publisher = publisher;
+        self
+    }
+
+    /// Deliver outbox events to webhooks
+    #[must_use]
+    pub fn with_webhooks(mut self, webhooks: Option<WebhookPublisher>) -> Self {
+        self.webhooks = webhooks.map(Arc::new);
+        self
+    }
+
+    /// Share `dead_letters` with the subsystems that fill it
+    #[must_use]
+    pub fn with_dead_letters(mut self, dead_letters: Arc<DeadLetterQueue>) -> Self {
+        self.dead_letters = dead_letters;
+        self
+    }
+
+    /// Enable data erasure with reports signed by `signer`
+    #[must_use]
+    pub fn with_erasure_signer(mut self, signer: Option<ErasureSigner>) -> Self {
+        self.erasure_signer = signer;
+        self
+    }
+
+    /// Replace the tenant directory
+    #[must_use]
+    pub fn with_tenants(mut self, tenants: TenantDirectory) -> Self {
+        self.tenants = Arc::new(tenants);
+        self
+    }
+
+    /// Replace the health thresholds and synthetic checks
+    #[must_use]
+    pub fn with_health(mut self, health: HealthMonitor) -> Self {
+        self.health = Arc::new(health);
+        self
+    }
+
+    /// Replace the outbound HTTP client
+    #[must_use]
+    pub fn with_http_client(mut self, http: HttpClient) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Write an access log for every request
+    #[must_use]
+    pub fn with_access_log(mut self, access_log: Option<AccessLog>) -> Self {
+        self.access_log = access_log.map(Arc::new);
+        self
+    }
+
+    /// Allow administrators to change the log filter at runtime
+    #[must_use]
+    pub fn with_log_filter(mut self, log_filter: LogFilter) -> Self {
+        self.log_filter = Some(Arc::new(log_filter));
+        self
+    }
+
+    /// Enable fault injection
+    #[must_use]
+    pub fn with_chaos(mut self, chaos: FaultInjector) -> Self {
+        self.chaos = Some(Arc::new(chaos));
+        self
+    }
+
+    /// Enable mock endpoints
+    #[must_use]
+    pub fn with_mocks(mut self, mocks: MockRegistry) -> Self {
+        self.mocks = Some(Arc::new(mocks));
+        self
+    }
+
+    /// Report on the dual writes made by the repository
+    #[must_use]
+    pub fn with_dual_write(mut self, dual_write: Option<Arc<DualWriteRepository>>) -> Self {
+        self.dual_write = dual_write;
+        self
+    }
+
+    /// Replace the system clock, usually with a `ManualClock` in tests
+    ///
+    /// Repositories are built before the state, so they take the same clock
+    /// through their own `with_clock`.
+    #[must_use]
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Run `hooks` around item changes, after any registered earlier
+    #[must_use]
+    pub fn with_hooks(mut self, hooks: impl ItemHooks + 'static) -> Self {
+        self.hooks.register(Arc::new(hooks));
+        self
+    }
+
+    /// Take cross-replica locks from `locks`
+    #[must_use]
+    pub fn with_locks(mut self, locks: DistributedLock) -> Self {
+        self.locks = Arc::new(locks);
+        self
+    }
+
+    /// Announce the endpoints in `deprecations` as deprecated
+    #[must_use]
+    pub fn with_deprecations(mut self, deprecations: Deprecations) -> Self {
+        self.deprecations = Arc::new(deprecations);
+        self
+    }
+
+    /// Check request bodies against `rules` as well
+    #[must_use]
+    pub fn with_validation_rules(mut self, rules: ValidationRules) -> Self {
+        self.validation = Arc::new(rules);
+        self
+    }
+
+    /// Answer repeated item submissions with the item created the first time
+    #[must_use]
+    pub fn with_duplicate_guard(mut self, duplicates: DuplicateGuard) -> Self {
+        self.duplicates = Some(Arc::new(duplicates));
+        self
+    }
+
+    /// Serve the materialized list `views`
+    #[must_use]
+    pub fn with_views(mut self, views: ListViews) -> Self {
+        self.views = Some(Arc::new(views));
+        self
+    }
+
+    /// Accept item attachments uploaded to S3
+    #[must_use]
+    pub fn with_attachments(mut self, attachments: Option<Attachments>) -> Self {
+        self.attachments = attachments.map(Arc::new);
+        self
+    }
+
+    /// Allow items to be shared through signed links
+    #[must_use]
+    pub fn with_share_links(mut self, shares: Option<ShareLinks>) -> Self {
+        self.shares = shares.map(Arc::new);
+        self
+    }
+
+    /// Send email notifications through `notifier`
+    #[must_use]
+    pub fn with_notifications(mut self, notifier: Option<Arc<Notifier>>) -> Self {
+        self.notifications = notifier;
+        self
+    }
+
+    /// Serve the notification inbox from `inbox`
+    #[must_use]
+    pub fn with_inbox(mut self, inbox: Option<Arc<dyn NotificationRepository>>) -> Self {
+        self.inbox = inbox;
+        self
+    }
+
+    /// Keep saved searches in `searches`
+    #[must_use]
+    pub fn with_saved_searches(mut self, searches: Arc<dyn SavedSearchRepository>) -> Self {
+        self.saved_searches = searches;
+        self
+    }
+
+    /// Keep comments on items in `comments`
+    #[must_use]
+    pub fn with_comments(mut self, comments: Arc<dyn CommentRepository>) -> Self {
+        self.comments = comments;
+        self
+    }
+
+    /// Keep check-out locks on items in `item_locks`
+    #[must_use]
+    pub fn with_item_locks(mut self, item_locks: Arc<dyn ItemLockRepository>) -> Self {
+        self.item_locks = item_locks;
+        self
+    }
+
+    /// Require approval for changes to the items `approvals` designates
+    #[must_use]
+    pub fn with_approvals(mut self, approvals: Approvals) -> Self {
+        self.approvals = Arc::new(approvals);
+        self
+    }
+
+    /// Keep change requests in `change_requests`
+    #[must_use]
+    pub fn with_change_requests(
+        mut self,
+        change_requests: Arc<dyn ChangeRequestRepository>,
+    ) -> Self {
+        self.change_requests = change_requests;
+        self
+    }
+
+    pub fn into_shared(self) -> SharedState {
+        Arc::new(self)
+    }
 }
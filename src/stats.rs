@@ -0,0 +1,530 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::Arc,
+};
+use utoipa::ToSchema;
+
+use crate::{
+    analytics::{AnalyticsBucket, AnalyticsRange},
+    clock::{system_clock, SharedClock},
+    db::{DatabaseResult, EventFilter, ItemFilter, ItemRepository},
+    events::OutboxEvent,
+    models::{CreateItemRequest, Item, ItemField, UpdateItemRequest},
+    pagination::Page,
+    tenancy::current_tenant,
+};
+
+/// Minutes of creations kept, the longest window reported
+const WINDOW_MINUTES: usize = 24 * 60;
+
+/// Owners tracked for the top owners; more than reported, so the reported
+/// ones are rarely evicted
+const TRACKED_OWNERS: usize = 64;
+
+/// Owners reported in the top owners
+pub const TOP_OWNERS: usize = 10;
+
+/// Bits of the hash choosing a HyperLogLog register
+const HLL_PRECISION: u32 = 12;
+
+/// Approximate count of distinct values in fixed memory (4 KiB), with a
+/// standard error of about 1.6%
+struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    fn new() -> Self {
+        Self {
+            registers: vec![0; 1 << HLL_PRECISION],
+        }
+    }
+
+    fn insert(&mut self, value: &str) {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        let hash = hasher.finish();
+        let register = (hash >> (64 - HLL_PRECISION)) as usize;
+        // Position of the first set bit in the rest of the hash, with a
+        // sentinel bit so an all-zero rest still ends
+        let rest = (hash << HLL_PRECISION) | (1 << (HLL_PRECISION - 1));
+        let rank = rest.leading_zeros() as u8 + 1;
+        self.registers[register] = self.registers[register].max(rank);
+    }
+
+    fn estimate(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|&rank| 2f64.powi(-i32::from(rank)))
+            .sum();
+        let raw = alpha * m * m / sum;
+        let empty = self.registers.iter().filter(|&&rank| rank == 0).count();
+        // Small cardinalities are counted more closely from empty registers
+        let estimate = if raw <= 2.5 * m && empty > 0 {
+            m * (m / empty as f64).ln()
+        } else {
+            raw
+        };
+        estimate.round() as u64
+    }
+}
+
+/// Most frequent values in fixed memory, by the Space-Saving algorithm
+///
+/// A value arriving when every slot is taken replaces the least frequent
+/// one and inherits its count, so counts may be overestimated by at most
+/// the count of the value replaced.
+struct TopValues {
+    counts: HashMap<String, u64>,
+}
+
+impl TopValues {
+    fn new() -> Self {
+        Self {
+            counts: HashMap::with_capacity(TRACKED_OWNERS),
+        }
+    }
+
+    fn insert(&mut self, value: &str) {
+        if let Some(count) = self.counts.get_mut(value) {
+            *count += 1;
+            return;
+        }
+        let mut inherited = 0;
+        if self.counts.len() >= TRACKED_OWNERS {
+            if let Some((least, count)) = self
+                .counts
+                .iter()
+                .min_by_key(|(_, &count)| count)
+                .map(|(value, &count)| (value.clone(), count))
+            {
+                self.counts.remove(&least);
+                inherited = count;
+            }
+        }
+        self.counts.insert(value.to_string(), inherited + 1);
+    }
+
+    fn top(&self, n: usize) -> Vec<OwnerCount> {
+        let mut top: Vec<_> = self
+            .counts
+            .iter()
+            .map(|(owner_id, &items)| OwnerCount {
+                owner_id: owner_id.clone(),
+                items,
+            })
+            .collect();
+        top.sort_by(|a, b| {
+            b.items
+                .cmp(&a.items)
+                .then_with(|| a.owner_id.cmp(&b.owner_id))
+        });
+        top.truncate(n);
+        top
+    }
+}
+
+/// Creations per minute over the last day, in a ring of minute buckets
+struct Creations {
+    /// Minute since the epoch each bucket counts, and its count
+    buckets: Vec<(i64, u64)>,
+}
+
+impl Creations {
+    fn new() -> Self {
+        Self {
+            buckets: vec![(i64::MIN, 0); WINDOW_MINUTES],
+        }
+    }
+
+    fn record(&mut self, minute: i64) {
+        let bucket = &mut self.buckets[minute.rem_euclid(WINDOW_MINUTES as i64) as usize];
+        if bucket.0 != minute {
+            *bucket = (minute, 0);
+        }
+        bucket.1 += 1;
+    }
+
+    /// Creations in the `minutes` up to and including `now`
+    fn within(&self, now: i64, minutes: i64) -> u64 {
+        self.buckets
+            .iter()
+            .filter(|(minute, _)| *minute <= now && *minute > now - minutes)
+            .map(|(_, count)| count)
+            .sum()
+    }
+}
+
+/// Statistics of one tenant's items
+struct TenantStats {
+    since: DateTime<Utc>,
+    /// Items counted by the last full count, adjusted for the changes
+    /// recorded before it; `None` until counted
+    counted: Option<i64>,
+    /// Items created less items deleted since statistics started
+    net: i64,
+    creations: Creations,
+    owners: HyperLogLog,
+    top_owners: TopValues,
+}
+
+impl TenantStats {
+    fn new(since: DateTime<Utc>) -> Self {
+        Self {
+            since,
+            counted: None,
+            net: 0,
+            creations: Creations::new(),
+            owners: HyperLogLog::new(),
+            top_owners: TopValues::new(),
+        }
+    }
+}
+
+/// Items created within the last 5 minutes, hour and day
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CreationCounts {
+    pub last_5m: u64,
+    pub last_1h: u64,
+    pub last_24h: u64,
+}
+
+/// An owner and about how many items they created
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct OwnerCount {
+    pub owner_id: String,
+    /// Items created, possibly overestimated
+    pub items: u64,
+}
+
+/// Statistics of the tenant's items, maintained as items change
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ItemStatsResponse {
+    /// Items stored
+    pub total: u64,
+    /// Items created, since `since` at the earliest
+    pub created: CreationCounts,
+    /// Approximate number of owners who created items since `since`
+    pub distinct_owners: u64,
+    /// Owners who created the most items since `since`
+    pub top_owners: Vec<OwnerCount>,
+    /// When this instance started keeping the statistics
+    pub since: DateTime<Utc>,
+}
+
+/// Item statistics kept per tenant as items are created and deleted
+///
+/// Reading them costs no scan: the total is counted once per tenant and
+/// kept up to date from then on, creation counts sit in minute buckets,
+/// owners are counted by a HyperLogLog sketch, and the top owners by a
+/// Space-Saving sketch. Statistics other than the total cover changes made
+/// through this instance since it started.
+pub struct ItemStats {
+    tenants: DashMap<String, TenantStats>,
+    clock: SharedClock,
+}
+
+impl Default for ItemStats {
+    fn default() -> Self {
+        Self::new(system_clock())
+    }
+}
+
+impl ItemStats {
+    pub fn new(clock: SharedClock) -> Self {
+        Self {
+            tenants: DashMap::new(),
+            clock,
+        }
+    }
+
+    fn tenant(&self) -> dashmap::mapref::one::RefMut<'_, String, TenantStats> {
+        self.tenants
+            .entry(current_tenant().unwrap_or_default())
+            .or_insert_with(|| TenantStats::new(self.clock.now()))
+    }
+
+    /// Record `item` as created in the current tenant
+    pub fn record_created(&self, item: &Item) {
+        let minute = self.clock.now().timestamp().div_euclid(60);
+        let mut stats = self.tenant();
+        stats.net += 1;
+        stats.creations.record(minute);
+        if let Some(owner) = &item.owner_id {
+            stats.owners.insert(owner);
+            stats.top_owners.insert(owner);
+        }
+    }
+
+    /// Record an item of the current tenant as deleted
+    pub fn record_deleted(&self) {
+        self.tenant().net -= 1;
+    }
+
+    /// Count the current tenant's items again on the next read, after a
+    /// change not tracked item by item
+    pub fn recount(&self) {
+        self.tenant().counted = None;
+    }
+
+    /// The current tenant's statistics, counting its items in `repo` the
+    /// first time
+    pub async fn snapshot(&self, repo: &dyn ItemRepository) -> DatabaseResult<ItemStatsResponse> {
+        let counted = self.tenant().counted;
+        if counted.is_none() {
+            let count =
+                i64::try_from(repo.count(&ItemFilter::default()).await?).unwrap_or(i64::MAX);
+            let mut stats = self.tenant();
+            // Changes recorded so far are already in the count
+            stats.counted = Some(count - stats.net);
+        }
+
+        let now = self.clock.now().timestamp().div_euclid(60);
+        let stats = self.tenant();
+        Ok(ItemStatsResponse {
+            total: u64::try_from(stats.counted.unwrap_or(0) + stats.net).unwrap_or(0),
+            created: CreationCounts {
+                last_5m: stats.creations.within(now, 5),
+                last_1h: stats.creations.within(now, 60),
+                last_24h: stats.creations.within(now, WINDOW_MINUTES as i64),
+            },
+            distinct_owners: stats.owners.estimate(),
+            top_owners: stats.top_owners.top(TOP_OWNERS),
+            since: stats.since,
+        })
+    }
+}
+
+/// Keeps [`ItemStats`] up to date with the changes made through `inner`
+///
+/// Restores and dropped tenants have the total counted again rather than
+/// tracked item by item.
+pub struct StatsRepository {
+    inner: Arc<dyn ItemRepository>,
+    stats: Arc<ItemStats>,
+}
+
+impl StatsRepository {
+    pub fn new(inner: Arc<dyn ItemRepository>, stats: Arc<ItemStats>) -> Self {
+        Self { inner, stats }
+    }
+}
+
+#[async_trait]
+impl ItemRepository for StatsRepository {
+    async fn create(
+        &self,
+        request: CreateItemRequest,
+        owner_id: Option<String>,
+    ) -> DatabaseResult<Item> {
+        let item = self.inner.create(request, owner_id).await?;
+        self.stats.record_created(&item);
+        Ok(item)
+    }
+
+    async fn get(&self, id: &str) -> DatabaseResult<Item> {
+        self.inner.get(id).await
+    }
+
+    async fn get_by_slug(&self, slug: &str) -> DatabaseResult<Item> {
+        self.inner.get_by_slug(slug).await
+    }
+
+    async fn get_by_external_id(
+        &self,
+        source: Option<&str>,
+        external_id: &str,
+    ) -> DatabaseResult<Item> {
+        self.inner.get_by_external_id(source, external_id).await
+    }
+
+    async fn update(
+        &self,
+        id: &str,
+        request: UpdateItemRequest,
+        expected_version: Option<u64>,
+    ) -> DatabaseResult<Item> {
+        self.inner.update(id, request, expected_version).await
+    }
+
+    async fn changed_since(&self, id: &str, version: u64) -> DatabaseResult<Vec<ItemField>> {
+        self.inner.changed_since(id, version).await
+    }
+
+    async fn delete(&self, id: &str) -> DatabaseResult<()> {
+        self.inner.delete(id).await?;
+        self.stats.record_deleted();
+        Ok(())
+    }
+
+    async fn list(&self, filter: &ItemFilter, page: &Page) -> DatabaseResult<Vec<Arc<Item>>> {
+        self.inner.list(filter, page).await
+    }
+
+    async fn count(&self, filter: &ItemFilter) -> DatabaseResult<usize> {
+        self.inner.count(filter).await
+    }
+
+    async fn list_with_total(
+        &self,
+        filter: &ItemFilter,
+        page: &Page,
+    ) -> DatabaseResult<(Vec<Arc<Item>>, usize)> {
+        self.inner.list_with_total(filter, page).await
+    }
+
+    async fn health_check(&self) -> DatabaseResult<()> {
+        self.inner.health_check().await
+    }
+
+    async fn reassign_owner(
+        &self,
+        owner_id: &str,
+        replacement: &str,
+    ) -> DatabaseResult<(usize, usize)> {
+        self.inner.reassign_owner(owner_id, replacement).await
+    }
+
+    async fn restore(&self, item: Item) -> DatabaseResult<Item> {
+        let result = self.inner.restore(item).await;
+        self.stats.recount();
+        result
+    }
+
+    async fn provision_tenant(&self, tenant: &str) -> DatabaseResult<()> {
+        self.inner.provision_tenant(tenant).await
+    }
+
+    async fn drop_tenant(&self, tenant: &str) -> DatabaseResult<Vec<String>> {
+        let result = self.inner.drop_tenant(tenant).await;
+        self.stats.tenants.remove(tenant);
+        result
+    }
+
+    async fn pending_events(&self, limit: usize) -> DatabaseResult<Vec<OutboxEvent>> {
+        self.inner.pending_events(limit).await
+    }
+
+    async fn event_history(
+        &self,
+        filter: &EventFilter,
+        after: u64,
+        limit: usize,
+    ) -> DatabaseResult<Vec<OutboxEvent>> {
+        self.inner.event_history(filter, after, limit).await
+    }
+
+    async fn pending_event_count(&self) -> DatabaseResult<usize> {
+        self.inner.pending_event_count().await
+    }
+
+    async fn mark_events_published(&self, sequences: &[u64]) -> DatabaseResult<()> {
+        self.inner.mark_events_published(sequences).await
+    }
+
+    async fn mark_event_failed(&self, sequence: u64, error: &str) -> DatabaseResult<()> {
+        self.inner.mark_event_failed(sequence, error).await
+    }
+
+    async fn purge_published_events(
+        &self,
+        cutoff: DateTime<Utc>,
+        dry_run: bool,
+    ) -> DatabaseResult<usize> {
+        self.inner.purge_published_events(cutoff, dry_run).await
+    }
+
+    async fn event_histogram(
+        &self,
+        filter: &ItemFilter,
+        range: &AnalyticsRange,
+    ) -> DatabaseResult<Vec<AnalyticsBucket>> {
+        self.inner.event_histogram(filter, range).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{clock::ManualClock, db::InMemoryRepository};
+    use std::time::Duration;
+
+    #[test]
+    fn test_distinct_owners_are_estimated_closely() {
+        let mut owners = HyperLogLog::new();
+        assert_eq!(owners.estimate(), 0);
+        for i in 0..20_000 {
+            // Repeats do not count twice
+            owners.insert(&format!("user-{}", i % 10_000));
+        }
+        let estimate = owners.estimate() as f64;
+        assert!((estimate - 10_000.0).abs() < 500.0, "estimated {estimate}");
+    }
+
+    #[test]
+    fn test_frequent_owners_survive_eviction() {
+        let mut top = TopValues::new();
+        for i in 0..1000 {
+            top.insert("busy");
+            top.insert(&format!("once-{i}"));
+        }
+        assert_eq!(top.top(1)[0].owner_id, "busy");
+        assert_eq!(top.top(1)[0].items, 1000);
+    }
+
+    #[tokio::test]
+    async fn test_stats_follow_changes_without_rescanning() {
+        let clock = Arc::new(ManualClock::default());
+        let stats = Arc::new(ItemStats::new(clock.clone()));
+        let inner: Arc<dyn ItemRepository> = Arc::new(InMemoryRepository::new());
+        let request = || CreateItemRequest {
+            name: "Counted".to_string(),
+            description: None,
+            metadata: None,
+            custom_fields: Default::default(),
+            status: Default::default(),
+            publish_at: None,
+            external_id: None,
+            source: None,
+        };
+        // Items stored before statistics started are found by the first count
+        inner.create(request(), None).await.unwrap();
+        let repo = StatsRepository::new(inner, stats.clone());
+
+        let first = repo
+            .create(request(), Some("alice".to_string()))
+            .await
+            .unwrap();
+        clock.advance(Duration::from_secs(2 * 60 * 60));
+        repo.create(request(), Some("alice".to_string()))
+            .await
+            .unwrap();
+        repo.create(request(), Some("bob".to_string()))
+            .await
+            .unwrap();
+        repo.delete(&first.id).await.unwrap();
+
+        let snapshot = stats.snapshot(&repo).await.unwrap();
+        assert_eq!(snapshot.total, 3);
+        assert_eq!((snapshot.created.last_1h, snapshot.created.last_24h), (2, 3));
+        assert_eq!(snapshot.distinct_owners, 2);
+        assert_eq!(
+            snapshot.top_owners[0],
+            OwnerCount {
+                owner_id: "alice".to_string(),
+                items: 2
+            }
+        );
+
+        repo.create(request(), None).await.unwrap();
+        assert_eq!(stats.snapshot(&repo).await.unwrap().total, 4);
+    }
+}
@@ -0,0 +1,411 @@
+use chrono::{DateTime, Utc};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+use sysinfo::System;
+use tokio::task::JoinHandle;
+
+/// cgroup v1 reports no memory limit as a value near `i64::MAX`
+const V1_UNLIMITED: u64 = 1 << 62;
+
+/// Host resource usage at one point in time
+#[derive(Debug, Clone, Copy)]
+pub struct SystemSnapshot {
+    pub memory_used_mb: u64,
+    pub memory_total_mb: u64,
+    pub memory_usage_percent: f32,
+    pub cpu_count: usize,
+    /// Limits of the process's cgroup, when running in one
+    pub container: Option<ContainerSnapshot>,
+    pub sampled_at: DateTime<Utc>,
+}
+
+/// Resource usage of the process's cgroup, against its limits
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContainerSnapshot {
+    /// 1 or 2
+    pub cgroup_version: u8,
+    /// Memory in use, less the page cache the kernel can reclaim
+    pub memory_used_mb: u64,
+    /// Memory limit, unset when the cgroup has none
+    pub memory_limit_mb: Option<u64>,
+    pub memory_usage_percent: Option<f32>,
+    /// CPUs the cgroup may use per period, unset without a quota
+    pub cpu_quota: Option<f64>,
+    /// CPUs used on average since the previous sample
+    pub cpu_used: Option<f64>,
+    pub cpu_usage_percent: Option<f32>,
+}
+
+impl SystemSnapshot {
+    /// Share of memory in use against the limit that applies: the cgroup's
+    /// when it has one, the host's otherwise
+    pub fn effective_memory_usage_percent(&self) -> f32 {
+        self.container
+            .and_then(|container| container.memory_usage_percent)
+            .unwrap_or(self.memory_usage_percent)
+    }
+}
+
+/// Samples host resource usage in the background for the health endpoint
+///
+/// Building a `System` and refreshing it is too slow to do per request, so one
+/// instance is kept and refreshed on an interval; readers only copy the last
+/// snapshot.
+pub struct SystemSampler {
+    state: Mutex<SamplerState>,
+    latest: RwLock<SystemSnapshot>,
+}
+
+struct SamplerState {
+    system: System,
+    cgroup: Option<Cgroup>,
+    /// CPU time the cgroup had used at the previous sample, in microseconds
+    last_cpu: Option<(u64, Instant)>,
+}
+
+impl SystemSampler {
+    /// Create a sampler holding an initial snapshot
+    pub fn new() -> Self {
+        Self::with_cgroup(Cgroup::detect())
+    }
+
+    fn with_cgroup(cgroup: Option<Cgroup>) -> Self {
+        let mut state = SamplerState {
+            system: System::new(),
+            cgroup,
+            last_cpu: None,
+        };
+        let latest = RwLock::new(state.sample());
+        Self {
+            state: Mutex::new(state),
+            latest,
+        }
+    }
+
+    /// Most recent snapshot
+    pub fn snapshot(&self) -> SystemSnapshot {
+        *self.latest.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Take a new snapshot now
+    pub fn refresh(&self) {
+        let snapshot = self
+            .state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .sample();
+        *self.latest.write().unwrap_or_else(|e| e.into_inner()) = snapshot;
+    }
+
+    /// Refresh the snapshot every `interval` until the task is aborted
+    pub fn spawn(self: Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                let sampler = self.clone();
+                // Refreshing reads /proc and may block briefly
+                if tokio::task::spawn_blocking(move || sampler.refresh())
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        })
+    }
+}
+
+impl Default for SystemSampler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SamplerState {
+    fn sample(&mut self) -> SystemSnapshot {
+        self.system.refresh_memory();
+
+        let memory_used_mb = self.system.used_memory() / 1024 / 1024;
+        let memory_total_mb = self.system.total_memory() / 1024 / 1024;
+
+        SystemSnapshot {
+            memory_used_mb,
+            memory_total_mb,
+            memory_usage_percent: percent(memory_used_mb as f64, memory_total_mb as f64)
+                .unwrap_or(0.0),
+            cpu_count: num_cpus::get(),
+            container: self.sample_container(memory_total_mb),
+            sampled_at: Utc::now(),
+        }
+    }
+
+    fn sample_container(&mut self, host_memory_mb: u64) -> Option<ContainerSnapshot> {
+        let cgroup = self.cgroup.as_ref()?;
+        let memory_used_mb = cgroup.memory_used()? / 1024 / 1024;
+        // A limit above the host's memory is no limit at all
+        let memory_limit_mb = cgroup
+            .memory_limit()
+            .map(|limit| limit / 1024 / 1024)
+            .filter(|limit| host_memory_mb == 0 || *limit < host_memory_mb);
+        let cpu_quota = cgroup.cpu_quota();
+
+        let now = Instant::now();
+        let cpu_usage = cgroup.cpu_usage_micros();
+        let cpu_used = match (cpu_usage, self.last_cpu) {
+            (Some(usage), Some((last, at))) => {
+                let elapsed = now.duration_since(at).as_micros() as f64;
+                (elapsed > 0.0).then(|| usage.saturating_sub(last) as f64 / elapsed)
+            }
+            _ => None,
+        };
+        self.last_cpu = cpu_usage.map(|usage| (usage, now));
+
+        Some(ContainerSnapshot {
+            cgroup_version: cgroup.version(),
+            memory_used_mb,
+            memory_limit_mb,
+            memory_usage_percent: memory_limit_mb
+                .and_then(|limit| percent(memory_used_mb as f64, limit as f64)),
+            cpu_quota,
+            cpu_used,
+            cpu_usage_percent: cpu_used
+                .zip(cpu_quota)
+                .and_then(|(used, quota)| percent(used, quota)),
+        })
+    }
+}
+
+fn percent(used: f64, total: f64) -> Option<f32> {
+    (total > 0.0).then(|| (used / total * 100.0) as f32)
+}
+
+/// Where the process's cgroup accounting files are
+#[derive(Debug, Clone, PartialEq)]
+enum Cgroup {
+    /// One directory per controller
+    V1 {
+        memory: PathBuf,
+        cpu: PathBuf,
+        cpuacct: PathBuf,
+    },
+    /// One directory for every controller
+    V2(PathBuf),
+}
+
+impl Cgroup {
+    fn detect() -> Option<Self> {
+        let membership = fs::read_to_string("/proc/self/cgroup").ok()?;
+        Self::detect_at(Path::new("/sys/fs/cgroup"), &membership)
+    }
+
+    /// Find the cgroup described by `membership` (the contents of
+    /// `/proc/self/cgroup`) under the hierarchy mounted at `root`
+    ///
+    /// Inside a container the cgroup is usually the root of what is mounted,
+    /// so a path that does not exist under `root` falls back to it.
+    fn detect_at(root: &Path, membership: &str) -> Option<Self> {
+        // Lines are `id:controllers:path`
+        let entries: Vec<(&str, &str)> = membership
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(3, ':');
+                let (_, controllers, path) = (fields.next()?, fields.next()?, fields.next()?);
+                Some((controllers, path.trim_start_matches('/')))
+            })
+            .collect();
+        let locate = |mount: PathBuf, path: &str, probe: &str| {
+            let nested = mount.join(path);
+            if nested.join(probe).exists() {
+                Some(nested)
+            } else {
+                mount.join(probe).exists().then_some(mount)
+            }
+        };
+
+        if root.join("cgroup.controllers").exists() {
+            let path = entries
+                .iter()
+                .find(|(controllers, _)| controllers.is_empty())
+                .map_or("", |(_, path)| path);
+            return locate(root.to_path_buf(), path, "memory.current").map(Self::V2);
+        }
+
+        let controller = |name: &str, probe: &str| {
+            let (controllers, path) = entries
+                .iter()
+                .find(|(controllers, _)| controllers.split(',').any(|c| c == name))?;
+            // Controllers mounted together share a directory, like `cpu,cpuacct`
+            [root.join(controllers), root.join(name)]
+                .into_iter()
+                .find_map(|mount| locate(mount, path, probe))
+        };
+        Some(Self::V1 {
+            memory: controller("memory", "memory.usage_in_bytes")?,
+            cpu: controller("cpu", "cpu.cfs_quota_us").unwrap_or_default(),
+            cpuacct: controller("cpuacct", "cpuacct.usage").unwrap_or_default(),
+        })
+    }
+
+    fn version(&self) -> u8 {
+        match self {
+            Self::V1 { .. } => 1,
+            Self::V2(_) => 2,
+        }
+    }
+
+    fn memory_limit(&self) -> Option<u64> {
+        match self {
+            Self::V1 { memory, .. } => read_number(&memory.join("memory.limit_in_bytes"))
+                .filter(|&limit| limit < V1_UNLIMITED),
+            // `max` when unlimited, which does not parse
+            Self::V2(dir) => read_number(&dir.join("memory.max")),
+        }
+    }
+
+    /// Memory in use less inactive page cache, as container runtimes count it
+    fn memory_used(&self) -> Option<u64> {
+        let (usage, stat, inactive) = match self {
+            Self::V1 { memory, .. } => (
+                memory.join("memory.usage_in_bytes"),
+                memory.join("memory.stat"),
+                "total_inactive_file",
+            ),
+            Self::V2(dir) => (dir.join("memory.current"), dir.join("memory.stat"), "inactive_file"),
+        };
+        let usage = read_number(&usage)?;
+        let inactive = stat_value(&stat, inactive).unwrap_or(0);
+        Some(usage.saturating_sub(inactive))
+    }
+
+    fn cpu_quota(&self) -> Option<f64> {
+        let (quota, period) = match self {
+            Self::V1 { cpu, .. } => {
+                let quota = fs::read_to_string(cpu.join("cpu.cfs_quota_us")).ok()?;
+                let period = read_number(&cpu.join("cpu.cfs_period_us"))?;
+                // -1 when unlimited, which does not parse
+                (quota.trim().parse::<u64>().ok()?, period)
+            }
+            Self::V2(dir) => {
+                let max = fs::read_to_string(dir.join("cpu.max")).ok()?;
+                let mut fields = max.split_whitespace();
+                let quota = fields.next()?.parse::<u64>().ok()?;
+                (quota, fields.next()?.parse::<u64>().ok()?)
+            }
+        };
+        (period > 0).then(|| quota as f64 / period as f64)
+    }
+
+    fn cpu_usage_micros(&self) -> Option<u64> {
+        match self {
+            Self::V1 { cpuacct, .. } => {
+                read_number(&cpuacct.join("cpuacct.usage")).map(|ns| ns / 1000)
+            }
+            Self::V2(dir) => stat_value(&dir.join("cpu.stat"), "usage_usec"),
+        }
+    }
+}
+
+fn read_number(path: &Path) -> Option<u64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Value of `key` in a flat-keyed stat file such as `memory.stat`
+fn stat_value(path: &Path, key: &str) -> Option<u64> {
+    fs::read_to_string(path).ok()?.lines().find_map(|line| {
+        let (name, value) = line.split_once(' ')?;
+        (name == key).then(|| value.trim().parse().ok()).flatten()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(files: &[(&str, &str)]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("ferrous-cgroup-{}", uuid::Uuid::new_v4()));
+        for (path, contents) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn test_refresh_replaces_snapshot() {
+        let sampler = SystemSampler::new();
+        let first = sampler.snapshot();
+        assert!(first.cpu_count > 0);
+
+        sampler.refresh();
+        assert!(sampler.snapshot().sampled_at >= first.sampled_at);
+    }
+
+    #[test]
+    fn test_cgroup_v2_limits() {
+        let root = fixture(&[
+            ("cgroup.controllers", "cpu memory"),
+            ("app/memory.current", "314572800"),
+            ("app/memory.max", "524288000"),
+            ("app/memory.stat", "anon 1\ninactive_file 104857600\n"),
+            ("app/cpu.max", "150000 100000"),
+            ("app/cpu.stat", "usage_usec 1000\n"),
+        ]);
+        let cgroup = Cgroup::detect_at(&root, "0::/app\n").unwrap();
+        assert_eq!(cgroup, Cgroup::V2(root.join("app")));
+
+        let sampler = SystemSampler::with_cgroup(Some(cgroup));
+        let container = sampler.snapshot().container.unwrap();
+        assert_eq!(container.cgroup_version, 2);
+        assert_eq!(container.memory_used_mb, 200);
+        assert_eq!(container.memory_limit_mb, Some(500));
+        assert_eq!(container.memory_usage_percent, Some(40.0));
+        assert_eq!(container.cpu_quota, Some(1.5));
+        // Usage needs two samples
+        assert_eq!(container.cpu_used, None);
+        assert_eq!(sampler.snapshot().effective_memory_usage_percent(), 40.0);
+
+        fs::write(root.join("app/memory.max"), "max\n").unwrap();
+        fs::write(root.join("app/cpu.max"), "max 100000\n").unwrap();
+        sampler.refresh();
+        let container = sampler.snapshot().container.unwrap();
+        assert_eq!((container.memory_limit_mb, container.cpu_quota), (None, None));
+        assert!(container.cpu_used.is_some());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_cgroup_v1_limits() {
+        let root = fixture(&[
+            ("memory/memory.usage_in_bytes", "209715200"),
+            ("memory/memory.limit_in_bytes", "9223372036854771712"),
+            ("memory/memory.stat", "total_inactive_file 0\n"),
+            ("cpu,cpuacct/cpu.cfs_quota_us", "50000"),
+            ("cpu,cpuacct/cpu.cfs_period_us", "100000"),
+            ("cpu,cpuacct/cpuacct.usage", "5000000"),
+        ]);
+        // Paths from the host's point of view fall back to the mount root
+        let membership = "4:memory:/docker/abc\n2:cpu,cpuacct:/docker/abc\n0::/\n";
+        let cgroup = Cgroup::detect_at(&root, membership).unwrap();
+
+        assert_eq!(cgroup.version(), 1);
+        assert_eq!(cgroup.memory_used(), Some(209_715_200));
+        assert_eq!(cgroup.memory_limit(), None);
+        assert_eq!(cgroup.cpu_quota(), Some(0.5));
+        assert_eq!(cgroup.cpu_usage_micros(), Some(5000));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_no_cgroup_reports_host_memory() {
+        let sampler = SystemSampler::with_cgroup(None);
+        let snapshot = sampler.snapshot();
+        assert!(snapshot.container.is_none());
+        assert_eq!(snapshot.effective_memory_usage_percent(), snapshot.memory_usage_percent);
+    }
+}
@@ -0,0 +1,269 @@
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    config::TenancyConfig,
+    db::{DatabaseError, DatabaseResult, InMemoryTenantRepository, TenantRepository},
+    models::Tenant,
+};
+
+tokio::task_local! {
+    /// Tenant of the request being handled, set by the tenancy middleware
+    static CURRENT_TENANT: String;
+}
+
+/// Maximum length of a tenant ID (fits schema and database name limits)
+pub const MAX_TENANT_ID_LENGTH: usize = 63;
+
+/// Tenant a response was served for
+///
+/// The tenant scope ends with the tenancy middleware, so it adds this to the
+/// response extensions for the layers around it, such as metrics.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServedTenant(pub String);
+
+/// Tenant of the current task, if it runs inside a tenant scope
+pub fn current_tenant() -> Option<String> {
+    CURRENT_TENANT.try_with(Clone::clone).ok()
+}
+
+/// Run `future` with `tenant` as the current tenant
+pub async fn with_tenant<F: Future>(tenant: String, future: F) -> F::Output {
+    CURRENT_TENANT.scope(tenant, future).await
+}
+
+/// Run `future` in the given tenant scope, or unscoped for `None`
+///
+/// Task-locals are not inherited by spawned tasks, so background work started
+/// on behalf of a request captures `current_tenant()` and re-enters it here.
+pub async fn with_optional_tenant<F: Future>(tenant: Option<String>, future: F) -> F::Output {
+    match tenant {
+        Some(tenant) => with_tenant(tenant, future).await,
+        None => future.await,
+    }
+}
+
+/// Whether `id` can be used as a tenant ID
+///
+/// Tenant IDs become schema and database names, so they are restricted to
+/// lowercase ASCII letters, digits, `-` and `_`, starting with a letter or digit.
+pub fn is_valid_tenant_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_TENANT_ID_LENGTH
+        && id.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+        && id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+/// Request header carrying a tenant API key
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Prefix of tenant API keys, which have the form `ftk_<tenant>.<secret>`
+const API_KEY_PREFIX: &str = "ftk_";
+
+/// Generate a new API key for `tenant`
+pub fn generate_api_key(tenant: &str) -> String {
+    format!(
+        "{API_KEY_PREFIX}{tenant}.{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+/// Tenant an API key was issued to, without verifying the key
+pub fn api_key_tenant(key: &str) -> Option<&str> {
+    let (tenant, _) = key.strip_prefix(API_KEY_PREFIX)?.split_once('.')?;
+    is_valid_tenant_id(tenant).then_some(tenant)
+}
+
+/// Hex-encoded SHA-256 of an API key, as stored on the tenant
+pub fn hash_api_key(key: &str) -> String {
+    Sha256::digest(key.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+struct CachedTenant {
+    tenant: Option<Tenant>,
+    expires_at: Instant,
+}
+
+/// Provisioned tenants as seen by the tenancy middleware
+///
+/// Lookups, including misses, are cached for the configured TTL. The admin API
+/// invalidates entries it changes, so the TTL only bounds how long other
+/// instances serve a stale tenant.
+pub struct TenantDirectory {
+    repo: Arc<dyn TenantRepository>,
+    cache: RwLock<HashMap<String, CachedTenant>>,
+    /// Per-tenant request counts in the current one-minute window
+    windows: Mutex<HashMap<String, (u32, Instant)>>,
+    ttl: Duration,
+    require_provisioned: bool,
+}
+
+impl TenantDirectory {
+    pub fn new(repo: Arc<dyn TenantRepository>, config: &TenancyConfig) -> Self {
+        Self {
+            repo,
+            cache: RwLock::new(HashMap::new()),
+            windows: Mutex::new(HashMap::new()),
+            ttl: Duration::from_secs(config.cache_ttl_seconds),
+            require_provisioned: config.require_provisioned,
+        }
+    }
+
+    pub fn repository(&self) -> &dyn TenantRepository {
+        self.repo.as_ref()
+    }
+
+    /// Whether requests for unprovisioned tenants are rejected
+    pub fn requires_provisioning(&self) -> bool {
+        self.require_provisioned
+    }
+
+    /// Provisioned tenant with `id`, if any
+    pub async fn lookup(&self, id: &str) -> DatabaseResult<Option<Tenant>> {
+        if let Some(cached) = self
+            .cache
+            .read()
+            .map_err(|_| DatabaseError::LockError)?
+            .get(id)
+            .filter(|cached| cached.expires_at > Instant::now())
+        {
+            return Ok(cached.tenant.clone());
+        }
+
+        let tenant = match self.repo.get(id).await {
+            Ok(tenant) => Some(tenant),
+            Err(DatabaseError::NotFound) => None,
+            Err(e) => return Err(e),
+        };
+
+        self.cache
+            .write()
+            .map_err(|_| DatabaseError::LockError)?
+            .insert(
+                id.to_string(),
+                CachedTenant {
+                    tenant: tenant.clone(),
+                    expires_at: Instant::now() + self.ttl,
+                },
+            );
+        Ok(tenant)
+    }
+
+    /// Drop the cached entry for `id` after the tenant changed
+    pub fn invalidate(&self, id: &str) {
+        if let Ok(mut cache) = self.cache.write() {
+            cache.remove(id);
+        }
+        if let Ok(mut windows) = self.windows.lock() {
+            windows.remove(id);
+        }
+    }
+
+    /// Count a request against the tenant's rate limit; false once it is exceeded
+    pub fn check_rate_limit(&self, tenant: &Tenant) -> bool {
+        let Some(limit) = tenant.quotas.requests_per_minute else {
+            return true;
+        };
+        let Ok(mut windows) = self.windows.lock() else {
+            return true;
+        };
+
+        let now = Instant::now();
+        let (count, reset_at) = windows
+            .entry(tenant.id.clone())
+            .or_insert((0, now + Duration::from_secs(60)));
+        if now >= *reset_at {
+            *count = 0;
+            *reset_at = now + Duration::from_secs(60);
+        }
+        if *count >= limit {
+            return false;
+        }
+        *count += 1;
+        true
+    }
+}
+
+impl Default for TenantDirectory {
+    fn default() -> Self {
+        Self::new(Arc::new(InMemoryTenantRepository::new()), &TenancyConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tenant_id_validation() {
+        assert!(is_valid_tenant_id("acme"));
+        assert!(is_valid_tenant_id("acme-corp_2"));
+        assert!(!is_valid_tenant_id(""));
+        assert!(!is_valid_tenant_id("Acme"));
+        assert!(!is_valid_tenant_id("-acme"));
+        assert!(!is_valid_tenant_id("acme; DROP SCHEMA"));
+        assert!(!is_valid_tenant_id(&"a".repeat(MAX_TENANT_ID_LENGTH + 1)));
+    }
+
+    #[tokio::test]
+    async fn test_tenant_scope() {
+        assert_eq!(current_tenant(), None);
+        let inside = with_tenant("acme".to_string(), async { current_tenant() }).await;
+        assert_eq!(inside.as_deref(), Some("acme"));
+        assert_eq!(with_optional_tenant(None, async { current_tenant() }).await, None);
+    }
+
+    #[test]
+    fn test_api_keys() {
+        let key = generate_api_key("acme");
+        assert_eq!(api_key_tenant(&key), Some("acme"));
+        assert_ne!(generate_api_key("acme"), key);
+        assert_eq!(hash_api_key(&key), hash_api_key(&key));
+        assert_eq!(hash_api_key(&key).len(), 64);
+
+        assert_eq!(api_key_tenant("ftk_acme"), None);
+        assert_eq!(api_key_tenant("ftk_../etc.secret"), None);
+        assert_eq!(api_key_tenant("acme.secret"), None);
+    }
+
+    #[tokio::test]
+    async fn test_directory_caches_lookups() {
+        let repo = Arc::new(InMemoryTenantRepository::new());
+        let directory = TenantDirectory::new(repo.clone(), &TenancyConfig::default());
+        assert!(directory.lookup("acme").await.unwrap().is_none());
+
+        let tenant = Tenant {
+            id: "acme".to_string(),
+            name: "Acme".to_string(),
+            status: crate::models::TenantStatus::Active,
+            quotas: crate::models::TenantQuotas {
+                max_items: None,
+                requests_per_minute: Some(2),
+            },
+            api_key_hash: String::new(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        };
+        repo.create(tenant.clone()).await.unwrap();
+
+        // The miss is still cached until invalidated
+        assert!(directory.lookup("acme").await.unwrap().is_none());
+        directory.invalidate("acme");
+        assert!(directory.lookup("acme").await.unwrap().is_some());
+
+        assert!(directory.check_rate_limit(&tenant));
+        assert!(directory.check_rate_limit(&tenant));
+        assert!(!directory.check_rate_limit(&tenant));
+    }
+}
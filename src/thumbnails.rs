@@ -0,0 +1,112 @@
+use reqwest::header::CONTENT_TYPE;
+
+use crate::{config::AttachmentsConfig, http_client::HttpClient};
+
+/// Object key of the thumbnail `width` pixels wide of the attachment stored
+/// at `key`
+pub fn key(key: &str, width: u32) -> String {
+    format!("{key}/thumbnails/{width}")
+}
+
+/// Whether thumbnails are rendered of files of `content_type`: raster
+/// images, as vector images make their own thumbnails
+pub fn renders(content_type: &str) -> bool {
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    essence.starts_with("image/") && essence != "image/svg+xml"
+}
+
+/// Renders thumbnails of image attachments with an HTTP service
+///
+/// Images are posted to the service with the `width` to scale them to as a
+/// query parameter, and it answers with the thumbnail, as the `/thumbnail`
+/// endpoint of imaginary does.
+pub struct Thumbnailer {
+    url: String,
+    sizes: Vec<u32>,
+    http: HttpClient,
+}
+
+impl Thumbnailer {
+    /// The configured thumbnail service, if any
+    pub fn from_config(config: &AttachmentsConfig, http: HttpClient) -> Option<Self> {
+        config.thumbnail_url.as_ref().map(|url| Self {
+            url: url.clone(),
+            sizes: config.thumbnail_sizes.clone(),
+            http,
+        })
+    }
+
+    /// Widths in pixels thumbnails are rendered at
+    pub fn sizes(&self) -> &[u32] {
+        &self.sizes
+    }
+
+    /// Thumbnail of the image `contents` scaled to `width` pixels, and its
+    /// content type
+    pub async fn render(&self, contents: &[u8], width: u32) -> Result<(Vec<u8>, String), String> {
+        let request = self
+            .http
+            .post(&self.url)
+            .query(&[("width", width)])
+            .header(CONTENT_TYPE, "application/octet-stream")
+            .body(contents.to_vec());
+        let response = self.http.send(request).await.map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("thumbnail service answered {}", response.status()));
+        }
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| renders(value))
+            .ok_or("thumbnail service answered without an image")?
+            .to_string();
+        let thumbnail = response.bytes().await.map_err(|e| e.to_string())?;
+        Ok((thumbnail.to_vec(), content_type))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_raster_images_get_thumbnails() {
+        assert!(renders("image/png"));
+        assert!(renders("Image/JPEG; q=0.9"));
+        assert!(!renders("image/svg+xml"));
+        assert!(!renders("application/pdf"));
+        assert_eq!(key("items/1/a", 128), "items/1/a/thumbnails/128");
+    }
+
+    #[tokio::test]
+    async fn test_images_are_posted_with_the_width() {
+        use axum::{extract::Query, routing::post, Router};
+        use std::collections::HashMap;
+
+        let app = Router::new().route(
+            "/thumbnail",
+            post(|Query(query): Query<HashMap<String, String>>, body: String| async move {
+                ([(CONTENT_TYPE, "image/png")], format!("{body}@{}", query["width"]))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let config = AttachmentsConfig {
+            thumbnail_url: Some(format!("http://{addr}/thumbnail")),
+            ..Default::default()
+        };
+        let thumbnailer = Thumbnailer::from_config(&config, HttpClient::default()).unwrap();
+        assert_eq!(thumbnailer.sizes(), [128, 512]);
+        let (thumbnail, content_type) = thumbnailer.render(b"image", 128).await.unwrap();
+        assert_eq!(thumbnail, b"image@128");
+        assert_eq!(content_type, "image/png");
+    }
+}
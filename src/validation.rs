@@ -1,33 +1,320 @@
 use axum::{
+    body::Body,
     extract::{rejection::JsonRejection, FromRequest, Request},
     http::StatusCode,
     response::{IntoResponse, Response},
     Json,
 };
+use regex::Regex;
 use serde::de::DeserializeOwned;
-use validator::{Validate, ValidationError, ValidationErrors};
+use serde_json::Value;
+use std::{
+    any::{Any, TypeId},
+    borrow::Cow,
+    collections::HashMap,
+    sync::Arc,
+};
+use utoipa::{
+    openapi::{schema::Schema, RefOr},
+    ToSchema,
+};
+use validator::{Validate, ValidationError, ValidationErrors, ValidationErrorsKind};
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::{
+    config::{ItemValidationConfig, SanitizationConfig},
+    jsonapi::{self, RequestDocument},
+    models::{CreateItemRequest, UpdateItemRequest},
+};
 
 /// A custom extractor that validates JSON payloads
+///
+/// Payloads are first cleaned by any sanitizer registered for their type in
+/// the request's [`ValidationRules`], then checked against their `Validate`
+/// rules and the registered ones, so rules see the text that will be stored.
+/// JSON:API request documents are accepted too, for their attributes.
+/// Fields `T` does not declare are ignored, unless [`UnknownFields::Reject`]
+/// is set for the route or in the rules.
 pub struct ValidatedJson<T>(pub T);
 
 impl<T, S> FromRequest<S> for ValidatedJson<T>
 where
-    T: DeserializeOwned + Validate,
+    T: DeserializeOwned + Validate + ToSchema + 'static,
     S: Send + Sync,
 {
     type Rejection = ValidationRejection;
 
-    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
-        let Json(value) = Json::<T>::from_request(req, state)
-            .await
-            .map_err(ValidationRejection::Json)?;
+    async fn from_request(mut req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let rules = req.extensions().get::<Arc<ValidationRules>>().cloned();
+        let unknown_fields = req
+            .extensions()
+            .get::<UnknownFields>()
+            .copied()
+            .or(rules.as_ref().map(|rules| rules.unknown_fields))
+            .unwrap_or_default();
+        if unknown_fields == UnknownFields::Reject {
+            req = reject_unknown_fields::<T, S>(req, state).await?;
+        }
+        // JSON:API documents carry the payload as the resource's attributes
+        let mut value = if jsonapi::sends_json_api(req.headers()) {
+            let Json(document) = Json::<RequestDocument<T>>::from_request(req, state)
+                .await
+                .map_err(ValidationRejection::Json)?;
+            document.data.attributes
+        } else {
+            let Json(value) = Json::<T>::from_request(req, state)
+                .await
+                .map_err(ValidationRejection::Json)?;
+            value
+        };
 
+        if let Some(rules) = &rules {
+            rules.sanitize(&mut value);
+        }
         value.validate().map_err(ValidationRejection::Validation)?;
+        if let Some(rules) = rules {
+            rules
+                .check(&value)
+                .map_err(ValidationRejection::Validation)?;
+        }
 
         Ok(ValidatedJson(value))
     }
 }
 
+/// How [`ValidatedJson`] treats body fields its type does not declare
+///
+/// Set for every route with [`ValidationRules::set_unknown_fields`], or for
+/// one route by adding it as an `Extension` layer, which takes precedence.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnknownFields {
+    /// Skip them, as serde does by default
+    #[default]
+    Ignore,
+    /// Fail validation, naming each of them
+    Reject,
+}
+
+/// Fail if the body has fields `T` does not declare, returning a request
+/// the body can be read from again
+async fn reject_unknown_fields<T, S>(
+    req: Request,
+    state: &S,
+) -> Result<Request, ValidationRejection>
+where
+    T: ToSchema,
+    S: Send + Sync,
+{
+    let json_api = jsonapi::sends_json_api(req.headers());
+    let (parts, body) = req.into_parts();
+    let Json(body) = Json::<Value>::from_request(Request::from_parts(parts.clone(), body), state)
+        .await
+        .map_err(ValidationRejection::Json)?;
+
+    let payload = if json_api {
+        &body["data"]["attributes"]
+    } else {
+        &body
+    };
+    let errors = unknown_fields::<T>(payload);
+    if !errors.is_empty() {
+        return Err(ValidationRejection::Validation(errors));
+    }
+
+    Ok(Request::from_parts(parts, Body::from(body.to_string())))
+}
+
+/// An `unknown_field` error for each field of `payload` missing from `T`'s
+/// schema; bodies that are not objects are left to deserialization
+fn unknown_fields<T: ToSchema>(payload: &Value) -> ValidationErrors {
+    let mut errors = ValidationErrors::new();
+    let (Value::Object(payload), RefOr::T(Schema::Object(schema))) = (payload, T::schema()) else {
+        return errors;
+    };
+
+    for field in payload.keys() {
+        if !schema.properties.contains_key(field) {
+            let error = ValidationError::new("unknown_field").with_message("Unknown field".into());
+            errors
+                .0
+                .insert(Cow::Owned(field.clone()), ValidationErrorsKind::Field(vec![error]));
+        }
+    }
+    errors
+}
+
+type Rule = Box<dyn Fn(&dyn Any, &mut ValidationErrors) + Send + Sync>;
+type Transform = Box<dyn Fn(&mut dyn Any) + Send + Sync>;
+
+/// Validation rules added at startup for request types, keyed by type
+///
+/// Lets deployments tighten what the models accept without changing them,
+/// from configuration with [`ValidationRules::from_config`] or in code with
+/// [`ValidationRules::add`].
+#[derive(Default)]
+pub struct ValidationRules {
+    rules: HashMap<TypeId, Vec<Rule>>,
+    sanitizers: HashMap<TypeId, Transform>,
+    unknown_fields: UnknownFields,
+}
+
+impl ValidationRules {
+    /// No extra rules, with item text cleaned by `sanitizer`
+    pub fn sanitizing(sanitizer: Sanitizer) -> Self {
+        let mut rules = Self::default();
+        let update = sanitizer.clone();
+        rules.add_sanitizer(move |request: &mut CreateItemRequest| {
+            *request = std::mem::take(request).sanitize(&sanitizer);
+        });
+        rules.add_sanitizer(move |request: &mut UpdateItemRequest| {
+            *request = std::mem::take(request).sanitize(&update);
+        });
+        rules
+    }
+
+    /// Clean every `T` with `sanitizer` before it is validated, replacing any
+    /// sanitizer registered for `T` before
+    pub fn add_sanitizer<T: 'static>(
+        &mut self,
+        sanitizer: impl Fn(&mut T) + Send + Sync + 'static,
+    ) -> &mut Self {
+        let sanitizer: Transform = Box::new(move |value| {
+            if let Some(value) = value.downcast_mut::<T>() {
+                sanitizer(value);
+            }
+        });
+        self.sanitizers.insert(TypeId::of::<T>(), sanitizer);
+        self
+    }
+
+    /// Clean `value` with the sanitizer registered for `T`, if any
+    pub fn sanitize<T: 'static>(&self, value: &mut T) {
+        if let Some(sanitizer) = self.sanitizers.get(&TypeId::of::<T>()) {
+            sanitizer(value);
+        }
+    }
+
+    /// Check `field` of every `T` with `rule`
+    pub fn add<T: 'static>(
+        &mut self,
+        field: &'static str,
+        rule: impl Fn(&T) -> Result<(), ValidationError> + Send + Sync + 'static,
+    ) -> &mut Self {
+        let rule: Rule = Box::new(move |value, errors| {
+            if let Some(Err(error)) = value.downcast_ref::<T>().map(&rule) {
+                errors.add(field, error);
+            }
+        });
+        self.rules.entry(TypeId::of::<T>()).or_default().push(rule);
+        self
+    }
+
+    /// Treat fields request types do not declare as `unknown_fields` says,
+    /// on routes that do not choose for themselves
+    pub fn set_unknown_fields(&mut self, unknown_fields: UnknownFields) -> &mut Self {
+        self.unknown_fields = unknown_fields;
+        self
+    }
+
+    /// Run every rule registered for `T`, collecting all failures
+    pub fn check<T: 'static>(&self, value: &T) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        for rule in self.rules.get(&TypeId::of::<T>()).into_iter().flatten() {
+            rule(value, &mut errors);
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Rules and sanitization for item requests from configuration
+    ///
+    /// Updates are held to the same rules for the fields they change.
+    pub fn from_config(
+        config: &ItemValidationConfig,
+        sanitization: &SanitizationConfig,
+    ) -> Result<Self, regex::Error> {
+        let mut rules = Self::sanitizing(Sanitizer::new(sanitization));
+
+        if let Some(pattern) = &config.name_pattern {
+            let pattern = Regex::new(pattern)?;
+            let create = pattern.clone();
+            rules.add("name", move |request: &CreateItemRequest| {
+                matches_pattern(&create, &request.name)
+            });
+            rules.add("name", move |request: &UpdateItemRequest| {
+                request
+                    .name
+                    .as_deref()
+                    .map_or(Ok(()), |name| matches_pattern(&pattern, name))
+            });
+        }
+
+        if !config.banned_words.is_empty() {
+            let banned = Arc::new(config.banned_words.clone());
+            let (name, description) = (banned.clone(), banned.clone());
+            rules.add("name", move |request: &CreateItemRequest| {
+                no_banned_words(&name, Some(&request.name))
+            });
+            rules.add("description", move |request: &CreateItemRequest| {
+                no_banned_words(&description, request.description.as_deref())
+            });
+            let name = banned.clone();
+            rules.add("name", move |request: &UpdateItemRequest| {
+                no_banned_words(&name, request.name.as_deref())
+            });
+            rules.add("description", move |request: &UpdateItemRequest| {
+                no_banned_words(&banned, request.description.as_deref())
+            });
+        }
+
+        if config.reject_unknown_fields {
+            rules.set_unknown_fields(UnknownFields::Reject);
+        }
+
+        if config.require_description {
+            rules.add("description", |request: &CreateItemRequest| {
+                validate_not_empty(request.description.as_deref().unwrap_or_default())
+            });
+            rules.add("description", |request: &UpdateItemRequest| {
+                request
+                    .description
+                    .as_deref()
+                    .map_or(Ok(()), validate_not_empty)
+            });
+        }
+
+        Ok(rules)
+    }
+}
+
+fn matches_pattern(pattern: &Regex, value: &str) -> Result<(), ValidationError> {
+    if pattern.is_match(value) {
+        return Ok(());
+    }
+    let mut error = ValidationError::new("pattern");
+    error.message = Some(Cow::Owned(format!("Must match {}", pattern.as_str())));
+    Err(error)
+}
+
+fn no_banned_words(banned: &[String], value: Option<&str>) -> Result<(), ValidationError> {
+    let Some(value) = value.map(str::to_lowercase) else {
+        return Ok(());
+    };
+    let found = value
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| banned.iter().any(|banned| banned == word));
+    if !found {
+        return Ok(());
+    }
+    let mut error = ValidationError::new("banned_word");
+    error.message = Some(Cow::Borrowed("Contains a word that is not allowed"));
+    Err(error)
+}
+
 /// Custom rejection type for validation errors
 #[derive(Debug)]
 pub enum ValidationRejection {
@@ -43,6 +330,20 @@ impl IntoResponse for ValidationRejection {
         use chrono::Utc;
 
         let (status, error_response) = match self {
+            ValidationRejection::Json(rejection)
+                if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE =>
+            {
+                (
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    ErrorResponse {
+                        error: ErrorCode::PayloadTooLarge,
+                        message: "Request body is too large".to_string(),
+                        details: None,
+                        timestamp: Utc::now(),
+                        request_id: None,
+                    },
+                )
+            }
             ValidationRejection::Json(rejection) => {
                 let message = match rejection {
                     JsonRejection::JsonDataError(_) => "Invalid JSON format",
@@ -133,6 +434,92 @@ pub fn validate_not_empty(s: &str) -> Result<(), ValidationError> {
     Ok(())
 }
 
+/// Cleans up item text before it is stored, as configured
+///
+/// Text is stripped of unwanted characters, normalized to NFC, trimmed and
+/// then, if enabled, HTML-escaped. Without escaping, cleaning is idempotent.
+#[derive(Debug, Clone, Default)]
+pub struct Sanitizer {
+    config: SanitizationConfig,
+}
+
+impl Sanitizer {
+    pub fn new(config: &SanitizationConfig) -> Self {
+        Self {
+            config: config.clone(),
+        }
+    }
+
+    pub fn clean(&self, s: &str) -> String {
+        let stripped = self.strip(s);
+        let normalized: String = if self.config.normalize_nfc {
+            stripped.nfc().collect()
+        } else {
+            stripped
+        };
+        let trimmed = normalized.trim();
+        if self.config.escape_html {
+            escape_html(trimmed)
+        } else {
+            trimmed.to_string()
+        }
+    }
+
+    /// Clean `s`, dropping it when nothing is left
+    pub fn clean_optional(&self, s: Option<String>) -> Option<String> {
+        s.map(|s| self.clean(&s)).filter(|s| !s.is_empty())
+    }
+
+    fn strip(&self, s: &str) -> String {
+        let chars: Vec<char> = s.chars().collect();
+        let mut out = String::with_capacity(s.len());
+        for (i, &c) in chars.iter().enumerate() {
+            let keep = if is_zero_width(c) {
+                // Joiners hold emoji sequences such as family emoji together
+                !self.config.strip_zero_width
+                    || (c == ZERO_WIDTH_JOINER
+                        && out.chars().next_back().is_some_and(is_pictographic)
+                        && chars.get(i + 1).copied().is_some_and(is_pictographic))
+            } else if c.is_control() && c != '\n' && c != '\t' {
+                !self.config.strip_control
+            } else {
+                true
+            };
+            if keep {
+                out.push(c);
+            }
+        }
+        out
+    }
+}
+
+const ZERO_WIDTH_JOINER: char = '\u{200D}';
+
+fn is_zero_width(c: char) -> bool {
+    matches!(c, '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{2060}' | '\u{FEFF}')
+}
+
+/// Emoji and the variation selector and symbols that take part in emoji
+/// sequences
+fn is_pictographic(c: char) -> bool {
+    matches!(c, '\u{2600}'..='\u{27BF}' | '\u{2B00}'..='\u{2BFF}' | '\u{FE0F}' | '\u{1F000}'..='\u{1FAFF}')
+}
+
+fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#x27;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
 /// Sanitize string by trimming whitespace
 pub fn sanitize_string(s: String) -> String {
     s.trim().to_string()
@@ -149,3 +536,150 @@ pub fn sanitize_optional_string(s: Option<String>) -> Option<String> {
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create(name: &str, description: Option<&str>) -> CreateItemRequest {
+        CreateItemRequest {
+            name: name.to_string(),
+            description: description.map(str::to_string),
+            metadata: None,
+            custom_fields: Default::default(),
+            status: Default::default(),
+            publish_at: None,
+            external_id: None,
+            source: None,
+        }
+    }
+
+    #[test]
+    fn test_configured_item_rules() {
+        let rules = ValidationRules::from_config(
+            &ItemValidationConfig {
+                name_pattern: Some("^[A-Z]".to_string()),
+                banned_words: vec!["spam".to_string()],
+                require_description: true,
+                reject_unknown_fields: false,
+            },
+            &SanitizationConfig::default(),
+        )
+        .unwrap();
+
+        assert!(rules.check(&create("Widget", Some("A widget"))).is_ok());
+        let errors = rules
+            .check(&create("widget", Some("Cheap SPAM")))
+            .unwrap_err();
+        let fields = errors.field_errors();
+        assert_eq!(fields["name"][0].code, "pattern");
+        assert_eq!(fields["description"][0].code, "banned_word");
+        assert!(rules.check(&create("Widget", None)).is_err());
+
+        // Updates are only checked on the fields they change
+        let update = UpdateItemRequest {
+            name: None,
+            description: Some("spam".to_string()),
+            metadata: None,
+            custom_fields: Default::default(),
+            regenerate_slug: false,
+            status: Default::default(),
+            publish_at: None,
+        };
+        let errors = rules.check(&update).unwrap_err();
+        assert!(!errors.field_errors().contains_key("name"));
+    }
+
+    #[test]
+    fn test_sanitizer_cleans_text() {
+        let sanitizer = Sanitizer::default();
+        // Decomposed "é", a zero-width space, a bell and a byte order mark
+        assert_eq!(sanitizer.clean(" \u{FEFF}Cafe\u{301}\u{200B} bar\u{7}\n"), "Café bar");
+        assert_eq!(sanitizer.clean("line one\nline\ttwo"), "line one\nline\ttwo");
+        // Joiners inside emoji sequences are kept, others removed
+        assert_eq!(sanitizer.clean("👨\u{200D}👩\u{200D}👧"), "👨\u{200D}👩\u{200D}👧");
+        assert_eq!(sanitizer.clean("a\u{200D}b"), "ab");
+        assert_eq!(sanitizer.clean_optional(Some("\u{200B} ".to_string())), None);
+
+        let escaping = Sanitizer::new(&SanitizationConfig {
+            escape_html: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            escaping.clean("<b>\"Tom\" & 'Jerry'</b>"),
+            "&lt;b&gt;&quot;Tom&quot; &amp; &#x27;Jerry&#x27;&lt;/b&gt;"
+        );
+
+        let off = Sanitizer::new(&SanitizationConfig {
+            normalize_nfc: false,
+            strip_control: false,
+            strip_zero_width: false,
+            escape_html: false,
+        });
+        assert_eq!(off.clean(" a\u{200B}\u{7} "), "a\u{200B}\u{7}");
+    }
+
+    #[test]
+    fn test_rules_apply_only_to_their_type() {
+        let mut rules = ValidationRules::default();
+        rules.add("name", |request: &CreateItemRequest| {
+            if request.name.len() > 3 {
+                Ok(())
+            } else {
+                Err(ValidationError::new("too_short"))
+            }
+        });
+
+        assert!(rules.check(&create("abc", None)).is_err());
+        assert!(rules.check(&"abc".to_string()).is_ok());
+    }
+
+    mod fuzz {
+        use super::*;
+        use proptest::prelude::*;
+        use unicode_normalization::is_nfc;
+
+        /// Text mixing plain characters with the ones the sanitizer handles
+        fn text() -> impl Strategy<Value = String> {
+            let special = prop::sample::select(vec![
+                '\u{0}', '\u{7}', '\u{1B}', '\u{7F}', '\u{85}', '\n', '\t', ' ', '\u{200B}',
+                '\u{200C}', '\u{200D}', '\u{2060}', '\u{FEFF}', '\u{301}', '\u{308}', 'e', 'A',
+                '👨', '\u{FE0F}', '<', '&', '"', '\'',
+            ]);
+            prop::collection::vec(prop_oneof![any::<char>(), special], 0..64)
+                .prop_map(|chars| chars.into_iter().collect())
+        }
+
+        proptest! {
+            #[test]
+            fn clean_is_idempotent(s in text()) {
+                let sanitizer = Sanitizer::default();
+                let once = sanitizer.clean(&s);
+                prop_assert_eq!(sanitizer.clean(&once), once.clone());
+            }
+
+            #[test]
+            fn clean_output_is_normalized_and_stripped(s in text()) {
+                let cleaned = Sanitizer::default().clean(&s);
+                prop_assert!(is_nfc(&cleaned));
+                prop_assert_eq!(cleaned.trim(), cleaned.as_str());
+                prop_assert!(!cleaned
+                    .chars()
+                    .any(|c| c.is_control() && c != '\n' && c != '\t'));
+                prop_assert!(!cleaned
+                    .chars()
+                    .any(|c| is_zero_width(c) && c != ZERO_WIDTH_JOINER));
+            }
+
+            #[test]
+            fn escaped_output_has_no_markup(s in text()) {
+                let sanitizer = Sanitizer::new(&SanitizationConfig {
+                    escape_html: true,
+                    ..Default::default()
+                });
+                let cleaned = sanitizer.clean(&s);
+                prop_assert!(!cleaned.contains(['<', '>', '"', '\'']));
+            }
+        }
+    }
+}
@@ -0,0 +1,384 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tokio::{sync::broadcast::error::RecvError, task::JoinHandle};
+use tracing::{debug, warn};
+use utoipa::ToSchema;
+
+use crate::{
+    clock::{system_clock, SharedClock},
+    config::ViewsConfig,
+    db::{DatabaseError, DatabaseResult, ItemFilter, ItemRepository},
+    events::{EventBus, ItemEvent},
+    models::Item,
+    odata::{self, ODataError},
+    pagination::{Page, MAX_PAGE_LIMIT},
+    query::Sort,
+    tenancy::{current_tenant, with_optional_tenant},
+};
+
+/// Whether `name` can name a view: lowercase ASCII letters, digits, `-` and
+/// `_`, starting with a letter or digit
+pub fn is_valid_view_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+/// A view's items as of its last refresh
+pub struct Snapshot {
+    /// Up to the view's item limit, in the view's order
+    pub items: Vec<Arc<Item>>,
+    /// Items matching the view, which may be more than it keeps
+    pub total: usize,
+    pub refreshed_at: DateTime<Utc>,
+    /// Set when a change that may affect the view arrives after the refresh
+    stale: AtomicBool,
+}
+
+impl Snapshot {
+    pub fn is_stale(&self) -> bool {
+        self.stale.load(Ordering::Relaxed)
+    }
+}
+
+/// One configured view and its snapshot per tenant
+struct View {
+    filter: ItemFilter,
+    order: Vec<Sort>,
+    snapshots: Mutex<HashMap<Option<String>, Arc<Snapshot>>>,
+    /// Held while refreshing, so concurrent readers wait for one refresh
+    /// rather than each running the query
+    refreshing: tokio::sync::Mutex<()>,
+}
+
+impl View {
+    fn snapshot(&self, tenant: &Option<String>) -> DatabaseResult<Option<Arc<Snapshot>>> {
+        let snapshots = self
+            .snapshots
+            .lock()
+            .map_err(|_| DatabaseError::LockError)?;
+        Ok(snapshots.get(tenant).cloned())
+    }
+
+    /// Whether `item` is or was in the view, judging by its latest version
+    fn affected_by(&self, snapshot: &Snapshot, item: &Item) -> bool {
+        self.filter.matches(item) || snapshot.items.iter().any(|kept| kept.id == item.id)
+    }
+}
+
+/// Named list queries kept materialized, refreshed when changes affect them
+///
+/// A view is computed for a tenant the first time it is read there. Changes
+/// arriving on the event bus mark the views they may affect as stale; a
+/// stale view is still served, with its age, until it has been stale for the
+/// refresh interval, when the maintenance task or the next read refreshes it.
+/// Views that nothing changed are never queried again.
+pub struct ListViews {
+    views: HashMap<String, View>,
+    refresh_interval: Duration,
+    max_items: usize,
+    clock: SharedClock,
+}
+
+impl ListViews {
+    /// Views defined in `config`, whose queries are checked by config
+    /// validation
+    pub fn from_config(config: &ViewsConfig) -> Result<Self, ODataError> {
+        let mut views = HashMap::new();
+        for definition in &config.definitions {
+            let conditions = definition
+                .filter
+                .as_deref()
+                .map(odata::parse_filter)
+                .transpose()?;
+            let order = definition
+                .orderby
+                .as_deref()
+                .map(odata::parse_orderby)
+                .transpose()?;
+            views.insert(
+                definition.name.clone(),
+                View {
+                    filter: ItemFilter::default().with_conditions(conditions.unwrap_or_default()),
+                    order: order.unwrap_or_default(),
+                    snapshots: Mutex::default(),
+                    refreshing: tokio::sync::Mutex::new(()),
+                },
+            );
+        }
+        Ok(Self {
+            views,
+            refresh_interval: Duration::from_secs(config.refresh_seconds),
+            max_items: config.max_items,
+            clock: system_clock(),
+        })
+    }
+
+    /// Measure staleness with `clock` instead of the system clock
+    #[must_use]
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The view `name` for the current tenant, refreshed first if it was
+    /// never computed or has been stale too long; `None` for unknown views
+    pub async fn get(
+        &self,
+        name: &str,
+        repo: &dyn ItemRepository,
+    ) -> DatabaseResult<Option<Arc<Snapshot>>> {
+        let Some(view) = self.views.get(name) else {
+            return Ok(None);
+        };
+        let tenant = current_tenant();
+        if let Some(snapshot) = view.snapshot(&tenant)?.filter(|s| !self.due(s)) {
+            return Ok(Some(snapshot));
+        }
+
+        let _refreshing = view.refreshing.lock().await;
+        // Another reader may have refreshed it while this one waited
+        if let Some(snapshot) = view.snapshot(&tenant)?.filter(|s| !self.due(s)) {
+            return Ok(Some(snapshot));
+        }
+        self.refresh(name, view, tenant, repo).await.map(Some)
+    }
+
+    /// How long ago `snapshot` was refreshed
+    pub fn age(&self, snapshot: &Snapshot) -> Duration {
+        (self.clock.now() - snapshot.refreshed_at)
+            .to_std()
+            .unwrap_or_default()
+    }
+
+    fn due(&self, snapshot: &Snapshot) -> bool {
+        snapshot.is_stale() && self.age(snapshot) >= self.refresh_interval
+    }
+
+    async fn refresh(
+        &self,
+        name: &str,
+        view: &View,
+        tenant: Option<String>,
+        repo: &dyn ItemRepository,
+    ) -> DatabaseResult<Arc<Snapshot>> {
+        let refreshed_at = self.clock.now();
+        let mut items = Vec::new();
+        let mut total = 0;
+        while items.len() < self.max_items {
+            let limit = MAX_PAGE_LIMIT.min(self.max_items - items.len());
+            let page = Page::offset(limit, items.len())
+                .and_then(|page| page.ordered_by(view.order.clone()))
+                .expect("view pages stay within the offset limit");
+            let (page_items, matching) = repo.list_with_total(&view.filter, &page).await?;
+            total = matching;
+            let last = page_items.len() < limit;
+            items.extend(page_items);
+            if last {
+                break;
+            }
+        }
+        debug!(view = name, items = items.len(), "Refreshed list view");
+
+        let snapshot = Arc::new(Snapshot {
+            items,
+            total,
+            refreshed_at,
+            stale: AtomicBool::new(false),
+        });
+        view.snapshots
+            .lock()
+            .map_err(|_| DatabaseError::LockError)?
+            .insert(tenant, snapshot.clone());
+        Ok(snapshot)
+    }
+
+    /// Mark the views `event` may affect as stale
+    pub fn observe(&self, event: &ItemEvent) {
+        let item = &event.data;
+        for view in self.views.values() {
+            // Reads of a view whose snapshots cannot be locked fail anyway
+            if let Ok(Some(snapshot)) = view.snapshot(&item.tenant_id) {
+                if view.affected_by(&snapshot, item) {
+                    snapshot.stale.store(true, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    fn mark_all_stale(&self) {
+        for view in self.views.values() {
+            let Ok(snapshots) = view.snapshots.lock() else {
+                continue;
+            };
+            for snapshot in snapshots.values() {
+                snapshot.stale.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Refresh every snapshot that has been stale for the refresh interval
+    pub async fn refresh_due(&self, repo: &dyn ItemRepository) {
+        for (name, view) in &self.views {
+            let due: Vec<_> = match view.snapshots.lock() {
+                Ok(snapshots) => snapshots
+                    .iter()
+                    .filter(|(_, snapshot)| self.due(snapshot))
+                    .map(|(tenant, _)| tenant.clone())
+                    .collect(),
+                Err(_) => {
+                    let e = DatabaseError::LockError;
+                    warn!(view = name.as_str(), "Failed to refresh list view: {}", e);
+                    continue;
+                }
+            };
+            for tenant in due {
+                let _refreshing = view.refreshing.lock().await;
+                let refresh = self.refresh(name, view, tenant.clone(), repo);
+                if let Err(e) = with_optional_tenant(tenant, refresh).await {
+                    warn!(view = name.as_str(), "Failed to refresh list view: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Follow changes on `bus` and refresh views as they fall due
+    pub fn spawn(self: Arc<Self>, bus: &EventBus, repo: Arc<dyn ItemRepository>) -> JoinHandle<()> {
+        let mut events = bus.subscribe();
+        tokio::spawn(async move {
+            // Half the interval, so views are refreshed within 1.5 intervals
+            let mut ticks =
+                tokio::time::interval((self.refresh_interval / 2).max(Duration::from_secs(1)));
+            loop {
+                tokio::select! {
+                    event = events.recv() => match event {
+                        Ok(event) => self.observe(&event),
+                        Err(RecvError::Lagged(missed)) => {
+                            warn!("List views missed {} changes; marking every view stale", missed);
+                            self.mark_all_stale();
+                        }
+                        Err(RecvError::Closed) => return,
+                    },
+                    _ = ticks.tick() => self.refresh_due(repo.as_ref()).await,
+                }
+            }
+        })
+    }
+}
+
+/// A page of a view's items with how fresh they are
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ViewResponse {
+    pub name: String,
+    #[schema(value_type = Vec<Item>)]
+    pub items: Vec<Arc<Item>>,
+    /// Items matching the view when it was refreshed
+    pub total: usize,
+    /// Items the view keeps; pages beyond them are empty
+    pub kept: usize,
+    pub limit: usize,
+    pub offset: usize,
+    pub refreshed_at: DateTime<Utc>,
+    /// Seconds since the view was refreshed
+    pub age_seconds: u64,
+    /// Whether items changed in ways that may affect the view since
+    pub stale: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        clock::ManualClock, config::ViewDefinition, db::InMemoryRepository, events::ItemEventType,
+        events::OutboxEvent, models::CreateItemRequest,
+    };
+
+    fn views(clock: Arc<ManualClock>) -> ListViews {
+        let config = ViewsConfig {
+            definitions: vec![ViewDefinition {
+                name: "widgets".to_string(),
+                filter: Some("startswith(name, 'Widget')".to_string()),
+                orderby: Some("name desc".to_string()),
+            }],
+            refresh_seconds: 60,
+            max_items: 150,
+        };
+        ListViews::from_config(&config).unwrap().with_clock(clock)
+    }
+
+    async fn create(repo: &InMemoryRepository, name: &str) -> Item {
+        let request = CreateItemRequest {
+            name: name.to_string(),
+            description: None,
+            metadata: None,
+            custom_fields: Default::default(),
+            status: Default::default(),
+            publish_at: None,
+            external_id: None,
+            source: None,
+        };
+        repo.create(request, None).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_views_are_refreshed_once_stale_for_the_interval() {
+        let clock = Arc::new(ManualClock::default());
+        let views = views(clock.clone());
+        let repo = InMemoryRepository::new();
+        create(&repo, "Widget A").await;
+        create(&repo, "Gadget").await;
+
+        let snapshot = views.get("widgets", &repo).await.unwrap().unwrap();
+        let names: Vec<_> = snapshot
+            .items
+            .iter()
+            .map(|item| item.name.as_str())
+            .collect();
+        assert_eq!(names, ["Widget A"]);
+        assert!(views.get("missing", &repo).await.unwrap().is_none());
+
+        // Changes outside the view leave it fresh
+        let gadget = create(&repo, "Gadget 2").await;
+        views.observe(&ItemEvent::from(&OutboxEvent::new(1, ItemEventType::Created, gadget)));
+        assert!(!snapshot.is_stale());
+
+        let widget = create(&repo, "Widget B").await;
+        views.observe(&ItemEvent::from(&OutboxEvent::new(2, ItemEventType::Created, widget)));
+        assert!(snapshot.is_stale());
+        // Served stale until the interval passes
+        let served = views.get("widgets", &repo).await.unwrap().unwrap();
+        assert_eq!(served.items.len(), 1);
+
+        clock.advance(Duration::from_secs(60));
+        let refreshed = views.get("widgets", &repo).await.unwrap().unwrap();
+        let names: Vec<_> = refreshed
+            .items
+            .iter()
+            .map(|item| item.name.as_str())
+            .collect();
+        assert_eq!(names, ["Widget B", "Widget A"]);
+        assert!(!refreshed.is_stale());
+    }
+
+    #[tokio::test]
+    async fn test_views_keep_at_most_their_item_limit() {
+        let views = views(Arc::new(ManualClock::default()));
+        let repo = InMemoryRepository::new();
+        for i in 0..160 {
+            create(&repo, &format!("Widget {i:03}")).await;
+        }
+
+        let snapshot = views.get("widgets", &repo).await.unwrap().unwrap();
+        assert_eq!((snapshot.items.len(), snapshot.total), (150, 160));
+        assert_eq!(snapshot.items[0].name, "Widget 159");
+    }
+}
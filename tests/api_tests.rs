@@ -114,6 +114,34 @@ async fn test_get_item() {
     assert_eq!(item["description"], "Test Description");
 }
 
+#[tokio::test]
+async fn test_get_item_by_slug() {
+    let state = common::create_test_state();
+    let app = ferrous::routes::create_routes(state.clone());
+
+    let created = common::create_test_item(&state.repo, "Slug Lookup Item", None).await;
+    assert_eq!(created.slug, "slug-lookup-item");
+
+    let response = app
+        .clone()
+        .oneshot(common::get_request("/api/v1/items/slug/slug-lookup-item"))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let item: serde_json::Value = common::response_json(response).await;
+    assert_eq!(item["id"], created.id);
+    assert_eq!(item["slug"], "slug-lookup-item");
+
+    let response = app
+        .oneshot(common::get_request("/api/v1/items/slug/missing-slug"))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
 #[tokio::test]
 async fn test_get_nonexistent_item() {
     let app = common::create_test_app().await;
@@ -152,6 +180,89 @@ async fn test_update_item() {
     assert_eq!(item["description"], "Original Description");
 }
 
+#[tokio::test]
+async fn test_update_with_stale_etag_conflicts_or_merges() {
+    let state = common::create_test_state();
+    let app = ferrous::routes::create_routes(state.clone());
+    let created = common::create_test_item(&state.repo, "Original", Some("Original")).await;
+    let uri = format!("/api/v1/items/{}", created.id);
+    let put = |uri: &str, etag: &str, body: serde_json::Value| {
+        let mut request = common::put_request(uri, body);
+        request
+            .headers_mut()
+            .insert("if-match", etag.parse().unwrap());
+        request
+    };
+
+    let response = app
+        .clone()
+        .oneshot(common::get_request(&uri))
+        .await
+        .unwrap();
+    let base = response.headers()["etag"].to_str().unwrap().to_string();
+    assert_eq!(base, "\"1\"");
+
+    // Another client renames the item
+    let response = app
+        .clone()
+        .oneshot(put(&uri, &base, json!({ "name": "Renamed elsewhere" })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["etag"], "\"2\"");
+
+    // A rename based on the old version conflicts, even with merging
+    let response = app
+        .clone()
+        .oneshot(put(&format!("{uri}?merge=fields"), &base, json!({ "name": "Renamed here" })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let conflict: serde_json::Value = common::response_json(response).await;
+    assert_eq!(conflict["error"], "CONFLICT");
+    assert_eq!(conflict["base_version"], 1);
+    assert_eq!(conflict["current"]["name"], "Renamed elsewhere");
+    assert_eq!(conflict["proposed"]["name"], "Renamed here");
+    assert_eq!(
+        conflict["diff"],
+        json!([{
+            "field": "name",
+            "current": "Renamed elsewhere",
+            "proposed": "Renamed here",
+            "conflicting": true
+        }])
+    );
+
+    // Changing only the description is rejected by default...
+    let describe = json!({ "description": "Described here" });
+    let response = app
+        .clone()
+        .oneshot(put(&uri, &base, describe.clone()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let conflict: serde_json::Value = common::response_json(response).await;
+    assert_eq!(conflict["diff"][0]["conflicting"], false);
+
+    // ...and merged into the current version on request
+    let response = app
+        .clone()
+        .oneshot(put(&format!("{uri}?merge=fields"), &base, describe))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let item: serde_json::Value = common::response_json(response).await;
+    assert_eq!(item["name"], "Renamed elsewhere");
+    assert_eq!(item["description"], "Described here");
+    assert_eq!(item["version"], 3);
+
+    let response = app
+        .oneshot(put(&uri, "not-an-etag", json!({ "name": "Renamed" })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
 #[tokio::test]
 async fn test_update_nonexistent_item() {
     let app = common::create_test_app().await;
@@ -230,231 +341,3075 @@ async fn test_list_items() {
 }
 
 #[tokio::test]
-async fn test_list_items_with_pagination() {
+async fn test_list_items_without_total() {
     let state = common::create_test_state();
     let app = ferrous::routes::create_routes(state.clone());
-
-    // Create multiple items
-    common::create_test_items(&state.repo, 5).await;
+    common::create_test_items(&state.repo, 3).await;
 
     let response = app
-        .oneshot(common::get_request("/api/v1/items?limit=2&offset=2"))
+        .oneshot(common::get_request("/api/v1/items?limit=2&include_total=false"))
         .await
         .unwrap();
 
     assert_eq!(response.status(), StatusCode::OK);
-
     let list_response: serde_json::Value = common::response_json(response).await;
     assert_eq!(list_response["items"].as_array().unwrap().len(), 2);
-    assert_eq!(list_response["total"], 5);
-    assert_eq!(list_response["limit"], 2);
-    assert_eq!(list_response["offset"], 2);
+    assert!(list_response.get("total").is_none());
+    assert!(list_response["next_cursor"].is_string());
 }
 
 #[tokio::test]
-#[ignore = "Query validation not yet implemented"]
-async fn test_invalid_pagination_params() {
-    let app = common::create_test_app().await;
+async fn test_item_stats() {
+    let state = common::create_test_state();
+    let app = ferrous::routes::create_routes(state.clone());
+    let items = common::create_test_items(&state.repo, 3).await;
+    state.repo.delete(&items[0].id).await.unwrap();
 
-    // Test negative limit
     let response = app
         .clone()
-        .oneshot(common::get_request("/api/v1/items?limit=-1"))
+        .oneshot(common::with_claims(
+            common::get_request("/api/v1/items/stats"),
+            "root",
+            &["admin"],
+        ))
         .await
         .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let stats: serde_json::Value = common::response_json(response).await;
+    assert_eq!(stats["total"], 2);
+    assert_eq!(stats["created"]["last_5m"], 3);
 
-    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
-
-    // Test limit exceeding max
+    // Statistics span every owner
     let response = app
-        .oneshot(common::get_request("/api/v1/items?limit=1000"))
+        .clone()
+        .oneshot(common::with_claims(common::get_request("/api/v1/items/stats"), "alice", &[]))
         .await
         .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
 
-    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = app
+        .oneshot(common::get_request("/api/v1/items/stats"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
 }
 
-// Rate limiting tests
 #[tokio::test]
-async fn test_rate_limit_headers() {
-    let app = common::create_test_app().await;
+async fn test_item_analytics() {
+    let state = common::create_test_state();
+    let app = ferrous::routes::create_routes(state.clone());
+    let items = common::create_test_items(&state.repo, 2).await;
+    state.repo.delete(&items[0].id).await.unwrap();
 
     let response = app
-        .oneshot(common::get_request("/api/v1/items"))
+        .clone()
+        .oneshot(common::get_request("/api/v1/items/analytics?granularity=hour"))
         .await
         .unwrap();
-
     assert_eq!(response.status(), StatusCode::OK);
-    assert!(response.headers().contains_key("X-RateLimit-Limit"));
-    assert!(response.headers().contains_key("X-RateLimit-Remaining"));
-    assert!(response.headers().contains_key("X-RateLimit-Reset"));
+    let analytics: serde_json::Value = common::response_json(response).await;
+    assert_eq!(analytics["granularity"], "hour");
+    let buckets = analytics["buckets"].as_array().unwrap();
+    assert_eq!(buckets.len(), 24);
+    let last = buckets.last().unwrap();
+    assert_eq!((last["created"].as_u64(), last["deleted"].as_u64()), (Some(2), Some(1)));
+
+    let response = app
+        .oneshot(common::get_request(
+            "/api/v1/items/analytics?granularity=hour&from=2020-01-01T00:00:00Z",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 }
 
 #[tokio::test]
-async fn test_rate_limit_multiple_requests() {
-    let app = common::create_test_app().await;
+async fn test_read_view() {
+    let views = ferrous::config::ViewsConfig {
+        definitions: vec![ferrous::config::ViewDefinition {
+            name: "by-name".to_string(),
+            filter: None,
+            orderby: Some("name desc".to_string()),
+        }],
+        ..Default::default()
+    };
+    let state = ferrous::state::AppState::new(common::create_test_repo())
+        .with_views(ferrous::views::ListViews::from_config(&views).unwrap())
+        .into_shared();
+    let app = ferrous::routes::create_routes(state.clone());
+    common::create_test_items(&state.repo, 3).await;
+    let admin = |request| common::with_claims(request, "root", &["admin"]);
 
-    // First request
     let response = app
         .clone()
-        .oneshot(common::get_request("/api/v1/items"))
+        .oneshot(admin(common::get_request("/api/v1/views/by-name?limit=2")))
         .await
         .unwrap();
-
     assert_eq!(response.status(), StatusCode::OK);
+    let view: serde_json::Value = common::response_json(response).await;
+    assert_eq!(view["items"].as_array().unwrap().len(), 2);
+    assert_eq!((view["total"].as_u64(), view["kept"].as_u64()), (Some(3), Some(3)));
+    assert_eq!(view["stale"], false);
+    assert!(view["items"][0]["name"].as_str() > view["items"][1]["name"].as_str());
 
-    let initial_remaining = response
-        .headers()
-        .get("X-RateLimit-Remaining")
-        .unwrap()
-        .to_str()
-        .unwrap()
-        .parse::<u32>()
+    let response = app
+        .clone()
+        .oneshot(admin(common::get_request("/api/v1/views/missing")))
+        .await
         .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
 
-    // Second request
+    // Views span every owner's items and drafts
     let response = app
-        .oneshot(common::get_request("/api/v1/items"))
+        .clone()
+        .oneshot(common::with_claims(common::get_request("/api/v1/views/by-name"), "alice", &[]))
         .await
         .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
 
-    assert_eq!(response.status(), StatusCode::OK);
-
-    let new_remaining = response
-        .headers()
-        .get("X-RateLimit-Remaining")
-        .unwrap()
-        .to_str()
-        .unwrap()
-        .parse::<u32>()
+    let response = app
+        .oneshot(common::get_request("/api/v1/views/by-name"))
+        .await
         .unwrap();
-
-    assert_eq!(new_remaining, initial_remaining - 1);
-}
-
-// Security headers tests
-#[tokio::test]
-async fn test_security_headers() {
-    let app = common::create_test_app().await;
-
-    let response = app.oneshot(common::get_request("/health")).await.unwrap();
-
-    assert_eq!(response.status(), StatusCode::OK);
-
-    // Check security headers
-    assert_eq!(response.headers().get("X-Content-Type-Options").unwrap(), "nosniff");
-    assert_eq!(response.headers().get("X-Frame-Options").unwrap(), "DENY");
-    assert!(response.headers().contains_key("X-Request-Id"));
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
 }
 
-// Error response tests
 #[tokio::test]
-async fn test_structured_error_response_format() {
+async fn test_metadata_schemas() {
     let app = common::create_test_app().await;
+    let schema = json!({
+        "schema": {
+            "type": "object",
+            "properties": { "color": { "enum": ["red", "blue"] } },
+            "required": ["color"]
+        }
+    });
 
     let response = app
-        .oneshot(common::get_request("/api/v1/items/nonexistent"))
+        .clone()
+        .oneshot(common::put_request("/api/v1/schemas/product", schema.clone()))
         .await
         .unwrap();
-
-    assert_eq!(response.status(), StatusCode::NOT_FOUND);
-
-    let error: serde_json::Value = common::response_json(response).await;
-
-    // Check error response structure
-    assert!(error["error"].is_string());
-    assert!(error["message"].is_string());
-    assert!(error["timestamp"].is_string());
-    // request_id is optional in error responses
-    if error.get("request_id").is_some() {
-        assert!(error["request_id"].is_string());
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    for expected in [1, 2] {
+        let request = common::put_request("/api/v1/schemas/product", schema.clone());
+        let response = app
+            .clone()
+            .oneshot(common::with_claims(request, "root", &["admin"]))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let registered: serde_json::Value = common::response_json(response).await;
+        assert_eq!(registered["version"], expected);
     }
-}
 
-#[tokio::test]
-async fn test_validation_error_structure() {
-    let app = common::create_test_app().await;
+    let response = app
+        .clone()
+        .oneshot(common::post_request(
+            "/api/v1/items",
+            json!({ "name": "Paint", "metadata": { "color": "green" } }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let error: serde_json::Value = common::response_json(response).await;
+    let details = &error["details"]["validation_errors"][0];
+    assert_eq!(details["field"], "metadata.color");
+    assert_eq!(details["message"], "Must be one of \"red\", \"blue\"");
 
     let response = app
-        .oneshot(
-            Request::builder()
-                .method("POST")
-                .uri("/api/v1/items")
-                .header("content-type", "application/json")
-                .body(Body::from("{invalid json"))
-                .unwrap(),
-        )
+        .clone()
+        .oneshot(common::post_request(
+            "/api/v1/items",
+            json!({ "name": "Paint", "metadata": { "color": "red" } }),
+        ))
         .await
         .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let item: serde_json::Value = common::response_json(response).await;
+    assert_eq!(item["metadata"]["color"], "red");
 
-    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = app
+        .clone()
+        .oneshot(common::get_request("/api/v1/schemas/product/versions"))
+        .await
+        .unwrap();
+    let versions: serde_json::Value = common::response_json(response).await;
+    assert_eq!(versions.as_array().unwrap().len(), 2);
 
-    let error: serde_json::Value = common::response_json(response).await;
-    assert_eq!(error["error"], "BAD_REQUEST");
-    assert!(error["message"].as_str().unwrap().contains("JSON"));
+    let request = common::delete_request("/api/v1/schemas/product");
+    let response = app
+        .clone()
+        .oneshot(common::with_claims(request, "root", &["admin"]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response = app
+        .oneshot(common::post_request("/api/v1/items", json!({ "name": "Plain" })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
 }
 
 #[tokio::test]
-async fn test_error_codes_match_status_codes() {
+async fn test_custom_fields() {
     let app = common::create_test_app().await;
-
-    // 404 Not Found
+    let definition = json!({ "type": "enum", "values": ["low", "high"], "required": true });
+    let request = common::put_request("/api/v1/fields/priority", definition);
     let response = app
         .clone()
-        .oneshot(common::get_request("/api/v1/items/nonexistent"))
+        .oneshot(common::with_claims(request, "root", &["admin"]))
         .await
         .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
 
-    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = app
+        .clone()
+        .oneshot(common::post_request("/api/v1/items", json!({ "name": "Unprioritized" })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
     let error: serde_json::Value = common::response_json(response).await;
-    assert_eq!(error["error"], "NOT_FOUND");
+    assert_eq!(error["details"]["validation_errors"][0]["field"], "custom_fields.priority");
+
+    for (name, priority) in [("Urgent", "high"), ("Someday", "low")] {
+        let body = json!({ "name": name, "custom_fields": { "priority": priority } });
+        let response = app
+            .clone()
+            .oneshot(common::post_request("/api/v1/items", body))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
 
-    // 400 Bad Request
     let response = app
-        .oneshot(
-            Request::builder()
-                .method("POST")
-                .uri("/api/v1/items")
-                .header("content-type", "application/json")
-                .body(Body::from("not json"))
-                .unwrap(),
-        )
+        .clone()
+        .oneshot(common::get_request(
+            "/api/v1/items?$filter=custom_fields/priority%20eq%20%27high%27",
+        ))
         .await
         .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let list: serde_json::Value = common::response_json(response).await;
+    assert_eq!(list["items"].as_array().unwrap().len(), 1);
+    assert_eq!(list["items"][0]["custom_fields"]["priority"], "high");
 
+    let response = app
+        .clone()
+        .oneshot(common::get_request(
+            "/api/v1/items?$filter=custom_fields/color%20eq%20%27red%27",
+        ))
+        .await
+        .unwrap();
     assert_eq!(response.status(), StatusCode::BAD_REQUEST);
-    let error: serde_json::Value = common::response_json(response).await;
-    assert_eq!(error["error"], "BAD_REQUEST");
+
+    let response = app
+        .oneshot(common::get_request("/openapi.json"))
+        .await
+        .unwrap();
+    let spec: serde_json::Value = common::response_json(response).await;
+    let create = &spec["components"]["schemas"]["CreateItemRequest"]["properties"]["custom_fields"];
+    assert_eq!(create["properties"]["priority"]["enum"], json!(["low", "high"]));
+    assert_eq!(create["required"], json!(["priority"]));
 }
 
-// OpenAPI tests
 #[tokio::test]
-async fn test_openapi_json_endpoint() {
-    let app = common::create_test_app().await;
+async fn test_attachments_are_uploaded_straight_to_s3() {
+    use axum::{
+        extract::{Path, State},
+        routing::get,
+    };
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    // A bucket that keeps whatever is put into it, without checking signatures
+    type Objects = Arc<Mutex<HashMap<String, axum::body::Bytes>>>;
+    let objects = Objects::default();
+    let bucket = axum::Router::new()
+        .route(
+            "/{*key}",
+            get(|State(objects): State<Objects>, Path(key): Path<String>| async move {
+                objects
+                    .lock()
+                    .unwrap()
+                    .get(&key)
+                    .cloned()
+                    .ok_or(StatusCode::NOT_FOUND)
+            })
+            .put(
+                |State(objects): State<Objects>, Path(key): Path<String>, body| async move {
+                    objects.lock().unwrap().insert(key, body);
+                },
+            ),
+        )
+        .with_state(objects.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, bucket).await.unwrap() });
+
+    let config = ferrous::config::AttachmentsConfig {
+        bucket: Some("attachments".to_string()),
+        endpoint: Some(format!("http://{addr}")),
+        access_key_id: Some("key".to_string()),
+        secret_access_key: Some("secret".to_string()),
+        ..Default::default()
+    };
+    let attachments =
+        ferrous::attachments::Attachments::from_config(&config, Default::default()).unwrap();
+    let state = ferrous::state::AppState::new(common::create_test_repo())
+        .with_attachments(attachments)
+        .into_shared();
+    let app = ferrous::routes::create_routes(state.clone());
+    let app = ferrous::middleware::add_middleware(app, &state);
 
     let response = app
-        .oneshot(common::get_request("/openapi.json"))
+        .clone()
+        .oneshot(common::post_request("/api/v1/items", json!({ "name": "Report" })))
+        .await
+        .unwrap();
+    let item: serde_json::Value = common::response_json(response).await;
+    let id = item["id"].as_str().unwrap();
+
+    let presign =
+        |body| common::post_request(&format!("/api/v1/items/{id}/attachments/presign"), body);
+    let response = app
+        .clone()
+        .oneshot(presign(json!({ "file_name": "", "size": 0 })))
         .await
         .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
 
+    let body = json!({ "file_name": "notes.txt", "content_type": "text/plain", "size": 11 });
+    let response = app.clone().oneshot(presign(body)).await.unwrap();
     assert_eq!(response.status(), StatusCode::OK);
+    let upload: serde_json::Value = common::response_json(response).await;
+    let url = upload["url"].as_str().unwrap();
+    assert!(url.starts_with(&format!("http://{addr}/attachments/items/{id}/")));
+    assert!(url.contains("X-Amz-Signature="));
+    assert_eq!(upload["headers"]["content-length"], "11");
+    let confirm = || common::post_request(upload["confirm_url"].as_str().unwrap(), json!({}));
+
+    // Nothing has been uploaded yet
+    let response = app.clone().oneshot(confirm()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let response = reqwest::Client::new()
+        .put(url)
+        .header("content-type", "text/plain")
+        .body("hello world")
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
 
-    let body = common::response_json::<serde_json::Value>(response).await;
+    let response = app.clone().oneshot(confirm()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let attachment: serde_json::Value = common::response_json(response).await;
+    assert_eq!(attachment["file_name"], "notes.txt");
+    assert_eq!(attachment["size"], 11);
+    let response = app.clone().oneshot(confirm()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
 
-    // Check OpenAPI structure
-    assert_eq!(body["openapi"], "3.1.0");
+    let response = app
+        .clone()
+        .oneshot(common::get_request(&format!("/api/v1/items/{id}/attachments")))
+        .await
+        .unwrap();
+    let list: serde_json::Value = common::response_json(response).await;
+    assert_eq!(list.as_array().unwrap().len(), 1);
+    assert_eq!(list[0]["id"], upload["upload_id"]);
+
+    // Attachments are forgotten along with their item
+    let response = app
+        .oneshot(common::delete_request(&format!("/api/v1/items/{id}")))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let attachments = state.attachments.as_ref().unwrap();
+    assert!(attachments.list(id).unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_attachments_are_scanned_before_they_are_shared() {
+    use axum::{
+        extract::{Path, State},
+        routing::{get, post},
+    };
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    // A bucket, and a scanner that finds the EICAR test string
+    type Objects = Arc<Mutex<HashMap<String, axum::body::Bytes>>>;
+    let objects = Objects::default();
+    let bucket = axum::Router::new()
+        .route(
+            "/{*key}",
+            get(|State(objects): State<Objects>, Path(key): Path<String>| async move {
+                objects
+                    .lock()
+                    .unwrap()
+                    .get(&key)
+                    .cloned()
+                    .ok_or(StatusCode::NOT_FOUND)
+            }),
+        )
+        .with_state(objects.clone())
+        .route(
+            "/scan",
+            post(|body: axum::body::Bytes| async move {
+                let infected = body.windows(5).any(|window| window == b"EICAR");
+                axum::Json(json!({ "infected": infected, "signature": "Eicar-Test-Signature" }))
+            }),
+        );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, bucket).await.unwrap() });
+
+    let config = ferrous::config::AttachmentsConfig {
+        bucket: Some("attachments".to_string()),
+        endpoint: Some(format!("http://{addr}")),
+        access_key_id: Some("key".to_string()),
+        secret_access_key: Some("secret".to_string()),
+        scan_url: Some(format!("http://{addr}/scan")),
+        ..Default::default()
+    };
+    let attachments =
+        ferrous::attachments::Attachments::from_config(&config, Default::default()).unwrap();
+    let sharing = ferrous::config::SharingConfig {
+        signing_key: Some("share-secret".to_string()),
+        ..Default::default()
+    };
+    let state = ferrous::state::AppState::new(common::create_test_repo())
+        .with_attachments(attachments)
+        .with_share_links(ferrous::sharing::ShareLinks::from_config(&sharing))
+        .into_shared();
+    let app = ferrous::routes::create_routes(state.clone());
+    let app = ferrous::middleware::add_middleware(app, &state);
+
+    let response = app
+        .clone()
+        .oneshot(common::post_request("/api/v1/items", json!({ "name": "Report" })))
+        .await
+        .unwrap();
+    let item: serde_json::Value = common::response_json(response).await;
+    let id = item["id"].as_str().unwrap();
+
+    for (file_name, contents) in [("notes.txt", "hello world"), ("virus.com", "EICAR-TEST")] {
+        let body = json!({ "file_name": file_name, "size": contents.len() });
+        let presign =
+            common::post_request(&format!("/api/v1/items/{id}/attachments/presign"), body);
+        let response = app.clone().oneshot(presign).await.unwrap();
+        let upload: serde_json::Value = common::response_json(response).await;
+        let key = upload["url"].as_str().unwrap().split('?').next().unwrap();
+        let key = key.trim_start_matches(&format!("http://{addr}/"));
+        objects
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), contents.into());
+
+        let confirm = common::post_request(upload["confirm_url"].as_str().unwrap(), json!({}));
+        let response = app.clone().oneshot(confirm).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let attachment: serde_json::Value = common::response_json(response).await;
+        assert_eq!(attachment["scan_status"], "pending");
+    }
+
+    let mut statuses = Vec::new();
+    for _ in 0..100 {
+        let list = common::get_request(&format!("/api/v1/items/{id}/attachments"));
+        let response = app.clone().oneshot(list).await.unwrap();
+        let list: serde_json::Value = common::response_json(response).await;
+        statuses = list
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|attachment| attachment["scan_status"].clone())
+            .collect();
+        if !statuses.contains(&json!("pending")) {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(statuses, vec![json!("clean"), json!("infected")]);
+
+    // Only the clean file can be downloaded
+    let share = json!({ "include_attachments": true });
+    let share = common::post_request(&format!("/api/v1/items/{id}/share"), share);
+    let response = app.clone().oneshot(share).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let created: serde_json::Value = common::response_json(response).await;
+    let open = common::get_request(created["url"].as_str().unwrap());
+    let response = app.oneshot(open).await.unwrap();
+    let shared: serde_json::Value = common::response_json(response).await;
+    let shared = shared["attachments"].as_array().unwrap();
+    assert_eq!(shared.len(), 1);
+    assert_eq!(shared[0]["file_name"], "notes.txt");
+}
+
+#[tokio::test]
+async fn test_image_attachments_get_thumbnails() {
+    use axum::{
+        extract::{Path, Query, State},
+        routing::{get, post},
+    };
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    // A bucket, and a thumbnail service that names the width it scaled to
+    type Objects = Arc<Mutex<HashMap<String, axum::body::Bytes>>>;
+    let objects = Objects::default();
+    let bucket = axum::Router::new()
+        .route(
+            "/{*key}",
+            get(|State(objects): State<Objects>, Path(key): Path<String>| async move {
+                objects
+                    .lock()
+                    .unwrap()
+                    .get(&key)
+                    .cloned()
+                    .ok_or(StatusCode::NOT_FOUND)
+            })
+            .put(
+                |State(objects): State<Objects>, Path(key): Path<String>, body| async move {
+                    objects.lock().unwrap().insert(key, body);
+                },
+            ),
+        )
+        .with_state(objects.clone())
+        .route(
+            "/thumbnail",
+            post(|Query(query): Query<HashMap<String, String>>| async move {
+                ([("content-type", "image/png")], format!("{}px", query["width"]))
+            }),
+        );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, bucket).await.unwrap() });
+
+    let config = ferrous::config::AttachmentsConfig {
+        bucket: Some("attachments".to_string()),
+        endpoint: Some(format!("http://{addr}")),
+        access_key_id: Some("key".to_string()),
+        secret_access_key: Some("secret".to_string()),
+        thumbnail_url: Some(format!("http://{addr}/thumbnail")),
+        thumbnail_sizes: vec![512, 128],
+        ..Default::default()
+    };
+    let attachments =
+        ferrous::attachments::Attachments::from_config(&config, Default::default()).unwrap();
+    let state = ferrous::state::AppState::new(common::create_test_repo())
+        .with_attachments(attachments)
+        .into_shared();
+    let app = ferrous::routes::create_routes(state.clone());
+    let app = ferrous::middleware::add_middleware(app, &state);
+
+    let response = app
+        .clone()
+        .oneshot(common::post_request("/api/v1/items", json!({ "name": "Album" })))
+        .await
+        .unwrap();
+    let item: serde_json::Value = common::response_json(response).await;
+    let id = item["id"].as_str().unwrap();
+
+    let mut uploads = Vec::new();
+    for (file_name, content_type) in [("photo.png", "image/png"), ("notes.txt", "text/plain")] {
+        let body = json!({ "file_name": file_name, "content_type": content_type, "size": 4 });
+        let presign =
+            common::post_request(&format!("/api/v1/items/{id}/attachments/presign"), body);
+        let response = app.clone().oneshot(presign).await.unwrap();
+        let upload: serde_json::Value = common::response_json(response).await;
+        let response = reqwest::Client::new()
+            .put(upload["url"].as_str().unwrap())
+            .header("content-type", content_type)
+            .body("data")
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        let confirm = common::post_request(upload["confirm_url"].as_str().unwrap(), json!({}));
+        let response = app.clone().oneshot(confirm).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        uploads.push(upload["upload_id"].as_str().unwrap().to_string());
+    }
+
+    let mut list = json!([]);
+    for _ in 0..100 {
+        let request = common::get_request(&format!("/api/v1/items/{id}/attachments"));
+        let response = app.clone().oneshot(request).await.unwrap();
+        list = common::response_json(response).await;
+        if list[0].get("thumbnails").is_some() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(list[0]["thumbnails"], json!([512, 128]));
+    assert!(list[1].get("thumbnails").is_none());
+
+    let thumbnail = |upload: &str, query: &str| {
+        let uri = format!("/api/v1/items/{id}/attachments/{upload}/thumbnail{query}");
+        app.clone().oneshot(common::get_request(&uri))
+    };
+    let response = thumbnail(&uploads[0], "").await.unwrap();
+    assert_eq!(response.status(), StatusCode::FOUND);
+    assert_eq!(response.headers()["cache-control"], "private, max-age=600");
+    let location = response.headers()["location"].to_str().unwrap();
+    assert!(location.contains("/thumbnails/128?"));
+    let body = reqwest::get(location).await.unwrap().text().await.unwrap();
+    assert_eq!(body, "128px");
+
+    let response = thumbnail(&uploads[0], "?size=512").await.unwrap();
+    assert_eq!(response.status(), StatusCode::FOUND);
+    let response = thumbnail(&uploads[0], "?size=64").await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = thumbnail(&uploads[1], "").await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_webhook_deliveries_are_redelivered_by_post() {
+    use ferrous::events::EventPublisher;
+
+    let receiver = axum::Router::new()
+        .route("/hooks", axum::routing::post(|| async { StatusCode::NO_CONTENT }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, receiver).await.unwrap() });
+
+    let config = ferrous::config::WebhooksConfig {
+        endpoints: vec![ferrous::config::WebhookEndpoint {
+            id: "orders".to_string(),
+            url: format!("http://{addr}/hooks"),
+        }],
+        secret: Some("secret".to_string()),
+        ..Default::default()
+    };
+    let webhooks = ferrous::events::WebhookPublisher::from_config(&config, Default::default());
+    let state = ferrous::state::AppState::new(common::create_test_repo())
+        .with_webhooks(webhooks)
+        .into_shared();
+    let app = ferrous::routes::create_routes(state.clone());
+    let app = ferrous::middleware::add_middleware(app, &state);
+
+    let response = app
+        .clone()
+        .oneshot(common::post_request("/api/v1/items", json!({ "name": "Order" })))
+        .await
+        .unwrap();
+    let item = serde_json::from_value(common::response_json(response).await).unwrap();
+    let event = ferrous::events::OutboxEvent::new(1, ferrous::events::ItemEventType::Created, item);
+    state
+        .webhooks
+        .as_ref()
+        .unwrap()
+        .publish(&event)
+        .await
+        .unwrap();
+
+    // Redelivering sends the event again, so it is not a safe method
+    let uri = format!("/api/v1/webhooks/orders/deliveries/{}/redeliver", event.id);
+    let admin = |request| common::with_claims(request, "root", &["admin"]);
+    let response = app
+        .clone()
+        .oneshot(admin(common::get_request(&uri)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    let response = app
+        .oneshot(admin(common::post_request(&uri, json!({}))))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let delivery: serde_json::Value = common::response_json(response).await;
+    assert_eq!(delivery["attempts"], 2);
+    assert_eq!(delivery["delivered"], true);
+}
+
+#[tokio::test]
+async fn test_share_links_open_items_until_revoked() {
+    let config = ferrous::config::SharingConfig {
+        signing_key: Some("share-secret".to_string()),
+        ..Default::default()
+    };
+    let state = ferrous::state::AppState::new(common::create_test_repo())
+        .with_share_links(ferrous::sharing::ShareLinks::from_config(&config))
+        .into_shared();
+    let app = ferrous::routes::create_routes(state.clone());
+    let app = ferrous::middleware::add_middleware(app, &state);
+
+    let request = common::post_request("/api/v1/items", json!({ "name": "Shared" }));
+    let response = app
+        .clone()
+        .oneshot(common::with_claims(request, "owner", &[]))
+        .await
+        .unwrap();
+    let item: serde_json::Value = common::response_json(response).await;
+    let id = item["id"].as_str().unwrap();
+
+    // Only those who can manage access may share
+    let share = || common::post_request(&format!("/api/v1/items/{id}/share"), json!({}));
+    let response = app
+        .clone()
+        .oneshot(common::with_claims(share(), "someone-else", &[]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = app
+        .clone()
+        .oneshot(common::with_claims(share(), "owner", &[]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let created: serde_json::Value = common::response_json(response).await;
+    let url = created["url"].as_str().unwrap();
+
+    for _ in 0..2 {
+        let response = app.clone().oneshot(common::get_request(url)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let shared: serde_json::Value = common::response_json(response).await;
+        assert_eq!(shared["item"]["name"], "Shared");
+        assert!(shared.get("attachments").is_none());
+    }
+
+    let list = common::get_request(&format!("/api/v1/items/{id}/share"));
+    let response = app
+        .clone()
+        .oneshot(common::with_claims(list, "owner", &[]))
+        .await
+        .unwrap();
+    let shares: serde_json::Value = common::response_json(response).await;
+    assert_eq!(shares[0]["access_count"], 2);
+
+    let share_id = created["share"]["id"].as_str().unwrap();
+    let revoke = common::delete_request(&format!("/api/v1/items/{id}/share/{share_id}"));
+    let response = app
+        .clone()
+        .oneshot(common::with_claims(revoke, "owner", &[]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app.clone().oneshot(common::get_request(url)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = app
+        .oneshot(common::get_request("/share/not-a-token"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_list_items_with_pagination() {
+    let state = common::create_test_state();
+    let app = ferrous::routes::create_routes(state.clone());
+
+    // Create multiple items
+    common::create_test_items(&state.repo, 5).await;
+
+    let response = app
+        .oneshot(common::get_request("/api/v1/items?limit=2&offset=2"))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let list_response: serde_json::Value = common::response_json(response).await;
+    assert_eq!(list_response["items"].as_array().unwrap().len(), 2);
+    assert_eq!(list_response["total"], 5);
+    assert_eq!(list_response["limit"], 2);
+    assert_eq!(list_response["offset"], 2);
+}
+
+#[tokio::test]
+async fn test_large_list_is_streamed() {
+    let state = common::create_test_state();
+    let app = ferrous::routes::create_routes(state.clone());
+    let created = common::create_test_items(&state.repo, 75).await;
+
+    let response = app
+        .oneshot(common::get_request("/api/v1/items?limit=100"))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/json");
+
+    let list_response: serde_json::Value = common::response_json(response).await;
+    let items = list_response["items"].as_array().unwrap();
+    assert_eq!(items.len(), 75);
+    assert_eq!(items[74]["id"], created[74].id);
+    assert_eq!(list_response["total"], 75);
+    assert_eq!(list_response["limit"], 100);
+}
+
+#[tokio::test]
+async fn test_invalid_pagination_params() {
+    let app = common::create_test_app().await;
+
+    // Test negative limit
+    let response = app
+        .clone()
+        .oneshot(common::get_request("/api/v1/items?limit=-1"))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Test limit exceeding max
+    let response = app
+        .clone()
+        .oneshot(common::get_request("/api/v1/items?limit=1000"))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Deep offsets are reached by cursor instead
+    let response = app
+        .clone()
+        .oneshot(common::get_request("/api/v1/items?offset=10001"))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = app
+        .oneshot(common::get_request("/api/v1/items?cursor=bogus"))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_list_items_with_cursor() {
+    let state = common::create_test_state();
+    let app = ferrous::routes::create_routes(state.clone());
+    let created = common::create_test_items(&state.repo, 5).await;
+
+    let response = app
+        .clone()
+        .oneshot(common::get_request("/api/v1/items?limit=3"))
+        .await
+        .unwrap();
+    let first: serde_json::Value = common::response_json(response).await;
+    let cursor = first["next_cursor"].as_str().unwrap();
+
+    let response = app
+        .oneshot(common::get_request(&format!("/api/v1/items?limit=3&cursor={cursor}")))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let next: serde_json::Value = common::response_json(response).await;
+    let items = next["items"].as_array().unwrap();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0]["id"], created[3].id);
+    assert_eq!(next["offset"], 0);
+    // The last page has no cursor
+    assert!(next.get("next_cursor").is_none());
+}
+
+#[tokio::test]
+async fn test_item_changes_long_poll() {
+    use ferrous::{config::EventsConfig, events::OutboxDispatcher};
+    use std::sync::Arc;
+
+    let state = common::create_test_state();
+    let app = ferrous::routes::create_routes(state.clone());
+    let dispatcher = OutboxDispatcher::new(
+        state.repo.clone(),
+        vec![Arc::new(state.events.clone())],
+        &EventsConfig::default(),
+    );
+
+    // Nothing changes within the wait, so the feed answers empty
+    let response = app
+        .clone()
+        .oneshot(common::get_request("/api/v1/items/changes?wait=0s"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let empty: serde_json::Value = common::response_json(response).await;
+    assert_eq!(empty["changes"], json!([]));
+    let cursor = empty["next_cursor"].as_str().unwrap().to_string();
+
+    let waiting = tokio::spawn({
+        let app = app.clone();
+        let uri = format!("/api/v1/items/changes?since={cursor}&wait=5s");
+        async move { app.oneshot(common::get_request(&uri)).await.unwrap() }
+    });
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    assert!(!waiting.is_finished());
+
+    let item = common::create_test_items(&state.repo, 1).await.remove(0);
+    dispatcher.dispatch_once().await.unwrap();
+    let response = waiting.await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let feed: serde_json::Value = common::response_json(response).await;
+    let changes = feed["changes"].as_array().unwrap();
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0]["type"], "created");
+    assert_eq!(changes[0]["item"]["id"], item.id);
+    assert_ne!(feed["next_cursor"], cursor.as_str());
+
+    for uri in [
+        "/api/v1/items/changes?wait=soon",
+        "/api/v1/items/changes?since=bogus&wait=0s",
+    ] {
+        let response = app.clone().oneshot(common::get_request(uri)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn test_item_text_is_sanitized_before_validation() {
+    let app = common::create_test_app().await;
+
+    let response = app
+        .clone()
+        .oneshot(common::post_request(
+            "/api/v1/items",
+            json!({ "name": " \u{FEFF}Cafe\u{301}\u{200B}\u{7} ", "description": "\u{200B}" }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let item: serde_json::Value = common::response_json(response).await;
+    assert_eq!(item["name"], "Caf\u{e9}");
+    assert!(item["description"].is_null());
+
+    // A name of nothing but invisible characters is empty once cleaned
+    let response = app
+        .oneshot(common::post_request("/api/v1/items", json!({ "name": "\u{200B}\u{7}" })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn test_configured_validation_rules() {
+    let config = ferrous::config::ItemValidationConfig {
+        banned_words: vec!["spam".to_string()],
+        ..Default::default()
+    };
+    let state = ferrous::state::AppState::new(common::create_test_repo())
+        .with_validation_rules(
+            ferrous::validation::ValidationRules::from_config(&config, &Default::default())
+                .unwrap(),
+        )
+        .into_shared();
+    let app = ferrous::routes::create_routes(state);
+
+    let response = app
+        .clone()
+        .oneshot(common::post_request("/api/v1/items", json!({ "name": "Spam offer" })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let error: serde_json::Value = common::response_json(response).await;
+    assert_eq!(error["details"]["validation_errors"][0]["code"], "banned_word");
+
+    let response = app
+        .oneshot(common::post_request("/api/v1/items", json!({ "name": "Offer" })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_unknown_fields_can_be_rejected() {
+    let config = ferrous::config::ItemValidationConfig {
+        reject_unknown_fields: true,
+        ..Default::default()
+    };
+    let state = ferrous::state::AppState::new(common::create_test_repo())
+        .with_validation_rules(
+            ferrous::validation::ValidationRules::from_config(&config, &Default::default())
+                .unwrap(),
+        )
+        .into_shared();
+    let app = ferrous::routes::create_routes(state);
+
+    let response = app
+        .clone()
+        .oneshot(common::post_request(
+            "/api/v1/items",
+            json!({ "name": "Widget", "descripton": "A widget" }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let error: serde_json::Value = common::response_json(response).await;
+    let field = &error["details"]["validation_errors"][0];
+    assert_eq!(field["field"], "descripton");
+    assert_eq!(field["code"], "unknown_field");
+
+    let response = app
+        .oneshot(common::post_request(
+            "/api/v1/items",
+            json!({ "name": "Widget", "description": "A widget", "metadata": { "any": 1 } }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_duplicate_submission_returns_earlier_item() {
+    let state = ferrous::state::AppState::new(common::create_test_repo())
+        .with_duplicate_guard(ferrous::duplicates::DuplicateGuard::new(
+            std::time::Duration::from_secs(30),
+        ))
+        .into_shared();
+    let app = ferrous::routes::create_routes(state.clone());
+    let submit = |name: &str, sub: &str| {
+        common::with_claims(
+            common::post_request("/api/v1/items", json!({ "name": name })),
+            sub,
+            &[],
+        )
+    };
+
+    let response = app
+        .clone()
+        .oneshot(submit("Widget", "alice"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let first: serde_json::Value = common::response_json(response).await;
+
+    // Whitespace differences are sanitized away, so this is the same body
+    let response = app
+        .clone()
+        .oneshot(submit(" Widget ", "alice"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let repeat: serde_json::Value = common::response_json(response).await;
+    assert_eq!(repeat["id"], first["id"]);
+
+    let response = app.oneshot(submit("Widget", "bob")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(
+        state
+            .repo
+            .count(&ferrous::db::ItemFilter::default())
+            .await
+            .unwrap(),
+        2
+    );
+}
+
+// HOOK tests
+struct TaggingHooks {
+    deleted: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+}
+
+#[async_trait::async_trait]
+impl ferrous::hooks::ItemHooks for TaggingHooks {
+    async fn before_create(
+        &self,
+        ctx: ferrous::hooks::HookContext<'_>,
+        request: &mut ferrous::models::CreateItemRequest,
+    ) -> Result<(), ferrous::hooks::HookRejection> {
+        if request.name.contains("spam") {
+            return Err(ferrous::hooks::HookRejection::invalid("name", "Looks like spam"));
+        }
+        let author = ctx.claims.map_or("anonymous", |claims| claims.sub.as_str());
+        request.description = Some(format!("[tagged by {author}]"));
+        Ok(())
+    }
+
+    async fn before_delete(
+        &self,
+        _ctx: ferrous::hooks::HookContext<'_>,
+        existing: &ferrous::models::Item,
+    ) -> Result<(), ferrous::hooks::HookRejection> {
+        if existing.name == "Keep" {
+            return Err(ferrous::hooks::HookRejection::Forbidden("Item is protected".to_string()));
+        }
+        Ok(())
+    }
+
+    async fn after_delete(
+        &self,
+        _ctx: ferrous::hooks::HookContext<'_>,
+        item: &ferrous::models::Item,
+    ) {
+        self.deleted.lock().unwrap().push(item.id.clone());
+    }
+}
+
+#[tokio::test]
+async fn test_item_hooks_enrich_and_veto() {
+    let deleted = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let hooks = TaggingHooks {
+        deleted: deleted.clone(),
+    };
+    let state = ferrous::state::AppState::new(common::create_test_repo())
+        .with_hooks(hooks)
+        .into_shared();
+    let app = ferrous::routes::create_routes(state.clone());
+
+    let request = common::post_request("/api/v1/items", json!({ "name": "Widget" }));
+    let response = app
+        .clone()
+        .oneshot(common::with_claims(request, "alice", &[]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let item: serde_json::Value = common::response_json(response).await;
+    assert_eq!(item["description"], "[tagged by alice]");
+
+    let response = app
+        .clone()
+        .oneshot(common::post_request("/api/v1/items", json!({ "name": "Buy spam" })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let error: serde_json::Value = common::response_json(response).await;
+    assert_eq!(error["details"]["validation_errors"][0]["field"], "name");
+
+    let keep = common::create_test_item(&state.repo, "Keep", None).await;
+    let response = app
+        .clone()
+        .oneshot(common::delete_request(&format!("/api/v1/items/{}", keep.id)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(state.repo.get(&keep.id).await.is_ok());
+
+    let id = item["id"].as_str().unwrap();
+    let request = common::delete_request(&format!("/api/v1/items/{id}"));
+    let response = app
+        .oneshot(common::with_claims(request, "alice", &[]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert_eq!(*deleted.lock().unwrap(), [id]);
+}
+
+// OWNERSHIP tests
+#[tokio::test]
+async fn test_created_items_belong_to_principal() {
+    let state = common::create_test_state();
+    let app = ferrous::routes::create_routes(state.clone());
+
+    let request = common::post_request("/api/v1/items", json!({ "name": "Alice's item" }));
+    let response = app
+        .oneshot(common::with_claims(request, "alice", &[]))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let item: serde_json::Value = common::response_json(response).await;
+    assert_eq!(item["owner_id"], "alice");
+}
+
+#[tokio::test]
+async fn test_list_is_scoped_to_owner() {
+    let state = common::create_test_state();
+    let app = ferrous::routes::create_routes(state.clone());
+
+    for (name, owner) in [("A1", "alice"), ("A2", "alice"), ("B1", "bob")] {
+        let request = common::post_request("/api/v1/items", json!({ "name": name }));
+        let response = app
+            .clone()
+            .oneshot(common::with_claims(request, owner, &[]))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    let request = common::with_claims(common::get_request("/api/v1/items"), "alice", &[]);
+    let response = app.clone().oneshot(request).await.unwrap();
+    let list: serde_json::Value = common::response_json(response).await;
+    assert_eq!(list["total"], 2);
+
+    // Non-admins cannot opt into every owner's items
+    let request = common::with_claims(common::get_request("/api/v1/items?all=true"), "bob", &[]);
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let request =
+        common::with_claims(common::get_request("/api/v1/items?all=true"), "root", &["admin"]);
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let list: serde_json::Value = common::response_json(response).await;
+    assert_eq!(list["total"], 3);
+}
+
+#[tokio::test]
+async fn test_anonymous_list_leaves_out_owned_items() {
+    let mut config = ferrous::config::Config::default();
+    config.auth.enabled = true;
+    config.auth.jwt_secret = Some("secret".to_string());
+    let state = ferrous::state::AppState::new(common::create_test_repo())
+        .with_auth(ferrous::auth::JwtValidator::new(&config.auth))
+        .with_config(config)
+        .into_shared();
+    let app = ferrous::routes::create_routes(state.clone());
+    let public = common::create_test_item(&state.repo, "Public", None).await;
+    let owned = state
+        .repo
+        .create(common::create_test_item_request("Owned", None), Some("alice".to_string()))
+        .await
+        .unwrap();
+
+    let response = app
+        .clone()
+        .oneshot(common::get_request("/api/v1/items"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let list: serde_json::Value = common::response_json(response).await;
+    assert_eq!(list["total"], 1);
+    assert_eq!(list["items"][0]["id"], public.id);
+
+    // Listing agrees with reading the owned item
+    let response = app
+        .oneshot(common::get_request(&format!("/api/v1/items/{}", owned.id)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_non_owner_cannot_modify_item() {
+    let state = common::create_test_state();
+    let app = ferrous::routes::create_routes(state.clone());
+
+    let request = common::post_request("/api/v1/items", json!({ "name": "Owned" }));
+    let response = app
+        .clone()
+        .oneshot(common::with_claims(request, "alice", &[]))
+        .await
+        .unwrap();
+    let item: serde_json::Value = common::response_json(response).await;
+    let uri = format!("/api/v1/items/{}", item["id"].as_str().unwrap());
+
+    let request = common::put_request(&uri, json!({ "name": "Hijacked" }));
+    let response = app
+        .clone()
+        .oneshot(common::with_claims(request, "bob", &[]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let error: serde_json::Value = common::response_json(response).await;
+    assert_eq!(error["error"], "FORBIDDEN");
+
+    let response = app
+        .clone()
+        .oneshot(common::with_claims(common::delete_request(&uri), "bob", &[]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // Anonymous callers cannot modify owned items either
+    let response = app
+        .clone()
+        .oneshot(common::delete_request(&uri))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = app
+        .oneshot(common::with_claims(common::delete_request(&uri), "alice", &[]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+}
+
+// SHARING tests
+#[tokio::test]
+async fn test_item_sharing_grants_access() {
+    let state = common::create_test_state();
+    let app = ferrous::routes::create_routes(state.clone());
+
+    let request = common::post_request("/api/v1/items", json!({ "name": "Shared" }));
+    let response = app
+        .clone()
+        .oneshot(common::with_claims(request, "alice", &[]))
+        .await
+        .unwrap();
+    let item: serde_json::Value = common::response_json(response).await;
+    let uri = format!("/api/v1/items/{}", item["id"].as_str().unwrap());
+    let as_bob = |request| common::with_claims(request, "bob", &[]);
+
+    let response = app
+        .clone()
+        .oneshot(as_bob(common::get_request(&uri)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // Only the owner can share
+    let grant = json!({ "grantee": { "principal": "bob" }, "permission": "read" });
+    let request = common::post_request(&format!("{uri}/permissions"), grant.clone());
+    let response = app.clone().oneshot(as_bob(request)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let request = common::post_request(&format!("{uri}/permissions"), grant);
+    let response = app
+        .clone()
+        .oneshot(common::with_claims(request, "alice", &[]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let grant: serde_json::Value = common::response_json(response).await;
+    assert_eq!(grant["grantee"]["principal"], "bob");
+    assert_eq!(grant["granted_by"], "alice");
+
+    let response = app
+        .clone()
+        .oneshot(as_bob(common::get_request(&uri)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Read access does not allow writes
+    let request = common::put_request(&uri, json!({ "name": "Edited" }));
+    let response = app.clone().oneshot(as_bob(request)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let revoke_uri = format!("{uri}/permissions/{}", grant["id"].as_str().unwrap());
+    let response = app
+        .clone()
+        .oneshot(common::with_claims(common::delete_request(&revoke_uri), "alice", &[]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = app
+        .oneshot(as_bob(common::get_request(&uri)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_role_grant_allows_writes() {
+    let state = common::create_test_state();
+    let app = ferrous::routes::create_routes(state.clone());
+
+    let request = common::post_request("/api/v1/items", json!({ "name": "Team item" }));
+    let response = app
+        .clone()
+        .oneshot(common::with_claims(request, "alice", &[]))
+        .await
+        .unwrap();
+    let item: serde_json::Value = common::response_json(response).await;
+    let uri = format!("/api/v1/items/{}", item["id"].as_str().unwrap());
+
+    let grant = json!({ "grantee": { "role": "editors" }, "permission": "write" });
+    let request = common::post_request(&format!("{uri}/permissions"), grant);
+    let response = app
+        .clone()
+        .oneshot(common::with_claims(request, "alice", &[]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let request = common::put_request(&uri, json!({ "name": "Edited by editor" }));
+    let response = app
+        .oneshot(common::with_claims(request, "carol", &["editors"]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+// Rate limiting tests
+#[tokio::test]
+async fn test_rate_limit_headers() {
+    let app = common::create_test_app().await;
+
+    let response = app
+        .oneshot(common::get_request("/api/v1/items"))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().contains_key("X-RateLimit-Limit"));
+    assert!(response.headers().contains_key("X-RateLimit-Remaining"));
+    assert!(response.headers().contains_key("X-RateLimit-Reset"));
+}
+
+#[tokio::test]
+async fn test_rate_limit_multiple_requests() {
+    let app = common::create_test_app().await;
+
+    // First request
+    let response = app
+        .clone()
+        .oneshot(common::get_request("/api/v1/items"))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let initial_remaining = response
+        .headers()
+        .get("X-RateLimit-Remaining")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .parse::<u32>()
+        .unwrap();
+
+    // Second request
+    let response = app
+        .oneshot(common::get_request("/api/v1/items"))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let new_remaining = response
+        .headers()
+        .get("X-RateLimit-Remaining")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .parse::<u32>()
+        .unwrap();
+
+    assert_eq!(new_remaining, initial_remaining - 1);
+}
+
+// Security headers tests
+#[tokio::test]
+async fn test_security_headers() {
+    let app = common::create_test_app().await;
+
+    let response = app.oneshot(common::get_request("/health")).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Check security headers
+    assert_eq!(response.headers().get("X-Content-Type-Options").unwrap(), "nosniff");
+    assert_eq!(response.headers().get("X-Frame-Options").unwrap(), "DENY");
+    assert!(response.headers().contains_key("X-Request-Id"));
+}
+
+#[tokio::test]
+async fn test_responses_can_be_enveloped() {
+    let mut config = ferrous::config::Config::default();
+    config.server.response_envelope = ferrous::config::ResponseEnvelope::Data;
+    let state = ferrous::state::AppState::new(common::create_test_repo())
+        .with_config(config)
+        .into_shared();
+    let app =
+        ferrous::middleware::add_middleware(ferrous::routes::create_routes(state.clone()), &state);
+
+    let response = app
+        .clone()
+        .oneshot(common::post_request("/api/v1/items", json!({ "name": "Widget" })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let request_id = response.headers()["x-request-id"]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let body: serde_json::Value = common::response_json(response).await;
+    assert_eq!(body["data"]["name"], "Widget");
+    assert_eq!(body["meta"]["request_id"], request_id);
+
+    // Errors and probes keep their own shape
+    let response = app
+        .clone()
+        .oneshot(common::get_request("/api/v1/items/missing"))
+        .await
+        .unwrap();
+    let error: serde_json::Value = common::response_json(response).await;
+    assert_eq!(error["error"], "NOT_FOUND");
+    let response = app
+        .clone()
+        .oneshot(common::get_request("/health"))
+        .await
+        .unwrap();
+    let health: serde_json::Value = common::response_json(response).await;
+    assert!(health.get("data").is_none());
+
+    // The document describes the envelope
+    let response = app
+        .oneshot(common::get_request("/openapi.json"))
+        .await
+        .unwrap();
+    let spec: serde_json::Value = common::response_json(response).await;
+    let schema = &spec["paths"]["/api/v1/items/{id}"]["get"]["responses"]["200"]["content"]
+        ["application/json"]["schema"];
+    assert!(schema["properties"]["data"].is_object());
+}
+
+#[tokio::test]
+async fn test_deprecated_endpoints_are_announced() {
+    use chrono::TimeZone;
+    use ferrous::deprecation::{Deprecation, Deprecations};
+
+    let sunset = chrono::Utc.with_ymd_and_hms(2027, 1, 1, 0, 0, 0).unwrap();
+    let deprecations = Deprecations::default().deprecate(
+        axum::http::Method::GET,
+        "/api/v1/items/stats",
+        Deprecation::new(chrono::Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap())
+            .with_sunset(sunset)
+            .with_successor("/api/v1/items/analytics"),
+    );
+    let state = ferrous::state::AppState::new(common::create_test_repo())
+        .with_deprecations(deprecations)
+        .into_shared();
+    let app = ferrous::routes::create_routes(state);
+
+    let response = app
+        .clone()
+        .oneshot(common::with_claims(
+            common::get_request("/api/v1/items/stats"),
+            "root",
+            &["admin"],
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["deprecation"], "@1767225600");
+    assert_eq!(response.headers()["sunset"], "Fri, 01 Jan 2027 00:00:00 GMT");
+    assert_eq!(
+        response.headers()["link"],
+        "</api/v1/items/analytics>; rel=\"successor-version\""
+    );
+
+    let response = app
+        .clone()
+        .oneshot(common::get_request("/api/v1/items"))
+        .await
+        .unwrap();
+    assert!(!response.headers().contains_key("deprecation"));
+
+    let response = app
+        .oneshot(common::get_request("/openapi.json"))
+        .await
+        .unwrap();
+    let spec: serde_json::Value = common::response_json(response).await;
+    assert_eq!(spec["paths"]["/api/v1/items/stats"]["get"]["deprecated"], true);
+}
+
+#[tokio::test]
+async fn test_middleware_uses_state_config() {
+    let mut config = ferrous::config::Config::default();
+    config.rate_limit.max_requests = 1;
+    config.rate_limit.window_seconds = 5;
+    config.security.strict_mode = true;
+    config.security.csp = Some("default-src 'none'".to_string());
+    let state = ferrous::state::AppState::new(common::create_test_repo())
+        .with_config(config)
+        .into_shared();
+    let app =
+        ferrous::middleware::add_middleware(ferrous::routes::create_routes(state.clone()), &state);
+
+    let response = app
+        .clone()
+        .oneshot(common::get_request("/api/v1/items"))
+        .await
+        .unwrap();
+    assert_eq!(response.headers()["content-security-policy"], "default-src 'none'");
+    assert!(response.headers().contains_key("strict-transport-security"));
+
+    let response = app
+        .oneshot(common::get_request("/api/v1/items"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.headers()["retry-after"], "5");
+}
+
+#[tokio::test]
+async fn test_request_context_reaches_the_repository() {
+    let state = common::create_test_state();
+    let app =
+        ferrous::middleware::add_middleware(ferrous::routes::create_routes(state.clone()), &state);
+
+    let mut request = common::post_request("/api/v1/items", json!({ "name": "Audited" }));
+    request
+        .headers_mut()
+        .insert("x-request-id", "req-audit".parse().unwrap());
+    let response = app
+        .oneshot(common::with_claims(request, "alice", &[]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let events = state.repo.pending_events(10).await.unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].actor.as_deref(), Some("alice"));
+    assert_eq!(events[0].request_id.as_deref(), Some("req-audit"));
+}
+
+#[tokio::test]
+async fn test_request_timeout_header_sets_the_deadline() {
+    let state = common::create_test_state();
+    let app =
+        ferrous::middleware::add_middleware(ferrous::routes::create_routes(state.clone()), &state);
+    let with_timeout = |uri: &str, timeout: &str| {
+        let mut request = common::get_request(uri);
+        request
+            .headers_mut()
+            .insert("x-request-timeout", timeout.parse().unwrap());
+        request
+    };
+
+    // The client has given up before the repository is asked
+    let response = app
+        .clone()
+        .oneshot(with_timeout("/api/v1/items", "0"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+    let body: serde_json::Value = common::response_json(response).await;
+    assert_eq!(body["error"], "DEADLINE_EXCEEDED");
+
+    let response = app
+        .clone()
+        .oneshot(with_timeout("/api/v1/items", "soon"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Long polls answer before the deadline instead of waiting it out
+    let started = std::time::Instant::now();
+    let response = app
+        .oneshot(with_timeout("/api/v1/items/changes?wait=30s", "100"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn test_rate_limit_window_follows_state_clock() {
+    let clock = std::sync::Arc::new(ferrous::clock::ManualClock::default());
+    let mut config = ferrous::config::Config::default();
+    config.rate_limit.max_requests = 1;
+    config.rate_limit.window_seconds = 60;
+    let state = ferrous::state::AppState::new(common::create_test_repo())
+        .with_config(config)
+        .with_clock(clock.clone())
+        .into_shared();
+    let app =
+        ferrous::middleware::add_middleware(ferrous::routes::create_routes(state.clone()), &state);
+
+    let response = app
+        .clone()
+        .oneshot(common::get_request("/api/v1/items"))
+        .await
+        .unwrap();
+    assert_eq!(response.headers()["x-ratelimit-reset"], "60");
+    let response = app
+        .clone()
+        .oneshot(common::get_request("/api/v1/items"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    // The budget is shared with admin routes, but probes are never limited
+    let response = app
+        .clone()
+        .oneshot(common::get_request("/admin/v1/config"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let response = app
+        .clone()
+        .oneshot(common::get_request("/health/live"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("x-ratelimit-limit").is_none());
+
+    // A new window opens once the clock passes the old one, without waiting
+    clock.advance(std::time::Duration::from_secs(60));
+    let response = app
+        .oneshot(common::get_request("/api/v1/items"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_route_groups_require_auth_and_limit_bodies() {
+    let mut config = ferrous::config::Config::default();
+    config.auth.enabled = true;
+    config.auth.required = true;
+    config.auth.jwt_secret = Some("secret".to_string());
+    config.server.max_body_bytes = 256;
+    let state = ferrous::state::AppState::new(common::create_test_repo())
+        .with_auth(ferrous::auth::JwtValidator::new(&config.auth))
+        .with_config(config)
+        .into_shared();
+    let app = ferrous::routes::create_routes(state);
+
+    let response = app
+        .clone()
+        .oneshot(common::get_request("/api/v1/items"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = app
+        .clone()
+        .oneshot(common::get_request("/health/live"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let create = |name: String| {
+        common::with_claims(
+            common::post_request("/api/v1/items", json!({ "name": name })),
+            "alice",
+            &[],
+        )
+    };
+    let response = app
+        .clone()
+        .oneshot(create("Small".to_string()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let response = app.oneshot(create("x".repeat(300))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    let error: serde_json::Value = common::response_json(response).await;
+    assert_eq!(error["error"], "PAYLOAD_TOO_LARGE");
+}
+
+// Error response tests
+#[tokio::test]
+async fn test_structured_error_response_format() {
+    let app = common::create_test_app().await;
+
+    let response = app
+        .oneshot(common::get_request("/api/v1/items/nonexistent"))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let error: serde_json::Value = common::response_json(response).await;
+
+    // Check error response structure
+    assert!(error["error"].is_string());
+    assert!(error["message"].is_string());
+    assert!(error["timestamp"].is_string());
+    // request_id is optional in error responses
+    if error.get("request_id").is_some() {
+        assert!(error["request_id"].is_string());
+    }
+}
+
+#[tokio::test]
+async fn test_validation_error_structure() {
+    let app = common::create_test_app().await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/items")
+                .header("content-type", "application/json")
+                .body(Body::from("{invalid json"))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let error: serde_json::Value = common::response_json(response).await;
+    assert_eq!(error["error"], "BAD_REQUEST");
+    assert!(error["message"].as_str().unwrap().contains("JSON"));
+}
+
+#[tokio::test]
+async fn test_error_codes_match_status_codes() {
+    let app = common::create_test_app().await;
+
+    // 404 Not Found
+    let response = app
+        .clone()
+        .oneshot(common::get_request("/api/v1/items/nonexistent"))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let error: serde_json::Value = common::response_json(response).await;
+    assert_eq!(error["error"], "NOT_FOUND");
+
+    // 400 Bad Request
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/items")
+                .header("content-type", "application/json")
+                .body(Body::from("not json"))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let error: serde_json::Value = common::response_json(response).await;
+    assert_eq!(error["error"], "BAD_REQUEST");
+}
+
+// OpenAPI tests
+#[tokio::test]
+async fn test_openapi_json_endpoint() {
+    let app = common::create_test_app().await;
+
+    let response = app
+        .oneshot(common::get_request("/openapi.json"))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = common::response_json::<serde_json::Value>(response).await;
+
+    // Check OpenAPI structure
+    assert_eq!(body["openapi"], "3.1.0");
     assert_eq!(body["info"]["title"], "Ferrous API");
     assert_eq!(body["info"]["version"], env!("CARGO_PKG_VERSION"));
 
-    // Check that paths are defined
-    assert!(body["paths"].is_object());
-    assert!(body["paths"]["/health"].is_object());
-    assert!(body["paths"]["/api/v1/items"].is_object());
-    assert!(body["paths"]["/api/v1/items/{id}"].is_object());
+    // Check that paths are defined
+    assert!(body["paths"].is_object());
+    assert!(body["paths"]["/health"].is_object());
+    assert!(body["paths"]["/api/v1/items"].is_object());
+    assert!(body["paths"]["/api/v1/items/{id}"].is_object());
+
+    // Check components
+    assert!(body["components"]["schemas"].is_object());
+    assert!(body["components"]["schemas"]["Item"].is_object());
+    assert!(body["components"]["schemas"]["CreateItemRequest"].is_object());
+    assert!(body["components"]["schemas"]["ErrorResponse"].is_object());
+}
+
+#[tokio::test]
+async fn test_trailing_slashes_reach_the_route() {
+    let app = common::create_test_app().await;
+
+    let response = app
+        .clone()
+        .oneshot(common::post_request("/api/v1/items/", json!({ "name": "Widget" })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let item: serde_json::Value = common::response_json(response).await;
+
+    let uri = format!("/api/v1/items/{}/", item["id"].as_str().unwrap());
+    let response = app.oneshot(common::get_request(&uri)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_options_and_unsupported_methods_list_allowed_methods() {
+    let app = common::create_test_app().await;
+    let request = |method: &str, uri: &str| {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(request("OPTIONS", "/api/v1/items/some-id"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let allow = response.headers()["allow"].to_str().unwrap().to_string();
+    for method in ["GET", "PUT", "DELETE", "OPTIONS"] {
+        assert!(allow.contains(method), "{allow}");
+    }
+    assert!(!allow.contains("POST"), "{allow}");
+
+    let response = app
+        .clone()
+        .oneshot(request("PATCH", "/api/v1/items"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    let allow = response.headers()["allow"].to_str().unwrap().to_string();
+    assert!(allow.contains("POST") && allow.contains("OPTIONS"), "{allow}");
+    let error: serde_json::Value = common::response_json(response).await;
+    assert_eq!(error["error"], "METHOD_NOT_ALLOWED");
+
+    // Unknown paths are still not found
+    let response = app
+        .oneshot(request("OPTIONS", "/api/v1/nothing"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_api_index_lists_documented_collections() {
+    // Serve every optional collection
+    let state = ferrous::state::AppState::new(common::create_test_repo())
+        .with_inbox(Some(std::sync::Arc::new(ferrous::db::InMemoryNotificationRepository::new())))
+        .into_shared();
+    let app = ferrous::routes::create_routes(state.clone());
+    let app = ferrous::middleware::add_middleware(app, &state);
+
+    let response = app
+        .clone()
+        .oneshot(common::get_request("/api"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let index: serde_json::Value = common::response_json(response).await;
+    assert_eq!(index["links"]["openapi"], "/openapi.json");
+    assert!(index["features"]
+        .as_array()
+        .unwrap()
+        .contains(&serde_json::json!("change_feed")));
+
+    let collections = index["versions"][0]["collections"].as_array().unwrap();
+    assert!(!collections.is_empty());
+    // Every collection in the index is actually served
+    for collection in collections {
+        let href = collection["href"].as_str().unwrap();
+        let request = common::with_claims(common::get_request(href), "user-123", &[]);
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{href}");
+    }
+}
+
+#[tokio::test]
+async fn test_item_links_follow_config_or_accept_profile() {
+    let app = common::create_test_app().await;
+    let response = app
+        .clone()
+        .oneshot(common::post_request("/api/v1/items", json!({"name": "Linked item"})))
+        .await
+        .unwrap();
+    let item: serde_json::Value = common::response_json(response).await;
+    assert!(item.get("_links").is_none());
+    let id = item["id"].as_str().unwrap();
+
+    let with_profile = |uri: &str| {
+        Request::builder()
+            .uri(uri)
+            .header("accept", "application/json; profile=\"links\"")
+            .body(Body::empty())
+            .unwrap()
+    };
+    let response = app
+        .clone()
+        .oneshot(with_profile(&format!("/api/v1/items/{id}")))
+        .await
+        .unwrap();
+    let item: serde_json::Value = common::response_json(response).await;
+    assert_eq!(item["_links"]["self"]["href"], format!("/api/v1/items/{id}"));
+    assert_eq!(item["_links"]["delete"]["method"], "DELETE");
+
+    let response = app
+        .clone()
+        .oneshot(with_profile("/api/v1/items?limit=1"))
+        .await
+        .unwrap();
+    let list: serde_json::Value = common::response_json(response).await;
+    assert_eq!(list["_links"]["self"]["href"], "/api/v1/items?limit=1");
+    assert_eq!(list["_links"]["create"]["method"], "POST");
+    assert!(list["_links"].get("next").is_some());
+    assert!(list["items"][0]["_links"]["self"].is_object());
+
+    // Enabled in the configuration, every response has them
+    let mut config = ferrous::config::Config::default();
+    config.links.enabled = true;
+    let state = ferrous::state::AppState::new(common::create_test_repo())
+        .with_config(config)
+        .into_shared();
+    let app = ferrous::routes::create_routes(state);
+    let response = app
+        .oneshot(common::get_request("/api/v1/items"))
+        .await
+        .unwrap();
+    let list: serde_json::Value = common::response_json(response).await;
+    assert!(list["_links"]["self"].is_object());
+}
+
+#[tokio::test]
+async fn test_json_api_representation_is_negotiated() {
+    let app = common::create_test_app().await;
+    let json_api = |method: &str, uri: &str, body: Option<serde_json::Value>| {
+        let builder = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("accept", "application/vnd.api+json");
+        match body {
+            Some(body) => builder
+                .header("content-type", "application/vnd.api+json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+            None => builder.body(Body::empty()).unwrap(),
+        }
+    };
+
+    let response = app
+        .clone()
+        .oneshot(json_api(
+            "POST",
+            "/api/v1/items",
+            Some(json!({"data": {"type": "items", "attributes": {"name": "JSON:API item"}}})),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(response.headers()["content-type"], "application/vnd.api+json");
+    let document: serde_json::Value = common::response_json(response).await;
+    assert_eq!(document["data"]["type"], "items");
+    assert_eq!(document["data"]["attributes"]["name"], "JSON:API item");
+    let id = document["data"]["id"].as_str().unwrap().to_string();
+
+    let response = app
+        .clone()
+        .oneshot(json_api("GET", "/api/v1/items?limit=1", None))
+        .await
+        .unwrap();
+    let document: serde_json::Value = common::response_json(response).await;
+    assert_eq!(document["data"][0]["id"], id.as_str());
+    assert_eq!(document["meta"]["total"], 1);
+    assert_eq!(document["links"]["first"], "/api/v1/items?limit=1");
+    assert!(document["links"]["next"].is_string());
+
+    // Errors are JSON:API error objects
+    let response = app
+        .clone()
+        .oneshot(json_api("GET", "/api/v1/items/missing", None))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(response.headers()["content-type"], "application/vnd.api+json");
+    let document: serde_json::Value = common::response_json(response).await;
+    assert_eq!(document["errors"][0]["status"], "404");
+    assert_eq!(document["errors"][0]["code"], "NOT_FOUND");
+
+    let response = app
+        .clone()
+        .oneshot(json_api(
+            "POST",
+            "/api/v1/items",
+            Some(json!({"data": {"type": "items", "attributes": {"name": ""}}})),
+        ))
+        .await
+        .unwrap();
+    let document: serde_json::Value = common::response_json(response).await;
+    assert_eq!(document["errors"][0]["source"]["pointer"], "/data/attributes/name");
+
+    // Plain JSON clients are unaffected
+    let response = app
+        .oneshot(common::get_request(&format!("/api/v1/items/{id}")))
+        .await
+        .unwrap();
+    let item: serde_json::Value = common::response_json(response).await;
+    assert_eq!(item["id"], id.as_str());
+}
+
+#[tokio::test]
+async fn test_odata_query_options_filter_sort_and_select() {
+    let app = common::create_test_app().await;
+    for name in ["Widget", "Gadget", "Wingnut"] {
+        let response = app
+            .clone()
+            .oneshot(common::post_request("/api/v1/items", json!({ "name": name })))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    let response = app
+        .clone()
+        .oneshot(common::get_request(
+            "/api/v1/items?$filter=startswith(name,%20'Wi')&$orderby=name%20desc&$top=1&$select=name",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let list: serde_json::Value = common::response_json(response).await;
+    assert_eq!(list["total"], 2);
+    assert_eq!(list["limit"], 1);
+    assert_eq!(list["items"], json!([{ "name": "Wingnut" }]));
+    // Sorted pages continue by offset
+    assert!(list.get("next_cursor").is_none());
+
+    let response = app
+        .clone()
+        .oneshot(common::get_request("/api/v1/items?$orderby=name&$skip=1&$top=1"))
+        .await
+        .unwrap();
+    let list: serde_json::Value = common::response_json(response).await;
+    assert_eq!(list["items"][0]["name"], "Widget");
+
+    let response = app
+        .oneshot(common::get_request(
+            "/api/v1/items?$filter=name%20eq%20'a'%20or%20name%20eq%20'b'",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
 
-    // Check components
-    assert!(body["components"]["schemas"].is_object());
-    assert!(body["components"]["schemas"]["Item"].is_object());
-    assert!(body["components"]["schemas"]["CreateItemRequest"].is_object());
-    assert!(body["components"]["schemas"]["ErrorResponse"].is_object());
+#[tokio::test]
+async fn test_client_streams_every_page_of_items() {
+    use ferrous::client::{Client, ListItems};
+    use futures_util::TryStreamExt;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let app = common::create_test_app().await;
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let client = Client::new(format!("http://{addr}"));
+    for i in 0..5 {
+        let response = reqwest::Client::new()
+            .post(format!("http://{addr}/api/v1/items"))
+            .json(&json!({ "name": format!("Streamed {i}") }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+    }
+
+    let list = ListItems {
+        limit: 2,
+        ..ListItems::default()
+    };
+    let items: Vec<_> = client.list_items(list.clone()).try_collect().await.unwrap();
+    let names: Vec<_> = items.iter().map(|item| item.name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "Streamed 0",
+            "Streamed 1",
+            "Streamed 2",
+            "Streamed 3",
+            "Streamed 4"
+        ]
+    );
+
+    let page = client.list_items_page(&list, None).await.unwrap();
+    assert_eq!((page.items.len(), page.total), (2, Some(5)));
+
+    // Errors carry the API's message
+    let error = client
+        .list_items_page(&ListItems { limit: 0, ..list }, None)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        ferrous::client::ClientError::Api { status, .. } if status == reqwest::StatusCode::BAD_REQUEST
+    ));
+}
+
+#[tokio::test]
+async fn test_notification_inbox_lists_changes_by_others() {
+    use ferrous::{
+        config::EventsConfig, db::InMemoryNotificationRepository, events::OutboxDispatcher,
+        inbox::InboxPublisher,
+    };
+    use std::sync::Arc;
+
+    let inbox = Arc::new(InMemoryNotificationRepository::new());
+    let state = ferrous::state::AppState::new(common::create_test_repo())
+        .with_inbox(Some(inbox.clone()))
+        .into_shared();
+    let app = ferrous::routes::create_routes(state.clone());
+    let app = ferrous::middleware::add_middleware(app, &state);
+    let dispatcher = OutboxDispatcher::new(
+        state.repo.clone(),
+        vec![Arc::new(InboxPublisher::new(inbox, state.access.clone()))],
+        &EventsConfig::default(),
+    );
+
+    let request = common::post_request("/api/v1/items", json!({ "name": "Shared" }));
+    let response = app
+        .clone()
+        .oneshot(common::with_claims(request, "alice", &[]))
+        .await
+        .unwrap();
+    let item: serde_json::Value = common::response_json(response).await;
+    let uri = format!("/api/v1/items/{}", item["id"].as_str().unwrap());
+    let grant = json!({ "grantee": { "principal": "bob" }, "permission": "write" });
+    let request = common::post_request(&format!("{uri}/permissions"), grant);
+    app.clone()
+        .oneshot(common::with_claims(request, "alice", &[]))
+        .await
+        .unwrap();
+    let request = common::put_request(&uri, json!({ "name": "Renamed" }));
+    let response = app
+        .clone()
+        .oneshot(common::with_claims(request, "bob", &[]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    dispatcher.dispatch_once().await.unwrap();
+
+    let as_alice = |request| common::with_claims(request, "alice", &[]);
+    let response = app
+        .clone()
+        .oneshot(as_alice(common::get_request("/api/v1/notifications")))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let list: serde_json::Value = common::response_json(response).await;
+    // Alice made the item, so only hears about Bob's change
+    assert_eq!(list["total"], 1);
+    assert_eq!(list["unread"], 1);
+    let notification = &list["notifications"][0];
+    assert_eq!(notification["event_type"], "updated");
+    assert_eq!(notification["item_name"], "Renamed");
+    assert_eq!(notification["actor"], "bob");
+    assert_eq!(notification["reason"], "owner");
+
+    let id = notification["id"].as_str().unwrap();
+    let read = common::post_request(&format!("/api/v1/notifications/{id}/read"), json!({}));
+    let response = app.clone().oneshot(as_alice(read)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let read: serde_json::Value = common::response_json(response).await;
+    assert!(read["read_at"].is_string());
+
+    let response = app
+        .clone()
+        .oneshot(as_alice(common::get_request("/api/v1/notifications/unread-count")))
+        .await
+        .unwrap();
+    let count: serde_json::Value = common::response_json(response).await;
+    assert_eq!(count["unread"], 0);
+
+    // Notifications are private to their recipient
+    let read = common::post_request(&format!("/api/v1/notifications/{id}/read"), json!({}));
+    let response = app
+        .clone()
+        .oneshot(common::with_claims(read, "bob", &[]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = app
+        .oneshot(common::get_request("/api/v1/notifications"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_saved_searches_run_again_for_their_owner() {
+    let state = common::create_test_state();
+    let app = ferrous::routes::create_routes(state.clone());
+    let app = ferrous::middleware::add_middleware(app, &state);
+    let as_alice = |request| common::with_claims(request, "alice", &[]);
+
+    for name in ["Widget A", "Widget B", "Gadget"] {
+        let request = common::post_request("/api/v1/items", json!({ "name": name }));
+        app.clone().oneshot(as_alice(request)).await.unwrap();
+    }
+    let search = json!({
+        "name": "Widgets",
+        "filter": "startswith(name, 'Widget')",
+        "orderby": "name desc",
+    });
+    let request = common::post_request("/api/v1/saved-searches", search.clone());
+    let response = app.clone().oneshot(as_alice(request)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let saved: serde_json::Value = common::response_json(response).await;
+    let id = saved["id"].as_str().unwrap();
+
+    // Names are unique per owner
+    let request = common::post_request("/api/v1/saved-searches", search);
+    let response = app.clone().oneshot(as_alice(request)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    for invalid in [
+        json!({ "name": "Broken", "filter": "name eq" }),
+        json!({ "name": "Subscribed", "notify": true }),
+    ] {
+        let request = common::post_request("/api/v1/saved-searches", invalid.clone());
+        let response = app.clone().oneshot(as_alice(request)).await.unwrap();
+        assert!(response.status().is_client_error(), "{invalid}");
+    }
+
+    let results = format!("/api/v1/saved-searches/{id}/results");
+    let response = app
+        .clone()
+        .oneshot(as_alice(common::get_request(&results)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let page: serde_json::Value = common::response_json(response).await;
+    assert_eq!(page["total"], 2);
+    assert_eq!(page["items"][0]["name"], "Widget B");
+    assert_eq!(page["search"]["name"], "Widgets");
+
+    // Other principals cannot see or run the search
+    let response = app
+        .clone()
+        .oneshot(common::with_claims(common::get_request(&results), "bob", &[]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let delete = common::delete_request(&format!("/api/v1/saved-searches/{id}"));
+    let response = app.clone().oneshot(as_alice(delete)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response = app
+        .oneshot(as_alice(common::get_request("/api/v1/saved-searches")))
+        .await
+        .unwrap();
+    let searches: serde_json::Value = common::response_json(response).await;
+    assert_eq!(searches, json!([]));
+}
+
+#[tokio::test]
+async fn test_item_comments_are_attributed_and_soft_deleted() {
+    let state = common::create_test_state();
+    let app = ferrous::routes::create_routes(state.clone());
+    let app = ferrous::middleware::add_middleware(app, &state);
+    let mut events = state.events.subscribe_comments();
+
+    let request = common::post_request("/api/v1/items", json!({ "name": "Discussed" }));
+    let response = app
+        .clone()
+        .oneshot(common::with_claims(request, "alice", &[]))
+        .await
+        .unwrap();
+    let item: serde_json::Value = common::response_json(response).await;
+    let uri = format!("/api/v1/items/{}/comments", item["id"].as_str().unwrap());
+    let grant = json!({ "grantee": { "principal": "bob" }, "permission": "read" });
+    let request = common::post_request(
+        &format!("/api/v1/items/{}/permissions", item["id"].as_str().unwrap()),
+        grant,
+    );
+    app.clone()
+        .oneshot(common::with_claims(request, "alice", &[]))
+        .await
+        .unwrap();
+
+    // Readers can comment; others cannot
+    let comment = || common::post_request(&uri, json!({ "body": "Nice one" }));
+    let response = app
+        .clone()
+        .oneshot(common::with_claims(comment(), "mallory", &[]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = app
+        .clone()
+        .oneshot(common::with_claims(comment(), "bob", &[]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let created: serde_json::Value = common::response_json(response).await;
+    assert_eq!(created["author"], "bob");
+    let event = events.recv().await.unwrap();
+    assert_eq!(event.event_type, "com.ferrous.comment.created");
+    assert_eq!(event.data.body, "Nice one");
+
+    // Only the author can edit
+    let comment_uri = format!("{uri}/{}", created["id"].as_str().unwrap());
+    let edit = || common::put_request(&comment_uri, json!({ "body": "Nice one!" }));
+    let response = app
+        .clone()
+        .oneshot(common::with_claims(edit(), "alice", &[]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = app
+        .clone()
+        .oneshot(common::with_claims(edit(), "bob", &[]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // The item's owner can delete it, and still find it among deleted comments
+    let delete = common::delete_request(&comment_uri);
+    let response = app
+        .clone()
+        .oneshot(common::with_claims(delete, "alice", &[]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response = app
+        .clone()
+        .oneshot(common::with_claims(common::get_request(&uri), "bob", &[]))
+        .await
+        .unwrap();
+    let list: serde_json::Value = common::response_json(response).await;
+    assert_eq!(list["total"], 0);
+    let deleted = format!("{uri}?include_deleted=true");
+    let response = app
+        .clone()
+        .oneshot(common::with_claims(common::get_request(&deleted), "bob", &[]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = app
+        .oneshot(common::with_claims(common::get_request(&deleted), "alice", &[]))
+        .await
+        .unwrap();
+    let list: serde_json::Value = common::response_json(response).await;
+    assert_eq!(list["comments"][0]["body"], "Nice one!");
+    assert!(list["comments"][0]["deleted_at"].is_string());
+}
+
+#[tokio::test]
+async fn test_collection_grants_reach_items_below_them() {
+    let state = common::create_test_state();
+    let app = ferrous::routes::create_routes(state.clone());
+    let app = ferrous::middleware::add_middleware(app, &state);
+    let send = |request, sub: &'static str| {
+        let app = app.clone();
+        async move {
+            app.oneshot(common::with_claims(request, sub, &[]))
+                .await
+                .unwrap()
+        }
+    };
+
+    let response = send(
+        common::post_request("/api/v1/collections", json!({ "name": "Shared" })),
+        "alice",
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let shared: serde_json::Value = common::response_json(response).await;
+    let shared_id = shared["id"].as_str().unwrap();
+    let nested = json!({ "name": "Invoices", "parent_id": shared_id });
+    let response = send(common::post_request("/api/v1/collections", nested), "alice").await;
+    let invoices: serde_json::Value = common::response_json(response).await;
+    let invoices_id = invoices["id"].as_str().unwrap();
+    assert_eq!(invoices["path"], json!([shared_id]));
+
+    let response =
+        send(common::post_request("/api/v1/items", json!({ "name": "Invoice 1" })), "alice").await;
+    let item: serde_json::Value = common::response_json(response).await;
+    let item_uri = format!("/api/v1/items/{}", item["id"].as_str().unwrap());
+    let filing = json!({ "collection_id": invoices_id });
+    let response =
+        send(common::put_request(&format!("{item_uri}/collection"), filing), "alice").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // A grant on the root collection lets Bob read the item below it
+    assert_eq!(
+        send(common::get_request(&item_uri), "bob").await.status(),
+        StatusCode::FORBIDDEN
+    );
+    let grant = json!({ "grantee": { "principal": "bob" }, "permission": "read" });
+    let uri = format!("/api/v1/collections/{shared_id}/permissions");
+    send(common::post_request(&uri, grant), "alice").await;
+    assert_eq!(send(common::get_request(&item_uri), "bob").await.status(), StatusCode::OK);
+
+    let uri = format!("/api/v1/collections/{shared_id}/items?recursive=true");
+    let response = send(common::get_request(&uri), "bob").await;
+    let listed: serde_json::Value = common::response_json(response).await;
+    assert_eq!(listed["total"], 1);
+    assert_eq!(listed["items"][0]["name"], "Invoice 1");
+
+    // Moving the subtree out of the shared collection takes the access away
+    let uri = format!("/api/v1/collections/{invoices_id}/move");
+    let response =
+        send(common::post_request(&uri, json!({ "parent_id": invoices_id })), "alice").await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let response = send(common::post_request(&uri, json!({ "parent_id": null })), "alice").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        send(common::get_request(&item_uri), "bob").await.status(),
+        StatusCode::FORBIDDEN
+    );
+}
+
+#[tokio::test]
+async fn test_duplicates_are_found_and_merged() {
+    let app = common::create_test_app().await;
+    let create = |body: serde_json::Value| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(common::post_request("/api/v1/items", body))
+                .await
+                .unwrap();
+            common::response_json::<serde_json::Value>(response).await
+        }
+    };
+    let original = create(json!({ "name": "Blue Widget", "metadata": { "tags": ["blue"] } })).await;
+    let copy = create(json!({
+        "name": "blue widgets",
+        "description": "Entered twice",
+        "metadata": { "tags": ["sale"] }
+    }))
+    .await;
+    create(json!({ "name": "Red Gadget" })).await;
+    let id = original["id"].as_str().unwrap();
+
+    let response = app
+        .clone()
+        .oneshot(common::get_request(&format!("/api/v1/items/{id}/duplicates")))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let found: serde_json::Value = common::response_json(response).await;
+    let duplicates = found["duplicates"].as_array().unwrap();
+    assert_eq!(duplicates.len(), 1);
+    assert_eq!(duplicates[0]["item"]["id"], copy["id"]);
+
+    let merge = json!({ "source_id": copy["id"] });
+    let response = app
+        .clone()
+        .oneshot(common::post_request(&format!("/api/v1/items/{id}/merge"), merge))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let report: serde_json::Value = common::response_json(response).await;
+    assert_eq!(report["item"]["description"], "Entered twice");
+    assert_eq!(report["item"]["metadata"]["tags"], json!(["blue", "sale"]));
+    assert_eq!(report["item"]["version"], 2);
+
+    let copy_uri = format!("/api/v1/items/{}", copy["id"].as_str().unwrap());
+    let response = app.oneshot(common::get_request(&copy_uri)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_merges_of_locked_items_conflict() {
+    use ferrous::{
+        db::InMemoryLeaseRepository,
+        locks::{DatabaseLockStore, DistributedLock},
+        state::AppState,
+    };
+    use std::{sync::Arc, time::Duration};
+
+    let locks = DistributedLock::new(
+        Arc::new(DatabaseLockStore::new(Arc::new(InMemoryLeaseRepository::new()))),
+        Duration::from_secs(30),
+        Duration::ZERO,
+    );
+    let state = AppState::new(common::create_test_repo())
+        .with_locks(locks)
+        .into_shared();
+    let app = ferrous::routes::create_routes(state.clone());
+    let mut ids = Vec::new();
+    for name in ["Widget", "Widget copy"] {
+        let response = app
+            .clone()
+            .oneshot(common::post_request("/api/v1/items", json!({ "name": name })))
+            .await
+            .unwrap();
+        let item: serde_json::Value = common::response_json(response).await;
+        ids.push(item["id"].as_str().unwrap().to_string());
+    }
+    let merge = || {
+        common::post_request(
+            &format!("/api/v1/items/{}/merge", ids[0]),
+            json!({ "source_id": ids[1] }),
+        )
+    };
+
+    // Another replica is merging the source item elsewhere
+    let held = state
+        .locks
+        .acquire(&format!("items/{}", ids[1]))
+        .await
+        .unwrap();
+    let response = app.clone().oneshot(merge()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    held.release().await;
+    let response = app.oneshot(merge()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_locked_items_refuse_edits_by_others() {
+    let state = common::create_test_state();
+    let app = ferrous::routes::create_routes(state.clone());
+    let app = ferrous::middleware::add_middleware(app, &state);
+    let send = |request, sub: &'static str| {
+        let app = app.clone();
+        async move {
+            app.oneshot(common::with_claims(request, sub, &[]))
+                .await
+                .unwrap()
+        }
+    };
+
+    let response =
+        send(common::post_request("/api/v1/items", json!({ "name": "Draft" })), "alice").await;
+    let item: serde_json::Value = common::response_json(response).await;
+    let uri = format!("/api/v1/items/{}", item["id"].as_str().unwrap());
+    let grant = json!({ "grantee": { "principal": "bob" }, "permission": "write" });
+    send(common::post_request(&format!("{uri}/permissions"), grant), "alice").await;
+
+    let lock_uri = format!("{uri}/lock");
+    let response =
+        send(common::post_request(&lock_uri, json!({ "ttl_seconds": 60 })), "alice").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let lock: serde_json::Value = common::response_json(response).await;
+    assert_eq!(lock["owner"], "alice");
+
+    // Others see who holds it and cannot take it or edit
+    let response = send(common::get_request(&uri), "bob").await;
+    assert_eq!(response.headers()["x-locked-by"], "alice");
+    assert!(response.headers().contains_key("x-lock-expires"));
+    let response = send(common::post_request(&lock_uri, json!({})), "bob").await;
+    assert_eq!(response.status(), StatusCode::LOCKED);
+    let edit = || common::put_request(&uri, json!({ "name": "Final" }));
+    let response = send(edit(), "bob").await;
+    assert_eq!(response.status(), StatusCode::LOCKED);
+    let response = send(common::delete_request(&lock_uri), "bob").await;
+    assert_eq!(response.status(), StatusCode::LOCKED);
+    let response =
+        send(common::post_request(&lock_uri, json!({ "ttl_seconds": 86400 })), "alice").await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    // The owner edits freely, and once released so can others
+    let response = send(edit(), "alice").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = send(common::delete_request(&lock_uri), "alice").await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response = send(common::get_request(&lock_uri), "bob").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = send(edit(), "bob").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!response.headers().contains_key("x-locked-by"));
+}
+
+#[tokio::test]
+async fn test_drafts_are_hidden_until_published() {
+    let state = common::create_test_state();
+    let app = ferrous::routes::create_routes(state.clone());
+    let app = ferrous::middleware::add_middleware(app, &state);
+    let send = |request, sub: &'static str| {
+        let app = app.clone();
+        async move {
+            app.oneshot(common::with_claims(request, sub, &[]))
+                .await
+                .unwrap()
+        }
+    };
+
+    let scheduled = json!({ "name": "Early", "publish_at": "2030-01-01T00:00:00Z" });
+    let response = send(common::post_request("/api/v1/items", scheduled), "alice").await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let draft = json!({ "name": "Announcement", "status": "draft" });
+    let response = send(common::post_request("/api/v1/items", draft), "alice").await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let item: serde_json::Value = common::response_json(response).await;
+    assert_eq!(item["status"], "draft");
+    let uri = format!("/api/v1/items/{}", item["id"].as_str().unwrap());
+    for (grantee, permission) in [("bob", "read"), ("carol", "write")] {
+        let grant = json!({ "grantee": { "principal": grantee }, "permission": permission });
+        send(common::post_request(&format!("{uri}/permissions"), grant), "alice").await;
+    }
+
+    // Only editors see the draft, and anonymous lists leave it out
+    let response = send(common::get_request(&uri), "bob").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = send(common::get_request(&uri), "carol").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app
+        .clone()
+        .oneshot(common::get_request("/api/v1/items"))
+        .await
+        .unwrap();
+    let list: serde_json::Value = common::response_json(response).await;
+    assert_eq!(list["items"], json!([]));
+
+    // Scheduling keeps it a draft; publishing shows it to readers
+    let publish = format!("{uri}/publish");
+    let later = json!({ "publish_at": "2030-01-01T00:00:00Z" });
+    let response = send(common::post_request(&publish, later), "bob").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let later = json!({ "publish_at": "2030-01-01T00:00:00Z" });
+    let response = send(common::post_request(&publish, later), "carol").await;
+    let scheduled: serde_json::Value = common::response_json(response).await;
+    assert_eq!(scheduled["status"], "draft");
+    assert_eq!(scheduled["publish_at"], "2030-01-01T00:00:00Z");
+    let response = send(common::post_request(&publish, json!({})), "alice").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let published: serde_json::Value = common::response_json(response).await;
+    assert_eq!(published["status"], "published");
+    assert!(published.get("publish_at").is_none());
+    let response = send(common::get_request(&uri), "bob").await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_changes_to_designated_items_wait_for_approvals() {
+    use ferrous::{
+        approvals::Approvals,
+        collections::ItemCollection,
+        config::ApprovalConfig,
+        db::{CollectionRepository, InMemoryCollectionRepository, InMemoryNotificationRepository},
+    };
+    use std::sync::Arc;
+
+    let collections = Arc::new(InMemoryCollectionRepository::new());
+    let contracts = ItemCollection::new(
+        "Contracts".to_string(),
+        None,
+        Some("alice".to_string()),
+        chrono::Utc::now(),
+    );
+    collections.create(contracts.clone()).await.unwrap();
+    let state = ferrous::state::AppState::new(common::create_test_repo())
+        .with_collections(collections)
+        .with_approvals(Approvals::from_config(&ApprovalConfig {
+            collections: vec![contracts.id.clone()],
+            required_approvals: 2,
+        }))
+        .with_inbox(Some(Arc::new(InMemoryNotificationRepository::new())))
+        .into_shared();
+    let app = ferrous::routes::create_routes(state.clone());
+    let app = ferrous::middleware::add_middleware(app, &state);
+    let send = |request, sub: &'static str| {
+        let app = app.clone();
+        async move {
+            app.oneshot(common::with_claims(request, sub, &[]))
+                .await
+                .unwrap()
+        }
+    };
+
+    let response =
+        send(common::post_request("/api/v1/items", json!({ "name": "Lease" })), "alice").await;
+    let item: serde_json::Value = common::response_json(response).await;
+    let uri = format!("/api/v1/items/{}", item["id"].as_str().unwrap());
+    let filing = json!({ "collection_id": contracts.id });
+    let response = send(common::put_request(&format!("{uri}/collection"), filing), "alice").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    for grantee in ["bob", "carol"] {
+        let grant = json!({ "grantee": { "principal": grantee }, "permission": "write" });
+        send(common::post_request(&format!("{uri}/permissions"), grant), "alice").await;
+    }
+
+    // The update is held, and the item keeps its name until approved
+    let response = send(common::put_request(&uri, json!({ "name": "Lease 2025" })), "bob").await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let change: serde_json::Value = common::response_json(response).await;
+    assert_eq!(change["status"], "pending");
+    assert_eq!(change["action"], "update");
+    let change_uri = format!("/api/v1/changes/{}", change["id"].as_str().unwrap());
+    let response = send(common::get_request(&uri), "bob").await;
+    let current: serde_json::Value = common::response_json(response).await;
+    assert_eq!(current["name"], "Lease");
+
+    let response = send(common::get_request("/api/v1/notifications"), "carol").await;
+    let inbox: serde_json::Value = common::response_json(response).await;
+    assert_eq!(inbox["notifications"][0]["reason"], "approval");
+    assert_eq!(inbox["notifications"][0]["change_request_id"], change["id"]);
+    let response = send(common::get_request("/api/v1/changes"), "dave").await;
+    let listed: serde_json::Value = common::response_json(response).await;
+    assert_eq!(listed["total"], 0);
+    let response = send(common::get_request(&change_uri), "dave").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let approve = || common::post_request(&format!("{change_uri}/approve"), json!({}));
+    let response = send(approve(), "bob").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = send(approve(), "alice").await;
+    let change: serde_json::Value = common::response_json(response).await;
+    assert_eq!(change["status"], "pending");
+    assert_eq!(change["approvals"][0]["principal"], "alice");
+    let response = send(approve(), "alice").await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    // The last approval applies it and tells the requester
+    let response = send(approve(), "carol").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let change: serde_json::Value = common::response_json(response).await;
+    assert_eq!(change["status"], "applied");
+    assert_eq!(change["resolved_by"], "carol");
+    let response = send(common::get_request(&uri), "bob").await;
+    let current: serde_json::Value = common::response_json(response).await;
+    assert_eq!(current["name"], "Lease 2025");
+    let response = send(common::get_request("/api/v1/notifications"), "bob").await;
+    let inbox: serde_json::Value = common::response_json(response).await;
+    assert_eq!(inbox["notifications"][0]["reason"], "change_request");
+
+    // Deletions are held too, and requesters can withdraw them
+    let response = send(common::delete_request(&uri), "bob").await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let change: serde_json::Value = common::response_json(response).await;
+    let reject = json!({ "reason": "Filed by mistake" });
+    let response = send(
+        common::post_request(
+            &format!("/api/v1/changes/{}/reject", change["id"].as_str().unwrap()),
+            reject,
+        ),
+        "bob",
+    )
+    .await;
+    let change: serde_json::Value = common::response_json(response).await;
+    assert_eq!(change["status"], "rejected");
+    let response = send(common::get_request(&uri), "bob").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = send(common::get_request("/api/v1/changes?status=pending"), "bob").await;
+    let listed: serde_json::Value = common::response_json(response).await;
+    assert_eq!(listed["total"], 0);
+
+    // Taking the item out of the collection would skip approvals
+    let unfiling = json!({ "collection_id": null });
+    let response = send(common::put_request(&format!("{uri}/collection"), unfiling), "bob").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // So would moving a collection below it to the root
+    let nested = json!({ "name": "Signed", "parent_id": contracts.id });
+    let response = send(common::post_request("/api/v1/collections", nested), "alice").await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let nested: serde_json::Value = common::response_json(response).await;
+    let moving = format!("/api/v1/collections/{}/move", nested["id"].as_str().unwrap());
+    let response = send(common::post_request(&moving, json!({ "parent_id": null })), "alice").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let request = common::post_request(&moving, json!({ "parent_id": null }));
+    let response = app
+        .clone()
+        .oneshot(common::with_claims(request, "root", &["admin"]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_changes_to_unowned_items_need_granted_approvers() {
+    use ferrous::{
+        approvals::Approvals,
+        collections::ItemCollection,
+        config::ApprovalConfig,
+        db::{CollectionRepository, InMemoryCollectionRepository},
+    };
+    use std::sync::Arc;
+
+    let collections = Arc::new(InMemoryCollectionRepository::new());
+    let contracts = ItemCollection::new("Contracts".to_string(), None, None, chrono::Utc::now());
+    collections.create(contracts.clone()).await.unwrap();
+    let state = ferrous::state::AppState::new(common::create_test_repo())
+        .with_collections(collections)
+        .with_approvals(Approvals::from_config(&ApprovalConfig {
+            collections: vec![contracts.id.clone()],
+            required_approvals: 1,
+        }))
+        .into_shared();
+    let app = ferrous::routes::create_routes(state.clone());
+    let app = ferrous::middleware::add_middleware(app, &state);
+    let send = |request, sub: &'static str, roles: &'static [&'static str]| {
+        let app = app.clone();
+        async move {
+            app.oneshot(common::with_claims(request, sub, roles))
+                .await
+                .unwrap()
+        }
+    };
+
+    let response = app
+        .clone()
+        .oneshot(common::post_request("/api/v1/items", json!({ "name": "Lease" })))
+        .await
+        .unwrap();
+    let item: serde_json::Value = common::response_json(response).await;
+    assert_eq!(item["owner_id"], serde_json::Value::Null);
+    let uri = format!("/api/v1/items/{}", item["id"].as_str().unwrap());
+    let filing = json!({ "collection_id": contracts.id });
+    send(common::put_request(&format!("{uri}/collection"), filing), "alice", &[]).await;
+
+    let response =
+        send(common::put_request(&uri, json!({ "name": "Lease 2025" })), "bob", &[]).await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let change: serde_json::Value = common::response_json(response).await;
+    let approve = format!("/api/v1/changes/{}/approve", change["id"].as_str().unwrap());
+
+    // Anyone may edit an unowned item, but approving takes a grant or the admin role
+    let response = send(common::post_request(&approve, json!({})), "dave", &[]).await;
+    assert_ne!(response.status(), StatusCode::OK);
+    let response = send(common::post_request(&approve, json!({})), "root", &["admin"]).await;
+    let change: serde_json::Value = common::response_json(response).await;
+    assert_eq!(change["status"], "applied");
+}
+
+#[tokio::test]
+async fn test_publishing_designated_drafts_waits_for_approval() {
+    use ferrous::{
+        approvals::Approvals,
+        collections::ItemCollection,
+        config::ApprovalConfig,
+        db::{CollectionRepository, InMemoryCollectionRepository},
+    };
+    use std::sync::Arc;
+
+    let collections = Arc::new(InMemoryCollectionRepository::new());
+    let contracts = ItemCollection::new(
+        "Contracts".to_string(),
+        None,
+        Some("alice".to_string()),
+        chrono::Utc::now(),
+    );
+    collections.create(contracts.clone()).await.unwrap();
+    let state = ferrous::state::AppState::new(common::create_test_repo())
+        .with_collections(collections)
+        .with_approvals(Approvals::from_config(&ApprovalConfig {
+            collections: vec![contracts.id.clone()],
+            required_approvals: 1,
+        }))
+        .into_shared();
+    let app = ferrous::routes::create_routes(state.clone());
+    let app = ferrous::middleware::add_middleware(app, &state);
+    let send = |request, sub: &'static str| {
+        let app = app.clone();
+        async move {
+            app.oneshot(common::with_claims(request, sub, &[]))
+                .await
+                .unwrap()
+        }
+    };
+
+    let draft = json!({ "name": "Lease", "status": "draft" });
+    let response = send(common::post_request("/api/v1/items", draft), "alice").await;
+    let item: serde_json::Value = common::response_json(response).await;
+    let uri = format!("/api/v1/items/{}", item["id"].as_str().unwrap());
+    let filing = json!({ "collection_id": contracts.id });
+    send(common::put_request(&format!("{uri}/collection"), filing), "alice").await;
+    let grant = json!({ "grantee": { "principal": "carol" }, "permission": "write" });
+    send(common::post_request(&format!("{uri}/permissions"), grant), "alice").await;
+
+    // Scheduling would publish it without approval
+    let later = (chrono::Utc::now() + chrono::Duration::hours(1)).to_rfc3339();
+    let publish = |body| common::post_request(&format!("{uri}/publish"), body);
+    let response = send(publish(json!({ "publish_at": later })), "alice").await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let response = send(publish(json!({})), "alice").await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let change: serde_json::Value = common::response_json(response).await;
+    assert_eq!(change["action"], "publish");
+    let response = send(common::get_request(&uri), "alice").await;
+    let current: serde_json::Value = common::response_json(response).await;
+    assert_eq!(current["status"], "draft");
+
+    let approve = format!("/api/v1/changes/{}/approve", change["id"].as_str().unwrap());
+    let response = send(common::post_request(&approve, json!({})), "carol").await;
+    let change: serde_json::Value = common::response_json(response).await;
+    assert_eq!(change["status"], "applied");
+    let response = send(common::get_request(&uri), "alice").await;
+    let current: serde_json::Value = common::response_json(response).await;
+    assert_eq!(current["status"], "published");
+}
+
+#[tokio::test]
+async fn test_upserts_by_external_id_are_idempotent() {
+    let state = common::create_test_state();
+    let app = ferrous::routes::create_routes(state.clone());
+    let app = ferrous::middleware::add_middleware(app, &state);
+    let send = |request, sub: &'static str| {
+        let app = app.clone();
+        async move {
+            app.oneshot(common::with_claims(request, sub, &[]))
+                .await
+                .unwrap()
+        }
+    };
+    let uri = "/api/v1/items/by-external-id/tf-widget";
+    let body = || json!({ "name": "Widget", "description": "Managed by Terraform" });
+
+    let response = send(common::put_request(uri, body()), "alice").await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let created: serde_json::Value = common::response_json(response).await;
+    assert_eq!(created["external_id"], "tf-widget");
+    assert_eq!(created["owner_id"], "alice");
+
+    // Repeating the request leaves the item as it is
+    let response = send(common::put_request(uri, body()), "alice").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let repeated: serde_json::Value = common::response_json(response).await;
+    assert_eq!(repeated["id"], created["id"]);
+    assert_eq!(repeated["version"], 1);
+
+    let changed = json!({ "name": "Widget", "description": "Resized" });
+    let response = send(common::put_request(uri, changed), "alice").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let updated: serde_json::Value = common::response_json(response).await;
+    assert_eq!(updated["id"], created["id"]);
+    assert_eq!(updated["description"], "Resized");
+    assert_eq!(updated["version"], 2);
+
+    // Status follows the body, both ways
+    let drafted = json!({ "name": "Widget", "description": "Resized", "status": "draft" });
+    let response = send(common::put_request(uri, drafted.clone()), "alice").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let updated: serde_json::Value = common::response_json(response).await;
+    assert_eq!(updated["status"], "draft");
+    assert_eq!(updated["version"], 3);
+    let response = send(common::put_request(uri, drafted), "alice").await;
+    let repeated: serde_json::Value = common::response_json(response).await;
+    assert_eq!(repeated["version"], 3);
+    let published = json!({ "name": "Widget", "description": "Resized" });
+    let response = send(common::put_request(uri, published), "alice").await;
+    let updated: serde_json::Value = common::response_json(response).await;
+    assert_eq!(updated["status"], "published");
+    assert_eq!(updated["version"], 4);
+
+    // Others need write access, and external IDs stay unique
+    let response = send(common::put_request(uri, body()), "bob").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let duplicate = json!({ "name": "Copy", "external_id": "tf-widget" });
+    let response = send(common::post_request("/api/v1/items", duplicate), "alice").await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let mismatched = json!({ "name": "Widget", "external_id": "tf-other" });
+    let response = send(common::put_request(uri, mismatched), "alice").await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn test_external_ids_are_unique_per_source() {
+    let state = common::create_test_state();
+    let app = ferrous::routes::create_routes(state.clone());
+    let app = ferrous::middleware::add_middleware(app, &state);
+    let send = |request, sub: &'static str| {
+        let app = app.clone();
+        async move {
+            app.oneshot(common::with_claims(request, sub, &[]))
+                .await
+                .unwrap()
+        }
+    };
+    let item =
+        |name: &str, source: &str| json!({ "name": name, "external_id": "4711", "source": source });
+
+    let response =
+        send(common::post_request("/api/v1/items", item("Account", "crm")), "alice").await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let account: serde_json::Value = common::response_json(response).await;
+    assert_eq!(account["source"], "crm");
+    let response =
+        send(common::post_request("/api/v1/items", item("Invoice", "erp")), "alice").await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let response = send(common::post_request("/api/v1/items", item("Copy", "crm")), "alice").await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let response = send(common::get_request("/api/v1/items?external_id=4711"), "alice").await;
+    let listed: serde_json::Value = common::response_json(response).await;
+    assert_eq!(listed["total"], 2);
+    let response =
+        send(common::get_request("/api/v1/items?external_id=4711&source=crm"), "alice").await;
+    let listed: serde_json::Value = common::response_json(response).await;
+    assert_eq!(listed["total"], 1);
+    assert_eq!(listed["items"][0]["id"], account["id"]);
+
+    // Upserts find the item of the body's source
+    let renamed = json!({ "name": "Renamed", "source": "crm" });
+    let response =
+        send(common::put_request("/api/v1/items/by-external-id/4711", renamed), "alice").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let updated: serde_json::Value = common::response_json(response).await;
+    assert_eq!(updated["id"], account["id"]);
+    assert_eq!(updated["name"], "Renamed");
 }
@@ -1,6 +1,7 @@
 use axum::{body::Body, http::Request};
 use ferrous::{
     db::{InMemoryRepository, ItemRepository, MetricsRepository},
+    middleware::auth::Claims,
     models::{CreateItemRequest, Item},
     state::SharedState,
 };
@@ -25,6 +26,12 @@ pub fn create_test_item_request(name: &str, description: Option<&str>) -> Create
     CreateItemRequest {
         name: name.to_string(),
         description: description.map(|s| s.to_string()),
+        metadata: None,
+        custom_fields: Default::default(),
+        status: Default::default(),
+        publish_at: None,
+        external_id: None,
+        source: None,
     }
 }
 
@@ -36,7 +43,7 @@ pub async fn create_test_item(
     description: Option<&str>,
 ) -> Item {
     let request = create_test_item_request(name, description);
-    repo.create(request).await.unwrap()
+    repo.create(request, None).await.unwrap()
 }
 
 /// Create multiple test items
@@ -56,16 +63,18 @@ pub async fn create_test_items(repo: &Arc<dyn ItemRepository>, count: usize) ->
 }
 
 /// Create a test app for integration testing
+#[allow(dead_code)]
 pub async fn create_test_app() -> axum::Router {
     // Initialize metrics for tests
     ferrous::metrics::init_metrics();
 
     let state = create_test_state();
-    let app = ferrous::routes::create_routes(state);
-    ferrous::middleware::add_middleware(app)
+    let app = ferrous::routes::create_routes(state.clone());
+    ferrous::middleware::add_middleware(app, &state)
 }
 
 /// Create a GET request
+#[allow(dead_code)]
 pub fn get_request(uri: &str) -> Request<Body> {
     Request::builder()
         .method("GET")
@@ -125,3 +134,15 @@ pub async fn response_body_string(response: axum::response::Response) -> String
         .unwrap();
     String::from_utf8(body.to_vec()).unwrap()
 }
+
+/// Attach authenticated claims to a request, as the auth middleware would
+#[allow(dead_code)]
+pub fn with_claims(mut request: Request<Body>, sub: &str, roles: &[&str]) -> Request<Body> {
+    request.extensions_mut().insert(Claims {
+        sub: sub.to_string(),
+        exp: usize::MAX,
+        roles: roles.iter().map(|role| role.to_string()).collect(),
+        ..Claims::default()
+    });
+    request
+}
@@ -139,3 +139,106 @@ fn test_legacy_database_type() {
 
     cleanup_env_vars();
 }
+
+#[test]
+fn test_health_checks_from_env() {
+    let _guard = ENV_MUTEX.lock().unwrap();
+    cleanup_env_vars();
+
+    env::set_var(
+        "HEALTH_CHECKS",
+        "payments=https://payments.internal/health, search=http://search:9200",
+    );
+    env::set_var("HEALTH_CHECKS_CRITICAL", "payments");
+    env::set_var("HEALTH_DB_DEGRADED_MS", "250");
+
+    let config = Config::load().unwrap();
+    let checks = &config.health.checks;
+    assert_eq!(checks.len(), 2);
+    assert_eq!(checks[0].name, "payments");
+    assert!(checks[0].critical);
+    assert_eq!(checks[1].url, "http://search:9200");
+    assert!(!checks[1].critical);
+    assert_eq!(config.health.db_degraded_ms, Some(250));
+
+    env::set_var("HEALTH_CHECKS", "payments");
+    assert!(Config::load().is_err());
+
+    env::remove_var("HEALTH_CHECKS");
+    env::remove_var("HEALTH_CHECKS_CRITICAL");
+    env::remove_var("HEALTH_DB_DEGRADED_MS");
+    cleanup_env_vars();
+}
+
+#[test]
+fn test_rate_limit_from_env() {
+    let _guard = ENV_MUTEX.lock().unwrap();
+    cleanup_env_vars();
+
+    env::set_var("RATE_LIMIT_MAX_REQUESTS", "100");
+    env::set_var("RATE_LIMIT_WINDOW_SECONDS", "10");
+    let config = Config::load().unwrap();
+    assert_eq!(config.rate_limit.max_requests, 100);
+    assert_eq!(config.rate_limit.window_seconds, 10);
+
+    // The legacy per-minute setting may not contradict the current one
+    env::set_var("RATE_LIMIT_PER_MINUTE", "500");
+    assert!(Config::load().is_err());
+
+    env::remove_var("RATE_LIMIT_MAX_REQUESTS");
+    env::remove_var("RATE_LIMIT_WINDOW_SECONDS");
+    let config = Config::load().unwrap();
+    assert_eq!(config.rate_limit.max_requests, 500);
+    assert_eq!(config.rate_limit.window_seconds, 60);
+
+    env::remove_var("RATE_LIMIT_PER_MINUTE");
+    cleanup_env_vars();
+}
+
+#[test]
+fn test_auth_issuers_from_env() {
+    let _guard = ENV_MUTEX.lock().unwrap();
+    cleanup_env_vars();
+
+    env::set_var("AUTH_ISSUERS", "auth0, internal-idp");
+    env::set_var("AUTH_ISSUER_AUTH0_ISSUER", "https://tenant.auth0.com/");
+    env::set_var("AUTH_ISSUER_AUTH0_JWKS_URL", "https://tenant.auth0.com/.well-known/jwks.json");
+    env::set_var("AUTH_ISSUER_AUTH0_AUDIENCES", "ferrous, ferrous-admin");
+    env::set_var("AUTH_ISSUER_AUTH0_ROLES_CLAIM", "https://ferrous/roles");
+    env::set_var("AUTH_ISSUER_AUTH0_ROLE_MAP", "ferrous-admins=admin");
+    env::set_var("AUTH_ISSUER_INTERNAL_IDP_ISSUER", "https://idp.internal");
+    env::set_var("AUTH_ISSUER_INTERNAL_IDP_JWKS_URL", "https://idp.internal/jwks");
+
+    let config = Config::load().unwrap();
+    let [auth0, internal] = config.auth.issuers.as_slice() else {
+        panic!("expected two issuers");
+    };
+    assert_eq!(auth0.audiences, ["ferrous", "ferrous-admin"]);
+    assert_eq!(auth0.roles_claim.as_deref(), Some("https://ferrous/roles"));
+    assert_eq!(auth0.role_map["ferrous-admins"], "admin");
+    assert_eq!(internal.issuer, "https://idp.internal");
+    assert!(internal.audiences.is_empty());
+
+    // Every issuer needs its own key set
+    env::remove_var("AUTH_ISSUER_INTERNAL_IDP_JWKS_URL");
+    assert!(Config::load().is_err());
+
+    // An issuer may not be configured twice
+    env::set_var("AUTH_ISSUER_INTERNAL_IDP_JWKS_URL", "https://idp.internal/jwks");
+    env::set_var("AUTH_ISSUER_INTERNAL_IDP_ISSUER", "https://tenant.auth0.com/");
+    assert!(Config::load().is_err());
+
+    for var in [
+        "AUTH_ISSUERS",
+        "AUTH_ISSUER_AUTH0_ISSUER",
+        "AUTH_ISSUER_AUTH0_JWKS_URL",
+        "AUTH_ISSUER_AUTH0_AUDIENCES",
+        "AUTH_ISSUER_AUTH0_ROLES_CLAIM",
+        "AUTH_ISSUER_AUTH0_ROLE_MAP",
+        "AUTH_ISSUER_INTERNAL_IDP_ISSUER",
+        "AUTH_ISSUER_INTERNAL_IDP_JWKS_URL",
+    ] {
+        env::remove_var(var);
+    }
+    cleanup_env_vars();
+}
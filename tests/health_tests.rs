@@ -51,6 +51,31 @@ async fn test_readiness_endpoint_when_ready() {
     assert!(body["timestamp"].is_string());
 }
 
+#[tokio::test]
+async fn test_readiness_fails_while_draining() {
+    let state = common::create_test_state();
+    state
+        .draining
+        .store(true, std::sync::atomic::Ordering::Relaxed);
+    let app = ferrous::routes::create_routes(state);
+
+    let response = app
+        .clone()
+        .oneshot(common::get_request("/health/ready"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let body = common::response_json::<serde_json::Value>(response).await;
+    assert_eq!(body["reason"], "draining");
+
+    // Draining replicas are still alive, and serve what reaches them
+    let response = app
+        .oneshot(common::get_request("/health/live"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
 #[tokio::test]
 async fn test_comprehensive_health_endpoint() {
     let app = common::create_test_app().await;
@@ -66,15 +91,39 @@ async fn test_comprehensive_health_endpoint() {
     assert!(body["timestamp"].is_string());
     assert!(body["version"].is_string());
     assert!(body["uptime_seconds"].is_number());
+    assert_eq!(body["build"]["version"], body["version"]);
 
     // Check components
     if body.get("components").is_some() {
         assert_eq!(body["components"]["database"]["status"], "healthy");
     }
 
+    // The error rate covers the requests made so far
+    assert_eq!(body["error_rate"]["window_seconds"], 300);
+    assert!(body["error_rate"]["ratio"].is_number());
+
     // Check system info
     if body.get("system").is_some() {
         assert!(body["system"]["memory_used_mb"].is_number());
         assert!(body["system"]["cpu_count"].is_u64());
     }
 }
+
+#[tokio::test]
+async fn test_version_endpoint() {
+    let app = common::create_test_app().await;
+
+    let response = app.oneshot(common::get_request("/version")).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = common::response_json::<serde_json::Value>(response).await;
+    assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+    assert!(!body["git_sha"].as_str().unwrap().is_empty());
+    assert!(body["built_at"].is_string());
+    assert!(body["rustc_version"]
+        .as_str()
+        .unwrap()
+        .starts_with("rustc "));
+    assert!(body["features"].is_array());
+}
@@ -148,3 +148,20 @@ async fn test_metrics_database_operations() {
     assert!(body.contains(r#"repository="items""#));
     assert!(body.contains(r#"status="success""#));
 }
+
+#[tokio::test]
+async fn test_unmatched_paths_are_normalized() {
+    let app = common::create_test_app().await;
+
+    let _ = app
+        .clone()
+        .oneshot(common::get_request("/api/v1/widgets/0b5e4a8e-3c2f-4f6a-9d1e-2a7b8c9d0e1f"))
+        .await
+        .unwrap();
+
+    let response = app.oneshot(common::get_request("/metrics")).await.unwrap();
+    let body = common::response_body_string(response).await;
+
+    assert!(body.contains(r#"endpoint="/api/v1/widgets/{id}""#));
+    assert!(!body.contains("0b5e4a8e-3c2f-4f6a-9d1e-2a7b8c9d0e1f"));
+}